@@ -0,0 +1,175 @@
+//! Quote latency of every DEX
+//!
+//! Loads a representative pool of each DEX from `fixtures/` (SOL/USDC on
+//! Orca, Raydium and Serum, USDC/USDT on an Orca stable pool), gives it
+//! mainnet-like reserves or a 100-level book, and measures the quotes the
+//! search makes in its hot loop: a single quote, a ladder of input sizes and
+//! an exact-out quote.
+//!
+//! `cargo bench -p solana-arb-core --bench quotes`; the heap allocations of
+//! one call of every quote are printed before the measurements, a quote that
+//! starts allocating shows up there first.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use solana_arb_core::pool::{pool_factory, PoolOperations, PoolType};
+use solana_arb_core::pool_utils::serum::{BookSnapshot, Level};
+use solana_arb_core::pools::PoolVariant;
+use solana_sdk::pubkey::Pubkey;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Counts the allocations of the benchmark process
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+const SOL: &str = "So11111111111111111111111111111111111111112";
+const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+const USDT: &str = "Es9vMFrzaCERmJfrF4H2FYD4KCoNWxpkcWNY4q8NNpmP";
+
+/// A fixture pool with its runtime state, and the mints it is quoted between
+struct Fixture {
+    name: &'static str,
+    pool: PoolVariant,
+    mint_in: Pubkey,
+    mint_out: Pubkey,
+    /// Input of the single quote (scaled `mint_in`)
+    amount_in: u128,
+}
+
+fn load(tipe: PoolType, file: &str) -> PoolVariant {
+    let path = format!("{}/benches/fixtures/{}", env!("CARGO_MANIFEST_DIR"), file);
+    let json = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+    pool_factory(&tipe, &json).unwrap_or_else(|e| panic!("{}: {}", path, e))
+}
+
+/// Resting orders around 150 USDC per SOL, 100 levels a side
+fn book() -> BookSnapshot {
+    // 0.1 SOL base lots, prices in 0.0001 USDC quote lots per base lot
+    let level = |i: u64, price: u64| Level {
+        price,
+        quantity: 10 + (i * 7) % 40,
+    };
+    BookSnapshot {
+        pc_lot_size: 100,
+        coin_lot_size: 100_000_000,
+        bids: (0..100).map(|i| level(i, 150_000 - 10 * i)).collect(),
+        asks: (0..100).map(|i| level(i, 150_010 + 10 * i)).collect(),
+    }
+}
+
+fn fixtures() -> Vec<Fixture> {
+    let [sol, usdc, usdt] = [SOL, USDC, USDT].map(|mint| Pubkey::from_str(mint).unwrap());
+    let reserves = |pool: &mut PoolVariant, amounts: [(Pubkey, u128); 2]| {
+        let amounts = amounts.iter().map(|(mint, amount)| (mint.to_string(), *amount)).collect();
+        match pool {
+            PoolVariant::Orca(pool) => pool.pool_amounts = amounts,
+            PoolVariant::Raydium(pool) => pool.pool_amounts = amounts,
+            _ => unreachable!("only AMMs have reserves"),
+        }
+    };
+
+    let mut orca = load(PoolType::OrcaPoolType, "orca.json");
+    reserves(&mut orca, [(sol, 60_000 * 10u128.pow(9)), (usdc, 9_000_000 * 10u128.pow(6))]);
+    let mut stable = load(PoolType::OrcaPoolType, "orca_stable.json");
+    reserves(&mut stable, [(usdc, 25_000_000 * 10u128.pow(6)), (usdt, 24_000_000 * 10u128.pow(6))]);
+    let mut raydium = load(PoolType::RaydiumType, "raydium.json");
+    reserves(&mut raydium, [(sol, 200_000 * 10u128.pow(9)), (usdc, 30_000_000 * 10u128.pow(6))]);
+    let mut serum = load(PoolType::SerumPoolType, "serum.json");
+    if let PoolVariant::Serum(market) = &mut serum {
+        market.book = Some(Arc::new(book()));
+    }
+
+    let fixture = |name, pool, mint_in, mint_out, amount_in| Fixture {
+        name,
+        pool,
+        mint_in,
+        mint_out,
+        amount_in,
+    };
+    vec![
+        fixture("orca", orca, sol, usdc, 10u128.pow(9)),
+        fixture("orca_stable", stable, usdc, usdt, 1_000 * 10u128.pow(6)),
+        fixture("raydium", raydium, sol, usdc, 10u128.pow(9)),
+        // a 4.5k USDC bid takes about 10 levels of asks
+        fixture("serum", serum, usdc, sol, 4_500 * 10u128.pow(6)),
+    ]
+}
+
+/// Input sizes of a ladder, from a hundredth to twice the single quote
+fn ladder(amount_in: u128) -> Vec<u128> {
+    [1, 5, 10, 25, 50, 100, 150, 200].iter().map(|pct| amount_in * pct / 100).collect()
+}
+
+/// Heap allocations of one call of `f`
+fn allocations<T>(f: impl FnOnce() -> T) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    black_box(f());
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn report_allocations(fixtures: &[Fixture]) {
+    println!("allocations per call:");
+    for f in fixtures {
+        let ladder = ladder(f.amount_in);
+        let quote =
+            allocations(|| f.pool.get_quote_with_amounts_scaled(f.amount_in, &f.mint_in, &f.mint_out));
+        let many = allocations(|| f.pool.get_quotes_for_amounts(&ladder, &f.mint_in, &f.mint_out));
+        println!("  {:<12} quote {:>3}  ladder {:>3}", f.name, quote, many);
+    }
+}
+
+fn quotes(c: &mut Criterion) {
+    let fixtures = fixtures();
+    report_allocations(&fixtures);
+
+    let mut group = c.benchmark_group("quote");
+    for f in fixtures.iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(f.name), f, |b, f| {
+            b.iter(|| f.pool.get_quote_with_amounts_scaled(black_box(f.amount_in), &f.mint_in, &f.mint_out))
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("ladder");
+    for f in fixtures.iter() {
+        let amounts = ladder(f.amount_in);
+        group.bench_with_input(BenchmarkId::from_parameter(f.name), &amounts, |b, amounts| {
+            b.iter(|| f.pool.get_quotes_for_amounts(black_box(amounts), &f.mint_in, &f.mint_out))
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("exact_out");
+    for f in fixtures.iter() {
+        let amount_out = f
+            .pool
+            .get_quote_with_amounts_scaled(f.amount_in, &f.mint_in, &f.mint_out)
+            .expect("fixture quotes")
+            / 2;
+        group.bench_with_input(BenchmarkId::from_parameter(f.name), &amount_out, |b, amount_out| {
+            b.iter(|| f.pool.get_quote_with_exact_out(black_box(*amount_out), &f.mint_in, &f.mint_out))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, quotes);
+criterion_main!(benches);
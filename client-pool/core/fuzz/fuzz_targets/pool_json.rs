@@ -0,0 +1,37 @@
+//! Pool JSON files: a file that doesn't parse or validate is an error, never
+//! a panic, and a pool that loads quotes without panicking
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use solana_arb_core::pool::{pool_factory, PoolOperations, PoolType};
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    tipe: u8,
+    json: String,
+    amount_in: u64,
+}
+
+const TYPES: [PoolType; 4] = [
+    PoolType::OrcaPoolType,
+    PoolType::RaydiumType,
+    PoolType::SerumPoolType,
+    PoolType::JupiterPoolType,
+];
+
+fuzz_target!(|input: Input| {
+    let tipe = &TYPES[input.tipe as usize % TYPES.len()];
+    let pool = match pool_factory(tipe, &input.json) {
+        Ok(pool) => pool,
+        Err(_) => return,
+    };
+    let mints = pool.get_mints();
+    for mint_in in mints.iter() {
+        for mint_out in mints.iter().filter(|mint| *mint != mint_in) {
+            let _ = pool.mint_2_addr(mint_in);
+            let _ = pool.mint_2_scale(mint_in);
+            let _ = pool.get_quote_with_amounts_scaled(input.amount_in as u128, mint_in, mint_out);
+        }
+    }
+});
@@ -0,0 +1,35 @@
+//! Serum market, bids and asks accounts: accounts that don't load as a
+//! market are `None`, never a panic, and a book that loads walks without
+//! panicking
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use solana_arb_core::constants::SERUM_PROGRAM_ID;
+use solana_arb_core::pool_utils::serum::{BookSnapshot, FeeTier};
+use solana_sdk::pubkey::Pubkey;
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    market: Vec<u8>,
+    bids: Vec<u8>,
+    asks: Vec<u8>,
+    amount_in: u64,
+    max_impact_bps: u64,
+}
+
+fuzz_target!(|input: Input| {
+    let (market, bids, asks) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let book = BookSnapshot::load(
+        (&market, &input.market),
+        (&bids, &input.bids),
+        (&asks, &input.asks),
+        &SERUM_PROGRAM_ID,
+    );
+    if let Some(book) = book {
+        for is_bid in [true, false] {
+            let _ = book.walk(input.amount_in, is_bid, FeeTier::default());
+            let _ = book.capacity(is_bid, input.max_impact_bps, FeeTier::default());
+        }
+    }
+});
@@ -0,0 +1,17 @@
+//! Token accounts and every registered account layout: data that doesn't
+//! decode is an error, never a panic
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use solana_arb_core::serialize::decoder::DECODERS;
+use solana_arb_core::serialize::token::{try_unpack_token_account, TOKEN_ACCOUNT_LEN};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(account) = try_unpack_token_account(data) {
+        assert!(data.len() >= TOKEN_ACCOUNT_LEN);
+        assert_eq!(account.amount.to_le_bytes(), data[64..72]);
+    }
+    for layout in DECODERS.layouts() {
+        let _ = DECODERS.decode_data(&layout.program_id, data);
+    }
+});
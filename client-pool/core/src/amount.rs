@@ -0,0 +1,171 @@
+//! Typed token amounts
+//!
+//! Amounts are `u128`s in a mint's smallest unit ("scaled", 10^decimals per
+//! whole token), and a bare `u128` doesn't say which mint it counts. A
+//! `ScaledAmount` carries its mint: quotes take one and return one of the
+//! output mint (`PoolOperations::quote`), arithmetic and comparisons between
+//! amounts of different mints are refused, and whole-token (UI) amounts only
+//! come from the mint's decimals (`PoolOperations::ui_amount` /
+//! `scaled_amount`, through `mint_2_scale`).
+//!
+//! Fees, slippage and loan fees are integer basis points of an amount, with
+//! one rounding rule: whatever is taken off rounds up (`bps_ceil`), so what
+//! is left (`bps_floor` of the complement) never overstates a route's output.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Basis points in a whole
+pub const BPS: u64 = 10_000;
+
+/// `bps` basis points of `amount`, rounded up. Split into whole and partial
+/// ten-thousandths so no amount overflows (for `bps` up to `BPS`).
+pub fn bps_ceil(amount: u128, bps: u64) -> u128 {
+    let (whole, rest) = (amount / BPS as u128, amount % BPS as u128);
+    whole * bps as u128 + (rest * bps as u128 + BPS as u128 - 1) / BPS as u128
+}
+
+/// `bps` basis points of `amount`, rounded down (same split as `bps_ceil`)
+pub fn bps_floor(amount: u128, bps: u64) -> u128 {
+    let (whole, rest) = (amount / BPS as u128, amount % BPS as u128);
+    whole * bps as u128 + rest * bps as u128 / BPS as u128
+}
+
+/// An amount of `mint` in its smallest unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScaledAmount {
+    pub mint: Pubkey,
+    pub amount: u128,
+}
+
+impl ScaledAmount {
+    pub fn new(mint: Pubkey, amount: u128) -> Self {
+        Self { mint, amount }
+    }
+
+    pub fn zero(mint: Pubkey) -> Self {
+        Self::new(mint, 0)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.amount == 0
+    }
+
+    /// `ui` whole tokens of `mint`, which has `decimals` decimals (rounded down)
+    pub fn from_ui(mint: Pubkey, ui: f64, decimals: u64) -> Self {
+        Self::new(mint, (ui * 10f64.powi(decimals as i32)) as u128)
+    }
+
+    /// Whole tokens, given the mint's `decimals`
+    pub fn to_ui(&self, decimals: u64) -> f64 {
+        self.amount as f64 / 10f64.powi(decimals as i32)
+    }
+
+    /// `None` if the mints differ or the sum overflows
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        self.same_mint(&other)?;
+        Some(Self::new(self.mint, self.amount.checked_add(other.amount)?))
+    }
+
+    /// `None` if the mints differ or `other` is larger
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.same_mint(&other)?;
+        Some(Self::new(self.mint, self.amount.checked_sub(other.amount)?))
+    }
+
+    /// What `self` gained over `cost` (negative for a loss), `None` if the
+    /// mints differ
+    pub fn profit_over(self, cost: Self) -> Option<i128> {
+        self.same_mint(&cost)?;
+        Some(self.amount as i128 - cost.amount as i128)
+    }
+
+    /// `fee_bps` basis points of the amount, rounded up
+    pub fn fee(self, fee_bps: u64) -> Self {
+        Self::new(self.mint, bps_ceil(self.amount, fee_bps))
+    }
+
+    /// The amount times `numerator / denominator`, rounded down; `None` on
+    /// overflow or a zero denominator
+    pub fn mul_div(self, numerator: u128, denominator: u128) -> Option<Self> {
+        let amount = self.amount.checked_mul(numerator)?.checked_div(denominator)?;
+        Some(Self::new(self.mint, amount))
+    }
+
+    fn same_mint(&self, other: &Self) -> Option<()> {
+        if self.mint == other.mint {
+            Some(())
+        } else {
+            None
+        }
+    }
+}
+
+/// Only amounts of the same mint compare; `a < b` is false for any two of
+/// different mints
+impl PartialOrd for ScaledAmount {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.same_mint(other)?;
+        Some(self.amount.cmp(&other.amount))
+    }
+}
+
+impl fmt::Display for ScaledAmount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} of {}", self.amount, self.mint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amounts_of_different_mints_dont_mix() {
+        let (usdc, sol) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let a = ScaledAmount::new(usdc, 1_500_000);
+        let b = ScaledAmount::new(usdc, 500_000);
+        assert_eq!(a.checked_add(b), Some(ScaledAmount::new(usdc, 2_000_000)));
+        assert_eq!(a.checked_sub(b), Some(ScaledAmount::new(usdc, 1_000_000)));
+        assert_eq!(b.checked_sub(a), None);
+        assert_eq!(b.profit_over(a), Some(-1_000_000));
+        assert!(b < a);
+
+        let c = ScaledAmount::new(sol, 500_000);
+        assert_eq!(a.checked_add(c), None);
+        assert_eq!(a.checked_sub(c), None);
+        assert_eq!(a.profit_over(c), None);
+        assert_eq!(a.partial_cmp(&c), None);
+        assert!(!a.gt(&c) && !a.lt(&c) && c != b);
+    }
+
+    #[test]
+    fn conversions_go_through_the_decimals() {
+        let usdc = Pubkey::new_unique();
+        let amount = ScaledAmount::from_ui(usdc, 1.5, 6);
+        assert_eq!(amount.amount, 1_500_000);
+        assert_eq!(amount.to_ui(6), 1.5);
+        assert_eq!(amount.fee(30).amount, 4_500);
+        assert_eq!(amount.mul_div(1, 3), Some(ScaledAmount::new(usdc, 500_000)));
+        assert_eq!(amount.mul_div(1, 0), None);
+    }
+
+    #[test]
+    fn bps_round_explicitly_and_stay_exact() {
+        assert_eq!(bps_ceil(1, 5), 1);
+        assert_eq!(bps_floor(1, 5), 0);
+        assert_eq!(bps_ceil(20_000, 5), 10);
+        assert_eq!(bps_floor(20_001, 5), 10);
+        assert_eq!(bps_ceil(20_001, 5), 11);
+        assert_eq!(bps_ceil(0, 30), 0);
+        // beyond f64's 53 bits, and where amount * bps would overflow
+        let large = u128::MAX / 3;
+        assert_eq!(bps_floor(large, BPS), large);
+        assert_eq!(bps_ceil(large, 1), large / 10_000 + 1);
+        let odd = (1u128 << 60) + 1;
+        assert_eq!(bps_floor(odd, 5_000), odd / 2);
+        assert_eq!(bps_ceil(odd, 5_000), odd / 2 + 1);
+    }
+}
@@ -0,0 +1,53 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+lazy_static! {
+    pub static ref TOKEN_PROGRAM_ID: Pubkey = Pubkey::from_str("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").unwrap();
+
+    pub static ref ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey = Pubkey::from_str("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL").unwrap();
+    
+    pub static ref TOKEN_2022_PROGRAM_ID: Pubkey = Pubkey::from_str("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb").unwrap();
+
+    pub static ref RAYDIUM_PROGRAM_ID: Pubkey = Pubkey::from_str("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8").unwrap();
+
+    pub static ref WHIRLPOOL_PROGRAM_ID: Pubkey = Pubkey::from_str("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc").unwrap();
+    
+    pub static ref ORCA_PROGRAM_ID: Pubkey = Pubkey::from_str("9W959DqEETiGZocYWCQPaJ6sBmUzgfxXfqGeTEdp3aQP").unwrap();
+
+    pub static ref MERCURIAL_PROGRAM_ID: Pubkey = Pubkey::from_str("MERLuDFBMmsHnsBPZw2sDQZHvXFMwp8EdjudcU2HKky").unwrap();
+
+    pub static ref ARB_PROGRAM_ID: Pubkey = Pubkey::from_str("CRQXfRGq3wTkjt7JkqhojPLiKLYLjHPGLebnfiiQB46T").unwrap();
+
+    pub static ref SABER_PROGRAM_ID : Pubkey = Pubkey::from_str("SSwpkEEcbUqx4vtoEByFjSkhKdCT862DNVb52nZg1UZ").unwrap();
+
+    pub static ref ALDRIN_V1_PROGRAM_ID : Pubkey = Pubkey::from_str("AMM55ShdkoGRB5jVYPjWziwk8m5MpwyDgsMWHaMSQWH6").unwrap();
+
+    pub static ref ALDRIN_V2_PROGRAM_ID : Pubkey = Pubkey::from_str("CURVGoZn8zycx6FXwwevgBTB2gVvdbGTEpvMJDbgs2t4").unwrap();
+
+    pub static ref SERUM_PROGRAM_ID : Pubkey = Pubkey::from_str("9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin").unwrap();
+
+    pub static ref OPENBOOK_PROGRAM_ID : Pubkey = Pubkey::from_str("srmqPvymJeFKQ4zGQed1GFppgkRHL9kaELCbyksJtPX").unwrap();
+
+    pub static ref PYTH_PROGRAM_ID : Pubkey = Pubkey::from_str("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bgdhRP").unwrap();
+
+    pub static ref USDC_MINT : Pubkey = Pubkey::from_str("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v").unwrap();
+
+    pub static ref USDT_MINT : Pubkey = Pubkey::from_str("Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB").unwrap();
+
+    pub static ref WSOL_MINT : Pubkey = Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap();
+
+    pub static ref SRM_MINT : Pubkey = Pubkey::from_str("SRMuApVNdxXokk5GT7XD5cUUgXMBCoAz2LHeuAoKWRt").unwrap();
+
+    pub static ref MSRM_MINT : Pubkey = Pubkey::from_str("MSRMcoVyrFxnSgo5uXwone5SKcGhT1KEJMFEkMEWf9L").unwrap();
+
+    pub static ref JITO_TIP_ACCOUNTS : Vec<Pubkey> = [
+        "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5",
+        "HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe",
+        "Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY",
+        "ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49",
+        "DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh",
+        "ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt",
+        "DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL",
+        "3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT",
+    ].iter().map(|s| Pubkey::from_str(s).unwrap()).collect();
+}
@@ -1,9 +1,77 @@
 //! Error types
+//!
+//! `SwapError` (curve math) and `QuoteError` (quotes) are always there,
+//! `ArbError` (pool layer) needs the `client` feature.
 
+#[cfg(feature = "client")]
+use anchor_client::solana_client::client_error::ClientError;
 use num_derive::FromPrimitive;
+#[cfg(feature = "client")]
+use solana_program::pubkey::Pubkey;
 use solana_program::{decode_error::DecodeError, program_error::ProgramError};
 use thiserror::Error;
 
+#[cfg(feature = "client")]
+use crate::pool::PoolType;
+#[cfg(feature = "client")]
+use crate::pool_schema::SchemaReport;
+#[cfg(feature = "client")]
+use crate::serialize::decoder::DecodeError as AccountDecodeError;
+
+/// Errors of the pool layer. A failing pool is skipped and logged instead of
+/// taking the whole bot down.
+#[cfg(feature = "client")]
+#[derive(Debug, Error)]
+pub enum ArbError {
+    #[error("invalid pool json: {0}")]
+    PoolJson(#[from] serde_json::Error),
+    #[error("pool json doesn't match the {tipe:?} schema: {report}")]
+    PoolSchema { tipe: PoolType, report: SchemaReport },
+    #[error("{0:?} pools are not supported yet")]
+    UnsupportedPoolType(PoolType),
+    #[error("pool json is of no pool type, or of several")]
+    UndetectedPoolType,
+    #[error("invalid pool {pool}: {reason}")]
+    InvalidPool { pool: Pubkey, reason: String },
+    #[error("account {0} is missing")]
+    MissingAccount(Pubkey),
+    #[error("could not decode account: {0}")]
+    AccountDecode(#[from] AccountDecodeError),
+    #[error("no open orders account of {owner} for market {market}")]
+    MissingOpenOrders { market: Pubkey, owner: Pubkey },
+    #[error("could not build instruction: {0}")]
+    Instruction(String),
+    #[error("{0}")]
+    Unsupported(&'static str),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("rpc error: {0}")]
+    Rpc(#[from] ClientError),
+}
+
+#[cfg(feature = "client")]
+pub type ArbResult<T> = std::result::Result<T, ArbError>;
+
+/// Why a pool can't quote a swap. The search treats the edge as unusable
+/// and moves on.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum QuoteError {
+    #[error("pool has no reserves")]
+    ZeroReserves,
+    #[error("order book side is empty")]
+    EmptyBook,
+    #[error("math overflow")]
+    Overflow,
+    #[error("pool doesn't trade {0}")]
+    UnknownMint(String),
+    #[error("pool data is missing or doesn't load")]
+    BadPoolData,
+    #[error("{0}")]
+    Unsupported(String),
+}
+
+pub type QuoteResult<T> = std::result::Result<T, QuoteError>;
+
 /// Errors that may be returned by the TokenSwap program.
 #[derive(Clone, Debug, Eq, Error, FromPrimitive, PartialEq)]
 pub enum SwapError {
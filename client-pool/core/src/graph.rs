@@ -0,0 +1,660 @@
+//! Pool loading and exchange graph construction
+//!
+//! Reads the configured pool directories, indexes every mint, fetches the
+//! pools' on-chain accounts and turns the result into the `PoolGraph` the
+//! searcher walks. Accounts come from any `AccountSource`, the bot passes
+//! its RPC pool.
+
+use anchor_client::solana_client::client_error::Result as ClientResult;
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+use solana_sdk::account::Account;
+
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, info, warn};
+
+use crate::error::ArbError;
+use crate::pool::{parse_any_pool, PoolDir, PoolOperations, PoolType};
+use crate::pools::PoolVariant;
+use crate::registry::{PoolRegistry, Registration};
+use crate::utils::{read_json_dir, PoolEdge, PoolGraph, PoolIndex, PoolQuote};
+
+/// Max accounts per `getMultipleAccounts` request
+pub const ACCOUNTS_PER_REQUEST: usize = 99;
+
+/// Where `PoolSet::refresh` reads accounts from
+pub trait AccountSource {
+    /// The accounts (same order, `None` if missing) and the slot they were read at
+    fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> ClientResult<(Vec<Option<Account>>, u64)>;
+
+    /// Fetches every chunk (at most `ACCOUNTS_PER_REQUEST` keys each), one
+    /// `ChunkFetch` per chunk in the same order. Sources may fetch chunks
+    /// concurrently and retry failed ones; by default they are fetched one
+    /// after the other, once.
+    fn get_chunks_with_slot(&self, chunks: &[&[Pubkey]]) -> Vec<ChunkFetch> {
+        chunks
+            .iter()
+            .map(|chunk| ChunkFetch {
+                result: self.get_multiple_accounts_with_slot(chunk),
+                attempts: 1,
+            })
+            .collect()
+    }
+}
+
+impl<S: AccountSource + ?Sized> AccountSource for &S {
+    fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> ClientResult<(Vec<Option<Account>>, u64)> {
+        (**self).get_multiple_accounts_with_slot(pubkeys)
+    }
+
+    fn get_chunks_with_slot(&self, chunks: &[&[Pubkey]]) -> Vec<ChunkFetch> {
+        (**self).get_chunks_with_slot(chunks)
+    }
+}
+
+/// A single node, read at the client's commitment
+impl AccountSource for RpcClient {
+    fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> ClientResult<(Vec<Option<Account>>, u64)> {
+        let response = self.get_multiple_accounts_with_commitment(pubkeys, self.commitment())?;
+        Ok((response.value, response.context.slot))
+    }
+}
+
+/// Outcome of fetching one chunk of accounts
+#[derive(Debug)]
+pub struct ChunkFetch {
+    pub result: ClientResult<(Vec<Option<Account>>, u64)>,
+    /// requests it took, retries included
+    pub attempts: u32,
+}
+
+/// A chunk that couldn't be fetched; its pools are untradeable until a
+/// later refresh reads them
+#[derive(Debug, Clone)]
+pub struct ChunkFailure {
+    /// position of the chunk in the refresh
+    pub chunk: usize,
+    pub accounts: usize,
+    pub attempts: u32,
+    pub error: String,
+}
+
+/// Every loaded pool plus the mint index built from them
+pub struct PoolSet {
+    pub token_mints: Vec<Pubkey>,
+    pub mint2idx: HashMap<Pubkey, usize>,
+    pub graph_edges: Vec<HashSet<usize>>, // used for quick searching over the graph
+    pub pools: Vec<PoolVariant>,
+    /// pool type of each pool in `pools`
+    pub pool_types: Vec<PoolType>,
+    /// mint indices of each pool in `pools`, two or more
+    pub pool_mints: Vec<Vec<usize>>,
+    /// whether each pool in `pools` took its last update; pools whose
+    /// accounts couldn't be fetched or decoded stay out of the graph
+    pub tradeable: Vec<bool>,
+}
+
+/// What a `PoolSet::refresh` fetched
+#[derive(Debug, Default)]
+pub struct RefreshReport {
+    /// `getMultipleAccounts` chunks the accounts were split into
+    pub chunks: usize,
+    /// requests beyond the first one per chunk
+    pub chunk_retries: usize,
+    /// chunks that failed every attempt
+    pub failed_chunks: Vec<ChunkFailure>,
+    /// accounts missing from the batched fetch
+    pub missing: usize,
+    /// missing accounts that a single-account retry returned
+    pub recovered: usize,
+    /// pools marked untradeable
+    pub untradeable: Vec<usize>,
+    /// pools whose accounts were all there but failed to decode, and why
+    pub decode_errors: Vec<(usize, String)>,
+    /// time spent fetching the accounts, retries included
+    pub fetch_time: Duration,
+    /// time spent decoding the accounts into the pools
+    pub decode_time: Duration,
+}
+
+/// What a `PoolSet::refresh_params` changed
+#[derive(Debug, Default)]
+pub struct ParamReport {
+    /// pools whose fee or decimals differed from their JSON
+    pub changed: usize,
+    /// pools whose parameter accounts couldn't be fetched or decoded
+    pub failed: usize,
+}
+
+impl PoolSet {
+    pub fn new() -> Self {
+        Self {
+            token_mints: vec![],
+            mint2idx: HashMap::new(),
+            graph_edges: vec![],
+            pools: vec![],
+            pool_types: vec![],
+            pool_mints: vec![],
+            tradeable: vec![],
+        }
+    }
+
+    /// Index of `mint`, adding it as a new (edgeless) node if needed
+    pub fn add_mint(&mut self, mint: Pubkey) -> usize {
+        if let Some(idx) = self.mint2idx.get(&mint) {
+            return *idx;
+        }
+        let idx = self.token_mints.len();
+        self.mint2idx.insert(mint, idx);
+        self.token_mints.push(mint);
+        self.graph_edges.push(HashSet::new());
+        idx
+    }
+
+    /// Adds a pool and an edge between every pair of its mints. Pools
+    /// trading fewer than two mints are skipped and `false` is returned.
+    pub fn add_pool(&mut self, tipe: PoolType, pool: PoolVariant) -> bool {
+        let mints = pool.get_mints();
+        if mints.len() < 2 {
+            return false;
+        }
+        let idxs: Vec<usize> = mints.into_iter().map(|mint| self.add_mint(mint)).collect();
+        for (idx_in, idx_out) in mint_pairs(&idxs) {
+            self.graph_edges[idx_in].insert(idx_out);
+        }
+
+        self.pools.push(pool);
+        self.pool_types.push(tipe);
+        self.pool_mints.push(idxs);
+        self.tradeable.push(true);
+        true
+    }
+
+    /// Loads every pool JSON file of the given directories
+    pub fn load(pool_dirs: &[PoolDir]) -> Self {
+        Self::load_with_sources(pool_dirs).0
+    }
+
+    /// Like `load`, plus the JSON of every loaded pool (aligned with `pools`).
+    /// Pools whose address was already loaded are dropped, see `registry`.
+    /// Files that can't be read or don't match their pool type's schema are
+    /// skipped with what's wrong with them, the others still load.
+    pub fn load_with_sources(pool_dirs: &[PoolDir]) -> (Self, Vec<String>) {
+        let mut set = Self::new();
+        let mut sources = vec![];
+        let mut registry = PoolRegistry::new();
+        let (mut invalid, mut retyped) = (0, 0);
+        for pool_dir in pool_dirs {
+            debug!("Pool dir: {:#?}", pool_dir);
+            for pool_path in read_json_dir(&pool_dir.dir_path) {
+                let json_str = match std::fs::read_to_string(&pool_path) {
+                    Ok(json_str) => json_str,
+                    Err(e) => {
+                        warn!("Skipping pool {:?}: {}", pool_path, ArbError::from(e));
+                        invalid += 1;
+                        continue;
+                    }
+                };
+                // the directory's type only decides when the contents can't
+                let (tipe, pool) = match parse_any_pool(&json_str, Some(pool_dir.tipe)) {
+                    Ok((tipe, pool, unknown)) => {
+                        if !unknown.is_empty() {
+                            warn!("Ignoring unknown fields of pool {:?}: {}", pool_path, unknown.join(", "));
+                        }
+                        if tipe != pool_dir.tipe {
+                            debug!("{:?} is a {:?} pool, not a {:?} one", pool_path, tipe, pool_dir.tipe);
+                            retyped += 1;
+                        }
+                        (tipe, pool)
+                    }
+                    Err(e) => {
+                        warn!("Skipping pool {:?}: {}", pool_path, e);
+                        invalid += 1;
+                        continue;
+                    }
+                };
+                match registry.register(tipe, &pool) {
+                    Registration::New => {}
+                    Registration::Duplicate => {
+                        debug!("Skipping duplicate pool {}: {:?}", pool.get_id(), pool_path);
+                        continue;
+                    }
+                    Registration::Conflict => {
+                        let conflict = registry.conflicts().last().unwrap();
+                        warn!("Skipping {:?}: {}", pool_path, conflict);
+                        continue;
+                    }
+                }
+                if !set.add_pool(tipe, pool) {
+                    warn!("Skipping pool with fewer than 2 mints: {:?}", pool_path);
+                    continue;
+                }
+                sources.push(json_str);
+            }
+        }
+        if invalid > 0 {
+            warn!("Skipped {} pool files that don't load, see above", invalid);
+        }
+        if retyped > 0 {
+            info!("Loaded {} pool files as another type than their directory's", retyped);
+        }
+        if registry.duplicates() > 0 || !registry.conflicts().is_empty() {
+            warn!(
+                "Dropped {} duplicate and {} conflicting pool files",
+                registry.duplicates(),
+                registry.conflicts().len()
+            );
+        }
+        info!("Added {:?} mints", set.token_mints.len());
+        info!("Added {:?} pools", set.pools.len());
+        (set, sources)
+    }
+
+    /// Drops the pools with the given indices and rebuilds the edges.
+    /// Mints stay indexed even if they lose all their pools.
+    pub fn remove_pools(&mut self, drop: &HashSet<usize>) {
+        if drop.is_empty() {
+            return;
+        }
+        let pools = std::mem::take(&mut self.pools);
+        let pool_types = std::mem::take(&mut self.pool_types);
+        let pool_mints = std::mem::take(&mut self.pool_mints);
+        let tradeable = std::mem::take(&mut self.tradeable);
+        for edges in self.graph_edges.iter_mut() {
+            edges.clear();
+        }
+        for (i, (((pool, tipe), idxs), ok)) in pools
+            .into_iter()
+            .zip(pool_types)
+            .zip(pool_mints)
+            .zip(tradeable)
+            .enumerate()
+        {
+            if drop.contains(&i) {
+                continue;
+            }
+            for (idx_in, idx_out) in mint_pairs(&idxs) {
+                self.graph_edges[idx_in].insert(idx_out);
+            }
+            self.pools.push(pool);
+            self.pool_types.push(tipe);
+            self.pool_mints.push(idxs);
+            self.tradeable.push(ok);
+        }
+    }
+
+    /// Indices of the pools connected to any of `start_mints` through other
+    /// pools (a pool's mints are all reached together); no cycle through a
+    /// start mint can use the rest
+    pub fn reachable_pools(&self, start_mints: &[Pubkey]) -> HashSet<usize> {
+        let mut reached: HashSet<usize> =
+            start_mints.iter().filter_map(|mint| self.mint2idx.get(mint)).copied().collect();
+        let mut queue: Vec<usize> = reached.iter().copied().collect();
+        while let Some(idx) = queue.pop() {
+            for next in self.graph_edges[idx].iter() {
+                if reached.insert(*next) {
+                    queue.push(*next);
+                }
+            }
+        }
+        (0..self.pools.len())
+            .filter(|i| reached.contains(&self.pool_mints[*i][0]))
+            .collect()
+    }
+
+    /// Drops the pools `reachable_pools` leaves out, before their accounts
+    /// are fetched; returns how many were dropped
+    pub fn retain_reachable(&mut self, start_mints: &[Pubkey]) -> usize {
+        let reachable = self.reachable_pools(start_mints);
+        let unreachable: HashSet<usize> = (0..self.pools.len()).filter(|i| !reachable.contains(i)).collect();
+        self.remove_pools(&unreachable);
+        unreachable.len()
+    }
+
+    /// Number of loaded pools per pool type
+    pub fn counts_by_type(&self) -> HashMap<PoolType, usize> {
+        let mut counts = HashMap::new();
+        for tipe in self.pool_types.iter() {
+            *counts.entry(*tipe).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Fetches every pool's update accounts and hands them to the pools.
+    ///
+    /// Accounts stay aligned with the pools: every pool gets exactly the
+    /// accounts it asked for, in order, with `None` for accounts that don't
+    /// exist. Accounts missing from the batched fetch are retried one by one;
+    /// pools that still miss accounts, or can't decode them, are marked
+    /// untradeable (see `tradeable`) until a later refresh succeeds.
+    ///
+    /// The chunks are fetched through `AccountSource::get_chunks_with_slot`.
+    /// A chunk that fails for good is listed in the report's `failed_chunks`
+    /// and only its pools are marked untradeable; the refresh only fails if
+    /// every chunk did.
+    ///
+    /// Chunks can be read at different slots, so every pool is tagged with the
+    /// oldest slot any of its accounts was read at.
+    pub fn refresh<S: AccountSource + ?Sized>(
+        &mut self,
+        rpc: &S,
+        cluster: &Cluster,
+    ) -> ClientResult<RefreshReport> {
+        let update_pks: Vec<Vec<Pubkey>> =
+            self.pools.iter().map(|p| p.get_update_accounts()).collect();
+        let flat_pks = update_pks.concat();
+
+        let fetch_start = Instant::now();
+        let mut report = RefreshReport::default();
+        let mut accounts: Vec<Option<Account>> = Vec::with_capacity(flat_pks.len());
+        let mut slots: Vec<u64> = Vec::with_capacity(flat_pks.len());
+        // accounts of failed chunks, not worth retrying one by one
+        let mut failed = vec![false; flat_pks.len()];
+        let chunks: Vec<&[Pubkey]> = flat_pks.chunks(ACCOUNTS_PER_REQUEST).collect();
+        report.chunks = chunks.len();
+        let mut last_err = None;
+        for (i, (chunk, fetch)) in chunks.iter().zip(rpc.get_chunks_with_slot(&chunks)).enumerate() {
+            report.chunk_retries += fetch.attempts.saturating_sub(1) as usize;
+            let (mut chunk_accounts, slot) = match fetch.result {
+                Ok(fetched) => fetched,
+                Err(e) => {
+                    warn!("chunk {} ({} accounts) failed after {} attempts: {}", i, chunk.len(), fetch.attempts, e);
+                    report.failed_chunks.push(ChunkFailure {
+                        chunk: i,
+                        accounts: chunk.len(),
+                        attempts: fetch.attempts,
+                        error: e.to_string(),
+                    });
+                    let start = accounts.len();
+                    failed[start..start + chunk.len()].iter_mut().for_each(|f| *f = true);
+                    last_err = Some(e);
+                    (vec![], 0)
+                }
+            };
+            if !chunk_accounts.is_empty() && chunk_accounts.len() != chunk.len() {
+                // never shift the accounts of later pools
+                warn!("rpc returned {} accounts for {} keys", chunk_accounts.len(), chunk.len());
+            }
+            chunk_accounts.resize(chunk.len(), None);
+            slots.extend(std::iter::repeat(slot).take(chunk.len()));
+            accounts.extend(chunk_accounts);
+        }
+        if report.failed_chunks.len() == report.chunks {
+            if let Some(e) = last_err {
+                return Err(e);
+            }
+        }
+
+        // retry the holes individually, a node may just have been behind
+        for (i, pk) in flat_pks.iter().enumerate() {
+            if accounts[i].is_some() || failed[i] {
+                continue;
+            }
+            report.missing += 1;
+            match rpc.get_multiple_accounts_with_slot(&[*pk]) {
+                Ok((mut retried, slot)) => {
+                    if let Some(Some(account)) = retried.pop() {
+                        accounts[i] = Some(account);
+                        slots[i] = slot;
+                        report.recovered += 1;
+                    }
+                }
+                Err(e) => debug!("retry of {} failed: {}", pk, e),
+            }
+        }
+        if let (Some(min), Some(max)) = (slots.iter().min(), slots.iter().max()) {
+            debug!("Pool accounts read at slots {}..={}", min, max);
+        }
+        report.fetch_time = fetch_start.elapsed();
+
+        let decode_start = Instant::now();
+        let mut ptr = 0;
+        for (i, (pool, pks)) in self.pools.iter_mut().zip(update_pks.iter()).enumerate() {
+            let slice = accounts[ptr..ptr + pks.len()].to_vec();
+            let slot = slots[ptr..ptr + pks.len()].iter().min().copied();
+            ptr += pks.len();
+
+            let result = match pks.iter().zip(slice.iter()).find(|(_, acc)| acc.is_none()) {
+                Some((pk, _)) => Err(ArbError::MissingAccount(*pk)),
+                None => pool.set_update_accounts(slice, cluster.clone()).map_err(|e| {
+                    report.decode_errors.push((i, e.to_string()));
+                    e
+                }),
+            };
+            match result {
+                Ok(()) => {
+                    self.tradeable[i] = true;
+                    if let Some(slot) = slot {
+                        pool.set_update_slot(slot);
+                    }
+                }
+                Err(e) => {
+                    debug!("pool {} is untradeable: {}", pool.get_id(), e);
+                    self.tradeable[i] = false;
+                    report.untradeable.push(i);
+                }
+            }
+        }
+        report.decode_time = decode_start.elapsed();
+        if report.missing > 0 || !report.untradeable.is_empty() || !report.failed_chunks.is_empty() {
+            warn!(
+                "{} accounts missing ({} recovered on retry), {}/{} chunks failed, {} pools untradeable",
+                report.missing,
+                report.recovered,
+                report.failed_chunks.len(),
+                report.chunks,
+                report.untradeable.len()
+            );
+        }
+        Ok(report)
+    }
+
+    /// Reads every pool's parameter accounts (`get_param_accounts`) and
+    /// takes their fees and decimals over the ones of the pool JSON. Mints
+    /// shared by many pools are fetched once. Pools whose accounts can't be
+    /// fetched or decoded keep their JSON values.
+    pub fn refresh_params<S: AccountSource + ?Sized>(&mut self, rpc: &S) -> ParamReport {
+        let param_pks: Vec<Vec<Pubkey>> =
+            self.pools.iter().map(|p| p.get_param_accounts()).collect();
+        let mut unique: Vec<Pubkey> = param_pks.concat();
+        unique.sort();
+        unique.dedup();
+
+        let mut fetched: HashMap<Pubkey, Option<Account>> = HashMap::new();
+        let chunks: Vec<&[Pubkey]> = unique.chunks(ACCOUNTS_PER_REQUEST).collect();
+        for (chunk, fetch) in chunks.iter().zip(rpc.get_chunks_with_slot(&chunks)) {
+            match fetch.result {
+                // a short answer leaves the rest of the chunk unread
+                Ok((accounts, _)) => fetched.extend(chunk.iter().copied().zip(accounts)),
+                Err(e) => warn!("parameter accounts ({} keys) could not be fetched: {}", chunk.len(), e),
+            }
+        }
+
+        let mut report = ParamReport::default();
+        for (pool, pks) in self.pools.iter_mut().zip(param_pks.iter()) {
+            if pks.is_empty() {
+                continue;
+            }
+            let accounts: Option<Vec<Option<Account>>> =
+                pks.iter().map(|pk| fetched.get(pk).cloned()).collect();
+            let accounts = match accounts {
+                Some(accounts) => accounts,
+                None => {
+                    report.failed += 1;
+                    continue;
+                }
+            };
+            match pool.set_param_accounts(&accounts) {
+                Ok(changes) if changes.is_empty() => {}
+                Ok(changes) => {
+                    info!("{}: {}", pool.get_id(), changes.join(", "));
+                    report.changed += 1;
+                }
+                Err(e) => {
+                    warn!("{}: can't read its on-chain parameters: {}", pool.get_id(), e);
+                    report.failed += 1;
+                }
+            }
+        }
+        report
+    }
+
+    /// Indices of the pools that took their last update
+    pub fn tradeable_pools(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.pools.len()).filter(move |i| self.tradeable[*i])
+    }
+
+    /// Builds the exchange graph; every pool becomes an edge for each ordered
+    /// pair of its mints. Untradeable pools are left out.
+    pub fn into_graph(mut self) -> (Vec<Pubkey>, Vec<HashSet<usize>>, PoolGraph) {
+        let untradeable: HashSet<usize> =
+            (0..self.pools.len()).filter(|i| !self.tradeable[*i]).collect();
+        self.remove_pools(&untradeable);
+
+        let mut graph = PoolGraph::new();
+        for (pool, idxs) in self.pools.into_iter().zip(self.pool_mints) {
+            let quote = PoolQuote::new(Rc::new(pool));
+            for (idx_in, idx_out) in mint_pairs(&idxs) {
+                add_pool_to_graph(&mut graph, PoolIndex(idx_in), PoolIndex(idx_out), &quote);
+            }
+        }
+        (self.token_mints, self.graph_edges, graph)
+    }
+}
+
+impl Default for PoolSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Every ordered pair of distinct mints of a pool (by index), the
+/// directions it can swap
+pub fn mint_pairs(idxs: &[usize]) -> impl Iterator<Item = (usize, usize)> + '_ {
+    idxs.iter()
+        .flat_map(move |idx_in| idxs.iter().map(move |idx_out| (*idx_in, *idx_out)))
+        .filter(|(idx_in, idx_out)| idx_in != idx_out)
+}
+
+/// Adds a pool connection to the arbitrage graph
+///
+/// # Arguments
+/// * `graph` - Mutable reference to the pool graph
+/// * `idx0` - Index of the first token
+/// * `idx1` - Index of the second token
+/// * `quote` - Pool quote information
+pub fn add_pool_to_graph(graph: &mut PoolGraph, idx0: PoolIndex, idx1: PoolIndex, quote: &PoolQuote) {
+    let edges = graph
+        .0
+        .entry(idx0)
+        .or_insert_with(|| PoolEdge(HashMap::new()));
+    let quotes = edges.0.entry(idx1).or_insert_with(Vec::new);
+    quotes.push(quote.clone());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TOKEN_PROGRAM_ID;
+    use crate::test_pools;
+    use crate::serialize::decoder::MINT_LEN;
+
+    /// Answers with the accounts it holds, `None` for the rest
+    struct Accounts(HashMap<Pubkey, Account>);
+
+    impl AccountSource for Accounts {
+        fn get_multiple_accounts_with_slot(
+            &self,
+            pubkeys: &[Pubkey],
+        ) -> ClientResult<(Vec<Option<Account>>, u64)> {
+            Ok((pubkeys.iter().map(|pk| self.0.get(pk).cloned()).collect(), 1))
+        }
+    }
+
+    fn mint_account(decimals: u8) -> Account {
+        let mut data = vec![0; MINT_LEN];
+        data[44] = decimals;
+        data[45] = 1;
+        Account {
+            lamports: 1,
+            data,
+            owner: *TOKEN_PROGRAM_ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    fn orca_pool(a: &Pubkey, b: &Pubkey) -> PoolVariant {
+        test_pools::orca_pool(&[*a, *b], &[1_000_000_000, 1_000_000_000]).into()
+    }
+
+    #[test]
+    fn on_chain_decimals_replace_the_json_ones() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut set = PoolSet::new();
+        set.add_pool(PoolType::OrcaPoolType, orca_pool(&a, &b));
+
+        // the swap state is missing: the fees stay, the mints still count
+        let source = Accounts([(a, mint_account(9)), (b, mint_account(6))].into_iter().collect());
+        let report = set.refresh_params(&source);
+        assert_eq!(report.changed, 1);
+        assert_eq!(report.failed, 0);
+        assert_eq!(set.pools[0].mint_2_scale(&a), 9);
+        assert_eq!(set.pools[0].mint_2_scale(&b), 6);
+    }
+
+    #[test]
+    fn pools_out_of_reach_of_the_start_mint_are_dropped() {
+        let mints: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+        let mut set = PoolSet::new();
+        // 0 - 1 - 2 and, apart, 3 - 4
+        for (x, y) in [(0, 1), (1, 2), (3, 4)] {
+            set.add_pool(PoolType::OrcaPoolType, orca_pool(&mints[x], &mints[y]));
+        }
+        let island = set.pools[2].get_address();
+
+        assert_eq!(set.reachable_pools(&[mints[2]]), [0, 1].into_iter().collect());
+        assert_eq!(set.reachable_pools(&[mints[0], mints[4]]).len(), 3);
+        assert!(set.reachable_pools(&[Pubkey::new_unique()]).is_empty());
+
+        assert_eq!(set.retain_reachable(&[mints[0]]), 1);
+        assert_eq!(set.pools.len(), 2);
+        assert!(set.pools.iter().all(|pool| pool.get_address() != island));
+        assert!(set.graph_edges[set.mint2idx[&mints[3]]].is_empty());
+    }
+
+    #[test]
+    fn pools_of_more_mints_trade_every_pair() {
+        let mints: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let mut set = PoolSet::new();
+        // a three token pool (skipping the two token validation) next to a pair
+        let basket = test_pools::orca_pool(&mints[..3], &[1_000_000_000; 3]);
+        assert!(set.add_pool(PoolType::OrcaPoolType, basket.into()));
+        set.add_pool(PoolType::OrcaPoolType, orca_pool(&mints[2], &mints[3]));
+        let idx = |i: usize| set.mint2idx[&mints[i]];
+        assert_eq!(set.pool_mints[0].len(), 3);
+        assert_eq!(set.graph_edges[idx(0)], [idx(1), idx(2)].into_iter().collect());
+        assert_eq!(set.graph_edges[idx(2)].len(), 3);
+
+        set.remove_pools(&[1].into_iter().collect());
+        assert_eq!(set.graph_edges[idx(2)], [idx(0), idx(1)].into_iter().collect());
+        assert!(set.graph_edges[idx(3)].is_empty());
+
+        let (_, _, graph) = set.into_graph();
+        let edges: usize = graph.0.values().map(|edge| edge.0.len()).sum();
+        assert_eq!(edges, 6);
+    }
+}
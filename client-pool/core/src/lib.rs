@@ -0,0 +1,53 @@
+//! Quote engine of the arbitrage bot
+//!
+//! Everything needed to price routes without executing them:
+//!
+//! * `amount`: `ScaledAmount`, a token amount that knows its mint
+//! * `pool` / `pools`: the `PoolOperations` trait and the Orca, Raydium,
+//!   Serum and Jupiter pools, built from their JSON files by `pool_factory`
+//!   into a statically dispatched `PoolVariant`, after `pool_schema` checked
+//!   the files' fields
+//! * `pool_utils`: the curve math (constant product, stable, order books)
+//! * `graph`: `PoolSet` loads pool directories, refreshes the pools from any
+//!   `AccountSource` and builds the exchange graph
+//! * `search`: `Searcher` walks the graph and hands every cycle to a
+//!   `SearchVisitor`
+//!
+//! Sending transactions, tips and the trading loop live in the `client` crate.
+//!
+//! Everything but the curve math needs the `client` feature (on by default),
+//! which pulls in the solana client crates. Without it the crate builds for
+//! `wasm32`; the `wasm32` feature adds JS bindings for the quotes (`wasm`).
+
+pub mod error;
+pub mod pool_utils;
+
+#[cfg(feature = "client")]
+pub mod amount;
+#[cfg(feature = "client")]
+pub mod constants;
+#[cfg(feature = "client")]
+pub mod graph;
+#[cfg(feature = "client")]
+pub mod pool;
+#[cfg(feature = "client")]
+pub mod pool_schema;
+#[cfg(feature = "client")]
+pub mod pools;
+#[cfg(feature = "client")]
+pub mod registry;
+#[cfg(feature = "client")]
+pub mod search;
+#[cfg(feature = "client")]
+pub mod serialize;
+#[cfg(feature = "client")]
+pub mod utils;
+#[cfg(all(feature = "client", any(test, feature = "test-pools")))]
+pub mod test_pools;
+
+#[cfg(feature = "wasm32")]
+pub mod wasm;
+
+#[cfg(feature = "client")]
+#[macro_use]
+extern crate lazy_static;
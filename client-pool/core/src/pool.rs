@@ -0,0 +1,276 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Program;
+use solana_sdk::account::Account;
+use solana_sdk::instruction::Instruction;
+
+use crate::amount::ScaledAmount;
+use crate::error::{ArbError, ArbResult, QuoteResult};
+use crate::pool_schema::{check, detect_pool_type, pool_schema};
+use crate::pools::*;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+use anchor_client::Cluster;
+use enum_dispatch::enum_dispatch;
+
+#[derive(Debug)]
+pub struct PoolDir {
+    pub tipe: PoolType,
+    pub dir_path: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum PoolType {
+    #[serde(rename = "orca")]
+    OrcaPoolType,
+    #[serde(rename = "raydium")]
+    RaydiumType,
+    #[serde(rename = "serum")]
+    SerumPoolType,
+    #[serde(rename = "jupiter")]
+    JupiterPoolType,
+    #[serde(rename = "meteora")]
+    MeteoraType,
+    #[serde(rename = "phoenix")]
+    PhoenixType,
+    #[serde(rename = "lifinity")]
+    LifinityType,
+}
+
+impl PoolType {
+    /// Whether `pool_factory` has an implementation for this pool type
+    pub fn is_supported(&self) -> bool {
+        matches!(
+            self,
+            PoolType::OrcaPoolType
+                | PoolType::RaydiumType
+                | PoolType::SerumPoolType
+                | PoolType::JupiterPoolType
+        )
+    }
+}
+
+/// Parses a pool JSON file of the given type
+pub fn pool_factory(tipe: &PoolType, json_str: &String) -> ArbResult<PoolVariant> {
+    parse_pool(tipe, json_str).map(|(pool, _)| pool)
+}
+
+/// Like `pool_factory`, plus the fields of the file its pool type doesn't
+/// have (see `pool_schema`)
+pub fn parse_pool(tipe: &PoolType, json_str: &str) -> ArbResult<(PoolVariant, Vec<String>)> {
+    parse_pool_json(tipe, serde_json::from_str(json_str)?)
+}
+
+/// Like `parse_pool` for a file of whatever pool type its contents are, see
+/// `detect_pool_type`; `hint` (the type of its directory) decides when they
+/// can't tell
+pub fn parse_any_pool(
+    json_str: &str,
+    hint: Option<PoolType>,
+) -> ArbResult<(PoolType, PoolVariant, Vec<String>)> {
+    let json: serde_json::Value = serde_json::from_str(json_str)?;
+    let tipe = detect_pool_type(&json, hint).ok_or(ArbError::UndetectedPoolType)?;
+    let (pool, unknown) = parse_pool_json(&tipe, json)?;
+    Ok((tipe, pool, unknown))
+}
+
+fn parse_pool_json(tipe: &PoolType, json: serde_json::Value) -> ArbResult<(PoolVariant, Vec<String>)> {
+    let schema = pool_schema(tipe).ok_or(ArbError::UnsupportedPoolType(*tipe))?;
+    let report = check(schema, &json);
+    if !report.is_valid() {
+        return Err(ArbError::PoolSchema { tipe: *tipe, report });
+    }
+    let pool: PoolVariant = match tipe {
+        PoolType::OrcaPoolType => {
+            let pool: OrcaPool = serde_json::from_value(json)?;
+            pool.validate()?;
+            pool.into()
+        }
+        PoolType::RaydiumType => {
+            let pool: RaydiumPool = serde_json::from_value(json)?;
+            pool.validate()?;
+            pool.into()
+        }
+        PoolType::SerumPoolType => serde_json::from_value::<SerumPool>(json)?.into(),
+        PoolType::JupiterPoolType => serde_json::from_value::<JupiterPool>(json)?.into(),
+        PoolType::MeteoraType | PoolType::PhoenixType | PoolType::LifinityType => {
+            return Err(ArbError::UnsupportedPoolType(*tipe))
+        }
+    };
+    Ok((pool, report.unknown))
+}
+
+#[enum_dispatch]
+pub trait PoolOperations: Debug {
+    fn get_name(&self) -> String;
+    fn get_address(&self) -> Pubkey;
+
+    /// Short id used in logs, metrics and dedup keys, e.g. `orca:2p7nYbtP`;
+    /// derived from the address so it is the same on every run
+    fn get_id(&self) -> String {
+        let address = self.get_address().to_string();
+        format!("{}:{}", self.get_name().to_lowercase(), &address[..8])
+    }
+
+    /// Independent copy, e.g. to quote on newer accounts without touching
+    /// the pool the search uses
+    fn clone_box(&self) -> Box<dyn PoolOperations>;
+
+    fn get_update_accounts(&self) -> Vec<Pubkey>;
+    /// Updates the pool from its `get_update_accounts` (same order, `None` if missing)
+    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, cluster: Cluster) -> ArbResult<()>;
+    /// Slot the last `set_update_accounts` data was fetched at
+    fn get_update_slot(&self) -> Option<u64>;
+    fn set_update_slot(&mut self, slot: u64);
+
+    /// Whether the pool's data is at most `max_age` slots behind `current_slot`;
+    /// pools that were never tagged with a slot are never fresh
+    fn is_fresh(&self, current_slot: u64, max_age: u64) -> bool {
+        match self.get_update_slot() {
+            Some(slot) => current_slot.saturating_sub(slot) <= max_age,
+            None => false,
+        }
+    }
+
+    fn mint_2_addr(&self, mint: &Pubkey) -> Pubkey;
+    fn get_mints(&self) -> Vec<Pubkey>;
+    fn mint_2_scale(&self, mint: &Pubkey) -> u64;
+
+    /// Whole tokens of `amount`, through its mint's decimals
+    fn ui_amount(&self, amount: &ScaledAmount) -> f64 {
+        amount.to_ui(self.mint_2_scale(&amount.mint))
+    }
+    /// `ui` whole tokens of `mint`, through its decimals
+    fn scaled_amount(&self, mint: &Pubkey, ui: f64) -> ScaledAmount {
+        ScaledAmount::from_ui(*mint, ui, self.mint_2_scale(mint))
+    }
+
+    /// `get_quote_with_amounts_scaled` of `amount_in`'s mint into `mint_out`,
+    /// the output tagged with `mint_out`
+    fn quote(&self, amount_in: &ScaledAmount, mint_out: &Pubkey) -> QuoteResult<ScaledAmount> {
+        let amount_out = self.get_quote_with_amounts_scaled(amount_in.amount, &amount_in.mint, mint_out)?;
+        Ok(ScaledAmount::new(*mint_out, amount_out))
+    }
+
+    /// Output (scaled) of swapping `amount_in` of `mint_in`; an error if the
+    /// pool can't quote it (no reserves, empty book, overflow), which makes
+    /// the hop unusable rather than the quote zero
+    fn get_quote_with_amounts_scaled(
+        &self,
+        amount_in: u128,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> QuoteResult<u128>;
+    /// Outputs (scaled) of swapping each of `amounts_in` of `mint_in`, the
+    /// same as quoting them one by one. Pools override it to share the work
+    /// between the amounts (one reserve read, one walk of the book), so a
+    /// ladder of input sizes costs little more than a single quote.
+    fn get_quotes_for_amounts(
+        &self,
+        amounts_in: &[u128],
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> Vec<QuoteResult<u128>> {
+        amounts_in
+            .iter()
+            .map(|amount_in| self.get_quote_with_amounts_scaled(*amount_in, mint_in, mint_out))
+            .collect()
+    }
+    /// Input needed to receive at least `amount_out` of `mint_out`, `None` if
+    /// the pool can't pay out that much
+    fn get_quote_with_exact_out(
+        &self,
+        amount_out: u128,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> Option<u128>;
+    /// Largest input (scaled) the pool fills within `max_impact_bps` of its
+    /// current price, `None` if it isn't an order book; AMM price impact is
+    /// already in their quotes
+    fn get_max_input_within(
+        &self,
+        _mint_in: &Pubkey,
+        _mint_out: &Pubkey,
+        _max_impact_bps: u64,
+    ) -> Option<u128> {
+        None
+    }
+    fn swap_ix(
+        &self,
+        program: &Program,
+        owner: &Pubkey,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> ArbResult<Vec<Instruction>>;
+
+    /// Instructions moving what trades left in the pool's accounts of `owner`
+    /// (unsettled open orders funds) back to its token accounts, first
+    /// consuming up to `consume_events` fill events; empty for pools that
+    /// pay out within the swap
+    fn settle_ix(&self, _owner: &Pubkey, _consume_events: Option<u16>) -> ArbResult<Vec<Instruction>> {
+        Ok(vec![])
+    }
+
+    /// Accounts holding the parameters the pool's JSON copies (its state
+    /// account for the fee, its mints for the decimals), read by
+    /// `PoolSet::refresh_params`; none by default
+    fn get_param_accounts(&self) -> Vec<Pubkey> {
+        vec![]
+    }
+
+    /// Takes the fee and decimals of the accounts of `get_param_accounts`
+    /// (same order, `None` if missing) over the JSON's and describes what
+    /// changed. Missing accounts leave their values as they are.
+    fn set_param_accounts(&mut self, _accounts: &[Option<Account>]) -> ArbResult<Vec<String>> {
+        Ok(vec![])
+    }
+
+    /// Replaces the trading fee read from the pool's JSON (a config
+    /// override); an error for pools whose quotes don't use that fee
+    fn set_fee_bps(&mut self, _fee_bps: u64) -> ArbResult<()> {
+        Err(ArbError::Unsupported("fee overrides for this pool type"))
+    }
+
+    /// Trading fee a swap pays (in bps), `None` if the pool type doesn't
+    /// know it (aggregators)
+    fn get_fee_bps(&self) -> Option<f64> {
+        None
+    }
+
+    fn can_trade(&self, mint_in: &Pubkey, mint_out: &Pubkey) -> bool; // used for tests
+
+    /// Current reserves per mint (scaled), `None` if the pool has no simple
+    /// reserves (order books, aggregators)
+    fn get_reserves(&self) -> Option<Vec<(Pubkey, u128)>> {
+        None
+    }
+
+    /// The pool as a later swap of the same transaction finds it, once this
+    /// one swapped `amount_in` of `mint_in` through it; `None` if the pool
+    /// type doesn't follow its state (order books, aggregators)
+    fn after_swap(&self, _amount_in: u128, _mint_in: &Pubkey, _mint_out: &Pubkey) -> Option<PoolVariant> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_pool_json_is_an_error() {
+        let json = "{ \"address\": 1 }".to_string();
+        assert!(matches!(
+            pool_factory(&PoolType::OrcaPoolType, &json),
+            Err(ArbError::PoolSchema { tipe: PoolType::OrcaPoolType, .. })
+        ));
+        assert!(matches!(
+            pool_factory(&PoolType::OrcaPoolType, &"{ \"address\": ".to_string()),
+            Err(ArbError::PoolJson(_))
+        ));
+        assert!(matches!(
+            pool_factory(&PoolType::PhoenixType, &json),
+            Err(ArbError::UnsupportedPoolType(PoolType::PhoenixType))
+        ));
+    }
+}
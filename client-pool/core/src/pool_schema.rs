@@ -0,0 +1,370 @@
+//! Pool file schemas
+//!
+//! The fields every pool type's JSON file has, with the format of their
+//! values, checked before a file is deserialized: a file that doesn't match
+//! is rejected with every missing and malformed field and the format it
+//! should have, rather than with the first error serde runs into. Fields a
+//! pool type doesn't have are ignored when loading, but reported since they
+//! are often a misspelled optional field.
+//!
+//! The schemas also tell the pool type of a file from its contents, so a
+//! file doesn't have to sit in a directory of its type: see
+//! `detect_pool_type`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use serde_json::Value;
+
+use crate::constants::{OPENBOOK_PROGRAM_ID, ORCA_PROGRAM_ID, RAYDIUM_PROGRAM_ID, SERUM_PROGRAM_ID};
+use crate::pool::PoolType;
+
+/// Format of a field's value
+#[derive(Debug)]
+pub enum Format {
+    /// A base58 pubkey string
+    Pubkey,
+    /// An array of base58 pubkey strings
+    Pubkeys,
+    /// A non-negative integer
+    Unsigned,
+    /// Any number
+    Number,
+    /// Any string
+    Text,
+    /// An object with the given fields
+    Object(&'static [Field]),
+    /// An object with values of the given format under any key
+    Map(&'static Format),
+}
+
+/// A field of a pool file
+#[derive(Debug)]
+pub struct Field {
+    pub name: &'static str,
+    pub format: Format,
+    /// Optional fields may also be `null`
+    pub required: bool,
+}
+
+const fn required(name: &'static str, format: Format) -> Field {
+    Field {
+        name,
+        format,
+        required: true,
+    }
+}
+
+const fn optional(name: &'static str, format: Format) -> Field {
+    Field {
+        name,
+        format,
+        required: false,
+    }
+}
+
+const FRACTION: &[Field] = &[
+    required("numerator", Format::Unsigned),
+    required("denominator", Format::Unsigned),
+];
+
+const FEE_STRUCTURE: &[Field] = &[
+    required("traderFee", Format::Object(FRACTION)),
+    required("ownerFee", Format::Object(FRACTION)),
+];
+
+const TOKEN: &Format = &Format::Object(&[
+    required("tag", Format::Text),
+    required("name", Format::Text),
+    required("mint", Format::Pubkey),
+    required("scale", Format::Unsigned),
+    required("addr", Format::Pubkey),
+]);
+
+const ORCA: &[Field] = &[
+    required("address", Format::Pubkey),
+    required("nonce", Format::Unsigned),
+    required("authority", Format::Pubkey),
+    required("poolTokenMint", Format::Pubkey),
+    required("poolTokenDecimals", Format::Unsigned),
+    required("feeAccount", Format::Pubkey),
+    required("tokenIds", Format::Pubkeys),
+    required("tokens", Format::Map(TOKEN)),
+    required("feeStructure", Format::Object(FEE_STRUCTURE)),
+    required("curveType", Format::Unsigned),
+    optional("amp", Format::Unsigned),
+    optional("programId", Format::Pubkey),
+];
+
+const RAYDIUM: &[Field] = &[
+    required("address", Format::Pubkey),
+    required("nonce", Format::Unsigned),
+    required("authority", Format::Pubkey),
+    required("lpTokenMint", Format::Pubkey),
+    required("feeAccount", Format::Pubkey),
+    required("tokenIds", Format::Pubkeys),
+    required("tokens", Format::Map(TOKEN)),
+    required("feeStructure", Format::Object(FEE_STRUCTURE)),
+    optional("openOrders", Format::Pubkey),
+    optional("poolAmounts", Format::Map(&Format::Unsigned)),
+    optional("programId", Format::Pubkey),
+];
+
+const SERUM: &[Field] = &[
+    required("ownAddress", Format::Pubkey),
+    required("baseMint", Format::Pubkey),
+    required("quoteMint", Format::Pubkey),
+    required("baseScale", Format::Unsigned),
+    required("quoteScale", Format::Unsigned),
+    required("baseVault", Format::Pubkey),
+    required("quoteVault", Format::Pubkey),
+    required("requestQueue", Format::Pubkey),
+    required("eventQueue", Format::Pubkey),
+    required("bids", Format::Pubkey),
+    required("asks", Format::Pubkey),
+    required("vaultSigner", Format::Pubkey),
+    required("takerFeePct", Format::Number),
+    optional("programId", Format::Pubkey),
+];
+
+const JUPITER: &[Field] = &[
+    required("ownAddress", Format::Pubkey),
+    required("baseMint", Format::Pubkey),
+    required("quoteMint", Format::Pubkey),
+    required("baseScale", Format::Unsigned),
+    required("quoteScale", Format::Unsigned),
+    optional("programId", Format::Pubkey),
+];
+
+/// The fields of a pool type's files, `None` for unsupported pool types
+pub fn pool_schema(tipe: &PoolType) -> Option<&'static [Field]> {
+    match tipe {
+        PoolType::OrcaPoolType => Some(ORCA),
+        PoolType::RaydiumType => Some(RAYDIUM),
+        PoolType::SerumPoolType => Some(SERUM),
+        PoolType::JupiterPoolType => Some(JUPITER),
+        PoolType::MeteoraType | PoolType::PhoenixType | PoolType::LifinityType => None,
+    }
+}
+
+/// The pool types `detect_pool_type` tells apart
+const DETECTABLE: [PoolType; 4] = [
+    PoolType::OrcaPoolType,
+    PoolType::RaydiumType,
+    PoolType::SerumPoolType,
+    PoolType::JupiterPoolType,
+];
+
+/// The pool type of a file's contents: the one its `programId` belongs to
+/// if it has one, otherwise the one whose schema it matches with the fewest
+/// unknown fields (a Serum market also has every field of a Jupiter pool).
+/// `hint` decides between types that match as well, and is the type if none
+/// matches so that the file is reported against its schema.
+pub fn detect_pool_type(json: &Value, hint: Option<PoolType>) -> Option<PoolType> {
+    let program_id = json
+        .get("programId")
+        .and_then(|id| id.as_str())
+        .and_then(|id| Pubkey::from_str(id).ok());
+    let programs = [
+        (*ORCA_PROGRAM_ID, PoolType::OrcaPoolType),
+        (*RAYDIUM_PROGRAM_ID, PoolType::RaydiumType),
+        (*SERUM_PROGRAM_ID, PoolType::SerumPoolType),
+        (*OPENBOOK_PROGRAM_ID, PoolType::SerumPoolType),
+    ];
+    if let Some((_, tipe)) = programs.iter().find(|(id, _)| Some(*id) == program_id) {
+        return Some(*tipe);
+    }
+    let matches: Vec<(PoolType, usize)> = DETECTABLE
+        .iter()
+        .filter_map(|tipe| {
+            let report = check(pool_schema(tipe)?, json);
+            report.is_valid().then(|| (*tipe, report.unknown.len()))
+        })
+        .collect();
+    let fewest = match matches.iter().map(|(_, unknown)| *unknown).min() {
+        Some(fewest) => fewest,
+        None => return hint,
+    };
+    let best: Vec<PoolType> = matches
+        .into_iter()
+        .filter(|(_, unknown)| *unknown == fewest)
+        .map(|(tipe, _)| tipe)
+        .collect();
+    match best.as_slice() {
+        [tipe] => Some(*tipe),
+        _ => hint.filter(|hint| best.contains(hint)),
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Pubkey => write!(f, "a base58 pubkey"),
+            Format::Pubkeys => write!(f, "an array of base58 pubkeys"),
+            Format::Unsigned => write!(f, "a non-negative integer"),
+            Format::Number => write!(f, "a number"),
+            Format::Text => write!(f, "a string"),
+            Format::Object(fields) => {
+                let names: Vec<&str> = fields.iter().map(|field| field.name).collect();
+                write!(f, "an object with {}", names.join(", "))
+            }
+            Format::Map(format) => write!(f, "an object of {} by key", format),
+        }
+    }
+}
+
+/// What a pool file has that its schema doesn't expect
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SchemaReport {
+    /// Required fields the file doesn't have, with their format
+    pub missing: Vec<String>,
+    /// Fields whose value isn't of their format
+    pub invalid: Vec<String>,
+    /// Fields the schema doesn't have (ignored when loading)
+    pub unknown: Vec<String>,
+}
+
+impl SchemaReport {
+    /// Whether the file can be loaded, unknown fields aside
+    pub fn is_valid(&self) -> bool {
+        self.missing.is_empty() && self.invalid.is_empty()
+    }
+}
+
+impl fmt::Display for SchemaReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = vec![];
+        if !self.missing.is_empty() {
+            parts.push(format!("missing {}", self.missing.join(", ")));
+        }
+        if !self.invalid.is_empty() {
+            parts.push(self.invalid.join(", "));
+        }
+        if !self.unknown.is_empty() {
+            parts.push(format!("unknown fields {}", self.unknown.join(", ")));
+        }
+        write!(f, "{}", parts.join("; "))
+    }
+}
+
+/// Checks a pool file against `fields`
+pub fn check(fields: &[Field], json: &Value) -> SchemaReport {
+    let mut report = SchemaReport::default();
+    check_object(fields, json, "", &mut report);
+    report
+}
+
+fn check_object(fields: &[Field], value: &Value, path: &str, report: &mut SchemaReport) {
+    let object = match value.as_object() {
+        Some(object) => object,
+        None => {
+            let name = if path.is_empty() { "the file" } else { path };
+            report.invalid.push(format!("{} should be an object, got {}", name, value));
+            return;
+        }
+    };
+    let join = |name: &str| if path.is_empty() { name.to_string() } else { format!("{}.{}", path, name) };
+    for field in fields {
+        match object.get(field.name) {
+            None | Some(Value::Null) if !field.required => {}
+            None => report.missing.push(format!("{} ({})", join(field.name), field.format)),
+            Some(value) => check_value(&field.format, value, &join(field.name), report),
+        }
+    }
+    for name in object.keys() {
+        if !fields.iter().any(|field| field.name == name) {
+            report.unknown.push(join(name));
+        }
+    }
+}
+
+fn check_value(format: &Format, value: &Value, path: &str, report: &mut SchemaReport) {
+    let is_pubkey = |value: &Value| value.as_str().map_or(false, |s| Pubkey::from_str(s).is_ok());
+    let valid = match format {
+        Format::Pubkey => is_pubkey(value),
+        Format::Pubkeys => value.as_array().map_or(false, |values| values.iter().all(is_pubkey)),
+        Format::Unsigned => value.is_u64(),
+        Format::Number => value.is_number(),
+        Format::Text => value.is_string(),
+        Format::Object(fields) => return check_object(fields, value, path, report),
+        Format::Map(format) => match value.as_object() {
+            Some(object) => {
+                for (key, value) in object {
+                    check_value(format, value, &format!("{}.{}", path, key), report);
+                }
+                true
+            }
+            None => false,
+        },
+    };
+    if !valid {
+        report.invalid.push(format!("{} should be {}, got {}", path, format, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_every_missing_malformed_and_unknown_field() {
+        let json: Value = serde_json::from_str(
+            r#"{ "ownAddress": "not a key", "baseMint": "So11111111111111111111111111111111111111112",
+                 "quoteMint": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "baseScale": "9",
+                 "quotescale": 6 }"#,
+        )
+        .unwrap();
+        let report = check(JUPITER, &json);
+        assert!(!report.is_valid());
+        assert_eq!(report.missing, vec!["quoteScale (a non-negative integer)"]);
+        assert_eq!(
+            report.invalid,
+            vec![
+                "ownAddress should be a base58 pubkey, got \"not a key\"",
+                "baseScale should be a non-negative integer, got \"9\"",
+            ]
+        );
+        assert_eq!(report.unknown, vec!["quotescale"]);
+
+        let report = check(ORCA, &Value::Array(vec![]));
+        assert_eq!(report.invalid, vec!["the file should be an object, got []"]);
+    }
+
+    #[test]
+    fn fixtures_match_their_schema() {
+        for (tipe, json) in [
+            (PoolType::OrcaPoolType, include_str!("../benches/fixtures/orca.json")),
+            (PoolType::OrcaPoolType, include_str!("../benches/fixtures/orca_stable.json")),
+            (PoolType::RaydiumType, include_str!("../benches/fixtures/raydium.json")),
+            (PoolType::SerumPoolType, include_str!("../benches/fixtures/serum.json")),
+        ] {
+            let report = check(pool_schema(&tipe).unwrap(), &serde_json::from_str(json).unwrap());
+            assert_eq!(report, SchemaReport::default(), "{:?}", tipe);
+        }
+    }
+
+    #[test]
+    fn pool_types_are_told_from_the_contents() {
+        let fixture = |json: &str| -> Value { serde_json::from_str(json).unwrap() };
+        let orca = fixture(include_str!("../benches/fixtures/orca.json"));
+        let raydium = fixture(include_str!("../benches/fixtures/raydium.json"));
+        let serum = fixture(include_str!("../benches/fixtures/serum.json"));
+        assert_eq!(detect_pool_type(&orca, Some(PoolType::RaydiumType)), Some(PoolType::OrcaPoolType));
+        assert_eq!(detect_pool_type(&raydium, Some(PoolType::OrcaPoolType)), Some(PoolType::RaydiumType));
+        // every field of a jupiter pool, and more
+        assert_eq!(detect_pool_type(&serum, Some(PoolType::JupiterPoolType)), Some(PoolType::SerumPoolType));
+
+        let mut jupiter = serum.clone();
+        jupiter.as_object_mut().unwrap().retain(|field, _| JUPITER.iter().any(|f| f.name == field));
+        assert_eq!(detect_pool_type(&jupiter, None), Some(PoolType::JupiterPoolType));
+
+        // the program id wins, nothing matching falls back to the hint
+        let mut tagged = jupiter;
+        tagged["programId"] = Value::String(SERUM_PROGRAM_ID.to_string());
+        assert_eq!(detect_pool_type(&tagged, None), Some(PoolType::SerumPoolType));
+        let empty = fixture("{}");
+        assert_eq!(detect_pool_type(&empty, Some(PoolType::OrcaPoolType)), Some(PoolType::OrcaPoolType));
+        assert_eq!(detect_pool_type(&empty, None), None);
+    }
+}
@@ -0,0 +1,137 @@
+//! Exact-output (reverse) quoting
+//!
+//! Finds the smallest input that yields at least a target output. Every
+//! forward quote is monotone in its input, so any pool can be inverted by
+//! bisection; constant product pools start from a closed-form estimate.
+
+use crate::pool_utils::fees::Fees;
+
+/// Largest input a swap instruction accepts (token amounts are u64 on chain)
+pub const MAX_SWAP_INPUT: u128 = u64::MAX as u128;
+
+/// An `amount_in <= max_in` with `quote(amount_in) >= amount_out` and
+/// `quote(amount_in - 1) < amount_out`, `None` if even `max_in` doesn't reach
+/// `amount_out`. (Fee rounding makes quotes only roughly monotone, so this is
+/// the smallest input around the bracketed point, which is what callers need.)
+///
+/// # Arguments
+/// * `amount_out` - Target output amount
+/// * `hint` - Estimate of the answer to start the search from
+/// * `max_in` - Largest input to try
+/// * `quote` - Forward quote, (roughly) non-decreasing in its input
+pub fn search_exact_out<F>(amount_out: u128, hint: Option<u128>, max_in: u128, quote: F) -> Option<u128>
+where
+    F: Fn(u128) -> u128,
+{
+    if amount_out == 0 {
+        return Some(0);
+    }
+
+    // invariant: quote(lo) < amount_out <= quote(hi)
+    let mut lo = 0;
+    let mut hi = match hint {
+        Some(hint) => {
+            // bracket the hint tightly so the bisection only takes a few steps
+            let below = hint.saturating_sub(hint / 1_000 + 1);
+            if below > 0 && quote(below) < amount_out {
+                lo = below;
+            }
+            hint.saturating_add(hint / 1_000 + 1)
+        }
+        None => amount_out,
+    }
+    .clamp(1, max_in.max(1));
+    while quote(hi) < amount_out {
+        if hi >= max_in {
+            return None;
+        }
+        lo = hi;
+        hi = hi.saturating_mul(2).min(max_in);
+    }
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        if quote(mid) >= amount_out {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    Some(hi)
+}
+
+/// Closed-form estimate of the input a constant product pool needs to pay
+/// out `amount_out`, with trade and owner fees taken from the input.
+/// Rounding of the on-chain math may put the exact answer a few units off.
+pub fn constant_product_exact_out(
+    amount_out: u128,
+    reserve_in: u128,
+    reserve_out: u128,
+    fees: &Fees,
+) -> Option<u128> {
+    if amount_out >= reserve_out {
+        return None;
+    }
+    // input (after fees) = ceil(reserve_in * amount_out / (reserve_out - amount_out))
+    let num = reserve_in.checked_mul(amount_out)?;
+    let den = reserve_out - amount_out;
+    let in_less_fees = (num + den - 1) / den;
+
+    // fees are fractions of the gross input: gross * (1 - f_trade - f_owner) = net
+    let fee_fraction = |n: u64, d: u64| if d == 0 { 0.0 } else { n as f64 / d as f64 };
+    let total_fee = fee_fraction(fees.trade_fee_numerator, fees.trade_fee_denominator)
+        + fee_fraction(fees.owner_trade_fee_numerator, fees.owner_trade_fee_denominator);
+    if total_fee >= 1.0 {
+        return None;
+    }
+    Some((in_less_fees as f64 / (1.0 - total_fee)).ceil() as u128)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pool_utils::base::CurveType;
+    use crate::pool_utils::orca::get_pool_quote_with_amounts;
+
+    fn fees() -> Fees {
+        Fees {
+            trade_fee_numerator: 25,
+            trade_fee_denominator: 10_000,
+            owner_trade_fee_numerator: 5,
+            owner_trade_fee_denominator: 10_000,
+            ..Fees::default()
+        }
+    }
+
+    fn quote(amount_in: u128) -> u128 {
+        get_pool_quote_with_amounts(
+            amount_in,
+            CurveType::ConstantProduct,
+            0,
+            &fees(),
+            1_000_000_000,
+            2_000_000_000,
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn exact_out_is_the_smallest_sufficient_input() {
+        for amount_out in [1, 10, 12_345, 1_000_000, 500_000_000] {
+            let hint = constant_product_exact_out(amount_out, 1_000_000_000, 2_000_000_000, &fees());
+            let amount_in = search_exact_out(amount_out, hint, MAX_SWAP_INPUT, quote).unwrap();
+            assert!(quote(amount_in) >= amount_out);
+            assert!(amount_in == 0 || quote(amount_in - 1) < amount_out);
+
+            let no_hint = search_exact_out(amount_out, None, MAX_SWAP_INPUT, quote).unwrap();
+            assert!(quote(no_hint) >= amount_out);
+            assert!(no_hint.abs_diff(amount_in) <= 2);
+        }
+    }
+
+    #[test]
+    fn unreachable_outputs_are_none() {
+        assert!(constant_product_exact_out(2_000_000_000, 1_000_000_000, 2_000_000_000, &fees()).is_none());
+        assert!(search_exact_out(2_000_000_000, None, 10_000_000_000, quote).is_none());
+    }
+}
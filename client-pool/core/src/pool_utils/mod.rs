@@ -4,8 +4,10 @@ pub mod base;
 pub mod calculator;
 pub mod constant_price;
 pub mod constant_product;
+pub mod exact_out;
 pub mod fees;
 pub mod offset;
+pub mod split;
 pub mod stable;
 
 // pool specific details 
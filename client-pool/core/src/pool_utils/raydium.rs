@@ -1,11 +1,10 @@
-use anyhow::Result;
 use crate::{
+    error::{QuoteError, QuoteResult},
     pool_utils,
     pool_utils::base::{SwapCurve, CurveType},
     pool_utils::fees::Fees,
     pool_utils::{constant_product::ConstantProductCurve, stable::StableCurve},
 };
-use core::panic;
 use std::sync::Arc;
 
 pub fn get_pool_quote_with_amounts(
@@ -16,7 +15,7 @@ pub fn get_pool_quote_with_amounts(
     input_token_pool_amount: u128,
     output_token_pool_amount: u128,
     slippage_percent: Option<[u128;2]>,
-) -> Result<u128> {
+) -> QuoteResult<u128> {
     let mut quote;
     let trade_direction = pool_utils::calculator::TradeDirection::AtoB;
     
@@ -34,12 +33,7 @@ pub fn get_pool_quote_with_amounts(
         );
         quote = match swap_quote {
             Some(v) => { v.destination_amount_swapped },
-            None => {
-                // println!("swap err: {} {} {}", amount_in, 
-                //     input_token_pool_amount, 
-                //     output_token_pool_amount);
-                0
-            }
+            None => no_output(amount_in, input_token_pool_amount, output_token_pool_amount)?,
         }
 
     } else if curve_type == CurveType::Stable { // stableswap (2 for orca)
@@ -47,23 +41,44 @@ pub fn get_pool_quote_with_amounts(
             curve_type: CurveType::Stable,
             calculator: Arc::new(StableCurve { amp }),
         };
-        quote = swap_curve.swap(
+        quote = match swap_curve.swap(
             amount_in, 
             input_token_pool_amount, 
             output_token_pool_amount, 
             trade_direction, 
             fees
-        ).unwrap().destination_amount_swapped;
+        ) {
+            Some(v) => v.destination_amount_swapped,
+            None => no_output(amount_in, input_token_pool_amount, output_token_pool_amount)?,
+        };
 
     } else {
-        panic!("invalid curve type for swap: {:?}", curve_type);
+        return Err(QuoteError::Unsupported(format!("invalid curve type for swap: {:?}", curve_type)));
     }
 
     // add slippage amount if its given 
     if let Some([num, denom]) = slippage_percent {
-        quote = quote * (denom - num) / denom
+        quote = quote
+            .checked_mul(denom.saturating_sub(num))
+            .and_then(|q| q.checked_div(denom))
+            .ok_or(QuoteError::Overflow)?;
     }
 
 
     Ok(quote)
 }
+
+/// Why a swap came out as `None`: an empty pool, numbers too large for the
+/// curve math, or an input too small to get anything out (`Ok(0)`)
+fn no_output(amount_in: u128, input_token_pool_amount: u128, output_token_pool_amount: u128) -> QuoteResult<u128> {
+    if input_token_pool_amount == 0 || output_token_pool_amount == 0 {
+        return Err(QuoteError::ZeroReserves);
+    }
+    let overflows = input_token_pool_amount.checked_mul(output_token_pool_amount).is_none()
+        || input_token_pool_amount.checked_add(amount_in).is_none();
+    if overflows {
+        Err(QuoteError::Overflow)
+    } else {
+        Ok(0)
+    }
+}
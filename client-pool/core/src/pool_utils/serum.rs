@@ -0,0 +1,735 @@
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use std::convert::TryInto;
+use std::ops::DerefMut;
+use solana_program::account_info::AccountInfo;
+use solana_program::pubkey::Pubkey;
+use anchor_spl::dex::serum_dex::{critbit::Slab, matching::OrderBookState, state::Market};
+
+use crate::error::{QuoteError, QuoteResult};
+
+// FEE COMPUTATION 
+mod stable_markets {
+    pub mod usdt_usdc {
+        solana_program::declare_id!("77quYg4MGneUdjgXCunt9GgM1usmrxKY31twEy3WHwcS");
+    }
+    pub mod msol_sol {
+        solana_program::declare_id!("5cLrMai1DsLRYc1Nio9qMTicsWtvzjzZfJPXyAoF4t1Z");
+    }
+    pub mod ust_usdc {
+        solana_program::declare_id!("EERNEEnBqdGzBS8dd46wwNY5F2kwnaCQ3vsq2fNKGogZ");
+    }
+    pub mod ust_usdt {
+        solana_program::declare_id!("8sFf9TW3KzxLiBXcDcjAxqabEsRroo4EiRr3UG1xbJ9m");
+    }
+    pub mod stsol_sol {
+        solana_program::declare_id!("2iDSTGhjJEiRxNaLF27CY6daMYPs5hgYrP2REHd5YD62");
+    }
+}
+
+#[derive(Copy, Clone, IntoPrimitive, TryFromPrimitive, Debug)]
+#[repr(u8)]
+pub enum FeeTier {
+    Base,
+    SRM2,
+    SRM3,
+    SRM4,
+    SRM5,
+    SRM6,
+    MSRM,
+    Stable,
+}
+
+#[repr(transparent)]
+#[derive(Copy, Clone)]
+struct U64F64(u128);
+
+impl U64F64 {
+    const ONE: Self = U64F64(1 << 64);
+
+    #[inline(always)]
+    const fn add(self, other: U64F64) -> U64F64 {
+        U64F64(self.0 + other.0)
+    }
+
+    #[inline(always)]
+    const fn div(self, other: U64F64) -> u128 {
+        self.0 / other.0
+    }
+
+    #[inline(always)]
+    const fn mul_u64(self, other: u64) -> U64F64 {
+        U64F64(self.0 * other as u128)
+    }
+
+    #[inline(always)]
+    const fn floor(self) -> u64 {
+        (self.0 >> 64) as u64
+    }
+
+    #[inline(always)]
+    const fn frac_part(self) -> u64 {
+        self.0 as u64
+    }
+
+    #[inline(always)]
+    const fn from_int(n: u64) -> Self {
+        U64F64((n as u128) << 64)
+    }
+}
+
+#[inline(always)]
+const fn fee_tenth_of_bps(tenth_of_bps: u64) -> U64F64 {
+    U64F64(((tenth_of_bps as u128) << 64) / 100_000)
+}
+
+#[inline(always)]
+const fn rebate_tenth_of_bps(tenth_of_bps: u64) -> U64F64 {
+    U64F64(fee_tenth_of_bps(tenth_of_bps).0 + 1)
+}
+
+impl FeeTier {
+    #[inline]
+    pub fn from_srm_and_msrm_balances(market: &Pubkey, srm_held: u64, msrm_held: u64) -> FeeTier {
+        let one_srm = 1_000_000;
+
+        if market == &stable_markets::usdt_usdc::ID || market == &stable_markets::msol_sol::ID || market == &stable_markets::ust_usdc::ID || market == &stable_markets::ust_usdt::ID || market == &stable_markets::stsol_sol::ID {
+            return FeeTier::Stable;
+        }
+
+        match () {
+            () if msrm_held >= 1 => FeeTier::MSRM,
+            () if srm_held >= one_srm * 1_000_000 => FeeTier::SRM6,
+            () if srm_held >= one_srm * 100_000 => FeeTier::SRM5,
+            () if srm_held >= one_srm * 10_000 => FeeTier::SRM4,
+            () if srm_held >= one_srm * 1_000 => FeeTier::SRM3,
+            () if srm_held >= one_srm * 100 => FeeTier::SRM2,
+            () => FeeTier::Base,
+        }
+    }
+
+    #[inline]
+    pub fn maker_rebate(self, pc_qty: u64) -> u64 {
+        rebate_tenth_of_bps(0).mul_u64(pc_qty).floor()
+    }
+
+    /// Taker fee in tenths of a bps
+    pub fn taker_tenth_of_bps(self) -> u64 {
+        use FeeTier::*;
+        match self {
+            Base => 40,
+            SRM2 => 39,
+            SRM3 => 38,
+            SRM4 => 36,
+            SRM5 => 34,
+            SRM6 => 32,
+            MSRM => 30,
+            Stable => 10,
+        }
+    }
+
+    fn taker_rate(self) -> U64F64 {
+        fee_tenth_of_bps(self.taker_tenth_of_bps())
+    }
+
+    #[inline]
+    pub fn taker_fee(self, pc_qty: u64) -> u64 {
+        let rate = self.taker_rate();
+        let exact_fee: U64F64 = rate.mul_u64(pc_qty);
+        exact_fee.floor() + ((exact_fee.frac_part() != 0) as u64)
+    }
+
+    #[inline]
+    pub fn remove_taker_fee(self, pc_qty_incl_fee: u64) -> u64 {
+        let rate = self.taker_rate();
+        U64F64::from_int(pc_qty_incl_fee)
+            .div(U64F64::ONE.add(rate))
+            .try_into()
+            .unwrap()
+    }
+}
+
+#[inline]
+pub fn referrer_rebate(amount: u64) -> u64 {
+    amount / 5
+}
+
+impl Default for FeeTier {
+    fn default() -> Self {
+        FeeTier::Base
+    }
+}
+
+// ORDER BOOK WALK
+
+/// A resting order: `price` in quote lots per base lot, `quantity` in base lots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Level {
+    pub price: u64,
+    pub quantity: u64,
+}
+
+/// The orders of a Serum market, copied out of its market, bids and asks
+/// accounts once per account update. Matching against the slabs in place
+/// removes the orders it fills, so a walk over them needs its own copy of
+/// the three accounts; quotes walk these levels instead and copy nothing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BookSnapshot {
+    pub pc_lot_size: u64,
+    pub coin_lot_size: u64,
+    /// From the highest price down
+    pub bids: Vec<Level>,
+    /// From the lowest price up
+    pub asks: Vec<Level>,
+}
+
+/// The levels a market order takes from, in the order it takes them, and
+/// how much of the best one it has taken so far
+pub struct BookCursor<'a> {
+    levels: &'a [Level],
+    taken: u64,
+}
+
+impl<'a> BookCursor<'a> {
+    pub fn new(levels: &'a [Level]) -> Self {
+        Self { levels, taken: 0 }
+    }
+
+    /// The best level with what's left of it
+    fn best(&self) -> Option<Level> {
+        self.levels.first().map(|level| Level {
+            price: level.price,
+            quantity: level.quantity - self.taken,
+        })
+    }
+
+    /// Takes `qty` base lots from the best level, moving on once it's filled
+    fn take(&mut self, qty: u64) {
+        self.taken += qty;
+        if self.levels.first().map_or(false, |level| self.taken >= level.quantity) {
+            self.levels = &self.levels[1..];
+            self.taken = 0;
+        }
+    }
+}
+
+/// Structure to track order matching iteration state
+pub struct Iteration {
+    /// Remaining input amount
+    pub amount_in: u64,
+    /// Accumulated output amount
+    pub amount_out: u64,
+}
+
+/// Base lots a bid with `pc_qty_remaining` quote lots left takes from an
+/// ask of `size` base lots at `price` (quote lots per base lot), and the
+/// quote lots that costs
+fn bid_fill(pc_qty_remaining: u64, price: u64, size: u64) -> QuoteResult<(u64, u64)> {
+    let affordable = pc_qty_remaining.checked_div(price).ok_or(QuoteError::BadPoolData)?;
+    let trade_qty = size.min(affordable);
+    let cost = trade_qty.checked_mul(price).ok_or(QuoteError::Overflow)?;
+    Ok((trade_qty, cost))
+}
+
+/// Base lots an ask with `unfilled_qty` base lots left sells into a bid of
+/// `size` base lots at `price`, and the quote lots that brings in
+fn ask_fill(unfilled_qty: u64, price: u64, size: u64) -> QuoteResult<(u64, u64)> {
+    let trade_qty = size.min(unfilled_qty);
+    let proceeds = trade_qty.checked_mul(price).ok_or(QuoteError::Overflow)?;
+    Ok((trade_qty, proceeds))
+}
+
+/// Native quote left of a bid's `start_amount_in` once `filled_pc_lots`
+/// quote lots were spent plus the taker fee on them. The fee rounds up, so
+/// the last unit may be short; nothing is left then rather than less than
+/// nothing.
+fn bid_remaining(
+    start_amount_in: u64,
+    filled_pc_lots: u64,
+    quote_lot_size: u64,
+    fee_tier: &FeeTier,
+) -> QuoteResult<u64> {
+    let native_fill = filled_pc_lots.checked_mul(quote_lot_size).ok_or(QuoteError::Overflow)?;
+    let native_taker_fee = fee_tier.taker_fee(native_fill);
+    Ok(start_amount_in
+        .saturating_sub(native_fill)
+        .saturating_sub(native_taker_fee))
+}
+
+/// Native quote an ask receives for `accum_fill_price` quote lots, net of
+/// the taker fee
+fn ask_proceeds(accum_fill_price: u64, pc_lot_size: u64, fee_tier: &FeeTier) -> QuoteResult<u64> {
+    let native_pc_qty = accum_fill_price.checked_mul(pc_lot_size).ok_or(QuoteError::Overflow)?;
+    let native_taker_fee = fee_tier.taker_fee(native_pc_qty);
+    native_pc_qty.checked_sub(native_taker_fee).ok_or(QuoteError::Overflow)
+}
+
+/// Process a bid order (quote -> base) against the asks
+/// 
+/// # Arguments
+/// * `iteration` - Current iteration state
+/// * `fee_tier` - Fee tier for the trader
+/// * `book` - Lot sizes of the market
+/// * `asks` - The asks left to take from
+/// 
+/// # Returns
+/// * Whether order matching is complete; an error if the lot math
+///   overflows or the market has zero lot sizes or prices
+pub fn bid_iteration(
+    iteration: &mut Iteration,
+    fee_tier: &FeeTier, 
+    book: &BookSnapshot,
+    asks: &mut BookCursor,
+) -> QuoteResult<bool> {
+    let quote_lot_size = book.pc_lot_size;
+    let base_lot_size = book.coin_lot_size;
+
+    let start_amount_in = iteration.amount_in;
+    let max_pc_qty = fee_tier
+        .remove_taker_fee(iteration.amount_in)
+        .checked_div(quote_lot_size)
+        .ok_or(QuoteError::BadPoolData)?;
+    let mut pc_qty_remaining = max_pc_qty; 
+
+    // Match against asks until complete or no more matching orders
+    let done = loop {
+        let best_offer = match asks.best() { // lowest price = best ask 
+            Some(best_offer) => best_offer,
+            None => break true,
+        };
+     
+        let (trade_qty, cost) = bid_fill(
+            pc_qty_remaining,
+            best_offer.price,
+            best_offer.quantity,
+        )?;
+        if trade_qty == 0 { // No more matching possible
+            break true;
+        }
+
+        // Update amounts
+        pc_qty_remaining -= cost; // cost <= pc_qty_remaining by bid_fill
+        iteration.amount_out = trade_qty
+            .checked_mul(base_lot_size)
+            .and_then(|base| iteration.amount_out.checked_add(base))
+            .ok_or(QuoteError::Overflow)?;
+
+        // Move through the book
+        asks.take(trade_qty);
+        break false; 
+    };
+
+    // Calculate final amounts including fees
+    iteration.amount_in = bid_remaining(
+        start_amount_in,
+        max_pc_qty - pc_qty_remaining,
+        quote_lot_size,
+        fee_tier,
+    )?;
+
+    Ok(done)
+}
+
+/// Process an ask order (base -> quote) against the bids
+/// 
+/// # Arguments
+/// * `iteration` - Current iteration state
+/// * `fee_tier` - Fee tier for the trader
+/// * `book` - Lot sizes of the market
+/// * `bids` - The bids left to take from
+/// 
+/// # Returns
+/// * Whether order matching is complete; an error if the lot math
+///   overflows or the market has zero lot sizes
+pub fn ask_iteration(
+    iteration: &mut Iteration,
+    fee_tier: &FeeTier, 
+    book: &BookSnapshot,
+    bids: &mut BookCursor,
+) -> QuoteResult<bool> {
+    let pc_lot_size = book.pc_lot_size;
+    let coin_lot_size = book.coin_lot_size;
+
+    let max_qty = iteration.amount_in; 
+    let mut unfilled_qty = max_qty.checked_div(coin_lot_size).ok_or(QuoteError::BadPoolData)?;
+    let mut accum_fill_price: u64 = 0;
+
+    // Match against bids until complete or no more matching orders
+    let done = loop {
+        let best_bid = match bids.best() { 
+            Some(best_bid) => {
+                best_bid
+            }, 
+            None => {
+                break true; // No more bids
+            }
+        };
+     
+        let (trade_qty, proceeds) = ask_fill(
+            unfilled_qty,
+            best_bid.price,
+            best_bid.quantity,
+        )?;
+        if trade_qty == 0 { // No more matching possible
+            break true;
+        }
+
+        // Update amounts
+        unfilled_qty -= trade_qty;
+        accum_fill_price = accum_fill_price
+            .checked_add(proceeds)
+            .ok_or(QuoteError::Overflow)?;
+
+        // Move through the book
+        bids.take(trade_qty);
+        break false; 
+    };
+
+    // Calculate final amounts including fees
+    let net_taker_pc_qty = ask_proceeds(accum_fill_price, pc_lot_size, fee_tier)?;
+    iteration.amount_out = iteration
+        .amount_out
+        .checked_add(net_taker_pc_qty)
+        .ok_or(QuoteError::Overflow)?;
+    // unfilled_qty only shrinks from max_qty / coin_lot_size, this can't overflow
+    iteration.amount_in = unfilled_qty * coin_lot_size; 
+
+    Ok(done)
+}
+
+/// Loads the market, bids and asks from copies of their account data (the
+/// matching functions mutate the book) and hands the order book to `f`.
+/// `None` if the accounts don't load as a market of `program_id`.
+fn with_order_book<T>(
+    market: (&Pubkey, &[u8]),
+    bids: (&Pubkey, &[u8]),
+    asks: (&Pubkey, &[u8]),
+    program_id: &Pubkey,
+    f: impl FnOnce(&mut OrderBookState) -> T,
+) -> Option<T> {
+    let (mut market_data, mut bids_data, mut asks_data) =
+        (market.1.to_vec(), bids.1.to_vec(), asks.1.to_vec());
+    let (mut market_lamports, mut bids_lamports, mut asks_lamports) = (0, 0, 0);
+    let market_info = AccountInfo::new(
+        market.0, false, true, &mut market_lamports, &mut market_data, program_id, false, 0,
+    );
+    let bids_info = AccountInfo::new(
+        bids.0, false, true, &mut bids_lamports, &mut bids_data, program_id, false, 0,
+    );
+    let asks_info = AccountInfo::new(
+        asks.0, false, true, &mut asks_lamports, &mut asks_data, program_id, false, 0,
+    );
+
+    let mut market = Market::load(&market_info, program_id).ok()?;
+    let mut bids = market.load_bids_mut(&bids_info).ok()?;
+    let mut asks = market.load_asks_mut(&asks_info).ok()?;
+    let mut orderbook = OrderBookState {
+        bids: bids.deref_mut(),
+        asks: asks.deref_mut(),
+        market_state: market.deref_mut(),
+    };
+    Some(f(&mut orderbook))
+}
+
+/// Removes the orders of one side of the book best first: bids from the
+/// highest price down, asks from the lowest up
+fn drain_levels(slab: &mut Slab, is_bids: bool) -> Vec<Level> {
+    let mut levels = vec![];
+    loop {
+        let best = match if is_bids { slab.find_max() } else { slab.find_min() } {
+            Some(best) => best,
+            None => break,
+        };
+        // a corrupted slab ends the book where it stops making sense
+        let order = match slab.get_mut(best).and_then(|node| node.as_leaf_mut()) {
+            Some(order) => order,
+            None => break,
+        };
+        levels.push(Level {
+            price: order.price().get(),
+            quantity: order.quantity(),
+        });
+        let order_id = order.order_id();
+        if slab.remove_by_key(order_id).is_none() {
+            break;
+        }
+    }
+    levels
+}
+
+impl BookSnapshot {
+    /// Copies the orders out of the market's accounts, `None` if they don't
+    /// load as a market of `program_id`
+    pub fn load(
+        market: (&Pubkey, &[u8]),
+        bids: (&Pubkey, &[u8]),
+        asks: (&Pubkey, &[u8]),
+        program_id: &Pubkey,
+    ) -> Option<Self> {
+        with_order_book(market, bids, asks, program_id, |ob| BookSnapshot {
+            pc_lot_size: ob.market_state.pc_lot_size,
+            coin_lot_size: ob.market_state.coin_lot_size,
+            bids: drain_levels(&mut *ob.bids, true),
+            asks: drain_levels(&mut *ob.asks, false),
+        })
+        // quotes divide by the lot sizes
+        .filter(|book| book.pc_lot_size > 0 && book.coin_lot_size > 0)
+    }
+
+    /// Output of a market order of `amount_in`. A bid spends quote for base,
+    /// an ask sells base for quote. An error if the side the order takes
+    /// from is empty.
+    pub fn walk(&self, amount_in: u64, is_bid: bool, fee_tier: FeeTier) -> QuoteResult<u64> {
+        let mut levels = BookCursor::new(if is_bid { &self.asks } else { &self.bids });
+        if levels.best().is_none() {
+            return Err(QuoteError::EmptyBook);
+        }
+        let mut iteration = Iteration {
+            amount_in,
+            amount_out: 0,
+        };
+        loop {
+            let done = if is_bid {
+                bid_iteration(&mut iteration, &fee_tier, self, &mut levels)?
+            } else {
+                ask_iteration(&mut iteration, &fee_tier, self, &mut levels)?
+            };
+            if done {
+                break;
+            }
+        }
+        Ok(iteration.amount_out)
+    }
+
+    /// Outputs of market orders of each of `amounts`, as `walk` gives them,
+    /// from one pass over the levels. Orders are taken smallest first; the
+    /// levels an order fills whole, every larger one fills whole too, so
+    /// what they take and pay is only worked out once. Per order, only the
+    /// levels from the first one it doesn't fill whole are walked.
+    pub fn walk_many(&self, amounts: &[u64], is_bid: bool, fee_tier: FeeTier) -> Vec<QuoteResult<u64>> {
+        let levels = if is_bid { &self.asks } else { &self.bids };
+        if levels.is_empty() {
+            return vec![Err(QuoteError::EmptyBook); amounts.len()];
+        }
+        let mut order: Vec<usize> = (0..amounts.len()).collect();
+        order.sort_by_key(|&i| amounts[i]);
+
+        let mut results = vec![Ok(0); amounts.len()];
+        // levels filled whole so far, the input they take (native quote for
+        // bids, base lots for asks) and the output they pay
+        let (mut filled, mut taken, mut paid) = (0, 0_u64, 0_u64);
+        for i in order {
+            let amount_in = amounts[i];
+            // what's left of `amount_in` once the first `filled` levels are
+            // filled; an ask drops what's short of a lot on its first fill
+            let left = |filled: usize, taken: u64| {
+                if filled == 0 {
+                    amount_in
+                } else if is_bid {
+                    amount_in.saturating_sub(taken)
+                } else {
+                    (amount_in / self.coin_lot_size).saturating_sub(taken) * self.coin_lot_size
+                }
+            };
+            let mut shared = true;
+            while shared && filled < levels.len() {
+                let mut probe = Iteration {
+                    amount_in: left(filled, taken),
+                    amount_out: 0,
+                };
+                let mut cursor = BookCursor::new(&levels[filled..filled + 1]);
+                let done = if is_bid {
+                    bid_iteration(&mut probe, &fee_tier, self, &mut cursor)
+                } else {
+                    ask_iteration(&mut probe, &fee_tier, self, &mut cursor)
+                };
+                match done {
+                    Ok(false) if cursor.best().is_none() => {
+                        let level_taken = if is_bid {
+                            left(filled, taken) - probe.amount_in
+                        } else {
+                            levels[filled].quantity
+                        };
+                        match (taken.checked_add(level_taken), paid.checked_add(probe.amount_out)) {
+                            (Some(new_taken), Some(new_paid)) => {
+                                taken = new_taken;
+                                paid = new_paid;
+                                filled += 1;
+                            }
+                            _ => shared = false,
+                        }
+                    }
+                    Ok(_) => break,
+                    Err(_) => shared = false,
+                }
+            }
+            if !shared {
+                // whatever went wrong, the plain walk reports it
+                results[i] = self.walk(amount_in, is_bid, fee_tier);
+                continue;
+            }
+
+            let mut iteration = Iteration {
+                amount_in: left(filled, taken),
+                amount_out: paid,
+            };
+            let mut cursor = BookCursor::new(&levels[filled..]);
+            results[i] = loop {
+                let done = if is_bid {
+                    bid_iteration(&mut iteration, &fee_tier, self, &mut cursor)
+                } else {
+                    ask_iteration(&mut iteration, &fee_tier, self, &mut cursor)
+                };
+                match done {
+                    Ok(true) => break Ok(iteration.amount_out),
+                    Ok(false) => {}
+                    Err(e) => break Err(e),
+                }
+            };
+        }
+        results
+    }
+
+    /// Largest market order input whose fills all stay within
+    /// `max_impact_bps` of the best price. Bids spend quote (taker fee
+    /// included), asks sell base. An empty side fills nothing.
+    pub fn capacity(&self, is_bid: bool, max_impact_bps: u64, fee_tier: FeeTier) -> u64 {
+        // a bid takes the asks from the lowest price up, an ask the bids from the highest down
+        let levels = if is_bid { &self.asks } else { &self.bids };
+        let limit = match levels.first() {
+            Some(best) if is_bid => best.price.saturating_mul(10_000 + max_impact_bps) / 10_000,
+            Some(best) => best.price.saturating_mul(10_000_u64.saturating_sub(max_impact_bps)) / 10_000,
+            None => return 0,
+        };
+        // quote lots for bids, base lots for asks
+        let filled_lots = levels
+            .iter()
+            .take_while(|level| if is_bid { level.price <= limit } else { level.price >= limit })
+            .fold(0_u64, |filled, level| {
+                let lots = if is_bid { level.quantity.saturating_mul(level.price) } else { level.quantity };
+                lots.saturating_add(filled)
+            });
+
+        // a capacity is an upper bound, so saturating is as good as exact
+        if is_bid {
+            let native_pc_qty = filled_lots.saturating_mul(self.pc_lot_size);
+            native_pc_qty.saturating_add(fee_tier.taker_fee(native_pc_qty))
+        } else {
+            filled_lots.saturating_mul(self.coin_lot_size)
+        }
+    }
+}
+
+/// Output of a market order of `amount_in` against a Serum order book read
+/// straight from the market, bids and asks account data, for accounts that
+/// aren't kept around (pools keep a `BookSnapshot`). An error if the
+/// accounts don't load as a market of `program_id` or the side the order
+/// takes from is empty.
+pub fn walk_order_book(
+    market: (&Pubkey, &[u8]),
+    bids: (&Pubkey, &[u8]),
+    asks: (&Pubkey, &[u8]),
+    program_id: &Pubkey,
+    amount_in: u64,
+    is_bid: bool,
+    fee_tier: FeeTier,
+) -> QuoteResult<u64> {
+    BookSnapshot::load(market, bids, asks, program_id)
+        .ok_or(QuoteError::BadPoolData)?
+        .walk(amount_in, is_bid, fee_tier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // lot sizes like the SOL/USDC market's: 0.1 SOL base lots (9 decimals),
+    // 0.0001 USDC quote lots (6 decimals)
+    const COIN_LOT_SIZE: u64 = 100_000_000;
+    const PC_LOT_SIZE: u64 = 100;
+
+    #[test]
+    fn fills_at_known_prices() {
+        // at 10 USDC a SOL a 0.1 SOL lot costs 1 USDC, 10_000 quote lots
+        let price = 10_000;
+        // 50 USDC of quote lots buys 5 SOL = 50 base lots, the ask has 30
+        let (qty, cost) = bid_fill(50 * 10_000, price, 30).unwrap();
+        assert_eq!((qty, cost), (30, 30 * price));
+        let (qty, proceeds) = ask_fill(20, price, 30).unwrap();
+        assert_eq!((qty, proceeds), (20, 20 * price));
+
+        // 300 quote lots are 0.03 USDC, the base taker fee of 0.04% is 12 native
+        assert_eq!(ask_proceeds(300, PC_LOT_SIZE, &FeeTier::Base).unwrap(), 30_000 - 12);
+        assert_eq!(bid_remaining(40_000, 300, PC_LOT_SIZE, &FeeTier::Base).unwrap(), 40_000 - 30_000 - 12);
+        // the rounded-up fee can't take the remainder below zero
+        assert_eq!(bid_remaining(30_000, 300, PC_LOT_SIZE, &FeeTier::Base).unwrap(), 0);
+    }
+
+    #[test]
+    fn unusual_lot_sizes_are_errors_not_nonsense() {
+        // a zero price would divide by zero
+        assert_eq!(bid_fill(1_000, 0, 10), Err(QuoteError::BadPoolData));
+        // lots this large don't fit in native amounts
+        assert_eq!(ask_fill(u64::MAX, u64::MAX / 2, 3), Err(QuoteError::Overflow));
+        assert_eq!(ask_proceeds(u64::MAX / 2, COIN_LOT_SIZE, &FeeTier::Base), Err(QuoteError::Overflow));
+        assert_eq!(
+            bid_remaining(u64::MAX, u64::MAX / 2, PC_LOT_SIZE, &FeeTier::Base),
+            Err(QuoteError::Overflow)
+        );
+        // and a tiny bid that can't afford a single lot fills nothing
+        assert_eq!(bid_fill(99, 100, 10).unwrap(), (0, 0));
+    }
+
+    fn book() -> BookSnapshot {
+        let level = |price, quantity| Level { price, quantity };
+        BookSnapshot {
+            pc_lot_size: PC_LOT_SIZE,
+            coin_lot_size: COIN_LOT_SIZE,
+            bids: vec![level(10_000, 30), level(9_000, 50)],
+            asks: vec![],
+        }
+    }
+
+    #[test]
+    fn snapshots_walk_through_levels_without_changing() {
+        let book = book();
+        // 40 lots sell 30 at 10 USDC and 10 at 9 USDC, less the 0.04% fee
+        let amount_out = book.walk(40 * COIN_LOT_SIZE, false, FeeTier::Base).unwrap();
+        assert_eq!(amount_out, (30_000_000 - 12_000) + (9_000_000 - 3_600));
+        // the same quote again gets the same fills
+        assert_eq!(book.walk(40 * COIN_LOT_SIZE, false, FeeTier::Base).unwrap(), amount_out);
+        assert_eq!(book.walk(1_000, true, FeeTier::Base), Err(QuoteError::EmptyBook));
+
+        // 9.5 USDC is 5% below the best bid, 9 USDC 10%
+        assert_eq!(book.capacity(false, 500, FeeTier::Base), 30 * COIN_LOT_SIZE);
+        assert_eq!(book.capacity(false, 1_000, FeeTier::Base), 80 * COIN_LOT_SIZE);
+        assert_eq!(book.capacity(true, 1_000, FeeTier::Base), 0);
+    }
+
+    #[test]
+    fn one_pass_over_many_amounts_matches_walking_each() {
+        let level = |price, quantity| Level { price, quantity };
+        let mut book = book();
+        book.asks = vec![level(10_000, 30), level(10_100, 5), level(12_000, 50)];
+        // unsorted, repeated, between lots and past the whole book
+        let amounts = [
+            40 * COIN_LOT_SIZE,
+            0,
+            COIN_LOT_SIZE - 1,
+            30 * COIN_LOT_SIZE + 7,
+            40 * COIN_LOT_SIZE,
+            90 * COIN_LOT_SIZE,
+            30_012_000,
+            1_000_000_000_000,
+        ];
+        for is_bid in [true, false] {
+            let many = book.walk_many(&amounts, is_bid, FeeTier::Base);
+            for (amount, quote) in amounts.iter().zip(many) {
+                assert_eq!(quote, book.walk(*amount, is_bid, FeeTier::Base), "{} {}", amount, is_bid);
+            }
+        }
+        assert_eq!(
+            BookSnapshot::default().walk_many(&[1, 2], true, FeeTier::Base),
+            vec![Err(QuoteError::EmptyBook); 2]
+        );
+    }
+}
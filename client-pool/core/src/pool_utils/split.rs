@@ -0,0 +1,133 @@
+//! Dividing a swap between two pools of a pair
+//!
+//! Past a point, a second pool of the same pair pays more for the next unit
+//! than the first one does after the price impact of what it already took.
+//! The best division puts `x` into one pool and the rest into the other
+//! where their marginal rates meet; every curve here has a decreasing
+//! marginal rate, so the summed output is concave in `x` and a ternary
+//! search finds it.
+
+/// How a swap is divided between pools `a` and `b`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Split {
+    pub amount_in_a: u128,
+    pub amount_in_b: u128,
+    pub amount_out_a: u128,
+    pub amount_out_b: u128,
+}
+
+impl Split {
+    pub fn amount_out(&self) -> u128 {
+        self.amount_out_a + self.amount_out_b
+    }
+
+    /// Whether both pools take a part
+    pub fn is_split(&self) -> bool {
+        self.amount_in_a > 0 && self.amount_in_b > 0
+    }
+}
+
+/// The division of `amount` between two pools with the most output; a
+/// failed quote counts as nothing out. Ties go to the division with more
+/// in `a`, so a second pool only takes a part when it pays for it.
+///
+/// # Arguments
+/// * `amount` - Input of the swap
+/// * `quote_a`, `quote_b` - Forward quotes of the two pools
+pub fn best_split<FA, FB>(amount: u128, quote_a: FA, quote_b: FB) -> Split
+where
+    FA: Fn(u128) -> u128,
+    FB: Fn(u128) -> u128,
+{
+    let split = |amount_in_a: u128| {
+        let amount_in_b = amount - amount_in_a;
+        Split {
+            amount_in_a,
+            amount_in_b,
+            amount_out_a: if amount_in_a == 0 { 0 } else { quote_a(amount_in_a) },
+            amount_out_b: if amount_in_b == 0 { 0 } else { quote_b(amount_in_b) },
+        }
+    };
+    let total = |amount_in_a: u128| split(amount_in_a).amount_out();
+
+    // invariant: the best `amount_in_a` is in [lo, hi]
+    let (mut lo, mut hi) = (0, amount);
+    while hi - lo > 2 {
+        let third = (hi - lo) / 3;
+        let (m1, m2) = (lo + third, hi - third);
+        if total(m1) < total(m2) {
+            lo = m1 + 1;
+        } else {
+            hi = m2;
+        }
+    }
+    // rounding makes the curve only roughly concave, the ends are checked too
+    [amount]
+        .into_iter()
+        .chain((lo..=hi).rev())
+        .chain([0])
+        .map(split)
+        .fold(None, |best: Option<Split>, candidate| match best {
+            Some(best) if best.amount_out() >= candidate.amount_out() => Some(best),
+            _ => Some(candidate),
+        })
+        .expect("at least one division")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pool_utils::base::CurveType;
+    use crate::pool_utils::fees::Fees;
+    use crate::pool_utils::orca::get_pool_quote_with_amounts;
+
+    fn quote(reserve_in: u128, reserve_out: u128) -> impl Fn(u128) -> u128 {
+        let fees = Fees {
+            trade_fee_numerator: 25,
+            trade_fee_denominator: 10_000,
+            owner_trade_fee_numerator: 5,
+            owner_trade_fee_denominator: 10_000,
+            ..Fees::default()
+        };
+        move |amount_in| {
+            get_pool_quote_with_amounts(
+                amount_in,
+                CurveType::ConstantProduct,
+                0,
+                &fees,
+                reserve_in,
+                reserve_out,
+                None,
+            )
+            .unwrap_or(0)
+        }
+    }
+
+    #[test]
+    fn equal_pools_share_a_large_swap() {
+        let deep = || quote(1_000_000_000, 1_000_000_000);
+        let split = best_split(200_000_000, deep(), deep());
+        assert!(split.is_split());
+        // the summed output is flat around the middle, to the unit
+        assert!(split.amount_in_a.abs_diff(split.amount_in_b) <= 200_000);
+        assert!(split.amount_out() > deep()(200_000_000));
+        // nothing does better
+        for amount_in_a in (0..=200_000_000).step_by(10_000_000) {
+            assert!(deep()(amount_in_a) + deep()(200_000_000 - amount_in_a) <= split.amount_out());
+        }
+    }
+
+    #[test]
+    fn a_shallow_pool_takes_less_and_a_useless_one_nothing() {
+        // rates meet where both pools took the same share of their reserves
+        let (deep, shallow) = (quote(1_000_000_000, 1_000_000_000), quote(100_000_000, 100_000_000));
+        let split = best_split(100_000_000, deep, shallow);
+        assert!(split.is_split());
+        assert!(split.amount_in_b.abs_diff(100_000_000 / 11) < 500_000);
+
+        // a pool paying half the rate never gets a part of a small swap
+        let split = best_split(1_000, quote(1_000_000_000, 1_000_000_000), quote(1_000_000_000, 500_000_000));
+        assert_eq!((split.amount_in_a, split.amount_in_b), (1_000, 0));
+        assert!(!split.is_split());
+    }
+}
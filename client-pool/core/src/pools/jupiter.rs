@@ -0,0 +1,252 @@
+/**
+ * Jupiter Aggregator Pool Implementation
+ *
+ * This module implements the pool operations interface for Jupiter routes.
+ * Jupiter is a Solana DEX aggregator that provides optimal swap routes across
+ * multiple DEX protocols. It has no pool accounts of its own: a Jupiter pool
+ * is a pair of mints, quoted from a ladder of Jupiter quote API answers that
+ * the client keeps fresh in `JupiterLadders`, and its swaps are fetched from
+ * the swap-instructions API for their exact amount when a route is built.
+ */
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, RwLock};
+use serde::{Deserialize, Serialize};
+use crate::error::{ArbError, ArbResult, QuoteError, QuoteResult};
+use crate::pool::PoolOperations;
+use crate::serialize::token::WrappedPubkey;
+
+use solana_sdk::pubkey::Pubkey;
+
+use anchor_client::{Program, Cluster};
+use solana_sdk::instruction::Instruction;
+
+use solana_sdk::account::Account;
+use crate::pool_utils::exact_out::{search_exact_out, MAX_SWAP_INPUT};
+
+/// Represents Jupiter routes between two mints
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JupiterPool {
+    /// Id of the pool, any address no other pool file uses
+    pub own_address: WrappedPubkey,
+    /// Base token mint address
+    pub base_mint: WrappedPubkey,
+    /// Quote token mint address
+    pub quote_mint: WrappedPubkey,
+    /// Decimals of the base mint
+    pub base_scale: u64,
+    /// Decimals of the quote mint
+    pub quote_scale: u64,
+    /// Quotes from the Jupiter quote API, shared with the client refreshing
+    /// them and with every clone of the pool (set at runtime)
+    #[serde(skip)]
+    pub ladders: Arc<JupiterLadders>,
+}
+
+#[derive(Debug, Default)]
+struct LadderState {
+    /// (input, output) quotes by direction, increasing inputs
+    rungs: HashMap<(Pubkey, Pubkey), Vec<(u128, u128)>>,
+    /// Slot the quotes were fetched at
+    slot: Option<u64>,
+}
+
+/// Jupiter's output for a ladder of inputs, per direction. Amounts between
+/// two rungs are quoted on the line joining them, which stays under Jupiter's
+/// (concave) output curve; amounts past the last rung aren't quoted.
+#[derive(Debug, Default)]
+pub struct JupiterLadders {
+    state: RwLock<LadderState>,
+}
+
+impl JupiterLadders {
+    /// Replaces the quotes of `mint_in` into `mint_out`; rungs that don't
+    /// add to the output of a smaller input are dropped
+    pub fn set(&self, mint_in: &Pubkey, mint_out: &Pubkey, mut rungs: Vec<(u128, u128)>, slot: u64) {
+        rungs.sort_unstable();
+        let mut kept: Vec<(u128, u128)> = Vec::with_capacity(rungs.len());
+        for (amount_in, amount_out) in rungs {
+            let last = kept.last().copied().unwrap_or((0, 0));
+            if amount_in > last.0 && amount_out > last.1 {
+                kept.push((amount_in, amount_out));
+            }
+        }
+        let mut state = self.state.write().unwrap();
+        state.rungs.insert((*mint_in, *mint_out), kept);
+        state.slot = Some(slot);
+    }
+
+    pub fn has_quotes(&self, mint_in: &Pubkey, mint_out: &Pubkey) -> bool {
+        let state = self.state.read().unwrap();
+        state.rungs.get(&(*mint_in, *mint_out)).map_or(false, |rungs| !rungs.is_empty())
+    }
+
+    pub fn slot(&self) -> Option<u64> {
+        self.state.read().unwrap().slot
+    }
+
+    fn set_slot(&self, slot: u64) {
+        self.state.write().unwrap().slot = Some(slot);
+    }
+
+    /// Output of `amount_in`, interpolated between the rungs around it
+    pub fn quote(&self, amount_in: u128, mint_in: &Pubkey, mint_out: &Pubkey) -> QuoteResult<u128> {
+        let state = self.state.read().unwrap();
+        let rungs = state
+            .rungs
+            .get(&(*mint_in, *mint_out))
+            .ok_or(QuoteError::BadPoolData)?;
+        let past_last = || QuoteError::Unsupported(format!("{} is past the last jupiter quote", amount_in));
+        let upper = rungs
+            .iter()
+            .position(|(rung_in, _)| *rung_in >= amount_in)
+            .ok_or_else(past_last)?;
+        let (in_hi, out_hi) = rungs[upper];
+        let (in_lo, out_lo) = if upper == 0 { (0, 0) } else { rungs[upper - 1] };
+        let over = (out_hi - out_lo)
+            .checked_mul(amount_in - in_lo)
+            .ok_or(QuoteError::Overflow)?
+            / (in_hi - in_lo);
+        Ok(out_lo + over)
+    }
+}
+
+/// Implementation of pool operations for Jupiter routes
+impl PoolOperations for JupiterPool {
+    /// Returns the name of the DEX
+    fn get_name(&self) -> String {
+        "Jupiter".to_string()
+    }
+
+    /// Returns the address of the pool account
+    fn get_address(&self) -> Pubkey {
+        self.own_address.0
+    }
+
+    fn clone_box(&self) -> Box<dyn PoolOperations> {
+        Box::new(self.clone())
+    }
+
+    /// Jupiter is quoted over http, there's no account to read
+    fn get_update_accounts(&self) -> Vec<Pubkey> {
+        vec![]
+    }
+
+    /// Only checks that the client fetched quotes, the pool stays out of the
+    /// graph until it did
+    fn set_update_accounts(
+        &mut self,
+        _accounts: Vec<Option<Account>>,
+        _cluster: Cluster,
+    ) -> ArbResult<()> {
+        if self.ladders.slot().is_none() {
+            return Err(ArbError::InvalidPool {
+                pool: self.own_address.0,
+                reason: "no jupiter quotes fetched yet".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Jupiter holds no tokens of its own
+    fn mint_2_addr(&self, _mint: &Pubkey) -> Pubkey {
+        panic!("jupiter pools have no token accounts");
+    }
+
+    /// Returns a sorted vector of the pool's token mint addresses
+    fn get_mints(&self) -> Vec<Pubkey> {
+        let mut mints = vec![
+            self.base_mint.0,
+            self.quote_mint.0,
+        ];
+        mints.sort();
+        mints
+    }
+
+    /// Returns the decimal scale for a given mint
+    fn mint_2_scale(&self, mint: &Pubkey) -> u64 {
+        if *mint == self.base_mint.0 {
+            self.base_scale
+        } else if *mint == self.quote_mint.0 {
+            self.quote_scale
+        } else {
+            panic!("Invalid mint provided");
+        }
+    }
+
+    /// Calculates the expected output amount for a given input amount
+    ///
+    /// # Arguments
+    /// * `amount_in` - Input token amount
+    /// * `mint_in` - Input token mint
+    /// * `mint_out` - Output token mint
+    ///
+    /// # Returns
+    /// * Expected output amount, an error without quotes or past the last one
+    fn get_quote_with_amounts_scaled(
+        &self,
+        amount_in: u128,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> QuoteResult<u128> {
+        self.ladders.quote(amount_in, mint_in, mint_out)
+    }
+
+    /// Calculates the input needed to receive a given output amount
+    ///
+    /// # Arguments
+    /// * `amount_out` - Target output amount
+    /// * `mint_in` - Input token mint
+    /// * `mint_out` - Output token mint
+    ///
+    /// # Returns
+    /// * Required input amount
+    fn get_quote_with_exact_out(
+        &self,
+        amount_out: u128,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> Option<u128> {
+        search_exact_out(amount_out, None, MAX_SWAP_INPUT, |amount_in| {
+            self.get_quote_with_amounts_scaled(amount_in, mint_in, mint_out).unwrap_or(0)
+        })
+    }
+
+    /// Jupiter's instructions carry their input amount, the client fetches
+    /// them from the swap-instructions API once a route's amounts are known
+    fn swap_ix(
+        &self,
+        _program: &Program,
+        _owner: &Pubkey,
+        _mint_in: &Pubkey,
+        _mint_out: &Pubkey,
+    ) -> ArbResult<Vec<Instruction>> {
+        Err(ArbError::Unsupported("jupiter swaps need their amount, see the client's jupiter api"))
+    }
+
+    fn get_update_slot(&self) -> Option<u64> {
+        self.ladders.slot()
+    }
+
+    fn set_update_slot(&mut self, slot: u64) {
+        self.ladders.set_slot(slot);
+    }
+
+    /// Checks if trading is possible between two tokens
+    ///
+    /// # Arguments
+    /// * `mint_in` - Input token mint
+    /// * `mint_out` - Output token mint
+    ///
+    /// # Returns
+    /// * Whether Jupiter quoted the direction
+    fn can_trade(
+        &self,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> bool {
+        self.ladders.has_quotes(mint_in, mint_out)
+    }
+}
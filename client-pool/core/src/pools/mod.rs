@@ -0,0 +1,91 @@
+/**
+ * DEX Pools Module
+ * 
+ * This module contains implementations for different Solana DEX pools that are supported
+ * by the arbitrage bot. Each submodule implements the common pool interface and provides
+ * specific functionality for interacting with different DEX protocols.
+ * 
+ * Currently supported DEXes:
+ * - Orca: Concentrated liquidity AMM
+ * - Raydium: Traditional AMM
+ * - Serum: Central limit order book
+ * - Jupiter: Aggregator and AMM
+ * 
+ * Planned/Disabled DEXes:
+ * - Meteora
+ * - Phoenix
+ * - Lifinity
+ */
+
+// Orca DEX pool implementation
+pub mod orca;
+pub use orca::*;
+
+// Raydium DEX pool implementation
+pub mod raydium;
+pub use raydium::*;
+
+// Serum DEX market implementation
+pub mod serum;
+pub use serum::*;
+
+// Jupiter DEX pool implementation
+pub mod jupiter;
+pub use jupiter::*;
+
+// All of the above behind one statically dispatched type
+pub mod variant;
+pub use variant::*;
+
+// Meteora DEX pool implementation (disabled)
+// pub mod meteora;
+// pub use meteora::*;
+
+// Phoenix DEX pool implementation (disabled)
+// pub mod phoenix;
+// pub use phoenix::*;
+
+// Lifinity DEX pool implementation (disabled)
+// pub mod lifinity;
+// pub use lifinity::*;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use solana_sdk::account::Account;
+
+use crate::error::ArbResult;
+use crate::serialize::decoder::DECODERS;
+use crate::serialize::pool::Fraction;
+
+/// Sets `decimals` to the decimals of the mint `account`, noting a change
+/// in `changes`; a missing account leaves them as they are
+pub(crate) fn refresh_decimals(
+    mint: &Pubkey,
+    account: &Option<Account>,
+    decimals: &mut u64,
+    changes: &mut Vec<String>,
+) -> ArbResult<()> {
+    if let Some(account) = account {
+        let onchain = DECODERS.mint(account)?.decimals as u64;
+        if onchain != *decimals {
+            changes.push(format!("decimals of {} {} -> {}", mint, decimals, onchain));
+            *decimals = onchain;
+        }
+    }
+    Ok(())
+}
+
+/// `fee` in bps, `None` for a zero denominator
+pub(crate) fn fraction_bps(fee: &Fraction) -> Option<f64> {
+    (fee.denominator != 0).then(|| fee.numerator as f64 * 10_000.0 / fee.denominator as f64)
+}
+
+/// Sets `fee` to `numerator / denominator`, noting a change in `changes`
+pub(crate) fn refresh_fee(what: &str, fee: &mut Fraction, numerator: u64, denominator: u64, changes: &mut Vec<String>) {
+    if fee.numerator != numerator || fee.denominator != denominator {
+        changes.push(format!(
+            "{} {}/{} -> {}/{}",
+            what, fee.numerator, fee.denominator, numerator, denominator
+        ));
+        *fee = Fraction { numerator, denominator };
+    }
+}
@@ -0,0 +1,479 @@
+/**
+ * Orca DEX Pool Implementation
+ * 
+ * This module implements the pool operations interface for Orca DEX pools.
+ * Orca is a Solana-based automated market maker (AMM) that supports both
+ * constant product and stable swap pools with concentrated liquidity.
+ */
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::str::FromStr;
+use serde;
+use serde::{Deserialize, Serialize};
+use solana_sdk::account::Account;
+use crate::serialize::token::{Token, WrappedPubkey};
+use crate::serialize::decoder::{DecodedAccount, DECODERS};
+use crate::pools::{fraction_bps, refresh_decimals, refresh_fee, PoolVariant};
+use crate::serialize::pool::{Fraction, JSONFeeStructure}; 
+use crate::error::{ArbError, ArbResult, QuoteError, QuoteResult};
+use crate::pool::PoolOperations;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+use anchor_client::Program;
+
+use solana_sdk::instruction::Instruction;
+
+use tmp::accounts as tmp_accounts;
+use tmp::instruction as tmp_ix;
+
+use crate::pool_utils::base::CurveType;
+use crate::utils::{str2pubkey, derive_token_address};
+use crate::pool_utils::{
+    orca::{get_pool_quote_with_amounts},
+    exact_out::{constant_product_exact_out, search_exact_out, MAX_SWAP_INPUT},
+    fees::Fees,
+};
+use crate::constants::*;
+
+/// Represents an Orca liquidity pool with its associated accounts and parameters
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OrcaPool {
+    /// Pool's program address
+    pub address: WrappedPubkey,
+    /// Pool's nonce for PDA derivation
+    pub nonce: u64,
+    /// Pool authority PDA
+    pub authority: WrappedPubkey,
+    /// LP token mint address
+    pub pool_token_mint: WrappedPubkey,
+    /// LP token decimal places
+    pub pool_token_decimals: u64,
+    /// Account that collects fees
+    pub fee_account: WrappedPubkey,
+    /// List of token IDs in the pool
+    pub token_ids: Vec<String>,
+    /// Map of token data keyed by token ID
+    pub tokens: HashMap<String, Token>,
+    /// Fee structure for the pool
+    pub fee_structure: JSONFeeStructure,
+    /// Pool curve type (0 = ConstantProduct, 2 = Stable)
+    pub curve_type: u8,
+    /// Amplification coefficient for stable pools
+    #[serde(default)]
+    pub amp: u64,
+    /// Current token amounts in the pool (set at runtime)
+    #[serde(skip)]
+    pub pool_amounts: HashMap<String, u128>,
+    /// Slot the update accounts were fetched at (set at runtime)
+    #[serde(skip)]
+    pub update_slot: Option<u64>,
+}
+
+impl OrcaPool {
+    /// Checks what the quoting code relies on, so a malformed pool JSON is
+    /// rejected when it's loaded instead of panicking later
+    pub fn validate(&self) -> ArbResult<()> {
+        let invalid = |reason: String| ArbError::InvalidPool {
+            pool: self.address.0,
+            reason,
+        };
+        if self.token_ids.len() != 2 {
+            return Err(invalid(format!("expected 2 tokens, got {}", self.token_ids.len())));
+        }
+        for id in self.token_ids.iter() {
+            if Pubkey::from_str(id).is_err() {
+                return Err(invalid(format!("token id {} is not a pubkey", id)));
+            }
+            if !self.tokens.contains_key(id) {
+                return Err(invalid(format!("token {} is missing from tokens", id)));
+            }
+        }
+        if self.curve_type != 0 && self.curve_type != 2 {
+            return Err(invalid(format!("unknown curve type {}", self.curve_type)));
+        }
+        Ok(())
+    }
+
+    /// Fee structure in the form the curve calculators expect
+    fn fees(&self) -> Fees {
+        let trader_fee = &self.fee_structure.trader_fee;
+        let owner_fee = &self.fee_structure.owner_fee;
+        Fees {
+            trade_fee_numerator: trader_fee.numerator,
+            trade_fee_denominator: trader_fee.denominator,
+            owner_trade_fee_numerator: owner_fee.numerator,
+            owner_trade_fee_denominator: owner_fee.denominator,
+            owner_withdraw_fee_numerator: 0,
+            owner_withdraw_fee_denominator: 0,
+            host_fee_numerator: 0,
+            host_fee_denominator: 0,
+        }
+    }
+
+    /// Curve type of the pool (0 = ConstantProduct, 2 = Stable)
+    fn ctype(&self) -> CurveType {
+        if self.curve_type == 0 { 
+            CurveType::ConstantProduct 
+        } else if self.curve_type == 2 {
+            CurveType::Stable
+        } else { 
+            unreachable!("curve type {} is rejected by validate", self.curve_type);
+        }
+    }
+
+    /// Reserves of `mint_in` and `mint_out`, an error for mints the pool doesn't hold
+    fn reserves(&self, mint_in: &Pubkey, mint_out: &Pubkey) -> QuoteResult<(u128, u128)> {
+        let reserve = |mint: &Pubkey| {
+            self.pool_amounts
+                .get(&mint.to_string())
+                .copied()
+                .ok_or_else(|| QuoteError::UnknownMint(mint.to_string()))
+        };
+        Ok((reserve(mint_in)?, reserve(mint_out)?))
+    }
+}
+
+/// Implementation of pool operations for Orca DEX
+impl PoolOperations for OrcaPool {
+    /// Creates swap instructions for executing a trade
+    /// 
+    /// # Arguments
+    /// * `program` - The Anchor program instance
+    /// * `owner` - The owner's public key
+    /// * `mint_in` - Input token mint
+    /// * `mint_out` - Output token mint
+    /// 
+    /// # Returns
+    /// * Vector of instructions for executing the swap
+    fn swap_ix(&self, 
+        program: &Program,
+        owner: &Pubkey,
+        mint_in: &Pubkey, 
+        mint_out: &Pubkey
+    ) -> ArbResult<Vec<Instruction>> {
+        // Derive swap state PDA
+        let (swap_state, _) = Pubkey::find_program_address(
+            &[b"swap_state"], 
+            &program.id()
+        );
+        
+        // Derive user token accounts
+        let user_src = derive_token_address(owner, mint_in);
+        let user_dst = derive_token_address(owner, mint_out); 
+
+        // Derive pool authority PDA
+        let (authority_pda, _) = Pubkey::find_program_address(
+            &[&self.address.to_bytes()],
+            &ORCA_PROGRAM_ID 
+        );
+
+        // Get pool token accounts
+        let pool_src = self.mint_2_addr(mint_in);
+        let pool_dst = self.mint_2_addr(mint_out);
+
+        // Build swap instruction
+        let swap_ix = program
+            .request()
+            .accounts(tmp_accounts::OrcaSwap {
+                token_swap: self.address.0, 
+                authority: authority_pda,
+                user_transfer_authority: *owner,
+                user_src,
+                pool_src,
+                user_dst,
+                pool_dst,
+                pool_mint: self.pool_token_mint.0,
+                fee_account: self.fee_account.0,
+                token_program: *TOKEN_PROGRAM_ID,
+                token_swap_program: *ORCA_PROGRAM_ID,
+                swap_state,
+            })
+            .args(tmp_ix::OrcaSwap { })
+            .instructions()
+            .map_err(|e| ArbError::Instruction(e.to_string()))?;
+
+        Ok(swap_ix)
+    }
+
+    /// Calculates the expected output amount for a given input amount
+    /// 
+    /// # Arguments
+    /// * `scaled_amount_in` - Input amount scaled to proper decimals
+    /// * `mint_in` - Input token mint
+    /// * `mint_out` - Output token mint
+    /// 
+    /// # Returns
+    /// * Expected output amount, an error for unknown mints, empty pools and overflows
+    fn get_quote_with_amounts_scaled(
+        &self, 
+        scaled_amount_in: u128, 
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> QuoteResult<u128> {
+        // Get current pool amounts
+        let (pool_src_amount, pool_dst_amount) = self.reserves(mint_in, mint_out)?;
+
+        // Calculate quote using appropriate curve formula
+        get_pool_quote_with_amounts(
+            scaled_amount_in,
+            self.ctype(),
+            self.amp, 
+            &self.fees(), 
+            pool_src_amount, 
+            pool_dst_amount, 
+            None,
+        )
+    }
+
+    /// Quotes every amount on a single read of the reserves and fees
+    fn get_quotes_for_amounts(
+        &self,
+        amounts_in: &[u128],
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> Vec<QuoteResult<u128>> {
+        let reserves = self.reserves(mint_in, mint_out);
+        let fees = self.fees();
+        amounts_in
+            .iter()
+            .map(|amount_in| {
+                let (pool_src_amount, pool_dst_amount) = reserves.clone()?;
+                get_pool_quote_with_amounts(
+                    *amount_in,
+                    self.ctype(),
+                    self.amp,
+                    &fees,
+                    pool_src_amount,
+                    pool_dst_amount,
+                    None,
+                )
+            })
+            .collect()
+    }
+
+    /// Calculates the input needed to receive a given output amount
+    /// 
+    /// # Arguments
+    /// * `amount_out` - Target output amount (scaled)
+    /// * `mint_in` - Input token mint
+    /// * `mint_out` - Output token mint
+    /// 
+    /// # Returns
+    /// * Required input amount, `None` if the pool can't pay out that much
+    fn get_quote_with_exact_out(
+        &self,
+        amount_out: u128,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> Option<u128> {
+        let pool_src_amount = *self.pool_amounts.get(&mint_in.to_string())?;
+        let pool_dst_amount = *self.pool_amounts.get(&mint_out.to_string())?;
+        if amount_out >= pool_dst_amount {
+            return None;
+        }
+
+        // constant product pools have a closed form to start the search from
+        let hint = match self.ctype() {
+            CurveType::ConstantProduct => constant_product_exact_out(
+                amount_out,
+                pool_src_amount,
+                pool_dst_amount,
+                &self.fees(),
+            ),
+            _ => None,
+        };
+        search_exact_out(amount_out, hint, MAX_SWAP_INPUT, |amount_in| {
+            // a failed quote pays nothing, the search moves past it
+            self.get_quote_with_amounts_scaled(amount_in, mint_in, mint_out).unwrap_or(0)
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn PoolOperations> {
+        Box::new(self.clone())
+    }
+
+    /// Returns a list of account public keys that need to be updated
+    fn get_update_accounts(&self) -> Vec<Pubkey> {
+        // Get pool vault accounts for all tokens
+        let accounts = self
+            .get_mints()
+            .iter()
+            .map(|mint| self.mint_2_addr(mint))
+            .collect();        
+        accounts 
+    }
+
+    fn get_update_slot(&self) -> Option<u64> {
+        self.update_slot
+    }
+
+    fn set_update_slot(&mut self, slot: u64) {
+        self.update_slot = Some(slot);
+    }
+
+    /// The swap state account, then the mints in `get_mints` order
+    fn get_param_accounts(&self) -> Vec<Pubkey> {
+        let mut accounts = vec![self.address.0];
+        accounts.extend(self.get_mints());
+        accounts
+    }
+
+    fn set_param_accounts(&mut self, accounts: &[Option<Account>]) -> ArbResult<Vec<String>> {
+        let mut changes = vec![];
+        if let Some(Some(state)) = accounts.first() {
+            match DECODERS.decode(state)?.account {
+                DecodedAccount::OrcaSwap(swap) => {
+                    let fees = &mut self.fee_structure;
+                    refresh_fee(
+                        "trader fee",
+                        &mut fees.trader_fee,
+                        swap.trade_fee_numerator,
+                        swap.trade_fee_denominator,
+                        &mut changes,
+                    );
+                    refresh_fee(
+                        "owner fee",
+                        &mut fees.owner_fee,
+                        swap.owner_trade_fee_numerator,
+                        swap.owner_trade_fee_denominator,
+                        &mut changes,
+                    );
+                }
+                _ => {
+                    return Err(ArbError::InvalidPool {
+                        pool: self.address.0,
+                        reason: "pool account is not a token-swap state".to_string(),
+                    })
+                }
+            }
+        }
+        for (mint, account) in self.get_mints().iter().zip(accounts.iter().skip(1)) {
+            if let Some(token) = self.tokens.get_mut(&mint.to_string()) {
+                refresh_decimals(mint, account, &mut token.scale, &mut changes)?;
+            }
+        }
+        Ok(changes)
+    }
+
+    fn set_fee_bps(&mut self, fee_bps: u64) -> ArbResult<()> {
+        self.fee_structure.trader_fee = Fraction {
+            numerator: fee_bps,
+            denominator: 10_000,
+        };
+        // the override is the whole fee
+        self.fee_structure.owner_fee = Fraction {
+            numerator: 0,
+            denominator: 10_000,
+        };
+        Ok(())
+    }
+
+    fn get_fee_bps(&self) -> Option<f64> {
+        let fees = &self.fee_structure;
+        Some(fraction_bps(&fees.trader_fee)? + fraction_bps(&fees.owner_fee)?)
+    }
+
+    /// Checks if trading is possible between two tokens
+    /// 
+    /// # Arguments
+    /// * `_mint_in` - Input token mint
+    /// * `_mint_out` - Output token mint
+    /// 
+    /// # Returns
+    /// * Boolean indicating if trading is possible
+    fn can_trade(&self, 
+        _mint_in: &Pubkey,
+        _mint_out: &Pubkey
+    ) -> bool {
+        // Check if any pool has zero liquidity
+        for amount in self.pool_amounts.values() {
+            if *amount == 0 { return false; }
+        }
+        true
+    }
+
+    /// Updates the pool's token amounts with new account data
+    /// 
+    /// # Arguments
+    /// * `accounts` - Vector of optional accounts
+    /// * `_cluster` - The Solana cluster being used
+    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) -> ArbResult<()> {
+        // Extract token amounts from account data (accounts follow `get_update_accounts`)
+        let mut amounts = vec![];
+        for (mint, account) in self.get_mints().iter().zip(accounts.iter()) {
+            let account = account
+                .as_ref()
+                .ok_or_else(|| ArbError::MissingAccount(self.mint_2_addr(mint)))?;
+            let amount = DECODERS.token_account(account)?.amount as u128;
+            amounts.push((mint.to_string(), amount));
+        }
+        if amounts.len() != 2 {
+            return Err(ArbError::InvalidPool {
+                pool: self.address.0,
+                reason: format!("expected 2 vault accounts, got {}", accounts.len()),
+            });
+        }
+
+        // Update pool amounts
+        self.pool_amounts.extend(amounts);
+        Ok(())
+    }
+
+    /// The whole input stays in the pool (its fees too), the output leaves it
+    fn after_swap(&self, amount_in: u128, mint_in: &Pubkey, mint_out: &Pubkey) -> Option<PoolVariant> {
+        let amount_out = self.get_quote_with_amounts_scaled(amount_in, mint_in, mint_out).ok()?;
+        let mut pool = self.clone();
+        *pool.pool_amounts.get_mut(&mint_in.to_string())? += amount_in;
+        let reserve_out = pool.pool_amounts.get_mut(&mint_out.to_string())?;
+        *reserve_out = reserve_out.checked_sub(amount_out)?;
+        Some(pool.into())
+    }
+
+    /// Returns the current pool reserves per mint
+    fn get_reserves(&self) -> Option<Vec<(Pubkey, u128)>> {
+        self.get_mints()
+            .iter()
+            .map(|mint| {
+                self.pool_amounts
+                    .get(&mint.to_string())
+                    .map(|amount| (*mint, *amount))
+            })
+            .collect()
+    }
+
+    /// Returns the name of the DEX
+    fn get_name(&self) -> String {
+        "Orca".to_string()
+    }
+
+    /// Returns the address of the pool account
+    fn get_address(&self) -> Pubkey {
+        self.address.0
+    }
+
+    /// Returns the token account address for a given mint
+    fn mint_2_addr(&self, mint: &Pubkey) -> Pubkey {
+        let token = self.tokens.get(&mint.to_string()).unwrap();
+        token.addr.0
+    }
+
+    /// Returns the decimal scale for a given mint
+    fn mint_2_scale(&self, mint: &Pubkey) -> u64 {
+        let token = self.tokens.get(&mint.to_string()).unwrap();
+        token.scale
+    }
+
+    /// Returns a sorted vector of the pool's token mint addresses
+    fn get_mints(&self) -> Vec<Pubkey> {
+        let mut mints: Vec<Pubkey> = self.token_ids
+            .iter()
+            .map(|k| str2pubkey(k))
+            .collect();
+        // Sort for consistent ordering across pools
+        mints.sort();
+        mints
+    }
+}
\ No newline at end of file
@@ -0,0 +1,547 @@
+/**
+ * Raydium DEX Pool Implementation
+ * 
+ * This module implements the pool operations interface for Raydium DEX pools.
+ * Raydium is a Solana-based automated market maker (AMM) that provides liquidity
+ * to the Serum order book, enabling better price discovery and deeper liquidity.
+ */
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use solana_sdk::account::Account;
+use crate::serialize::token::{Token, WrappedPubkey};
+use crate::serialize::decoder::{DecodedAccount, DECODERS};
+use crate::pools::{fraction_bps, refresh_fee, PoolVariant};
+use crate::serialize::pool::{Fraction, JSONFeeStructure};
+use crate::error::{ArbError, ArbResult, QuoteError, QuoteResult};
+use crate::pool::PoolOperations;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+use anchor_client::Program;
+
+use solana_sdk::instruction::Instruction;
+
+use tmp::accounts as tmp_accounts;
+use tmp::instruction as tmp_ix;
+
+use crate::pool_utils::base::CurveType;
+use crate::utils::{str2pubkey, derive_token_address};
+use crate::pool_utils::{
+    raydium::{get_pool_quote_with_amounts},
+    exact_out::{constant_product_exact_out, search_exact_out, MAX_SWAP_INPUT},
+    fees::Fees,
+};
+use crate::constants::*;
+
+/// Represents a Raydium liquidity pool with its associated accounts and parameters
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RaydiumPool {
+    /// Pool's program address
+    pub address: WrappedPubkey,
+    /// Pool's nonce for PDA derivation
+    pub nonce: u64,
+    /// Pool authority PDA
+    pub authority: WrappedPubkey,
+    /// LP token mint address
+    pub lp_token_mint: WrappedPubkey,
+    /// Account that collects fees
+    pub fee_account: WrappedPubkey,
+    /// List of token IDs in the pool
+    pub token_ids: Vec<String>,
+    /// Map of token data keyed by token ID
+    pub tokens: HashMap<String, Token>,
+    /// Fee structure for the pool
+    pub fee_structure: JSONFeeStructure,
+    /// The amm's open orders on its Serum/OpenBook market; if set, what it
+    /// holds there counts toward the reserves
+    #[serde(default)]
+    pub open_orders: Option<WrappedPubkey>,
+    /// Current token amounts in the pool (set at runtime)
+    #[serde(default)]
+    pub pool_amounts: HashMap<String, u128>,
+    /// Slot the update accounts were fetched at (set at runtime)
+    #[serde(skip)]
+    pub update_slot: Option<u64>,
+}
+
+impl RaydiumPool {
+    /// Checks what the quoting code relies on, so a malformed pool JSON is
+    /// rejected when it's loaded instead of panicking later
+    pub fn validate(&self) -> ArbResult<()> {
+        let invalid = |reason: String| ArbError::InvalidPool {
+            pool: self.address.0,
+            reason,
+        };
+        if self.token_ids.len() != 2 {
+            return Err(invalid(format!("expected 2 tokens, got {}", self.token_ids.len())));
+        }
+        for id in self.token_ids.iter() {
+            if Pubkey::from_str(id).is_err() {
+                return Err(invalid(format!("token id {} is not a pubkey", id)));
+            }
+            if !self.tokens.contains_key(id) {
+                return Err(invalid(format!("token {} is missing from tokens", id)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Fee structure in the form the curve calculators expect
+    fn fees(&self) -> Fees {
+        let trader_fee = &self.fee_structure.trader_fee;
+        Fees {
+            trade_fee_numerator: trader_fee.numerator,
+            trade_fee_denominator: trader_fee.denominator,
+            owner_trade_fee_numerator: 0,
+            owner_trade_fee_denominator: 0,
+            owner_withdraw_fee_numerator: 0,
+            owner_withdraw_fee_denominator: 0,
+            host_fee_numerator: 0,
+            host_fee_denominator: 0,
+        }
+    }
+
+    /// What the amm holds on its market and the pnl it owes, by mint. Its
+    /// swaps quote from the vaults plus the open orders totals less the pnl
+    /// (`calc_total_without_take_pnl` in the amm program); the pnl is kept in
+    /// the amm state, the target orders account only tracks how it accrues.
+    fn order_book_amounts(
+        &self,
+        open_orders: &Account,
+        state: &Account,
+    ) -> ArbResult<[(Pubkey, u128, u128); 2]> {
+        let invalid = |reason: &str| ArbError::InvalidPool {
+            pool: self.address.0,
+            reason: reason.to_string(),
+        };
+        let open_orders = match DECODERS.decode(open_orders)?.account {
+            DecodedAccount::SerumOpenOrders(open_orders) => open_orders,
+            _ => return Err(invalid("open orders account is not a serum open orders")),
+        };
+        let amm = match DECODERS.decode(state)?.account {
+            DecodedAccount::RaydiumAmm(amm) => amm,
+            _ => return Err(invalid("pool account is not an amm v4 state")),
+        };
+        Ok([
+            (amm.base_mint, open_orders.native_coin_total as u128, amm.base_need_take_pnl as u128),
+            (amm.quote_mint, open_orders.native_pc_total as u128, amm.quote_need_take_pnl as u128),
+        ])
+    }
+
+    /// Reserves of `mint_in` and `mint_out`, an error for mints the pool doesn't hold
+    fn reserves(&self, mint_in: &Pubkey, mint_out: &Pubkey) -> QuoteResult<(u128, u128)> {
+        let reserve = |mint: &Pubkey| {
+            self.pool_amounts
+                .get(&mint.to_string())
+                .copied()
+                .ok_or_else(|| QuoteError::UnknownMint(mint.to_string()))
+        };
+        Ok((reserve(mint_in)?, reserve(mint_out)?))
+    }
+}
+
+/// Implementation of pool operations for Raydium DEX
+impl PoolOperations for RaydiumPool {
+    /// Creates swap instructions for executing a trade
+    /// 
+    /// # Arguments
+    /// * `program` - The Anchor program instance
+    /// * `owner` - The owner's public key
+    /// * `mint_in` - Input token mint
+    /// * `mint_out` - Output token mint
+    /// 
+    /// # Returns
+    /// * Vector of instructions for executing the swap
+    fn swap_ix(&self, 
+        program: &Program,
+        owner: &Pubkey,
+        mint_in: &Pubkey, 
+        mint_out: &Pubkey
+    ) -> ArbResult<Vec<Instruction>> {
+        // Derive swap state PDA
+        let (swap_state, _) = Pubkey::find_program_address(
+            &[b"swap_state"], 
+            &program.id()
+        );
+
+        // Derive user token accounts
+        let user_src = derive_token_address(owner, mint_in);
+        let user_dst = derive_token_address(owner, mint_out); 
+
+        // Derive pool authority PDA
+        let (authority_pda, _) = Pubkey::find_program_address(
+            &[&self.address.to_bytes()],
+            &RAYDIUM_PROGRAM_ID 
+        );
+
+        // Get pool token accounts
+        let pool_src = self.mint_2_addr(mint_in);
+        let pool_dst = self.mint_2_addr(mint_out);
+
+        // Build swap instruction
+        let swap_ix = program
+            .request()
+            .accounts(tmp_accounts::RaydiumSwap {
+                token_swap: self.address.0, 
+                authority: authority_pda,
+                user_transfer_authority: *owner,
+                user_src,
+                pool_src,
+                user_dst,
+                pool_dst,
+                lp_mint: self.lp_token_mint.0,
+                fee_account: self.fee_account.0,
+                token_program: *TOKEN_PROGRAM_ID,
+                swap_program: *RAYDIUM_PROGRAM_ID,
+                swap_state,
+            })
+            .args(tmp_ix::RaydiumSwap { })
+            .instructions()
+            .map_err(|e| ArbError::Instruction(e.to_string()))?;
+
+        Ok(swap_ix)
+    }
+
+    /// Calculates the expected output amount for a given input amount
+    /// 
+    /// # Arguments
+    /// * `scaled_amount_in` - Input amount scaled to proper decimals
+    /// * `mint_in` - Input token mint
+    /// * `mint_out` - Output token mint
+    /// 
+    /// # Returns
+    /// * Expected output amount, an error for unknown mints, empty pools and overflows
+    fn get_quote_with_amounts_scaled(
+        &self, 
+        scaled_amount_in: u128, 
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> QuoteResult<u128> {
+        // Get current pool amounts
+        let (pool_src_amount, pool_dst_amount) = self.reserves(mint_in, mint_out)?;
+
+        // Calculate quote using Raydium's constant product formula
+        get_pool_quote_with_amounts(
+            scaled_amount_in,
+            CurveType::ConstantProduct,
+            0, // amp is unused by constant product curves
+            &self.fees(), 
+            pool_src_amount, 
+            pool_dst_amount, 
+            None,
+        )
+    }
+
+    /// Quotes every amount on a single read of the reserves and fees
+    fn get_quotes_for_amounts(
+        &self,
+        amounts_in: &[u128],
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> Vec<QuoteResult<u128>> {
+        let reserves = self.reserves(mint_in, mint_out);
+        let fees = self.fees();
+        amounts_in
+            .iter()
+            .map(|amount_in| {
+                let (pool_src_amount, pool_dst_amount) = reserves.clone()?;
+                get_pool_quote_with_amounts(
+                    *amount_in,
+                    CurveType::ConstantProduct,
+                    0, // amp is unused by constant product curves
+                    &fees,
+                    pool_src_amount,
+                    pool_dst_amount,
+                    None,
+                )
+            })
+            .collect()
+    }
+
+    /// Calculates the input needed to receive a given output amount
+    /// 
+    /// # Arguments
+    /// * `amount_out` - Target output amount (scaled)
+    /// * `mint_in` - Input token mint
+    /// * `mint_out` - Output token mint
+    /// 
+    /// # Returns
+    /// * Required input amount, `None` if the pool can't pay out that much
+    fn get_quote_with_exact_out(
+        &self,
+        amount_out: u128,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> Option<u128> {
+        let pool_src_amount = *self.pool_amounts.get(&mint_in.to_string())?;
+        let pool_dst_amount = *self.pool_amounts.get(&mint_out.to_string())?;
+        let hint = constant_product_exact_out(
+            amount_out,
+            pool_src_amount,
+            pool_dst_amount,
+            &self.fees(),
+        )?;
+        search_exact_out(amount_out, Some(hint), MAX_SWAP_INPUT, |amount_in| {
+            // a failed quote pays nothing, the search moves past it
+            self.get_quote_with_amounts_scaled(amount_in, mint_in, mint_out).unwrap_or(0)
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn PoolOperations> {
+        Box::new(self.clone())
+    }
+
+    /// Returns a list of account public keys that need to be updated
+    fn get_update_accounts(&self) -> Vec<Pubkey> {
+        // Get pool vault accounts for all tokens
+        let mut accounts: Vec<Pubkey> = self.get_mints()
+            .iter()
+            .map(|mint| self.mint_2_addr(mint))
+            .collect();
+        // then the open orders and the amm state with the pnl it owes
+        if let Some(open_orders) = &self.open_orders {
+            accounts.extend([open_orders.0, self.address.0]);
+        }
+        accounts
+    }
+
+    fn get_update_slot(&self) -> Option<u64> {
+        self.update_slot
+    }
+
+    fn set_update_slot(&mut self, slot: u64) {
+        self.update_slot = Some(slot);
+    }
+
+    /// The amm state account, which holds the decimals too
+    fn get_param_accounts(&self) -> Vec<Pubkey> {
+        vec![self.address.0]
+    }
+
+    fn set_param_accounts(&mut self, accounts: &[Option<Account>]) -> ArbResult<Vec<String>> {
+        let mut changes = vec![];
+        let state = match accounts.first() {
+            Some(Some(state)) => state,
+            _ => return Ok(changes),
+        };
+        let amm = match DECODERS.decode(state)?.account {
+            DecodedAccount::RaydiumAmm(amm) => amm,
+            _ => {
+                return Err(ArbError::InvalidPool {
+                    pool: self.address.0,
+                    reason: "pool account is not an amm v4 state".to_string(),
+                })
+            }
+        };
+        // the swap fee is all of it, see `fetch`
+        refresh_fee(
+            "swap fee",
+            &mut self.fee_structure.trader_fee,
+            amm.swap_fee_numerator,
+            amm.swap_fee_denominator,
+            &mut changes,
+        );
+        for (mint, decimals) in [(amm.base_mint, amm.base_decimal), (amm.quote_mint, amm.quote_decimal)] {
+            if let Some(token) = self.tokens.get_mut(&mint.to_string()) {
+                if token.scale != decimals {
+                    changes.push(format!("decimals of {} {} -> {}", mint, token.scale, decimals));
+                    token.scale = decimals;
+                }
+            }
+        }
+        Ok(changes)
+    }
+
+    fn set_fee_bps(&mut self, fee_bps: u64) -> ArbResult<()> {
+        self.fee_structure.trader_fee = Fraction {
+            numerator: fee_bps,
+            denominator: 10_000,
+        };
+        Ok(())
+    }
+
+    fn get_fee_bps(&self) -> Option<f64> {
+        fraction_bps(&self.fee_structure.trader_fee)
+    }
+
+    /// Checks if trading is possible between two tokens
+    /// 
+    /// # Arguments
+    /// * `_mint_in` - Input token mint
+    /// * `_mint_out` - Output token mint
+    /// 
+    /// # Returns
+    /// * Boolean indicating if trading is possible
+    fn can_trade(&self, 
+        _mint_in: &Pubkey,
+        _mint_out: &Pubkey
+    ) -> bool {
+        // Check if any pool has zero liquidity
+        for amount in self.pool_amounts.values() {
+            if *amount == 0 { return false; }
+        }
+        true
+    }
+
+    /// Updates the pool's token amounts with new account data
+    /// 
+    /// # Arguments
+    /// * `accounts` - Vector of optional accounts
+    /// * `_cluster` - The Solana cluster being used
+    fn set_update_accounts(&mut self, accounts: Vec<Option<Account>>, _cluster: Cluster) -> ArbResult<()> {
+        // Extract token amounts from account data (accounts follow `get_update_accounts`)
+        let mut amounts = vec![];
+        for (mint, account) in self.get_mints().iter().zip(accounts.iter()) {
+            let account = account
+                .as_ref()
+                .ok_or_else(|| ArbError::MissingAccount(self.mint_2_addr(mint)))?;
+            let amount = DECODERS.token_account(account)?.amount as u128;
+            amounts.push((mint.to_string(), amount));
+        }
+        if amounts.len() != 2 {
+            return Err(ArbError::InvalidPool {
+                pool: self.address.0,
+                reason: format!("expected 2 vault accounts, got {}", accounts.len()),
+            });
+        }
+        if let Some(open_orders_key) = &self.open_orders {
+            let open_orders = accounts
+                .get(2)
+                .and_then(|account| account.as_ref())
+                .ok_or(ArbError::MissingAccount(open_orders_key.0))?;
+            let state = accounts
+                .get(3)
+                .and_then(|account| account.as_ref())
+                .ok_or(ArbError::MissingAccount(self.address.0))?;
+            for (mint, held, owed) in self.order_book_amounts(open_orders, state)? {
+                if let Some((_, amount)) = amounts.iter_mut().find(|(m, _)| *m == mint.to_string()) {
+                    *amount = (*amount + held).saturating_sub(owed);
+                }
+            }
+        }
+
+        // Update pool amounts
+        self.pool_amounts.extend(amounts);
+        Ok(())
+    }
+
+    /// The whole input stays in the pool (its fees too), the output leaves it
+    fn after_swap(&self, amount_in: u128, mint_in: &Pubkey, mint_out: &Pubkey) -> Option<PoolVariant> {
+        let amount_out = self.get_quote_with_amounts_scaled(amount_in, mint_in, mint_out).ok()?;
+        let mut pool = self.clone();
+        *pool.pool_amounts.get_mut(&mint_in.to_string())? += amount_in;
+        let reserve_out = pool.pool_amounts.get_mut(&mint_out.to_string())?;
+        *reserve_out = reserve_out.checked_sub(amount_out)?;
+        Some(pool.into())
+    }
+
+    /// Returns the current pool reserves per mint
+    fn get_reserves(&self) -> Option<Vec<(Pubkey, u128)>> {
+        self.get_mints()
+            .iter()
+            .map(|mint| {
+                self.pool_amounts
+                    .get(&mint.to_string())
+                    .map(|amount| (*mint, *amount))
+            })
+            .collect()
+    }
+
+    /// Returns the name of the DEX
+    fn get_name(&self) -> String {
+        "Raydium".to_string()
+    }
+
+    /// Returns the address of the pool account
+    fn get_address(&self) -> Pubkey {
+        self.address.0
+    }
+
+    /// Returns the token account address for a given mint
+    fn mint_2_addr(&self, mint: &Pubkey) -> Pubkey {
+        let token = self.tokens.get(&mint.to_string()).unwrap();
+        token.addr.0
+    }
+
+    /// Returns the decimal scale for a given mint
+    fn mint_2_scale(&self, mint: &Pubkey) -> u64 {
+        let token = self.tokens.get(&mint.to_string()).unwrap();
+        token.scale
+    }
+
+    /// Returns a sorted vector of the pool's token mint addresses
+    fn get_mints(&self) -> Vec<Pubkey> {
+        let mut mints: Vec<Pubkey> = self.token_ids
+            .iter()
+            .map(|k| str2pubkey(k))
+            .collect();
+        // Sort for consistent ordering across pools
+        mints.sort();
+        mints
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialize::decoder::{RAYDIUM_AMM_V4_LEN, SERUM_OPEN_ORDERS_LEN};
+    use crate::serialize::token::TOKEN_ACCOUNT_LEN;
+
+    fn account(owner: Pubkey, data: Vec<u8>) -> Option<Account> {
+        Some(Account {
+            owner,
+            data,
+            ..Account::default()
+        })
+    }
+
+    fn vault(amount: u64) -> Option<Account> {
+        let mut data = vec![0u8; TOKEN_ACCOUNT_LEN];
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+        data[108] = 1; // initialized
+        account(*TOKEN_PROGRAM_ID, data)
+    }
+
+    #[test]
+    fn open_orders_count_toward_the_reserves() {
+        let fixture = include_str!("../../benches/fixtures/raydium.json");
+        let mut pool: RaydiumPool = serde_json::from_str(fixture).unwrap();
+        let (sol, usdc) = (str2pubkey(&pool.token_ids[0]), str2pubkey(&pool.token_ids[1]));
+        let open_orders_key = Pubkey::new_unique();
+        pool.open_orders = Some(WrappedPubkey(open_orders_key));
+        let update_accounts = pool.get_update_accounts();
+        assert_eq!(update_accounts[2..], [open_orders_key, pool.address.0]);
+
+        // sol is the base (coin) of the amm, usdc the quote (pc)
+        let mut open_orders = vec![0u8; SERUM_OPEN_ORDERS_LEN];
+        open_orders[..5].copy_from_slice(b"serum");
+        open_orders[5..13].copy_from_slice(&5u64.to_le_bytes());
+        open_orders[85..93].copy_from_slice(&4_000u64.to_le_bytes());
+        open_orders[101..109].copy_from_slice(&600_000u64.to_le_bytes());
+        let mut state = vec![0u8; RAYDIUM_AMM_V4_LEN];
+        state[192..200].copy_from_slice(&1_000u64.to_le_bytes());
+        state[200..208].copy_from_slice(&100_000u64.to_le_bytes());
+        state[400..432].copy_from_slice(sol.as_ref());
+        state[432..464].copy_from_slice(usdc.as_ref());
+
+        // the vaults come in sorted mint order
+        let vaults = match sol < usdc {
+            true => [vault(10_000), vault(1_500_000)],
+            false => [vault(1_500_000), vault(10_000)],
+        };
+        let mut accounts = vaults.to_vec();
+        accounts.extend([account(*OPENBOOK_PROGRAM_ID, open_orders), account(*RAYDIUM_PROGRAM_ID, state)]);
+        pool.set_update_accounts(accounts.clone(), Cluster::Mainnet).unwrap();
+        // vault + open orders - pnl owed
+        let reserves = pool.reserves(&sol, &usdc).unwrap();
+        assert_eq!(reserves, (10_000 + 4_000 - 1_000, 1_500_000 + 600_000 - 100_000));
+
+        // the open orders are needed once configured
+        accounts[2] = None;
+        assert!(pool.set_update_accounts(accounts, Cluster::Mainnet).is_err());
+    }
+}
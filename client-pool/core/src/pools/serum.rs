@@ -0,0 +1,460 @@
+/**
+ * Serum DEX Pool Implementation
+ * 
+ * This module implements the pool operations interface for Serum DEX markets.
+ * Serum is a Solana-based decentralized exchange that uses an order book model
+ * rather than an automated market maker (AMM) model. This implementation handles:
+ * 1. Order book state management
+ * 2. Price discovery through order matching
+ * 3. Trade execution and settlement
+ */
+
+use core::panic;
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use serde;
+use serde::{Deserialize, Serialize};
+use crate::error::{ArbError, ArbResult, QuoteError, QuoteResult};
+use crate::pool::PoolOperations;
+use crate::serialize::token::{WrappedPubkey};
+
+use crate::utils::{derive_token_address}; 
+
+use solana_sdk::pubkey::Pubkey;
+
+use anchor_client::{Program, Cluster};
+use solana_sdk::instruction::Instruction;
+
+use solana_sdk::account::Account;
+use crate::constants::*;
+use crate::pool_utils::exact_out::{search_exact_out, MAX_SWAP_INPUT};
+use crate::pool_utils::serum::*;
+use crate::pools::refresh_decimals;
+
+use anchor_spl::dex::serum_dex::{
+    error::DexError,
+    instruction as dex_instruction,
+    matching::Side,
+};
+
+use std::str::FromStr;
+use std::sync::Arc;
+use tmp::accounts as tmp_accounts;
+use tmp::instruction as tmp_instructions;
+
+/// Represents a Serum market with its associated accounts and parameters
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerumPool {
+    /// Market's program address
+    pub own_address: WrappedPubkey,
+    /// Base token mint address
+    pub base_mint: WrappedPubkey,
+    /// Quote token mint address
+    pub quote_mint: WrappedPubkey,
+    /// Base token decimal places
+    pub base_scale: u64,
+    /// Quote token decimal places
+    pub quote_scale: u64,
+    /// Base token vault address
+    pub base_vault: WrappedPubkey,
+    /// Quote token vault address
+    pub quote_vault: WrappedPubkey,
+    /// Request queue account address
+    pub request_queue: WrappedPubkey,
+    /// Event queue account address
+    pub event_queue: WrappedPubkey,
+    /// Bids account address
+    pub bids: WrappedPubkey,
+    /// Asks account address
+    pub asks: WrappedPubkey,
+    /// Vault signer PDA address
+    pub vault_signer: WrappedPubkey,
+    /// Taker fee percentage
+    pub taker_fee_pct: f64,
+    /// Market accounts (loaded at runtime)
+    #[serde(skip)]
+    pub accounts: Option<Vec<Option<Account>>>,
+    /// Open orders map (loaded at runtime)
+    #[serde(skip)]
+    pub open_orders: Option<HashMap<String, String>>,
+    /// Slot the update accounts were fetched at (set at runtime)
+    #[serde(skip)]
+    pub update_slot: Option<u64>,
+    /// Orders copied out of `accounts` when they were set, what quotes walk
+    /// (set at runtime)
+    #[serde(skip)]
+    pub book: Option<Arc<BookSnapshot>>,
+    /// Fee tier the owner trades the market at, what quotes charge (set at
+    /// runtime, see `set_fee_tier`)
+    #[serde(skip)]
+    pub fee_tier: FeeTier,
+}
+
+impl SerumPool {
+    /// Takes the fee tier of an owner holding `srm_held` SRM and `msrm_held`
+    /// MSRM (native amounts), stable markets always trading at theirs
+    pub fn set_fee_tier(&mut self, srm_held: u64, msrm_held: u64) {
+        self.fee_tier = FeeTier::from_srm_and_msrm_balances(&self.own_address.0, srm_held, msrm_held);
+    }
+
+    /// The market's open orders account, as written by `setup_open_orders`
+    /// (keyed by market)
+    fn open_orders_account(&self, owner: &Pubkey) -> ArbResult<Pubkey> {
+        let missing = || ArbError::MissingOpenOrders {
+            market: self.own_address.0,
+            owner: *owner,
+        };
+        let open_orders = self.open_orders
+            .as_ref()
+            .and_then(|oo| oo.get(&self.own_address.0.to_string()))
+            .ok_or_else(missing)?;
+        Pubkey::from_str(open_orders).map_err(|_| missing())
+    }
+}
+
+/// Implementation of pool operations for Serum DEX
+impl PoolOperations for SerumPool {
+    /// Returns the name of the DEX
+    fn get_name(&self) -> String {
+        "Serum".to_string()
+    }
+
+    /// Returns the address of the pool account
+    fn get_address(&self) -> Pubkey {
+        self.own_address.0
+    }
+
+    fn clone_box(&self) -> Box<dyn PoolOperations> {
+        Box::new(self.clone())
+    }
+
+    /// Returns a list of account public keys that need to be updated
+    fn get_update_accounts(&self) -> Vec<Pubkey> {
+        vec![
+            self.own_address.0, 
+            self.bids.0, 
+            self.asks.0,
+        ]
+    }
+
+    /// Updates the market's accounts with new account data
+    /// 
+    /// # Arguments
+    /// * `accounts` - Vector of optional accounts
+    /// * `cluster` - The Solana cluster being used
+    fn set_update_accounts(
+        &mut self, 
+        accounts: Vec<Option<Account>>,
+        cluster: Cluster,
+    ) -> ArbResult<()> {
+        // market, bids and asks are all needed to walk the book
+        for (pk, account) in self.get_update_accounts().iter().zip(accounts.iter()) {
+            if account.is_none() {
+                return Err(ArbError::MissingAccount(*pk));
+            }
+        }
+        if accounts.len() != 3 {
+            return Err(ArbError::InvalidPool {
+                pool: self.own_address.0,
+                reason: format!("expected 3 market accounts, got {}", accounts.len()),
+            });
+        }
+
+        // Load open orders from file based on cluster
+        let oo_path = match cluster { 
+            Cluster::Localnet => {
+                "./serum_open_orders.json"
+            }, 
+            Cluster::Mainnet => {
+                return Err(ArbError::Unsupported("serum open orders on mainnet"));
+            },
+            _ => return Err(ArbError::Unsupported("serum on this cluster")),
+        };
+        let oo_str = std::fs::read_to_string(oo_path)?;
+        let oo_book: HashMap<String, String> = serde_json::from_str(&oo_str)?;
+        // copy the orders out once here rather than on every quote
+        let data = |i: usize| accounts[i].as_ref().map_or(&[][..], |account| account.data.as_slice());
+        self.book = BookSnapshot::load(
+            (&self.own_address.0, data(0)),
+            (&self.bids.0, data(1)),
+            (&self.asks.0, data(2)),
+            &SERUM_PROGRAM_ID,
+        )
+        .map(Arc::new);
+        self.accounts = Some(accounts);
+        self.open_orders = Some(oo_book); 
+        Ok(())
+    }
+
+    /// Returns the token account address for a given mint (Not implemented)
+    fn mint_2_addr(&self, _mint: &Pubkey) -> Pubkey {
+        panic!("Function not implemented")
+    }
+
+    /// Returns a sorted vector of the market's token mint addresses
+    fn get_mints(&self) -> Vec<Pubkey> {
+        let mut mints = vec![
+            self.base_mint.0,
+            self.quote_mint.0
+        ];
+        mints.sort();
+        mints
+    }
+
+    /// Returns the decimal scale for a given mint
+    fn mint_2_scale(&self, mint: &Pubkey) -> u64 {
+        if *mint == self.base_mint.0 {
+            self.base_scale
+        } else if *mint == self.quote_mint.0 { 
+            self.quote_scale
+        } else { 
+            panic!("Invalid mint provided")
+        }
+    }
+
+    /// Calculates the expected output amount for a given input amount
+    /// 
+    /// # Arguments
+    /// * `amount_in` - Input amount scaled to proper decimals
+    /// * `mint_in` - Input token mint
+    /// * `_mint_out` - Output token mint
+    /// 
+    /// # Returns
+    /// * Expected output amount, an error if the accounts aren't loaded or
+    ///   the side of the book the order takes from is empty
+    fn get_quote_with_amounts_scaled(
+        &self, 
+        amount_in: u128, 
+        mint_in: &Pubkey,
+        _mint_out: &Pubkey,
+    ) -> QuoteResult<u128> {
+        let book = self.book.as_ref().ok_or(QuoteError::BadPoolData)?;
+        let amount_in = u64::try_from(amount_in).map_err(|_| QuoteError::Overflow)?;
+
+        // Process order based on direction
+        let is_bid = *mint_in == self.quote_mint.0;
+        book.walk(amount_in, is_bid, self.fee_tier)
+            .map(|amount_out| amount_out as u128)
+    }
+
+    /// Quotes every amount in one pass over the book, see `BookSnapshot::walk_many`
+    fn get_quotes_for_amounts(
+        &self,
+        amounts_in: &[u128],
+        mint_in: &Pubkey,
+        _mint_out: &Pubkey,
+    ) -> Vec<QuoteResult<u128>> {
+        let book = match &self.book {
+            Some(book) => book,
+            None => return vec![Err(QuoteError::BadPoolData); amounts_in.len()],
+        };
+        let is_bid = *mint_in == self.quote_mint.0;
+        // amounts past u64 can't be filled, the rest are walked together
+        let walkable: Vec<u64> = amounts_in.iter().filter_map(|a| u64::try_from(*a).ok()).collect();
+        let mut walked = book.walk_many(&walkable, is_bid, self.fee_tier).into_iter();
+        amounts_in
+            .iter()
+            .map(|amount_in| match u64::try_from(*amount_in) {
+                Ok(_) => walked.next().unwrap().map(|amount_out| amount_out as u128),
+                Err(_) => Err(QuoteError::Overflow),
+            })
+            .collect()
+    }
+
+    /// Calculates the input needed to receive a given output amount by
+    /// searching over the order book walk of `get_quote_with_amounts_scaled`
+    /// 
+    /// # Arguments
+    /// * `amount_out` - Target output amount
+    /// * `mint_in` - Input token mint
+    /// * `mint_out` - Output token mint
+    /// 
+    /// # Returns
+    /// * Required input amount, `None` if the book isn't deep enough
+    fn get_quote_with_exact_out(
+        &self,
+        amount_out: u128,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> Option<u128> {
+        search_exact_out(amount_out, None, MAX_SWAP_INPUT, |amount_in| {
+            self.get_quote_with_amounts_scaled(amount_in, mint_in, mint_out).unwrap_or(0)
+        })
+    }
+
+    /// Walks the side of the book a market order from `mint_in` takes from
+    /// and sums the levels within `max_impact_bps` of the best price
+    fn get_max_input_within(
+        &self,
+        mint_in: &Pubkey,
+        _mint_out: &Pubkey,
+        max_impact_bps: u64,
+    ) -> Option<u128> {
+        let book = self.book.as_ref()?;
+        let is_bid = *mint_in == self.quote_mint.0;
+        Some(book.capacity(is_bid, max_impact_bps, self.fee_tier) as u128)
+    }
+
+    /// Creates swap instructions for executing a trade
+    /// 
+    /// # Arguments
+    /// * `program` - The Anchor program instance
+    /// * `owner` - The owner's public key
+    /// * `mint_in` - Input token mint
+    /// * `_mint_out` - Output token mint
+    /// 
+    /// # Returns
+    /// * Vector of instructions for executing the swap
+    fn swap_ix(&self, 
+        program: &Program,
+        owner: &Pubkey,
+        mint_in: &Pubkey, 
+        _mint_out: &Pubkey
+    ) -> ArbResult<Vec<Instruction>> {
+        // Determine trade direction
+        let is_bid = *mint_in == self.quote_mint.0;
+        let side = if is_bid { Side::Bid } else { Side::Ask };
+
+        let open_orders = self.open_orders_account(owner)?;
+
+        // Derive token accounts
+        let user_src = derive_token_address(owner, mint_in);
+        let user_dst = derive_token_address(
+            owner, 
+            if is_bid { &self.base_mint.0 } else { &self.quote_mint.0 }
+        );
+
+        // Build swap instruction
+        let swap_ix = program
+            .request()
+            .accounts(tmp_accounts::SerumSwap {
+                market: self.own_address.0,
+                open_orders,
+                request_queue: self.request_queue.0,
+                event_queue: self.event_queue.0,
+                bids: self.bids.0,
+                asks: self.asks.0,
+                base_vault: self.base_vault.0,
+                quote_vault: self.quote_vault.0,
+                vault_signer: self.vault_signer.0,
+                user_src,
+                user_dst,
+                user_owner: *owner,
+                token_program: *TOKEN_PROGRAM_ID,
+                serum_program: *SERUM_PROGRAM_ID,
+            })
+            .args(tmp_instructions::SerumSwap { 
+                side,
+            })
+            .instructions()
+            .map_err(|e| ArbError::Instruction(e.to_string()))?;
+
+        Ok(swap_ix)
+    }
+
+    /// Settles the owner's open orders into its base and quote token
+    /// accounts, cranking the event queue first if asked to
+    ///
+    /// # Arguments
+    /// * `owner` - The owner's public key
+    /// * `consume_events` - Most fill events to consume, none if `None`
+    ///
+    /// # Returns
+    /// * Vector of settle (and consume events) instructions
+    fn settle_ix(&self, owner: &Pubkey, consume_events: Option<u16>) -> ArbResult<Vec<Instruction>> {
+        let open_orders = self.open_orders_account(owner)?;
+        let base_wallet = derive_token_address(owner, &self.base_mint.0);
+        let quote_wallet = derive_token_address(owner, &self.quote_mint.0);
+        let to_ix_err = |e: DexError| ArbError::Instruction(format!("{:?}", e));
+
+        let mut ixs = vec![];
+        // fills only reach the open orders once their events are consumed;
+        // the crank stops at the first event of someone else's account
+        if let Some(limit) = consume_events {
+            ixs.push(
+                dex_instruction::consume_events(
+                    &SERUM_PROGRAM_ID,
+                    vec![&open_orders],
+                    &self.own_address.0,
+                    &self.event_queue.0,
+                    &base_wallet,
+                    &quote_wallet,
+                    limit,
+                )
+                .map_err(to_ix_err)?,
+            );
+        }
+        ixs.push(
+            dex_instruction::settle_funds(
+                &SERUM_PROGRAM_ID,
+                &self.own_address.0,
+                &TOKEN_PROGRAM_ID,
+                &open_orders,
+                owner,
+                &self.base_vault.0,
+                &base_wallet,
+                &self.quote_vault.0,
+                &quote_wallet,
+                None,
+                &self.vault_signer.0,
+            )
+            .map_err(to_ix_err)?,
+        );
+        Ok(ixs)
+    }
+
+    fn get_update_slot(&self) -> Option<u64> {
+        self.update_slot
+    }
+
+    fn set_update_slot(&mut self, slot: u64) {
+        self.update_slot = Some(slot);
+    }
+
+    /// The base and quote mints; the market's fee comes from the owner's
+    /// fee tier, not from the market
+    fn get_param_accounts(&self) -> Vec<Pubkey> {
+        vec![self.base_mint.0, self.quote_mint.0]
+    }
+
+    fn set_param_accounts(&mut self, accounts: &[Option<Account>]) -> ArbResult<Vec<String>> {
+        let mut changes = vec![];
+        if let [base, quote] = accounts {
+            refresh_decimals(&self.base_mint.0, base, &mut self.base_scale, &mut changes)?;
+            refresh_decimals(&self.quote_mint.0, quote, &mut self.quote_scale, &mut changes)?;
+        }
+        Ok(changes)
+    }
+
+    /// The taker fee of the owner's fee tier, what quotes charge
+    fn get_fee_bps(&self) -> Option<f64> {
+        Some(self.fee_tier.taker_tenth_of_bps() as f64 / 10.0)
+    }
+
+    /// Checks if trading is possible between two tokens
+    /// 
+    /// # Arguments
+    /// * `mint_in` - Input token mint
+    /// * `_mint_out` - Output token mint
+    /// 
+    /// # Returns
+    /// * Boolean indicating if trading is possible
+    fn can_trade(&self, 
+        mint_in: &Pubkey,
+        _mint_out: &Pubkey
+    ) -> bool {
+        // Check if there are matching orders
+        let book = match &self.book {
+            Some(book) => book,
+            None => return false,
+        };
+        let is_bid = *mint_in == self.quote_mint.0;
+        if is_bid {
+            !book.asks.is_empty()
+        } else {
+            !book.bids.is_empty()
+        }
+    }
+}
\ No newline at end of file
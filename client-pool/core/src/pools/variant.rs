@@ -0,0 +1,29 @@
+//! Statically dispatched pools
+//!
+//! The search quotes every edge of every cycle it walks, and a quote through
+//! `Box<dyn PoolOperations>` is an indirect call the compiler can't inline.
+//! `PoolVariant` holds any of the concrete pools; its `PoolOperations`
+//! implementation is generated by `enum_dispatch`, a `match` over the
+//! variants that forwards to the concrete method, which can be inlined.
+//! `PoolSet` and the graph hold pools as `PoolVariant`; `clone_box` still
+//! gives a trait object where one is needed.
+
+// the forwarding impl is generated here and names these types
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::{Cluster, Program};
+use enum_dispatch::enum_dispatch;
+use solana_sdk::account::Account;
+use solana_sdk::instruction::Instruction;
+
+use crate::error::{ArbResult, QuoteResult};
+use crate::pool::PoolOperations;
+use crate::pools::{JupiterPool, OrcaPool, RaydiumPool, SerumPool};
+
+#[enum_dispatch(PoolOperations)]
+#[derive(Debug, Clone)]
+pub enum PoolVariant {
+    Orca(OrcaPool),
+    Raydium(RaydiumPool),
+    Serum(SerumPool),
+    Jupiter(JupiterPool),
+}
@@ -0,0 +1,162 @@
+//! Pool registry
+//!
+//! The same market can show up in several pool directories (or twice in
+//! one). While loading, every pool is registered by its address: a pool
+//! whose address is already known is dropped, and if its metadata (mints and
+//! update accounts) differs from the first registration the pair is reported
+//! as a conflict, since one of the files must be wrong. The first pool wins.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::pool::{PoolOperations, PoolType};
+
+/// What identifies a pool's on-chain state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolMetadata {
+    pub tipe: PoolType,
+    pub id: String,
+    pub mints: Vec<Pubkey>,
+    pub update_accounts: Vec<Pubkey>,
+}
+
+impl PoolMetadata {
+    pub fn of(tipe: PoolType, pool: &dyn PoolOperations) -> Self {
+        Self {
+            tipe,
+            id: pool.get_id(),
+            mints: pool.get_mints(),
+            update_accounts: pool.get_update_accounts(),
+        }
+    }
+
+    /// Same market, whichever directory it came from
+    fn same_market(&self, other: &Self) -> bool {
+        self.mints == other.mints && self.update_accounts == other.update_accounts
+    }
+}
+
+/// Two pool files with the same address that disagree
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub address: Pubkey,
+    pub kept: PoolMetadata,
+    pub dropped: PoolMetadata,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pool {} is {:?} {} but also {:?} {}",
+            self.address,
+            self.kept.tipe,
+            self.kept.id,
+            self.dropped.tipe,
+            self.dropped.id
+        )?;
+        if self.kept.mints != self.dropped.mints {
+            write!(f, " (mints {:?} vs {:?})", self.kept.mints, self.dropped.mints)?;
+        } else {
+            write!(f, " (different update accounts)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of `PoolRegistry::register`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Registration {
+    New,
+    /// Same address and metadata as an earlier pool
+    Duplicate,
+    /// Same address, different metadata (see `PoolRegistry::conflicts`)
+    Conflict,
+}
+
+/// Pools seen during a load, by address (see the module docs)
+#[derive(Debug, Default)]
+pub struct PoolRegistry {
+    by_address: HashMap<Pubkey, PoolMetadata>,
+    duplicates: usize,
+    conflicts: Vec<Conflict>,
+}
+
+impl PoolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a pool; only `Registration::New` pools should be kept
+    pub fn register(&mut self, tipe: PoolType, pool: &dyn PoolOperations) -> Registration {
+        let metadata = PoolMetadata::of(tipe, pool);
+        let address = pool.get_address();
+        match self.by_address.get(&address) {
+            None => {
+                self.by_address.insert(address, metadata);
+                Registration::New
+            }
+            Some(kept) if kept.same_market(&metadata) => {
+                self.duplicates += 1;
+                Registration::Duplicate
+            }
+            Some(kept) => {
+                self.conflicts.push(Conflict {
+                    address,
+                    kept: kept.clone(),
+                    dropped: metadata,
+                });
+                Registration::Conflict
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_address.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_address.is_empty()
+    }
+
+    pub fn duplicates(&self) -> usize {
+        self.duplicates
+    }
+
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pool::pool_factory;
+    use crate::test_pools::orca_json;
+
+    #[test]
+    fn duplicates_and_conflicts_are_told_apart() {
+        let (address, a, b, c) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let json = orca_json(&address, &[a, b]);
+        let first = pool_factory(&PoolType::OrcaPoolType, &json).unwrap();
+        let same = pool_factory(&PoolType::OrcaPoolType, &json).unwrap();
+        let other = pool_factory(&PoolType::OrcaPoolType, &orca_json(&address, &[a, c])).unwrap();
+
+        let mut registry = PoolRegistry::new();
+        assert_eq!(registry.register(PoolType::OrcaPoolType, &first), Registration::New);
+        assert_eq!(registry.register(PoolType::OrcaPoolType, &same), Registration::Duplicate);
+        assert_eq!(registry.register(PoolType::OrcaPoolType, &other), Registration::Conflict);
+
+        assert_eq!(registry.len(), 1);
+        assert_eq!(registry.duplicates(), 1);
+        assert_eq!(registry.conflicts()[0].address, address);
+        assert_eq!(first.get_id(), format!("orca:{}", &address.to_string()[..8]));
+    }
+}
@@ -0,0 +1,484 @@
+//! Cycle search over the exchange graph
+//!
+//! Walks every path from a start mint back to itself, quoting each hop with
+//! the previous hop's output. What a cycle is worth (slippage, fees, loan
+//! repayment) and what happens to it is up to the `SearchVisitor`.
+//!
+//! A quote that fails (no reserves, an empty book side, an overflow), panics
+//! or returns nothing for a non-zero input ends that branch of the search
+//! and is reported to the visitor instead of taking the whole search down.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+
+use std::collections::HashSet;
+use std::panic::{self, AssertUnwindSafe};
+
+use serde::{Deserialize, Serialize};
+use tracing::{trace, trace_span};
+
+use crate::amount::ScaledAmount;
+use crate::error::QuoteError;
+use crate::pool::PoolOperations;
+use crate::utils::{PoolGraph, PoolIndex, PoolQuote};
+
+/// Most mints on a path (A -> B -> C -> D); longer routes don't fit a transaction
+pub const MAX_PATH_LEN: usize = 4;
+
+/// A path from the start mint back to itself
+#[derive(Debug, Clone)]
+pub struct Cycle {
+    /// mint indices, starting and ending with the start mint
+    pub path: Vec<usize>,
+    /// pool of every hop
+    pub pools: Vec<PoolQuote>,
+    /// quoted output of the last hop, of the start mint
+    pub amount_out: ScaledAmount,
+}
+
+/// Which pools a cycle may combine; nothing is ruled out by default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteConstraints {
+    /// A pool is used at most once, swapping back through the pool a route
+    /// came through only pays its fee twice
+    #[serde(default)]
+    pub no_repeated_pool: bool,
+    /// A DEX is used at most once
+    #[serde(default)]
+    pub no_repeated_dex: bool,
+    /// Fewest different DEXes in a cycle, 0 and 1 allow every cycle
+    #[serde(default)]
+    pub min_distinct_dexes: usize,
+}
+
+impl RouteConstraints {
+    /// Whether a path through `pools` may swap through `pool` next and still
+    /// close a valid cycle within `hops_after` more hops
+    pub fn allows_hop(&self, pools: &[PoolQuote], pool: &PoolQuote, hops_after: usize) -> bool {
+        if self.no_repeated_pool && pools.iter().any(|p| p.0.get_address() == pool.0.get_address()) {
+            return false;
+        }
+        let name = pool.0.get_name();
+        if self.no_repeated_dex && pools.iter().any(|p| p.0.get_name() == name) {
+            return false;
+        }
+        let mut dexes: HashSet<String> = pools.iter().map(|p| p.0.get_name()).collect();
+        dexes.insert(name);
+        dexes.len() + hops_after >= self.min_distinct_dexes
+    }
+
+    /// Whether a cycle through `pools` is allowed
+    pub fn allows_route(&self, pools: &[PoolQuote]) -> bool {
+        pools
+            .iter()
+            .enumerate()
+            .all(|(i, pool)| self.allows_hop(&pools[..i], pool, pools.len() - i - 1))
+    }
+}
+
+/// Why a pool couldn't quote a hop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuoteFailure {
+    /// The pool said it can't quote the hop
+    Error(QuoteError),
+    /// The quote panicked, with the panic message
+    Panic(String),
+    /// Nothing out for a non-zero input, the pool has no reserves
+    ZeroOutput,
+}
+
+/// What the search asks its caller
+pub trait SearchVisitor {
+    /// Checked before every step, the search ends once it returns true
+    fn stop(&self) -> bool {
+        false
+    }
+
+    /// Whether `pool` may be used for a hop into `dst_mint`
+    fn use_hop(&self, _pool: &PoolQuote, _dst_mint: &Pubkey) -> bool {
+        true
+    }
+
+    /// Most pools tried per edge, in the graph's order (see
+    /// `PoolGraph::rank_pools`); every pool if `None`. Pools `use_hop`
+    /// skips don't count.
+    fn max_pools_per_edge(&self) -> Option<usize> {
+        None
+    }
+
+    /// Whether paths whose first hop goes into mint `dst_mint_idx` are
+    /// searched, see `shard_first_hops`
+    fn use_first_hop(&self, _dst_mint_idx: usize) -> bool {
+        true
+    }
+
+    /// Which pools a cycle may combine, pools it rules out for a hop don't
+    /// count towards `max_pools_per_edge`
+    fn route_constraints(&self) -> RouteConstraints {
+        RouteConstraints::default()
+    }
+
+    /// Called for every cycle back to the start mint
+    fn on_cycle(&mut self, cycle: Cycle);
+
+    /// Called when `pool` failed to quote a hop; the hop is skipped
+    fn on_quote_failure(&mut self, _pool: &PoolQuote, _failure: QuoteFailure) {}
+}
+
+/// The first hops of shard `shard` out of `shards`: the start mint's
+/// neighbours in index order, cut into `shards` ranges of (nearly) the same
+/// size. Every cycle starts with exactly one first hop, so the shards'
+/// searches together visit every cycle, each once.
+pub fn shard_first_hops(
+    graph_edges: &[HashSet<usize>],
+    start_mint_idx: usize,
+    shard: usize,
+    shards: usize,
+) -> HashSet<usize> {
+    let mut neighbours: Vec<usize> = graph_edges[start_mint_idx].iter().copied().collect();
+    neighbours.sort_unstable();
+    let n = neighbours.len();
+    neighbours[shard * n / shards..(shard + 1) * n / shards].iter().copied().collect()
+}
+
+/// Depth-first search over a built graph (see `PoolSet::into_graph`)
+pub struct Searcher<'a> {
+    pub token_mints: &'a [Pubkey],
+    pub graph_edges: &'a [HashSet<usize>],
+    pub graph: &'a PoolGraph,
+}
+
+impl<'a> Searcher<'a> {
+    pub fn new(
+        token_mints: &'a [Pubkey],
+        graph_edges: &'a [HashSet<usize>],
+        graph: &'a PoolGraph,
+    ) -> Self {
+        Self {
+            token_mints,
+            graph_edges,
+            graph,
+        }
+    }
+
+    /// Visits every cycle through `start_mint_idx` swapping `amount` (of the
+    /// start mint) in
+    pub fn search<V: SearchVisitor + ?Sized>(
+        &self,
+        start_mint_idx: usize,
+        amount: ScaledAmount,
+        visitor: &mut V,
+    ) {
+        assert_eq!(amount.mint, self.token_mints[start_mint_idx], "search amount of another mint");
+        self.walk(start_mint_idx, amount, vec![start_mint_idx], vec![], visitor);
+    }
+
+    fn walk<V: SearchVisitor + ?Sized>(
+        &self,
+        start_mint_idx: usize,
+        curr_balance: ScaledAmount,
+        path: Vec<usize>,
+        pool_path: Vec<PoolQuote>,
+        visitor: &mut V,
+    ) {
+        if visitor.stop() || path.len() == MAX_PATH_LEN {
+            return;
+        }
+        let src_curr = path[path.len() - 1]; // last mint
+        let constraints = visitor.route_constraints();
+
+        for dst_mint_idx in self.graph_edges[src_curr].iter() {
+            let dst_mint_idx = *dst_mint_idx;
+            if path.contains(&dst_mint_idx) && dst_mint_idx != start_mint_idx {
+                continue;
+            }
+            if path.len() == 1 && !visitor.use_first_hop(dst_mint_idx) {
+                continue;
+            }
+            let pools = match self
+                .graph
+                .0
+                .get(&PoolIndex(src_curr))
+                .and_then(|edges| edges.0.get(&PoolIndex(dst_mint_idx)))
+            {
+                Some(pools) => pools,
+                None => continue,
+            };
+            let dst_mint = self.token_mints[dst_mint_idx];
+            let max_pools = visitor.max_pools_per_edge().unwrap_or(usize::MAX);
+            // a hop back to the start closes the cycle
+            let hops_after = match dst_mint_idx == start_mint_idx {
+                true => 0,
+                false => MAX_PATH_LEN - 1 - path.len(),
+            };
+            let mut tried = 0;
+
+            for pool in pools {
+                if tried == max_pools {
+                    break;
+                }
+                if !constraints.allows_hop(&pool_path, pool, hops_after) {
+                    continue;
+                }
+                if !visitor.use_hop(pool, &dst_mint) {
+                    continue;
+                }
+                tried += 1;
+                let quote = trace_span!("quote", pool = %pool.0.get_id(), amount_in = %curr_balance.amount);
+                let quoted = quote.in_scope(|| {
+                    let amount_out =
+                        panic::catch_unwind(AssertUnwindSafe(|| pool.0.quote(&curr_balance, &dst_mint)));
+                    trace!(amount_out = ?amount_out.as_ref().ok(), "quoted");
+                    amount_out
+                });
+                let new_balance = match quoted {
+                    Ok(Ok(out)) if out.is_zero() && !curr_balance.is_zero() => {
+                        visitor.on_quote_failure(pool, QuoteFailure::ZeroOutput);
+                        continue;
+                    }
+                    Ok(Ok(amount_out)) => amount_out,
+                    Ok(Err(error)) => {
+                        visitor.on_quote_failure(pool, QuoteFailure::Error(error));
+                        continue;
+                    }
+                    Err(panic) => {
+                        let message = panic
+                            .downcast_ref::<&str>()
+                            .map(|s| s.to_string())
+                            .or_else(|| panic.downcast_ref::<String>().cloned())
+                            .unwrap_or_default();
+                        visitor.on_quote_failure(pool, QuoteFailure::Panic(message));
+                        continue;
+                    }
+                };
+
+                let mut new_path = path.clone();
+                new_path.push(dst_mint_idx);
+                let mut new_pool_path = pool_path.clone();
+                new_pool_path.push(pool.clone()); // clone the pointer
+
+                if dst_mint_idx == start_mint_idx {
+                    visitor.on_cycle(Cycle {
+                        path: new_path,
+                        pools: new_pool_path,
+                        amount_out: new_balance,
+                    });
+                } else {
+                    // ... search deeper
+                    self.walk(start_mint_idx, new_balance, new_path, new_pool_path, visitor);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::PoolSet;
+    use crate::pool::PoolType;
+    use crate::pools::OrcaPool;
+    use crate::test_pools;
+
+    fn orca_pool(a: &Pubkey, b: &Pubkey) -> OrcaPool {
+        test_pools::orca_pool(&[*a, *b], &[1_000_000_000, 1_000_000_000])
+    }
+
+    #[derive(Default)]
+    struct Collect {
+        cycles: Vec<Cycle>,
+        failures: Vec<(String, QuoteFailure)>,
+        max_pools_per_edge: Option<usize>,
+        first_hops: Option<HashSet<usize>>,
+        constraints: RouteConstraints,
+    }
+
+    impl SearchVisitor for Collect {
+        fn max_pools_per_edge(&self) -> Option<usize> {
+            self.max_pools_per_edge
+        }
+
+        fn use_first_hop(&self, dst_mint_idx: usize) -> bool {
+            self.first_hops.as_ref().map_or(true, |hops| hops.contains(&dst_mint_idx))
+        }
+
+        fn route_constraints(&self) -> RouteConstraints {
+            self.constraints
+        }
+
+        fn on_cycle(&mut self, cycle: Cycle) {
+            self.cycles.push(cycle);
+        }
+
+        fn on_quote_failure(&mut self, pool: &PoolQuote, failure: QuoteFailure) {
+            self.failures.push((pool.0.get_id(), failure));
+        }
+    }
+
+    #[test]
+    fn finds_every_cycle_through_the_start_mint() {
+        let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut set = PoolSet::new();
+        for (x, y) in [(&a, &b), (&b, &c), (&c, &a)] {
+            assert!(set.add_pool(PoolType::OrcaPoolType, orca_pool(x, y).into()));
+        }
+        let start = set.mint2idx[&a];
+        let (token_mints, graph_edges, graph) = set.into_graph();
+        let amount = ScaledAmount::new(token_mints[start], 1_000_000);
+
+        let mut visitor = Collect::default();
+        Searcher::new(&token_mints, &graph_edges, &graph).search(start, amount, &mut visitor);
+
+        // a-b-a, a-c-a, a-b-c-a, a-c-b-a
+        assert_eq!(visitor.cycles.len(), 4);
+        for cycle in visitor.cycles.iter() {
+            assert_eq!(cycle.path.first(), Some(&start));
+            assert_eq!(cycle.path.last(), Some(&start));
+            assert_eq!(cycle.pools.len(), cycle.path.len() - 1);
+            // fees make every round trip lose
+            assert!(cycle.amount_out.amount < 1_000_000);
+        }
+    }
+
+    #[test]
+    fn empty_pools_are_skipped_and_reported() {
+        let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut set = PoolSet::new();
+        let mut empty = orca_pool(&a, &b);
+        empty.pool_amounts = [(a.to_string(), 0), (b.to_string(), 0)].into_iter().collect();
+        let empty_id = empty.get_id();
+        assert!(set.add_pool(PoolType::OrcaPoolType, empty.into()));
+        for (x, y) in [(&b, &c), (&c, &a)] {
+            assert!(set.add_pool(PoolType::OrcaPoolType, orca_pool(x, y).into()));
+        }
+        let start = set.mint2idx[&a];
+        let (token_mints, graph_edges, graph) = set.into_graph();
+        let amount = ScaledAmount::new(token_mints[start], 1_000_000);
+
+        let mut visitor = Collect::default();
+        Searcher::new(&token_mints, &graph_edges, &graph).search(start, amount, &mut visitor);
+
+        // only a-c-a avoids the empty pool, which failed a->b and b->a
+        assert_eq!(visitor.cycles.len(), 1);
+        assert_eq!(visitor.failures.len(), 2);
+        assert!(visitor
+            .failures
+            .iter()
+            .all(|failure| *failure == (empty_id.clone(), QuoteFailure::Error(QuoteError::ZeroReserves))));
+    }
+
+    #[test]
+    fn only_the_best_ranked_pools_of_an_edge_are_tried() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut set = PoolSet::new();
+        // one pool pays more b for a, the other more a for b
+        let mut cheap_b = orca_pool(&a, &b);
+        cheap_b.pool_amounts = [(a.to_string(), 1_000_000_000), (b.to_string(), 2_000_000_000)]
+            .into_iter()
+            .collect();
+        let cheap_b_id = cheap_b.get_id();
+        assert!(set.add_pool(PoolType::OrcaPoolType, cheap_b.into()));
+        assert!(set.add_pool(PoolType::OrcaPoolType, orca_pool(&a, &b).into()));
+        let start = set.mint2idx[&a];
+        let (token_mints, graph_edges, mut graph) = set.into_graph();
+        let amount = ScaledAmount::new(token_mints[start], 1_000_000);
+
+        let mut every = Collect::default();
+        Searcher::new(&token_mints, &graph_edges, &graph).search(start, amount, &mut every);
+        assert_eq!(every.cycles.len(), 4);
+
+        graph.rank_pools(&token_mints);
+        let mut best = Collect {
+            max_pools_per_edge: Some(1),
+            ..Collect::default()
+        };
+        Searcher::new(&token_mints, &graph_edges, &graph).search(start, amount, &mut best);
+        assert_eq!(best.cycles.len(), 1);
+        let pools: Vec<String> = best.cycles[0].pools.iter().map(|p| p.0.get_id()).collect();
+        assert_eq!(pools[0], cheap_b_id);
+        assert_ne!(pools[1], cheap_b_id);
+        // the best pair of pools is also the best cycle of all
+        assert!(every.cycles.iter().all(|c| c.amount_out <= best.cycles[0].amount_out));
+    }
+
+    #[test]
+    fn constraints_rule_out_repeated_pools_and_dexes() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut set = PoolSet::new();
+        for _ in 0..2 {
+            assert!(set.add_pool(PoolType::OrcaPoolType, orca_pool(&a, &b).into()));
+        }
+        let start = set.mint2idx[&a];
+        let (token_mints, graph_edges, graph) = set.into_graph();
+        let amount = ScaledAmount::new(token_mints[start], 1_000_000);
+        let searcher = Searcher::new(&token_mints, &graph_edges, &graph);
+        let search = |constraints: RouteConstraints| {
+            let mut visitor = Collect {
+                constraints,
+                ..Collect::default()
+            };
+            searcher.search(start, amount, &mut visitor);
+            visitor.cycles
+        };
+
+        // out and back through either pool
+        assert_eq!(search(RouteConstraints::default()).len(), 4);
+        let distinct = search(RouteConstraints {
+            no_repeated_pool: true,
+            ..RouteConstraints::default()
+        });
+        assert_eq!(distinct.len(), 2);
+        for cycle in distinct.iter() {
+            assert_ne!(cycle.pools[0].0.get_address(), cycle.pools[1].0.get_address());
+            assert!(RouteConstraints::default().allows_route(&cycle.pools));
+        }
+        // both pools are orca pools
+        let one_dex = [
+            RouteConstraints {
+                no_repeated_dex: true,
+                ..RouteConstraints::default()
+            },
+            RouteConstraints {
+                min_distinct_dexes: 2,
+                ..RouteConstraints::default()
+            },
+        ];
+        for constraints in one_dex {
+            assert!(search(constraints).is_empty());
+            assert!(!constraints.allows_route(&distinct[0].pools));
+        }
+    }
+
+    #[test]
+    fn shards_split_the_cycles_by_first_hop() {
+        let mints: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let mut set = PoolSet::new();
+        for (i, x) in mints.iter().enumerate() {
+            for y in mints[i + 1..].iter() {
+                assert!(set.add_pool(PoolType::OrcaPoolType, orca_pool(x, y).into()));
+            }
+        }
+        let start = set.mint2idx[&mints[0]];
+        let (token_mints, graph_edges, graph) = set.into_graph();
+        let amount = ScaledAmount::new(token_mints[start], 1_000_000);
+        let searcher = Searcher::new(&token_mints, &graph_edges, &graph);
+
+        let mut every = Collect::default();
+        searcher.search(start, amount, &mut every);
+        let mut sharded: Vec<Vec<usize>> = vec![];
+        for shard in 0..2 {
+            let first_hops = shard_first_hops(&graph_edges, start, shard, 2);
+            assert!(!first_hops.is_empty());
+            let mut visitor = Collect {
+                first_hops: Some(first_hops.clone()),
+                ..Collect::default()
+            };
+            searcher.search(start, amount, &mut visitor);
+            assert!(visitor.cycles.iter().all(|c| first_hops.contains(&c.path[1])));
+            sharded.extend(visitor.cycles.into_iter().map(|c| c.path));
+        }
+        let mut every: Vec<Vec<usize>> = every.cycles.into_iter().map(|c| c.path).collect();
+        every.sort();
+        sharded.sort();
+        assert_eq!(sharded, every);
+    }
+}
@@ -0,0 +1,770 @@
+//! Account data decoder registry
+//!
+//! Maps an account's owning program + discriminator/size to a typed, versioned
+//! decoder so pool adapters never unpack raw account bytes themselves.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use arrayref::array_ref;
+use sha2::{Digest, Sha256};
+use solana_sdk::account::Account;
+use thiserror::Error;
+
+use crate::constants::*;
+use crate::serialize::token::{try_unpack_token_account, TokenAccount, TOKEN_ACCOUNT_LEN};
+
+lazy_static! {
+    /// Registry with every layout the bot knows about
+    pub static ref DECODERS: DecoderRegistry = DecoderRegistry::default();
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum DecodeError {
+    #[error("no decoder registered for account owned by {owner} ({len} bytes)")]
+    UnknownLayout { owner: Pubkey, len: usize },
+    #[error("{layout} v{version}: expected {expected} bytes, got {got}")]
+    TooShort {
+        layout: &'static str,
+        version: u8,
+        expected: usize,
+        got: usize,
+    },
+    #[error("{layout} v{version}: invalid account data")]
+    InvalidData { layout: &'static str, version: u8 },
+    #[error("expected a {expected} account, decoded {got}")]
+    UnexpectedLayout {
+        expected: &'static str,
+        got: &'static str,
+    },
+}
+
+/// Subset of the Orca Whirlpool state used for quoting
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhirlpoolState {
+    pub tick_spacing: u16,
+    pub fee_rate: u16,
+    pub protocol_fee_rate: u16,
+    pub liquidity: u128,
+    pub sqrt_price: u128,
+    pub tick_current_index: i32,
+    pub token_mint_a: Pubkey,
+    pub token_vault_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_vault_b: Pubkey,
+}
+
+/// Subset of the Raydium AMM v4 `AmmInfo` state used for quoting
+#[derive(Debug, Clone, PartialEq)]
+pub struct RaydiumAmmInfo {
+    pub status: u64,
+    pub nonce: u64,
+    pub base_decimal: u64,
+    pub quote_decimal: u64,
+    pub trade_fee_numerator: u64,
+    pub trade_fee_denominator: u64,
+    pub swap_fee_numerator: u64,
+    pub swap_fee_denominator: u64,
+    pub base_need_take_pnl: u64,
+    pub quote_need_take_pnl: u64,
+    pub base_vault: Pubkey,
+    pub quote_vault: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub lp_mint: Pubkey,
+    pub open_orders: Pubkey,
+    pub market_id: Pubkey,
+    pub market_program_id: Pubkey,
+    pub target_orders: Pubkey,
+}
+
+/// Subset of the Orca token-swap (v2) state, everything a pool file needs
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrcaSwapState {
+    pub nonce: u8,
+    pub token_a: Pubkey,
+    pub token_b: Pubkey,
+    pub pool_mint: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub fee_account: Pubkey,
+    pub trade_fee_numerator: u64,
+    pub trade_fee_denominator: u64,
+    pub owner_trade_fee_numerator: u64,
+    pub owner_trade_fee_denominator: u64,
+    /// 0 = constant product, 2 = stable
+    pub curve_type: u8,
+    /// Amplification coefficient of stable curves, 0 otherwise
+    pub amp: u64,
+}
+
+/// Subset of an spl-token mint
+#[derive(Debug, Clone, PartialEq)]
+pub struct MintState {
+    pub supply: u64,
+    pub decimals: u8,
+}
+
+/// Subset of the Serum market state (without the order book slabs)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SerumMarketState {
+    pub own_address: Pubkey,
+    pub vault_signer_nonce: u64,
+    pub coin_mint: Pubkey,
+    pub pc_mint: Pubkey,
+    pub coin_vault: Pubkey,
+    pub pc_vault: Pubkey,
+    pub request_queue: Pubkey,
+    pub event_queue: Pubkey,
+    pub bids: Pubkey,
+    pub asks: Pubkey,
+    pub coin_lot_size: u64,
+    pub pc_lot_size: u64,
+    pub fee_rate_bps: u64,
+}
+
+/// Balances of a Serum (or OpenBook) open orders account
+#[derive(Debug, Clone, PartialEq)]
+pub struct SerumOpenOrders {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub native_coin_free: u64,
+    /// Coin held by the account, free or locked in orders
+    pub native_coin_total: u64,
+    pub native_pc_free: u64,
+    /// Price currency held by the account, free or locked in orders
+    pub native_pc_total: u64,
+}
+
+/// Aggregate price of a Pyth v2 price account
+#[derive(Debug, Clone, PartialEq)]
+pub struct PythPrice {
+    pub expo: i32,
+    pub price: i64,
+    pub conf: u64,
+    /// 1 = trading, anything else means the price shouldn't be used
+    pub status: u32,
+    pub publish_slot: u64,
+}
+
+impl PythPrice {
+    pub const STATUS_TRADING: u32 = 1;
+
+    /// Price as a float (`price * 10^expo`)
+    pub fn value(&self) -> f64 {
+        self.price as f64 * 10f64.powi(self.expo)
+    }
+
+    /// Confidence interval as a float
+    pub fn confidence(&self) -> f64 {
+        self.conf as f64 * 10f64.powi(self.expo)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodedAccount {
+    Token(TokenAccount),
+    Token2022(TokenAccount),
+    Mint(MintState),
+    Whirlpool(WhirlpoolState),
+    OrcaSwap(OrcaSwapState),
+    RaydiumAmm(RaydiumAmmInfo),
+    SerumMarket(SerumMarketState),
+    SerumOpenOrders(SerumOpenOrders),
+    PythPrice(PythPrice),
+}
+
+/// A decoded account tagged with the layout + version that produced it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decoded {
+    pub layout: &'static str,
+    pub version: u8,
+    pub account: DecodedAccount,
+}
+
+/// How a layout recognises its accounts (besides the owning program)
+#[derive(Debug, Clone)]
+pub enum SizeRule {
+    Exact(usize),
+    AtLeast(usize),
+}
+
+impl SizeRule {
+    fn matches(&self, len: usize) -> bool {
+        match self {
+            SizeRule::Exact(n) => len == *n,
+            SizeRule::AtLeast(n) => len >= *n,
+        }
+    }
+}
+
+pub type DecodeFn = fn(&AccountLayout, &[u8]) -> Result<DecodedAccount, DecodeError>;
+
+/// A single registered account layout
+pub struct AccountLayout {
+    pub name: &'static str,
+    pub version: u8,
+    pub program_id: Pubkey,
+    /// Leading bytes the account data must start with (anchor discriminator, magic, ...)
+    pub discriminator: Option<Vec<u8>>,
+    pub size: SizeRule,
+    pub decode: DecodeFn,
+}
+
+impl AccountLayout {
+    fn matches(&self, owner: &Pubkey, data: &[u8]) -> bool {
+        if self.program_id != *owner || !self.size.matches(data.len()) {
+            return false;
+        }
+        match &self.discriminator {
+            Some(disc) => data.starts_with(disc),
+            None => true,
+        }
+    }
+
+    fn too_short(&self, expected: usize, got: usize) -> DecodeError {
+        DecodeError::TooShort {
+            layout: self.name,
+            version: self.version,
+            expected,
+            got,
+        }
+    }
+
+    fn invalid(&self) -> DecodeError {
+        DecodeError::InvalidData {
+            layout: self.name,
+            version: self.version,
+        }
+    }
+}
+
+pub struct DecoderRegistry {
+    layouts: Vec<AccountLayout>,
+}
+
+impl DecoderRegistry {
+    pub fn new() -> Self {
+        Self { layouts: vec![] }
+    }
+
+    /// Registers a layout. Layouts registered later take precedence so a newer
+    /// version of a layout can shadow an older one with the same matcher.
+    pub fn register(&mut self, layout: AccountLayout) {
+        self.layouts.push(layout);
+    }
+
+    pub fn layouts(&self) -> &[AccountLayout] {
+        &self.layouts
+    }
+
+    /// Decodes raw account data owned by `owner`
+    pub fn decode_data(&self, owner: &Pubkey, data: &[u8]) -> Result<Decoded, DecodeError> {
+        let layout = self
+            .layouts
+            .iter()
+            .rev()
+            .find(|l| l.matches(owner, data))
+            .ok_or(DecodeError::UnknownLayout {
+                owner: *owner,
+                len: data.len(),
+            })?;
+        let account = (layout.decode)(layout, data)?;
+        Ok(Decoded {
+            layout: layout.name,
+            version: layout.version,
+            account,
+        })
+    }
+
+    pub fn decode(&self, account: &Account) -> Result<Decoded, DecodeError> {
+        self.decode_data(&account.owner, &account.data)
+    }
+
+    /// Decodes an spl-token or token-2022 account
+    /// Decodes a Pyth price account
+    pub fn pyth_price(&self, account: &Account) -> Result<PythPrice, DecodeError> {
+        let decoded = self.decode(account)?;
+        match decoded.account {
+            DecodedAccount::PythPrice(price) => Ok(price),
+            _ => Err(DecodeError::UnexpectedLayout {
+                expected: "pyth price",
+                got: decoded.layout,
+            }),
+        }
+    }
+
+    /// Decodes an spl-token mint
+    pub fn mint(&self, account: &Account) -> Result<MintState, DecodeError> {
+        let decoded = self.decode(account)?;
+        match decoded.account {
+            DecodedAccount::Mint(mint) => Ok(mint),
+            _ => Err(DecodeError::UnexpectedLayout {
+                expected: "mint",
+                got: decoded.layout,
+            }),
+        }
+    }
+
+    pub fn token_account(&self, account: &Account) -> Result<TokenAccount, DecodeError> {
+        let decoded = self.decode(account)?;
+        match decoded.account {
+            DecodedAccount::Token(acc) | DecodedAccount::Token2022(acc) => Ok(acc),
+            _ => Err(DecodeError::UnexpectedLayout {
+                expected: "token account",
+                got: decoded.layout,
+            }),
+        }
+    }
+}
+
+impl Default for DecoderRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register(AccountLayout {
+            name: "spl-token account",
+            version: 1,
+            program_id: *TOKEN_PROGRAM_ID,
+            discriminator: None,
+            size: SizeRule::Exact(TOKEN_ACCOUNT_LEN),
+            decode: decode_token_account,
+        });
+        registry.register(AccountLayout {
+            name: "token-2022 account",
+            version: 1,
+            program_id: *TOKEN_2022_PROGRAM_ID,
+            discriminator: None,
+            size: SizeRule::AtLeast(TOKEN_ACCOUNT_LEN),
+            decode: decode_token_2022_account,
+        });
+        registry.register(AccountLayout {
+            name: "spl-token mint",
+            version: 1,
+            program_id: *TOKEN_PROGRAM_ID,
+            discriminator: None,
+            size: SizeRule::Exact(MINT_LEN),
+            decode: decode_mint,
+        });
+        registry.register(AccountLayout {
+            name: "whirlpool",
+            version: 1,
+            program_id: *WHIRLPOOL_PROGRAM_ID,
+            discriminator: Some(anchor_discriminator("Whirlpool").to_vec()),
+            size: SizeRule::Exact(WHIRLPOOL_LEN),
+            decode: decode_whirlpool,
+        });
+        registry.register(AccountLayout {
+            name: "orca token swap",
+            version: 2,
+            program_id: *ORCA_PROGRAM_ID,
+            discriminator: Some(vec![ORCA_SWAP_VERSION]),
+            size: SizeRule::Exact(ORCA_SWAP_LEN),
+            decode: decode_orca_swap,
+        });
+        registry.register(AccountLayout {
+            name: "raydium amm v4",
+            version: 4,
+            program_id: *RAYDIUM_PROGRAM_ID,
+            discriminator: None,
+            size: SizeRule::Exact(RAYDIUM_AMM_V4_LEN),
+            decode: decode_raydium_amm_v4,
+        });
+        registry.register(AccountLayout {
+            name: "serum market",
+            version: 3,
+            program_id: *SERUM_PROGRAM_ID,
+            discriminator: Some(b"serum".to_vec()),
+            size: SizeRule::AtLeast(SERUM_MARKET_LEN),
+            decode: decode_serum_market,
+        });
+        // after the market, whose size rule matches open orders accounts too
+        for program_id in [*SERUM_PROGRAM_ID, *OPENBOOK_PROGRAM_ID] {
+            registry.register(AccountLayout {
+                name: "serum open orders",
+                version: 3,
+                program_id,
+                discriminator: Some(b"serum".to_vec()),
+                size: SizeRule::Exact(SERUM_OPEN_ORDERS_LEN),
+                decode: decode_serum_open_orders,
+            });
+        }
+        registry.register(AccountLayout {
+            name: "pyth price",
+            version: 2,
+            program_id: *PYTH_PROGRAM_ID,
+            discriminator: Some(pyth_price_discriminator()),
+            size: SizeRule::AtLeast(PYTH_PRICE_MIN_LEN),
+            decode: decode_pyth_price,
+        });
+        registry
+    }
+}
+
+/// Anchor account discriminator: sha256("account:<name>")[..8]
+pub fn anchor_discriminator(name: &str) -> [u8; 8] {
+    let hash = Sha256::digest(format!("account:{}", name).as_bytes());
+    let mut disc = [0u8; 8];
+    disc.copy_from_slice(&hash[..8]);
+    disc
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(*array_ref![data, offset, 2])
+}
+
+fn read_i32(data: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes(*array_ref![data, offset, 4])
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(*array_ref![data, offset, 8])
+}
+
+fn read_u128(data: &[u8], offset: usize) -> u128 {
+    u128::from_le_bytes(*array_ref![data, offset, 16])
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    Pubkey::new_from_array(*array_ref![data, offset, 32])
+}
+
+fn decode_token_account(layout: &AccountLayout, data: &[u8]) -> Result<DecodedAccount, DecodeError> {
+    let acc = try_unpack_token_account(data).map_err(|_| layout.invalid())?;
+    Ok(DecodedAccount::Token(acc))
+}
+
+/// Account type byte token-2022 appends after the base layout
+const TOKEN_2022_ACCOUNT_TYPE: u8 = 2;
+
+fn decode_token_2022_account(
+    layout: &AccountLayout,
+    data: &[u8],
+) -> Result<DecodedAccount, DecodeError> {
+    if data.len() > TOKEN_ACCOUNT_LEN && data[TOKEN_ACCOUNT_LEN] != TOKEN_2022_ACCOUNT_TYPE {
+        return Err(layout.invalid());
+    }
+    let acc = try_unpack_token_account(data).map_err(|_| layout.invalid())?;
+    Ok(DecodedAccount::Token2022(acc))
+}
+
+/// Length of an spl-token mint
+pub const MINT_LEN: usize = 82;
+
+fn decode_mint(layout: &AccountLayout, data: &[u8]) -> Result<DecodedAccount, DecodeError> {
+    // mint_authority (36), supply, decimals, is_initialized, freeze_authority (36)
+    if data[45] != 1 {
+        return Err(layout.invalid());
+    }
+    Ok(DecodedAccount::Mint(MintState {
+        supply: read_u64(data, 36),
+        decimals: data[44],
+    }))
+}
+
+const WHIRLPOOL_LEN: usize = 653;
+
+fn decode_whirlpool(layout: &AccountLayout, data: &[u8]) -> Result<DecodedAccount, DecodeError> {
+    if data.len() < WHIRLPOOL_LEN {
+        return Err(layout.too_short(WHIRLPOOL_LEN, data.len()));
+    }
+    Ok(DecodedAccount::Whirlpool(WhirlpoolState {
+        tick_spacing: read_u16(data, 41),
+        fee_rate: read_u16(data, 45),
+        protocol_fee_rate: read_u16(data, 47),
+        liquidity: read_u128(data, 49),
+        sqrt_price: read_u128(data, 65),
+        tick_current_index: read_i32(data, 81),
+        token_mint_a: read_pubkey(data, 101),
+        token_vault_a: read_pubkey(data, 133),
+        token_mint_b: read_pubkey(data, 181),
+        token_vault_b: read_pubkey(data, 213),
+    }))
+}
+
+/// Version byte the token-swap program writes first
+const ORCA_SWAP_VERSION: u8 = 1;
+/// version, is_initialized, nonce, 7 pubkeys, 8 fee u64s, curve type + 32 bytes of curve params
+pub const ORCA_SWAP_LEN: usize = 324;
+
+fn decode_orca_swap(layout: &AccountLayout, data: &[u8]) -> Result<DecodedAccount, DecodeError> {
+    if data[1] != 1 {
+        return Err(layout.invalid());
+    }
+    let curve_type = data[291];
+    Ok(DecodedAccount::OrcaSwap(OrcaSwapState {
+        nonce: data[2],
+        token_a: read_pubkey(data, 35),
+        token_b: read_pubkey(data, 67),
+        pool_mint: read_pubkey(data, 99),
+        mint_a: read_pubkey(data, 131),
+        mint_b: read_pubkey(data, 163),
+        fee_account: read_pubkey(data, 195),
+        trade_fee_numerator: read_u64(data, 227),
+        trade_fee_denominator: read_u64(data, 235),
+        owner_trade_fee_numerator: read_u64(data, 243),
+        owner_trade_fee_denominator: read_u64(data, 251),
+        curve_type,
+        amp: if curve_type == 2 { read_u64(data, 292) } else { 0 },
+    }))
+}
+
+pub const RAYDIUM_AMM_V4_LEN: usize = 752;
+
+fn decode_raydium_amm_v4(
+    layout: &AccountLayout,
+    data: &[u8],
+) -> Result<DecodedAccount, DecodeError> {
+    if data.len() < RAYDIUM_AMM_V4_LEN {
+        return Err(layout.too_short(RAYDIUM_AMM_V4_LEN, data.len()));
+    }
+    // the state starts with 32 u64 fields, followed by swap accounting (80 bytes)
+    // and then the pubkeys
+    let u64_field = |idx: usize| read_u64(data, idx * 8);
+    Ok(DecodedAccount::RaydiumAmm(RaydiumAmmInfo {
+        status: u64_field(0),
+        nonce: u64_field(1),
+        base_decimal: u64_field(4),
+        quote_decimal: u64_field(5),
+        trade_fee_numerator: u64_field(18),
+        trade_fee_denominator: u64_field(19),
+        swap_fee_numerator: u64_field(22),
+        swap_fee_denominator: u64_field(23),
+        base_need_take_pnl: u64_field(24),
+        quote_need_take_pnl: u64_field(25),
+        base_vault: read_pubkey(data, 336),
+        quote_vault: read_pubkey(data, 368),
+        base_mint: read_pubkey(data, 400),
+        quote_mint: read_pubkey(data, 432),
+        lp_mint: read_pubkey(data, 464),
+        open_orders: read_pubkey(data, 496),
+        market_id: read_pubkey(data, 528),
+        market_program_id: read_pubkey(data, 560),
+        target_orders: read_pubkey(data, 592),
+    }))
+}
+
+/// 5 bytes "serum" + 47 u64 fields + 7 bytes "padding"
+pub const SERUM_MARKET_LEN: usize = 388;
+
+fn decode_serum_market(layout: &AccountLayout, data: &[u8]) -> Result<DecodedAccount, DecodeError> {
+    if data.len() < SERUM_MARKET_LEN {
+        return Err(layout.too_short(SERUM_MARKET_LEN, data.len()));
+    }
+    Ok(DecodedAccount::SerumMarket(SerumMarketState {
+        own_address: read_pubkey(data, 13),
+        vault_signer_nonce: read_u64(data, 45),
+        coin_mint: read_pubkey(data, 53),
+        pc_mint: read_pubkey(data, 85),
+        coin_vault: read_pubkey(data, 117),
+        pc_vault: read_pubkey(data, 165),
+        request_queue: read_pubkey(data, 221),
+        event_queue: read_pubkey(data, 253),
+        bids: read_pubkey(data, 285),
+        asks: read_pubkey(data, 317),
+        coin_lot_size: read_u64(data, 349),
+        pc_lot_size: read_u64(data, 357),
+        fee_rate_bps: read_u64(data, 365),
+    }))
+}
+
+/// 5 bytes "serum" + flags, market, owner, 4 balances, 2 u128 bit sets, 128
+/// u128 orders, 128 u64 client ids, referrer rebates + 7 bytes "padding"
+pub const SERUM_OPEN_ORDERS_LEN: usize = 3228;
+/// `Initialized | OpenOrders` account flags
+const SERUM_OPEN_ORDERS_FLAGS: u64 = 1 | 4;
+
+fn decode_serum_open_orders(
+    layout: &AccountLayout,
+    data: &[u8],
+) -> Result<DecodedAccount, DecodeError> {
+    if read_u64(data, 5) & SERUM_OPEN_ORDERS_FLAGS != SERUM_OPEN_ORDERS_FLAGS {
+        return Err(layout.invalid());
+    }
+    Ok(DecodedAccount::SerumOpenOrders(SerumOpenOrders {
+        market: read_pubkey(data, 13),
+        owner: read_pubkey(data, 45),
+        native_coin_free: read_u64(data, 77),
+        native_coin_total: read_u64(data, 85),
+        native_pc_free: read_u64(data, 93),
+        native_pc_total: read_u64(data, 101),
+    }))
+}
+
+/// magic, version and account type (3 = price) of a Pyth v2 price account
+const PYTH_MAGIC: u32 = 0xa1b2c3d4;
+const PYTH_VERSION: u32 = 2;
+const PYTH_ACCOUNT_TYPE_PRICE: u32 = 3;
+/// Everything up to and including the aggregate price's publish slot
+const PYTH_PRICE_MIN_LEN: usize = 240;
+
+fn pyth_price_discriminator() -> Vec<u8> {
+    [PYTH_MAGIC, PYTH_VERSION, PYTH_ACCOUNT_TYPE_PRICE]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect()
+}
+
+fn decode_pyth_price(layout: &AccountLayout, data: &[u8]) -> Result<DecodedAccount, DecodeError> {
+    if data.len() < PYTH_PRICE_MIN_LEN {
+        return Err(layout.too_short(PYTH_PRICE_MIN_LEN, data.len()));
+    }
+    Ok(DecodedAccount::PythPrice(PythPrice {
+        expo: read_i32(data, 20),
+        price: read_u64(data, 208) as i64,
+        conf: read_u64(data, 216),
+        status: u32::from_le_bytes(*array_ref![data, 224, 4]),
+        publish_slot: read_u64(data, 232),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_account_data(amount: u64) -> Vec<u8> {
+        let mut data = vec![0u8; TOKEN_ACCOUNT_LEN];
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+        data[108] = 1; // initialized
+        data
+    }
+
+    #[test]
+    fn decodes_token_accounts() {
+        let registry = DecoderRegistry::default();
+        let decoded = registry
+            .decode_data(&TOKEN_PROGRAM_ID, &token_account_data(42))
+            .unwrap();
+        assert_eq!(decoded.layout, "spl-token account");
+        match decoded.account {
+            DecodedAccount::Token(acc) => assert_eq!(acc.amount, 42),
+            other => panic!("unexpected account: {:?}", other),
+        }
+
+        let mut data = token_account_data(7);
+        data.push(TOKEN_2022_ACCOUNT_TYPE);
+        data.extend_from_slice(&[0u8; 16]);
+        let decoded = registry.decode_data(&TOKEN_2022_PROGRAM_ID, &data).unwrap();
+        assert_eq!(decoded.account, {
+            let acc = try_unpack_token_account(&data).unwrap();
+            DecodedAccount::Token2022(acc)
+        });
+    }
+
+    #[test]
+    fn rejects_unknown_layouts() {
+        let registry = DecoderRegistry::default();
+        let err = registry
+            .decode_data(&TOKEN_PROGRAM_ID, &[0u8; 100])
+            .unwrap_err();
+        assert_eq!(
+            err,
+            DecodeError::UnknownLayout {
+                owner: *TOKEN_PROGRAM_ID,
+                len: 100
+            }
+        );
+    }
+
+    #[test]
+    fn later_versions_shadow_earlier_ones() {
+        let mut registry = DecoderRegistry::default();
+        registry.register(AccountLayout {
+            name: "spl-token account",
+            version: 2,
+            program_id: *TOKEN_PROGRAM_ID,
+            discriminator: None,
+            size: SizeRule::Exact(TOKEN_ACCOUNT_LEN),
+            decode: decode_token_account,
+        });
+        let decoded = registry
+            .decode_data(&TOKEN_PROGRAM_ID, &token_account_data(1))
+            .unwrap();
+        assert_eq!(decoded.version, 2);
+    }
+
+    #[test]
+    fn decodes_mints_and_orca_swaps() {
+        let registry = DecoderRegistry::default();
+        let mut mint = vec![0u8; MINT_LEN];
+        mint[36..44].copy_from_slice(&1_000u64.to_le_bytes());
+        mint[44] = 9;
+        mint[45] = 1;
+        let mint_account = Account {
+            owner: *TOKEN_PROGRAM_ID,
+            data: mint,
+            ..Account::default()
+        };
+        assert_eq!(
+            registry.mint(&mint_account).unwrap(),
+            MintState { supply: 1_000, decimals: 9 }
+        );
+
+        let (mint_a, fee_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut swap = vec![0u8; ORCA_SWAP_LEN];
+        swap[..3].copy_from_slice(&[ORCA_SWAP_VERSION, 1, 254]);
+        swap[131..163].copy_from_slice(mint_a.as_ref());
+        swap[195..227].copy_from_slice(fee_account.as_ref());
+        swap[227..235].copy_from_slice(&25u64.to_le_bytes());
+        swap[235..243].copy_from_slice(&10_000u64.to_le_bytes());
+        swap[291] = 2;
+        swap[292..300].copy_from_slice(&100u64.to_le_bytes());
+        match registry.decode_data(&ORCA_PROGRAM_ID, &swap).unwrap().account {
+            DecodedAccount::OrcaSwap(state) => {
+                assert_eq!(state.nonce, 254);
+                assert_eq!(state.mint_a, mint_a);
+                assert_eq!(state.fee_account, fee_account);
+                assert_eq!((state.trade_fee_numerator, state.trade_fee_denominator), (25, 10_000));
+                assert_eq!((state.curve_type, state.amp), (2, 100));
+            }
+            other => panic!("unexpected account: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decodes_open_orders_and_not_as_markets() {
+        let (market, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut data = vec![0u8; SERUM_OPEN_ORDERS_LEN];
+        data[..5].copy_from_slice(b"serum");
+        data[5..13].copy_from_slice(&SERUM_OPEN_ORDERS_FLAGS.to_le_bytes());
+        data[13..45].copy_from_slice(market.as_ref());
+        data[45..77].copy_from_slice(owner.as_ref());
+        data[85..93].copy_from_slice(&1_500u64.to_le_bytes());
+        data[101..109].copy_from_slice(&2_000_000u64.to_le_bytes());
+        let decoded = DecoderRegistry::default()
+            .decode_data(&OPENBOOK_PROGRAM_ID, &data)
+            .unwrap();
+        assert_eq!(decoded.layout, "serum open orders");
+        match decoded.account {
+            DecodedAccount::SerumOpenOrders(oo) => {
+                assert_eq!((oo.market, oo.owner), (market, owner));
+                assert_eq!((oo.native_coin_total, oo.native_pc_total), (1_500, 2_000_000));
+            }
+            other => panic!("unexpected account: {:?}", other),
+        }
+
+        // a market account isn't taken for open orders
+        data[5..13].copy_from_slice(&3u64.to_le_bytes());
+        assert!(DecoderRegistry::default().decode_data(&SERUM_PROGRAM_ID, &data).is_err());
+    }
+
+    #[test]
+    fn decodes_pyth_prices() {
+        let mut data = pyth_price_discriminator();
+        data.resize(3312, 0);
+        data[20..24].copy_from_slice(&(-8i32).to_le_bytes());
+        data[208..216].copy_from_slice(&2_345_000_000i64.to_le_bytes());
+        data[216..224].copy_from_slice(&1_000_000u64.to_le_bytes());
+        data[224..228].copy_from_slice(&PythPrice::STATUS_TRADING.to_le_bytes());
+        data[232..240].copy_from_slice(&99u64.to_le_bytes());
+
+        let decoded = DecoderRegistry::default()
+            .decode_data(&PYTH_PROGRAM_ID, &data)
+            .unwrap();
+        match decoded.account {
+            DecodedAccount::PythPrice(price) => {
+                assert!((price.value() - 23.45).abs() < 1e-9);
+                assert!((price.confidence() - 0.01).abs() < 1e-9);
+                assert_eq!(price.publish_slot, 99);
+            }
+            other => panic!("unexpected account: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod decoder;
+pub mod pool; 
+pub mod token;
\ No newline at end of file
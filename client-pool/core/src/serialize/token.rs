@@ -27,7 +27,7 @@ pub struct Token {
 pub struct WrappedString(pub String);
 
 #[derive(Deserialize, PartialEq, Eq)]
-#[serde(from = "WrappedString")]
+#[serde(try_from = "WrappedString")]
 pub struct WrappedPubkey(pub Pubkey);
 
 impl Serialize for WrappedPubkey {
@@ -64,9 +64,13 @@ impl DerefMut for WrappedPubkey {
     }
 }
 
-impl From<WrappedString> for WrappedPubkey {
-    fn from(s: WrappedString) -> Self {
-        WrappedPubkey(Pubkey::from_str(&s.0).unwrap())
+impl TryFrom<WrappedString> for WrappedPubkey {
+    type Error = String;
+
+    fn try_from(s: WrappedString) -> Result<Self, Self::Error> {
+        Pubkey::from_str(&s.0)
+            .map(WrappedPubkey)
+            .map_err(|_| format!("{} is not a valid pubkey", s.0))
     }
 }
 
@@ -134,20 +138,30 @@ fn unpack_coption_u64(src: &[u8; 12]) -> Result<COption<u64>, ProgramError> {
     }
 }
 
-pub fn unpack_token_account(data: &[u8]) -> TokenAccount {
+/// Length of an spl-token account
+pub const TOKEN_ACCOUNT_LEN: usize = 165;
+
+pub fn try_unpack_token_account(data: &[u8]) -> Result<TokenAccount, ProgramError> {
+    if data.len() < TOKEN_ACCOUNT_LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
     let src = array_ref![data, 0, 165];
     let (mint, owner, amount, delegate, state, is_native, delegated_amount, close_authority) =
         array_refs![src, 32, 32, 8, 36, 1, 12, 8, 36];
-    
-    TokenAccount {
+
+    Ok(TokenAccount {
         mint: Pubkey::new_from_array(*mint),
         owner: Pubkey::new_from_array(*owner),
         amount: u64::from_le_bytes(*amount),
-        delegate: unpack_coption_key(delegate).unwrap(),
+        delegate: unpack_coption_key(delegate)?,
         state: AccountState::try_from_primitive(state[0])
-            .or(Err(ProgramError::InvalidAccountData)).unwrap(),
-        is_native: unpack_coption_u64(is_native).unwrap(),
+            .or(Err(ProgramError::InvalidAccountData))?,
+        is_native: unpack_coption_u64(is_native)?,
         delegated_amount: u64::from_le_bytes(*delegated_amount),
-        close_authority: unpack_coption_key(close_authority).unwrap(),
-    }
-}
\ No newline at end of file
+        close_authority: unpack_coption_key(close_authority)?,
+    })
+}
+
+pub fn unpack_token_account(data: &[u8]) -> TokenAccount {
+    try_unpack_token_account(data).unwrap()
+}
@@ -0,0 +1,45 @@
+//! Pools for unit tests, built in memory
+//!
+//! Compiled for this crate's tests, and for other crates' tests with the
+//! `test-pools` feature (the client enables it in its dev-dependencies).
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::pools::OrcaPool;
+
+/// JSON file of a constant product Orca pool at `address` (6 decimals,
+/// 0.3% fee) trading `mints`
+pub fn orca_json(address: &Pubkey, mints: &[Pubkey]) -> String {
+    let token = |mint: &Pubkey| {
+        format!(
+            "\"{}\": {{ \"tag\": \"t\", \"name\": \"t\", \"mint\": \"{}\", \"scale\": 6, \"addr\": \"{}\" }}",
+            mint,
+            mint,
+            Pubkey::new_unique()
+        )
+    };
+    let ids: Vec<String> = mints.iter().map(|mint| format!("\"{}\"", mint)).collect();
+    let tokens: Vec<String> = mints.iter().map(token).collect();
+    format!(
+        "{{ \"address\": \"{p}\", \"nonce\": 255, \"authority\": \"{p}\", \"poolTokenMint\": \"{p}\",
+            \"poolTokenDecimals\": 6, \"feeAccount\": \"{p}\", \"tokenIds\": [{ids}],
+            \"tokens\": {{ {tokens} }}, \"curveType\": 0,
+            \"feeStructure\": {{ \"traderFee\": {{ \"numerator\": 25, \"denominator\": 10000 }},
+                                 \"ownerFee\": {{ \"numerator\": 5, \"denominator\": 10000 }} }} }}",
+        p = address,
+        ids = ids.join(", "),
+        tokens = tokens.join(", "),
+    )
+}
+
+/// Orca pool (see `orca_json`) holding `reserves[i]` of `mints[i]`; pools
+/// of more than two mints skip the pool file validation
+pub fn orca_pool(mints: &[Pubkey], reserves: &[u128]) -> OrcaPool {
+    let mut pool: OrcaPool = serde_json::from_str(&orca_json(&Pubkey::new_unique(), mints)).unwrap();
+    pool.pool_amounts = mints
+        .iter()
+        .zip(reserves)
+        .map(|(mint, reserve)| (mint.to_string(), *reserve))
+        .collect();
+    pool
+}
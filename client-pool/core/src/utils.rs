@@ -0,0 +1,123 @@
+use crate::constants::*;
+use crate::pool::PoolOperations;
+use crate::pools::PoolVariant;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::fs;
+use std::rc::Rc;
+use std::str::FromStr;
+
+/// The JSON files of `dir`, none if it can't be read
+pub fn read_json_dir(dir: &String) -> Vec<String> {
+    let _paths = match fs::read_dir(dir) {
+        Ok(paths) => paths,
+        Err(e) => {
+            tracing::warn!("Skipping pool dir {}: {}", dir, e);
+            return vec![];
+        }
+    };
+    let mut paths = Vec::new();
+    for path in _paths.flatten() {
+        let p = path.path();
+        let path_str = p;
+        match path_str.extension() {
+            Some(ex) => {
+                if ex == "json" {
+                    let path = path_str.to_str().unwrap().to_string();
+                    paths.push(path);
+                }
+            }
+            None => {}
+        }
+    }
+    paths
+}
+
+pub fn str2pubkey(s: &str) -> Pubkey {
+    Pubkey::from_str(s).unwrap()
+}
+
+pub fn derive_token_address(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    let (pda, _) = Pubkey::find_program_address(
+        &[
+            &owner.to_bytes(),
+            &TOKEN_PROGRAM_ID.to_bytes(),
+            &mint.to_bytes(),
+        ],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    );
+    pda
+}
+
+#[derive(Debug, Clone)]
+pub struct PoolQuote(pub Rc<PoolVariant>);
+
+impl PoolQuote {
+    pub fn new(quote: Rc<PoolVariant>) -> Self {
+        Self(quote)
+    }
+}
+
+#[derive(Debug)]
+pub struct PoolGraph(pub HashMap<PoolIndex, PoolEdge>);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct PoolIndex(pub usize);
+
+#[derive(Debug, Clone)]
+pub struct PoolEdge(pub HashMap<PoolIndex, Vec<PoolQuote>>);
+
+/// Probe amounts are this fraction of the smallest input reserve of an edge
+const RANK_PROBE_RESERVE_DIVISOR: u128 = 1_000;
+
+impl PoolGraph {
+    pub fn new() -> Self {
+        Self(HashMap::new())
+    }
+
+    /// Orders the pools of every edge by their marginal rate, best first:
+    /// what they quote for a small probe of the input mint (a thousandth of
+    /// the edge's smallest input reserve, or one token for order books).
+    /// Pools that can't quote the probe go last. The search takes an edge's
+    /// pools in this order (see `SearchVisitor::max_pools_per_edge`).
+    pub fn rank_pools(&mut self, token_mints: &[Pubkey]) {
+        for (src, edge) in self.0.iter_mut() {
+            let mint_in = token_mints[src.0];
+            for (dst, pools) in edge.0.iter_mut() {
+                let mint_out = token_mints[dst.0];
+                let probe = rank_probe(pools, &mint_in);
+                let mut ranked: Vec<(u128, PoolQuote)> = pools
+                    .drain(..)
+                    .map(|pool| {
+                        let out = pool
+                            .0
+                            .get_quote_with_amounts_scaled(probe, &mint_in, &mint_out)
+                            .unwrap_or(0);
+                        (out, pool)
+                    })
+                    .collect();
+                // stable, so pools quoting the same keep their order
+                ranked.sort_by(|a, b| b.0.cmp(&a.0));
+                pools.extend(ranked.into_iter().map(|(_, pool)| pool));
+            }
+        }
+    }
+}
+
+/// The same input for every pool of an edge, so their quotes compare
+fn rank_probe(pools: &[PoolQuote], mint_in: &Pubkey) -> u128 {
+    let min_reserve = pools
+        .iter()
+        .filter_map(|pool| pool.0.get_reserves())
+        .flatten()
+        .filter(|(mint, _)| mint == mint_in)
+        .map(|(_, amount)| amount)
+        .min();
+    match min_reserve {
+        Some(reserve) if reserve / RANK_PROBE_RESERVE_DIVISOR > 0 => reserve / RANK_PROBE_RESERVE_DIVISOR,
+        _ => match pools.first() {
+            Some(pool) => 10u128.pow(pool.0.mint_2_scale(mint_in) as u32),
+            None => 0,
+        },
+    }
+}
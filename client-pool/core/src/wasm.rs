@@ -0,0 +1,94 @@
+//! JS bindings of the quote math (`wasm32` feature)
+//!
+//! The functions the bot quotes with, over plain numbers and raw account
+//! bytes, so front-ends and serverless functions price routes exactly like
+//! the bot does. Amounts are scaled integers (`BigInt` on the JS side).
+
+use solana_program::pubkey::Pubkey;
+use wasm_bindgen::prelude::*;
+
+use crate::pool_utils::base::CurveType;
+use crate::pool_utils::fees::Fees;
+use crate::pool_utils::orca::get_pool_quote_with_amounts;
+use crate::pool_utils::serum::{walk_order_book, FeeTier};
+
+/// End of the asks key in a Serum market account (own address at 13, bids
+/// at 285, asks at 317, same as `serialize::decoder`)
+const MARKET_KEYS_END: usize = 349;
+
+/// Output of a token-swap style pool (Orca, Raydium) for `amount_in`.
+/// `curve_type` is 0 (constant product) or 2 (stable, with `amp`); the fees
+/// are the pool file's `traderFee` and `ownerFee` fractions.
+#[wasm_bindgen(js_name = quoteSwap)]
+#[allow(clippy::too_many_arguments)]
+pub fn quote_swap(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    curve_type: u8,
+    amp: u64,
+    trade_fee_numerator: u64,
+    trade_fee_denominator: u64,
+    owner_fee_numerator: u64,
+    owner_fee_denominator: u64,
+) -> Result<u64, JsValue> {
+    let curve_type = match curve_type {
+        0 => CurveType::ConstantProduct,
+        2 => CurveType::Stable,
+        other => return Err(JsValue::from_str(&format!("unsupported curve type {}", other))),
+    };
+    let fees = Fees {
+        trade_fee_numerator,
+        trade_fee_denominator,
+        owner_trade_fee_numerator: owner_fee_numerator,
+        owner_trade_fee_denominator: owner_fee_denominator,
+        owner_withdraw_fee_numerator: 0,
+        owner_withdraw_fee_denominator: 0,
+        host_fee_numerator: 0,
+        host_fee_denominator: 0,
+    };
+    get_pool_quote_with_amounts(
+        amount_in as u128,
+        curve_type,
+        amp,
+        &fees,
+        reserve_in as u128,
+        reserve_out as u128,
+        None,
+    )
+    .map(|quote| quote as u64)
+    .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Output of a market order against a Serum market, from the raw data of
+/// its market, bids and asks accounts. `is_bid` spends quote for base.
+#[wasm_bindgen(js_name = quoteOrderBook)]
+pub fn quote_order_book(
+    market: &[u8],
+    bids: &[u8],
+    asks: &[u8],
+    program_id: &[u8],
+    amount_in: u64,
+    is_bid: bool,
+) -> Result<u64, JsValue> {
+    if program_id.len() != 32 {
+        return Err(JsValue::from_str("program id must be 32 bytes"));
+    }
+    if market.len() < MARKET_KEYS_END {
+        return Err(JsValue::from_str("market account is too short"));
+    }
+    let program_id = Pubkey::new(program_id);
+    // the market checks the books are its own, take their keys from it
+    let key_at = |offset: usize| Pubkey::new(&market[offset..offset + 32]);
+    let (market_key, bids_key, asks_key) = (key_at(13), key_at(285), key_at(317));
+    walk_order_book(
+        (&market_key, market),
+        (&bids_key, bids),
+        (&asks_key, asks),
+        &program_id,
+        amount_in,
+        is_bid,
+        FeeTier::default(),
+    )
+    .map_err(|e| JsValue::from_str(&e.to_string()))
+}
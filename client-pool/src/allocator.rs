@@ -0,0 +1,183 @@
+//! Capital allocation across strategies
+//!
+//! Every strategy spending from the shared start-mint balance reserves its
+//! input amount here first. Each strategy's budget is its weighted share of
+//! the balance; reservations are released once the transaction resolves.
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy {
+    /// Low-latency 2-hop routes
+    FastLane,
+    /// Full graph search over longer cycles
+    DeepSearch,
+    /// Inventory rebalancing back into the start mint
+    Rebalancer,
+}
+
+/// Only the deep search runs by default, so it gets the whole balance
+pub fn default_strategy_weights() -> HashMap<Strategy, u32> {
+    let mut weights = HashMap::new();
+    weights.insert(Strategy::DeepSearch, 1);
+    weights
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReservationId(u64);
+
+#[derive(Debug, Clone)]
+pub struct Reservation {
+    pub id: ReservationId,
+    pub strategy: Strategy,
+    pub amount: u128,
+}
+
+#[derive(Debug)]
+struct AllocatorState {
+    balance: u128,
+    reserved: HashMap<Strategy, u128>,
+    reservations: HashMap<ReservationId, Reservation>,
+    next_id: u64,
+}
+
+#[derive(Debug)]
+pub struct CapitalAllocator {
+    weights: Mutex<HashMap<Strategy, u32>>,
+    state: Mutex<AllocatorState>,
+}
+
+impl CapitalAllocator {
+    /// # Arguments
+    /// * `balance` - Spendable start-mint balance (scaled)
+    /// * `weights` - Relative share of the balance each strategy may reserve
+    pub fn new(balance: u128, weights: HashMap<Strategy, u32>) -> Self {
+        Self {
+            weights: Mutex::new(weights),
+            state: Mutex::new(AllocatorState {
+                balance,
+                reserved: HashMap::new(),
+                reservations: HashMap::new(),
+                next_id: 0,
+            }),
+        }
+    }
+
+    /// Updates the shared balance (e.g. after re-reading the wallet ATA)
+    pub fn set_balance(&self, balance: u128) {
+        self.state.lock().unwrap().balance = balance;
+    }
+
+    /// Replaces the strategy weights (e.g. on config reload). Existing
+    /// reservations are kept, so a strategy may briefly be over budget.
+    pub fn set_weights(&self, weights: HashMap<Strategy, u32>) {
+        *self.weights.lock().unwrap() = weights;
+    }
+
+    /// Total amount a strategy may have reserved at once
+    pub fn budget(&self, strategy: Strategy) -> u128 {
+        let balance = self.state.lock().unwrap().balance;
+        self.budget_for(strategy, balance)
+    }
+
+    fn budget_for(&self, strategy: Strategy, balance: u128) -> u128 {
+        let weights = self.weights.lock().unwrap();
+        let total_weight: u128 = weights.values().map(|w| *w as u128).sum();
+        if total_weight == 0 {
+            return 0;
+        }
+        let weight = *weights.get(&strategy).unwrap_or(&0) as u128;
+        balance * weight / total_weight
+    }
+
+    /// Amount the strategy can still reserve
+    pub fn available(&self, strategy: Strategy) -> u128 {
+        let state = self.state.lock().unwrap();
+        let budget = self.budget_for(strategy, state.balance);
+        let reserved = *state.reserved.get(&strategy).unwrap_or(&0);
+        budget.saturating_sub(reserved)
+    }
+
+    /// Reserves `min(amount, available)` for the strategy.
+    /// Returns `None` if nothing is available.
+    pub fn reserve_up_to(&self, strategy: Strategy, amount: u128) -> Option<Reservation> {
+        let mut state = self.state.lock().unwrap();
+        let budget = self.budget_for(strategy, state.balance);
+        let reserved = *state.reserved.get(&strategy).unwrap_or(&0);
+        let amount = amount.min(budget.saturating_sub(reserved));
+        if amount == 0 {
+            return None;
+        }
+
+        let id = ReservationId(state.next_id);
+        state.next_id += 1;
+        *state.reserved.entry(strategy).or_insert(0) += amount;
+        let reservation = Reservation {
+            id,
+            strategy,
+            amount,
+        };
+        state.reservations.insert(id, reservation.clone());
+        Some(reservation)
+    }
+
+    /// Releases a reservation once its transaction resolved (landed or failed)
+    pub fn release(&self, id: ReservationId) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(reservation) = state.reservations.remove(&id) {
+            let reserved = state.reserved.entry(reservation.strategy).or_insert(0);
+            *reserved = reserved.saturating_sub(reservation.amount);
+        }
+    }
+
+    pub fn reserved(&self, strategy: Strategy) -> u128 {
+        *self
+            .state
+            .lock()
+            .unwrap()
+            .reserved
+            .get(&strategy)
+            .unwrap_or(&0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budgets_follow_weights() {
+        let mut weights = HashMap::new();
+        weights.insert(Strategy::FastLane, 3);
+        weights.insert(Strategy::DeepSearch, 1);
+        let allocator = CapitalAllocator::new(1_000, weights);
+        assert_eq!(allocator.budget(Strategy::FastLane), 750);
+        assert_eq!(allocator.budget(Strategy::DeepSearch), 250);
+        assert_eq!(allocator.budget(Strategy::Rebalancer), 0);
+    }
+
+    #[test]
+    fn reservations_are_capped_and_released() {
+        let mut weights = HashMap::new();
+        weights.insert(Strategy::FastLane, 1);
+        weights.insert(Strategy::DeepSearch, 1);
+        weights.insert(Strategy::Rebalancer, 1);
+        let allocator = CapitalAllocator::new(900, weights);
+        let r0 = allocator.reserve_up_to(Strategy::DeepSearch, 200).unwrap();
+        let r1 = allocator.reserve_up_to(Strategy::DeepSearch, 200).unwrap();
+        assert_eq!(r0.amount, 200);
+        assert_eq!(r1.amount, 100);
+        assert!(allocator.reserve_up_to(Strategy::DeepSearch, 1).is_none());
+        // other strategies are unaffected
+        assert_eq!(allocator.available(Strategy::FastLane), 300);
+
+        allocator.release(r0.id);
+        assert_eq!(allocator.available(Strategy::DeepSearch), 200);
+        allocator.release(r0.id); // double release is a no-op
+        assert_eq!(allocator.reserved(Strategy::DeepSearch), 100);
+    }
+}
@@ -0,0 +1,457 @@
+//! Quote, opportunity and execution events for analysis
+//!
+//! With `analytics` set every tick's hop quotes (`quotes`, on by default),
+//! opportunities and every execution outcome are kept as `AnalyticsEvent`
+//! rows and written in batches of `batch_size` (or every `flush_secs`) by a
+//! background thread, so the search never waits on the writes:
+//!
+//! * `parquet_dir`: a Snappy compressed Parquet file per batch,
+//!   `events-v<schema>-<unix ms>.parquet`
+//! * `clickhouse`: an `INSERT ... FORMAT JSONEachRow` into `table` over
+//!   ClickHouse's HTTP interface; the table is created at start if missing
+//!
+//! Every row carries `schema_version` (also in the Parquet key-value
+//! metadata and the file name); columns are only ever added, with the
+//! version bumped. Amounts are scaled token amounts, saturated to `i64`.
+//! A batch that can't be written is logged and dropped.
+
+use parquet::basic::Compression;
+use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::format::KeyValue;
+use parquet::schema::parser::parse_message_type;
+use reqwest::blocking::Client as HttpClient;
+use serde::{Deserialize, Serialize};
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use tracing::{info, warn};
+
+use crate::arb::ArbOpportunity;
+
+/// Version of the `AnalyticsEvent` columns
+pub const SCHEMA_VERSION: i64 = 1;
+
+/// Parquet schema of a batch, in `AnalyticsEvent` order
+const PARQUET_SCHEMA: &str = "
+message arb_event {
+    REQUIRED INT64 schema_version;
+    REQUIRED BYTE_ARRAY kind (UTF8);
+    REQUIRED INT64 timestamp_ms;
+    REQUIRED INT64 slot;
+    REQUIRED BYTE_ARRAY route (UTF8);
+    REQUIRED BYTE_ARRAY pools (UTF8);
+    REQUIRED BYTE_ARRAY mint_in (UTF8);
+    REQUIRED BYTE_ARRAY mint_out (UTF8);
+    REQUIRED INT64 amount_in;
+    REQUIRED INT64 amount_out;
+    REQUIRED INT64 expected_profit;
+    REQUIRED BYTE_ARRAY outcome (UTF8);
+}";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ClickHouseConfig {
+    /// HTTP interface, e.g. `http://127.0.0.1:8123`
+    pub url: String,
+    #[serde(default = "default_table")]
+    pub table: String,
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+fn default_table() -> String {
+    "arb_events".to_string()
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AnalyticsConfig {
+    /// Directory the Parquet files are written to
+    #[serde(default)]
+    pub parquet_dir: Option<String>,
+    /// ClickHouse table the rows are inserted into
+    #[serde(default)]
+    pub clickhouse: Option<ClickHouseConfig>,
+    /// Rows per batch
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Longest a row waits for its batch to be written
+    #[serde(default = "default_flush_secs")]
+    pub flush_secs: u64,
+    /// Keep a row per hop quote of every opportunity
+    #[serde(default = "default_quotes")]
+    pub quotes: bool,
+}
+
+fn default_batch_size() -> usize {
+    10_000
+}
+
+fn default_flush_secs() -> u64 {
+    60
+}
+
+fn default_quotes() -> bool {
+    true
+}
+
+impl AnalyticsConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.parquet_dir.is_none() && self.clickhouse.is_none() {
+            problems.push("analytics needs parquet_dir or clickhouse".to_string());
+        }
+        if self.batch_size == 0 {
+            problems.push("analytics.batch_size must be > 0".to_string());
+        }
+        if let Some(clickhouse) = &self.clickhouse {
+            if !clickhouse.url.starts_with("http://") && !clickhouse.url.starts_with("https://") {
+                problems.push(format!("analytics.clickhouse.url {} is not an http url", clickhouse.url));
+            }
+            if !valid_identifier(&clickhouse.table) {
+                problems.push(format!("analytics.clickhouse.table {} is not a table name", clickhouse.table));
+            }
+        }
+        problems
+    }
+}
+
+/// Letters, digits, `_` and a `.` between database and table
+fn valid_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && name.split('.').count() <= 2
+        && name.split('.').all(|part| {
+            !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+}
+
+/// One row: a hop quote, an opportunity or an execution outcome
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AnalyticsEvent {
+    pub schema_version: i64,
+    /// `quote`, `opportunity` or `execution`
+    pub kind: String,
+    pub timestamp_ms: i64,
+    pub slot: i64,
+    pub route: String,
+    /// The quoting pool, or every pool of the route
+    pub pools: String,
+    /// Input and output mint of the quote or the route
+    pub mint_in: String,
+    pub mint_out: String,
+    pub amount_in: i64,
+    pub amount_out: i64,
+    pub expected_profit: i64,
+    /// `landed`, `failed: <why>` or `unsent: <why>` for executions, empty otherwise
+    pub outcome: String,
+}
+
+impl AnalyticsEvent {
+    fn new(kind: &str, slot: u64, route: &str) -> Self {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis() as i64);
+        Self {
+            schema_version: SCHEMA_VERSION,
+            kind: kind.to_string(),
+            timestamp_ms,
+            slot: saturate(slot as i128),
+            route: route.to_string(),
+            pools: String::new(),
+            mint_in: String::new(),
+            mint_out: String::new(),
+            amount_in: 0,
+            amount_out: 0,
+            expected_profit: 0,
+            outcome: String::new(),
+        }
+    }
+}
+
+fn saturate(amount: i128) -> i64 {
+    amount.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
+
+/// Collects the rows and hands full batches to the writer thread
+pub struct EventExporter {
+    quotes: bool,
+    batch_size: usize,
+    flush_every: Duration,
+    pending: Mutex<(Vec<AnalyticsEvent>, Instant)>,
+    batches: Mutex<Option<Sender<Vec<AnalyticsEvent>>>>,
+    writer: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl EventExporter {
+    /// Starts the writer thread; creates the ClickHouse table if missing
+    pub fn spawn(config: &AnalyticsConfig) -> Arc<Self> {
+        let sinks = Sinks::new(config);
+        sinks.create_table();
+        let (sender, batches) = mpsc::channel::<Vec<AnalyticsEvent>>();
+        let writer = thread::spawn(move || {
+            for batch in batches {
+                sinks.write(&batch);
+            }
+        });
+        Arc::new(Self {
+            quotes: config.quotes,
+            batch_size: config.batch_size,
+            flush_every: Duration::from_secs(config.flush_secs),
+            pending: Mutex::new((vec![], Instant::now())),
+            batches: Mutex::new(Some(sender)),
+            writer: Mutex::new(Some(writer)),
+        })
+    }
+
+    /// Rows for the opportunities of a tick and, with `quotes`, their hops
+    pub fn record_opportunities(&self, opportunities: &[ArbOpportunity], slot: u64) {
+        let mut rows = vec![];
+        for opportunity in opportunities {
+            let mut row = AnalyticsEvent::new("opportunity", slot, &opportunity.route);
+            row.pools = opportunity.pool_ids().join(",");
+            row.mint_in = opportunity.mints[0].to_string();
+            row.mint_out = row.mint_in.clone();
+            row.amount_in = saturate(opportunity.amount_in as i128);
+            row.amount_out = saturate(opportunity.min_amount_out as i128);
+            row.expected_profit = saturate(opportunity.expected_profit);
+            rows.push(row);
+            if !self.quotes || opportunity.amounts.len() != opportunity.pools.len() + 1 {
+                continue;
+            }
+            for hop in opportunity.hops() {
+                let mut row = AnalyticsEvent::new("quote", slot, &opportunity.route);
+                row.pools = hop.pool;
+                row.mint_in = hop.mint_in.to_string();
+                row.mint_out = hop.mint_out.to_string();
+                row.amount_in = saturate(hop.amount_in as i128);
+                row.amount_out = saturate(hop.amount_out as i128);
+                rows.push(row);
+            }
+        }
+        self.push(rows);
+    }
+
+    /// A row for the outcome of a route sent (or simulated, or paper traded)
+    pub fn record_execution(
+        &self,
+        route: &str,
+        pool_ids: &[String],
+        expected_profit: i128,
+        slot: u64,
+        outcome: String,
+    ) {
+        let mut row = AnalyticsEvent::new("execution", slot, route);
+        row.pools = pool_ids.join(",");
+        row.expected_profit = saturate(expected_profit);
+        row.outcome = outcome;
+        self.push(vec![row]);
+    }
+
+    fn push(&self, rows: Vec<AnalyticsEvent>) {
+        let mut pending = self.pending.lock().unwrap();
+        pending.0.extend(rows);
+        if pending.0.len() >= self.batch_size || pending.1.elapsed() >= self.flush_every {
+            let batch = std::mem::take(&mut pending.0);
+            pending.1 = Instant::now();
+            self.send(batch);
+        }
+    }
+
+    fn send(&self, batch: Vec<AnalyticsEvent>) {
+        if batch.is_empty() {
+            return;
+        }
+        if let Some(batches) = self.batches.lock().unwrap().as_ref() {
+            let _ = batches.send(batch);
+        }
+    }
+
+    /// Writes what is pending and waits for the writer to finish, on exit
+    pub fn close(&self) {
+        let batch = std::mem::take(&mut self.pending.lock().unwrap().0);
+        self.send(batch);
+        self.batches.lock().unwrap().take();
+        if let Some(writer) = self.writer.lock().unwrap().take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+/// Where the writer thread puts the batches
+struct Sinks {
+    parquet_dir: Option<PathBuf>,
+    clickhouse: Option<(ClickHouseConfig, HttpClient)>,
+}
+
+impl Sinks {
+    fn new(config: &AnalyticsConfig) -> Self {
+        let client = HttpClient::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .unwrap_or_default();
+        Self {
+            parquet_dir: config.parquet_dir.as_ref().map(PathBuf::from),
+            clickhouse: config.clickhouse.clone().map(|clickhouse| (clickhouse, client)),
+        }
+    }
+
+    fn create_table(&self) {
+        if let Some((config, client)) = &self.clickhouse {
+            if let Err(e) = clickhouse_query(client, config, clickhouse_ddl(&config.table)) {
+                warn!("analytics: could not create {}: {}", config.table, e);
+            }
+        }
+    }
+
+    fn write(&self, batch: &[AnalyticsEvent]) {
+        if let Some(dir) = &self.parquet_dir {
+            let path = dir.join(format!(
+                "events-v{}-{}.parquet",
+                SCHEMA_VERSION,
+                batch[0].timestamp_ms
+            ));
+            match write_parquet(&path, batch) {
+                Ok(()) => info!("analytics: {} events written to {}", batch.len(), path.display()),
+                Err(e) => warn!("analytics: dropping {} events, {}: {}", batch.len(), path.display(), e),
+            }
+        }
+        if let Some((config, client)) = &self.clickhouse {
+            let insert = format!("INSERT INTO {} FORMAT JSONEachRow\n{}", config.table, json_each_row(batch));
+            match clickhouse_query(client, config, insert) {
+                Ok(()) => info!("analytics: {} events inserted into {}", batch.len(), config.table),
+                Err(e) => warn!("analytics: dropping {} events, {}: {}", batch.len(), config.table, e),
+            }
+        }
+    }
+}
+
+/// `CREATE TABLE` of the `AnalyticsEvent` columns
+pub fn clickhouse_ddl(table: &str) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} (\
+         schema_version Int64, kind LowCardinality(String), timestamp_ms Int64, slot Int64, \
+         route String, pools String, mint_in String, mint_out String, \
+         amount_in Int64, amount_out Int64, expected_profit Int64, outcome String\
+         ) ENGINE = MergeTree ORDER BY (kind, timestamp_ms)",
+        table
+    )
+}
+
+/// One JSON object per line
+pub fn json_each_row(batch: &[AnalyticsEvent]) -> String {
+    batch
+        .iter()
+        .filter_map(|row| serde_json::to_string(row).ok())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn clickhouse_query(client: &HttpClient, config: &ClickHouseConfig, query: String) -> Result<(), String> {
+    let mut request = client.post(&config.url).body(query);
+    if let Some(user) = &config.user {
+        request = request.basic_auth(user, config.password.as_ref());
+    }
+    let response = request.send().map_err(|e| e.to_string())?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("{}: {}", response.status(), response.text().unwrap_or_default().trim()))
+    }
+}
+
+/// Writes `batch` as one row group
+pub fn write_parquet(path: &Path, batch: &[AnalyticsEvent]) -> Result<(), String> {
+    let schema = Arc::new(parse_message_type(PARQUET_SCHEMA).map_err(|e| e.to_string())?);
+    let metadata = KeyValue::new("schema_version".to_string(), SCHEMA_VERSION.to_string());
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_key_value_metadata(Some(vec![metadata]))
+        .build();
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut writer =
+        SerializedFileWriter::new(file, schema, Arc::new(properties)).map_err(|e| e.to_string())?;
+    let mut row_group = writer.next_row_group().map_err(|e| e.to_string())?;
+
+    let ints = |column: fn(&AnalyticsEvent) -> i64| batch.iter().map(column).collect::<Vec<i64>>();
+    let strings = |column: fn(&AnalyticsEvent) -> &str| {
+        batch.iter().map(|row| ByteArray::from(column(row))).collect::<Vec<ByteArray>>()
+    };
+    let mut column_index = 0;
+    while let Some(mut column) = row_group.next_column().map_err(|e| e.to_string())? {
+        let written = match column_index {
+            0 => column.typed::<Int64Type>().write_batch(&ints(|r| r.schema_version), None, None),
+            1 => column.typed::<ByteArrayType>().write_batch(&strings(|r| r.kind.as_str()), None, None),
+            2 => column.typed::<Int64Type>().write_batch(&ints(|r| r.timestamp_ms), None, None),
+            3 => column.typed::<Int64Type>().write_batch(&ints(|r| r.slot), None, None),
+            4 => column.typed::<ByteArrayType>().write_batch(&strings(|r| r.route.as_str()), None, None),
+            5 => column.typed::<ByteArrayType>().write_batch(&strings(|r| r.pools.as_str()), None, None),
+            6 => column.typed::<ByteArrayType>().write_batch(&strings(|r| r.mint_in.as_str()), None, None),
+            7 => column.typed::<ByteArrayType>().write_batch(&strings(|r| r.mint_out.as_str()), None, None),
+            8 => column.typed::<Int64Type>().write_batch(&ints(|r| r.amount_in), None, None),
+            9 => column.typed::<Int64Type>().write_batch(&ints(|r| r.amount_out), None, None),
+            10 => column.typed::<Int64Type>().write_batch(&ints(|r| r.expected_profit), None, None),
+            _ => column.typed::<ByteArrayType>().write_batch(&strings(|r| r.outcome.as_str()), None, None),
+        };
+        written.map_err(|e| e.to_string())?;
+        column.close().map_err(|e| e.to_string())?;
+        column_index += 1;
+    }
+    row_group.close().map_err(|e| e.to_string())?;
+    writer.close().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    fn row(kind: &str, amount: i128) -> AnalyticsEvent {
+        let mut row = AnalyticsEvent::new(kind, 42, "0->1->0 via orca:a,orca:b");
+        row.amount_in = saturate(amount);
+        row
+    }
+
+    #[test]
+    fn batches_are_written_as_parquet_and_json_rows() {
+        let batch = vec![row("opportunity", 1_000), row("quote", i128::MAX)];
+        assert_eq!(batch[1].amount_in, i64::MAX);
+
+        let path = std::env::temp_dir().join(format!("analytics-test-{}.parquet", std::process::id()));
+        write_parquet(&path, &batch).unwrap();
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        let metadata = reader.metadata().file_metadata();
+        assert_eq!(metadata.num_rows(), 2);
+        assert_eq!(metadata.schema_descr().num_columns(), 12);
+        let version = metadata.key_value_metadata().unwrap()[0].value.clone();
+        assert_eq!(version, Some(SCHEMA_VERSION.to_string()));
+        std::fs::remove_file(&path).unwrap();
+
+        let rows = json_each_row(&batch);
+        assert_eq!(rows.lines().count(), 2);
+        let first: AnalyticsEvent = serde_json::from_str(rows.lines().next().unwrap()).unwrap();
+        assert_eq!(first, batch[0]);
+    }
+
+    #[test]
+    fn configs_need_a_sink_and_a_plain_table_name() {
+        let config: AnalyticsConfig = serde_json::from_str(r#"{ "parquet_dir": "events" }"#).unwrap();
+        assert!(config.problems().is_empty());
+        let config: AnalyticsConfig =
+            serde_json::from_str(r#"{ "clickhouse": { "url": "http://ch:8123", "table": "db.arb" } }"#).unwrap();
+        assert!(config.problems().is_empty());
+        let config: AnalyticsConfig =
+            serde_json::from_str(r#"{ "clickhouse": { "url": "ch", "table": "x; DROP TABLE y" } }"#).unwrap();
+        assert_eq!(config.problems().len(), 2);
+        let config: AnalyticsConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(config.problems().len(), 1);
+    }
+}
@@ -1,214 +1,772 @@
-use anchor_client::solana_client::rpc_client::RpcClient;
-use anchor_client::solana_client::rpc_config::RpcSendTransactionConfig;
+//! Opportunity discovery
+//!
+//! The `Arbitrager` walks the exchange graph from the start mint and turns
+//! every cycle that returns more than it takes into an `ArbOpportunity`:
+//! sized by the route's history and order book depth, quoted after slippage
+//! and, with a flash loan, net of the loan fee. Nothing is sent from here;
+//! the opportunities of a round go to the `Executor` (see `executor.rs`).
 
 use anchor_client::solana_sdk::pubkey::Pubkey;
-
-use anchor_client::solana_sdk::signature::{Keypair, Signer};
-use anchor_client::{Cluster, Program};
 use std::collections::{HashMap, HashSet};
-
-use solana_sdk::instruction::Instruction;
-use solana_sdk::transaction::Transaction;
-
-use std::borrow::Borrow;
 use std::rc::Rc;
+use std::str::FromStr;
 
-use std::vec;
-
-use log::info;
+use std::sync::Arc;
 
-use tmp::accounts as tmp_accounts;
-use tmp::instruction as tmp_ix;
+use std::time::Instant;
+use std::vec;
 
+use crate::amount::{bps_ceil, ScaledAmount};
+use crate::config::RuntimeParams;
+use crate::error::QuoteError;
+use crate::exposure::ExposureTracker;
+use crate::flash_loan::FlashLoan;
+use crate::jupiter_pools::is_jupiter;
+use crate::latency::{LatencyStats, Stage};
+use crate::oracle::Hop;
 use crate::pool::PoolOperations;
+use crate::pool_utils::split::{best_split, Split};
+use crate::quarantine::{PoolFailure, PoolQuarantine};
+use crate::shutdown::Shutdown;
+use crate::sizing::PositionSizer;
+use crate::slippage::SlippageTracker;
 
-use crate::utils::{derive_token_address, PoolGraph, PoolIndex, PoolQuote};
+use crate::search::{Cycle, QuoteFailure, RouteConstraints, SearchVisitor, Searcher};
+use crate::utils::{PoolGraph, PoolIndex, PoolQuote};
 
 pub struct Arbitrager {
     pub token_mints: Vec<Pubkey>,
     pub graph_edges: Vec<HashSet<usize>>, // used for quick searching over the graph
     pub graph: PoolGraph,
-    pub cluster: Cluster,
-    // vv -- need to clone these explicitly -- vv
-    pub owner: Rc<Keypair>,
-    pub program: Program,
-    pub connection: RpcClient,
+    /// hot-reloadable parameters, swapped in by the main loop on config reload
+    pub params: RuntimeParams,
+    /// borrow the swap input, routes have to make the loan fee on top
+    pub flash_loan: Option<FlashLoan>,
+    /// latest slot seen, pools older than `params.max_quote_age_slots` are skipped
+    pub current_slot: u64,
+    /// the search ends early once a shutdown was requested
+    pub shutdown: Shutdown,
+    /// scales route sizes by their landing and slippage history, full size if unset
+    pub sizer: Option<Arc<PositionSizer>>,
+    /// pads the quotes of venues that under-deliver (shared with the executor)
+    pub slippage: Option<Arc<SlippageTracker>>,
+    /// caps the value routed through every token (shared with the executor)
+    pub exposure: Option<Arc<ExposureTracker>>,
+    /// pools that keep failing are left out of the search (shared with the executor)
+    pub quarantine: Arc<PoolQuarantine>,
+    /// time spent per pipeline stage (shared with the control api)
+    pub latency: Arc<LatencyStats>,
+    /// first hops this process searches when the search is sharded (see
+    /// `shard.rs`), every one if unset
+    pub first_hops: Option<HashSet<usize>>,
+}
+
+/// A leg of a route divided between the route's pool and a second pool of
+/// the same pair. The router can't divide a swap, so the route is sent as
+/// two chains, each with its own start snapshot and profit guard, that only
+/// differ in the pool of this leg: the first one gets the route's input and
+/// output less what's here. The second one swaps after the first, and is
+/// quoted on the pools as the first one left them.
+#[derive(Debug, Clone)]
+pub struct SplitLeg {
+    /// index of the divided hop
+    pub leg: usize,
+    /// pool of the hop in the second chain
+    pub pool: PoolQuote,
+    /// route input (scaled start mint) of the second chain
+    pub amount_in: u128,
+    /// quoted output of the second chain after slippage
+    pub min_amount_out: u128,
+}
+
+/// A profitable route found by the search, not sent yet
+#[derive(Debug, Clone)]
+pub struct ArbOpportunity {
+    /// `<mint>-><mint>... via <pool>,...`, what cooldowns, dedup and stats key on
+    pub route: String,
+    /// mints of the route, starting and ending with the start mint
+    pub mints: Vec<Pubkey>,
+    /// pool of every hop
+    pub pools: Vec<PoolQuote>,
+    /// Input of the route (scaled start mint), what `start_swap` snapshots
+    pub amount_in: u128,
+    /// Amount quoted into the first hop (`amount_in` plus fees), then the
+    /// quoted output of every hop
+    pub amounts: Vec<u128>,
+    /// Quoted output after slippage; the program reverts if less comes out
+    pub min_amount_out: u128,
+    /// `min_amount_out - amount_in`
+    pub expected_profit: i128,
+    /// Slot and time the route was found (or quoted again) at, see `expired`
+    pub found_slot: u64,
+    pub found_at: Instant,
+    /// leg divided between two pools, `amounts`, `amount_in` and
+    /// `min_amount_out` are the sums of both chains then
+    pub split: Option<SplitLeg>,
+}
+
+impl ArbOpportunity {
+    pub fn pool_ids(&self) -> Vec<String> {
+        self.swap_pools().iter().map(|p| p.0.get_id()).collect()
+    }
+
+    /// Every pool the route swaps through, the second pool of a split leg
+    /// included
+    pub fn swap_pools(&self) -> Vec<PoolQuote> {
+        let mut pools = self.pools.clone();
+        pools.extend(self.split.iter().map(|split| split.pool.clone()));
+        pools
+    }
+
+    /// DEXes the route swaps on, routes of a shape share their compute estimate
+    pub fn shape(&self) -> String {
+        let names: Vec<String> = self.pools.iter().map(|p| p.0.get_name()).collect();
+        names.join(",")
+    }
+
+    /// Why the route is too old to send at `current_slot`, `None` while it
+    /// is within `params.max_opportunity_age_slots` and `_ms` (0 disables)
+    pub fn expired(&self, params: &RuntimeParams, current_slot: u64) -> Option<String> {
+        let slots = current_slot.saturating_sub(self.found_slot);
+        if params.max_opportunity_age_slots > 0 && slots > params.max_opportunity_age_slots {
+            return Some(format!("found {} slots ago", slots));
+        }
+        let age = self.found_at.elapsed();
+        if params.max_opportunity_age_ms > 0 && age.as_millis() > params.max_opportunity_age_ms as u128 {
+            return Some(format!("found {} ms ago", age.as_millis()));
+        }
+        None
+    }
+
+    /// Quoted input of every hop less the route's slippage in proportion,
+    /// what a hop swapping a fixed amount can count on receiving
+    pub fn min_hop_inputs(&self) -> Vec<u128> {
+        let amount_out = self.amounts.last().copied().unwrap_or(0);
+        self.amounts[..self.pools.len()]
+            .iter()
+            .map(|amount| match amount_out {
+                0 => 0,
+                _ => amount * self.min_amount_out / amount_out,
+            })
+            .collect()
+    }
+
+    /// Every swap of the route with its quoted amounts
+    pub fn hops(&self) -> Vec<Hop> {
+        self.pools
+            .iter()
+            .enumerate()
+            .map(|(i, pool)| Hop {
+                pool: pool.0.get_id(),
+                mint_in: self.mints[i],
+                mint_out: self.mints[i + 1],
+                amount_in: self.amounts[i],
+                amount_out: self.amounts[i + 1],
+                decimals_in: pool.0.mint_2_scale(&self.mints[i]),
+                decimals_out: pool.0.mint_2_scale(&self.mints[i + 1]),
+            })
+            .collect()
+    }
+}
+
+/// Collects the opportunities among the searcher's cycles
+struct ArbVisitor<'a> {
+    arb: &'a Arbitrager,
+    start_mint: Pubkey,
+    init_balance: u128,
+    /// amount the search quotes with, `init_balance` plus fees
+    amount: u128,
+    opportunities: Vec<ArbOpportunity>,
+    /// every cycle visited, profitable or not, when they're kept
+    cycles: Option<Vec<Cycle>>,
+}
+
+impl<'a> SearchVisitor for ArbVisitor<'a> {
+    /// the search ends early once a shutdown was requested
+    fn stop(&self) -> bool {
+        self.arb.shutdown.is_requested()
+    }
+
+    fn use_hop(&self, pool: &PoolQuote, dst_mint: &Pubkey) -> bool {
+        // jupiter swaps can only close a route, see `jupiter_pools`
+        if is_jupiter(pool) && *dst_mint != self.start_mint {
+            return false;
+        }
+        self.arb.usable_hop(pool, dst_mint)
+    }
+
+    fn max_pools_per_edge(&self) -> Option<usize> {
+        self.arb.params.max_pools_per_edge
+    }
+
+    fn route_constraints(&self) -> RouteConstraints {
+        self.arb.params.route_constraints
+    }
+
+    fn use_first_hop(&self, dst_mint_idx: usize) -> bool {
+        self.arb.first_hops.as_ref().map_or(true, |hops| hops.contains(&dst_mint_idx))
+    }
+
+    fn on_quote_failure(&mut self, pool: &PoolQuote, failure: QuoteFailure) {
+        let (failure, error) = match failure {
+            QuoteFailure::Error(e @ (QuoteError::ZeroReserves | QuoteError::EmptyBook)) => {
+                (PoolFailure::ZeroReserves, e.to_string())
+            }
+            QuoteFailure::Error(e) => (PoolFailure::QuoteError, e.to_string()),
+            QuoteFailure::Panic(message) => (PoolFailure::QuotePanic, message),
+            QuoteFailure::ZeroOutput => (PoolFailure::ZeroReserves, "no output".to_string()),
+        };
+        self.arb.quarantine.record_failure(&pool.0.get_id(), failure, &error);
+    }
+
+    fn on_cycle(&mut self, cycle: Cycle) {
+        if let Some(cycles) = self.cycles.as_mut() {
+            cycles.push(cycle.clone());
+        }
+        if let Some(opportunity) = self.arb.opportunity(self.init_balance, self.amount, cycle) {
+            self.opportunities.push(opportunity);
+        }
+    }
 }
 
 impl Arbitrager {
-    pub fn brute_force_search(
+    /// Every cycle through `start_mint_idx` that swaps `amount` in and
+    /// returns more than `init_balance`, most profitable first
+    pub fn find_opportunities(
         &self,
         start_mint_idx: usize,
         init_balance: u128,
-        curr_balance: u128,
-        path: Vec<usize>,
-        pool_path: Vec<PoolQuote>,
-        sent_arbs: &mut HashSet<String>,
-    ) {
-        let src_curr = path[path.len() - 1]; // last mint
-        let src_mint = self.token_mints[src_curr];
-
-        let out_edges = &self.graph_edges[src_curr];
-
-        // path = 4 = A -> B -> C -> D
-        // path >= 5 == not valid bc max tx size is swaps
-        if path.len() == 4 {
-            return;
+        amount: u128,
+    ) -> Vec<ArbOpportunity> {
+        self.search(start_mint_idx, init_balance, amount, false).0
+    }
+
+    /// `find_opportunities`, and every cycle the search visited (see
+    /// `incremental.rs`)
+    pub fn find_opportunities_and_cycles(
+        &self,
+        start_mint_idx: usize,
+        init_balance: u128,
+        amount: u128,
+    ) -> (Vec<ArbOpportunity>, Vec<Cycle>) {
+        let (opportunities, cycles) = self.search(start_mint_idx, init_balance, amount, true);
+        (opportunities, cycles.unwrap_or_default())
+    }
+
+    fn search(
+        &self,
+        start_mint_idx: usize,
+        init_balance: u128,
+        amount: u128,
+        keep_cycles: bool,
+    ) -> (Vec<ArbOpportunity>, Option<Vec<Cycle>>) {
+        let searcher = Searcher::new(&self.token_mints, &self.graph_edges, &self.graph);
+        let mut visitor = ArbVisitor {
+            arb: self,
+            start_mint: self.token_mints[start_mint_idx],
+            init_balance,
+            amount,
+            opportunities: vec![],
+            cycles: keep_cycles.then(Vec::new),
         };
+        let start = Instant::now();
+        let amount_in = ScaledAmount::new(self.token_mints[start_mint_idx], amount);
+        searcher.search(start_mint_idx, amount_in, &mut visitor);
+        self.latency.record(Stage::Search, start.elapsed());
+
+        let mut opportunities = visitor.opportunities;
+        sort_opportunities(&mut opportunities);
+        (opportunities, visitor.cycles)
+    }
 
-        for dst_mint_idx in out_edges {
-            let pools = self
-                .graph
-                .0
-                .get(&PoolIndex(src_curr))
-                .unwrap()
-                .0
-                .get(&PoolIndex(*dst_mint_idx))
-                .unwrap();
+    /// The opportunities among known cycles, each given as its mint indices
+    /// and the address of every hop's pool, quoted on the graph's current
+    /// pools; most profitable first. Cycles through a pool the graph
+    /// doesn't hold, or a hop the search would skip or can't quote, are left
+    /// out.
+    pub fn requote_cycles<'a>(
+        &self,
+        cycles: impl IntoIterator<Item = (&'a [usize], &'a [Pubkey])>,
+        init_balance: u128,
+        amount: u128,
+    ) -> Vec<ArbOpportunity> {
+        let start = Instant::now();
+        let mut opportunities: Vec<ArbOpportunity> = cycles
+            .into_iter()
+            .filter_map(|(path, pools)| {
+                let cycle = self.quote_cycle(path, pools, amount)?;
+                self.opportunity(init_balance, amount, cycle)
+            })
+            .collect();
+        self.latency.record(Stage::Search, start.elapsed());
+        sort_opportunities(&mut opportunities);
+        opportunities
+    }
 
-            if path.contains(dst_mint_idx) && *dst_mint_idx != start_mint_idx {
-                continue;
+    /// `requote_cycles` for routes given by the addresses of their mints
+    /// and pools, as cached or received from another instance. Routes
+    /// through mints the graph doesn't index, or that don't start and end
+    /// at `start_mint_idx`, are left out.
+    pub fn requote_routes<'a>(
+        &self,
+        routes: impl IntoIterator<Item = (&'a [String], &'a [String])>,
+        start_mint_idx: usize,
+        init_balance: u128,
+        amount: u128,
+    ) -> Vec<ArbOpportunity> {
+        let mint_idx: HashMap<Pubkey, usize> =
+            self.token_mints.iter().enumerate().map(|(i, mint)| (*mint, i)).collect();
+        let cycles: Vec<(Vec<usize>, Vec<Pubkey>)> = routes
+            .into_iter()
+            .filter_map(|(mints, pools)| {
+                let path = mints
+                    .iter()
+                    .map(|mint| mint_idx.get(&Pubkey::from_str(mint).ok()?).copied())
+                    .collect::<Option<Vec<usize>>>()?;
+                let pools = pools
+                    .iter()
+                    .map(|pool| Pubkey::from_str(pool).ok())
+                    .collect::<Option<Vec<Pubkey>>>()?;
+                let valid = path.first() == Some(&start_mint_idx)
+                    && path.last() == Some(&start_mint_idx)
+                    && pools.len() + 1 == path.len();
+                valid.then(|| (path, pools))
+            })
+            .collect();
+        self.requote_cycles(
+            cycles.iter().map(|(path, pools)| (path.as_slice(), pools.as_slice())),
+            init_balance,
+            amount,
+        )
+    }
+
+    fn quote_cycle(&self, path: &[usize], pool_addresses: &[Pubkey], amount: u128) -> Option<Cycle> {
+        let mut pools = Vec::with_capacity(pool_addresses.len());
+        let mut amount_out = ScaledAmount::new(self.token_mints[path[0]], amount);
+        for (i, address) in pool_addresses.iter().enumerate() {
+            let (mint_in, mint_out) = (self.token_mints[path[i]], self.token_mints[path[i + 1]]);
+            let pool = self
+                .pools_between(&mint_in, &mint_out)
+                .into_iter()
+                .find(|pool| pool.0.get_address() == *address)?;
+            if !self.usable_hop(&pool, &mint_out) {
+                return None;
             }
+            amount_out = match pool.0.quote(&amount_out, &mint_out) {
+                Ok(out) if !out.is_zero() => out,
+                _ => return None,
+            };
+            pools.push(pool);
+        }
+        // the constraints may have changed since the cycle was found
+        if !self.params.route_constraints.allows_route(&pools) {
+            return None;
+        }
+        Some(Cycle {
+            path: path.to_vec(),
+            pools,
+            amount_out,
+        })
+    }
+
+    /// Whether the search may swap through `pool` into `dst_mint`
+    fn usable_hop(&self, pool: &PoolQuote, dst_mint: &Pubkey) -> bool {
+        if self.params.blacklisted_mints.contains(dst_mint) {
+            return false;
+        }
+        let max_age = self.params.max_quote_age_slots;
+        // stale data gives stale quotes
+        if max_age != 0 && !pool.0.is_fresh(self.current_slot, max_age) {
+            return false;
+        }
+        !self.quarantine.is_quarantined(&pool.0.get_id())
+    }
+
+    /// Every pool of the graph by address
+    pub fn pools_by_address(&self) -> HashMap<Pubkey, PoolQuote> {
+        self.graph
+            .0
+            .values()
+            .flat_map(|edge| edge.0.values().flatten())
+            .map(|pool| (pool.0.get_address(), pool.clone()))
+            .collect()
+    }
+
+    /// Swaps the graph's pools for the ones in `updated` with the same
+    /// address and ranks the edges again on their new rates
+    pub fn replace_pools(&mut self, updated: &HashMap<Pubkey, PoolQuote>) {
+        if updated.is_empty() {
+            return;
+        }
+        for edge in self.graph.0.values_mut() {
+            for pool in edge.0.values_mut().flatten() {
+                if let Some(new) = updated.get(&pool.0.get_address()) {
+                    *pool = new.clone();
+                }
+            }
+        }
+        self.graph.rank_pools(&self.token_mints);
+    }
+
+    /// Pools of the graph that swap `mint_in` into `mint_out`
+    pub fn pools_between(&self, mint_in: &Pubkey, mint_out: &Pubkey) -> Vec<PoolQuote> {
+        let idx = |mint: &Pubkey| self.token_mints.iter().position(|m| m == mint).map(PoolIndex);
+        let (idx_in, idx_out) = match (idx(mint_in), idx(mint_out)) {
+            (Some(idx_in), Some(idx_out)) => (idx_in, idx_out),
+            _ => return vec![],
+        };
+        self.graph
+            .0
+            .get(&idx_in)
+            .and_then(|edges| edges.0.get(&idx_out))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// The opportunity in a cycle the searcher found, if it's profitable at
+    /// the size it can be traded at
+    fn opportunity(&self, mut init_balance: u128, amount: u128, cycle: Cycle) -> Option<ArbOpportunity> {
+        let Cycle {
+            path: new_path,
+            pools: new_pool_path,
+            amount_out,
+        } = cycle;
+        let mut amount_out = amount_out.amount;
+        let mint_keys: Vec<String> = new_path.iter().map(|i| i.to_string()).collect();
+        let pool_ids: Vec<String> = new_pool_path.iter().map(|p| p.0.get_id()).collect();
+        let route = format!("{} via {}", mint_keys.join("->"), pool_ids.join(","));
+
+        // trade less on routes that land rarely or below their quote, and no
+        // more than what their order books fill without walking deep, their
+        // pools' size overrides allow or their tokens' exposure limits have left
+        let mut size = amount;
+        if let Some(sizer) = &self.sizer {
+            size = (size as f64 * sizer.fraction(&route)) as u128;
+        }
+        if let Some(capacity) = self.route_capacity(amount, &new_path, &new_pool_path) {
+            size = size.min(capacity);
+        }
+        if let Some(exposure) = &self.exposure {
+            let mints: Vec<Pubkey> = new_path.iter().map(|i| self.token_mints[*i]).collect();
+            if let Some(capacity) = exposure.route_capacity(&mints) {
+                size = size.min(capacity);
+            }
+        }
+        if size < amount {
+            if size == 0 {
+                return None;
+            }
+            init_balance = init_balance * size / amount;
+            amount_out = self
+                .route_hops(size, &new_path, &new_pool_path)
+                .last()
+                .map_or(0, |hop| hop.amount_out);
+        }
+        let new_balance = self.min_amount_out(amount_out, &new_pool_path);
+        // flash loans have to be repaid with their fee on top
+        let required =
+            init_balance + self.flash_loan.as_ref().map_or(0, |fl| fl.fee(init_balance));
+        if new_balance <= required {
+            return None;
+        }
+        // ... profitable arb!
+        let mut amounts = vec![size];
+        amounts.extend(
+            self.route_hops(size, &new_path, &new_pool_path)
+                .iter()
+                .map(|hop| hop.amount_out),
+        );
+        let opportunity = ArbOpportunity {
+            route,
+            mints: new_path.iter().map(|i| self.token_mints[*i]).collect(),
+            pools: new_pool_path,
+            amount_in: init_balance,
+            amounts,
+            min_amount_out: new_balance,
+            expected_profit: new_balance as i128 - init_balance as i128,
+            found_slot: self.current_slot,
+            found_at: Instant::now(),
+            split: None,
+        };
+        // a loan is repaid once, after a single chain
+        if !self.params.split_routes || self.flash_loan.is_some() {
+            return Some(opportunity);
+        }
+        Some(self.split_route(opportunity))
+    }
 
-            let dst_mint_idx = *dst_mint_idx;
-            let dst_mint = self.token_mints[dst_mint_idx];
-
-            for pool in pools {
-                let new_balance =
-                    pool.0
-                        .get_quote_with_amounts_scaled(curr_balance, &src_mint, &dst_mint);
-
-                let mut new_path = path.clone();
-                new_path.push(dst_mint_idx);
-
-                let mut new_pool_path = pool_path.clone();
-                new_pool_path.push(pool.clone()); // clone the pointer
-
-                if dst_mint_idx == start_mint_idx {
-                    // info!("{:?} -> {:?} (-{:?})", init_balance, new_balance, init_balance - new_balance);
-
-                    // if new_balance > init_balance - 1086310399 {
-                    if new_balance > init_balance {
-                        // ... profitable arb!
-                        info!("found arbitrage: {:?} -> {:?}", init_balance, new_balance);
-
-                        // check if arb was sent with a larger size
-                        // key = {mint_path}{pool_names}
-                        let mint_keys: Vec<String> =
-                            new_path.clone().iter_mut().map(|i| i.to_string()).collect();
-                        let pool_keys: Vec<String> =
-                            new_pool_path.iter().map(|p| p.0.get_name()).collect();
-                        let arb_key = format!("{}{}", mint_keys.join(""), pool_keys.join(""));
-                        if sent_arbs.contains(&arb_key) {
-                            info!("arb already sent...");
-                            continue; // dont re-send an already sent arb -- bad for network
-                        } else {
-                            sent_arbs.insert(arb_key);
-                        }
-
-                        let ixs = self.get_arbitrage_instructions(
-                            init_balance,
-                            &new_path,
-                            &new_pool_path,
-                        );
-                        self.send_ixs(ixs);
-                    }
-                } else if !path.contains(&dst_mint_idx) {
-                    // ... search deeper
-                    self.brute_force_search(
-                        start_mint_idx,
-                        init_balance,
-                        new_balance,   // !
-                        new_path,      // !
-                        new_pool_path, // !
-                        sent_arbs,
-                    );
+    /// A route's quoted output after its slippage and the pads of its venues
+    fn min_amount_out(&self, amount_out: u128, pools: &[PoolQuote]) -> u128 {
+        let mut min_amount_out = self.params.apply_route_slippage(amount_out, pools);
+        if let Some(slippage) = &self.slippage {
+            min_amount_out -= bps_ceil(min_amount_out, slippage.route_pad_bps(pools));
+        }
+        min_amount_out
+    }
+
+    /// `opportunity` with the leg divided between its pool and another pool
+    /// of the pair that returns the most, if any division does better than
+    /// the route alone (see `SplitLeg`). The division is the best one of the
+    /// leg's input, then each chain is quoted from its share of the route's
+    /// input: the first one on the pools as they are, the second one on the
+    /// pools the first one moved, as it swaps after it in the transaction.
+    /// Both chains have to be profitable on their own.
+    fn split_route(&self, opportunity: ArbOpportunity) -> ArbOpportunity {
+        // the second chain would need a jupiter swap of its own amount
+        if opportunity.pools.iter().any(is_jupiter) {
+            return opportunity;
+        }
+        let mut best: Option<ArbOpportunity> = None;
+        for (leg, pool) in opportunity.pools.iter().enumerate() {
+            let (mint_in, mint_out) = (opportunity.mints[leg], opportunity.mints[leg + 1]);
+            let quote = |pool: &PoolQuote, amount_in: u128| {
+                pool.0
+                    .get_quote_with_amounts_scaled(amount_in, &mint_in, &mint_out)
+                    .unwrap_or(0)
+            };
+            let others = self.pools_between(&mint_in, &mint_out).into_iter().filter(|other| {
+                let address = other.0.get_address();
+                !opportunity.pools.iter().any(|p| p.0.get_address() == address)
+                    && !is_jupiter(other)
+                    && self.usable_hop(other, &mint_out)
+            });
+            for other in others {
+                let split = best_split(
+                    opportunity.amounts[leg],
+                    |amount_in| quote(pool, amount_in),
+                    |amount_in| quote(&other, amount_in),
+                );
+                if !split.is_split() {
+                    continue;
+                }
+                let candidate = match self.split_at(&opportunity, leg, other, split) {
+                    Some(candidate) => candidate,
+                    None => continue,
+                };
+                let current = best.as_ref().map_or(opportunity.min_amount_out, |b| b.min_amount_out);
+                if candidate.min_amount_out > current {
+                    best = Some(candidate);
                 }
             }
         }
+        best.unwrap_or(opportunity)
     }
 
-    fn get_arbitrage_instructions(
+    /// `opportunity` with `leg` divided as in `split`, `None` if a chain
+    /// doesn't pay
+    fn split_at(
         &self,
-        swap_start_amount: u128,
-        mint_idxs: &Vec<usize>,
-        pools: &Vec<PoolQuote>,
-    ) -> Vec<Instruction> {
-        // gather swap ixs
-        let mut ixs = vec![];
-        let (swap_state_pda, _) =
-            Pubkey::find_program_address(&[b"swap_state"], &self.program.id());
-
-        let src_mint = self.token_mints[mint_idxs[0]];
-        let src_ata = derive_token_address(&self.owner.pubkey(), &src_mint);
-
-        // initialize swap ix
-        let ix = self
-            .program
-            .request()
-            .accounts(tmp_accounts::TokenAndSwapState {
-                src: src_ata,
-                swap_state: swap_state_pda,
-            })
-            .args(tmp_ix::StartSwap {
-                swap_input: swap_start_amount as u64,
-            })
-            .instructions()
-            .unwrap();
-        ixs.push(ix);
+        opportunity: &ArbOpportunity,
+        leg: usize,
+        pool: PoolQuote,
+        split: Split,
+    ) -> Option<ArbOpportunity> {
+        if split.amount_out() == 0 {
+            return None;
+        }
+        let leg_in = opportunity.amounts[leg].max(1);
+        let amount_in_b = opportunity.amount_in * split.amount_in_b / leg_in;
+        let amount_in_a = opportunity.amount_in - amount_in_b;
+        let quoted_in_b = opportunity.amounts[0] * split.amount_in_b / leg_in;
+        let quoted_in_a = opportunity.amounts[0] - quoted_in_b;
+        let mut pools_b = opportunity.pools.clone();
+        pools_b[leg] = pool.clone();
+        if !self.params.route_constraints.allows_route(&pools_b) {
+            return None;
+        }
+        let mut moved = HashMap::new();
+        let amounts_a = quote_chain(quoted_in_a, &opportunity.mints, &opportunity.pools, &mut moved)?;
+        let amounts_b = quote_chain(quoted_in_b, &opportunity.mints, &pools_b, &mut moved)?;
+        let min_amount_out_a = self.min_amount_out(*amounts_a.last()?, &opportunity.pools);
+        let min_amount_out_b = self.min_amount_out(*amounts_b.last()?, &pools_b);
+        if min_amount_out_a <= amount_in_a || min_amount_out_b <= amount_in_b {
+            return None;
+        }
+        let amounts = amounts_a.iter().zip(amounts_b.iter()).map(|(a, b)| a + b).collect();
+        let min_amount_out = min_amount_out_a + min_amount_out_b;
+        Some(ArbOpportunity {
+            amounts,
+            min_amount_out,
+            expected_profit: min_amount_out as i128 - opportunity.amount_in as i128,
+            split: Some(SplitLeg {
+                leg,
+                pool,
+                amount_in: amount_in_b,
+                min_amount_out: min_amount_out_b,
+            }),
+            ..opportunity.clone()
+        })
+    }
 
-        for i in 0..mint_idxs.len() - 1 {
-            let [mint_idx0, mint_idx1] = [mint_idxs[i], mint_idxs[i + 1]];
-            let [mint0, mint1] = [self.token_mints[mint_idx0], self.token_mints[mint_idx1]];
-            let pool = &pools[i];
+    /// Largest input (scaled start mint, at most `amount`) the route's order
+    /// book legs fill within `params.max_book_impact_bps` and its pools'
+    /// `max_trade_size` overrides allow, `None` if every leg takes `amount`.
+    /// A leg's capacity is in its own input mint, so it
+    /// is scaled back to the start mint by the ratio of the two amounts on
+    /// the route quoted from `amount`.
+    fn route_capacity(&self, amount: u128, path: &[usize], pool_path: &[PoolQuote]) -> Option<u128> {
+        let mut capacity: Option<u128> = None;
+        for (hop, pool) in self.route_hops(amount, path, pool_path).iter().zip(pool_path) {
+            let book = pool.0.get_max_input_within(
+                &hop.mint_in,
+                &hop.mint_out,
+                self.params.max_book_impact_bps,
+            );
+            let limit = self.params.max_trade_size(&pool.0.get_address());
+            let max_in = match book.into_iter().chain(limit).min() {
+                Some(max_in) if max_in < hop.amount_in => max_in,
+                _ => continue,
+            };
+            let leg_capacity = amount * max_in / hop.amount_in;
+            capacity = Some(capacity.map_or(leg_capacity, |c| c.min(leg_capacity)));
+        }
+        capacity
+    }
 
-            let swap_ix = pool
+    /// Re-quotes the route hop by hop from `init_balance`
+    fn route_hops(&self, init_balance: u128, path: &[usize], pool_path: &[PoolQuote]) -> Vec<Hop> {
+        let mut hops = vec![];
+        let mut amount_in = init_balance;
+        for (i, pool) in pool_path.iter().enumerate() {
+            let mint_in = self.token_mints[path[i]];
+            let mint_out = self.token_mints[path[i + 1]];
+            // a hop that can't be quoted any more makes the route worthless
+            let amount_out = pool
                 .0
-                .swap_ix(&self.program, &self.owner.pubkey(), &mint0, &mint1);
-            ixs.push(swap_ix);
+                .get_quote_with_amounts_scaled(amount_in, &mint_in, &mint_out)
+                .unwrap_or(0);
+            hops.push(Hop {
+                pool: pool.0.get_id(),
+                mint_in,
+                mint_out,
+                amount_in,
+                amount_out,
+                decimals_in: pool.0.mint_2_scale(&mint_in),
+                decimals_out: pool.0.mint_2_scale(&mint_out),
+            });
+            amount_in = amount_out;
         }
+        hops
+    }
+}
 
-        // PROFIT OR REVERT instruction
-        let ix = self
-            .program
-            .request()
-            .accounts(tmp_accounts::TokenAndSwapState {
-                src: src_ata,
-                swap_state: swap_state_pda,
-            })
-            .args(tmp_ix::ProfitOrRevert {})
-            .instructions()
-            .unwrap();
-        ixs.push(ix);
-
-        // flatten to Vec<Instructions>
-        ixs.concat()
-    }
-
-    fn send_ixs(&self, ixs: Vec<Instruction>) {
-        let owner: &Keypair = self.owner.borrow();
-        let tx = Transaction::new_signed_with_payer(
-            &ixs,
-            Some(&owner.pubkey()),
-            &[owner],
-            self.connection.get_latest_blockhash().unwrap(),
-        );
+/// Input then quoted output of every hop of a chain swapping `amount_in`
+/// through `pools`, on the pools as the swaps before it in the transaction
+/// left them (`moved`, by address, which takes this chain's swaps too);
+/// `None` if a hop pays nothing or a pool it shares with an earlier swap
+/// doesn't follow its state (see `PoolOperations::after_swap`)
+fn quote_chain(
+    amount_in: u128,
+    mints: &[Pubkey],
+    pools: &[PoolQuote],
+    moved: &mut HashMap<Pubkey, Option<PoolQuote>>,
+) -> Option<Vec<u128>> {
+    let mut amounts = vec![amount_in];
+    let mut amount = amount_in;
+    for (i, pool) in pools.iter().enumerate() {
+        let (mint_in, mint_out) = (&mints[i], &mints[i + 1]);
+        let address = pool.0.get_address();
+        let pool = match moved.get(&address) {
+            Some(after) => after.clone()?,
+            None => pool.clone(),
+        };
+        let amount_out = pool
+            .0
+            .get_quote_with_amounts_scaled(amount, mint_in, mint_out)
+            .ok()
+            .filter(|out| *out > 0)?;
+        let after = pool.0.after_swap(amount, mint_in, mint_out);
+        moved.insert(address, after.map(|after| PoolQuote::new(Rc::new(after))));
+        amounts.push(amount_out);
+        amount = amount_out;
+    }
+    Some(amounts)
+}
+
+/// Most profitable first, ties by route so the order is the same every run
+fn sort_opportunities(opportunities: &mut [ArbOpportunity]) {
+    opportunities.sort_by(|a, b| {
+        b.expected_profit
+            .cmp(&a.expected_profit)
+            .then_with(|| a.route.cmp(&b.route))
+    });
+}
 
-        if self.cluster == Cluster::Localnet {
-            let res = self.connection.simulate_transaction(&tx).unwrap();
-            println!("{:#?}", res);
-        } else if self.cluster == Cluster::Mainnet {
-            let signature = self
-                .connection
-                .send_transaction_with_config(
-                    &tx,
-                    RpcSendTransactionConfig {
-                        skip_preflight: true,
-                        ..RpcSendTransactionConfig::default()
-                    },
-                )
-                .unwrap();
-            println!("signature: {:?}", signature);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::graph::PoolSet;
+    use crate::pools::PoolVariant;
+    use crate::tests::pools::orca_pool;
+
+    fn arbitrager() -> Arbitrager {
+        let config: Config = serde_json::from_str(r#"{ "fee_bps": 10 }"#).unwrap();
+        let mut params = RuntimeParams::from_config(&config);
+        params.slippage_bps = 0;
+        let (token_mints, graph_edges, graph) = PoolSet::new().into_graph();
+        Arbitrager {
+            token_mints,
+            graph_edges,
+            graph,
+            params,
+            flash_loan: None,
+            current_slot: 0,
+            shutdown: Shutdown::default(),
+            sizer: None,
+            slippage: None,
+            exposure: None,
+            quarantine: Arc::new(PoolQuarantine::new(&config.quarantine)),
+            latency: Arc::new(LatencyStats::new()),
+            first_hops: None,
         }
     }
+
+    #[test]
+    fn second_chains_are_quoted_on_the_pools_the_first_moved() {
+        let (usdc, sol) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let pool = |reserve_usdc, reserve_sol| {
+            PoolQuote::new(Rc::new(PoolVariant::from(orca_pool(&usdc, &sol, reserve_usdc, reserve_sol))))
+        };
+        // sol is cheap in the two deep pools and dear in the shallow one
+        // both chains sell it back through
+        let (deep_a, deep_b) = (pool(10_000_000, 20_000_000), pool(10_000_000, 20_000_000));
+        let shallow = pool(1_000_000, 1_000_000);
+        let quote = |pool: &PoolQuote, amount_in, mint_in: &Pubkey, mint_out: &Pubkey| {
+            pool.0.get_quote_with_amounts_scaled(amount_in, mint_in, mint_out).unwrap()
+        };
+        let sol_out = quote(&deep_a, 100_000, &usdc, &sol);
+        let opportunity = ArbOpportunity {
+            route: "0->1->0".to_string(),
+            mints: vec![usdc, sol, usdc],
+            pools: vec![deep_a.clone(), shallow.clone()],
+            amount_in: 100_000,
+            amounts: vec![100_000, sol_out, quote(&shallow, sol_out, &sol, &usdc)],
+            min_amount_out: 0,
+            expected_profit: 0,
+            found_slot: 0,
+            found_at: Instant::now(),
+            split: None,
+        };
+        let split = Split {
+            amount_in_a: 50_000,
+            amount_in_b: 50_000,
+            amount_out_a: quote(&deep_a, 50_000, &usdc, &sol),
+            amount_out_b: quote(&deep_b, 50_000, &usdc, &sol),
+        };
+
+        let divided = arbitrager().split_at(&opportunity, 0, deep_b.clone(), split).unwrap();
+        let leg = divided.split.as_ref().unwrap();
+        // the first chain sells into the shallow pool as it is
+        let sol_a = quote(&deep_a, 50_000, &usdc, &sol);
+        let usdc_a = quote(&shallow, sol_a, &sol, &usdc);
+        // the second one sells after it, for less than the pool paid before
+        let moved = PoolQuote::new(Rc::new(shallow.0.after_swap(sol_a, &sol, &usdc).unwrap()));
+        let sol_b = quote(&deep_b, 50_000, &usdc, &sol);
+        let usdc_b = quote(&moved, sol_b, &sol, &usdc);
+        assert!(usdc_b < quote(&shallow, sol_b, &sol, &usdc));
+        assert_eq!((leg.amount_in, leg.min_amount_out), (50_000, usdc_b));
+        assert_eq!(divided.min_amount_out, usdc_a + usdc_b);
+        assert_eq!(divided.amounts, vec![100_000, sol_a + sol_b, usdc_a + usdc_b]);
+    }
 }
@@ -0,0 +1,162 @@
+//! Background blockhash cache
+//!
+//! Fetching a blockhash before signing puts an RPC round trip on the path of
+//! every send. With `blockhash_cache` set a background thread fetches the
+//! latest blockhash (and the last block height it is valid at) at the sends'
+//! commitment every `refresh_ms`, and the dispatcher signs with the cached
+//! one, read without a lock. It only fetches a blockhash itself while the
+//! cache is empty or older than `max_age_ms`, e.g. while the node is down.
+
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use arc_swap::ArcSwapOption;
+use serde::{Deserialize, Serialize};
+use solana_sdk::hash::Hash;
+
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, warn};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BlockhashCacheConfig {
+    /// Time between two fetches of the latest blockhash
+    #[serde(default = "default_refresh_ms")]
+    pub refresh_ms: u64,
+    /// Oldest cached blockhash transactions are signed with
+    #[serde(default = "default_max_age_ms")]
+    pub max_age_ms: u64,
+}
+
+fn default_refresh_ms() -> u64 {
+    400
+}
+
+fn default_max_age_ms() -> u64 {
+    10_000
+}
+
+impl BlockhashCacheConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.refresh_ms == 0 {
+            problems.push("blockhash_cache.refresh_ms must be > 0".to_string());
+        }
+        if self.max_age_ms <= self.refresh_ms {
+            problems.push("blockhash_cache.max_age_ms must be > refresh_ms".to_string());
+        }
+        problems
+    }
+}
+
+/// A fetched blockhash
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedBlockhash {
+    pub blockhash: Hash,
+    /// Last block height a transaction signed with it can land at
+    pub last_valid_block_height: u64,
+    pub fetched_at: Instant,
+}
+
+/// Latest blockhash at one commitment, refreshed in the background
+pub struct BlockhashCache {
+    commitment: CommitmentConfig,
+    max_age: Duration,
+    latest: Arc<ArcSwapOption<CachedBlockhash>>,
+}
+
+impl BlockhashCache {
+    /// An empty cache of blockhashes at `commitment`, nothing refreshes it
+    pub fn new(config: &BlockhashCacheConfig, commitment: CommitmentConfig) -> Self {
+        Self {
+            commitment,
+            max_age: Duration::from_millis(config.max_age_ms),
+            latest: Arc::new(ArcSwapOption::empty()),
+        }
+    }
+
+    /// A cache refreshed from `url` every `refresh_ms` by a background
+    /// thread, which stops once the cache is dropped
+    pub fn spawn(url: &str, config: &BlockhashCacheConfig, commitment: CommitmentConfig) -> Self {
+        let cache = Self::new(config, commitment);
+        let latest = Arc::downgrade(&cache.latest);
+        let client = RpcClient::new_with_commitment(url.to_string(), commitment);
+        let refresh = Duration::from_millis(config.refresh_ms);
+        thread::spawn(move || refresh_loop(client, commitment, refresh, latest));
+        cache
+    }
+
+    /// Commitment of the cached blockhashes
+    pub fn commitment(&self) -> CommitmentConfig {
+        self.commitment
+    }
+
+    /// Caches a blockhash fetched at `fetched_at`
+    pub fn store(&self, blockhash: Hash, last_valid_block_height: u64, fetched_at: Instant) {
+        self.latest.store(Some(Arc::new(CachedBlockhash {
+            blockhash,
+            last_valid_block_height,
+            fetched_at,
+        })));
+    }
+
+    /// The cached blockhash, `None` if there is none yet or it is older
+    /// than `max_age_ms`
+    pub fn get(&self) -> Option<Arc<CachedBlockhash>> {
+        self.latest
+            .load_full()
+            .filter(|cached| cached.fetched_at.elapsed() <= self.max_age)
+    }
+}
+
+fn refresh_loop(
+    client: RpcClient,
+    commitment: CommitmentConfig,
+    refresh: Duration,
+    latest: Weak<ArcSwapOption<CachedBlockhash>>,
+) {
+    loop {
+        let started = Instant::now();
+        let fetched = client.get_latest_blockhash_with_commitment(commitment);
+        let latest = match latest.upgrade() {
+            Some(latest) => latest,
+            None => return, // the cache is gone
+        };
+        match fetched {
+            Ok((blockhash, last_valid_block_height)) => {
+                debug!("cached blockhash {} (valid up to {})", blockhash, last_valid_block_height);
+                latest.store(Some(Arc::new(CachedBlockhash {
+                    blockhash,
+                    last_valid_block_height,
+                    fetched_at: started,
+                })));
+            }
+            Err(e) => warn!("failed to refresh the blockhash: {}", e),
+        }
+        drop(latest);
+        thread::sleep(refresh.saturating_sub(started.elapsed()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_blockhashes_are_not_served() {
+        let config: BlockhashCacheConfig = serde_json::from_str("{}").unwrap();
+        assert!(config.problems().is_empty());
+        let cache = BlockhashCache::new(&config, CommitmentConfig::confirmed());
+        assert!(cache.get().is_none());
+
+        let blockhash = Hash::new_unique();
+        cache.store(blockhash, 100, Instant::now());
+        let cached = cache.get().unwrap();
+        assert_eq!((cached.blockhash, cached.last_valid_block_height), (blockhash, 100));
+
+        cache.store(Hash::new_unique(), 101, Instant::now() - Duration::from_secs(11));
+        assert!(cache.get().is_none());
+    }
+}
@@ -0,0 +1,278 @@
+//! Multi-route Jito bundles
+//!
+//! With `bundle` set, profitable routes found during a search round aren't sent
+//! one by one as they are found. They are collected and, once the round is
+//! over, the most profitable set of disjoint routes (no write lock in common,
+//! so one route's fill can't move another's price, see `locks`) goes out as a
+//! single Jito bundle: one transaction per route, executed in order and
+//! atomically, all of them land or none does. A versioned transaction could
+//! hold several routes at once, but needs a newer solana-sdk than the one this
+//! crate builds with.
+//!
+//! Routes are only added while the bundle stays within its limits: at most
+//! `max_routes` routes and 5 transactions (a route too large for one
+//! transaction takes several, see `tx_size`), at most
+//! `MAX_TX_ACCOUNT_LOCKS` accounts per transaction and at most
+//! `max_compute_units` estimated compute units over the whole bundle. The
+//! tips of the routes are paid once, by the last transaction.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use reqwest::blocking::Client as HttpClient;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::transaction::Transaction;
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Most transactions a Jito bundle takes
+pub const MAX_BUNDLE_TXS: usize = 5;
+
+/// Most accounts a transaction may lock
+pub const MAX_TX_ACCOUNT_LOCKS: usize = 64;
+
+/// Bundle executor settings
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BundleConfig {
+    /// Jito block engine bundle endpoint, e.g.
+    /// `https://mainnet.block-engine.jito.wtf/api/v1/bundles`
+    pub url: String,
+    /// Most routes per bundle
+    #[serde(default = "default_max_routes")]
+    pub max_routes: usize,
+    /// Most estimated compute units over all transactions of a bundle
+    #[serde(default = "default_max_compute_units")]
+    pub max_compute_units: u64,
+}
+
+fn default_max_routes() -> usize {
+    MAX_BUNDLE_TXS
+}
+
+fn default_max_compute_units() -> u64 {
+    7_000_000
+}
+
+impl BundleConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.url.is_empty() {
+            problems.push("bundle.url is empty".to_string());
+        }
+        if !(1..=MAX_BUNDLE_TXS).contains(&self.max_routes) {
+            problems.push(format!(
+                "bundle.max_routes must be in 1..={}, got {}",
+                MAX_BUNDLE_TXS, self.max_routes
+            ));
+        }
+        if self.max_compute_units == 0 {
+            problems.push("bundle.max_compute_units must be > 0".to_string());
+        }
+        problems
+    }
+}
+
+/// A profitable route waiting for the end of the search round
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub route: String,
+    /// DEXes the route swaps on (see `ComputeEstimator`)
+    pub shape: String,
+    pub profit: i128,
+    pub tip: u64,
+    /// Instructions of each of the route's transactions, usually just one
+    pub txs: Vec<Vec<Instruction>>,
+    /// Accounts the route writes, the owner's aside (see `locks`)
+    pub write_locks: HashSet<Pubkey>,
+    /// Ids of the pools on the route, in route order
+    pub pool_ids: Vec<String>,
+    /// Compute estimate of the route's transaction
+    pub compute_units: u64,
+}
+
+impl Candidate {
+    /// Most accounts one of the route's transactions locks (programs included)
+    pub fn account_locks(&self) -> usize {
+        self.txs
+            .iter()
+            .map(|ixs| {
+                let mut keys: HashSet<Pubkey> = HashSet::new();
+                for ix in ixs.iter() {
+                    keys.insert(ix.program_id);
+                    keys.extend(ix.accounts.iter().map(|meta| meta.pubkey));
+                }
+                keys.len()
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// The most profitable set of disjoint candidates within the limits, most
+/// profitable first (greedy by profit)
+pub fn pick(mut candidates: Vec<Candidate>, max_routes: usize, max_compute_units: u64) -> Vec<Candidate> {
+    candidates.sort_by(|a, b| b.profit.cmp(&a.profit).then(a.route.cmp(&b.route)));
+    let mut picked: Vec<Candidate> = vec![];
+    let mut locked: HashSet<Pubkey> = HashSet::new();
+    let mut compute_units = 0;
+    let mut txs = 0;
+    for candidate in candidates {
+        if picked.len() == max_routes {
+            break;
+        }
+        if candidate.account_locks() > MAX_TX_ACCOUNT_LOCKS
+            || txs + candidate.txs.len() > MAX_BUNDLE_TXS
+            || compute_units + candidate.compute_units > max_compute_units
+            || !candidate.write_locks.is_disjoint(&locked)
+        {
+            continue;
+        }
+        compute_units += candidate.compute_units;
+        txs += candidate.txs.len();
+        locked.extend(candidate.write_locks.iter().copied());
+        picked.push(candidate);
+    }
+    picked
+}
+
+/// Collects the routes of a search round and submits bundles
+pub struct Bundler {
+    pub config: BundleConfig,
+    http: HttpClient,
+    pending: Mutex<Vec<Candidate>>,
+}
+
+impl Bundler {
+    pub fn new(config: &BundleConfig) -> Self {
+        Self {
+            config: config.clone(),
+            http: HttpClient::builder()
+                .timeout(Duration::from_secs(5))
+                .build()
+                .expect("http client"),
+            pending: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn push(&self, candidate: Candidate) {
+        self.pending.lock().unwrap().push(candidate);
+    }
+
+    /// Picks the routes of the next bundle and forgets the rest
+    pub fn take(&self) -> Vec<Candidate> {
+        let candidates = std::mem::take(&mut *self.pending.lock().unwrap());
+        pick(candidates, self.config.max_routes, self.config.max_compute_units)
+    }
+
+    /// Submits signed transactions as one bundle, returns the bundle id
+    pub fn send(&self, txs: &[Transaction]) -> Result<String, String> {
+        let encoded = txs
+            .iter()
+            .map(|tx| bincode::serialize(tx).map(base64::encode))
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(|e| e.to_string())?;
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "sendBundle",
+            "params": [encoded, { "encoding": "base64" }],
+        });
+        let response: Value = self
+            .http
+            .post(&self.config.url)
+            .json(&body)
+            .send()
+            .and_then(|r| r.json())
+            .map_err(|e| e.to_string())?;
+        if let Some(err) = response.get("error") {
+            return Err(err.to_string());
+        }
+        response["result"]
+            .as_str()
+            .map(|id| id.to_string())
+            .ok_or_else(|| format!("unexpected sendBundle response: {}", response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::instruction::AccountMeta;
+
+    fn candidate(route: &str, profit: i128, pools: &[Pubkey], compute_units: u64) -> Candidate {
+        split_candidate(route, profit, pools, compute_units, 1)
+    }
+
+    fn split_candidate(
+        route: &str,
+        profit: i128,
+        pools: &[Pubkey],
+        compute_units: u64,
+        txs: usize,
+    ) -> Candidate {
+        let ix = Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: pools.iter().map(|p| AccountMeta::new(*p, false)).collect(),
+            data: vec![],
+        };
+        Candidate {
+            route: route.to_string(),
+            shape: "Orca".to_string(),
+            profit,
+            tip: 0,
+            txs: vec![vec![ix]; txs],
+            write_locks: pools.iter().copied().collect(),
+            pool_ids: vec![],
+            compute_units,
+        }
+    }
+
+    #[test]
+    fn picks_disjoint_routes_by_profit() {
+        let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let picked = pick(
+            vec![
+                candidate("ab", 10, &[a, b], 100),
+                candidate("bc", 30, &[b, c], 100),
+                candidate("a", 5, &[a], 100),
+                candidate("c", 50, &[c], 100),
+            ],
+            5,
+            1_000,
+        );
+        // bc shares c with the more profitable c, a shares a with ab
+        let routes: Vec<&str> = picked.iter().map(|c| c.route.as_str()).collect();
+        assert_eq!(routes, vec!["c", "ab"]);
+    }
+
+    #[test]
+    fn limits_are_respected() {
+        let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let candidates: Vec<Candidate> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| candidate(&i.to_string(), i as i128, &[*key], 400))
+            .collect();
+        assert_eq!(pick(candidates.clone(), 2, 10_000).len(), 2);
+        assert_eq!(pick(candidates, 5, 1_000).len(), 2);
+
+        let many: Vec<Pubkey> = (0..MAX_TX_ACCOUNT_LOCKS).map(|_| Pubkey::new_unique()).collect();
+        assert!(pick(vec![candidate("big", 1, &many, 1)], 5, 1_000).is_empty());
+
+        // a split route takes several of the bundle's transactions
+        let picked = pick(
+            vec![
+                split_candidate("split", 10, &[keys[0]], 1, 4),
+                split_candidate("also split", 5, &[keys[1]], 1, 2),
+                candidate("single", 1, &[keys[2]], 1),
+            ],
+            5,
+            1_000,
+        );
+        let routes: Vec<&str> = picked.iter().map(|c| c.route.as_str()).collect();
+        assert_eq!(routes, vec!["split", "single"]);
+    }
+}
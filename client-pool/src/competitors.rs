@@ -0,0 +1,355 @@
+//! Competitor analysis of landed arbitrages
+//!
+//! `competitors` reads the last `--slots` blocks and picks the successful
+//! transactions that touch at least two of the loaded pools. One of those is
+//! an arbitrage when its signer's token balances only went up: every mint it
+//! swapped through came back to zero and at least one ended higher (or, for
+//! routes starting from SOL, the signer's lamports grew by more than the fee
+//! and tip). The report lists who lands them, the route shapes (DEXes in
+//! account order), the Jito tips they paid, and where in the block they
+//! landed, to set our own tips and latency targets against.
+
+use anchor_client::solana_client::rpc_config::RpcBlockConfig;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use serde::Serialize;
+use solana_transaction_status::{
+    EncodedTransaction, EncodedTransactionWithStatusMeta, TransactionDetails, UiMessage,
+    UiTransactionEncoding, UiTransactionTokenBalance,
+};
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use tracing::{info, warn};
+
+use crate::constants::JITO_TIP_ACCOUNTS;
+use crate::graph::PoolSet;
+use crate::pool::PoolOperations;
+use crate::rpc::RpcPool;
+
+/// Signers and shapes listed in the report
+const TOP_ENTRIES: usize = 10;
+
+/// What the report needs of a landed transaction
+#[derive(Debug, Clone, Default)]
+pub struct TxView {
+    pub signature: String,
+    /// Index of the transaction in its block
+    pub position: usize,
+    pub account_keys: Vec<Pubkey>,
+    pub fee: u64,
+    /// Lamport change of every account key
+    pub lamport_deltas: Vec<i128>,
+    /// Token change per `(owner, mint)`
+    pub token_deltas: HashMap<(String, String), i128>,
+}
+
+impl TxView {
+    /// The view of a successful JSON-encoded transaction
+    pub fn from_encoded(tx: &EncodedTransactionWithStatusMeta, position: usize) -> Option<Self> {
+        let meta = tx.meta.as_ref()?;
+        if meta.err.is_some() {
+            return None;
+        }
+        let ui = match &tx.transaction {
+            EncodedTransaction::Json(ui) => ui,
+            _ => return None,
+        };
+        let keys = match &ui.message {
+            UiMessage::Raw(raw) => &raw.account_keys,
+            _ => return None,
+        };
+        let account_keys = keys
+            .iter()
+            .map(|key| key.parse().ok())
+            .collect::<Option<Vec<Pubkey>>>()?;
+        let lamport_deltas = meta
+            .pre_balances
+            .iter()
+            .zip(meta.post_balances.iter())
+            .map(|(pre, post)| *post as i128 - *pre as i128)
+            .collect();
+        let mut token_deltas = HashMap::new();
+        let balances = |balances: &Option<Vec<UiTransactionTokenBalance>>, sign: i128| {
+            balances
+                .iter()
+                .flatten()
+                .filter_map(|balance| {
+                    let owner = balance.owner.clone()?;
+                    let amount: i128 = balance.ui_token_amount.amount.parse().ok()?;
+                    Some(((owner, balance.mint.clone()), sign * amount))
+                })
+                .collect::<Vec<_>>()
+        };
+        for (key, amount) in balances(&meta.pre_token_balances, -1)
+            .into_iter()
+            .chain(balances(&meta.post_token_balances, 1))
+        {
+            *token_deltas.entry(key).or_insert(0) += amount;
+        }
+        Some(Self {
+            signature: ui.signatures.first()?.clone(),
+            position,
+            account_keys,
+            fee: meta.fee,
+            lamport_deltas,
+            token_deltas,
+        })
+    }
+}
+
+/// A competitor's landed arbitrage
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArbTx {
+    pub slot: u64,
+    pub position: usize,
+    pub signature: String,
+    pub signer: String,
+    /// DEXes of the touched pools in account order, e.g. `Orca,Raydium`
+    pub shape: String,
+    pub pools: Vec<String>,
+    /// Mint the profit was made in, `SOL` for lamports
+    pub profit_mint: String,
+    /// Profit in the mint's scaled units (lamports for SOL, net of fee and tip)
+    pub profit: i128,
+    pub tip_lamports: u64,
+    pub fee_lamports: u64,
+}
+
+/// The arbitrage in `tx`, if it is one through at least two of `pools`
+/// (address -> `(id, dex)`)
+pub fn classify(
+    slot: u64,
+    tx: &TxView,
+    pools: &HashMap<Pubkey, (String, String)>,
+    tip_accounts: &HashSet<Pubkey>,
+) -> Option<ArbTx> {
+    let mut touched = vec![];
+    for key in tx.account_keys.iter() {
+        if let Some(pool) = pools.get(key) {
+            if !touched.contains(&pool) {
+                touched.push(pool);
+            }
+        }
+    }
+    if touched.len() < 2 {
+        return None;
+    }
+    let signer = tx.account_keys.first()?.to_string();
+    let signer_deltas: Vec<(&String, i128)> = tx
+        .token_deltas
+        .iter()
+        .filter(|((owner, _), delta)| *owner == signer && **delta != 0)
+        .map(|((_, mint), delta)| (mint, *delta))
+        .collect();
+    if signer_deltas.iter().any(|(_, delta)| *delta < 0) {
+        return None;
+    }
+    let tip: i128 = tx
+        .account_keys
+        .iter()
+        .zip(tx.lamport_deltas.iter())
+        .filter(|(key, delta)| tip_accounts.contains(*key) && **delta > 0)
+        .map(|(_, delta)| *delta)
+        .sum();
+    let (profit_mint, profit) = match signer_deltas.iter().max_by_key(|(_, delta)| *delta) {
+        Some((mint, delta)) => (mint.to_string(), *delta),
+        None => {
+            // a route from SOL: what the signer has left after fee and tip
+            let lamports = *tx.lamport_deltas.first()?;
+            let gross = lamports + tx.fee as i128 + tip;
+            if gross <= 0 {
+                return None;
+            }
+            ("SOL".to_string(), lamports)
+        }
+    };
+    Some(ArbTx {
+        slot,
+        position: tx.position,
+        signature: tx.signature.clone(),
+        signer,
+        shape: touched.iter().map(|(_, dex)| dex.as_str()).collect::<Vec<&str>>().join(","),
+        pools: touched.iter().map(|(id, _)| id.clone()).collect(),
+        profit_mint,
+        profit,
+        tip_lamports: tip as u64,
+        fee_lamports: tx.fee,
+    })
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CompetitorReport {
+    pub slots_scanned: u64,
+    /// Successful transactions through at least two loaded pools
+    pub candidates: u64,
+    pub arbitrages: Vec<ArbTx>,
+    /// Arbitrages per signer, most first
+    pub signers: Vec<(String, u64)>,
+    /// Arbitrages per route shape, most first
+    pub shapes: Vec<(String, u64)>,
+    /// Tip percentiles of the arbitrages: p50, p90, p99, max (lamports)
+    pub tip_percentiles: [u64; 4],
+    /// Median index of the arbitrages in their blocks
+    pub median_position: usize,
+}
+
+impl CompetitorReport {
+    pub fn new(slots_scanned: u64, candidates: u64, arbitrages: Vec<ArbTx>) -> Self {
+        let mut tips: Vec<u64> = arbitrages.iter().map(|arb| arb.tip_lamports).collect();
+        tips.sort_unstable();
+        let mut positions: Vec<usize> = arbitrages.iter().map(|arb| arb.position).collect();
+        positions.sort_unstable();
+        let percentile = |p: usize| tips.get((tips.len() * p / 100).min(tips.len().saturating_sub(1)));
+        Self {
+            slots_scanned,
+            candidates,
+            signers: ranked(arbitrages.iter().map(|arb| arb.signer.clone())),
+            shapes: ranked(arbitrages.iter().map(|arb| arb.shape.clone())),
+            tip_percentiles: [
+                percentile(50).copied().unwrap_or(0),
+                percentile(90).copied().unwrap_or(0),
+                percentile(99).copied().unwrap_or(0),
+                tips.last().copied().unwrap_or(0),
+            ],
+            median_position: positions.get(positions.len() / 2).copied().unwrap_or(0),
+            arbitrages,
+        }
+    }
+}
+
+/// Counts of every value, most frequent first, at most `TOP_ENTRIES`
+fn ranked(values: impl Iterator<Item = String>) -> Vec<(String, u64)> {
+    let mut counts: HashMap<String, u64> = HashMap::new();
+    for value in values {
+        *counts.entry(value).or_insert(0) += 1;
+    }
+    let mut counts: Vec<(String, u64)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    counts.truncate(TOP_ENTRIES);
+    counts
+}
+
+impl fmt::Display for CompetitorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "===== competitors =====")?;
+        writeln!(
+            f,
+            "{} slots, {} transactions through our pools, {} arbitrages",
+            self.slots_scanned,
+            self.candidates,
+            self.arbitrages.len()
+        )?;
+        let [p50, p90, p99, max] = self.tip_percentiles;
+        writeln!(f, "tips (lamports): p50 {} / p90 {} / p99 {} / max {}", p50, p90, p99, max)?;
+        writeln!(f, "median position in block: {}", self.median_position)?;
+        writeln!(f, "top signers:")?;
+        for (signer, count) in self.signers.iter() {
+            writeln!(f, "  {:>6} {}", count, signer)?;
+        }
+        writeln!(f, "route shapes:")?;
+        for (shape, count) in self.shapes.iter() {
+            writeln!(f, "  {:>6} {}", count, shape)?;
+        }
+        Ok(())
+    }
+}
+
+/// Scans the `slots` blocks before the current slot for arbitrages through
+/// the pools of `pool_set`
+pub fn scan(rpc: &RpcPool, pool_set: &PoolSet, slots: u64) -> Result<CompetitorReport, String> {
+    let pools: HashMap<Pubkey, (String, String)> = pool_set
+        .pools
+        .iter()
+        .map(|pool| (pool.get_address(), (pool.get_id(), pool.get_name())))
+        .collect();
+    let tip_accounts: HashSet<Pubkey> = JITO_TIP_ACCOUNTS.iter().copied().collect();
+    let last = rpc.call(|client| client.get_slot()).map_err(|e| e.to_string())?;
+    let config = RpcBlockConfig {
+        encoding: Some(UiTransactionEncoding::Json),
+        transaction_details: Some(TransactionDetails::Full),
+        rewards: Some(false),
+        ..RpcBlockConfig::default()
+    };
+    let (mut candidates, mut arbitrages) = (0, vec![]);
+    for slot in last.saturating_sub(slots)..last {
+        // skipped slots have no block
+        let block = match rpc.call(|client| client.get_block_with_config(slot, config.clone())) {
+            Ok(block) => block,
+            Err(e) => {
+                warn!("no block at slot {}: {}", slot, e);
+                continue;
+            }
+        };
+        for (position, tx) in block.transactions.iter().flatten().enumerate() {
+            let view = match TxView::from_encoded(tx, position) {
+                Some(view) => view,
+                None => continue,
+            };
+            if view.account_keys.iter().filter(|key| pools.contains_key(*key)).count() < 2 {
+                continue;
+            }
+            candidates += 1;
+            if let Some(arb) = classify(slot, &view, &pools, &tip_accounts) {
+                arbitrages.push(arb);
+            }
+        }
+    }
+    info!("scanned {} slots, {} arbitrages", slots, arbitrages.len());
+    Ok(CompetitorReport::new(slots, candidates, arbitrages))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pools(keys: &[Pubkey]) -> HashMap<Pubkey, (String, String)> {
+        keys.iter()
+            .zip(["Orca", "Raydium"])
+            .map(|(key, dex)| {
+                let id = format!("{}:{}", dex.to_lowercase(), &key.to_string()[..8]);
+                (*key, (id, dex.to_string()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn only_round_trips_that_gain_are_arbitrages() {
+        let (signer, orca, raydium, tip) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let pools = pools(&[orca, raydium]);
+        let tips: HashSet<Pubkey> = [tip].into_iter().collect();
+        let owner = signer.to_string();
+        let mut tx = TxView {
+            signature: "sig".to_string(),
+            position: 3,
+            account_keys: vec![signer, orca, raydium, tip],
+            fee: 5_000,
+            lamport_deltas: vec![-15_000, 0, 0, 10_000],
+            token_deltas: [
+                ((owner.clone(), "USDC".to_string()), 1_500),
+                ((owner.clone(), "SOL".to_string()), 0),
+                ((orca.to_string(), "USDC".to_string()), -1_500),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        let arb = classify(7, &tx, &pools, &tips).unwrap();
+        assert_eq!(arb.shape, "Orca,Raydium");
+        assert_eq!((arb.profit_mint.as_str(), arb.profit), ("USDC", 1_500));
+        assert_eq!(arb.tip_lamports, 10_000);
+
+        // a plain swap spends one mint for another
+        tx.token_deltas.insert((owner, "SOL".to_string()), -2);
+        assert_eq!(classify(7, &tx, &pools, &tips), None);
+
+        let report = CompetitorReport::new(1, 1, vec![arb]);
+        assert_eq!(report.tip_percentiles, [10_000; 4]);
+        assert_eq!(report.shapes, vec![("Orca,Raydium".to_string(), 1)]);
+        assert_eq!(report.median_position, 3);
+    }
+}
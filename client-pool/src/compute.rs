@@ -0,0 +1,247 @@
+//! Compute-unit limits from simulation
+//!
+//! Without a limit every transaction requests the default (200k per
+//! instruction, up to 1.4M), and priority fees are charged on the requested
+//! units. The first time a route shape (the sequence of DEXes it swaps on) is
+//! sent, the transaction is simulated and the units it consumed are cached;
+//! every send of that shape then requests the largest consumption seen plus
+//! `margin_bps`.
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::compute_budget;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::transaction::Transaction;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use tracing::{debug, warn};
+
+use crate::rpc_api::RpcApi;
+
+/// Most units a transaction can request
+pub const MAX_COMPUTE_UNITS: u32 = 1_400_000;
+
+/// `ComputeBudgetInstruction::SetComputeUnitLimit` / `SetComputeUnitPrice`
+/// tags, built by hand since the pinned sdk predates the instructions
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+
+/// Compute-unit limit settings
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ComputeConfig {
+    /// Request simulated units instead of the default
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Headroom on top of the simulated units (bps)
+    #[serde(default = "default_margin_bps")]
+    pub margin_bps: u64,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_margin_bps() -> u64 {
+    1_000
+}
+
+impl Default for ComputeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            margin_bps: default_margin_bps(),
+        }
+    }
+}
+
+impl ComputeConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.margin_bps > 10_000 {
+            problems.push(format!("compute.margin_bps must be <= 10000, got {}", self.margin_bps));
+        }
+        problems
+    }
+}
+
+/// Instruction requesting `units` compute units for the transaction
+pub fn set_compute_unit_limit(units: u32) -> Instruction {
+    let mut data = vec![SET_COMPUTE_UNIT_LIMIT];
+    data.extend_from_slice(&units.to_le_bytes());
+    Instruction::new_with_bytes(compute_budget::id(), &data, vec![])
+}
+
+/// Instruction paying `micro_lamports` per requested compute unit
+pub fn set_compute_unit_price(micro_lamports: u64) -> Instruction {
+    let mut data = vec![SET_COMPUTE_UNIT_PRICE];
+    data.extend_from_slice(&micro_lamports.to_le_bytes());
+    Instruction::new_with_bytes(compute_budget::id(), &data, vec![])
+}
+
+/// Units consumed by the top-level instructions of a simulation, from its
+/// `Program .. consumed N of M compute units` log lines (CPIs are already
+/// included in their caller's consumption)
+pub fn consumed_units(logs: &[String]) -> Option<u64> {
+    let mut depth: i64 = 0;
+    let mut total = None;
+    for line in logs {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["Program", _, "invoke", level] => {
+                depth = level.trim_matches(|c| c == '[' || c == ']').parse().unwrap_or(depth);
+            }
+            ["Program", _, "consumed", units, "of", _, "compute", "units"] if depth == 1 => {
+                *total.get_or_insert(0) += units.parse::<u64>().ok()?;
+            }
+            ["Program", _, "success"] | ["Program", _, "failed:", ..] => {
+                depth -= 1;
+            }
+            _ => {}
+        }
+    }
+    total
+}
+
+/// Simulated compute units per route shape, see the module docs
+pub struct ComputeEstimator {
+    config: ComputeConfig,
+    units: Mutex<HashMap<String, u64>>,
+}
+
+impl ComputeEstimator {
+    pub fn new(config: &ComputeConfig) -> Self {
+        Self {
+            config: config.clone(),
+            units: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records a consumption of `shape`, the estimate is the largest seen
+    pub fn record(&self, shape: &str, units: u64) {
+        let mut cache = self.units.lock().unwrap();
+        let estimate = cache.entry(shape.to_string()).or_insert(0);
+        *estimate = (*estimate).max(units);
+    }
+
+    /// Limit to request for `shape`, `None` if it was never simulated
+    pub fn limit(&self, shape: &str) -> Option<u32> {
+        let units = *self.units.lock().unwrap().get(shape)?;
+        let limit = units + units * self.config.margin_bps / 10_000;
+        Some(limit.min(MAX_COMPUTE_UNITS as u64) as u32)
+    }
+
+    /// Limit instruction for `shape`, simulating `tx` first if the shape has
+    /// no estimate yet (no earlier than `min_context_slot` if set). `None` if
+    /// disabled or the simulation didn't succeed, in which case the default
+    /// limit is left in place.
+    pub fn limit_ix(
+        &self,
+        rpc: &dyn RpcApi,
+        shape: &str,
+        tx: &Transaction,
+        min_context_slot: Option<u64>,
+    ) -> Option<Instruction> {
+        if !self.config.enabled {
+            return None;
+        }
+        if self.limit(shape).is_none() {
+            let res = match rpc.simulate(tx, min_context_slot) {
+                Ok(res) => res.value,
+                Err(e) => {
+                    warn!("failed to simulate {}: {}", shape, e);
+                    return None;
+                }
+            };
+            if let Some(err) = res.err {
+                debug!("simulation of {} failed: {:?}", shape, err);
+                return None; // partial consumption would underestimate
+            }
+            let units = consumed_units(&res.logs.unwrap_or_default())?;
+            debug!("{} consumed {} compute units", shape, units);
+            self.record(shape, units);
+        }
+        self.limit(shape).map(set_compute_unit_limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc_api::MockRpc;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::signature::{Keypair, Signer};
+    use solana_sdk::transaction::TransactionError;
+
+    fn logs(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn only_top_level_consumption_is_counted() {
+        let logs = logs(&[
+            "Program Arb111 invoke [1]",
+            "Program Swap111 invoke [2]",
+            "Program Swap111 consumed 30000 of 170000 compute units",
+            "Program Swap111 success",
+            "Program Arb111 consumed 52000 of 200000 compute units",
+            "Program Arb111 success",
+            "Program Arb111 invoke [1]",
+            "Program log: profit",
+            "Program Arb111 consumed 8000 of 148000 compute units",
+            "Program Arb111 success",
+        ]);
+        assert_eq!(consumed_units(&logs), Some(60_000));
+        assert_eq!(consumed_units(&[]), None);
+    }
+
+    #[test]
+    fn limits_keep_the_largest_consumption_plus_margin() {
+        let estimator = ComputeEstimator::new(&ComputeConfig::default());
+        assert_eq!(estimator.limit("Orca,Raydium"), None);
+
+        estimator.record("Orca,Raydium", 100_000);
+        estimator.record("Orca,Raydium", 80_000);
+        assert_eq!(estimator.limit("Orca,Raydium"), Some(110_000));
+
+        estimator.record("Serum,Serum,Serum", 1_300_000);
+        assert_eq!(estimator.limit("Serum,Serum,Serum"), Some(MAX_COMPUTE_UNITS));
+    }
+
+    #[test]
+    fn shapes_are_simulated_once() {
+        let rpc = MockRpc::new(100);
+        let estimator = ComputeEstimator::new(&ComputeConfig::default());
+        let payer = Keypair::new();
+        let tx = Transaction::new_signed_with_payer(&[], Some(&payer.pubkey()), &[&payer], Hash::default());
+
+        // a failed simulation leaves the default limit in place
+        rpc.push_simulation(MockRpc::simulation(Some(TransactionError::AccountNotFound), &[]));
+        assert_eq!(estimator.limit_ix(&rpc, "Orca", &tx, None), None);
+        rpc.push_simulation(MockRpc::simulation(
+            None,
+            &[
+                "Program Arb111 invoke [1]",
+                "Program Arb111 consumed 50000 of 200000 compute units",
+                "Program Arb111 success",
+            ],
+        ));
+        assert_eq!(estimator.limit_ix(&rpc, "Orca", &tx, None), Some(set_compute_unit_limit(55_000)));
+        assert_eq!(estimator.limit_ix(&rpc, "Orca", &tx, Some(100)), Some(set_compute_unit_limit(55_000)));
+        assert_eq!(rpc.simulated().len(), 2);
+
+        // a node behind the route's slot isn't asked
+        assert_eq!(estimator.limit_ix(&rpc, "Raydium", &tx, Some(101)), None);
+        assert_eq!(rpc.simulated().len(), 2);
+    }
+
+    #[test]
+    fn compute_budget_instruction_layout() {
+        let ix = set_compute_unit_limit(110_000);
+        assert_eq!(ix.program_id, compute_budget::id());
+        assert_eq!(ix.data, vec![2, 0xb0, 0xad, 0x01, 0x00]);
+        let ix = set_compute_unit_price(1_000);
+        assert_eq!(ix.data, vec![3, 0xe8, 0x03, 0, 0, 0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,1087 @@
+//! Bot configuration
+//!
+//! Everything that used to be hardcoded in `main.rs` (keypair, RPC URLs, pool
+//! directories, start mint) is read from a JSON config file and validated up
+//! front, so a bad config fails with a list of every problem at once.
+
+use anchor_client::solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use anchor_client::Cluster;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::str::FromStr;
+
+use crate::allocator::{default_strategy_weights, Strategy};
+use crate::amount::{bps_ceil, BPS};
+use crate::bundle::BundleConfig;
+use crate::compute::ComputeConfig;
+use crate::cooldown::CooldownConfig;
+use crate::dedup::DedupConfig;
+use crate::dust::DustConfig;
+use crate::events::EventStreamConfig;
+use crate::executor::ExecutorKind;
+use crate::exposure::ExposureConfig;
+use crate::fee_balance::FeeBalanceConfig;
+use crate::analytics::AnalyticsConfig;
+use crate::blockhash::BlockhashCacheConfig;
+use crate::flash_loan::FlashLoanConfig;
+use crate::fleet::FleetConfig;
+use crate::incremental::IncrementalConfig;
+use crate::jupiter_check::JupiterCheckConfig;
+use crate::jupiter_pools::JupiterPoolsConfig;
+use crate::oracle::OracleConfig;
+use crate::overrides::{OverridesConfig, PoolOverride};
+use crate::provider::PriorityFeeConfig;
+use crate::quarantine::QuarantineConfig;
+use crate::rate_limit::RateLimitConfig;
+use crate::tip::TipConfig;
+use crate::keystore::{read_passphrase, EncryptedKeypair};
+use crate::pool::{PoolOperations, PoolType};
+use crate::profit_sweep::ProfitSweepConfig;
+use crate::requote::RequoteConfig;
+use crate::route_cache::RouteCacheConfig;
+use crate::search::{RouteConstraints, MAX_PATH_LEN};
+use crate::shard::ShardConfig;
+use crate::settle::SettleConfig;
+use crate::signer::SignerConfig;
+use crate::token_probe::TokenProbeConfig;
+use crate::tokens::TokensConfig;
+use crate::universe::UniverseConfig;
+use crate::utils::PoolQuote;
+use crate::sizing::SizingConfig;
+use crate::slippage::SlippageConfig;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("could not read config file {path}: {source}")]
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("could not parse config file {path}: {source}")]
+    Parse {
+        path: String,
+        source: serde_json::Error,
+    },
+    #[error("invalid config:\n  - {}", .0.join("\n  - "))]
+    Invalid(Vec<String>),
+    #[error("could not load keypair: {0}")]
+    Keypair(String),
+    #[error("could not connect to signer: {0}")]
+    Signer(String),
+}
+
+/// Per-cluster connection settings
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ClusterConfig {
+    /// RPC endpoints used for reads, in order of preference
+    #[serde(default)]
+    pub rpc_endpoints: Vec<String>,
+    /// Endpoints signed transactions are broadcast to
+    #[serde(default)]
+    pub send_endpoints: Vec<String>,
+    /// Optional Jito block engine endpoint transactions are also sent to
+    #[serde(default)]
+    pub jito_url: Option<String>,
+    /// Overrides the top-level keypair for this cluster
+    #[serde(default)]
+    pub keypair_path: Option<String>,
+    /// WebSocket endpoint; derived from the first rpc endpoint if unset
+    #[serde(default)]
+    pub ws_url: Option<String>,
+    /// Overrides the top-level pool dirs for this cluster (pools differ
+    /// between clusters)
+    #[serde(default)]
+    pub pool_dirs: Option<Vec<PoolDirConfig>>,
+}
+
+impl ClusterConfig {
+    /// `ws_url`, or the first rpc endpoint with its scheme swapped (and
+    /// port 8899 for 8900, like `solana-test-validator`)
+    pub fn ws_endpoint(&self) -> Option<String> {
+        if let Some(ws_url) = &self.ws_url {
+            return Some(ws_url.clone());
+        }
+        let rpc = self.rpc_endpoints.first()?;
+        let ws = if let Some(rest) = rpc.strip_prefix("https://") {
+            format!("wss://{}", rest)
+        } else if let Some(rest) = rpc.strip_prefix("http://") {
+            format!("ws://{}", rest)
+        } else {
+            rpc.clone()
+        };
+        Some(ws.replace(":8899", ":8900"))
+    }
+
+    /// The anchor cluster `name` stands for: the well-known clusters by
+    /// name (localnet routes are only simulated), any other name by its
+    /// endpoints
+    pub fn kind(&self, name: &str) -> Cluster {
+        match name {
+            "localnet" => Cluster::Localnet,
+            "devnet" => Cluster::Devnet,
+            "testnet" => Cluster::Testnet,
+            "mainnet" => Cluster::Mainnet,
+            _ => self.endpoints(),
+        }
+    }
+
+    /// A custom anchor cluster on this cluster's own endpoints
+    pub fn endpoints(&self) -> Cluster {
+        let rpc = self.rpc_endpoints.first().cloned().unwrap_or_default();
+        let ws = self.ws_endpoint().unwrap_or_default();
+        Cluster::Custom(rpc, ws)
+    }
+}
+
+/// A directory of pool JSON files and the pool type they deserialize into
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PoolDirConfig {
+    pub pool_type: PoolType,
+    pub dir_path: String,
+}
+
+/// Token every arbitrage cycle starts and ends in
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StartMintConfig {
+    pub mint: String,
+    /// Smallest input amount (scaled) the search is run with
+    #[serde(default = "default_min_swap_amount")]
+    pub min_swap_amount: u128,
+    /// How many times the input amount is halved and searched again
+    #[serde(default = "default_search_rounds")]
+    pub search_rounds: usize,
+}
+
+/// Thresholds of the pruning pass run after the pools are loaded (see `prune`)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PruneConfig {
+    /// Pools with less liquidity (in USD) are dropped; 0 disables the check
+    #[serde(default)]
+    pub min_liquidity_usd: f64,
+    /// Pools that can't fill this much input (in USD) in either direction
+    /// within `max_depth_impact` are dropped; 0 disables the check
+    #[serde(default)]
+    pub min_depth_usd: f64,
+    /// Share of the `min_depth_usd` probe's value fees and price impact may eat
+    #[serde(default = "default_max_depth_impact")]
+    pub max_depth_impact: f64,
+    /// Max relative deviation of a pool's quote from the pair's median
+    #[serde(default = "default_max_quote_deviation")]
+    pub max_quote_deviation: f64,
+    /// Mints valued at $1; defaults to USDC and USDT
+    #[serde(default)]
+    pub usd_mints: Vec<String>,
+}
+
+fn default_max_quote_deviation() -> f64 {
+    0.5
+}
+
+fn default_max_depth_impact() -> f64 {
+    0.05
+}
+
+impl Default for PruneConfig {
+    fn default() -> Self {
+        Self {
+            min_liquidity_usd: 0.0,
+            min_depth_usd: 0.0,
+            max_depth_impact: default_max_depth_impact(),
+            max_quote_deviation: default_max_quote_deviation(),
+            usd_mints: vec![],
+        }
+    }
+}
+
+/// Commitment level per kind of request: `processed`, `confirmed` or `finalized`
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CommitmentLevels {
+    /// Pool accounts, balances and other reads
+    #[serde(default = "default_commitment")]
+    pub reads: CommitmentLevel,
+    /// Simulations (compute estimates, localnet sends) and the blockhash they use
+    #[serde(default = "default_commitment")]
+    pub simulations: CommitmentLevel,
+    /// Blockhash of sent transactions, and the status they count as landed at
+    #[serde(default = "default_commitment")]
+    pub sends: CommitmentLevel,
+    /// Send and simulate routes with `minContextSlot` at the slot their
+    /// pools were read at, so lagging nodes don't run them on older state
+    #[serde(default = "default_min_context_slot")]
+    pub min_context_slot: bool,
+}
+
+fn default_commitment() -> CommitmentLevel {
+    CommitmentLevel::Confirmed
+}
+
+fn default_min_context_slot() -> bool {
+    true
+}
+
+impl Default for CommitmentLevels {
+    fn default() -> Self {
+        Self {
+            reads: default_commitment(),
+            simulations: default_commitment(),
+            sends: default_commitment(),
+            min_context_slot: default_min_context_slot(),
+        }
+    }
+}
+
+impl CommitmentLevels {
+    pub fn reads(&self) -> CommitmentConfig {
+        CommitmentConfig { commitment: self.reads }
+    }
+
+    pub fn simulations(&self) -> CommitmentConfig {
+        CommitmentConfig { commitment: self.simulations }
+    }
+
+    pub fn sends(&self) -> CommitmentConfig {
+        CommitmentConfig { commitment: self.sends }
+    }
+
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let supported = [
+            CommitmentLevel::Processed,
+            CommitmentLevel::Confirmed,
+            CommitmentLevel::Finalized,
+        ];
+        let mut problems = vec![];
+        for (name, level) in [
+            ("reads", self.reads),
+            ("simulations", self.simulations),
+            ("sends", self.sends),
+        ] {
+            if !supported.contains(&level) {
+                problems.push(format!(
+                    "commitment.{} must be processed, confirmed or finalized, got {:?}",
+                    name, level
+                ));
+            }
+        }
+        problems
+    }
+}
+
+fn default_min_swap_amount() -> u128 {
+    10_u128.pow(6) // 1 USDC
+}
+
+fn default_search_rounds() -> usize {
+    4
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Config {
+    /// Fee taken off the swap amount before searching, in bps (rounded up)
+    pub fee_bps: u64,
+    /// The fee as a fraction, replaced by `fee_bps`; only read to report it
+    #[serde(default, skip_serializing)]
+    pub fee_percentage: Option<f64>,
+    /// URLs for different DEX APIs
+    #[serde(default)]
+    pub dex_urls: Vec<String>,
+    /// Path to the owner keypair file
+    #[serde(default)]
+    pub keypair_path: Option<String>,
+    /// Name of an environment variable holding the owner keypair as a JSON byte array
+    #[serde(default)]
+    pub keypair_env: Option<String>,
+    /// Name of an environment variable holding the passphrase of an encrypted
+    /// keypair file; prompted for if unset
+    #[serde(default)]
+    pub keypair_passphrase_env: Option<String>,
+    /// External signer (remote service or Ledger) used instead of a keypair
+    #[serde(default)]
+    pub signer: Option<SignerConfig>,
+    /// Connection settings keyed by cluster name (`mainnet`, `localnet`, ...)
+    #[serde(default)]
+    pub clusters: HashMap<String, ClusterConfig>,
+    #[serde(default)]
+    pub pool_dirs: Vec<PoolDirConfig>,
+    /// Binary cache of the loaded pools and mint graph, rebuilt when the
+    /// pool files change; disabled if unset
+    #[serde(default)]
+    pub graph_cache: Option<String>,
+    /// Read pool fees and mint decimals from chain when the pools are loaded,
+    /// over the values of the pool files
+    #[serde(default = "default_onchain_params")]
+    pub onchain_params: bool,
+    /// Where the mints indexed ahead of the pools come from
+    #[serde(default)]
+    pub tokens: TokensConfig,
+    #[serde(default)]
+    pub start_mint: Option<StartMintConfig>,
+    /// Share of the start-mint balance each strategy may reserve
+    #[serde(default = "default_strategy_weights")]
+    pub strategy_weights: HashMap<Strategy, u32>,
+    /// Slippage (in bps) taken off every quoted route output
+    #[serde(default)]
+    pub slippage_bps: u64,
+    /// Mints that are never routed through
+    #[serde(default)]
+    pub blacklisted_mints: Vec<String>,
+    /// Jito tip paid per bundle (lamports); replaced by `tip` when that is set
+    #[serde(default)]
+    pub tip_lamports: u64,
+    /// Pools whose data is more than this many slots old are skipped by the
+    /// search; 0 disables the check
+    #[serde(default)]
+    pub max_quote_age_slots: u64,
+    /// Routes found more than this many slots before they're executed are
+    /// dropped instead of sent; 0 disables the check
+    #[serde(default)]
+    pub max_opportunity_age_slots: u64,
+    /// Routes found more than this many milliseconds before they're executed
+    /// are dropped instead of sent; 0 disables the check
+    #[serde(default)]
+    pub max_opportunity_age_ms: u64,
+    /// Order book legs (Serum) cap a route's input at what their book fills
+    /// within this many bps of the best price
+    #[serde(default = "default_max_book_impact_bps")]
+    pub max_book_impact_bps: u64,
+    /// Pools the search tries per pair of mints, those with the best rate at
+    /// their last update first; every pool if unset
+    #[serde(default)]
+    pub max_pools_per_edge: Option<usize>,
+    /// Directory the session summary is written to on exit
+    #[serde(default = "default_session_dir")]
+    pub session_dir: String,
+    /// Address of the HTTP control api (e.g. `127.0.0.1:8080`); disabled if unset
+    #[serde(default)]
+    pub control_addr: Option<String>,
+    /// Bearer token required by the control api
+    #[serde(default)]
+    pub control_token: Option<String>,
+    #[serde(default)]
+    pub prune: PruneConfig,
+    /// Borrow the swap input via a flash loan instead of using the wallet balance
+    #[serde(default)]
+    pub flash_loan: Option<FlashLoanConfig>,
+    /// How long a sent route is suppressed before it may be sent again
+    #[serde(default)]
+    pub dedup: DedupConfig,
+    /// Skip routes that keep failing for an exponentially growing time
+    #[serde(default)]
+    pub cooldown: CooldownConfig,
+    /// Leave pools that keep failing out of the search for a while
+    #[serde(default)]
+    pub quarantine: QuarantineConfig,
+    /// Reject routes whose pool prices deviate too far from Pyth prices
+    #[serde(default)]
+    pub oracle: Option<OracleConfig>,
+    /// Flag (or reject) routes whose hops are quoted well over the Jupiter quote api
+    #[serde(default)]
+    pub jupiter_check: Option<JupiterCheckConfig>,
+    /// Quote routes again on fresh pool accounts right before sending them
+    #[serde(default)]
+    pub requote: Option<RequoteConfig>,
+    /// Settle the Serum markets of landed routes at the end of every round
+    #[serde(default)]
+    pub settle: Option<SettleConfig>,
+    /// Swap leftover intermediate tokens back into the start mint every so often
+    #[serde(default)]
+    pub dust: Option<DustConfig>,
+    /// Warn about (and optionally top up) a SOL balance too low for the next sends
+    #[serde(default)]
+    pub fee_balance: Option<FeeBalanceConfig>,
+    /// Move start-mint profits above the working capital to a cold wallet
+    #[serde(default)]
+    pub profit_sweep: Option<ProfitSweepConfig>,
+    /// Tip a share of the expected profit that adapts to the landing rate
+    #[serde(default)]
+    pub tip: Option<TipConfig>,
+    /// Request the simulated compute units of a route instead of the default
+    #[serde(default)]
+    pub compute: ComputeConfig,
+    /// Pay a compute unit price estimated from recent fees of the written accounts
+    #[serde(default)]
+    pub priority_fee: Option<PriorityFeeConfig>,
+    /// Scale trade sizes down on routes that land rarely or below their quote
+    #[serde(default)]
+    pub sizing: Option<SizingConfig>,
+    /// Send the disjoint routes of a search round as one Jito bundle
+    #[serde(default)]
+    pub bundle: Option<BundleConfig>,
+    /// Where routes go: `rpc`, `jito`, `simulate` or `paper`; by default
+    /// simulated on localnet, bundled on mainnet with `bundle` set and sent
+    /// over rpc otherwise
+    #[serde(default)]
+    pub executor: Option<ExecutorKind>,
+    /// Routes the `rpc` executor broadcasts before it waits for the oldest
+    /// to confirm; 1 confirms every route before the next is sent
+    #[serde(default = "default_max_in_flight")]
+    pub max_in_flight: usize,
+    /// Address lookup tables route transactions are also sized against
+    #[serde(default)]
+    pub lookup_tables: Vec<String>,
+    /// Commitment of reads, simulations and sends
+    #[serde(default)]
+    pub commitment: CommitmentLevels,
+    /// Request budgets of the RPC endpoints and retries of failed calls
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    /// Pool types to leave out, and fees, slippage and size caps of single pools
+    #[serde(default)]
+    pub overrides: OverridesConfig,
+    /// Re-read the pools' accounts every tick and only quote the cycles
+    /// through changed pools between full searches
+    #[serde(default)]
+    pub incremental: Option<IncrementalConfig>,
+    /// Quote the routes that were profitable before first, and only search
+    /// the graph when none of them pays
+    #[serde(default)]
+    pub route_cache: Option<RouteCacheConfig>,
+    /// Publish every opportunity found to WebSocket clients
+    #[serde(default)]
+    pub events: Option<EventStreamConfig>,
+    /// Run as the discovery or an execution instance of a fleet sharing Redis
+    #[serde(default)]
+    pub fleet: Option<FleetConfig>,
+    /// Split the search between a coordinator and worker processes
+    #[serde(default)]
+    pub shard: Option<ShardConfig>,
+    /// Export quotes, opportunities and executions to Parquet or ClickHouse
+    #[serde(default)]
+    pub analytics: Option<AnalyticsConfig>,
+    /// Sign with a blockhash fetched in the background instead of fetching
+    /// one per send
+    #[serde(default)]
+    pub blockhash_cache: Option<BlockhashCacheConfig>,
+    /// File every pool account read, simulation and blockhash fetch of the
+    /// run is recorded to, for `replay`; nothing is recorded if unset
+    #[serde(default)]
+    pub record: Option<String>,
+    /// Measure the realized output of every leg of landed routes and pad
+    /// the quotes of the venues that under-deliver
+    #[serde(default)]
+    pub slippage: Option<SlippageConfig>,
+    /// Divide a leg of a route between two pools of its pair when that
+    /// returns more (not with a flash loan)
+    #[serde(default)]
+    pub split_routes: bool,
+    /// Which pools a route may combine, every route uses a pool at most once
+    /// by default
+    #[serde(default = "default_route_constraints")]
+    pub route_constraints: RouteConstraints,
+    /// Cap the value routed through tokens other than the start mint, per
+    /// transaction and over a rolling window
+    #[serde(default)]
+    pub exposure: Option<ExposureConfig>,
+    /// Trade only the stables or the largest tokens by liquidity, and the
+    /// pools between them
+    #[serde(default)]
+    pub universe: Option<UniverseConfig>,
+    /// Simulate a round trip into every new mint at start and blacklist the
+    /// ones that tax transfers or block sells
+    #[serde(default)]
+    pub token_probe: Option<TokenProbeConfig>,
+    /// Quote and swap the Jupiter pools through the Jupiter api; they stay
+    /// out of the graph if unset
+    #[serde(default)]
+    pub jupiter_pools: Option<JupiterPoolsConfig>,
+}
+
+fn default_route_constraints() -> RouteConstraints {
+    RouteConstraints {
+        no_repeated_pool: true,
+        ..RouteConstraints::default()
+    }
+}
+
+fn default_onchain_params() -> bool {
+    true
+}
+
+fn default_max_book_impact_bps() -> u64 {
+    100
+}
+
+fn default_max_in_flight() -> usize {
+    1
+}
+
+fn default_session_dir() -> String {
+    "sessions".to_string()
+}
+
+/// What a config still setting `fee_percentage` is told, with the `fee_bps`
+/// of its fee if it's a number
+fn fee_percentage_replaced(fee_percentage: Option<f64>) -> String {
+    let replaced = "fee_percentage was replaced by fee_bps (0.001 = 10 bps)";
+    match fee_percentage {
+        Some(fee) => format!("{}, set fee_bps to {}", replaced, (fee * BPS as f64).round()),
+        None => replaced.to_string(),
+    }
+}
+
+/// Parameters that can change while the bot is running (see `config_watcher`).
+/// A recording keeps the ones of every round (see `replay`).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RuntimeParams {
+    pub fee_bps: u64,
+    pub slippage_bps: u64,
+    #[serde(with = "pubkey_strings")]
+    pub blacklisted_mints: HashSet<Pubkey>,
+    pub tip_lamports: u64,
+    pub max_quote_age_slots: u64,
+    pub max_opportunity_age_slots: u64,
+    pub max_opportunity_age_ms: u64,
+    pub max_book_impact_bps: u64,
+    pub max_pools_per_edge: Option<usize>,
+    pub strategy_weights: HashMap<Strategy, u32>,
+    #[serde(with = "pubkey_keys")]
+    pub pool_overrides: HashMap<Pubkey, PoolOverride>,
+    pub split_routes: bool,
+    pub route_constraints: RouteConstraints,
+}
+
+/// A set of pubkeys as base58 strings, like the config has them
+mod pubkey_strings {
+    use super::*;
+    use serde::de::Error;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(keys: &HashSet<Pubkey>, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+        keys.sort();
+        keys.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashSet<Pubkey>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|key| Pubkey::from_str(key).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+/// A map keyed by pubkeys, keyed by their base58 strings (JSON keys are strings)
+mod pubkey_keys {
+    use super::*;
+    use serde::de::Error;
+    use serde::{Deserializer, Serializer};
+    use std::collections::BTreeMap;
+
+    pub fn serialize<S: Serializer, V: Serialize>(
+        map: &HashMap<Pubkey, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let map: BTreeMap<String, &V> = map.iter().map(|(key, value)| (key.to_string(), value)).collect();
+        map.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, V: Deserialize<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<Pubkey, V>, D::Error> {
+        HashMap::<String, V>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(key, value)| Pubkey::from_str(&key).map(|key| (key, value)).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+impl RuntimeParams {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            fee_bps: config.fee_bps,
+            slippage_bps: config.slippage_bps,
+            blacklisted_mints: config
+                .blacklisted_mints
+                .iter()
+                .filter_map(|m| Pubkey::from_str(m).ok())
+                .collect(),
+            tip_lamports: config.tip_lamports,
+            max_quote_age_slots: config.max_quote_age_slots,
+            max_opportunity_age_slots: config.max_opportunity_age_slots,
+            max_opportunity_age_ms: config.max_opportunity_age_ms,
+            max_book_impact_bps: config.max_book_impact_bps,
+            max_pools_per_edge: config.max_pools_per_edge,
+            strategy_weights: config.strategy_weights.clone(),
+            pool_overrides: config.overrides.pool_overrides(),
+            split_routes: config.split_routes,
+            route_constraints: config.route_constraints,
+        }
+    }
+
+    /// Applies the configured slippage to a quoted amount (rounded down)
+    pub fn apply_slippage(&self, amount: u128) -> u128 {
+        amount - bps_ceil(amount, self.slippage_bps.min(BPS))
+    }
+
+    /// The slippage of a route through `pools`: the global one, or the
+    /// highest override of its pools if that's more
+    pub fn route_slippage_bps(&self, pools: &[PoolQuote]) -> u64 {
+        pools
+            .iter()
+            .filter_map(|pool| self.pool_overrides.get(&pool.0.get_address())?.slippage_bps)
+            .fold(self.slippage_bps, u64::max)
+    }
+
+    /// Applies the slippage of a route through `pools` to its quoted output
+    /// (rounded down)
+    pub fn apply_route_slippage(&self, amount: u128, pools: &[PoolQuote]) -> u128 {
+        amount - bps_ceil(amount, self.route_slippage_bps(pools).min(BPS))
+    }
+
+    /// Most a route may put into `pool`, if capped by an override
+    pub fn max_trade_size(&self, pool: &Pubkey) -> Option<u128> {
+        self.pool_overrides
+            .get(pool)?
+            .max_trade_size
+            .map(|size| size as u128)
+    }
+}
+
+/// `path` with a leading `~/` replaced by `$HOME`, as a shell would
+pub fn expand_home(path: &str) -> PathBuf {
+    expand_home_from(path, std::env::var_os("HOME"))
+}
+
+fn expand_home_from(path: &str, home: Option<OsString>) -> PathBuf {
+    match (path.strip_prefix("~/"), home) {
+        (Some(rest), Some(home)) => Path::new(&home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+impl Config {
+    /// Reads and parses the config file (without validating it)
+    pub fn load(path: &str) -> Result<Config, ConfigError> {
+        let contents = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_string(),
+            source,
+        })?;
+        Self::parse(path, &contents)
+    }
+
+    fn parse(path: &str, contents: &str) -> Result<Config, ConfigError> {
+        serde_json::from_str(contents).map_err(|source| {
+            // an old config fails on the missing `fee_bps`, say what replaced its fee
+            match serde_json::from_str::<serde_json::Value>(contents) {
+                Ok(json) if json.get("fee_bps").is_none() && json.get("fee_percentage").is_some() => {
+                    ConfigError::Invalid(vec![fee_percentage_replaced(json["fee_percentage"].as_f64())])
+                }
+                _ => ConfigError::Parse {
+                    path: path.to_string(),
+                    source,
+                },
+            }
+        })
+    }
+
+    /// Checks that everything needed to run on `cluster` is present.
+    /// Every problem is reported, not just the first one.
+    pub fn validate(&self, cluster: &str) -> Result<(), ConfigError> {
+        self.validate_for(cluster, true)
+    }
+
+    /// Like `validate`; a signer is only required if the command `signs`
+    pub fn validate_for(&self, cluster: &str, signs: bool) -> Result<(), ConfigError> {
+        let mut problems = vec![];
+
+        if self.fee_bps >= BPS {
+            problems.push(format!("fee_bps must be < 10000, got {}", self.fee_bps));
+        }
+        if let Some(fee_percentage) = self.fee_percentage {
+            problems.push(fee_percentage_replaced(Some(fee_percentage)));
+        }
+
+        match self.clusters.get(cluster) {
+            None => problems.push(format!("clusters.{} is missing", cluster)),
+            Some(c) => {
+                if c.rpc_endpoints.is_empty() {
+                    problems.push(format!("clusters.{}.rpc_endpoints is empty", cluster));
+                }
+                if c.send_endpoints.is_empty() && c.jito_url.is_none() {
+                    problems.push(format!(
+                        "clusters.{} needs send_endpoints or jito_url",
+                        cluster
+                    ));
+                }
+            }
+        }
+
+        match (self.keypair_path_of(cluster), &self.keypair_env) {
+            _ if self.signer.is_some() || !signs => {}
+            (None, None) => problems.push(format!(
+                "keypair_path, keypair_env or clusters.{}.keypair_path is required",
+                cluster
+            )),
+            (Some(path), _) if !expand_home(path).exists() => {
+                problems.push(format!("keypair file {} does not exist", path))
+            }
+            _ => {}
+        }
+
+        let pool_dirs_key = match self.clusters.get(cluster).and_then(|c| c.pool_dirs.as_ref()) {
+            Some(_) => format!("clusters.{}.pool_dirs", cluster),
+            None => "pool_dirs".to_string(),
+        };
+        let pool_dirs = self.cluster_pool_dirs(cluster);
+        if pool_dirs.is_empty() {
+            problems.push(format!("{} is empty", pool_dirs_key));
+        }
+        for (i, dir) in pool_dirs.iter().enumerate() {
+            if !Path::new(&dir.dir_path).is_dir() {
+                problems.push(format!(
+                    "{}[{}].dir_path {} is not a directory",
+                    pool_dirs_key, i, dir.dir_path
+                ));
+            }
+            if !dir.pool_type.is_supported() {
+                problems.push(format!(
+                    "{}[{}].pool_type {:?} is not supported yet",
+                    pool_dirs_key, i, dir.pool_type
+                ));
+            }
+        }
+
+        match &self.start_mint {
+            None => problems.push("start_mint is missing".to_string()),
+            Some(start) => {
+                if Pubkey::from_str(&start.mint).is_err() {
+                    problems.push(format!("start_mint.mint {} is not a valid pubkey", start.mint));
+                }
+                if start.search_rounds == 0 {
+                    problems.push("start_mint.search_rounds must be > 0".to_string());
+                }
+            }
+        }
+
+        if let Some(addr) = &self.control_addr {
+            if addr.parse::<std::net::SocketAddr>().is_err() {
+                problems.push(format!("control_addr {} is not a valid socket address", addr));
+            }
+        }
+
+        if self.max_in_flight == 0 {
+            problems.push("max_in_flight must be > 0".to_string());
+        }
+        if self.prune.min_liquidity_usd < 0.0 {
+            problems.push("prune.min_liquidity_usd must be >= 0".to_string());
+        }
+        if self.prune.min_depth_usd < 0.0 {
+            problems.push("prune.min_depth_usd must be >= 0".to_string());
+        }
+        if !(0.0..1.0).contains(&self.prune.max_depth_impact) {
+            problems.push("prune.max_depth_impact must be in [0, 1)".to_string());
+        }
+        if self.prune.max_quote_deviation <= 0.0 {
+            problems.push("prune.max_quote_deviation must be > 0".to_string());
+        }
+        for (i, mint) in self.prune.usd_mints.iter().enumerate() {
+            if Pubkey::from_str(mint).is_err() {
+                problems.push(format!("prune.usd_mints[{}] {} is not a valid pubkey", i, mint));
+            }
+        }
+
+        if let Some(flash_loan) = &self.flash_loan {
+            problems.extend(flash_loan.problems());
+        }
+        problems.extend(self.dedup.problems());
+        if let Some(oracle) = &self.oracle {
+            problems.extend(oracle.problems());
+        }
+        if let Some(jupiter_check) = &self.jupiter_check {
+            problems.extend(jupiter_check.problems());
+        }
+        if let Some(requote) = &self.requote {
+            problems.extend(requote.problems());
+        }
+        if let Some(settle) = &self.settle {
+            problems.extend(settle.problems());
+        }
+        if let Some(dust) = &self.dust {
+            problems.extend(dust.problems());
+        }
+        if let Some(fee_balance) = &self.fee_balance {
+            problems.extend(fee_balance.problems());
+        }
+        if let Some(profit_sweep) = &self.profit_sweep {
+            problems.extend(profit_sweep.problems());
+        }
+        problems.extend(self.overrides.problems());
+        if let Some(tip) = &self.tip {
+            problems.extend(tip.problems());
+        }
+        problems.extend(self.tokens.problems());
+        problems.extend(self.compute.problems());
+        problems.extend(self.cooldown.problems());
+        problems.extend(self.quarantine.problems());
+        problems.extend(self.commitment.problems());
+        problems.extend(self.rate_limit.problems());
+        if let Some(priority_fee) = &self.priority_fee {
+            problems.extend(priority_fee.problems());
+        }
+        if let Some(signer) = &self.signer {
+            problems.extend(signer.problems());
+        }
+        if let Some(sizing) = &self.sizing {
+            problems.extend(sizing.problems());
+        }
+        if let Some(slippage) = &self.slippage {
+            problems.extend(slippage.problems());
+        }
+        if let Some(exposure) = &self.exposure {
+            problems.extend(exposure.problems());
+        }
+        if let Some(universe) = &self.universe {
+            problems.extend(universe.problems());
+        }
+        if let Some(token_probe) = &self.token_probe {
+            problems.extend(token_probe.problems());
+        }
+        if let Some(jupiter_pools) = &self.jupiter_pools {
+            problems.extend(jupiter_pools.problems());
+        }
+        if let Some(bundle) = &self.bundle {
+            problems.extend(bundle.problems());
+        }
+        if let Some(incremental) = &self.incremental {
+            problems.extend(incremental.problems());
+        }
+        if let Some(route_cache) = &self.route_cache {
+            problems.extend(route_cache.problems());
+        }
+        if let Some(events) = &self.events {
+            problems.extend(events.problems());
+        }
+        if let Some(fleet) = &self.fleet {
+            problems.extend(fleet.problems());
+        }
+        if let Some(shard) = &self.shard {
+            problems.extend(shard.problems());
+        }
+        if let Some(analytics) = &self.analytics {
+            problems.extend(analytics.problems());
+        }
+        if let Some(blockhash_cache) = &self.blockhash_cache {
+            problems.extend(blockhash_cache.problems());
+        }
+        if self.executor == Some(ExecutorKind::Jito) && self.bundle.is_none() {
+            problems.push("executor jito needs bundle".to_string());
+        }
+
+        if self.slippage_bps > 10_000 {
+            problems.push(format!("slippage_bps must be <= 10000, got {}", self.slippage_bps));
+        }
+        if self.max_book_impact_bps > 10_000 {
+            problems.push(format!(
+                "max_book_impact_bps must be <= 10000, got {}",
+                self.max_book_impact_bps
+            ));
+        }
+        if self.max_pools_per_edge == Some(0) {
+            problems.push("max_pools_per_edge must be > 0".to_string());
+        }
+        if self.route_constraints.min_distinct_dexes > MAX_PATH_LEN - 1 {
+            problems.push(format!(
+                "route_constraints.min_distinct_dexes must be <= {} (hops of the longest route), got {}",
+                MAX_PATH_LEN - 1,
+                self.route_constraints.min_distinct_dexes
+            ));
+        }
+        for (i, mint) in self.blacklisted_mints.iter().enumerate() {
+            if Pubkey::from_str(mint).is_err() {
+                problems.push(format!("blacklisted_mints[{}] {} is not a valid pubkey", i, mint));
+            }
+        }
+        for (i, table) in self.lookup_tables.iter().enumerate() {
+            if Pubkey::from_str(table).is_err() {
+                problems.push(format!("lookup_tables[{}] {} is not a valid pubkey", i, table));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    /// Settings of the given cluster (call `validate` first)
+    pub fn cluster(&self, cluster: &str) -> &ClusterConfig {
+        &self.clusters[cluster]
+    }
+
+    /// Pool dirs of `cluster`: its own if it lists any, the top-level ones otherwise
+    pub fn cluster_pool_dirs(&self, cluster: &str) -> &[PoolDirConfig] {
+        self.clusters
+            .get(cluster)
+            .and_then(|c| c.pool_dirs.as_deref())
+            .unwrap_or(&self.pool_dirs)
+    }
+
+    /// Points `cluster` at `rpc_url` for reads and sends and at `ws_url`
+    /// (`--rpc-url` / `--ws-url`), adding the cluster if it isn't configured.
+    /// Its Jito endpoint, keypair and pool dirs are kept.
+    pub fn override_endpoints(&mut self, cluster: &str, rpc_url: Option<&str>, ws_url: Option<&str>) {
+        if rpc_url.is_none() && ws_url.is_none() {
+            return;
+        }
+        let settings = self.clusters.entry(cluster.to_string()).or_default();
+        if let Some(rpc_url) = rpc_url {
+            settings.rpc_endpoints = vec![rpc_url.to_string()];
+            settings.send_endpoints = vec![rpc_url.to_string()];
+            if ws_url.is_none() {
+                settings.ws_url = None;
+            }
+        }
+        if let Some(ws_url) = ws_url {
+            settings.ws_url = Some(ws_url.to_string());
+        }
+    }
+
+    pub fn start_mint(&self) -> Pubkey {
+        Pubkey::from_str(&self.start_mint.as_ref().unwrap().mint).unwrap()
+    }
+
+    /// The owner's signer: the configured external signer, otherwise the
+    /// keypair (see `load_keypair`)
+    pub fn load_signer(&self, cluster: &str) -> Result<Rc<dyn Signer>, ConfigError> {
+        match &self.signer {
+            Some(signer) => signer.connect().map_err(ConfigError::Signer),
+            None => Ok(Rc::new(self.load_keypair(cluster)?)),
+        }
+    }
+
+    /// The cluster's keypair file, else the top-level one, as configured
+    fn keypair_path_of(&self, cluster: &str) -> Option<&String> {
+        self.clusters
+            .get(cluster)
+            .and_then(|c| c.keypair_path.as_ref())
+            .or(self.keypair_path.as_ref())
+    }
+
+    /// Loads the owner keypair: the cluster's keypair file, then the top-level
+    /// keypair file, then the `keypair_env` environment variable. Encrypted
+    /// keypair files (see `keystore`) are unlocked with the passphrase from
+    /// `keypair_passphrase_env` or a prompt.
+    pub fn load_keypair(&self, cluster: &str) -> Result<Keypair, ConfigError> {
+        if let Some(path) = self.keypair_path_of(cluster) {
+            let contents = std::fs::read_to_string(expand_home(path))
+                .map_err(|e| ConfigError::Keypair(format!("{}: {}", path, e)))?;
+            if !EncryptedKeypair::is_encrypted(&contents) {
+                return read_keypair_file(expand_home(path))
+                    .map_err(|e| ConfigError::Keypair(format!("{}: {}", path, e)));
+            }
+            let encrypted: EncryptedKeypair = serde_json::from_str(&contents)
+                .map_err(|e| ConfigError::Keypair(format!("{}: {}", path, e)))?;
+            let prompt = format!("Passphrase for {}: ", path);
+            let passphrase = read_passphrase(self.keypair_passphrase_env.as_deref(), &prompt)
+                .map_err(|e| ConfigError::Keypair(e.to_string()))?;
+            return encrypted
+                .decrypt(&passphrase)
+                .map_err(|e| ConfigError::Keypair(format!("{}: {}", path, e)));
+        }
+
+        let var = self
+            .keypair_env
+            .as_ref()
+            .ok_or_else(|| ConfigError::Keypair("no keypair configured".to_string()))?;
+        let raw = std::env::var(var)
+            .map_err(|_| ConfigError::Keypair(format!("env var {} is not set", var)))?;
+        let bytes: Vec<u8> = serde_json::from_str(&raw)
+            .map_err(|e| ConfigError::Keypair(format!("env var {}: {}", var, e)))?;
+        Keypair::from_bytes(&bytes).map_err(|e| ConfigError::Keypair(format!("env var {}: {}", var, e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation_lists_every_problem() {
+        let config: Config = serde_json::from_str(r#"{ "fee_bps": 10 }"#).unwrap();
+        let problems = match config.validate("mainnet") {
+            Err(ConfigError::Invalid(problems)) => problems,
+            other => panic!("unexpected result: {:?}", other),
+        };
+        assert_eq!(
+            problems,
+            vec![
+                "clusters.mainnet is missing",
+                "keypair_path, keypair_env or clusters.mainnet.keypair_path is required",
+                "pool_dirs is empty",
+                "start_mint is missing",
+            ]
+        );
+    }
+
+    #[test]
+    fn old_fee_percentage_configs_are_told_about_fee_bps() {
+        let problems = match Config::parse("config.json", r#"{ "fee_percentage": 0.003 }"#) {
+            Err(ConfigError::Invalid(problems)) => problems,
+            other => panic!("unexpected result: {:?}", other),
+        };
+        assert_eq!(
+            problems,
+            vec!["fee_percentage was replaced by fee_bps (0.001 = 10 bps), set fee_bps to 30"]
+        );
+        // other parse errors are left alone
+        assert!(matches!(Config::parse("config.json", "{}"), Err(ConfigError::Parse { .. })));
+
+        // a leftover next to fee_bps isn't silently ignored
+        let config = Config::parse("config.json", r#"{ "fee_bps": 10, "fee_percentage": 0.001 }"#).unwrap();
+        let problems = match config.validate("mainnet") {
+            Err(ConfigError::Invalid(problems)) => problems,
+            other => panic!("unexpected result: {:?}", other),
+        };
+        assert_eq!(problems[0], "fee_percentage was replaced by fee_bps (0.001 = 10 bps), set fee_bps to 10");
+    }
+
+    #[test]
+    fn keypair_paths_expand_the_home_directory() {
+        let home = Some(OsString::from("/home/arb"));
+        assert_eq!(
+            expand_home_from("~/.config/solana/id.json", home.clone()),
+            PathBuf::from("/home/arb/.config/solana/id.json")
+        );
+        assert_eq!(expand_home_from("keys/id.json", home.clone()), PathBuf::from("keys/id.json"));
+        assert_eq!(expand_home_from("~other/id.json", home), PathBuf::from("~other/id.json"));
+        assert_eq!(expand_home_from("~/id.json", None), PathBuf::from("~/id.json"));
+    }
+
+    #[test]
+    fn clusters_come_from_config_and_overrides() {
+        let mut config: Config = serde_json::from_str(
+            r#"{
+                "fee_bps": 10,
+                "pool_dirs": [{ "pool_type": "orca", "dir_path": "pools/orca" }],
+                "clusters": {
+                    "devnet": {
+                        "rpc_endpoints": ["https://api.devnet.solana.com"],
+                        "pool_dirs": [{ "pool_type": "orca", "dir_path": "pools/devnet/orca" }]
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.cluster_pool_dirs("devnet")[0].dir_path, "pools/devnet/orca");
+        assert_eq!(config.cluster_pool_dirs("mainnet")[0].dir_path, "pools/orca");
+        let devnet = config.cluster("devnet");
+        assert_eq!(devnet.kind("devnet"), Cluster::Devnet);
+        assert_eq!(devnet.ws_endpoint().unwrap(), "wss://api.devnet.solana.com");
+
+        config.override_endpoints("fork", Some("http://10.0.0.2:8899"), None);
+        let fork = config.cluster("fork");
+        assert_eq!(fork.send_endpoints, vec!["http://10.0.0.2:8899"]);
+        assert_eq!(
+            fork.kind("fork"),
+            Cluster::Custom("http://10.0.0.2:8899".to_string(), "ws://10.0.0.2:8900".to_string())
+        );
+    }
+}
@@ -0,0 +1,296 @@
+//! Runtime config reloading
+//!
+//! Re-reads the config file when its mtime changes or the process receives
+//! SIGHUP. Only `RuntimeParams` are applied live; changes to anything else
+//! (keypair, clusters, pool dirs, graph cache, on-chain pool parameters,
+//! token sources, start mint, control api, pruning, flash loans, dedup, route
+//! cooldowns, pool quarantine, oracle, jupiter check, re-quotes, settlement,
+//! dust consolidation, the fee balance monitor, profit sweeps, adaptive tips,
+//! compute limits, priority fees, position sizing, bundling, the executor,
+//! lookup tables, commitment levels, rpc rate limits, overrides, incremental
+//! search, the route cache, the event stream, the fleet role, the shard, the
+//! analytics export, the blockhash cache, the recording, realized slippage,
+//! exposure limits, the trading universe, the token probe, the Jupiter
+//! pools) are rejected until a restart.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use signal_hook::consts::SIGHUP;
+use tracing::{info, warn};
+
+use crate::config::{Config, ConfigError, RuntimeParams};
+
+#[derive(Debug)]
+pub enum ReloadOutcome {
+    /// The new runtime parameters were applied
+    Applied(RuntimeParams),
+    /// The file changed but nothing hot-reloadable differs
+    Unchanged,
+    /// The file changed fields that need a restart; nothing was applied
+    RestartRequired(Vec<&'static str>),
+    /// The new file failed to load or validate; nothing was applied
+    Invalid(String),
+}
+
+pub struct ConfigWatcher {
+    path: String,
+    cluster: String,
+    /// `--rpc-url` / `--ws-url`, applied to every reloaded config
+    rpc_url: Option<String>,
+    ws_url: Option<String>,
+    current: Config,
+    last_modified: Option<SystemTime>,
+    sighup: Arc<AtomicBool>,
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl ConfigWatcher {
+    /// # Arguments
+    /// * `path` - Config file the bot was started with
+    /// * `cluster` - Cluster name the new config is validated against
+    /// * `current` - The config the bot is currently running with
+    pub fn new(path: &str, cluster: &str, current: Config) -> Self {
+        let sighup = Arc::new(AtomicBool::new(false));
+        if let Err(e) = signal_hook::flag::register(SIGHUP, sighup.clone()) {
+            warn!("could not register SIGHUP handler: {}", e);
+        }
+        Self {
+            path: path.to_string(),
+            cluster: cluster.to_string(),
+            rpc_url: None,
+            ws_url: None,
+            current,
+            last_modified: modified(path),
+            sighup,
+        }
+    }
+
+    /// Endpoints that override the cluster's configured ones (see
+    /// `Config::override_endpoints`)
+    pub fn with_endpoint_overrides(mut self, rpc_url: Option<String>, ws_url: Option<String>) -> Self {
+        self.rpc_url = rpc_url;
+        self.ws_url = ws_url;
+        self
+    }
+
+    pub fn current(&self) -> &Config {
+        &self.current
+    }
+
+    /// Checks for a config change. Returns `None` if neither the file changed
+    /// nor a SIGHUP was received since the last call.
+    pub fn poll(&mut self) -> Option<ReloadOutcome> {
+        let mtime = modified(&self.path);
+        let hup = self.sighup.swap(false, Ordering::Relaxed);
+        if !hup && mtime == self.last_modified {
+            return None;
+        }
+        self.last_modified = mtime;
+        info!("reloading config from {} (sighup: {})", self.path, hup);
+
+        let outcome = match self.load() {
+            Err(e) => ReloadOutcome::Invalid(e.to_string()),
+            Ok(new) => {
+                let blocked = restart_required_changes(&self.current, &new);
+                if !blocked.is_empty() {
+                    ReloadOutcome::RestartRequired(blocked)
+                } else {
+                    let old_params = RuntimeParams::from_config(&self.current);
+                    let new_params = RuntimeParams::from_config(&new);
+                    self.current = new;
+                    if old_params == new_params {
+                        ReloadOutcome::Unchanged
+                    } else {
+                        ReloadOutcome::Applied(new_params)
+                    }
+                }
+            }
+        };
+
+        match &outcome {
+            ReloadOutcome::Applied(params) => info!("applied new runtime params: {:?}", params),
+            ReloadOutcome::Unchanged => info!("config reloaded, runtime params unchanged"),
+            ReloadOutcome::RestartRequired(fields) => warn!(
+                "config changes to [{}] require a restart; ignoring the reload",
+                fields.join(", ")
+            ),
+            ReloadOutcome::Invalid(e) => warn!("ignoring invalid config reload: {}", e),
+        }
+        Some(outcome)
+    }
+
+    fn load(&self) -> Result<Config, ConfigError> {
+        let mut config = Config::load(&self.path)?;
+        config.override_endpoints(&self.cluster, self.rpc_url.as_deref(), self.ws_url.as_deref());
+        config.validate(&self.cluster)?;
+        Ok(config)
+    }
+}
+
+/// Fields that differ between `old` and `new` and can't be applied live
+pub fn restart_required_changes(old: &Config, new: &Config) -> Vec<&'static str> {
+    let mut changed = vec![];
+    if old.keypair_path != new.keypair_path
+        || old.keypair_env != new.keypair_env
+        || old.keypair_passphrase_env != new.keypair_passphrase_env
+        || old.signer != new.signer
+    {
+        changed.push("keypair");
+    }
+    if serde_json::to_value(&old.clusters).ok() != serde_json::to_value(&new.clusters).ok() {
+        changed.push("clusters");
+    }
+    if serde_json::to_value(&old.pool_dirs).ok() != serde_json::to_value(&new.pool_dirs).ok() {
+        changed.push("pool_dirs");
+    }
+    if old.graph_cache != new.graph_cache {
+        changed.push("graph_cache");
+    }
+    if old.onchain_params != new.onchain_params {
+        changed.push("onchain_params");
+    }
+    if old.tokens != new.tokens {
+        changed.push("tokens");
+    }
+    if serde_json::to_value(&old.start_mint).ok() != serde_json::to_value(&new.start_mint).ok() {
+        changed.push("start_mint");
+    }
+    if old.dex_urls != new.dex_urls {
+        changed.push("dex_urls");
+    }
+    if old.control_addr != new.control_addr || old.control_token != new.control_token {
+        changed.push("control api");
+    }
+    if serde_json::to_value(&old.prune).ok() != serde_json::to_value(&new.prune).ok() {
+        changed.push("prune");
+    }
+    if serde_json::to_value(&old.flash_loan).ok() != serde_json::to_value(&new.flash_loan).ok() {
+        changed.push("flash_loan");
+    }
+    if serde_json::to_value(&old.dedup).ok() != serde_json::to_value(&new.dedup).ok() {
+        changed.push("dedup");
+    }
+    if serde_json::to_value(&old.cooldown).ok() != serde_json::to_value(&new.cooldown).ok() {
+        changed.push("cooldown");
+    }
+    if serde_json::to_value(&old.quarantine).ok() != serde_json::to_value(&new.quarantine).ok() {
+        changed.push("quarantine");
+    }
+    if serde_json::to_value(&old.oracle).ok() != serde_json::to_value(&new.oracle).ok() {
+        changed.push("oracle");
+    }
+    if serde_json::to_value(&old.jupiter_check).ok() != serde_json::to_value(&new.jupiter_check).ok() {
+        changed.push("jupiter_check");
+    }
+    if serde_json::to_value(&old.requote).ok() != serde_json::to_value(&new.requote).ok() {
+        changed.push("requote");
+    }
+    if serde_json::to_value(&old.settle).ok() != serde_json::to_value(&new.settle).ok() {
+        changed.push("settle");
+    }
+    if serde_json::to_value(&old.dust).ok() != serde_json::to_value(&new.dust).ok() {
+        changed.push("dust");
+    }
+    if serde_json::to_value(&old.fee_balance).ok() != serde_json::to_value(&new.fee_balance).ok() {
+        changed.push("fee_balance");
+    }
+    if serde_json::to_value(&old.profit_sweep).ok() != serde_json::to_value(&new.profit_sweep).ok() {
+        changed.push("profit_sweep");
+    }
+    if serde_json::to_value(&old.tip).ok() != serde_json::to_value(&new.tip).ok() {
+        changed.push("tip");
+    }
+    if serde_json::to_value(&old.compute).ok() != serde_json::to_value(&new.compute).ok() {
+        changed.push("compute");
+    }
+    if serde_json::to_value(&old.priority_fee).ok() != serde_json::to_value(&new.priority_fee).ok() {
+        changed.push("priority_fee");
+    }
+    if serde_json::to_value(&old.sizing).ok() != serde_json::to_value(&new.sizing).ok() {
+        changed.push("sizing");
+    }
+    if serde_json::to_value(&old.bundle).ok() != serde_json::to_value(&new.bundle).ok() {
+        changed.push("bundle");
+    }
+    if old.executor != new.executor || old.max_in_flight != new.max_in_flight {
+        changed.push("executor");
+    }
+    if old.lookup_tables != new.lookup_tables {
+        changed.push("lookup_tables");
+    }
+    if old.commitment != new.commitment {
+        changed.push("commitment");
+    }
+    if serde_json::to_value(&old.rate_limit).ok() != serde_json::to_value(&new.rate_limit).ok() {
+        changed.push("rate_limit");
+    }
+    if serde_json::to_value(&old.overrides).ok() != serde_json::to_value(&new.overrides).ok() {
+        changed.push("overrides");
+    }
+    if serde_json::to_value(&old.incremental).ok() != serde_json::to_value(&new.incremental).ok() {
+        changed.push("incremental");
+    }
+    if serde_json::to_value(&old.route_cache).ok() != serde_json::to_value(&new.route_cache).ok() {
+        changed.push("route_cache");
+    }
+    if serde_json::to_value(&old.events).ok() != serde_json::to_value(&new.events).ok() {
+        changed.push("events");
+    }
+    if serde_json::to_value(&old.fleet).ok() != serde_json::to_value(&new.fleet).ok() {
+        changed.push("fleet");
+    }
+    if serde_json::to_value(&old.shard).ok() != serde_json::to_value(&new.shard).ok() {
+        changed.push("shard");
+    }
+    if serde_json::to_value(&old.analytics).ok() != serde_json::to_value(&new.analytics).ok() {
+        changed.push("analytics");
+    }
+    if serde_json::to_value(&old.blockhash_cache).ok() != serde_json::to_value(&new.blockhash_cache).ok() {
+        changed.push("blockhash_cache");
+    }
+    if old.record != new.record {
+        changed.push("record");
+    }
+    if serde_json::to_value(&old.slippage).ok() != serde_json::to_value(&new.slippage).ok() {
+        changed.push("slippage");
+    }
+    if serde_json::to_value(&old.exposure).ok() != serde_json::to_value(&new.exposure).ok() {
+        changed.push("exposure");
+    }
+    if serde_json::to_value(&old.universe).ok() != serde_json::to_value(&new.universe).ok() {
+        changed.push("universe");
+    }
+    if serde_json::to_value(&old.token_probe).ok() != serde_json::to_value(&new.token_probe).ok() {
+        changed.push("token_probe");
+    }
+    if serde_json::to_value(&old.jupiter_pools).ok() != serde_json::to_value(&new.jupiter_pools).ok() {
+        changed.push("jupiter_pools");
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(json: &str) -> Config {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn only_restart_fields_are_reported() {
+        let old = config(r#"{ "fee_bps": 10, "keypair_path": "a.json" }"#);
+        let new = config(
+            r#"{ "fee_bps": 20, "slippage_bps": 10, "keypair_path": "a.json" }"#,
+        );
+        assert!(restart_required_changes(&old, &new).is_empty());
+
+        let new = config(r#"{ "fee_bps": 10, "keypair_path": "b.json" }"#);
+        assert_eq!(restart_required_changes(&old, &new), vec!["keypair"]);
+    }
+}
@@ -0,0 +1,343 @@
+//! HTTP control / status API
+//!
+//! Optional embedded HTTP server (enabled with `control_addr` in the config)
+//! to operate the bot remotely:
+//!
+//! * `GET  /status`        - pools loaded, pause state, tick latency, PnL
+//! * `GET  /opportunities` - most recent opportunities found
+//! * `GET  /landing`       - landing rates by endpoint, fee band and tip band
+//! * `GET  /cooldowns`     - routes skipped after failing repeatedly, and why
+//! * `GET  /quarantine`    - pools left out of the search after failing repeatedly
+//! * `GET  /latency`       - time spent per pipeline stage
+//! * `POST /pause`, `POST /resume` - stop / restart executing
+//! * `POST /params`        - adjust thresholds, body e.g. `{"slippage_bps": 20}`
+//!
+//! If `control_token` is set every request needs `Authorization: Bearer <token>`.
+
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use tracing::{info, warn};
+
+use crate::config::RuntimeParams;
+use crate::cooldown::{CooldownEntry, RouteCooldowns};
+use crate::landing::LandingReport;
+use crate::latency::{LatencyReport, LatencyStats};
+use crate::quarantine::{PoolQuarantine, QuarantineEntry};
+use crate::session::{Opportunity, SessionStats, SessionSummary};
+
+/// Threshold changes posted to `/params`; unset fields are left as they are
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ParamsUpdate {
+    pub fee_bps: Option<u64>,
+    pub slippage_bps: Option<u64>,
+    pub tip_lamports: Option<u64>,
+}
+
+impl ParamsUpdate {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(fee) = self.fee_bps {
+            if fee >= 10_000 {
+                return Err(format!("fee_bps must be < 10000, got {}", fee));
+            }
+        }
+        if let Some(bps) = self.slippage_bps {
+            if bps > 10_000 {
+                return Err(format!("slippage_bps must be <= 10000, got {}", bps));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, params: &mut RuntimeParams) {
+        if let Some(fee) = self.fee_bps {
+            params.fee_bps = fee;
+        }
+        if let Some(bps) = self.slippage_bps {
+            params.slippage_bps = bps;
+        }
+        if let Some(tip) = self.tip_lamports {
+            params.tip_lamports = tip;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+struct TickInfo {
+    ticks: u64,
+    last_tick_ms: Option<u128>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Status {
+    pub pools_loaded: usize,
+    pub mints_loaded: usize,
+    pub paused: bool,
+    pub ticks: u64,
+    pub last_tick_ms: Option<u128>,
+    pub session: SessionSummary,
+}
+
+/// State shared between the trading loop and the HTTP server
+pub struct ControlState {
+    pools_loaded: usize,
+    mints_loaded: usize,
+    stats: Arc<SessionStats>,
+    paused: AtomicBool,
+    tick: Mutex<TickInfo>,
+    pending_params: Mutex<Option<ParamsUpdate>>,
+    landing: Option<Arc<Mutex<LandingReport>>>,
+    cooldowns: Option<Arc<RouteCooldowns>>,
+    quarantine: Option<Arc<PoolQuarantine>>,
+    latency: Option<Arc<LatencyStats>>,
+}
+
+impl ControlState {
+    pub fn new(pools_loaded: usize, mints_loaded: usize, stats: Arc<SessionStats>) -> Self {
+        Self {
+            pools_loaded,
+            mints_loaded,
+            stats,
+            paused: AtomicBool::new(false),
+            tick: Mutex::new(TickInfo::default()),
+            pending_params: Mutex::new(None),
+            landing: None,
+            cooldowns: None,
+            quarantine: None,
+            latency: None,
+        }
+    }
+
+    /// Serves the landing tracker's report on `/landing`
+    pub fn with_landing(mut self, report: Arc<Mutex<LandingReport>>) -> Self {
+        self.landing = Some(report);
+        self
+    }
+
+    /// Serves the routes in cooldown on `/cooldowns`
+    pub fn with_cooldowns(mut self, cooldowns: Arc<RouteCooldowns>) -> Self {
+        self.cooldowns = Some(cooldowns);
+        self
+    }
+
+    /// Serves the quarantined pools on `/quarantine`
+    pub fn with_quarantine(mut self, quarantine: Arc<PoolQuarantine>) -> Self {
+        self.quarantine = Some(quarantine);
+        self
+    }
+
+    /// Serves the stage timings on `/latency`
+    pub fn with_latency(mut self, latency: Arc<LatencyStats>) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    /// Records how long the last search tick took
+    pub fn record_tick(&self, latency: Duration) {
+        let mut tick = self.tick.lock().unwrap();
+        tick.ticks += 1;
+        tick.last_tick_ms = Some(latency.as_millis());
+    }
+
+    /// Threshold update posted since the last call, if any
+    pub fn take_params_update(&self) -> Option<ParamsUpdate> {
+        self.pending_params.lock().unwrap().take()
+    }
+
+    fn queue_params_update(&self, update: ParamsUpdate) {
+        let mut pending = self.pending_params.lock().unwrap();
+        // merge with an update the loop hasn't picked up yet
+        let merged = match pending.take() {
+            Some(old) => ParamsUpdate {
+                fee_bps: update.fee_bps.or(old.fee_bps),
+                slippage_bps: update.slippage_bps.or(old.slippage_bps),
+                tip_lamports: update.tip_lamports.or(old.tip_lamports),
+            },
+            None => update,
+        };
+        *pending = Some(merged);
+    }
+
+    pub fn status(&self) -> Status {
+        let tick = self.tick.lock().unwrap().clone();
+        Status {
+            pools_loaded: self.pools_loaded,
+            mints_loaded: self.mints_loaded,
+            paused: self.is_paused(),
+            ticks: tick.ticks,
+            last_tick_ms: tick.last_tick_ms,
+            session: self.stats.summary(None),
+        }
+    }
+
+    pub fn recent_opportunities(&self) -> Vec<Opportunity> {
+        self.stats.recent()
+    }
+
+    /// Landing report, empty if transactions aren't tracked (localnet)
+    pub fn landing(&self) -> LandingReport {
+        self.landing
+            .as_ref()
+            .map(|report| report.lock().unwrap().clone())
+            .unwrap_or_default()
+    }
+
+    /// Routes cooling down right now
+    pub fn cooldowns(&self) -> Vec<CooldownEntry> {
+        self.cooldowns
+            .as_ref()
+            .map(|cooldowns| cooldowns.active())
+            .unwrap_or_default()
+    }
+
+    /// Pools quarantined right now
+    pub fn quarantine(&self) -> Vec<QuarantineEntry> {
+        self.quarantine
+            .as_ref()
+            .map(|quarantine| quarantine.quarantined())
+            .unwrap_or_default()
+    }
+
+    pub fn latency(&self) -> LatencyReport {
+        self.latency
+            .as_ref()
+            .map(|latency| latency.report())
+            .unwrap_or_default()
+    }
+}
+
+/// Starts the server on a background thread
+///
+/// # Arguments
+/// * `addr` - Address to listen on, e.g. `127.0.0.1:8080`
+/// * `token` - Optional bearer token every request must carry
+/// * `state` - State shared with the trading loop
+pub fn spawn_server(
+    addr: &str,
+    token: Option<String>,
+    state: Arc<ControlState>,
+) -> Result<thread::JoinHandle<()>, String> {
+    let server = Server::http(addr).map_err(|e| format!("could not bind {}: {}", addr, e))?;
+    info!("control api listening on {}", addr);
+    Ok(thread::spawn(move || {
+        for request in server.incoming_requests() {
+            handle(request, token.as_deref(), &state);
+        }
+    }))
+}
+
+fn authorized(request: &Request, token: Option<&str>) -> bool {
+    let token = match token {
+        Some(token) => token,
+        None => return true,
+    };
+    let expected = format!("Bearer {}", token);
+    request
+        .headers()
+        .iter()
+        .any(|h| h.field.equiv("Authorization") && h.value.as_str() == expected)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Response<std::io::Cursor<Vec<u8>>> {
+    let body = serde_json::to_string(body).unwrap_or_else(|_| "{}".to_string());
+    let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+    Response::from_string(body)
+        .with_status_code(status)
+        .with_header(header)
+}
+
+fn error_response(status: u16, msg: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    json_response(status, &serde_json::json!({ "error": msg }))
+}
+
+fn handle(mut request: Request, token: Option<&str>, state: &ControlState) {
+    let response = if !authorized(&request, token) {
+        error_response(401, "unauthorized")
+    } else {
+        match (request.method(), request.url()) {
+            (Method::Get, "/status") => json_response(200, &state.status()),
+            (Method::Get, "/opportunities") => json_response(200, &state.recent_opportunities()),
+            (Method::Get, "/landing") => json_response(200, &state.landing()),
+            (Method::Get, "/cooldowns") => json_response(200, &state.cooldowns()),
+            (Method::Get, "/quarantine") => json_response(200, &state.quarantine()),
+            (Method::Get, "/latency") => json_response(200, &state.latency()),
+            (Method::Post, "/pause") => {
+                info!("execution paused via control api");
+                state.set_paused(true);
+                json_response(200, &serde_json::json!({ "paused": true }))
+            }
+            (Method::Post, "/resume") => {
+                info!("execution resumed via control api");
+                state.set_paused(false);
+                json_response(200, &serde_json::json!({ "paused": false }))
+            }
+            (Method::Post, "/params") => {
+                let mut body = String::new();
+                let parsed = request
+                    .as_reader()
+                    .read_to_string(&mut body)
+                    .map_err(|e| e.to_string())
+                    .and_then(|_| serde_json::from_str::<ParamsUpdate>(&body).map_err(|e| e.to_string()))
+                    .and_then(|update| update.validate().map(|_| update));
+                match parsed {
+                    Ok(update) => {
+                        info!("params update queued via control api: {:?}", update);
+                        state.queue_params_update(update.clone());
+                        json_response(202, &update)
+                    }
+                    Err(e) => error_response(400, &e),
+                }
+            }
+            _ => error_response(404, "not found"),
+        }
+    };
+    if let Err(e) = request.respond(response) {
+        warn!("control api: failed to respond: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queued_updates_are_merged() {
+        let state = ControlState::new(0, 0, Arc::new(SessionStats::new(0)));
+        state.queue_params_update(ParamsUpdate {
+            slippage_bps: Some(10),
+            tip_lamports: Some(1_000),
+            ..Default::default()
+        });
+        state.queue_params_update(ParamsUpdate {
+            slippage_bps: Some(20),
+            ..Default::default()
+        });
+        let update = state.take_params_update().unwrap();
+        assert_eq!(update.slippage_bps, Some(20));
+        assert_eq!(update.tip_lamports, Some(1_000));
+        assert!(state.take_params_update().is_none());
+    }
+
+    #[test]
+    fn invalid_updates_are_rejected() {
+        let update = ParamsUpdate {
+            slippage_bps: Some(10_001),
+            ..Default::default()
+        };
+        assert!(update.validate().is_err());
+    }
+}
@@ -0,0 +1,215 @@
+//! Per-route circuit breaker
+//!
+//! A route that keeps failing (signing or instruction errors, reverts,
+//! transactions that never land) is usually broken until the market moves:
+//! a stale pool, a missing open orders account, a competitor always ahead.
+//! After `max_failures` failures in a row the route is skipped for
+//! `base_secs`, doubling with every further failure up to `max_secs`. A
+//! landed send closes the breaker again.
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+/// Circuit breaker settings
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CooldownConfig {
+    /// Failures in a row before a route is cooled down; 0 disables the breaker
+    #[serde(default = "default_max_failures")]
+    pub max_failures: u32,
+    /// First cooldown (seconds), doubled with every further failure
+    #[serde(default = "default_base_secs")]
+    pub base_secs: u64,
+    /// Longest cooldown (seconds)
+    #[serde(default = "default_max_secs")]
+    pub max_secs: u64,
+}
+
+fn default_max_failures() -> u32 {
+    3
+}
+
+fn default_base_secs() -> u64 {
+    10
+}
+
+fn default_max_secs() -> u64 {
+    600
+}
+
+impl Default for CooldownConfig {
+    fn default() -> Self {
+        Self {
+            max_failures: default_max_failures(),
+            base_secs: default_base_secs(),
+            max_secs: default_max_secs(),
+        }
+    }
+}
+
+impl CooldownConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.base_secs == 0 {
+            problems.push("cooldown.base_secs must be > 0".to_string());
+        }
+        if self.max_secs < self.base_secs {
+            problems.push(format!(
+                "cooldown.max_secs ({}) must be >= cooldown.base_secs ({})",
+                self.max_secs, self.base_secs
+            ));
+        }
+        problems
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RouteState {
+    failures: u32,
+    until: Option<Instant>,
+    last_error: String,
+}
+
+/// A route currently skipped, served on `GET /cooldowns`
+#[derive(Debug, Clone, Serialize)]
+pub struct CooldownEntry {
+    pub route: String,
+    /// Failures in a row
+    pub failures: u32,
+    pub remaining_secs: u64,
+    pub last_error: String,
+}
+
+/// Failure streaks and cooldowns of every route, see the module docs
+pub struct RouteCooldowns {
+    config: CooldownConfig,
+    routes: Mutex<HashMap<String, RouteState>>,
+}
+
+impl RouteCooldowns {
+    pub fn new(config: &CooldownConfig) -> Self {
+        Self {
+            config: config.clone(),
+            routes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether `route` is cooling down and must not be sent
+    pub fn is_cooling(&self, route: &str) -> bool {
+        let routes = self.routes.lock().unwrap();
+        match routes.get(route).and_then(|state| state.until) {
+            Some(until) => Instant::now() < until,
+            None => false,
+        }
+    }
+
+    /// A send of `route` failed with `error`; returns the cooldown it started, if any
+    pub fn record_failure(&self, route: &str, error: &str) -> Option<Duration> {
+        if self.config.max_failures == 0 {
+            return None;
+        }
+        let mut routes = self.routes.lock().unwrap();
+        let state = routes.entry(route.to_string()).or_insert(RouteState {
+            failures: 0,
+            until: None,
+            last_error: String::new(),
+        });
+        state.failures += 1;
+        state.last_error = error.to_string();
+        if state.failures < self.config.max_failures {
+            return None;
+        }
+        let cooldown = self.cooldown(state.failures);
+        state.until = Some(Instant::now() + cooldown);
+        info!(
+            "{} failed {} times in a row ({}), cooling down for {}s",
+            route,
+            state.failures,
+            error,
+            cooldown.as_secs()
+        );
+        Some(cooldown)
+    }
+
+    /// A send of `route` landed, its failure streak is over
+    pub fn record_success(&self, route: &str) {
+        self.routes.lock().unwrap().remove(route);
+    }
+
+    /// Cooldown after `failures` failures in a row (at least `max_failures`)
+    fn cooldown(&self, failures: u32) -> Duration {
+        let doublings = (failures - self.config.max_failures).min(32);
+        let secs = self
+            .config
+            .base_secs
+            .saturating_mul(1 << doublings)
+            .min(self.config.max_secs);
+        Duration::from_secs(secs)
+    }
+
+    /// Routes cooling down right now, longest remaining first
+    pub fn active(&self) -> Vec<CooldownEntry> {
+        let now = Instant::now();
+        let routes = self.routes.lock().unwrap();
+        let mut entries: Vec<CooldownEntry> = routes
+            .iter()
+            .filter_map(|(route, state)| {
+                let until = state.until.filter(|until| *until > now)?;
+                Some(CooldownEntry {
+                    route: route.clone(),
+                    failures: state.failures,
+                    remaining_secs: (until - now).as_secs(),
+                    last_error: state.last_error.clone(),
+                })
+            })
+            .collect();
+        entries.sort_by(|a, b| b.remaining_secs.cmp(&a.remaining_secs).then(a.route.cmp(&b.route)));
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_failures_back_off_exponentially() {
+        let cooldowns = RouteCooldowns::new(&CooldownConfig {
+            max_failures: 2,
+            base_secs: 10,
+            max_secs: 30,
+        });
+        assert_eq!(cooldowns.record_failure("a", "timeout"), None);
+        assert!(!cooldowns.is_cooling("a"));
+        assert_eq!(cooldowns.record_failure("a", "timeout"), Some(Duration::from_secs(10)));
+        assert!(cooldowns.is_cooling("a"));
+        assert_eq!(cooldowns.record_failure("a", "revert"), Some(Duration::from_secs(20)));
+        assert_eq!(cooldowns.record_failure("a", "revert"), Some(Duration::from_secs(30)));
+
+        let active = cooldowns.active();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].failures, 4);
+        assert_eq!(active[0].last_error, "revert");
+
+        cooldowns.record_success("a");
+        assert!(!cooldowns.is_cooling("a"));
+        assert!(cooldowns.active().is_empty());
+    }
+
+    #[test]
+    fn zero_max_failures_disables_the_breaker() {
+        let cooldowns = RouteCooldowns::new(&CooldownConfig {
+            max_failures: 0,
+            ..CooldownConfig::default()
+        });
+        for _ in 0..10 {
+            assert_eq!(cooldowns.record_failure("a", "timeout"), None);
+        }
+        assert!(!cooldowns.is_cooling("a"));
+    }
+}
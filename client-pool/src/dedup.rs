@@ -0,0 +1,197 @@
+//! Opportunity deduplication
+//!
+//! Remembers which routes were sent recently so the same arb isn't spammed
+//! every search round. Entries are keyed by (route, amount bucket) and expire
+//! after `ttl_secs` or once the chain moved `ttl_slots` past the slot they were
+//! sent in, so a route can be retried once the pool state has changed.
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Dedup cache settings
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DedupConfig {
+    /// Seconds a sent route is suppressed for; 0 disables the time expiry
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+    /// Slots a sent route is suppressed for; 0 disables the slot expiry
+    #[serde(default = "default_ttl_slots")]
+    pub ttl_slots: u64,
+    /// Width of an amount bucket in bps: amounts within the same bucket
+    /// count as the same opportunity
+    #[serde(default = "default_bucket_bps")]
+    pub bucket_bps: u64,
+}
+
+fn default_ttl_secs() -> u64 {
+    30
+}
+
+fn default_ttl_slots() -> u64 {
+    10
+}
+
+fn default_bucket_bps() -> u64 {
+    1_000
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: default_ttl_secs(),
+            ttl_slots: default_ttl_slots(),
+            bucket_bps: default_bucket_bps(),
+        }
+    }
+}
+
+impl DedupConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.ttl_secs == 0 && self.ttl_slots == 0 {
+            problems.push("dedup.ttl_secs and dedup.ttl_slots can't both be 0".to_string());
+        }
+        if self.bucket_bps == 0 {
+            problems.push("dedup.bucket_bps must be > 0".to_string());
+        }
+        problems
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sent {
+    at: Instant,
+    slot: u64,
+}
+
+/// Routes sent recently, see the module docs
+pub struct DedupCache {
+    ttl: Option<Duration>,
+    ttl_slots: Option<u64>,
+    bucket_bps: u64,
+    slot: u64,
+    sent: HashMap<(String, u32), Sent>,
+}
+
+impl DedupCache {
+    pub fn new(config: &DedupConfig) -> Self {
+        Self {
+            ttl: (config.ttl_secs > 0).then(|| Duration::from_secs(config.ttl_secs)),
+            ttl_slots: (config.ttl_slots > 0).then(|| config.ttl_slots),
+            bucket_bps: config.bucket_bps.max(1),
+            slot: 0,
+            sent: HashMap::new(),
+        }
+    }
+
+    /// Logarithmic amount bucket: each bucket is `bucket_bps` wider than the last
+    pub fn bucket(&self, amount: u128) -> u32 {
+        if amount == 0 {
+            return 0;
+        }
+        let step = (1.0 + self.bucket_bps as f64 / 10_000.0).ln();
+        ((amount as f64).ln() / step) as u32 + 1
+    }
+
+    /// Latest slot seen by the bot, used for the slot expiry
+    pub fn set_slot(&mut self, slot: u64) {
+        self.slot = self.slot.max(slot);
+    }
+
+    fn expired(&self, sent: &Sent, now: Instant) -> bool {
+        let by_time = self.ttl.map_or(false, |ttl| now.duration_since(sent.at) >= ttl);
+        let by_slot = self
+            .ttl_slots
+            .map_or(false, |ttl| self.slot.saturating_sub(sent.slot) >= ttl);
+        by_time || by_slot
+    }
+
+    /// `true` (and the route is recorded as sent) if the route hasn't been
+    /// sent with a similar amount recently, `false` if it should be skipped
+    pub fn check_and_insert(&mut self, route: &str, amount: u128) -> bool {
+        self.check_and_insert_at(route, amount, Instant::now())
+    }
+
+    fn check_and_insert_at(&mut self, route: &str, amount: u128, now: Instant) -> bool {
+        let key = (route.to_string(), self.bucket(amount));
+        if let Some(sent) = self.sent.get(&key) {
+            if !self.expired(sent, now) {
+                return false;
+            }
+        }
+        self.sent.insert(key, Sent { at: now, slot: self.slot });
+        true
+    }
+
+    /// Drops expired entries so the cache doesn't grow forever
+    pub fn purge(&mut self) {
+        let now = Instant::now();
+        let expired: Vec<(String, u32)> = self
+            .sent
+            .iter()
+            .filter(|(_, sent)| self.expired(sent, now))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            self.sent.remove(&key);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.sent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sent.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(ttl_secs: u64, ttl_slots: u64) -> DedupCache {
+        DedupCache::new(&DedupConfig {
+            ttl_secs,
+            ttl_slots,
+            bucket_bps: 1_000,
+        })
+    }
+
+    #[test]
+    fn routes_expire_after_the_ttl() {
+        let mut cache = cache(10, 0);
+        let now = Instant::now();
+        assert!(cache.check_and_insert_at("a", 1_000_000, now));
+        assert!(!cache.check_and_insert_at("a", 1_000_000, now + Duration::from_secs(5)));
+        assert!(cache.check_and_insert_at("b", 1_000_000, now));
+        assert!(cache.check_and_insert_at("a", 1_000_000, now + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn routes_expire_after_the_slot_ttl() {
+        let mut cache = cache(0, 5);
+        let now = Instant::now();
+        cache.set_slot(100);
+        assert!(cache.check_and_insert_at("a", 1_000_000, now));
+        cache.set_slot(104);
+        assert!(!cache.check_and_insert_at("a", 1_000_000, now));
+        cache.set_slot(105);
+        assert!(cache.check_and_insert_at("a", 1_000_000, now));
+    }
+
+    #[test]
+    fn amounts_are_bucketed() {
+        let mut cache = cache(10, 0);
+        let now = Instant::now();
+        assert!(cache.check_and_insert_at("a", 1_000_000, now));
+        // within 10%: same bucket or the next one
+        let close = cache.bucket(1_020_000);
+        assert!(close.abs_diff(cache.bucket(1_000_000)) <= 1);
+        // halved amounts are a different opportunity
+        assert!(cache.check_and_insert_at("a", 500_000, now));
+    }
+}
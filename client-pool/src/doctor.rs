@@ -0,0 +1,275 @@
+//! Startup prerequisite checks (`doctor` subcommand)
+//!
+//! Everything a run needs that only shows up once it's running: a signer
+//! that signs, enough SOL for fees and tips, a start-mint token account with
+//! something to trade, open orders accounts for the Serum markets and the
+//! router program on the cluster. Every check is run and reported, a failing
+//! one doesn't hide the others.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signature::Signer;
+use anchor_client::Cluster;
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::compute::MAX_COMPUTE_UNITS;
+use crate::config::Config;
+use crate::constants::{ARB_PROGRAM_ID, SERUM_PROGRAM_ID};
+use crate::graph::PoolSet;
+use crate::pool::{PoolOperations, PoolType};
+use crate::rpc::RpcPool;
+use crate::serialize::decoder::DECODERS;
+use crate::session::LAMPORTS_PER_SIGNATURE;
+use crate::utils::derive_token_address;
+
+/// Sends the SOL balance should cover the fees and tips of
+pub const FEE_RUNWAY_SENDS: u64 = 100;
+
+/// Where `setup_open_orders` writes the open orders account of every market
+pub const OPEN_ORDERS_PATH: &str = "./serum_open_orders.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    /// Works, but probably not as intended
+    Warn,
+    /// A run would fail or trade nothing
+    Fail,
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Outcome of every check
+pub struct DoctorReport(pub Vec<Check>);
+
+impl DoctorReport {
+    /// Whether nothing failed (warnings are fine)
+    pub fn passed(&self) -> bool {
+        self.0.iter().all(|check| check.status != Status::Fail)
+    }
+}
+
+impl fmt::Display for DoctorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in self.0.iter() {
+            let status = match check.status {
+                Status::Ok => "ok",
+                Status::Warn => "WARN",
+                Status::Fail => "FAIL",
+            };
+            writeln!(f, "[{:<4}] {:<14} {}", status, check.name, check.detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lamports one send costs at most: the signature fee, the largest tip and
+/// the priority fee cap for a full compute budget
+pub fn send_cost(config: &Config) -> u64 {
+    let tip = config.tip.as_ref().map_or(config.tip_lamports, |tip| tip.max_lamports);
+    let priority_fee = config.priority_fee.as_ref().map_or(0, |fee| {
+        fee.max_micro_lamports.saturating_mul(MAX_COMPUTE_UNITS as u64) / 1_000_000
+    });
+    LAMPORTS_PER_SIGNATURE + tip + priority_fee
+}
+
+/// Lamports the fees and tips of `FEE_RUNWAY_SENDS` sends cost at most
+pub fn fee_budget(config: &Config) -> u64 {
+    send_cost(config).saturating_mul(FEE_RUNWAY_SENDS)
+}
+
+/// Runs every check against the cluster, `pool_set` only needs to be read
+/// (see `PoolSet::load`), not refreshed
+pub fn run_checks(
+    config: &Config,
+    cluster: &Cluster,
+    rpc_pool: &RpcPool,
+    owner: &dyn Signer,
+    pool_set: &PoolSet,
+) -> DoctorReport {
+    DoctorReport(vec![
+        check_signer(owner),
+        check_sol_balance(config, rpc_pool, &owner.pubkey()),
+        check_start_mint(config, rpc_pool, &owner.pubkey()),
+        check_open_orders(cluster, rpc_pool, pool_set),
+        check_router_program(rpc_pool),
+    ])
+}
+
+fn check_signer(owner: &dyn Signer) -> Check {
+    // remote and hardware signers only fail once asked to sign
+    match owner.try_sign_message(b"doctor") {
+        Ok(_) => Check::new("signer", Status::Ok, format!("{} signs", owner.pubkey())),
+        Err(e) => Check::new("signer", Status::Fail, format!("{} can't sign: {}", owner.pubkey(), e)),
+    }
+}
+
+fn check_sol_balance(config: &Config, rpc_pool: &RpcPool, owner: &Pubkey) -> Check {
+    let needed = fee_budget(config);
+    match rpc_pool.call(|client| client.get_balance(owner)) {
+        Ok(lamports) if lamports >= needed => Check::new(
+            "sol balance",
+            Status::Ok,
+            format!("{} lamports, {} needed for {} sends", lamports, needed, FEE_RUNWAY_SENDS),
+        ),
+        Ok(0) => Check::new("sol balance", Status::Fail, "no SOL to pay fees with"),
+        Ok(lamports) => Check::new(
+            "sol balance",
+            Status::Warn,
+            format!(
+                "{} lamports, less than the {} {} sends may cost",
+                lamports, needed, FEE_RUNWAY_SENDS
+            ),
+        ),
+        Err(e) => Check::new("sol balance", Status::Fail, format!("failed to fetch: {}", e)),
+    }
+}
+
+fn check_start_mint(config: &Config, rpc_pool: &RpcPool, owner: &Pubkey) -> Check {
+    let start_mint = config.start_mint();
+    let min_swap_amount = config.start_mint.as_ref().map_or(0, |c| c.min_swap_amount);
+    let ata = derive_token_address(owner, &start_mint);
+    let account = match rpc_pool.get_multiple_accounts(&[ata]) {
+        Ok(mut accounts) => accounts.pop().flatten(),
+        Err(e) => return Check::new("start mint", Status::Fail, format!("failed to fetch {}: {}", ata, e)),
+    };
+    let amount = match account.map(|acc| DECODERS.token_account(&acc)) {
+        None => {
+            return Check::new(
+                "start mint",
+                Status::Fail,
+                format!("token account {} of {} doesn't exist", ata, start_mint),
+            )
+        }
+        Some(Err(e)) => return Check::new("start mint", Status::Fail, format!("{} doesn't decode: {}", ata, e)),
+        Some(Ok(token)) => token.amount as u128,
+    };
+    // flash loans borrow the input, the account only has to exist
+    let flash_loan = config.flash_loan.as_ref().map_or(false, |fl| fl.enabled);
+    if amount < min_swap_amount && !flash_loan {
+        return Check::new(
+            "start mint",
+            Status::Fail,
+            format!("{} holds {}, less than min_swap_amount {}", ata, amount, min_swap_amount),
+        );
+    }
+    Check::new("start mint", Status::Ok, format!("{} holds {}", ata, amount))
+}
+
+fn check_open_orders(cluster: &Cluster, rpc_pool: &RpcPool, pool_set: &PoolSet) -> Check {
+    let markets: Vec<Pubkey> = pool_set
+        .pools
+        .iter()
+        .zip(pool_set.pool_types.iter())
+        .filter(|(_, tipe)| **tipe == PoolType::SerumPoolType)
+        .map(|(pool, _)| pool.get_address())
+        .collect();
+    if markets.is_empty() {
+        return Check::new("open orders", Status::Ok, "no serum markets loaded");
+    }
+    if *cluster != Cluster::Localnet {
+        return Check::new(
+            "open orders",
+            Status::Warn,
+            format!("{} serum markets loaded, serum only trades on localnet", markets.len()),
+        );
+    }
+    let open_orders: HashMap<String, String> = match std::fs::read_to_string(OPEN_ORDERS_PATH)
+        .map_err(|e| e.to_string())
+        .and_then(|raw| serde_json::from_str(&raw).map_err(|e| e.to_string()))
+    {
+        Ok(open_orders) => open_orders,
+        Err(e) => return Check::new("open orders", Status::Fail, format!("{}: {}", OPEN_ORDERS_PATH, e)),
+    };
+    let mut missing = vec![];
+    let mut accounts = vec![];
+    for market in markets.iter() {
+        match open_orders.get(&market.to_string()).and_then(|oo| oo.parse::<Pubkey>().ok()) {
+            Some(oo) => accounts.push(oo),
+            None => missing.push(market.to_string()),
+        }
+    }
+    for chunk in accounts.chunks(100) {
+        match rpc_pool.get_multiple_accounts(chunk) {
+            Ok(fetched) => {
+                for (oo, account) in chunk.iter().zip(fetched) {
+                    if account.map_or(true, |acc| acc.owner != *SERUM_PROGRAM_ID) {
+                        missing.push(oo.to_string());
+                    }
+                }
+            }
+            Err(e) => return Check::new("open orders", Status::Fail, format!("failed to fetch: {}", e)),
+        }
+    }
+    if missing.is_empty() {
+        Check::new("open orders", Status::Ok, format!("{} serum markets", markets.len()))
+    } else {
+        Check::new(
+            "open orders",
+            Status::Fail,
+            format!("{} of {} missing: {}", missing.len(), markets.len(), missing.join(", ")),
+        )
+    }
+}
+
+fn check_router_program(rpc_pool: &RpcPool) -> Check {
+    match rpc_pool.get_multiple_accounts(&[*ARB_PROGRAM_ID]) {
+        Ok(mut accounts) => match accounts.pop().flatten() {
+            Some(account) if account.executable => {
+                Check::new("router program", Status::Ok, format!("{} deployed", *ARB_PROGRAM_ID))
+            }
+            Some(_) => Check::new(
+                "router program",
+                Status::Fail,
+                format!("{} exists but isn't executable", *ARB_PROGRAM_ID),
+            ),
+            None => Check::new("router program", Status::Fail, format!("{} not deployed", *ARB_PROGRAM_ID)),
+        },
+        Err(e) => Check::new("router program", Status::Fail, format!("failed to fetch: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fee_budget_covers_tips_and_priority_fees() {
+        let config: Config = serde_json::from_str(r#"{ "fee_bps": 10, "tip_lamports": 10000 }"#).unwrap();
+        assert_eq!(fee_budget(&config), (5_000 + 10_000) * FEE_RUNWAY_SENDS);
+
+        let config: Config = serde_json::from_str(
+            r#"{ "fee_bps": 10, "priority_fee": { "max_micro_lamports": 1000000 } }"#,
+        )
+        .unwrap();
+        assert_eq!(fee_budget(&config), (5_000 + 1_400_000) * FEE_RUNWAY_SENDS);
+    }
+
+    #[test]
+    fn warnings_pass_failures_dont() {
+        let mut report = DoctorReport(vec![
+            Check::new("signer", Status::Ok, "signs"),
+            Check::new("sol balance", Status::Warn, "low"),
+        ]);
+        assert!(report.passed());
+        report.0.push(Check::new("router program", Status::Fail, "not deployed"));
+        assert!(!report.passed());
+        assert!(report.to_string().contains("[FAIL] router program"));
+    }
+}
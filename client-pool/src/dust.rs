@@ -0,0 +1,295 @@
+//! Dust consolidation
+//!
+//! Routes that revert halfway (split routes, partial fills) leave small
+//! balances of intermediate tokens in the owner's token accounts. Every
+//! `interval_secs` the owner's token accounts are listed; a balance of
+//! another mint than the start mint is swapped back through the direct pool
+//! that quotes the most for it, if that's worth at least `min_value` of the
+//! start mint. With `close_empty`, empty accounts of mints no loaded pool
+//! trades are closed and their rent goes back to the owner; accounts the
+//! search may route through stay open.
+//!
+//! Tokens without a direct pool to the start mint, and tokens outside the
+//! owner's associated token accounts, are left where they are.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signature::Signer;
+use serde::{Deserialize, Serialize};
+use solana_sdk::instruction::Instruction;
+
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+use crate::arb::Arbitrager;
+use crate::executor::Dispatcher;
+use crate::pool::PoolOperations;
+use crate::rpc::RpcPool;
+use crate::serialize::token::try_unpack_token_account;
+use crate::utils::{derive_token_address, PoolQuote};
+
+/// Accounts closed in one transaction
+const CLOSES_PER_TX: usize = 10;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DustConfig {
+    /// Time between two consolidations
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// Smallest swap output worth sending (start mint, scaled)
+    #[serde(default)]
+    pub min_value: u64,
+    /// Close empty accounts of mints no loaded pool trades
+    #[serde(default = "default_close_empty")]
+    pub close_empty: bool,
+}
+
+fn default_interval_secs() -> u64 {
+    600
+}
+
+fn default_close_empty() -> bool {
+    true
+}
+
+impl DustConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.interval_secs == 0 {
+            problems.push("dust.interval_secs must be > 0".to_string());
+        }
+        problems
+    }
+}
+
+/// One of the owner's token accounts
+#[derive(Debug, Clone, PartialEq)]
+pub struct Holding {
+    pub account: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    /// Whether it's the owner's associated account of `mint`, the only one
+    /// swaps move tokens out of
+    pub ata: bool,
+}
+
+/// What a consolidation does with a holding
+#[derive(Debug)]
+pub enum DustAction {
+    /// Swap the whole balance into the start mint through `pool`
+    Sweep {
+        holding: Holding,
+        pool: PoolQuote,
+        amount_out: u128,
+    },
+    /// Close the empty account
+    Close(Holding),
+}
+
+/// Actions for `holdings`: `exits` lists the pools from a mint into
+/// `start_mint`, `traded` the mints the search routes through
+pub fn plan(
+    config: &DustConfig,
+    holdings: &[Holding],
+    start_mint: &Pubkey,
+    traded: &HashSet<Pubkey>,
+    exits: impl Fn(&Pubkey) -> Vec<PoolQuote>,
+) -> Vec<DustAction> {
+    let mut actions = vec![];
+    for holding in holdings.iter().filter(|h| h.mint != *start_mint) {
+        if holding.amount == 0 {
+            if config.close_empty && !traded.contains(&holding.mint) {
+                actions.push(DustAction::Close(holding.clone()));
+            }
+            continue;
+        }
+        if !holding.ata {
+            continue;
+        }
+        let best = exits(&holding.mint)
+            .into_iter()
+            .filter_map(|pool| {
+                let out = pool
+                    .0
+                    .get_quote_with_amounts_scaled(holding.amount as u128, &holding.mint, start_mint)
+                    .ok()?;
+                Some((pool, out))
+            })
+            .max_by_key(|(_, out)| *out);
+        if let Some((pool, amount_out)) = best {
+            if amount_out > 0 && amount_out >= config.min_value as u128 {
+                actions.push(DustAction::Sweep {
+                    holding: holding.clone(),
+                    pool,
+                    amount_out,
+                });
+            }
+        }
+    }
+    actions
+}
+
+/// The owner's token accounts that could be read
+pub fn holdings(rpc: &RpcPool, owner: &Pubkey) -> Result<Vec<Holding>, String> {
+    let accounts = rpc
+        .get_token_accounts_of(owner)
+        .map_err(|e| format!("could not list token accounts: {}", e))?;
+    Ok(accounts
+        .into_iter()
+        .filter_map(|(account, data)| {
+            let token = try_unpack_token_account(&data.data).ok()?;
+            Some(Holding {
+                account,
+                mint: token.mint,
+                amount: token.amount,
+                ata: account == derive_token_address(owner, &token.mint),
+            })
+        })
+        .collect())
+}
+
+/// Consolidates dust every `interval_secs`
+pub struct DustSweeper {
+    config: DustConfig,
+    last_run: Option<Instant>,
+}
+
+impl DustSweeper {
+    pub fn new(config: &DustConfig) -> Self {
+        Self {
+            config: config.clone(),
+            last_run: None,
+        }
+    }
+
+    /// Whether a consolidation is due, the first one right away
+    pub fn is_due(&self) -> bool {
+        self.last_run
+            .map_or(true, |last| last.elapsed() >= Duration::from_secs(self.config.interval_secs))
+    }
+
+    /// Lists the owner's accounts and sends the sweeps and closes; failures
+    /// are logged and retried at the next run
+    pub fn run(&mut self, ctx: &Dispatcher, arb: &Arbitrager, rpc: &RpcPool, start_mint: &Pubkey) {
+        self.last_run = Some(Instant::now());
+        // simulations and paper trades move no tokens
+        if !ctx.sends() {
+            return;
+        }
+        let owner = ctx.owner.pubkey();
+        let holdings = match holdings(rpc, &owner) {
+            Ok(holdings) => holdings,
+            Err(e) => {
+                warn!("dust: {}", e);
+                return;
+            }
+        };
+        let traded: HashSet<Pubkey> = arb.token_mints.iter().copied().collect();
+        let actions = plan(&self.config, &holdings, start_mint, &traded, |mint| {
+            arb.pools_between(mint, start_mint)
+        });
+        let mut closes = vec![];
+        for action in actions {
+            match action {
+                DustAction::Sweep {
+                    holding,
+                    pool,
+                    amount_out,
+                } => {
+                    info!(
+                        "dust: sweeping {} of {} through {} for {}",
+                        holding.amount,
+                        holding.mint,
+                        pool.0.get_id(),
+                        amount_out
+                    );
+                    match ctx.swap_ixs(holding.amount, &holding.mint, start_mint, &pool) {
+                        Ok(ixs) => {
+                            ctx.send_maintenance(&ixs, &[], "dust sweep");
+                        }
+                        Err(e) => warn!("dust: can't sweep {}: {}", holding.mint, e),
+                    }
+                }
+                DustAction::Close(holding) => {
+                    match spl_token::instruction::close_account(
+                        &spl_token::id(),
+                        &holding.account,
+                        &owner,
+                        &owner,
+                        &[],
+                    ) {
+                        Ok(ix) => closes.push(ix),
+                        Err(e) => warn!("dust: can't close {}: {}", holding.account, e),
+                    }
+                }
+            }
+        }
+        for chunk in closes.chunks(CLOSES_PER_TX) {
+            info!("dust: closing {} empty token accounts", chunk.len());
+            ctx.send_maintenance(chunk, &[], "dust close");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pools::PoolVariant;
+    use crate::tests::pools::orca_pool;
+    use std::rc::Rc;
+
+    fn holding(mint: &Pubkey, amount: u64) -> Holding {
+        Holding {
+            account: Pubkey::new_unique(),
+            mint: *mint,
+            amount,
+            ata: true,
+        }
+    }
+
+    #[test]
+    fn dust_goes_through_the_best_pool_and_unused_accounts_close() {
+        let (start, dust, tiny, gone, kept) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let shallow = PoolQuote::new(Rc::new(PoolVariant::from(orca_pool(&dust, &start, 1_000_000, 1_000_000))));
+        let deep = PoolQuote::new(Rc::new(PoolVariant::from(orca_pool(&dust, &start, 1_000_000_000, 1_000_000_000))));
+        let tiny_pool = PoolQuote::new(Rc::new(PoolVariant::from(orca_pool(&tiny, &start, 1_000_000, 1_000_000))));
+        let config: DustConfig = serde_json::from_str(r#"{ "min_value": 1000 }"#).unwrap();
+        let holdings = vec![
+            holding(&start, 5_000_000),
+            holding(&dust, 100_000),
+            holding(&tiny, 100),
+            holding(&gone, 0),
+            holding(&kept, 0),
+        ];
+        let traded: HashSet<Pubkey> = [start, dust, tiny, kept].into_iter().collect();
+        let actions = plan(&config, &holdings, &start, &traded, |mint| {
+            if *mint == dust {
+                vec![shallow.clone(), deep.clone()]
+            } else if *mint == tiny {
+                vec![tiny_pool.clone()]
+            } else {
+                vec![]
+            }
+        });
+
+        assert_eq!(actions.len(), 2);
+        match &actions[0] {
+            DustAction::Sweep { holding, pool, amount_out } => {
+                assert_eq!(holding.mint, dust);
+                assert_eq!(pool.0.get_id(), deep.0.get_id());
+                assert!(*amount_out >= 99_000);
+            }
+            other => panic!("{:?}", other),
+        }
+        // `kept` is still routed through, its account stays open
+        assert!(matches!(&actions[1], DustAction::Close(h) if h.mint == gone));
+    }
+}
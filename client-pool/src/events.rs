@@ -0,0 +1,269 @@
+//! Opportunity event stream
+//!
+//! With `events` set, a WebSocket server on `events.addr` pushes every
+//! opportunity a tick finds to every connected client as a JSON text
+//! message (an `OpportunityEvent`), before the opportunities are checked and
+//! sent. Dashboards or secondary executors can follow the bot's signal from
+//! it instead of reading logs.
+//!
+//! Clients only receive; every client has its own queue of `buffer` events
+//! and a client that falls that far behind misses events instead of holding
+//! up the search. Clients that disconnect are dropped at the next publish.
+//! If `token` is set, clients have to connect with `?token=<token>`.
+
+use serde::{Deserialize, Serialize};
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::StatusCode;
+use tungstenite::Message;
+
+use std::net::{SocketAddr, TcpListener};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tracing::{debug, info, warn};
+
+use crate::arb::ArbOpportunity;
+use crate::pool::PoolOperations;
+use crate::symbols::TokenSymbols;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EventStreamConfig {
+    /// Address the WebSocket server listens on, e.g. `127.0.0.1:8081`
+    pub addr: String,
+    /// Token clients pass as `?token=`; anyone may connect if unset
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Events queued per client before it misses some
+    #[serde(default = "default_buffer")]
+    pub buffer: usize,
+}
+
+fn default_buffer() -> usize {
+    1_000
+}
+
+impl EventStreamConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.addr.parse::<SocketAddr>().is_err() {
+            problems.push(format!("events.addr {} is not a socket address", self.addr));
+        }
+        if self.buffer == 0 {
+            problems.push("events.buffer must be > 0".to_string());
+        }
+        problems
+    }
+}
+
+/// An opportunity as published
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct OpportunityEvent {
+    /// Always `opportunity`, so other kinds of events can be added
+    pub kind: String,
+    /// Route key (mint indices and pool ids), as in the session stats
+    pub route: String,
+    /// The route with token symbols and DEXes
+    pub path: String,
+    pub mints: Vec<String>,
+    /// Address of every hop's pool
+    pub pools: Vec<String>,
+    /// Input (scaled start mint)
+    pub amount_in: u128,
+    /// Quoted output after slippage
+    pub min_amount_out: u128,
+    pub expected_profit: i128,
+    /// Slot the tick read the chain at
+    pub slot: u64,
+    /// Unix time in milliseconds
+    pub timestamp_ms: u64,
+}
+
+impl OpportunityEvent {
+    pub fn new(opportunity: &ArbOpportunity, symbols: &TokenSymbols, slot: u64) -> Self {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis() as u64);
+        Self {
+            kind: "opportunity".to_string(),
+            route: opportunity.route.clone(),
+            path: symbols.format_route(opportunity),
+            mints: opportunity.mints.iter().map(|mint| mint.to_string()).collect(),
+            pools: opportunity.pools.iter().map(|pool| pool.0.get_address().to_string()).collect(),
+            amount_in: opportunity.amount_in,
+            min_amount_out: opportunity.min_amount_out,
+            expected_profit: opportunity.expected_profit,
+            slot,
+            timestamp_ms,
+        }
+    }
+}
+
+/// The queues of the connected clients
+type Subscribers = Arc<Mutex<Vec<SyncSender<String>>>>;
+
+/// WebSocket server publishing opportunities
+pub struct EventStream {
+    addr: SocketAddr,
+    subscribers: Subscribers,
+}
+
+impl EventStream {
+    /// Binds `config.addr` and accepts clients in the background
+    pub fn spawn(config: &EventStreamConfig) -> Result<Self, String> {
+        let listener =
+            TcpListener::bind(&config.addr).map_err(|e| format!("could not bind {}: {}", config.addr, e))?;
+        let addr = listener.local_addr().map_err(|e| e.to_string())?;
+        let subscribers: Subscribers = Arc::new(Mutex::new(vec![]));
+        let accepted = subscribers.clone();
+        let (token, buffer) = (config.token.clone(), config.buffer);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        warn!("event stream: accept failed: {}", e);
+                        continue;
+                    }
+                };
+                let check_token = |request: &Request, response: Response| {
+                    if authorized(request.uri().query(), token.as_deref()) {
+                        Ok(response)
+                    } else {
+                        let mut denied = ErrorResponse::new(Some("unauthorized".to_string()));
+                        *denied.status_mut() = StatusCode::UNAUTHORIZED;
+                        Err::<Response, ErrorResponse>(denied)
+                    }
+                };
+                let mut socket = match tungstenite::accept_hdr(stream, check_token) {
+                    Ok(socket) => socket,
+                    Err(e) => {
+                        debug!("event stream: handshake failed: {}", e);
+                        continue;
+                    }
+                };
+                let (sender, receiver) = mpsc::sync_channel::<String>(buffer);
+                accepted.lock().unwrap().push(sender);
+                thread::spawn(move || {
+                    for event in receiver {
+                        if socket.write_message(Message::Text(event)).is_err() {
+                            break;
+                        }
+                    }
+                    let _ = socket.close(None);
+                });
+            }
+        });
+        info!("event stream listening on ws://{}", addr);
+        Ok(Self { addr, subscribers })
+    }
+
+    /// The address the server listens on (with the actual port for port 0)
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Connected clients
+    pub fn subscribers(&self) -> usize {
+        self.subscribers.lock().unwrap().len()
+    }
+
+    /// Queues `events` for every client; clients that are gone are dropped
+    pub fn publish(&self, events: &[OpportunityEvent]) {
+        if events.is_empty() {
+            return;
+        }
+        let messages: Vec<String> = events
+            .iter()
+            .filter_map(|event| serde_json::to_string(event).ok())
+            .collect();
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|subscriber| {
+            for message in messages.iter() {
+                match subscriber.try_send(message.clone()) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(_)) => {
+                        debug!("event stream: a client is behind, dropping an event");
+                    }
+                    Err(TrySendError::Disconnected(_)) => return false,
+                }
+            }
+            true
+        });
+    }
+}
+
+/// Whether the query string of a connection carries `token`
+fn authorized(query: Option<&str>, token: Option<&str>) -> bool {
+    let token = match token {
+        Some(token) => token,
+        None => return true,
+    };
+    query.map_or(false, |query| {
+        query.split('&').any(|pair| pair.strip_prefix("token=") == Some(token))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn event(profit: i128) -> OpportunityEvent {
+        OpportunityEvent {
+            kind: "opportunity".to_string(),
+            route: "0->1->0 via orca:a,orca:b".to_string(),
+            path: "USDC → SOL (Orca) → USDC (Orca) +1 USDC".to_string(),
+            mints: vec![],
+            pools: vec![],
+            amount_in: 1_000_000,
+            min_amount_out: 1_000_000 + profit as u128,
+            expected_profit: profit,
+            slot: 42,
+            timestamp_ms: 0,
+        }
+    }
+
+    #[test]
+    fn clients_receive_published_opportunities() {
+        let config: EventStreamConfig =
+            serde_json::from_str(r#"{ "addr": "127.0.0.1:0", "token": "secret" }"#).unwrap();
+        assert!(config.problems().is_empty());
+        let stream = EventStream::spawn(&config).unwrap();
+        let url = format!("ws://{}", stream.local_addr());
+        assert!(tungstenite::connect(url.as_str()).is_err());
+
+        let (mut client, _) = tungstenite::connect(format!("{}/?token=secret", url).as_str()).unwrap();
+        let start = Instant::now();
+        while stream.subscribers() == 0 {
+            assert!(start.elapsed() < Duration::from_secs(5), "client never subscribed");
+            thread::sleep(Duration::from_millis(10));
+        }
+        stream.publish(&[event(7), event(3)]);
+        for profit in [7, 3] {
+            let message = client.read_message().unwrap();
+            let received: OpportunityEvent = serde_json::from_str(message.to_text().unwrap()).unwrap();
+            assert_eq!(received, event(profit));
+        }
+
+        // gone clients are dropped at the next publish
+        client.close(None).unwrap();
+        drop(client);
+        let start = Instant::now();
+        while stream.subscribers() > 0 {
+            assert!(start.elapsed() < Duration::from_secs(5), "client never dropped");
+            stream.publish(&[event(1)]);
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn tokens_are_matched_in_the_query() {
+        assert!(authorized(None, None));
+        assert!(authorized(Some("a=1&token=t"), Some("t")));
+        assert!(!authorized(Some("token=tt"), Some("t")));
+        assert!(!authorized(None, Some("t")));
+    }
+}
@@ -0,0 +1,1281 @@
+//! Opportunity execution
+//!
+//! The `Dispatcher` takes the opportunities of a search round, highest net
+//! profit first (see `queue`), and decides what happens to each of them. Routes
+//! cooling down, rejected by the oracle or the Jupiter check, unable to
+//! afford their tip or sent recently are skipped; the rest are built
+//! (measured and, for bundles, split, see `tx_size`) and handed to the
+//! `Executor` backend picked by `executor`:
+//!
+//! - `rpc`: signed, broadcast to the send endpoints and confirmed, up to
+//!   `max_in_flight` routes at a time
+//! - `jito`: collected and sent as one Jito bundle at the end of the round
+//!   (see `bundle`), the default on mainnet with `bundle` set
+//! - `simulate`: signed and simulated, nothing is sent; the default on localnet
+//! - `paper`: nothing is signed or sent, every route counts as landed at its
+//!   quote, to see what a configuration would trade
+//!
+//! Outcomes feed back into route cooldowns, pool quarantine, position sizing
+//! and tips. The search doesn't know which backend runs.
+
+use anchor_client::solana_client::client_error::Result as ClientResult;
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_client::rpc_response::RpcSimulateTransactionResult;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+
+use anchor_client::solana_sdk::signature::Signer;
+use anchor_client::{Cluster, Program};
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::system_instruction;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::{Transaction, TransactionError};
+use solana_transaction_status::UiTransactionEncoding;
+
+use std::cell::RefCell;
+use std::collections::{HashSet, VecDeque};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use std::time::{Duration, Instant};
+
+use tracing::{debug, field, info, info_span, warn, Span};
+
+use crate::analytics::EventExporter;
+use crate::arb::ArbOpportunity;
+use crate::blockhash::BlockhashCache;
+use crate::bundle::{BundleConfig, Bundler, Candidate};
+use crate::compute::{
+    set_compute_unit_limit, set_compute_unit_price, ComputeEstimator, MAX_COMPUTE_UNITS,
+};
+use crate::config::{CommitmentLevels, RuntimeParams};
+use crate::constants::{JITO_TIP_ACCOUNTS, WSOL_MINT};
+use crate::cooldown::RouteCooldowns;
+use crate::dedup::DedupCache;
+use crate::error::{ArbError, ArbResult};
+use crate::exposure::ExposureTracker;
+use crate::failure::{decode_failure, DecodedFailure};
+use crate::flash_loan::FlashLoan;
+use crate::jupiter_check::JupiterCheck;
+use crate::jupiter_pools::{is_jupiter, JupiterApi};
+use crate::landing::{LandingTracker, Submission};
+use crate::latency::{LatencyStats, Stage};
+use crate::locks::{own_accounts, write_locks};
+use crate::oracle::Oracle;
+use crate::pool::PoolOperations;
+use crate::provider::PriorityFeeEstimator;
+use crate::quarantine::{PoolFailure, PoolQuarantine};
+use crate::queue::{InFlightLimit, OpportunityQueue, Permit};
+use crate::rate_limit::with_retries;
+use crate::requote::{fresh_pools, requote, Requote, RequoteConfig};
+use crate::rpc_api::RpcApi;
+use crate::sender::{BroadcastResult, BroadcastSender};
+use crate::session::{SessionStats, LAMPORTS_PER_SIGNATURE};
+use crate::settle::Settler;
+use crate::shutdown::Shutdown;
+use crate::sizing::PositionSizer;
+use crate::slippage::{self, SlippageTracker};
+use crate::symbols::TokenSymbols;
+use crate::templates::SwapTemplates;
+use crate::tip::TipController;
+use crate::tx_size::{self, LookupTables};
+
+use crate::utils::{derive_token_address, PoolQuote};
+
+/// Execution backends, see the module docs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutorKind {
+    Rpc,
+    Jito,
+    Simulate,
+    Paper,
+}
+
+impl ExecutorKind {
+    /// Backend used when `executor` isn't set: simulations on localnet,
+    /// bundles on mainnet with `bundle` set, plain sends otherwise
+    pub fn default_for(cluster: &Cluster, bundle: bool) -> Self {
+        match cluster {
+            Cluster::Localnet => ExecutorKind::Simulate,
+            Cluster::Mainnet if bundle => ExecutorKind::Jito,
+            _ => ExecutorKind::Rpc,
+        }
+    }
+
+    /// The backend of this kind; `jito` needs the `bundle` settings
+    pub fn backend(self, bundle: Option<&BundleConfig>) -> Box<dyn Executor> {
+        match (self, bundle) {
+            (ExecutorKind::Rpc, _) => Box::new(RpcExecutor::default()),
+            (ExecutorKind::Jito, Some(bundle)) => Box::new(JitoExecutor {
+                bundler: Bundler::new(bundle),
+            }),
+            (ExecutorKind::Jito, None) => {
+                warn!("executor jito needs bundle settings, sending routes one by one");
+                Box::new(RpcExecutor::default())
+            }
+            (ExecutorKind::Simulate, _) => Box::new(SimulateExecutor),
+            (ExecutorKind::Paper, _) => Box::new(PaperExecutor),
+        }
+    }
+}
+
+/// What became of a route handed to an `Executor`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionResult {
+    /// Landed (simulations: ran without error, paper trades: always)
+    Landed,
+    /// Not signed, reverted or never landed
+    Failed(String),
+    /// Not sent because an RPC call failed, which says nothing about the route
+    Unsent(String),
+    /// Held back for the end of the round, see `Executor::end_round`
+    Queued,
+}
+
+impl ExecutionResult {
+    /// `landed`, `failed: <why>`, `unsent: <why>` or `queued`
+    pub fn describe(&self) -> String {
+        match self {
+            ExecutionResult::Landed => "landed".to_string(),
+            ExecutionResult::Failed(error) => format!("failed: {}", error),
+            ExecutionResult::Unsent(error) => format!("unsent: {}", error),
+            ExecutionResult::Queued => "queued".to_string(),
+        }
+    }
+}
+
+/// A route's transactions as the `Dispatcher` built them
+#[derive(Debug, Clone)]
+pub struct BuiltRoute {
+    /// Instructions of each transaction, more than one only if the backend
+    /// `can_split`; tip and compute budget instructions aren't added yet
+    pub txs: Vec<Vec<Instruction>>,
+    pub tip: u64,
+    /// DEXes the route swaps on, routes of a shape share their compute estimate
+    pub shape: String,
+    /// Accounts the route writes, the owner's aside (see `locks`)
+    pub write_locks: HashSet<Pubkey>,
+    pub build_start: Instant,
+}
+
+/// Where the routes the `Dispatcher` lets through go
+pub trait Executor {
+    fn kind(&self) -> ExecutorKind;
+
+    /// Whether a route too large for one transaction may be split into several
+    fn can_split(&self) -> bool {
+        false
+    }
+
+    fn execute(&self, ctx: &Dispatcher, opportunity: &ArbOpportunity, route: BuiltRoute) -> ExecutionResult;
+
+    /// Called once the opportunities of a round are done
+    fn end_round(&self, _ctx: &Dispatcher) {}
+}
+
+/// Broadcasts every route on its own. With an in-flight limit of one each
+/// route is confirmed before the next is sent; above that routes are only
+/// confirmed once the limit is reached or the round ends.
+#[derive(Default)]
+pub struct RpcExecutor {
+    /// broadcast but not confirmed yet, oldest first
+    pending: RefCell<VecDeque<Pending>>,
+}
+
+/// A broadcast route waiting for its confirmation
+struct Pending {
+    opportunity: ArbOpportunity,
+    result: BroadcastResult,
+    signatures: usize,
+    tip: u64,
+    write_locks: HashSet<Pubkey>,
+    _permit: Permit,
+}
+
+impl RpcExecutor {
+    fn confirm(
+        &self,
+        ctx: &Dispatcher,
+        opportunity: &ArbOpportunity,
+        mut result: BroadcastResult,
+        signatures: usize,
+        tip: u64,
+    ) -> ExecutionResult {
+        let landed = ctx.latency.time(Stage::Confirm, || {
+            ctx.sender.confirm(&mut result, Duration::from_secs(30))
+        });
+        info!("landed: {} via {:?}", landed, result.landed_by);
+        if let Some(tips) = &ctx.tips {
+            tips.record_outcome(landed);
+        }
+        if landed {
+            let fee = LAMPORTS_PER_SIGNATURE * signatures as u64;
+            ctx.stats
+                .record_landed(&opportunity.route, opportunity.expected_profit, fee, tip);
+            ctx.measure_slippage(opportunity, result.signature);
+            ExecutionResult::Landed
+        } else if result.landed_by.is_some() {
+            ctx.stats.record_error("custom program error: tx landed but failed");
+            let failure = ctx.decode_failure(result.signature, result.error.as_ref());
+            warn!("{} reverted: {}", opportunity.route, failure);
+            ctx.stats.record_failure_reason(failure.reason.as_str());
+            ExecutionResult::Failed(format!("reverted: {}", failure))
+        } else {
+            ctx.stats.record_error("timed out waiting for confirmation");
+            ExecutionResult::Failed("not landed".to_string())
+        }
+    }
+
+    /// Confirms the oldest pending route, `false` if none is pending
+    fn confirm_oldest(&self, ctx: &Dispatcher) -> bool {
+        let pending = match self.pending.borrow_mut().pop_front() {
+            Some(pending) => pending,
+            None => return false,
+        };
+        let outcome = self.confirm(
+            ctx,
+            &pending.opportunity,
+            pending.result,
+            pending.signatures,
+            pending.tip,
+        );
+        ctx.record_outcome(&pending.opportunity, &outcome, None);
+        true
+    }
+}
+
+impl Executor for RpcExecutor {
+    fn kind(&self) -> ExecutorKind {
+        ExecutorKind::Rpc
+    }
+
+    fn execute(&self, ctx: &Dispatcher, opportunity: &ArbOpportunity, route: BuiltRoute) -> ExecutionResult {
+        // a route writing what one in flight writes would swap against the
+        // state that one leaves behind: sequence them
+        while self
+            .pending
+            .borrow()
+            .iter()
+            .any(|pending| !pending.write_locks.is_disjoint(&route.write_locks))
+        {
+            debug!(
+                "{} conflicts with a route in flight, confirming that first",
+                opportunity.route
+            );
+            self.confirm_oldest(ctx);
+        }
+        // wait for a slot before signing, confirming can take a while
+        let permit = loop {
+            if let Some(permit) = ctx.in_flight.try_acquire() {
+                break permit;
+            }
+            if !self.confirm_oldest(ctx) {
+                return ExecutionResult::Unsent("too many transactions in flight".to_string());
+            }
+        };
+        let (tx, priority_fee) = match ctx.sign_route(&route, ctx.commitment.sends()) {
+            Ok(signed) => signed,
+            Err(outcome) => return outcome,
+        };
+        ctx.stats.record_executed();
+        let sent_at = Instant::now();
+        let result = ctx.latency.time(Stage::Send, || ctx.sender.broadcast(&tx, ctx.context_slot()));
+        if let Some(signature) = result.signature {
+            Span::current().record("signature", &field::display(signature));
+        }
+        if let (Some(landing), Some(signature)) = (&ctx.landing, result.signature) {
+            landing.track(Submission {
+                signature,
+                endpoint: result.first_ack().unwrap_or("none").to_string(),
+                sent_at,
+                sent_slot: ctx.current_slot,
+                priority_fee,
+                tip_lamports: route.tip,
+            });
+        }
+        for (_, err) in result.errors.iter() {
+            ctx.stats.record_error(err);
+        }
+        info!(
+            signature = ?result.signature,
+            acks = result.acks.len(),
+            errors = result.errors.len(),
+            "route broadcast"
+        );
+        if ctx.in_flight.max() == 1 {
+            return self.confirm(ctx, opportunity, result, tx.signatures.len(), route.tip);
+        }
+        self.pending.borrow_mut().push_back(Pending {
+            opportunity: opportunity.clone(),
+            result,
+            signatures: tx.signatures.len(),
+            tip: route.tip,
+            write_locks: route.write_locks,
+            _permit: permit,
+        });
+        ExecutionResult::Queued
+    }
+
+    fn end_round(&self, ctx: &Dispatcher) {
+        while self.confirm_oldest(ctx) {}
+    }
+}
+
+/// Collects the routes of a round and sends the best disjoint ones as one
+/// Jito bundle at its end
+pub struct JitoExecutor {
+    pub bundler: Bundler,
+}
+
+impl Executor for JitoExecutor {
+    fn kind(&self) -> ExecutorKind {
+        ExecutorKind::Jito
+    }
+
+    fn can_split(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, ctx: &Dispatcher, opportunity: &ArbOpportunity, route: BuiltRoute) -> ExecutionResult {
+        self.bundler.push(Candidate {
+            compute_units: ctx.compute.limit(&route.shape).unwrap_or(MAX_COMPUTE_UNITS) as u64,
+            route: opportunity.route.clone(),
+            shape: route.shape,
+            profit: opportunity.expected_profit,
+            tip: route.tip,
+            txs: route.txs,
+            write_locks: route.write_locks,
+            pool_ids: opportunity.pool_ids(),
+        });
+        ExecutionResult::Queued
+    }
+
+    fn end_round(&self, ctx: &Dispatcher) {
+        ctx.send_bundle(&self.bundler);
+    }
+}
+
+/// Signs and simulates every route, nothing is sent
+pub struct SimulateExecutor;
+
+impl Executor for SimulateExecutor {
+    fn kind(&self) -> ExecutorKind {
+        ExecutorKind::Simulate
+    }
+
+    fn execute(&self, ctx: &Dispatcher, opportunity: &ArbOpportunity, route: BuiltRoute) -> ExecutionResult {
+        let (tx, _) = match ctx.sign_route(&route, ctx.commitment.simulations()) {
+            Ok(signed) => signed,
+            Err(outcome) => return outcome,
+        };
+        ctx.stats.record_executed();
+        let res = match ctx
+            .latency
+            .time(Stage::Send, || ctx.rpc.simulate(&tx, ctx.context_slot()))
+        {
+            Ok(res) => res,
+            Err(e) => return ExecutionResult::Unsent(format!("simulation: {}", e)),
+        };
+        debug!(
+            slot = res.context.slot,
+            err = ?res.value.err,
+            logs = ?res.value.logs,
+            "route simulated"
+        );
+        match &res.value.err {
+            Some(err) => {
+                ctx.stats.record_error(&format!("{:?}", err));
+                let logs = res.value.logs.clone().unwrap_or_default();
+                let failure = decode_failure(&ctx.program.id(), Some(err), &logs);
+                ctx.stats.record_failure_reason(failure.reason.as_str());
+                ExecutionResult::Failed(format!("simulation: {}", failure))
+            }
+            None => {
+                ctx.stats.record_landed(&opportunity.route, opportunity.expected_profit, 0, 0);
+                ExecutionResult::Landed
+            }
+        }
+    }
+}
+
+/// Counts every route as landed at its quote without signing or sending it
+pub struct PaperExecutor;
+
+impl Executor for PaperExecutor {
+    fn kind(&self) -> ExecutorKind {
+        ExecutorKind::Paper
+    }
+
+    fn execute(&self, ctx: &Dispatcher, opportunity: &ArbOpportunity, route: BuiltRoute) -> ExecutionResult {
+        info!("paper trade of {} at its quote", ctx.symbols.format_route(opportunity));
+        ctx.stats.record_executed();
+        let fee = LAMPORTS_PER_SIGNATURE * route.txs.len() as u64;
+        ctx.stats
+            .record_landed(&opportunity.route, opportunity.expected_profit, fee, route.tip);
+        ExecutionResult::Landed
+    }
+}
+
+pub struct Dispatcher {
+    pub cluster: Cluster,
+    // vv -- need to clone these explicitly -- vv
+    pub owner: Rc<dyn Signer>, // keypair, remote signer or ledger
+    pub program: Program,
+    pub connection: RpcClient,
+    /// simulations, blockhashes and re-quote reads of routes (a `MockRpc` in tests)
+    pub rpc: Box<dyn RpcApi>,
+    pub sender: BroadcastSender,
+    /// hot-reloadable parameters, swapped in by the main loop on config reload
+    pub params: RuntimeParams,
+    pub stats: Arc<SessionStats>, // shared with the control api
+    /// borrow the swap input instead of using the wallet balance
+    pub flash_loan: Option<FlashLoan>,
+    /// latest slot seen, for oracle freshness and landing latency
+    pub current_slot: u64,
+    /// rejects routes whose pool prices are far off the Pyth prices
+    pub oracle: Option<Oracle>,
+    /// compares hop quotes with the Jupiter quote api
+    pub jupiter_check: Option<JupiterCheck>,
+    /// swaps of the Jupiter pools (shared with the main loop refreshing their quotes)
+    pub jupiter: Option<Arc<JupiterApi>>,
+    /// quotes routes again on fresh pool accounts right before they're built
+    pub requote: Option<RequoteConfig>,
+    /// adaptive tips, `params.tip_lamports` is tipped if unset
+    pub tips: Option<TipController>,
+    /// simulated compute units per route shape
+    pub compute: ComputeEstimator,
+    /// follows every broadcast signature in the background (not on localnet)
+    pub landing: Option<LandingTracker>,
+    /// compute unit price from recent fees, none is paid if unset
+    pub priority_fees: Option<PriorityFeeEstimator>,
+    /// no new routes are sent once a shutdown was requested
+    pub shutdown: Shutdown,
+    /// learns from the outcome of every send, paper trades aside (shared
+    /// with the `Arbitrager`)
+    pub sizer: Option<Arc<PositionSizer>>,
+    /// measures the legs of landed routes (shared with the `Arbitrager`)
+    pub slippage: Option<Arc<SlippageTracker>>,
+    /// value sent through every token (shared with the `Arbitrager`)
+    pub exposure: Option<Arc<ExposureTracker>>,
+    /// routes that keep failing are skipped for a while (shared with the control api)
+    pub cooldowns: Arc<RouteCooldowns>,
+    /// pools whose routes keep reverting are left out of the search (shared
+    /// with the `Arbitrager` and the control api)
+    pub quarantine: Arc<PoolQuarantine>,
+    /// time spent per pipeline stage (shared with the control api)
+    pub latency: Arc<LatencyStats>,
+    /// where the routes that pass the checks go
+    pub backend: Box<dyn Executor>,
+    /// accounts of the configured address lookup tables, for sizing routes
+    pub lookup_tables: LookupTables,
+    /// commitment of the blockhash of simulated and sent transactions
+    pub commitment: CommitmentLevels,
+    /// latest blockhash at the sends' commitment, fetched in the background
+    pub blockhashes: Option<BlockhashCache>,
+    /// the owner's swaps of every pool, built when the pools were loaded
+    pub templates: SwapTemplates,
+    /// times a transient error of a `connection` or `rpc` call is retried
+    pub rpc_retries: u32,
+    /// transactions broadcast and not confirmed yet (`max_in_flight`)
+    pub in_flight: Arc<InFlightLimit>,
+    /// settles the markets of landed routes at the end of every round
+    pub settler: Option<Settler>,
+    /// mint symbols, for logging routes
+    pub symbols: TokenSymbols,
+    /// rows for the analytics export (shared with the main loop)
+    pub analytics: Option<Arc<EventExporter>>,
+}
+
+impl Dispatcher {
+    /// Executes the opportunities of a search round, highest net profit
+    /// first, then lets the backend finish the round
+    pub fn execute_all(&self, opportunities: Vec<ArbOpportunity>, dedup: &mut DedupCache) {
+        let mut queue = OpportunityQueue::default();
+        for opportunity in opportunities {
+            queue.push(self.net_profit(&opportunity), opportunity);
+        }
+        while let Some(opportunity) = queue.pop() {
+            if self.shutdown.is_requested() {
+                break;
+            }
+            self.execute(&opportunity, dedup);
+        }
+        self.backend.end_round(self);
+        if let Some(settler) = &self.settler {
+            self.settle(settler);
+        }
+    }
+
+    /// Sends the settle transactions of the markets traded this round; a
+    /// failed settle is only logged, the funds wait for the next one
+    fn settle(&self, settler: &Settler) {
+        for ixs in settler.take(&self.owner.pubkey()) {
+            self.send_maintenance(&ixs, &[], "settle");
+        }
+    }
+
+    /// Slot route transactions are sent and simulated at the earliest: the
+    /// slot their pools were read at, if `commitment.min_context_slot` is set
+    pub fn context_slot(&self) -> Option<u64> {
+        Some(self.current_slot).filter(|slot| self.commitment.min_context_slot && *slot > 0)
+    }
+
+    /// Whether routes go to the chain, as opposed to simulations and paper
+    /// trades
+    pub fn sends(&self) -> bool {
+        matches!(self.backend.kind(), ExecutorKind::Rpc | ExecutorKind::Jito)
+    }
+
+    /// Signs, sends and confirms a transaction of the bot's own upkeep
+    /// (settling, sweeping), not a route; `signers` sign along with the
+    /// owner, `what` names it in logs and stats
+    pub fn send_maintenance(&self, ixs: &[Instruction], signers: &[&dyn Signer], what: &str) -> bool {
+        let blockhash = match self.blockhash(self.commitment.sends()) {
+            Ok(blockhash) => blockhash,
+            Err(e) => {
+                warn!("could not {}: blockhash: {}", what, e);
+                self.stats.record_error(&format!("blockhash: {}", e));
+                return false;
+            }
+        };
+        let mut tx = Transaction::new_with_payer(ixs, Some(&self.owner.pubkey()));
+        let mut all: Vec<&dyn Signer> = vec![self.owner.as_ref()];
+        all.extend_from_slice(signers);
+        if let Err(e) = tx.try_sign(&all, blockhash) {
+            warn!("failed to sign {} transaction: {}", what, e);
+            self.stats.record_error(&format!("signer: {}", e));
+            return false;
+        }
+        let mut result = self.sender.broadcast(&tx, None);
+        let landed = self.sender.confirm(&mut result, Duration::from_secs(30));
+        info!("{} {:?} landed: {}", what, result.signature, landed);
+        if !landed {
+            self.stats.record_error(&format!("{} not landed", what));
+        }
+        landed
+    }
+
+    /// Simulates a transaction of `ixs` signed by the owner, `None` (logged)
+    /// if the node couldn't be asked
+    pub fn simulate_maintenance(
+        &self,
+        ixs: &[Instruction],
+        what: &str,
+    ) -> Option<RpcSimulateTransactionResult> {
+        let blockhash = match self.blockhash(self.commitment.simulations()) {
+            Ok(blockhash) => blockhash,
+            Err(e) => {
+                warn!("could not simulate {}: blockhash: {}", what, e);
+                return None;
+            }
+        };
+        let tx = self.sign(ixs, blockhash)?;
+        match self.rpc.simulate(&tx, None) {
+            Ok(response) => Some(response.value),
+            Err(e) => {
+                warn!("could not simulate {}: {}", what, e);
+                None
+            }
+        }
+    }
+
+    /// Expected profit less the tip and signature fee, for SOL routes; other
+    /// routes pay in a different token and rank by their profit
+    fn net_profit(&self, opportunity: &ArbOpportunity) -> i128 {
+        let profit = opportunity.expected_profit;
+        if opportunity.mints[0] != *WSOL_MINT {
+            return profit;
+        }
+        let tip = self.tip_for(*WSOL_MINT, profit).unwrap_or(0);
+        profit - tip as i128 - LAMPORTS_PER_SIGNATURE as i128
+    }
+
+    /// Checks an opportunity against the policy and hands it to the backend
+    fn execute(&self, opportunity: &ArbOpportunity, dedup: &mut DedupCache) {
+        if self.cooldowns.is_cooling(&opportunity.route) {
+            return;
+        }
+        // confirming the routes before it can take long enough for the
+        // pools to have moved on
+        if let Some(age) = opportunity.expired(&self.params, self.current_slot) {
+            debug!("{} expired: {}", opportunity.route, age);
+            self.stats.record_error("expired");
+            return;
+        }
+        let requoted;
+        let opportunity = match &self.requote {
+            Some(config) => match self.requoted(config, opportunity) {
+                Some(fresh) => {
+                    requoted = fresh;
+                    &requoted
+                }
+                None => return,
+            },
+            None => opportunity,
+        };
+        let route = &opportunity.route;
+        let init_balance = opportunity.amount_in;
+        let new_balance = opportunity.min_amount_out;
+        let execution = info_span!(
+            "execute",
+            route = %route,
+            amount_in = %init_balance,
+            amount_out = %new_balance,
+            signature = field::Empty,
+            latency_ms = field::Empty,
+            outcome = field::Empty,
+        );
+        let _execution = execution.enter();
+        info!(
+            "found arbitrage: {} ({:?} -> {:?})",
+            self.symbols.format_route(opportunity),
+            init_balance,
+            new_balance
+        );
+        self.stats.record_found(route, init_balance, new_balance);
+        if let Some(oracle) = &self.oracle {
+            if let Err(reason) = oracle.check_route(&self.connection, &opportunity.hops(), self.current_slot) {
+                info!("oracle rejected {}: {}", route, reason);
+                self.stats.record_error(&format!("oracle: {}", reason));
+                return;
+            }
+        }
+        if let Some(jupiter_check) = &self.jupiter_check {
+            if let Some(divergence) = jupiter_check.check_route(&opportunity.hops()) {
+                if jupiter_check.block {
+                    info!("jupiter check rejected {}: {}", route, divergence);
+                    self.stats.record_error(&format!("jupiter check: {}", divergence));
+                    return;
+                }
+                warn!("jupiter check flagged {}: {}", route, divergence);
+            }
+        }
+        let profit = opportunity.expected_profit;
+        let start_mint = opportunity.mints[0];
+        let tip = match self.tip_for(start_mint, profit) {
+            Some(tip) => tip,
+            None => {
+                info!("{} can't afford its tip, skipping", route);
+                return;
+            }
+        };
+        // routes sent earlier in the round may have used up a token's window
+        let capacity = self.exposure.as_ref().and_then(|e| e.route_capacity(&opportunity.mints));
+        if capacity.map_or(false, |capacity| capacity < init_balance) {
+            info!("{} is over a token's exposure limit, skipping", route);
+            self.stats.record_error("exposure limit");
+            return;
+        }
+        if !dedup.check_and_insert(route, init_balance) {
+            info!("arb already sent recently...");
+            return; // dont re-send an already sent arb -- bad for network
+        }
+
+        let kind = self.backend.kind();
+        // the balance change of a landed send is what the route really made
+        // (not with several in flight, they all move the balance)
+        let measure =
+            self.sizer.is_some() && kind == ExecutorKind::Rpc && self.in_flight.max() == 1;
+        let balance_before = if measure { self.start_balance(&start_mint) } else { None };
+
+        let sent_at = Instant::now();
+        // the program reverts the whole transaction if the route makes less
+        // than quoted (after slippage), flash loan fee included
+        let ixs = match self.route_instructions(opportunity) {
+            Ok(ixs) => ixs,
+            Err(e) => {
+                warn!("skipping {}: {}", route, e);
+                self.stats.record_error(&e.to_string());
+                self.cooldowns.record_failure(route, &e.to_string());
+                return;
+            }
+        };
+        // too large for one transaction: split inside a bundle, or give up
+        let can_split = self.backend.can_split() && self.flash_loan.is_none();
+        let txs = match tx_size::fit(
+            ixs,
+            &self.overhead_ixs(tip),
+            &self.owner.pubkey(),
+            &self.lookup_tables,
+            can_split,
+        ) {
+            Ok(txs) => txs,
+            Err(e) => {
+                info!("skipping {}: {}", route, e);
+                self.stats.record_error("transaction too large");
+                self.cooldowns.record_failure(route, &e);
+                return;
+            }
+        };
+        let own = own_accounts(
+            &self.owner.pubkey(),
+            &opportunity.mints,
+            &self.program.id(),
+            self.flash_loan.as_ref(),
+        );
+        // paper trades and simulations leave nothing to settle
+        if let Some(settler) = self.settler.as_ref().filter(|_| self.sends()) {
+            settler.register(&opportunity.swap_pools());
+        }
+        if let Some(exposure) = self.exposure.as_ref().filter(|_| self.sends()) {
+            exposure.record(&opportunity.mints, init_balance);
+        }
+        let built = BuiltRoute {
+            write_locks: write_locks(&txs, &own),
+            txs,
+            tip,
+            shape: opportunity.shape(),
+            build_start: sent_at,
+        };
+        let outcome = self.backend.execute(self, opportunity, built);
+        if outcome == ExecutionResult::Queued {
+            execution.record("outcome", &"queued");
+            return;
+        }
+        execution.record("latency_ms", &(sent_at.elapsed().as_millis() as u64));
+        execution.record("outcome", &field::debug(&outcome));
+        info!("route executed");
+        self.record_outcome(opportunity, &outcome, balance_before);
+    }
+
+    /// Feeds a route's outcome back into cooldowns, quarantine and sizing;
+    /// `balance_before` is the start-mint balance before it was sent, if
+    /// the realized profit should be measured
+    fn record_outcome(
+        &self,
+        opportunity: &ArbOpportunity,
+        outcome: &ExecutionResult,
+        balance_before: Option<u128>,
+    ) {
+        let route = &opportunity.route;
+        let pool_ids = opportunity.pool_ids();
+        match outcome {
+            ExecutionResult::Landed => {
+                self.cooldowns.record_success(route);
+                for pool_id in pool_ids.iter() {
+                    self.quarantine.record_success(pool_id);
+                }
+                if let Some(settler) = &self.settler {
+                    settler.record_landed(&pool_ids);
+                }
+            }
+            ExecutionResult::Failed(error) => {
+                self.cooldowns.record_failure(route, error);
+                // the route made it to the chain and the program failed it
+                if error.starts_with("reverted") || error.starts_with("simulation") {
+                    for pool_id in pool_ids.iter() {
+                        self.quarantine.record_failure(pool_id, PoolFailure::OnChain, error);
+                    }
+                }
+            }
+            ExecutionResult::Unsent(error) => {
+                warn!("{} not sent: {}", route, error);
+                self.stats.record_error(error);
+            }
+            ExecutionResult::Queued => {}
+        }
+        if let Some(analytics) = self.analytics.as_ref().filter(|_| *outcome != ExecutionResult::Queued) {
+            analytics.record_execution(
+                route,
+                &pool_ids,
+                opportunity.expected_profit,
+                self.current_slot,
+                outcome.describe(),
+            );
+        }
+        // paper trades land by definition, there's nothing to learn from them
+        let learn =
+            self.backend.kind() != ExecutorKind::Paper && !matches!(outcome, ExecutionResult::Unsent(_));
+        if let Some(sizer) = self.sizer.as_ref().filter(|_| learn) {
+            let landed = *outcome == ExecutionResult::Landed;
+            let realized = match (landed, balance_before) {
+                (true, Some(before)) => self
+                    .start_balance(&opportunity.mints[0])
+                    .map(|after| after as i128 - before as i128),
+                _ => None,
+            };
+            sizer.record(route, landed, opportunity.expected_profit, realized);
+        }
+    }
+
+    /// Feeds the realized output of every leg of a landed route to the
+    /// slippage tracker, if its transaction can be fetched
+    fn measure_slippage(&self, opportunity: &ArbOpportunity, signature: Option<Signature>) {
+        let (tracker, signature) = match (&self.slippage, signature) {
+            (Some(tracker), Some(signature)) => (tracker, signature),
+            _ => return,
+        };
+        let meta = match self
+            .connection
+            .get_transaction(&signature, UiTransactionEncoding::JsonParsed)
+        {
+            Ok(tx) => tx.transaction.meta,
+            Err(e) => {
+                debug!("could not fetch {} to measure its slippage: {}", signature, e);
+                return;
+            }
+        };
+        // as json, the meta's shape differs between sdk versions
+        let meta = match meta.map(|meta| serde_json::to_value(&meta)) {
+            Some(Ok(meta)) => meta,
+            _ => return,
+        };
+        let accounts: HashSet<String> = opportunity
+            .mints
+            .iter()
+            .map(|mint| self.templates.token_account(mint).to_string())
+            .collect();
+        let credits = slippage::credits(&meta, &accounts);
+        let legs = slippage::measure(opportunity, |mint| self.templates.token_account(mint), &credits);
+        debug!("{}: {} legs measured", opportunity.route, legs.len());
+        tracker.record(&legs);
+    }
+
+    /// Why a landed transaction of the router failed, from its logs if they
+    /// can be fetched
+    fn decode_failure(
+        &self,
+        signature: Option<Signature>,
+        error: Option<&TransactionError>,
+    ) -> DecodedFailure {
+        let logs = signature
+            .and_then(|signature| {
+                self.connection
+                    .get_transaction(&signature, UiTransactionEncoding::Json)
+                    .map_err(|e| debug!("could not fetch the logs of {}: {}", signature, e))
+                    .ok()
+            })
+            .and_then(|tx| tx.transaction.meta)
+            .and_then(|meta| meta.log_messages)
+            .unwrap_or_default();
+        decode_failure(&self.program.id(), error, &logs)
+    }
+
+    /// Sends the routes the bundler picked from this round as one bundle:
+    /// a transaction per route (or per part of a split route), the summed
+    /// tip paid by the last one
+    fn send_bundle(&self, bundler: &Bundler) {
+        let candidates = bundler.take();
+        if candidates.is_empty() {
+            return;
+        }
+        let routes: Vec<&str> = candidates.iter().map(|c| c.route.as_str()).collect();
+        let bundle = info_span!("bundle", routes = %routes.join(" | "), bundle_id = field::Empty);
+        let _bundle = bundle.enter();
+
+        let build_start = Instant::now();
+        let blockhash = match self.blockhash(self.commitment.sends()) {
+            Ok(blockhash) => blockhash,
+            Err(e) => {
+                warn!("failed to fetch a blockhash, dropping the bundle: {}", e);
+                self.stats.record_error(&format!("blockhash: {}", e));
+                return;
+            }
+        };
+        let tip: u64 = candidates.iter().map(|c| c.tip).sum();
+        let last = candidates.len() - 1;
+        let mut txs = vec![];
+        for (i, candidate) in candidates.iter().enumerate() {
+            let last_part = candidate.txs.len() - 1;
+            for (j, part) in candidate.txs.iter().enumerate() {
+                let mut ixs = part.clone();
+                if let Some(limit) = self.compute.limit(&candidate.shape) {
+                    ixs.insert(0, set_compute_unit_limit(limit));
+                }
+                if i == last && j == last_part && tip > 0 {
+                    ixs.push(self.tip_ix(tip));
+                }
+                match self.latency.time(Stage::Sign, || self.sign(&ixs, blockhash)) {
+                    Some(tx) => txs.push(tx),
+                    None => return,
+                }
+            }
+        }
+        self.latency.record(Stage::TxBuild, build_start.elapsed());
+
+        for _ in candidates.iter() {
+            self.stats.record_executed();
+        }
+        let sent_at = Instant::now();
+        let bundle_id = match self.latency.time(Stage::Send, || bundler.send(&txs)) {
+            Ok(id) => id,
+            Err(e) => {
+                warn!("failed to send bundle: {}", e);
+                self.stats.record_error(&format!("bundle: {}", e));
+                return;
+            }
+        };
+        bundle.record("bundle_id", &bundle_id.as_str());
+        info!("sent bundle {} with {} routes", bundle_id, candidates.len());
+
+        // bundles land atomically, the last transaction stands for all of them
+        let mut result = BroadcastResult {
+            signature: txs.last().and_then(|tx| tx.signatures.first().copied()),
+            acks: vec![(bundler.config.url.clone(), sent_at.elapsed())],
+            errors: vec![],
+            landed_by: None,
+            error: None,
+        };
+        let landed = self.latency.time(Stage::Confirm, || {
+            self.sender.confirm(&mut result, Duration::from_secs(30))
+        });
+        info!("bundle {} landed: {}", bundle_id, landed);
+        if let Some(tips) = &self.tips {
+            tips.record_outcome(landed);
+        }
+        for (i, candidate) in candidates.iter().enumerate() {
+            if landed {
+                let fee = LAMPORTS_PER_SIGNATURE * candidate.txs.len() as u64;
+                let tip = if i == last { tip } else { 0 };
+                self.stats.record_landed(&candidate.route, candidate.profit, fee, tip);
+                self.cooldowns.record_success(&candidate.route);
+                for pool_id in candidate.pool_ids.iter() {
+                    self.quarantine.record_success(pool_id);
+                }
+                if let Some(settler) = &self.settler {
+                    settler.record_landed(&candidate.pool_ids);
+                }
+            } else {
+                self.cooldowns.record_failure(&candidate.route, "bundle not landed");
+            }
+            if let Some(analytics) = &self.analytics {
+                let outcome = if landed {
+                    ExecutionResult::Landed
+                } else {
+                    ExecutionResult::Failed("bundle not landed".to_string())
+                };
+                analytics.record_execution(
+                    &candidate.route,
+                    &candidate.pool_ids,
+                    candidate.profit,
+                    self.current_slot,
+                    outcome.describe(),
+                );
+            }
+            if let Some(sizer) = &self.sizer {
+                sizer.record(&candidate.route, landed, candidate.profit, None);
+            }
+        }
+        if !landed {
+            self.stats.record_error("timed out waiting for confirmation");
+        }
+    }
+
+    /// Latest blockhash at `commitment`, transient errors are retried
+    fn blockhash(&self, commitment: CommitmentConfig) -> ClientResult<Hash> {
+        let cached = self
+            .blockhashes
+            .as_ref()
+            .filter(|cache| cache.commitment() == commitment)
+            .and_then(|cache| cache.get());
+        if let Some(cached) = cached {
+            return Ok(cached.blockhash);
+        }
+        with_retries(self.rpc_retries, Duration::from_millis(200), || {
+            self.rpc.latest_blockhash(commitment)
+        })
+        .map(|(blockhash, _)| blockhash)
+    }
+
+    fn tip_ix(&self, tip: u64) -> Instruction {
+        match &self.tips {
+            Some(tips) => tips.tip_ix(&self.owner.pubkey(), tip),
+            None => system_instruction::transfer(&self.owner.pubkey(), &JITO_TIP_ACCOUNTS[0], tip),
+        }
+    }
+
+    /// Instructions `sign_route` and `send_bundle` add to a route's
+    /// transaction, with placeholder values of the same size, to measure it
+    /// before they are known
+    fn overhead_ixs(&self, tip: u64) -> Vec<Instruction> {
+        let mut ixs = vec![set_compute_unit_limit(MAX_COMPUTE_UNITS)];
+        if self.priority_fees.is_some() {
+            ixs.push(set_compute_unit_price(0));
+        }
+        if tip > 0 {
+            ixs.push(system_instruction::transfer(
+                &self.owner.pubkey(),
+                &JITO_TIP_ACCOUNTS[0],
+                tip,
+            ));
+        }
+        ixs
+    }
+
+    /// The owner's token balance of `mint` (scaled), `None` if it can't be read
+    pub fn start_balance(&self, mint: &Pubkey) -> Option<u128> {
+        let ata = derive_token_address(&self.owner.pubkey(), mint);
+        let balance = self.connection.get_token_account_balance(&ata).ok()?;
+        balance.amount.parse().ok()
+    }
+
+    /// The opportunity quoted on its pools' current accounts, `None` if its
+    /// profit dropped too far or it couldn't be re-quoted
+    fn requoted(&self, config: &RequoteConfig, opportunity: &ArbOpportunity) -> Option<ArbOpportunity> {
+        let route = &opportunity.route;
+        let fresh = match fresh_pools(&*self.rpc, &self.cluster, &opportunity.pools) {
+            Ok(fresh) => fresh,
+            Err(e) => {
+                warn!("could not re-quote {}: {}", route, e);
+                self.stats.record_error(&format!("requote: {}", e));
+                return None;
+            }
+        };
+        let slippage = self.slippage.as_deref();
+        match requote(config, opportunity, &fresh, &self.params, slippage, self.flash_loan.as_ref()) {
+            Requote::Send(fresh) => {
+                if fresh.amount_in != opportunity.amount_in {
+                    info!(
+                        "{} resized from {} to {} on re-quote, profit {} -> {}",
+                        route,
+                        opportunity.amount_in,
+                        fresh.amount_in,
+                        opportunity.expected_profit,
+                        fresh.expected_profit
+                    );
+                }
+                Some(fresh)
+            }
+            Requote::Abort(profit) => {
+                info!(
+                    "{} profit dropped from {} to {} on re-quote, skipping",
+                    route, opportunity.expected_profit, profit
+                );
+                self.stats.record_error("requote: profit dropped");
+                None
+            }
+        }
+    }
+
+    /// Tip for a route with the given profit (scaled `start_mint`), `None`
+    /// if it can't afford one. Profits only convert to lamports for SOL
+    /// routes, other routes get the static `tip_lamports`.
+    fn tip_for(&self, start_mint: Pubkey, profit: i128) -> Option<u64> {
+        match &self.tips {
+            Some(tips) if start_mint == *WSOL_MINT => tips.tip_for(profit.max(0) as u64),
+            _ => Some(self.params.tip_lamports),
+        }
+    }
+
+    /// The route's chain of swaps, or both chains of a route with a divided
+    /// leg (see `SplitLeg`), each guarded by its own share of the profit: the
+    /// second chain's was quoted on the pools the first one moves
+    fn route_instructions(&self, opportunity: &ArbOpportunity) -> ArbResult<Vec<Instruction>> {
+        let (amount_in, min_amount_out) = (opportunity.amount_in, opportunity.min_amount_out);
+        let split = match &opportunity.split {
+            Some(split) => split,
+            None => {
+                let min_profit = (min_amount_out - amount_in) as u64;
+                return self.get_arbitrage_instructions(
+                    amount_in,
+                    min_profit,
+                    &opportunity.mints,
+                    &opportunity.pools,
+                    &opportunity.min_hop_inputs(),
+                );
+            }
+        };
+        let amount_in_a = amount_in - split.amount_in;
+        let min_profit_a = (min_amount_out - split.min_amount_out - amount_in_a) as u64;
+        let mints = &opportunity.mints;
+        // split routes have no jupiter hops, see `Arbitrager::split_route`
+        let pools_a = &opportunity.pools;
+        let mut ixs = self.get_arbitrage_instructions(amount_in_a, min_profit_a, mints, pools_a, &[])?;
+        let mut pools_b = opportunity.pools.clone();
+        pools_b[split.leg] = split.pool.clone();
+        let min_profit_b = (split.min_amount_out - split.amount_in) as u64;
+        ixs.extend(self.get_arbitrage_instructions(split.amount_in, min_profit_b, mints, &pools_b, &[])?);
+        Ok(ixs)
+    }
+
+    /// Swaps of the route between the program's start-balance snapshot and
+    /// its profit guard, which requires `min_profit` (start mint, scaled);
+    /// Jupiter hops swap their entry of `hop_inputs`
+    fn get_arbitrage_instructions(
+        &self,
+        swap_start_amount: u128,
+        min_profit: u64,
+        mints: &[Pubkey],
+        pools: &[PoolQuote],
+        hop_inputs: &[u128],
+    ) -> ArbResult<Vec<Instruction>> {
+        // everything but the amounts comes from the templates
+        let src_mint = mints[0];
+        let src_ata = self.templates.token_account(&src_mint);
+        let mut ixs = vec![self.templates.start_swap_ix(&src_mint, swap_start_amount as u64)];
+        for i in 0..mints.len() - 1 {
+            let [mint0, mint1] = [mints[i], mints[i + 1]];
+            if is_jupiter(&pools[i]) {
+                ixs.extend(self.jupiter_swap_ixs(&mint0, &mint1, hop_inputs.get(i).copied())?);
+            } else {
+                ixs.extend(self.templates.swap_ix(&self.program, &pools[i], &mint0, &mint1)?);
+            }
+        }
+        ixs.push(self.templates.profit_or_revert_ix(&src_mint, min_profit));
+        Ok(match &self.flash_loan {
+            Some(flash_loan) => flash_loan.wrap(
+                swap_start_amount as u64,
+                ixs,
+                &src_ata,
+                &self.owner.pubkey(),
+            ),
+            None => ixs,
+        })
+    }
+
+    /// Jupiter's swap of `amount` of the owner's `mint_in` into `mint_out`
+    fn jupiter_swap_ixs(
+        &self,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+        amount: Option<u128>,
+    ) -> ArbResult<Vec<Instruction>> {
+        let jupiter = self
+            .jupiter
+            .as_ref()
+            .ok_or(ArbError::Unsupported("jupiter pools without `jupiter_pools` set"))?;
+        let amount = amount.ok_or(ArbError::Unsupported("jupiter hops of split routes"))?;
+        jupiter.swap_ixs(&self.owner.pubkey(), mint_in, mint_out, amount as u64)
+    }
+
+    /// The router's swap of `amount` of the owner's `mint_in` into `mint_out`
+    /// through `pool`, outside of a route: there's no profit guard, the
+    /// input and output mints differ
+    pub fn swap_ixs(
+        &self,
+        amount: u64,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+        pool: &PoolQuote,
+    ) -> ArbResult<Vec<Instruction>> {
+        let mut ixs = vec![self.templates.start_swap_ix(mint_in, amount)];
+        ixs.extend(self.templates.swap_ix(&self.program, pool, mint_in, mint_out)?);
+        Ok(ixs)
+    }
+
+    /// Builds and signs a transaction paid by the owner
+    fn sign(&self, ixs: &[Instruction], blockhash: Hash) -> Option<Transaction> {
+        let mut tx = Transaction::new_with_payer(ixs, Some(&self.owner.pubkey()));
+        // remote / hardware signers can fail, which shouldn't take the bot down
+        if let Err(e) = tx.try_sign(&[self.owner.as_ref()], blockhash) {
+            warn!("failed to sign transaction: {}", e);
+            self.stats.record_error(&format!("signer: {}", e));
+            return None;
+        }
+        Some(tx)
+    }
+
+    /// Signs a route sent on its own with its tip, priority fee and compute
+    /// limit, with a blockhash at `commitment`; returns the transaction and
+    /// its compute unit price
+    fn sign_route(
+        &self,
+        route: &BuiltRoute,
+        commitment: CommitmentConfig,
+    ) -> Result<(Transaction, u64), ExecutionResult> {
+        // not split without a bundle
+        let mut ixs = route.txs.concat();
+        if route.tip > 0 {
+            ixs.push(self.tip_ix(route.tip));
+        }
+        let mut priority_fee = 0;
+        if let Some(priority_fees) = &self.priority_fees {
+            let mut writable: Vec<Pubkey> = ixs
+                .iter()
+                .flat_map(|ix| ix.accounts.iter().filter(|a| a.is_writable).map(|a| a.pubkey))
+                .collect();
+            writable.sort();
+            writable.dedup();
+            if let Some(price) = priority_fees.estimate(&writable).filter(|p| *p > 0) {
+                priority_fee = price;
+                ixs.insert(0, set_compute_unit_price(price));
+            }
+        }
+        let blockhash = self
+            .blockhash(commitment)
+            .map_err(|e| ExecutionResult::Unsent(format!("blockhash: {}", e)))?;
+        let mut build_time = route.build_start.elapsed();
+
+        let signing_failed = || ExecutionResult::Failed("signing failed".to_string());
+        let sign_start = Instant::now();
+        let mut tx = match self.sign(&ixs, blockhash) {
+            Some(tx) => tx,
+            None => return Err(signing_failed()),
+        };
+        let mut sign_time = sign_start.elapsed();
+        // request only the units the route needs instead of the default
+        let limit_start = Instant::now();
+        let limit_ix = self
+            .compute
+            .limit_ix(&*self.rpc, &route.shape, &tx, self.context_slot());
+        if let Some(limit_ix) = limit_ix {
+            ixs.insert(0, limit_ix);
+            build_time += limit_start.elapsed();
+            let sign_start = Instant::now();
+            tx = match self.sign(&ixs, blockhash) {
+                Some(tx) => tx,
+                None => return Err(signing_failed()),
+            };
+            sign_time += sign_start.elapsed();
+        } else {
+            build_time += limit_start.elapsed();
+        }
+        self.latency.record(Stage::TxBuild, build_time);
+        self.latency.record(Stage::Sign, sign_time);
+        Ok((tx, priority_fee))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn default_backend_follows_the_cluster() {
+        assert_eq!(ExecutorKind::default_for(&Cluster::Localnet, true), ExecutorKind::Simulate);
+        assert_eq!(ExecutorKind::default_for(&Cluster::Mainnet, true), ExecutorKind::Jito);
+        assert_eq!(ExecutorKind::default_for(&Cluster::Mainnet, false), ExecutorKind::Rpc);
+        assert_eq!(ExecutorKind::default_for(&Cluster::Devnet, true), ExecutorKind::Rpc);
+
+        let kind: ExecutorKind = serde_json::from_str("\"paper\"").unwrap();
+        assert_eq!(kind, ExecutorKind::Paper);
+        assert_eq!(kind.backend(None).kind(), ExecutorKind::Paper);
+        // jito without bundle settings falls back to plain sends
+        assert_eq!(ExecutorKind::Jito.backend(None).kind(), ExecutorKind::Rpc);
+    }
+
+    #[test]
+    fn old_routes_expire() {
+        let config: Config = serde_json::from_str(r#"{ "fee_bps": 10 }"#).unwrap();
+        let mut params = RuntimeParams::from_config(&config);
+        let mut opportunity = ArbOpportunity {
+            route: "a->b->a".to_string(),
+            mints: vec![],
+            pools: vec![],
+            amount_in: 1_000,
+            amounts: vec![1_000],
+            min_amount_out: 1_100,
+            expected_profit: 100,
+            found_slot: 100,
+            found_at: Instant::now() - Duration::from_secs(1),
+            split: None,
+        };
+        // no limits by default
+        assert_eq!(opportunity.expired(&params, 105), None);
+
+        params.max_opportunity_age_slots = 2;
+        assert_eq!(opportunity.expired(&params, 102), None);
+        assert_eq!(opportunity.expired(&params, 103), Some("found 3 slots ago".to_string()));
+
+        params.max_opportunity_age_ms = 800;
+        assert!(opportunity.expired(&params, 100).unwrap().ends_with("ms ago"));
+        opportunity.found_at = Instant::now();
+        assert_eq!(opportunity.expired(&params, 100), None);
+    }
+}
@@ -0,0 +1,248 @@
+//! Pool graph export
+//!
+//! `export-graph` writes the loaded graph for tools outside the bot: a node
+//! per mint (with its symbol, see `symbols.rs`) and an edge per tradeable
+//! pool with its DEX, fee, reserves and USD liquidity (valued like `prune`
+//! does, through pools against USDC or USDT). `json` is this module's
+//! `GraphExport`, `dot` loads into Graphviz and `graphml` into Gephi,
+//! networkx or yEd.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::constants::{USDC_MINT, USDT_MINT};
+use crate::graph::{mint_pairs, PoolSet};
+use crate::pool::PoolOperations;
+use crate::prune::{liquidity_usd, usd_prices};
+use crate::symbols::TokenSymbols;
+
+/// Format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    Json,
+    Dot,
+    Graphml,
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(GraphFormat::Json),
+            "dot" => Ok(GraphFormat::Dot),
+            "graphml" => Ok(GraphFormat::Graphml),
+            other => Err(format!("unknown graph format {}, expected json, dot or graphml", other)),
+        }
+    }
+}
+
+/// A mint of the graph
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ExportNode {
+    pub mint: String,
+    pub symbol: String,
+    /// Pools trading the mint
+    pub pools: usize,
+}
+
+/// A pool between two mints, one per pair of mints for pools of more
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ExportEdge {
+    /// `<dex>:<address prefix>`, as in logs
+    pub pool: String,
+    pub address: String,
+    pub dex: String,
+    pub source: String,
+    pub target: String,
+    pub fee_bps: Option<f64>,
+    /// Reserves of `source` and `target` (scaled), for pools that have them
+    pub reserves: Option<[u128; 2]>,
+    pub liquidity_usd: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GraphExport {
+    pub nodes: Vec<ExportNode>,
+    pub edges: Vec<ExportEdge>,
+}
+
+impl GraphExport {
+    /// The mints with a tradeable pool, and those pools
+    pub fn from_pool_set(pool_set: &PoolSet, symbols: &TokenSymbols) -> Self {
+        let usd: HashSet<Pubkey> = [*USDC_MINT, *USDT_MINT].into_iter().collect();
+        let prices = usd_prices(&pool_set.pools, &usd);
+        let mut counts = vec![0; pool_set.token_mints.len()];
+        let mut edges = vec![];
+        for i in pool_set.tradeable_pools() {
+            let pool = &pool_set.pools[i];
+            let idxs = &pool_set.pool_mints[i];
+            for idx in idxs {
+                counts[*idx] += 1;
+            }
+            for (idx0, idx1) in mint_pairs(idxs).filter(|(idx0, idx1)| idx0 < idx1) {
+                let (source, target) = (pool_set.token_mints[idx0], pool_set.token_mints[idx1]);
+                let reserves = pool.get_reserves().and_then(|reserves| {
+                    let of = |mint: &Pubkey| {
+                        reserves.iter().find(|(m, _)| m == mint).map(|(_, amount)| *amount)
+                    };
+                    Some([of(&source)?, of(&target)?])
+                });
+                edges.push(ExportEdge {
+                    pool: pool.get_id(),
+                    address: pool.get_address().to_string(),
+                    dex: pool.get_name(),
+                    source: source.to_string(),
+                    target: target.to_string(),
+                    fee_bps: pool.get_fee_bps(),
+                    reserves,
+                    liquidity_usd: liquidity_usd(pool, &prices),
+                });
+            }
+        }
+        let nodes = pool_set
+            .token_mints
+            .iter()
+            .zip(counts)
+            .filter(|(_, pools)| *pools > 0)
+            .map(|(mint, pools)| ExportNode {
+                mint: mint.to_string(),
+                symbol: symbols.symbol(mint),
+                pools,
+            })
+            .collect();
+        Self { nodes, edges }
+    }
+
+    pub fn render(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Json => serde_json::to_string_pretty(self).unwrap(),
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::Graphml => self.to_graphml(),
+        }
+    }
+
+    /// An undirected Graphviz graph, nodes labelled by symbol and edges by pool
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("graph pools {\n");
+        for node in self.nodes.iter() {
+            writeln!(dot, "  \"{}\" [label=\"{}\", pools={}];", node.mint, dot_escape(&node.symbol), node.pools)
+                .unwrap();
+        }
+        for edge in self.edges.iter() {
+            let mut attrs = format!("label=\"{}\", dex=\"{}\"", edge.pool, edge.dex);
+            if let Some(fee_bps) = edge.fee_bps {
+                write!(attrs, ", fee_bps={}", fee_bps).unwrap();
+            }
+            if let Some(liquidity) = edge.liquidity_usd {
+                write!(attrs, ", liquidity_usd={:.2}", liquidity).unwrap();
+            }
+            writeln!(dot, "  \"{}\" -- \"{}\" [{}];", edge.source, edge.target, attrs).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// GraphML with the node and edge fields as typed keys
+    pub fn to_graphml(&self) -> String {
+        let mut xml = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <key id=\"symbol\" for=\"node\" attr.name=\"symbol\" attr.type=\"string\"/>\n",
+            "  <key id=\"pools\" for=\"node\" attr.name=\"pools\" attr.type=\"int\"/>\n",
+            "  <key id=\"pool\" for=\"edge\" attr.name=\"pool\" attr.type=\"string\"/>\n",
+            "  <key id=\"address\" for=\"edge\" attr.name=\"address\" attr.type=\"string\"/>\n",
+            "  <key id=\"dex\" for=\"edge\" attr.name=\"dex\" attr.type=\"string\"/>\n",
+            "  <key id=\"fee_bps\" for=\"edge\" attr.name=\"fee_bps\" attr.type=\"double\"/>\n",
+            "  <key id=\"liquidity_usd\" for=\"edge\" attr.name=\"liquidity_usd\" attr.type=\"double\"/>\n",
+            "  <graph id=\"pools\" edgedefault=\"undirected\">\n",
+        ));
+        for node in self.nodes.iter() {
+            writeln!(
+                xml,
+                "    <node id=\"{}\"><data key=\"symbol\">{}</data><data key=\"pools\">{}</data></node>",
+                node.mint,
+                xml_escape(&node.symbol),
+                node.pools
+            )
+            .unwrap();
+        }
+        for edge in self.edges.iter() {
+            write!(
+                xml,
+                "    <edge source=\"{}\" target=\"{}\"><data key=\"pool\">{}</data>\
+                 <data key=\"address\">{}</data><data key=\"dex\">{}</data>",
+                edge.source,
+                edge.target,
+                xml_escape(&edge.pool),
+                edge.address,
+                xml_escape(&edge.dex)
+            )
+            .unwrap();
+            if let Some(fee_bps) = edge.fee_bps {
+                write!(xml, "<data key=\"fee_bps\">{}</data>", fee_bps).unwrap();
+            }
+            if let Some(liquidity) = edge.liquidity_usd {
+                write!(xml, "<data key=\"liquidity_usd\">{:.2}</data>", liquidity).unwrap();
+            }
+            xml.push_str("</edge>\n");
+        }
+        xml.push_str("  </graph>\n</graphml>\n");
+        xml
+    }
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pool::PoolType;
+    use crate::tests::pools::orca_pool;
+
+    #[test]
+    fn every_format_has_the_mints_and_tradeable_pools() {
+        let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut pool_set = PoolSet::new();
+        for (x, y) in [(&a, &b), (&b, &c), (&c, &a)] {
+            pool_set.add_pool(PoolType::OrcaPoolType, orca_pool(x, y, 1_000_000, 2_000_000).into());
+        }
+        pool_set.tradeable[2] = false;
+        let mut symbols = TokenSymbols::default();
+        symbols.insert(a, "A&B".to_string());
+
+        let export = GraphExport::from_pool_set(&pool_set, &symbols);
+        assert_eq!(export.nodes.len(), 3);
+        assert_eq!(export.nodes[0].pools, 1);
+        assert_eq!(export.edges.len(), 2);
+        let edge = &export.edges[0];
+        assert_eq!(edge.dex, "Orca");
+        assert_eq!(edge.reserves, Some([1_000_000, 2_000_000]));
+        // 25 bps trader fee and 5 bps owner fee
+        assert_eq!(edge.fee_bps, Some(30.0));
+
+        let json = export.render(GraphFormat::Json);
+        assert_eq!(serde_json::from_str::<GraphExport>(&json).unwrap(), export);
+        let dot = export.render(GraphFormat::Dot);
+        assert_eq!(dot.matches(" -- ").count(), 2);
+        assert!(dot.contains("label=\"A&B\""));
+        let graphml = export.render(GraphFormat::Graphml);
+        assert_eq!(graphml.matches("<edge ").count(), 2);
+        assert!(graphml.contains("A&amp;B"));
+        assert_eq!("graphml".parse::<GraphFormat>(), Ok(GraphFormat::Graphml));
+        assert!("csv".parse::<GraphFormat>().is_err());
+    }
+}
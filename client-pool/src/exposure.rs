@@ -0,0 +1,201 @@
+//! Per-token exposure limits
+//!
+//! A route swaps its whole input into every mint along the way, so a thin
+//! or manipulated token in the middle of a route holds the full trade size
+//! until the route closes. Value routed through a mint other than the start
+//! mint is capped per transaction and over a rolling window of
+//! `window_secs`, both measured as the route's input (scaled start mint).
+//! Routes are traded smaller to fit what a mint has left, and not at all
+//! once its window is used up.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use serde::{Deserialize, Serialize};
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Caps on the value routed through one mint (scaled start mint)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ExposureLimit {
+    /// Most a single route may put through the mint
+    #[serde(default)]
+    pub max_per_tx: Option<u64>,
+    /// Most the routes sent within the window may put through the mint
+    #[serde(default)]
+    pub max_per_window: Option<u64>,
+}
+
+/// Exposure limit settings
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ExposureConfig {
+    /// Length of the rolling window of `max_per_window`
+    #[serde(default = "default_window_secs")]
+    pub window_secs: u64,
+    /// Limit of every mint without its own entry in `mints`, unlimited if unset
+    #[serde(default)]
+    pub default: Option<ExposureLimit>,
+    /// Limits by mint address
+    #[serde(default)]
+    pub mints: HashMap<String, ExposureLimit>,
+}
+
+fn default_window_secs() -> u64 {
+    3600
+}
+
+impl ExposureConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.window_secs == 0 {
+            problems.push("exposure.window_secs must be > 0".to_string());
+        }
+        let limits = self
+            .default
+            .iter()
+            .map(|limit| ("default".to_string(), limit))
+            .chain(self.mints.iter().map(|(mint, limit)| (format!("mints.{}", mint), limit)));
+        for (name, limit) in limits {
+            if limit.max_per_tx == Some(0) || limit.max_per_window == Some(0) {
+                problems.push(format!("exposure.{} limits must be > 0", name));
+            }
+        }
+        for mint in self.mints.keys() {
+            if Pubkey::from_str(mint).is_err() {
+                problems.push(format!("exposure.mints key {} is not a valid pubkey", mint));
+            }
+        }
+        problems
+    }
+}
+
+/// Value routed through every limited mint within the window, see the
+/// module docs
+pub struct ExposureTracker {
+    window: Duration,
+    default: Option<ExposureLimit>,
+    limits: HashMap<Pubkey, ExposureLimit>,
+    /// time and value of every route through a limited mint, oldest first
+    routed: Mutex<HashMap<Pubkey, VecDeque<(Instant, u128)>>>,
+}
+
+impl ExposureTracker {
+    pub fn new(config: &ExposureConfig) -> Self {
+        Self {
+            window: Duration::from_secs(config.window_secs),
+            default: config.default,
+            limits: config
+                .mints
+                .iter()
+                .filter_map(|(mint, limit)| Some((Pubkey::from_str(mint).ok()?, *limit)))
+                .collect(),
+            routed: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn limit(&self, mint: &Pubkey) -> Option<ExposureLimit> {
+        self.limits.get(mint).copied().or(self.default)
+    }
+
+    /// The mints of a route the limits apply to, each once
+    fn limited_mints<'a>(&self, mints: &'a [Pubkey]) -> HashSet<&'a Pubkey> {
+        let start_mint = mints.first();
+        mints
+            .iter()
+            .filter(|mint| Some(*mint) != start_mint && self.limit(mint).is_some())
+            .collect()
+    }
+
+    /// Most a route through `mints` (starting and ending with the start mint)
+    /// may take in now, `None` if no limit applies
+    pub fn route_capacity(&self, mints: &[Pubkey]) -> Option<u128> {
+        self.route_capacity_at(mints, Instant::now())
+    }
+
+    fn route_capacity_at(&self, mints: &[Pubkey], now: Instant) -> Option<u128> {
+        let mut routed = self.routed.lock().unwrap();
+        self.limited_mints(mints)
+            .into_iter()
+            .filter_map(|mint| {
+                let limit = self.limit(mint)?;
+                let left = limit.max_per_window.map(|max| {
+                    let used: u128 = self.prune(&mut routed, mint, now).iter().map(|(_, value)| value).sum();
+                    (max as u128).saturating_sub(used)
+                });
+                left.into_iter().chain(limit.max_per_tx.map(u128::from)).min()
+            })
+            .min()
+    }
+
+    /// Records a route sent through `mints` with `amount_in` (scaled start mint)
+    pub fn record(&self, mints: &[Pubkey], amount_in: u128) {
+        self.record_at(mints, amount_in, Instant::now())
+    }
+
+    fn record_at(&self, mints: &[Pubkey], amount_in: u128, now: Instant) {
+        let mut routed = self.routed.lock().unwrap();
+        for mint in self.limited_mints(mints) {
+            self.prune(&mut routed, mint, now).push_back((now, amount_in));
+        }
+    }
+
+    /// The routes through `mint` within the window of `now`
+    fn prune<'a>(
+        &self,
+        routed: &'a mut HashMap<Pubkey, VecDeque<(Instant, u128)>>,
+        mint: &Pubkey,
+        now: Instant,
+    ) -> &'a mut VecDeque<(Instant, u128)> {
+        let entries = routed.entry(*mint).or_default();
+        while entries.front().map_or(false, |(at, _)| now.duration_since(*at) >= self.window) {
+            entries.pop_front();
+        }
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_fit_the_tightest_limit_of_their_mints() {
+        let (start, thin, deep) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let config: ExposureConfig = serde_json::from_str(&format!(
+            r#"{{ "window_secs": 60, "default": {{ "max_per_tx": 1000 }},
+                 "mints": {{ "{}": {{ "max_per_tx": 300, "max_per_window": 500 }} }} }}"#,
+            thin
+        ))
+        .unwrap();
+        assert!(config.problems().is_empty());
+        let tracker = ExposureTracker::new(&config);
+        let now = Instant::now();
+
+        // the start mint itself isn't limited
+        assert_eq!(tracker.route_capacity_at(&[start, deep, start], now), Some(1000));
+        assert_eq!(tracker.route_capacity_at(&[start, deep, thin, start], now), Some(300));
+
+        tracker.record_at(&[start, thin, start], 300, now);
+        assert_eq!(tracker.route_capacity_at(&[start, thin, start], now), Some(200));
+        tracker.record_at(&[start, deep, thin, start], 200, now);
+        assert_eq!(tracker.route_capacity_at(&[start, thin, start], now), Some(0));
+        // only per transaction limits on the other mint
+        assert_eq!(tracker.route_capacity_at(&[start, deep, start], now), Some(1000));
+
+        // the window rolls over
+        let later = now + Duration::from_secs(60);
+        assert_eq!(tracker.route_capacity_at(&[start, thin, start], later), Some(300));
+    }
+
+    #[test]
+    fn nothing_is_limited_without_a_default() {
+        let (start, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let config: ExposureConfig = serde_json::from_str(r#"{ "mints": { "x": {} } }"#).unwrap();
+        assert_eq!(config.problems().len(), 1);
+        let tracker = ExposureTracker::new(&config);
+        tracker.record(&[start, other, start], 1_000_000);
+        assert_eq!(tracker.route_capacity(&[start, other, start]), None);
+    }
+}
@@ -0,0 +1,318 @@
+//! Why a route failed on chain
+//!
+//! A reverted transaction only says `custom program error: 0x10` in some
+//! instruction. `decode_failure` finds the program that failed in the
+//! transaction's logs (the innermost `Program <id> failed: ...` line, or an
+//! Anchor error log) and maps its error code to a `FailureReason`: the
+//! router's own errors, the Orca and Raydium swap errors, the token and
+//! system program's missing funds, compute exhaustion and order books that
+//! moved. Without logs the code alone is matched against the router's
+//! errors. Reasons are counted in the session summary and end up in the
+//! execution outcome (cooldowns, analytics).
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use serde::Serialize;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::transaction::TransactionError;
+
+use std::fmt;
+
+use crate::constants::{
+    ORCA_PROGRAM_ID, RAYDIUM_PROGRAM_ID, SERUM_PROGRAM_ID, TOKEN_PROGRAM_ID, WHIRLPOOL_PROGRAM_ID,
+};
+
+/// System program, which fails transfers of lamports the payer doesn't have
+const SYSTEM_PROGRAM_ID: &str = "11111111111111111111111111111111";
+/// Code of the router program's first error (`NoProfit`), Anchor's offset
+const ROUTER_FIRST_ERROR: u32 = 6000;
+/// Router errors in declaration order, from `ROUTER_FIRST_ERROR`
+const ROUTER_ERRORS: [(&str, FailureReason); 9] = [
+    ("NoProfit", FailureReason::NoProfit),
+    ("InvalidState", FailureReason::InvalidAccount),
+    ("NotEnoughFunds", FailureReason::InsufficientFunds),
+    ("RaydiumSwapFailed", FailureReason::Slippage),
+    ("InvalidRaydiumPool", FailureReason::StalePool),
+    ("ProfitBelowMinimum", FailureReason::NoProfit),
+    ("ArithmeticOverflow", FailureReason::Overflow),
+    ("InvalidPhoenixMarket", FailureReason::InvalidAccount),
+    ("AmountBelowLotSize", FailureReason::StaleOrderBook),
+];
+
+/// What went wrong, coarse enough to count
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureReason {
+    /// A swap returned less than its minimum out
+    Slippage,
+    /// The route returned less than it took in (or than the minimum profit)
+    NoProfit,
+    /// The wallet lacked the input or the lamports for fees
+    InsufficientFunds,
+    /// The order book changed between the quote and the swap
+    StaleOrderBook,
+    /// A pool's state didn't match what the route was built from
+    StalePool,
+    /// An account the route passed was the wrong one
+    InvalidAccount,
+    /// The transaction ran out of compute units
+    ComputeExhausted,
+    Overflow,
+    /// The blockhash expired before the transaction landed
+    Blockhash,
+    Unknown,
+}
+
+impl FailureReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureReason::Slippage => "slippage",
+            FailureReason::NoProfit => "no_profit",
+            FailureReason::InsufficientFunds => "insufficient_funds",
+            FailureReason::StaleOrderBook => "stale_order_book",
+            FailureReason::StalePool => "stale_pool",
+            FailureReason::InvalidAccount => "invalid_account",
+            FailureReason::ComputeExhausted => "compute_exhausted",
+            FailureReason::Overflow => "overflow",
+            FailureReason::Blockhash => "blockhash",
+            FailureReason::Unknown => "unknown",
+        }
+    }
+}
+
+/// A failure with the program and error it came from, as far as known
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DecodedFailure {
+    pub reason: FailureReason,
+    /// Name of the program that failed, e.g. `Orca`
+    pub program: Option<String>,
+    /// Custom error code of the program
+    pub code: Option<u32>,
+    /// The program's name for the error, or the failure as logged
+    pub detail: String,
+}
+
+impl fmt::Display for DecodedFailure {
+    /// `slippage (Orca ExceededSlippage, 0x10)`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (", self.reason.as_str())?;
+        if let Some(program) = &self.program {
+            write!(f, "{} ", program)?;
+        }
+        write!(f, "{}", self.detail)?;
+        if let Some(code) = self.code {
+            write!(f, ", {:#x}", code)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Decodes a failed transaction of the router program `router` from its
+/// error and logs, either of which may be missing
+pub fn decode_failure(
+    router: &Pubkey,
+    error: Option<&TransactionError>,
+    logs: &[String],
+) -> DecodedFailure {
+    if let Some(decoded) = decode_logs(router, logs) {
+        return decoded;
+    }
+    match error {
+        Some(TransactionError::InstructionError(_, InstructionError::Custom(code))) => {
+            let (detail, reason) = router_error(*code)
+                .map_or(("custom error".to_string(), FailureReason::Unknown), |(name, reason)| {
+                    (name.to_string(), reason)
+                });
+            DecodedFailure {
+                reason,
+                program: None,
+                code: Some(*code),
+                detail,
+            }
+        }
+        Some(TransactionError::InstructionError(_, InstructionError::ComputationalBudgetExceeded)) => {
+            unknown_program(FailureReason::ComputeExhausted, "computational budget exceeded")
+        }
+        Some(TransactionError::InsufficientFundsForFee) => {
+            unknown_program(FailureReason::InsufficientFunds, "insufficient funds for fee")
+        }
+        Some(TransactionError::BlockhashNotFound) => {
+            unknown_program(FailureReason::Blockhash, "blockhash not found")
+        }
+        Some(error) => unknown_program(FailureReason::Unknown, &format!("{:?}", error)),
+        None => unknown_program(FailureReason::Unknown, "no error reported"),
+    }
+}
+
+fn unknown_program(reason: FailureReason, detail: &str) -> DecodedFailure {
+    DecodedFailure {
+        reason,
+        program: None,
+        code: None,
+        detail: detail.to_string(),
+    }
+}
+
+/// The first (innermost) failure in the logs
+fn decode_logs(router: &Pubkey, logs: &[String]) -> Option<DecodedFailure> {
+    for line in logs {
+        // Anchor programs log their error before the runtime does
+        if let Some(anchor) = line.strip_prefix("Program log: AnchorError") {
+            let code = field(anchor, "Error Number: ").and_then(|number| number.parse().ok());
+            let name = field(anchor, "Error Code: ").unwrap_or("anchor error");
+            let reason = code
+                .and_then(router_error)
+                .map_or(FailureReason::Unknown, |(_, reason)| reason);
+            return Some(DecodedFailure {
+                reason,
+                program: None,
+                code,
+                detail: name.to_string(),
+            });
+        }
+        let rest = match line.strip_prefix("Program ") {
+            Some(rest) => rest,
+            None => continue,
+        };
+        let (program_id, failure) = match rest.split_once(" failed: ") {
+            Some(failed) => failed,
+            None => continue,
+        };
+        let program_id = program_id.parse::<Pubkey>().ok();
+        let program = program_id.map(|id| program_name(router, &id));
+        let code = failure
+            .strip_prefix("custom program error: 0x")
+            .and_then(|hex| u32::from_str_radix(hex.trim(), 16).ok());
+        let (detail, reason) = match (program_id, code) {
+            (Some(id), Some(code)) => program_error(router, &id, code),
+            _ if failure.contains("exceeded CUs meter") || failure.contains("computational budget") => {
+                (failure.to_string(), FailureReason::ComputeExhausted)
+            }
+            _ if failure.contains("insufficient") => (failure.to_string(), FailureReason::InsufficientFunds),
+            _ => (failure.to_string(), FailureReason::Unknown),
+        };
+        return Some(DecodedFailure {
+            reason,
+            program,
+            code,
+            detail,
+        });
+    }
+    None
+}
+
+/// `<value>` of `... <name><value>. ...` in an Anchor error log
+fn field<'a>(log: &'a str, name: &str) -> Option<&'a str> {
+    let start = log.find(name)? + name.len();
+    let value = &log[start..];
+    Some(value.split(". ").next().unwrap_or(value).trim_end_matches('.'))
+}
+
+fn router_error(code: u32) -> Option<(&'static str, FailureReason)> {
+    let index = code.checked_sub(ROUTER_FIRST_ERROR)? as usize;
+    ROUTER_ERRORS.get(index).copied()
+}
+
+fn program_name(router: &Pubkey, id: &Pubkey) -> String {
+    let name = if id == router {
+        "router"
+    } else if id == &*ORCA_PROGRAM_ID {
+        "Orca"
+    } else if id == &*RAYDIUM_PROGRAM_ID {
+        "Raydium"
+    } else if id == &*SERUM_PROGRAM_ID {
+        "Serum"
+    } else if id == &*WHIRLPOOL_PROGRAM_ID {
+        "Whirlpool"
+    } else if id == &*TOKEN_PROGRAM_ID {
+        "Token"
+    } else if id.to_string() == SYSTEM_PROGRAM_ID {
+        "System"
+    } else {
+        return id.to_string();
+    };
+    name.to_string()
+}
+
+/// The error `code` of program `id`, and what it means for the route
+fn program_error(router: &Pubkey, id: &Pubkey, code: u32) -> (String, FailureReason) {
+    let known = if id == router {
+        router_error(code)
+    } else if id == &*ORCA_PROGRAM_ID {
+        // spl-token-swap's SwapError
+        match code {
+            0x0e => Some(("CalculationFailure", FailureReason::Overflow)),
+            0x10 => Some(("ExceededSlippage", FailureReason::Slippage)),
+            0x11 => Some(("ZeroTradingTokens", FailureReason::StalePool)),
+            _ => None,
+        }
+    } else if id == &*RAYDIUM_PROGRAM_ID {
+        match code {
+            0x1e => Some(("ExceededSlippage", FailureReason::Slippage)),
+            _ => None,
+        }
+    } else if id == &*SERUM_PROGRAM_ID {
+        // the book no longer has the liquidity the route was quoted on
+        Some(("order book error", FailureReason::StaleOrderBook))
+    } else if id == &*TOKEN_PROGRAM_ID {
+        match code {
+            0x1 => Some(("InsufficientFunds", FailureReason::InsufficientFunds)),
+            _ => None,
+        }
+    } else if id.to_string() == SYSTEM_PROGRAM_ID {
+        match code {
+            0x1 => Some(("ResultWithNegativeLamports", FailureReason::InsufficientFunds)),
+            _ => None,
+        }
+    } else {
+        None
+    };
+    known.map_or(("custom error".to_string(), FailureReason::Unknown), |(name, reason)| {
+        (name.to_string(), reason)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn logs(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn the_innermost_failing_program_decides() {
+        let router = Pubkey::new_unique();
+        let orca = ORCA_PROGRAM_ID.to_string();
+        let logs = logs(&[
+            &format!("Program {} invoke [1]", router),
+            &format!("Program {} invoke [2]", orca),
+            &format!("Program {} failed: custom program error: 0x10", orca),
+            &format!("Program {} failed: custom program error: 0x10", router),
+        ]);
+        let decoded = decode_failure(&router, None, &logs);
+        assert_eq!(decoded.reason, FailureReason::Slippage);
+        assert_eq!(decoded.to_string(), "slippage (Orca ExceededSlippage, 0x10)");
+
+        let compute = [format!("Program {} failed: exceeded CUs meter at BPF instruction #1", router)];
+        assert_eq!(decode_failure(&router, None, &compute).reason, FailureReason::ComputeExhausted);
+    }
+
+    #[test]
+    fn router_errors_decode_from_anchor_logs_or_the_code() {
+        let router = Pubkey::new_unique();
+        let anchor = logs(&[
+            "Program log: AnchorError thrown in programs/tmp/src/lib.rs:80. \
+             Error Code: NoProfit. Error Number: 6000. Error Message: No profit at the end. Reverting....",
+        ]);
+        let decoded = decode_failure(&router, None, &anchor);
+        assert_eq!(decoded.reason, FailureReason::NoProfit);
+        assert_eq!(decoded.detail, "NoProfit");
+        assert_eq!(decoded.code, Some(6000));
+
+        let error = TransactionError::InstructionError(2, InstructionError::Custom(0x1772));
+        let decoded = decode_failure(&router, Some(&error), &[]);
+        assert_eq!(decoded.reason, FailureReason::InsufficientFunds);
+        assert_eq!(decoded.to_string(), "insufficient_funds (NotEnoughFunds, 0x1772)");
+        assert_eq!(decode_failure(&router, None, &[]).reason, FailureReason::Unknown);
+    }
+}
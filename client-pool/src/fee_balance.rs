@@ -0,0 +1,306 @@
+//! SOL fee balance monitor
+//!
+//! Every send pays its signature fee, tip and priority fee in SOL. Once the
+//! signer runs dry, sends fail before they reach a leader and the bot stalls
+//! without a revert to show for it. Every `interval_secs` the signer's SOL
+//! balance is compared with what `min_sends` sends may cost (see
+//! `doctor::send_cost`); below that a warning is logged on every check, and
+//! with `top_up` `amount` of `top_up.mint` is swapped into SOL through the
+//! direct pool that pays the most, at most `max_per_session` times.
+//!
+//! The swap lands in the owner's wrapped SOL account, which routes may use,
+//! so the quoted output (after slippage) is moved to a temporary wrapped SOL
+//! account that is closed into the signer right away.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signature::{Keypair, Signer};
+use anchor_client::solana_sdk::system_instruction;
+use serde::{Deserialize, Serialize};
+use solana_sdk::instruction::Instruction;
+
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+use crate::arb::Arbitrager;
+use crate::config::Config;
+use crate::constants::{USDC_MINT, WSOL_MINT};
+use crate::doctor::send_cost;
+use crate::executor::Dispatcher;
+use crate::pool::PoolOperations;
+use crate::serialize::token::TOKEN_ACCOUNT_LEN;
+use crate::utils::derive_token_address;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FeeBalanceConfig {
+    /// Sends the SOL balance should cover
+    #[serde(default = "default_min_sends")]
+    pub min_sends: u64,
+    /// Time between two balance checks
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// Swap tokens into SOL when the balance is low, only alert if unset
+    #[serde(default)]
+    pub top_up: Option<TopUpConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TopUpConfig {
+    /// Mint swapped into SOL
+    #[serde(default = "default_top_up_mint")]
+    pub mint: String,
+    /// How much of `mint` one top-up swaps (scaled)
+    pub amount: u64,
+    /// Most top-ups in one session, in case the balance keeps draining
+    #[serde(default = "default_max_per_session")]
+    pub max_per_session: u32,
+}
+
+fn default_min_sends() -> u64 {
+    100
+}
+
+fn default_interval_secs() -> u64 {
+    60
+}
+
+fn default_top_up_mint() -> String {
+    USDC_MINT.to_string()
+}
+
+fn default_max_per_session() -> u32 {
+    3
+}
+
+impl FeeBalanceConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.min_sends == 0 {
+            problems.push("fee_balance.min_sends must be > 0".to_string());
+        }
+        if self.interval_secs == 0 {
+            problems.push("fee_balance.interval_secs must be > 0".to_string());
+        }
+        if let Some(top_up) = &self.top_up {
+            match Pubkey::from_str(&top_up.mint) {
+                Ok(mint) if mint == *WSOL_MINT => {
+                    problems.push("fee_balance.top_up.mint can't be wrapped SOL".to_string())
+                }
+                Ok(_) => {}
+                Err(_) => {
+                    problems.push(format!("fee_balance.top_up.mint {} is not a valid pubkey", top_up.mint))
+                }
+            }
+            if top_up.amount == 0 {
+                problems.push("fee_balance.top_up.amount must be > 0".to_string());
+            }
+        }
+        problems
+    }
+}
+
+/// What a balance check found
+#[derive(Debug, PartialEq)]
+pub enum FeeBalance {
+    /// Covers `min_sends`
+    Ok,
+    /// Too low, and topping up is off or used up
+    Low { lamports: u64, needed: u64 },
+    /// Too low, a top-up should be sent
+    TopUp { lamports: u64, needed: u64 },
+}
+
+/// Checks the signer's SOL balance every `interval_secs`
+pub struct FeeBalanceMonitor {
+    config: FeeBalanceConfig,
+    /// lamports `min_sends` sends may cost
+    needed: u64,
+    last_check: Option<Instant>,
+    top_ups: u32,
+}
+
+impl FeeBalanceMonitor {
+    pub fn new(config: &FeeBalanceConfig, bot_config: &Config) -> Self {
+        Self {
+            config: config.clone(),
+            needed: send_cost(bot_config).saturating_mul(config.min_sends),
+            last_check: None,
+            top_ups: 0,
+        }
+    }
+
+    /// Whether a check is due, the first one right away
+    pub fn is_due(&self) -> bool {
+        self.last_check
+            .map_or(true, |last| last.elapsed() >= Duration::from_secs(self.config.interval_secs))
+    }
+
+    /// What to make of a balance of `lamports`
+    pub fn assess(&self, lamports: u64) -> FeeBalance {
+        if lamports >= self.needed {
+            return FeeBalance::Ok;
+        }
+        let can_top_up = self
+            .config
+            .top_up
+            .as_ref()
+            .map_or(false, |top_up| self.top_ups < top_up.max_per_session);
+        if can_top_up {
+            FeeBalance::TopUp { lamports, needed: self.needed }
+        } else {
+            FeeBalance::Low { lamports, needed: self.needed }
+        }
+    }
+
+    /// Reads the balance, warns when it's low and tops it up if configured
+    pub fn check(&mut self, ctx: &Dispatcher, arb: &Arbitrager) {
+        self.last_check = Some(Instant::now());
+        // simulations and paper trades pay no fees
+        if !ctx.sends() {
+            return;
+        }
+        let lamports = match ctx.connection.get_balance(&ctx.owner.pubkey()) {
+            Ok(lamports) => lamports,
+            Err(e) => {
+                warn!("could not read the SOL balance: {}", e);
+                return;
+            }
+        };
+        match self.assess(lamports) {
+            FeeBalance::Ok => {}
+            FeeBalance::Low { lamports, needed } => {
+                warn!(
+                    "SOL balance {} is below the {} lamports {} sends may cost",
+                    lamports, needed, self.config.min_sends
+                );
+                ctx.stats.record_error("sol balance low");
+            }
+            FeeBalance::TopUp { lamports, needed } => {
+                warn!("SOL balance {} is below {} lamports, topping up", lamports, needed);
+                ctx.stats.record_error("sol balance low");
+                self.top_ups += 1;
+                if let Some(top_up) = &self.config.top_up {
+                    self.top_up(ctx, arb, top_up);
+                }
+            }
+        }
+    }
+
+    fn top_up(&self, ctx: &Dispatcher, arb: &Arbitrager, top_up: &TopUpConfig) {
+        let mint = Pubkey::from_str(&top_up.mint).unwrap();
+        let best = arb
+            .pools_between(&mint, &WSOL_MINT)
+            .into_iter()
+            .filter_map(|pool| {
+                let out = pool
+                    .0
+                    .get_quote_with_amounts_scaled(top_up.amount as u128, &mint, &WSOL_MINT)
+                    .ok()?;
+                Some((pool, out))
+            })
+            .max_by_key(|(_, out)| *out);
+        let (pool, quoted) = match best {
+            Some(best) => best,
+            None => {
+                warn!("no pool swaps {} into SOL, can't top up", mint);
+                return;
+            }
+        };
+        // the unwrap transfer fails (and reverts the swap) below this
+        let lamports = ctx.params.apply_route_slippage(quoted, std::slice::from_ref(&pool)) as u64;
+        let mut ixs = match ctx.swap_ixs(top_up.amount, &mint, &WSOL_MINT, &pool) {
+            Ok(ixs) => ixs,
+            Err(e) => {
+                warn!("can't top up through {}: {}", pool.0.get_id(), e);
+                return;
+            }
+        };
+        let rent = match ctx.connection.get_minimum_balance_for_rent_exemption(TOKEN_ACCOUNT_LEN) {
+            Ok(rent) => rent,
+            Err(e) => {
+                warn!("can't top up: {}", e);
+                return;
+            }
+        };
+        let temp = Keypair::new();
+        match unwrap_ixs(&ctx.owner.pubkey(), &temp.pubkey(), lamports, rent) {
+            Ok(unwrap) => ixs.extend(unwrap),
+            Err(e) => {
+                warn!("can't top up: {}", e);
+                return;
+            }
+        }
+        info!(
+            "topping up SOL: {} of {} through {} for {} lamports",
+            top_up.amount,
+            mint,
+            pool.0.get_id(),
+            lamports
+        );
+        ctx.send_maintenance(&ixs, &[&temp], "sol top-up");
+    }
+}
+
+/// Moves `lamports` of the owner's wrapped SOL into a new account `temp`
+/// and closes it into the owner, unwrapping them
+fn unwrap_ixs(owner: &Pubkey, temp: &Pubkey, lamports: u64, rent: u64) -> Result<Vec<Instruction>, String> {
+    let token_program = spl_token::id();
+    let wsol_ata = derive_token_address(owner, &WSOL_MINT);
+    Ok(vec![
+        system_instruction::create_account(owner, temp, rent, TOKEN_ACCOUNT_LEN as u64, &token_program),
+        spl_token::instruction::initialize_account(&token_program, temp, &WSOL_MINT, owner)
+            .map_err(|e| e.to_string())?,
+        spl_token::instruction::transfer(&token_program, &wsol_ata, temp, owner, &[], lamports)
+            .map_err(|e| e.to_string())?,
+        spl_token::instruction::close_account(&token_program, temp, owner, owner, &[])
+            .map_err(|e| e.to_string())?,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(top_up: &str) -> FeeBalanceMonitor {
+        let bot: Config =
+            serde_json::from_str(r#"{ "fee_bps": 10, "tip_lamports": 5000 }"#).unwrap();
+        let config: FeeBalanceConfig =
+            serde_json::from_str(&format!(r#"{{ "min_sends": 10 {} }}"#, top_up)).unwrap();
+        FeeBalanceMonitor::new(&config, &bot)
+    }
+
+    #[test]
+    fn low_balances_top_up_until_the_limit() {
+        // 10 sends of 5000 fee + 5000 tip
+        let alert_only = monitor("");
+        assert_eq!(alert_only.assess(100_000), FeeBalance::Ok);
+        assert_eq!(
+            alert_only.assess(99_999),
+            FeeBalance::Low { lamports: 99_999, needed: 100_000 }
+        );
+
+        let mut topping_up = monitor(r#", "top_up": { "amount": 1000000, "max_per_session": 1 }"#);
+        assert_eq!(
+            topping_up.assess(50_000),
+            FeeBalance::TopUp { lamports: 50_000, needed: 100_000 }
+        );
+        topping_up.top_ups = 1;
+        assert_eq!(
+            topping_up.assess(50_000),
+            FeeBalance::Low { lamports: 50_000, needed: 100_000 }
+        );
+    }
+
+    #[test]
+    fn unwrapping_closes_the_temporary_account_into_the_owner() {
+        let (owner, temp) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let ixs = unwrap_ixs(&owner, &temp, 1_000_000, 2_039_280).unwrap();
+        assert_eq!(ixs.len(), 4);
+        let close = &ixs[3];
+        assert_eq!(close.program_id, spl_token::id());
+        assert_eq!(close.accounts[0].pubkey, temp);
+        assert_eq!(close.accounts[1].pubkey, owner);
+    }
+}
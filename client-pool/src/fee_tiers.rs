@@ -0,0 +1,91 @@
+//! Serum fee tiers
+//!
+//! A Serum taker pays 4 bps on most markets, down to 3 bps the more SRM it
+//! holds (or with an MSRM), and 1 bp on the stable markets whatever it
+//! holds. Each market's tier is taken at startup from the balances of the
+//! owner's SRM and MSRM associated token accounts; quotes charge it, so the
+//! profit of a route through the market is counted at the fee paid.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+
+use tracing::{info, warn};
+
+use crate::constants::{MSRM_MINT, SRM_MINT};
+use crate::graph::{AccountSource, PoolSet};
+use crate::pool_utils::serum::FeeTier;
+use crate::pools::PoolVariant;
+use crate::serialize::decoder::DECODERS;
+use crate::utils::derive_token_address;
+
+/// The owner's SRM and MSRM (native amounts), nothing for accounts that
+/// don't exist or can't be read
+pub fn srm_holdings<S: AccountSource + ?Sized>(reads: &S, owner: &Pubkey) -> (u64, u64) {
+    let atas = [derive_token_address(owner, &SRM_MINT), derive_token_address(owner, &MSRM_MINT)];
+    let accounts = match reads.get_multiple_accounts_with_slot(&atas) {
+        Ok((accounts, _)) => accounts,
+        Err(e) => {
+            warn!("can't read the SRM balances, serum markets are quoted at the base fee: {}", e);
+            return (0, 0);
+        }
+    };
+    let balance = |i: usize| {
+        accounts
+            .get(i)
+            .and_then(|account| account.as_ref())
+            .and_then(|account| DECODERS.token_account(account).ok())
+            .map_or(0, |token| token.amount)
+    };
+    (balance(0), balance(1))
+}
+
+/// Sets the fee tier of every Serum market for an owner holding `srm_held`
+/// and `msrm_held`, returns how many trade below the base fee
+pub fn apply_fee_tiers(pool_set: &mut PoolSet, srm_held: u64, msrm_held: u64) -> usize {
+    let mut discounted = 0;
+    for pool in pool_set.pools.iter_mut() {
+        if let PoolVariant::Serum(market) = pool {
+            market.set_fee_tier(srm_held, msrm_held);
+            if !matches!(market.fee_tier, FeeTier::Base) {
+                discounted += 1;
+            }
+        }
+    }
+    if discounted > 0 {
+        info!(
+            "{} serum markets trade below the base fee ({} SRM, {} MSRM held)",
+            discounted, srm_held, msrm_held
+        );
+    }
+    discounted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pool::{PoolOperations, PoolType};
+    use crate::serialize::token::WrappedPubkey;
+    use crate::tests::pools::serum_market;
+    use std::str::FromStr;
+
+    #[test]
+    fn markets_take_the_tier_of_the_holdings() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut stable = serum_market(&a, &b, None);
+        let usdt_usdc = Pubkey::from_str("77quYg4MGneUdjgXCunt9GgM1usmrxKY31twEy3WHwcS").unwrap();
+        stable.own_address = WrappedPubkey(usdt_usdc);
+        let mut pool_set = PoolSet::new();
+        pool_set.add_pool(PoolType::SerumPoolType, serum_market(&a, &b, None).into());
+        pool_set.add_pool(PoolType::SerumPoolType, stable.into());
+        let fees = |pool_set: &PoolSet| -> Vec<Option<f64>> {
+            pool_set.pools.iter().map(|pool| pool.get_fee_bps()).collect()
+        };
+
+        assert_eq!(apply_fee_tiers(&mut pool_set, 0, 0), 1);
+        assert_eq!(fees(&pool_set), vec![Some(4.0), Some(1.0)]);
+        // 1000 SRM
+        assert_eq!(apply_fee_tiers(&mut pool_set, 1_000_000_000, 0), 2);
+        assert_eq!(fees(&pool_set), vec![Some(3.8), Some(1.0)]);
+        assert_eq!(apply_fee_tiers(&mut pool_set, 0, 1), 2);
+        assert_eq!(fees(&pool_set), vec![Some(3.0), Some(1.0)]);
+    }
+}
@@ -0,0 +1,372 @@
+//! Pool fetcher
+//!
+//! Rebuilds the pool JSON files from chain instead of relying on
+//! hand-maintained ones (`main fetch-pools`). Every pool account of a DEX
+//! program is fetched and decoded, its mints are fetched for their decimals,
+//! and the pool struct the bot deserializes is filled in and serialized, so
+//! the files always have the exact schema `pool_factory` reads. Every file is
+//! parsed back through `pool_factory` before it is written.
+//!
+//! Orca (token-swap v2), Raydium (AMM v4) and Serum (v3 markets) can be
+//! fetched. Jupiter is an aggregator without pool accounts of its own.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use solana_sdk::account::Account;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use tracing::{debug, info, warn};
+
+use crate::constants::*;
+use crate::error::{ArbError, ArbResult};
+use crate::graph::ACCOUNTS_PER_REQUEST;
+use crate::pool::{pool_factory, PoolType};
+use crate::pool_utils::serum::FeeTier;
+use crate::pools::{OrcaPool, RaydiumPool, SerumPool};
+use crate::rpc::RpcPool;
+use crate::serialize::decoder::{
+    DecodedAccount, OrcaSwapState, RaydiumAmmInfo, SerumMarketState, DECODERS, ORCA_SWAP_LEN,
+    RAYDIUM_AMM_V4_LEN, SERUM_MARKET_LEN,
+};
+use crate::serialize::pool::{Fraction, JSONFeeStructure};
+use crate::serialize::token::{Token, WrappedPubkey};
+
+/// Taker fee of Serum's base fee tier (4 bps, see `FeeTier::Base`)
+const SERUM_TAKER_FEE_PCT: f64 = 0.0004;
+
+/// Seed of the Raydium AMM v4 authority
+const RAYDIUM_AUTHORITY_SEED: &[u8] = b"amm authority";
+
+/// A pool read from chain, ready to be written
+#[derive(Debug, Clone)]
+pub struct FetchedPool {
+    pub address: Pubkey,
+    pub json: String,
+}
+
+/// Program and pool account size of a fetchable pool type
+fn pool_program(tipe: PoolType) -> ArbResult<(Pubkey, usize)> {
+    match tipe {
+        PoolType::OrcaPoolType => Ok((*ORCA_PROGRAM_ID, ORCA_SWAP_LEN)),
+        PoolType::RaydiumType => Ok((*RAYDIUM_PROGRAM_ID, RAYDIUM_AMM_V4_LEN)),
+        PoolType::SerumPoolType => Ok((*SERUM_PROGRAM_ID, SERUM_MARKET_LEN)),
+        _ => Err(ArbError::UnsupportedPoolType(tipe)),
+    }
+}
+
+/// Directory name of a pool type, as used in the config (`orca`, `raydium`, ...)
+pub fn dex_name(tipe: PoolType) -> String {
+    serde_json::to_value(tipe)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_else(|| format!("{:?}", tipe))
+}
+
+/// Short label for a mint; the chain has no symbols, so only the well-known
+/// mints get one
+fn mint_tag(mint: &Pubkey) -> String {
+    if *mint == *USDC_MINT {
+        "USDC".to_string()
+    } else if *mint == *USDT_MINT {
+        "USDT".to_string()
+    } else if *mint == *WSOL_MINT {
+        "SOL".to_string()
+    } else {
+        mint.to_string()[..4].to_string()
+    }
+}
+
+fn token(mint: Pubkey, scale: u64, vault: Pubkey) -> Token {
+    Token {
+        tag: mint_tag(&mint),
+        name: mint.to_string(),
+        mint: WrappedPubkey(mint),
+        scale,
+        addr: WrappedPubkey(vault),
+    }
+}
+
+/// Decimals of every given mint that exists
+fn mint_decimals(rpc: &RpcPool, mints: &HashSet<Pubkey>) -> ArbResult<HashMap<Pubkey, u64>> {
+    let mints: Vec<Pubkey> = mints.iter().copied().collect();
+    let mut decimals = HashMap::new();
+    for chunk in mints.chunks(ACCOUNTS_PER_REQUEST) {
+        let accounts = rpc.get_multiple_accounts(chunk)?;
+        for (mint, account) in chunk.iter().zip(accounts) {
+            match account.as_ref().map(|acc| DECODERS.mint(acc)) {
+                Some(Ok(state)) => {
+                    decimals.insert(*mint, state.decimals as u64);
+                }
+                Some(Err(e)) => debug!("mint {} doesn't decode: {}", mint, e),
+                None => debug!("mint {} is missing", mint),
+            }
+        }
+    }
+    Ok(decimals)
+}
+
+/// Fetches every pool of `tipe` from chain. With `mints` set only pools
+/// between two of those mints are kept.
+pub fn fetch_pools(
+    rpc: &RpcPool,
+    tipe: PoolType,
+    mints: Option<&HashSet<Pubkey>>,
+) -> ArbResult<Vec<FetchedPool>> {
+    let (program_id, size) = pool_program(tipe)?;
+    let accounts = rpc.get_program_accounts_with_size(&program_id, size)?;
+    info!("{} {:?} pool accounts", accounts.len(), tipe);
+
+    let states: Vec<(Pubkey, DecodedAccount)> = accounts
+        .iter()
+        .filter_map(|(address, account)| decode(address, account))
+        .filter(|(_, state)| match (mints, pool_mints(state)) {
+            (Some(mints), Some([a, b])) => mints.contains(&a) && mints.contains(&b),
+            (None, Some(_)) => true,
+            (_, None) => false,
+        })
+        .collect();
+
+    let mut needed = HashSet::new();
+    for (_, state) in states.iter() {
+        match state {
+            DecodedAccount::OrcaSwap(swap) => {
+                needed.extend([swap.mint_a, swap.mint_b, swap.pool_mint]);
+            }
+            DecodedAccount::SerumMarket(market) => {
+                needed.extend([market.coin_mint, market.pc_mint]);
+            }
+            _ => {}
+        }
+    }
+    let decimals = mint_decimals(rpc, &needed)?;
+
+    let mut pools = vec![];
+    for (address, state) in states {
+        let json = match &state {
+            DecodedAccount::OrcaSwap(swap) => orca_json(&address, swap, &decimals),
+            DecodedAccount::RaydiumAmm(amm) => raydium_json(&address, amm),
+            DecodedAccount::SerumMarket(market) => serum_json(&address, market, &decimals),
+            _ => continue,
+        };
+        let json = match json {
+            Some(json) => json?,
+            None => {
+                debug!("skipping {}: a mint is missing", address);
+                continue;
+            }
+        };
+        // what is written must load
+        if let Err(e) = pool_factory(&tipe, &json) {
+            warn!("skipping {}: {}", address, e);
+            continue;
+        }
+        pools.push(FetchedPool { address, json });
+    }
+    Ok(pools)
+}
+
+fn decode(address: &Pubkey, account: &Account) -> Option<(Pubkey, DecodedAccount)> {
+    match DECODERS.decode(account) {
+        Ok(decoded) => Some((*address, decoded.account)),
+        Err(e) => {
+            debug!("skipping {}: {}", address, e);
+            None
+        }
+    }
+}
+
+/// Mints of a decoded pool, `None` for pools that can't be traded
+fn pool_mints(state: &DecodedAccount) -> Option<[Pubkey; 2]> {
+    match state {
+        DecodedAccount::OrcaSwap(swap) => Some([swap.mint_a, swap.mint_b]),
+        // status 0 is an uninitialized amm
+        DecodedAccount::RaydiumAmm(amm) if amm.status != 0 => Some([amm.base_mint, amm.quote_mint]),
+        DecodedAccount::SerumMarket(market) => Some([market.coin_mint, market.pc_mint]),
+        _ => None,
+    }
+}
+
+fn orca_json(
+    address: &Pubkey,
+    swap: &OrcaSwapState,
+    decimals: &HashMap<Pubkey, u64>,
+) -> Option<ArbResult<String>> {
+    let authority =
+        Pubkey::create_program_address(&[address.as_ref(), &[swap.nonce]], &ORCA_PROGRAM_ID).ok()?;
+    let tokens = [
+        token(swap.mint_a, *decimals.get(&swap.mint_a)?, swap.token_a),
+        token(swap.mint_b, *decimals.get(&swap.mint_b)?, swap.token_b),
+    ];
+    let pool = OrcaPool {
+        address: WrappedPubkey(*address),
+        nonce: swap.nonce as u64,
+        authority: WrappedPubkey(authority),
+        pool_token_mint: WrappedPubkey(swap.pool_mint),
+        pool_token_decimals: *decimals.get(&swap.pool_mint)?,
+        fee_account: WrappedPubkey(swap.fee_account),
+        token_ids: tokens.iter().map(|t| t.mint.to_string()).collect(),
+        tokens: tokens.into_iter().map(|t| (t.mint.to_string(), t)).collect(),
+        fee_structure: JSONFeeStructure {
+            trader_fee: Fraction {
+                numerator: swap.trade_fee_numerator,
+                denominator: swap.trade_fee_denominator,
+            },
+            owner_fee: Fraction {
+                numerator: swap.owner_trade_fee_numerator,
+                denominator: swap.owner_trade_fee_denominator,
+            },
+        },
+        curve_type: swap.curve_type,
+        amp: swap.amp,
+        pool_amounts: HashMap::new(),
+        update_slot: None,
+    };
+    Some(serde_json::to_string_pretty(&pool).map_err(ArbError::from))
+}
+
+fn raydium_json(address: &Pubkey, amm: &RaydiumAmmInfo) -> Option<ArbResult<String>> {
+    let authority = Pubkey::create_program_address(
+        &[RAYDIUM_AUTHORITY_SEED, &[amm.nonce as u8]],
+        &RAYDIUM_PROGRAM_ID,
+    )
+    .ok()?;
+    let tokens = [
+        token(amm.base_mint, amm.base_decimal, amm.base_vault),
+        token(amm.quote_mint, amm.quote_decimal, amm.quote_vault),
+    ];
+    let pool = RaydiumPool {
+        address: WrappedPubkey(*address),
+        nonce: amm.nonce,
+        authority: WrappedPubkey(authority),
+        lp_token_mint: WrappedPubkey(amm.lp_mint),
+        // amm v4 has no fee account, its swap takes the amm's open orders there
+        fee_account: WrappedPubkey(amm.open_orders),
+        token_ids: tokens.iter().map(|t| t.mint.to_string()).collect(),
+        tokens: tokens.into_iter().map(|t| (t.mint.to_string(), t)).collect(),
+        // the swap fee is all of it, nothing goes to an owner account
+        fee_structure: JSONFeeStructure {
+            trader_fee: Fraction {
+                numerator: amm.swap_fee_numerator,
+                denominator: amm.swap_fee_denominator,
+            },
+            owner_fee: Fraction {
+                numerator: 0,
+                denominator: amm.swap_fee_denominator,
+            },
+        },
+        open_orders: Some(WrappedPubkey(amm.open_orders)),
+        pool_amounts: HashMap::new(),
+        update_slot: None,
+    };
+    Some(serde_json::to_string_pretty(&pool).map_err(ArbError::from))
+}
+
+fn serum_json(
+    address: &Pubkey,
+    market: &SerumMarketState,
+    decimals: &HashMap<Pubkey, u64>,
+) -> Option<ArbResult<String>> {
+    let vault_signer = Pubkey::create_program_address(
+        &[address.as_ref(), &market.vault_signer_nonce.to_le_bytes()],
+        &SERUM_PROGRAM_ID,
+    )
+    .ok()?;
+    let pool = SerumPool {
+        own_address: WrappedPubkey(*address),
+        base_mint: WrappedPubkey(market.coin_mint),
+        quote_mint: WrappedPubkey(market.pc_mint),
+        base_scale: *decimals.get(&market.coin_mint)?,
+        quote_scale: *decimals.get(&market.pc_mint)?,
+        base_vault: WrappedPubkey(market.coin_vault),
+        quote_vault: WrappedPubkey(market.pc_vault),
+        request_queue: WrappedPubkey(market.request_queue),
+        event_queue: WrappedPubkey(market.event_queue),
+        bids: WrappedPubkey(market.bids),
+        asks: WrappedPubkey(market.asks),
+        vault_signer: WrappedPubkey(vault_signer),
+        taker_fee_pct: SERUM_TAKER_FEE_PCT,
+        accounts: None,
+        open_orders: None,
+        update_slot: None,
+        book: None,
+        fee_tier: FeeTier::default(),
+    };
+    Some(serde_json::to_string_pretty(&pool).map_err(ArbError::from))
+}
+
+/// Writes the pools to `<out_dir>/<dex>/<address>.json` and returns the
+/// directory. With `prune` set, other JSON files in it are removed.
+pub fn write_pools(
+    out_dir: &str,
+    tipe: PoolType,
+    pools: &[FetchedPool],
+    prune: bool,
+) -> ArbResult<String> {
+    let dir = Path::new(out_dir).join(dex_name(tipe));
+    fs::create_dir_all(&dir)?;
+    let mut written = HashSet::new();
+    for pool in pools {
+        let file = format!("{}.json", pool.address);
+        fs::write(dir.join(&file), &pool.json)?;
+        written.insert(file);
+    }
+    if prune {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
+            if name.ends_with(".json") && !written.contains(&name) {
+                debug!("removing stale pool file {:?}", path);
+                fs::remove_file(&path)?;
+            }
+        }
+    }
+    Ok(dir.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pool::PoolOperations;
+
+    #[test]
+    fn fetched_raydium_pools_load() {
+        let (base, quote) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (_, nonce) = Pubkey::find_program_address(&[RAYDIUM_AUTHORITY_SEED], &RAYDIUM_PROGRAM_ID);
+        let amm = RaydiumAmmInfo {
+            status: 6,
+            nonce: nonce as u64,
+            base_decimal: 9,
+            quote_decimal: 6,
+            trade_fee_numerator: 25,
+            trade_fee_denominator: 10_000,
+            swap_fee_numerator: 25,
+            swap_fee_denominator: 10_000,
+            base_need_take_pnl: 0,
+            quote_need_take_pnl: 0,
+            base_vault: Pubkey::new_unique(),
+            quote_vault: Pubkey::new_unique(),
+            base_mint: base,
+            quote_mint: quote,
+            lp_mint: Pubkey::new_unique(),
+            open_orders: Pubkey::new_unique(),
+            market_id: Pubkey::new_unique(),
+            market_program_id: *SERUM_PROGRAM_ID,
+            target_orders: Pubkey::new_unique(),
+        };
+        let address = Pubkey::new_unique();
+        let json = raydium_json(&address, &amm).unwrap().unwrap();
+        let pool = pool_factory(&PoolType::RaydiumType, &json).unwrap();
+        assert_eq!(pool.get_address(), address);
+        assert_eq!(pool.get_mints().len(), 2);
+        assert!(pool.get_mints().contains(&base));
+    }
+
+    #[test]
+    fn dex_names_match_the_config() {
+        assert_eq!(dex_name(PoolType::OrcaPoolType), "orca");
+        assert_eq!(dex_name(PoolType::SerumPoolType), "serum");
+        assert!(pool_program(PoolType::JupiterPoolType).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+//! Flash-loan backed execution
+//!
+//! Borrows the swap input from a lending reserve at the top of the arb
+//! transaction and repays it (plus the flash-loan fee) as the last
+//! instruction, so the trade size isn't limited by the wallet balance.
+//!
+//! Uses Solend-style flash loans: the lending program checks via the
+//! instructions sysvar that a matching repay instruction follows the borrow
+//! in the same transaction, so no CPI from the router is needed. If the cycle
+//! doesn't return enough to repay, the repay fails and the whole transaction
+//! reverts.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use serde::{Deserialize, Serialize};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::sysvar;
+
+use std::str::FromStr;
+
+use crate::amount::bps_ceil;
+use crate::constants::TOKEN_PROGRAM_ID;
+
+/// `LendingInstruction::FlashBorrowReserveLiquidity`
+const FLASH_BORROW_TAG: u8 = 19;
+/// `LendingInstruction::FlashRepayReserveLiquidity`
+const FLASH_REPAY_TAG: u8 = 20;
+
+/// Lending reserve the start mint is borrowed from
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FlashLoanConfig {
+    /// Use flash loans instead of the wallet balance
+    #[serde(default)]
+    pub enabled: bool,
+    pub lending_program: String,
+    pub lending_market: String,
+    /// Reserve of the start mint
+    pub reserve: String,
+    /// Token account holding the reserve's liquidity
+    pub reserve_liquidity_supply: String,
+    /// Token account the flash-loan fee is paid to
+    pub reserve_fee_receiver: String,
+    /// Optional host fee receiver (defaults to the reserve fee receiver)
+    #[serde(default)]
+    pub host_fee_receiver: Option<String>,
+    /// Flash-loan fee in bps of the borrowed amount
+    pub fee_bps: u64,
+    /// Largest amount (scaled) borrowed for a single cycle
+    pub max_borrow: u128,
+}
+
+impl FlashLoanConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        let keys = [
+            ("lending_program", Some(&self.lending_program)),
+            ("lending_market", Some(&self.lending_market)),
+            ("reserve", Some(&self.reserve)),
+            ("reserve_liquidity_supply", Some(&self.reserve_liquidity_supply)),
+            ("reserve_fee_receiver", Some(&self.reserve_fee_receiver)),
+            ("host_fee_receiver", self.host_fee_receiver.as_ref()),
+        ];
+        for (name, key) in keys {
+            if let Some(key) = key {
+                if Pubkey::from_str(key).is_err() {
+                    problems.push(format!("flash_loan.{} {} is not a valid pubkey", name, key));
+                }
+            }
+        }
+        if self.fee_bps >= 10_000 {
+            problems.push(format!("flash_loan.fee_bps must be < 10000, got {}", self.fee_bps));
+        }
+        if self.max_borrow == 0 || self.max_borrow > u64::MAX as u128 {
+            problems.push("flash_loan.max_borrow must be in (0, u64::MAX]".to_string());
+        }
+        problems
+    }
+}
+
+/// Builds the borrow / repay instruction pair (see `FlashLoanConfig`)
+#[derive(Debug, Clone)]
+pub struct FlashLoan {
+    pub lending_program: Pubkey,
+    pub lending_market: Pubkey,
+    pub reserve: Pubkey,
+    pub reserve_liquidity_supply: Pubkey,
+    pub reserve_fee_receiver: Pubkey,
+    pub host_fee_receiver: Pubkey,
+    pub fee_bps: u64,
+    pub max_borrow: u128,
+}
+
+impl FlashLoan {
+    /// Call `FlashLoanConfig::problems` first, invalid keys panic
+    pub fn from_config(config: &FlashLoanConfig) -> Self {
+        let key = |s: &str| Pubkey::from_str(s).unwrap();
+        let reserve_fee_receiver = key(&config.reserve_fee_receiver);
+        Self {
+            lending_program: key(&config.lending_program),
+            lending_market: key(&config.lending_market),
+            reserve: key(&config.reserve),
+            reserve_liquidity_supply: key(&config.reserve_liquidity_supply),
+            reserve_fee_receiver,
+            host_fee_receiver: config
+                .host_fee_receiver
+                .as_deref()
+                .map(key)
+                .unwrap_or(reserve_fee_receiver),
+            fee_bps: config.fee_bps,
+            max_borrow: config.max_borrow,
+        }
+    }
+
+    /// Fee charged for borrowing `amount` (rounded up, like the lending program)
+    pub fn fee(&self, amount: u128) -> u128 {
+        bps_ceil(amount, self.fee_bps)
+    }
+
+    fn lending_market_authority(&self) -> Pubkey {
+        let (authority, _) =
+            Pubkey::find_program_address(&[self.lending_market.as_ref()], &self.lending_program);
+        authority
+    }
+
+    /// Borrows `amount` into `destination` (the owner's start-mint ATA)
+    pub fn borrow_ix(&self, amount: u64, destination: &Pubkey) -> Instruction {
+        let mut data = vec![FLASH_BORROW_TAG];
+        data.extend_from_slice(&amount.to_le_bytes());
+        Instruction {
+            program_id: self.lending_program,
+            accounts: vec![
+                AccountMeta::new(self.reserve_liquidity_supply, false),
+                AccountMeta::new(*destination, false),
+                AccountMeta::new(self.reserve, false),
+                AccountMeta::new_readonly(self.lending_market, false),
+                AccountMeta::new_readonly(self.lending_market_authority(), false),
+                AccountMeta::new_readonly(sysvar::instructions::id(), false),
+                AccountMeta::new_readonly(*TOKEN_PROGRAM_ID, false),
+            ],
+            data,
+        }
+    }
+
+    /// Repays `amount` (the fee is added by the lending program) from `source`
+    ///
+    /// # Arguments
+    /// * `amount` - Amount that was borrowed
+    /// * `borrow_ix_index` - Index of the borrow instruction in the transaction
+    /// * `source` - Owner's start-mint ATA
+    /// * `owner` - Owner signing the transfer
+    pub fn repay_ix(&self, amount: u64, borrow_ix_index: u8, source: &Pubkey, owner: &Pubkey) -> Instruction {
+        let mut data = vec![FLASH_REPAY_TAG];
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.push(borrow_ix_index);
+        Instruction {
+            program_id: self.lending_program,
+            accounts: vec![
+                AccountMeta::new(*source, false),
+                AccountMeta::new(self.reserve_liquidity_supply, false),
+                AccountMeta::new(self.reserve_fee_receiver, false),
+                AccountMeta::new(self.host_fee_receiver, false),
+                AccountMeta::new(self.reserve, false),
+                AccountMeta::new_readonly(self.lending_market, false),
+                AccountMeta::new_readonly(*owner, true),
+                AccountMeta::new_readonly(sysvar::instructions::id(), false),
+                AccountMeta::new_readonly(*TOKEN_PROGRAM_ID, false),
+            ],
+            data,
+        }
+    }
+
+    /// Wraps the cycle's instructions in a borrow / repay pair
+    pub fn wrap(&self, amount: u64, ixs: Vec<Instruction>, ata: &Pubkey, owner: &Pubkey) -> Vec<Instruction> {
+        let mut wrapped = Vec::with_capacity(ixs.len() + 2);
+        wrapped.push(self.borrow_ix(amount, ata));
+        wrapped.extend(ixs);
+        wrapped.push(self.repay_ix(amount, 0, ata, owner));
+        wrapped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flash_loan() -> FlashLoan {
+        FlashLoan {
+            lending_program: Pubkey::new_unique(),
+            lending_market: Pubkey::new_unique(),
+            reserve: Pubkey::new_unique(),
+            reserve_liquidity_supply: Pubkey::new_unique(),
+            reserve_fee_receiver: Pubkey::new_unique(),
+            host_fee_receiver: Pubkey::new_unique(),
+            fee_bps: 30,
+            max_borrow: 1_000_000,
+        }
+    }
+
+    #[test]
+    fn wrapped_cycle_borrows_first_and_repays_last() {
+        let fl = flash_loan();
+        let (ata, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let inner = Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: vec![],
+            data: vec![],
+        };
+        let ixs = fl.wrap(500, vec![inner.clone(), inner], &ata, &owner);
+        assert_eq!(ixs.len(), 4);
+        assert_eq!(ixs[0].data, [&[FLASH_BORROW_TAG][..], &500u64.to_le_bytes()].concat());
+        assert_eq!(ixs[3].data, [&[FLASH_REPAY_TAG][..], &500u64.to_le_bytes(), &[0]].concat());
+        assert!(ixs[3].accounts.iter().any(|a| a.pubkey == owner && a.is_signer));
+    }
+
+    #[test]
+    fn fee_rounds_up() {
+        let fl = flash_loan();
+        assert_eq!(fl.fee(10_000), 30);
+        assert_eq!(fl.fee(1), 1);
+        assert_eq!(fl.fee(0), 0);
+    }
+}
@@ -0,0 +1,318 @@
+//! Discovery and execution instances over Redis
+//!
+//! With `fleet` set the bot runs as one role of a fleet sharing a Redis
+//! server at `fleet.url`:
+//!
+//! * `discover` searches as usual but sends nothing: the opportunities of
+//!   every tick are published on `fleet.channel` (as `events::OpportunityEvent`
+//!   JSON)
+//! * `execute` doesn't search: every tick takes the opportunities received
+//!   since the last one, quotes them again on its own pools and amount, and
+//!   sends those that still pay once it won their claim
+//!
+//! Execution instances (other wallets, other regions) race for the same
+//! opportunities. Before sending, an instance sets the route's claim key
+//! (`<channel>:claim:<pools>:<slot>`) with `SET NX` for `claim_ttl_secs`;
+//! only the instance that set it sends the route, so it isn't submitted
+//! twice. A claim that can't be checked (Redis down) is treated as lost.
+
+use redis::{Client, Connection};
+use serde::{Deserialize, Serialize};
+
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use tracing::{debug, info, warn};
+
+use crate::arb::{ArbOpportunity, Arbitrager};
+use crate::events::OpportunityEvent;
+
+/// Time between two attempts to subscribe again after the connection broke
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FleetRole {
+    /// Search and publish, send nothing
+    Discover,
+    /// Receive, claim and send, search nothing
+    Execute,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FleetConfig {
+    pub role: FleetRole,
+    /// Redis server, e.g. `redis://127.0.0.1:6379`
+    pub url: String,
+    /// Channel the opportunities are published on, and prefix of the claim keys
+    #[serde(default = "default_channel")]
+    pub channel: String,
+    /// How long a claim holds a route
+    #[serde(default = "default_claim_ttl_secs")]
+    pub claim_ttl_secs: u64,
+    /// Name stored in the claims this instance wins; the signer's pubkey if unset
+    #[serde(default)]
+    pub instance: Option<String>,
+    /// Longest an execution instance waits for opportunities per tick
+    #[serde(default = "default_wait_ms")]
+    pub wait_ms: u64,
+}
+
+fn default_channel() -> String {
+    "arb:opportunities".to_string()
+}
+
+fn default_claim_ttl_secs() -> u64 {
+    30
+}
+
+fn default_wait_ms() -> u64 {
+    1_000
+}
+
+impl FleetConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if Client::open(self.url.as_str()).is_err() {
+            problems.push(format!("fleet.url {} is not a redis url", self.url));
+        }
+        if self.channel.is_empty() {
+            problems.push("fleet.channel can't be empty".to_string());
+        }
+        if self.claim_ttl_secs == 0 {
+            problems.push("fleet.claim_ttl_secs must be > 0".to_string());
+        }
+        problems
+    }
+}
+
+/// Key an instance sets to send the route of `event`
+pub fn claim_key(channel: &str, event: &OpportunityEvent) -> String {
+    format!("{}:claim:{}:{}", channel, event.pools.join(","), event.slot)
+}
+
+/// Publishes the opportunities of a discovery instance
+pub struct FleetPublisher {
+    channel: String,
+    client: Client,
+    connection: Option<Connection>,
+}
+
+impl FleetPublisher {
+    pub fn new(config: &FleetConfig) -> Result<Self, String> {
+        let client = Client::open(config.url.as_str()).map_err(|e| format!("fleet: {}", e))?;
+        Ok(Self {
+            channel: config.channel.clone(),
+            client,
+            connection: None,
+        })
+    }
+
+    /// Publishes `events`; on a broken connection the rest are dropped and
+    /// the next call connects again
+    pub fn publish(&mut self, events: &[OpportunityEvent]) {
+        if events.is_empty() {
+            return;
+        }
+        if self.connection.is_none() {
+            match self.client.get_connection() {
+                Ok(connection) => self.connection = Some(connection),
+                Err(e) => {
+                    warn!("fleet: can't connect to publish {} opportunities: {}", events.len(), e);
+                    return;
+                }
+            }
+        }
+        let connection = self.connection.as_mut().unwrap();
+        for event in events {
+            let message = match serde_json::to_string(event) {
+                Ok(message) => message,
+                Err(_) => continue,
+            };
+            let published = redis::cmd("PUBLISH")
+                .arg(&self.channel)
+                .arg(message)
+                .query::<u64>(connection);
+            match published {
+                Ok(receivers) => debug!("fleet: {} published to {} instances", event.route, receivers),
+                Err(e) => {
+                    warn!("fleet: publish failed: {}", e);
+                    self.connection = None;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Receives the opportunities of the discovery instances and claims them
+pub struct FleetSubscriber {
+    config: FleetConfig,
+    instance: String,
+    client: Client,
+    connection: Option<Connection>,
+    received: Receiver<OpportunityEvent>,
+}
+
+impl FleetSubscriber {
+    /// Subscribes to `config.channel` in the background, subscribing again
+    /// whenever the connection breaks
+    pub fn spawn(config: &FleetConfig, instance: String) -> Result<Self, String> {
+        let client = Client::open(config.url.as_str()).map_err(|e| format!("fleet: {}", e))?;
+        let (sender, received) = mpsc::channel();
+        let (subscriber, channel) = (client.clone(), config.channel.clone());
+        thread::spawn(move || loop {
+            let mut connection = match subscriber.get_connection() {
+                Ok(connection) => connection,
+                Err(e) => {
+                    warn!("fleet: can't connect to subscribe: {}", e);
+                    thread::sleep(RESUBSCRIBE_DELAY);
+                    continue;
+                }
+            };
+            let mut pubsub = connection.as_pubsub();
+            if let Err(e) = pubsub.subscribe(&channel) {
+                warn!("fleet: can't subscribe to {}: {}", channel, e);
+                thread::sleep(RESUBSCRIBE_DELAY);
+                continue;
+            }
+            info!("fleet: subscribed to {}", channel);
+            loop {
+                let payload: String = match pubsub.get_message().and_then(|message| message.get_payload()) {
+                    Ok(payload) => payload,
+                    Err(e) => {
+                        warn!("fleet: subscription broke: {}", e);
+                        break;
+                    }
+                };
+                match serde_json::from_str::<OpportunityEvent>(&payload) {
+                    Ok(event) => {
+                        if sender.send(event).is_err() {
+                            // the subscriber is gone
+                            return;
+                        }
+                    }
+                    Err(e) => debug!("fleet: ignoring a message that isn't an opportunity: {}", e),
+                }
+            }
+            thread::sleep(RESUBSCRIBE_DELAY);
+        });
+        Ok(Self {
+            config: config.clone(),
+            instance,
+            client,
+            connection: None,
+            received,
+        })
+    }
+
+    /// The opportunities received since the last call, waiting up to
+    /// `wait_ms` for the first one
+    pub fn receive(&self) -> Vec<OpportunityEvent> {
+        let first = match self.received.recv_timeout(Duration::from_millis(self.config.wait_ms)) {
+            Ok(event) => event,
+            Err(_) => return vec![],
+        };
+        let mut events = vec![first];
+        events.extend(self.received.try_iter());
+        events
+    }
+
+    /// Whether this instance won the claim of `event`'s route
+    pub fn claim(&mut self, event: &OpportunityEvent) -> bool {
+        if self.connection.is_none() {
+            match self.client.get_connection() {
+                Ok(connection) => self.connection = Some(connection),
+                Err(e) => {
+                    warn!("fleet: can't connect to claim {}: {}", event.route, e);
+                    return false;
+                }
+            }
+        }
+        let claimed = redis::cmd("SET")
+            .arg(claim_key(&self.config.channel, event))
+            .arg(&self.instance)
+            .arg("NX")
+            .arg("EX")
+            .arg(self.config.claim_ttl_secs)
+            .query::<Option<String>>(self.connection.as_mut().unwrap());
+        match claimed {
+            Ok(set) => set.is_some(),
+            Err(e) => {
+                warn!("fleet: claim of {} failed: {}", event.route, e);
+                self.connection = None;
+                false
+            }
+        }
+    }
+
+    /// The received opportunities that still pay on this instance's pools
+    /// and amount and whose claim it won, most profitable first
+    pub fn take(
+        &mut self,
+        arb: &Arbitrager,
+        start_mint_idx: usize,
+        init_balance: u128,
+        amount: u128,
+    ) -> Vec<ArbOpportunity> {
+        let events = self.receive();
+        let mut taken = vec![];
+        for event in events.iter() {
+            let requoted = arb.requote_routes(
+                [(event.mints.as_slice(), event.pools.as_slice())],
+                start_mint_idx,
+                init_balance,
+                amount,
+            );
+            let opportunity = match requoted.into_iter().next() {
+                Some(opportunity) => opportunity,
+                None => continue,
+            };
+            if self.claim(event) {
+                taken.push(opportunity);
+            } else {
+                debug!("fleet: {} was claimed by another instance", event.route);
+            }
+        }
+        if !events.is_empty() {
+            info!("fleet: {} of {} received opportunities claimed", taken.len(), events.len());
+        }
+        taken.sort_by(|a, b| b.expected_profit.cmp(&a.expected_profit));
+        taken
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn claims_are_per_route_and_slot() {
+        let config: FleetConfig =
+            serde_json::from_str(r#"{ "role": "execute", "url": "redis://127.0.0.1:6379" }"#).unwrap();
+        assert!(config.problems().is_empty());
+        assert_eq!(config.channel, "arb:opportunities");
+        let bad: FleetConfig =
+            serde_json::from_str(r#"{ "role": "discover", "url": "http://x", "claim_ttl_secs": 0 }"#).unwrap();
+        assert_eq!(bad.problems().len(), 2);
+
+        let mut event = OpportunityEvent {
+            kind: "opportunity".to_string(),
+            route: "0->1->0 via orca:a,orca:b".to_string(),
+            path: String::new(),
+            mints: vec!["A".to_string(), "B".to_string(), "A".to_string()],
+            pools: vec!["P".to_string(), "Q".to_string()],
+            amount_in: 1,
+            min_amount_out: 2,
+            expected_profit: 1,
+            slot: 100,
+            timestamp_ms: 0,
+        };
+        assert_eq!(claim_key(&config.channel, &event), "arb:opportunities:claim:P,Q:100");
+        // the same route found again later is a new claim
+        event.slot = 101;
+        assert_ne!(claim_key(&config.channel, &event), "arb:opportunities:claim:P,Q:100");
+    }
+}
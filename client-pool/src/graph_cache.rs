@@ -0,0 +1,229 @@
+//! Token graph cache
+//!
+//! Reading and parsing thousands of pool JSON files on every start is slow.
+//! After a load the mint index, the graph edges and every pool's type, mints
+//! and JSON are written to a single bincode file; the next start loads that
+//! file instead of walking the pool directories.
+//!
+//! The cache is keyed by a fingerprint of the pool directories (every JSON
+//! file's path, size and mtime), so adding, removing or editing a pool file
+//! invalidates it.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use tracing::{debug, info, warn};
+
+use crate::graph::PoolSet;
+use crate::pool::{pool_factory, PoolDir, PoolType};
+use crate::utils::read_json_dir;
+
+/// Bumped whenever the layout below changes
+const CACHE_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedPool {
+    pub tipe: PoolType,
+    /// mint indices into `token_mints`
+    pub mints: Vec<usize>,
+    pub json: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphCache {
+    pub version: u32,
+    pub fingerprint: [u8; 32],
+    pub token_mints: Vec<Pubkey>,
+    pub mint2idx: HashMap<Pubkey, usize>,
+    pub graph_edges: Vec<Vec<usize>>,
+    pub pools: Vec<CachedPool>,
+}
+
+/// Fingerprint of every pool JSON file in the given directories
+pub fn fingerprint(pool_dirs: &[PoolDir]) -> io::Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    for pool_dir in pool_dirs {
+        hasher.update(format!("{:?} {}\n", pool_dir.tipe, pool_dir.dir_path));
+        let mut paths = read_json_dir(&pool_dir.dir_path);
+        paths.sort();
+        for path in paths {
+            let meta = fs::metadata(&path)?;
+            let mtime = meta
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or(0);
+            hasher.update(format!("{} {} {}\n", path, meta.len(), mtime));
+        }
+    }
+    Ok(hasher.finalize().into())
+}
+
+impl GraphCache {
+    /// Snapshot of a freshly loaded set; `json` holds the source of every
+    /// pool in `set.pools`, in order
+    pub fn from_pool_set(set: &PoolSet, json: Vec<String>, fingerprint: [u8; 32]) -> Self {
+        let pools = set
+            .pool_types
+            .iter()
+            .zip(set.pool_mints.iter())
+            .zip(json)
+            .map(|((tipe, mints), json)| CachedPool {
+                tipe: *tipe,
+                mints: mints.clone(),
+                json,
+            })
+            .collect();
+        Self {
+            version: CACHE_VERSION,
+            fingerprint,
+            token_mints: set.token_mints.clone(),
+            mint2idx: set.mint2idx.clone(),
+            graph_edges: set
+                .graph_edges
+                .iter()
+                .map(|edges| {
+                    let mut edges: Vec<usize> = edges.iter().copied().collect();
+                    edges.sort_unstable();
+                    edges
+                })
+                .collect(),
+            pools,
+        }
+    }
+
+    /// Reads a cache file, `None` if it's missing, unreadable or of an older version
+    pub fn read(path: &str) -> Option<Self> {
+        let bytes = fs::read(path).ok()?;
+        match bincode::deserialize::<GraphCache>(&bytes) {
+            Ok(cache) if cache.version == CACHE_VERSION => Some(cache),
+            Ok(cache) => {
+                debug!("graph cache {} has version {}, ignoring it", path, cache.version);
+                None
+            }
+            Err(e) => {
+                warn!("graph cache {} is unreadable, ignoring it: {}", path, e);
+                None
+            }
+        }
+    }
+
+    pub fn write(&self, path: &str) -> io::Result<()> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let bytes = bincode::serialize(self).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        // write then rename, a crash mid-write must not leave a truncated cache
+        let tmp = format!("{}.tmp", path);
+        fs::write(&tmp, bytes)?;
+        fs::rename(&tmp, path)
+    }
+
+    /// Rebuilds the pool set, `None` if a cached pool no longer parses
+    pub fn into_pool_set(self) -> Option<PoolSet> {
+        let mut set = PoolSet::new();
+        set.token_mints = self.token_mints;
+        set.mint2idx = self.mint2idx;
+        set.graph_edges = self
+            .graph_edges
+            .into_iter()
+            .map(|edges| edges.into_iter().collect::<HashSet<usize>>())
+            .collect();
+        for cached in self.pools {
+            let pool = match pool_factory(&cached.tipe, &cached.json) {
+                Ok(pool) => pool,
+                Err(e) => {
+                    warn!("cached pool no longer parses: {}", e);
+                    return None;
+                }
+            };
+            set.pools.push(pool);
+            set.pool_types.push(cached.tipe);
+            set.pool_mints.push(cached.mints);
+            set.tradeable.push(true);
+        }
+        Some(set)
+    }
+}
+
+/// Loads the pools from `cache_path` if it matches the pool directories,
+/// otherwise from the directories, refreshing the cache
+pub fn load_cached(pool_dirs: &[PoolDir], cache_path: &str) -> PoolSet {
+    let fingerprint = match fingerprint(pool_dirs) {
+        Ok(fingerprint) => fingerprint,
+        Err(e) => {
+            warn!("could not fingerprint the pool dirs, not using the graph cache: {}", e);
+            return PoolSet::load(pool_dirs);
+        }
+    };
+    if let Some(cache) = GraphCache::read(cache_path).filter(|c| c.fingerprint == fingerprint) {
+        if let Some(set) = cache.into_pool_set() {
+            info!(
+                "Loaded {} pools / {} mints from the graph cache {}",
+                set.pools.len(),
+                set.token_mints.len(),
+                cache_path
+            );
+            return set;
+        }
+    }
+
+    let (set, json) = PoolSet::load_with_sources(pool_dirs);
+    match GraphCache::from_pool_set(&set, json, fingerprint).write(cache_path) {
+        Ok(()) => info!("Graph cache written to {}", cache_path),
+        Err(e) => warn!("Failed to write the graph cache {}: {}", cache_path, e),
+    }
+    set
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_round_trips_through_bincode() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let cache = GraphCache {
+            version: CACHE_VERSION,
+            fingerprint: [7; 32],
+            token_mints: vec![a, b],
+            mint2idx: [(a, 0), (b, 1)].into_iter().collect(),
+            graph_edges: vec![vec![1], vec![0]],
+            pools: vec![],
+        };
+        let bytes = bincode::serialize(&cache).unwrap();
+        let read: GraphCache = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(read.token_mints, vec![a, b]);
+        assert_eq!(read.mint2idx[&b], 1);
+        assert_eq!(read.graph_edges, vec![vec![1], vec![0]]);
+
+        let set = read.into_pool_set().unwrap();
+        assert_eq!(set.graph_edges[0], [1].into_iter().collect());
+    }
+
+    #[test]
+    fn fingerprint_changes_with_the_pool_files() {
+        let dir = std::env::temp_dir().join(format!("graph-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let pool_dirs = vec![PoolDir {
+            tipe: PoolType::OrcaPoolType,
+            dir_path: dir.to_str().unwrap().to_string(),
+        }];
+
+        let empty = fingerprint(&pool_dirs).unwrap();
+        fs::write(dir.join("pool.json"), "{}").unwrap();
+        let one = fingerprint(&pool_dirs).unwrap();
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+        assert_ne!(empty, one);
+        assert_eq!(one, fingerprint(&pool_dirs).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
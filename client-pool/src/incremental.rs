@@ -0,0 +1,302 @@
+//! Incremental re-search
+//!
+//! Without it the pools are read once before the loop starts and every tick
+//! searches the whole graph. With `incremental` set, every tick fetches the
+//! accounts the graph's pools read again and compares them with the last
+//! copy. A changed account only moves the quotes of the pools reading it, so
+//! the cycles a full search visited are kept in a `CycleIndex` (account ->
+//! pools reading it -> cycles through them): the pools of the changed
+//! accounts are replaced in the graph and only the cycles through them are
+//! quoted again.
+//!
+//! Cycles whose pools didn't change aren't looked at again until the next
+//! full search, which runs every `full_search_every` ticks; it also picks up
+//! cycles a failed quote kept out of the last one.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+use serde::{Deserialize, Serialize};
+use solana_sdk::account::Account;
+
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use tracing::{debug, warn};
+
+use crate::arb::{ArbOpportunity, Arbitrager};
+use crate::graph::{AccountSource, ACCOUNTS_PER_REQUEST};
+use crate::pool::PoolOperations;
+use crate::search::Cycle;
+use crate::utils::PoolQuote;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IncrementalConfig {
+    /// Ticks between two full searches; the ones in between only quote the
+    /// cycles through pools whose accounts changed
+    #[serde(default = "default_full_search_every")]
+    pub full_search_every: u32,
+}
+
+fn default_full_search_every() -> u32 {
+    10
+}
+
+impl IncrementalConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.full_search_every == 0 {
+            problems.push("incremental.full_search_every must be > 0".to_string());
+        }
+        problems
+    }
+}
+
+/// A cycle of the last full search: mint indices, and the pool of every hop
+/// by address so it's quoted on the pool the graph holds now
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedCycle {
+    pub path: Vec<usize>,
+    pub pools: Vec<Pubkey>,
+}
+
+/// From accounts to the pools reading them, and from pools to the cycles
+/// through them
+#[derive(Debug, Default)]
+pub struct CycleIndex {
+    pools_by_account: HashMap<Pubkey, Vec<Pubkey>>,
+    cycles: Vec<CachedCycle>,
+    cycles_by_pool: HashMap<Pubkey, Vec<usize>>,
+}
+
+impl CycleIndex {
+    /// Index of the accounts `pools` read, with no cycles yet
+    pub fn new(pools: &HashMap<Pubkey, PoolQuote>) -> Self {
+        let mut pools_by_account: HashMap<Pubkey, Vec<Pubkey>> = HashMap::new();
+        for (address, pool) in pools {
+            for account in pool.0.get_update_accounts() {
+                pools_by_account.entry(account).or_default().push(*address);
+            }
+        }
+        Self {
+            pools_by_account,
+            ..Self::default()
+        }
+    }
+
+    /// Replaces the cycles with the ones of a new full search
+    pub fn set_cycles(&mut self, cycles: &[Cycle]) {
+        self.cycles = cycles
+            .iter()
+            .map(|cycle| CachedCycle {
+                path: cycle.path.clone(),
+                pools: cycle.pools.iter().map(|pool| pool.0.get_address()).collect(),
+            })
+            .collect();
+        self.cycles_by_pool.clear();
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            for pool in cycle.pools.iter() {
+                let through = self.cycles_by_pool.entry(*pool).or_default();
+                // a cycle may swap through the same pool twice
+                if through.last() != Some(&i) {
+                    through.push(i);
+                }
+            }
+        }
+    }
+
+    /// Every account a pool reads
+    pub fn accounts(&self) -> Vec<Pubkey> {
+        self.pools_by_account.keys().copied().collect()
+    }
+
+    /// Pools reading any of `accounts`
+    pub fn pools_reading(&self, accounts: &[Pubkey]) -> HashSet<Pubkey> {
+        accounts
+            .iter()
+            .filter_map(|account| self.pools_by_account.get(account))
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    /// Cycles through any of `pools`, each once, in the order the search found them
+    pub fn cycles_through(&self, pools: &HashSet<Pubkey>) -> Vec<&CachedCycle> {
+        let mut affected: Vec<usize> = pools
+            .iter()
+            .filter_map(|pool| self.cycles_by_pool.get(pool))
+            .flatten()
+            .copied()
+            .collect();
+        affected.sort_unstable();
+        affected.dedup();
+        affected.into_iter().map(|i| &self.cycles[i]).collect()
+    }
+}
+
+/// The last data of every watched account
+#[derive(Debug, Default)]
+pub struct AccountWatch {
+    accounts: HashMap<Pubkey, Account>,
+}
+
+impl AccountWatch {
+    /// Fetches `keys` and returns the ones that changed since the last poll
+    /// (all of them on the first) with the slot they were read at. Accounts
+    /// of chunks that can't be fetched, or gone missing, are kept as they were.
+    pub fn poll<S: AccountSource + ?Sized>(&mut self, rpc: &S, keys: &[Pubkey]) -> (Vec<Pubkey>, u64) {
+        let chunks: Vec<&[Pubkey]> = keys.chunks(ACCOUNTS_PER_REQUEST).collect();
+        let mut changed = vec![];
+        let mut slot = 0;
+        for (chunk, fetch) in chunks.iter().zip(rpc.get_chunks_with_slot(&chunks)) {
+            let (accounts, chunk_slot) = match fetch.result {
+                Ok(fetched) => fetched,
+                Err(e) => {
+                    warn!("incremental: could not fetch {} accounts: {}", chunk.len(), e);
+                    continue;
+                }
+            };
+            slot = slot.max(chunk_slot);
+            for (key, account) in chunk.iter().zip(accounts) {
+                let account = match account {
+                    Some(account) => account,
+                    None => continue,
+                };
+                if self.accounts.get(key).map_or(true, |old| old.data != account.data) {
+                    self.accounts.insert(*key, account);
+                    changed.push(*key);
+                }
+            }
+        }
+        (changed, slot)
+    }
+
+    pub fn get(&self, key: &Pubkey) -> Option<Account> {
+        self.accounts.get(key).cloned()
+    }
+}
+
+/// Full searches every `full_search_every` ticks, cycles through changed
+/// pools in between
+pub struct IncrementalSearch {
+    config: IncrementalConfig,
+    cluster: Cluster,
+    watch: AccountWatch,
+    index: CycleIndex,
+    /// pools updated since the last search
+    changed: HashSet<Pubkey>,
+    /// ticks since the last full search, `None` before the first
+    since_full: Option<u32>,
+}
+
+impl IncrementalSearch {
+    pub fn new(config: &IncrementalConfig, arb: &Arbitrager, cluster: Cluster) -> Self {
+        Self {
+            config: config.clone(),
+            cluster,
+            watch: AccountWatch::default(),
+            index: CycleIndex::new(&arb.pools_by_address()),
+            changed: HashSet::new(),
+            since_full: None,
+        }
+    }
+
+    /// The tick's opportunities, most profitable first: a full search when
+    /// one is due, else the cycles through the pools `refresh` updated
+    pub fn search(
+        &mut self,
+        arb: &Arbitrager,
+        start_mint_idx: usize,
+        init_balance: u128,
+        amount: u128,
+    ) -> Vec<ArbOpportunity> {
+        let changed = std::mem::take(&mut self.changed);
+        let full = self.since_full.map_or(true, |ticks| ticks + 1 >= self.config.full_search_every);
+        if full {
+            let (opportunities, cycles) =
+                arb.find_opportunities_and_cycles(start_mint_idx, init_balance, amount);
+            self.index.set_cycles(&cycles);
+            self.since_full = Some(0);
+            return opportunities;
+        }
+        self.since_full = self.since_full.map(|ticks| ticks + 1);
+        let affected = self.index.cycles_through(&changed);
+        debug!("incremental: {} pools changed, re-quoting {} cycles", changed.len(), affected.len());
+        arb.requote_cycles(
+            affected.iter().map(|cycle| (cycle.path.as_slice(), cycle.pools.as_slice())),
+            init_balance,
+            amount,
+        )
+    }
+
+    /// Polls the pools' accounts and swaps the pools reading changed ones
+    /// for copies on the new data, for the next `search`; returns how many
+    /// were swapped
+    pub fn refresh<S: AccountSource + ?Sized>(&mut self, arb: &mut Arbitrager, rpc: &S) -> usize {
+        let (changed, slot) = self.watch.poll(rpc, &self.index.accounts());
+        let stale = self.index.pools_reading(&changed);
+        if stale.is_empty() {
+            return 0;
+        }
+        let pools = arb.pools_by_address();
+        let mut updated = HashMap::new();
+        for address in stale.iter() {
+            let pool = match pools.get(address) {
+                Some(pool) => pool,
+                None => continue,
+            };
+            let mut copy = (*pool.0).clone();
+            let accounts = copy.get_update_accounts().iter().map(|key| self.watch.get(key)).collect();
+            match copy.set_update_accounts(accounts, self.cluster.clone()) {
+                Ok(()) => {
+                    copy.set_update_slot(slot);
+                    updated.insert(*address, PoolQuote::new(Rc::new(copy)));
+                }
+                Err(e) => warn!("incremental: can't update {}: {}", pool.0.get_id(), e),
+            }
+        }
+        arb.replace_pools(&updated);
+        let swapped = updated.len();
+        self.changed.extend(updated.into_keys());
+        swapped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::ScaledAmount;
+    use crate::pools::PoolVariant;
+    use crate::tests::pools::orca_pool;
+
+    #[test]
+    fn changed_accounts_lead_to_the_cycles_through_their_pools() {
+        let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let pool = |x: &Pubkey, y: &Pubkey| PoolQuote::new(Rc::new(PoolVariant::from(orca_pool(x, y, 1_000, 1_000))));
+        let (ab, ba, ac) = (pool(&a, &b), pool(&b, &a), pool(&a, &c));
+        let pools: HashMap<Pubkey, PoolQuote> =
+            [&ab, &ba, &ac].iter().map(|p| (p.0.get_address(), (*p).clone())).collect();
+        let cycle = |path: Vec<usize>, pools: Vec<PoolQuote>| Cycle {
+            path,
+            pools,
+            amount_out: ScaledAmount::zero(a),
+        };
+
+        let mut index = CycleIndex::new(&pools);
+        index.set_cycles(&[
+            cycle(vec![0, 1, 0], vec![ab.clone(), ba.clone()]),
+            cycle(vec![0, 2, 0], vec![ac.clone(), ac.clone()]),
+        ]);
+        assert_eq!(index.accounts().len(), 6);
+
+        let changed = index.pools_reading(&[ab.0.get_update_accounts()[0], Pubkey::new_unique()]);
+        assert_eq!(changed, [ab.0.get_address()].into_iter().collect());
+        let affected = index.cycles_through(&changed);
+        assert_eq!(affected.len(), 1);
+        assert_eq!(affected[0].path, vec![0, 1, 0]);
+
+        // a pool used twice still gives its cycle once
+        let twice = index.cycles_through(&[ac.0.get_address(), ba.0.get_address()].into_iter().collect());
+        assert_eq!(twice.len(), 2);
+    }
+}
@@ -0,0 +1,214 @@
+//! Jupiter quote cross-check
+//!
+//! Before a route is sent, every hop is quoted by the Jupiter quote API for
+//! the same input mint, output mint and amount. Jupiter routes through the
+//! best pools it knows, so a hop the bot quotes for more than Jupiter by more
+//! than `max_deviation_pct` points at broken curve math or stale pool data
+//! rather than a real opportunity. Such routes are logged, and rejected with
+//! `block`.
+//!
+//! Hops Jupiter can't quote (unknown mints, api errors) are left unchecked.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use reqwest::blocking::Client as HttpClient;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use std::time::Duration;
+
+use tracing::debug;
+
+use crate::oracle::Hop;
+
+/// Where to ask and how far the bot's quotes may be off
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct JupiterCheckConfig {
+    #[serde(default = "default_quote_url")]
+    pub quote_url: String,
+    /// Largest allowed excess of a hop's quote over Jupiter's (percent)
+    #[serde(default = "default_max_deviation_pct")]
+    pub max_deviation_pct: f64,
+    /// Reject routes that fail the check instead of only logging them
+    #[serde(default)]
+    pub block: bool,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_quote_url() -> String {
+    "https://quote-api.jup.ag/v6/quote".to_string()
+}
+
+fn default_max_deviation_pct() -> f64 {
+    1.0
+}
+
+fn default_timeout_ms() -> u64 {
+    1_000
+}
+
+impl JupiterCheckConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if !self.quote_url.starts_with("http://") && !self.quote_url.starts_with("https://") {
+            problems.push(format!("jupiter_check.quote_url {} is not an http(s) url", self.quote_url));
+        }
+        if self.max_deviation_pct <= 0.0 {
+            problems.push("jupiter_check.max_deviation_pct must be > 0".to_string());
+        }
+        if self.timeout_ms == 0 {
+            problems.push("jupiter_check.timeout_ms must be > 0".to_string());
+        }
+        problems
+    }
+}
+
+/// A hop quoted for more than Jupiter allows
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    pub pool: String,
+    pub mint_in: Pubkey,
+    pub mint_out: Pubkey,
+    pub quoted: u128,
+    pub jupiter: u128,
+}
+
+impl Divergence {
+    /// How much more the bot quoted, relative to Jupiter
+    pub fn excess(&self) -> f64 {
+        excess(self.quoted, self.jupiter)
+    }
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} -> {} quoted {} is {:.2}% over jupiter's {}",
+            self.pool,
+            self.mint_in,
+            self.mint_out,
+            self.quoted,
+            self.excess() * 100.0,
+            self.jupiter
+        )
+    }
+}
+
+/// Relative excess of `quoted` over `reference`
+pub fn excess(quoted: u128, reference: u128) -> f64 {
+    if reference == 0 {
+        return if quoted == 0 { 0.0 } else { f64::INFINITY };
+    }
+    quoted as f64 / reference as f64 - 1.0
+}
+
+/// Checks routes against the Jupiter quote API (see the module docs)
+pub struct JupiterCheck {
+    client: HttpClient,
+    quote_url: String,
+    max_deviation: f64,
+    /// reject diverging routes instead of only logging them
+    pub block: bool,
+}
+
+impl JupiterCheck {
+    pub fn new(config: &JupiterCheckConfig) -> Self {
+        Self {
+            client: HttpClient::builder()
+                .timeout(Duration::from_millis(config.timeout_ms))
+                .build()
+                .expect("http client"),
+            quote_url: config.quote_url.clone(),
+            max_deviation: config.max_deviation_pct / 100.0,
+            block: config.block,
+        }
+    }
+
+    /// Jupiter's output for `amount` of `mint_in`, `None` if it has no quote
+    fn quote(&self, mint_in: &Pubkey, mint_out: &Pubkey, amount: u128) -> Option<u128> {
+        let response = self
+            .client
+            .get(&self.quote_url)
+            .query(&[
+                ("inputMint", mint_in.to_string()),
+                ("outputMint", mint_out.to_string()),
+                ("amount", amount.to_string()),
+                ("slippageBps", "0".to_string()),
+            ])
+            .send()
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.json::<Value>());
+        match response {
+            Ok(response) => response["outAmount"].as_str().and_then(|out| out.parse().ok()),
+            Err(e) => {
+                debug!("jupiter quote {} -> {} failed: {}", mint_in, mint_out, e);
+                None
+            }
+        }
+    }
+
+    /// The hop quoted furthest over Jupiter's quote beyond the threshold, if any
+    pub fn check_hops(&self, hops: &[Hop], jupiter: &[Option<u128>]) -> Option<Divergence> {
+        hops.iter()
+            .zip(jupiter)
+            .filter_map(|(hop, out)| out.map(|out| (hop, out)))
+            .map(|(hop, out)| Divergence {
+                pool: hop.pool.clone(),
+                mint_in: hop.mint_in,
+                mint_out: hop.mint_out,
+                quoted: hop.amount_out,
+                jupiter: out,
+            })
+            .filter(|d| d.excess() > self.max_deviation)
+            .max_by(|a, b| a.excess().partial_cmp(&b.excess()).unwrap_or(std::cmp::Ordering::Equal))
+    }
+
+    /// Quotes every hop on Jupiter and checks it
+    pub fn check_route(&self, hops: &[Hop]) -> Option<Divergence> {
+        let jupiter: Vec<Option<u128>> = hops
+            .iter()
+            .map(|hop| self.quote(&hop.mint_in, &hop.mint_out, hop.amount_in))
+            .collect();
+        self.check_hops(hops, &jupiter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hop(amount_out: u128) -> Hop {
+        Hop {
+            pool: "Orca".to_string(),
+            mint_in: Pubkey::new_unique(),
+            mint_out: Pubkey::new_unique(),
+            amount_in: 1_000_000_000,
+            amount_out,
+            decimals_in: 9,
+            decimals_out: 6,
+        }
+    }
+
+    #[test]
+    fn only_hops_over_jupiter_diverge() {
+        let check = JupiterCheck::new(&JupiterCheckConfig {
+            quote_url: default_quote_url(),
+            max_deviation_pct: 1.0,
+            block: true,
+            timeout_ms: 1_000,
+        });
+        let hops = [hop(100_500_000), hop(90_000_000), hop(110_000_000)];
+
+        // within 1%, below jupiter, unquoted
+        assert!(check
+            .check_hops(&hops, &[Some(100_000_000), Some(100_000_000), None])
+            .is_none());
+        let divergence = check
+            .check_hops(&hops, &[Some(100_000_000), Some(100_000_000), Some(100_000_000)])
+            .unwrap();
+        assert_eq!(divergence.quoted, 110_000_000);
+        assert!((divergence.excess() - 0.1).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,334 @@
+//! Jupiter pools
+//!
+//! A Jupiter pool (`JupiterPool`, a file under the `jupiter` pool type)
+//! trades its pair through whatever route Jupiter finds. It's quoted from a
+//! ladder of Jupiter quote api answers for `ladder` whole tokens of the input
+//! mint in both directions, fetched again once older than `ttl_secs`; its
+//! swap comes from the swap-instructions api for the exact amount once a
+//! route is sent, and is kept for `ttl_secs` too.
+//!
+//! Jupiter's instruction swaps a fixed amount and doesn't pass its output on
+//! to the router's next swap, so a Jupiter hop may only close a route (the
+//! profit guard comes right after it) and routes through one aren't split.
+//! It swaps the hop's quoted input less the route's slippage.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use reqwest::blocking::Client as HttpClient;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tracing::{debug, info, warn};
+
+use crate::error::{ArbError, ArbResult};
+use crate::graph::PoolSet;
+use crate::pools::{JupiterLadders, PoolVariant};
+use crate::utils::PoolQuote;
+
+/// Jupiter api settings of the Jupiter pools
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct JupiterPoolsConfig {
+    #[serde(default = "default_quote_url")]
+    pub quote_url: String,
+    #[serde(default = "default_swap_instructions_url")]
+    pub swap_instructions_url: String,
+    /// Inputs quoted in each direction, in whole tokens of the input mint
+    #[serde(default = "default_ladder")]
+    pub ladder: Vec<f64>,
+    /// Age at which quotes and swap instructions are fetched again
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+    /// Slippage Jupiter's own swap allows, the route's profit guard still applies
+    #[serde(default = "default_slippage_bps")]
+    pub slippage_bps: u64,
+    /// Most accounts a Jupiter route may use, so it fits a legacy transaction
+    #[serde(default = "default_max_accounts")]
+    pub max_accounts: usize,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_quote_url() -> String {
+    "https://quote-api.jup.ag/v6/quote".to_string()
+}
+
+fn default_swap_instructions_url() -> String {
+    "https://quote-api.jup.ag/v6/swap-instructions".to_string()
+}
+
+fn default_ladder() -> Vec<f64> {
+    vec![1.0, 10.0, 100.0, 1_000.0, 10_000.0]
+}
+
+fn default_ttl_secs() -> u64 {
+    10
+}
+
+fn default_slippage_bps() -> u64 {
+    50
+}
+
+fn default_max_accounts() -> usize {
+    20
+}
+
+fn default_timeout_ms() -> u64 {
+    2_000
+}
+
+impl JupiterPoolsConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        for (name, url) in [
+            ("quote_url", &self.quote_url),
+            ("swap_instructions_url", &self.swap_instructions_url),
+        ] {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                problems.push(format!("jupiter_pools.{} {} is not an http(s) url", name, url));
+            }
+        }
+        if self.ladder.is_empty() || self.ladder.iter().any(|amount| amount.is_nan() || *amount <= 0.0) {
+            problems.push("jupiter_pools.ladder must list amounts > 0".to_string());
+        }
+        if self.ttl_secs == 0 {
+            problems.push("jupiter_pools.ttl_secs must be > 0".to_string());
+        }
+        if self.timeout_ms == 0 {
+            problems.push("jupiter_pools.timeout_ms must be > 0".to_string());
+        }
+        problems
+    }
+}
+
+/// Whether `pool` is a Jupiter pool, see the module docs
+pub fn is_jupiter(pool: &PoolQuote) -> bool {
+    matches!(&*pool.0, PoolVariant::Jupiter(_))
+}
+
+/// `outAmount` of a quote response
+fn out_amount(quote: &Value) -> Option<u128> {
+    quote["outAmount"].as_str()?.parse().ok()
+}
+
+/// An instruction of a swap-instructions response
+fn parse_instruction(value: &Value) -> Option<Instruction> {
+    let accounts = value["accounts"]
+        .as_array()?
+        .iter()
+        .map(|account| {
+            let pubkey = Pubkey::from_str(account["pubkey"].as_str()?).ok()?;
+            Some(AccountMeta {
+                pubkey,
+                is_signer: account["isSigner"].as_bool()?,
+                is_writable: account["isWritable"].as_bool()?,
+            })
+        })
+        .collect::<Option<Vec<AccountMeta>>>()?;
+    Some(Instruction {
+        program_id: Pubkey::from_str(value["programId"].as_str()?).ok()?,
+        accounts,
+        data: base64::decode(value["data"].as_str()?).ok()?,
+    })
+}
+
+/// The setup, swap and cleanup instructions of a swap-instructions response;
+/// its compute budget ones are left out, the executor sets its own
+fn swap_instructions(response: &Value) -> Option<Vec<Instruction>> {
+    let mut ixs = vec![];
+    for setup in response["setupInstructions"].as_array().into_iter().flatten() {
+        ixs.push(parse_instruction(setup)?);
+    }
+    ixs.push(parse_instruction(&response["swapInstruction"])?);
+    if !response["cleanupInstruction"].is_null() {
+        ixs.push(parse_instruction(&response["cleanupInstruction"])?);
+    }
+    Some(ixs)
+}
+
+/// The mints and decimals of a Jupiter pool, with its quotes
+struct JupiterPair {
+    ladders: Arc<JupiterLadders>,
+    mints: [(Pubkey, u64); 2],
+}
+
+/// Fetches the quotes and swaps of the Jupiter pools, see the module docs
+pub struct JupiterApi {
+    client: HttpClient,
+    config: JupiterPoolsConfig,
+    pairs: Vec<JupiterPair>,
+    fetched_at: Mutex<Option<Instant>>,
+    /// swap instructions by mint in, mint out, owner and amount
+    swaps: Mutex<HashMap<(Pubkey, Pubkey, Pubkey, u64), (Instant, Vec<Instruction>)>>,
+}
+
+impl JupiterApi {
+    /// Quotes the Jupiter pools of `pool_set`; the pools share their quotes
+    /// with every copy, the graph's included
+    pub fn new(config: &JupiterPoolsConfig, pool_set: &PoolSet) -> Self {
+        let pairs = pool_set
+            .pools
+            .iter()
+            .filter_map(|pool| match pool {
+                PoolVariant::Jupiter(pool) => Some(JupiterPair {
+                    ladders: pool.ladders.clone(),
+                    mints: [
+                        (pool.base_mint.0, pool.base_scale),
+                        (pool.quote_mint.0, pool.quote_scale),
+                    ],
+                }),
+                _ => None,
+            })
+            .collect();
+        Self {
+            client: HttpClient::builder()
+                .timeout(Duration::from_millis(config.timeout_ms))
+                .build()
+                .expect("http client"),
+            config: config.clone(),
+            pairs,
+            fetched_at: Mutex::new(None),
+            swaps: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn ttl(&self) -> Duration {
+        Duration::from_secs(self.config.ttl_secs)
+    }
+
+    /// Jupiter's quote of `amount` of `mint_in` into `mint_out`
+    fn quote(&self, mint_in: &Pubkey, mint_out: &Pubkey, amount: u64) -> Result<Value, reqwest::Error> {
+        self.client
+            .get(&self.config.quote_url)
+            .query(&[
+                ("inputMint", mint_in.to_string()),
+                ("outputMint", mint_out.to_string()),
+                ("amount", amount.to_string()),
+                ("slippageBps", self.config.slippage_bps.to_string()),
+                ("maxAccounts", self.config.max_accounts.to_string()),
+                ("asLegacyTransaction", "true".to_string()),
+            ])
+            .send()
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.json::<Value>())
+    }
+
+    /// Fetches the ladders of every pool once the last ones are older than
+    /// `ttl_secs`, tagged with `slot`; returns whether it did
+    pub fn refresh(&self, slot: u64) -> bool {
+        let mut fetched_at = self.fetched_at.lock().unwrap();
+        if self.pairs.is_empty() || fetched_at.map_or(false, |at| at.elapsed() < self.ttl()) {
+            return false;
+        }
+        let mut quoted = 0;
+        for pair in self.pairs.iter() {
+            for [(mint_in, scale_in), (mint_out, _)] in [pair.mints, [pair.mints[1], pair.mints[0]]] {
+                let rungs: Vec<(u128, u128)> = self
+                    .config
+                    .ladder
+                    .iter()
+                    .map(|ui| (ui * 10f64.powi(scale_in as i32)) as u64)
+                    .filter_map(|amount| match self.quote(&mint_in, &mint_out, amount) {
+                        Ok(quote) => Some((amount as u128, out_amount(&quote)?)),
+                        Err(e) => {
+                            debug!("jupiter quote {} -> {} failed: {}", mint_in, mint_out, e);
+                            None
+                        }
+                    })
+                    .collect();
+                quoted += rungs.len();
+                pair.ladders.set(&mint_in, &mint_out, rungs, slot);
+            }
+        }
+        info!("fetched {} jupiter quotes of {} pools", quoted, self.pairs.len());
+        *fetched_at = Some(Instant::now());
+        true
+    }
+
+    /// Instructions of Jupiter's swap of `amount` of `owner`'s `mint_in` into
+    /// `mint_out`, fetched or from the cache
+    pub fn swap_ixs(
+        &self,
+        owner: &Pubkey,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+        amount: u64,
+    ) -> ArbResult<Vec<Instruction>> {
+        let key = (*mint_in, *mint_out, *owner, amount);
+        let mut swaps = self.swaps.lock().unwrap();
+        let ttl = self.ttl();
+        swaps.retain(|_, (at, _)| at.elapsed() < ttl);
+        if let Some((_, ixs)) = swaps.get(&key) {
+            return Ok(ixs.clone());
+        }
+        let failed =
+            |e: String| ArbError::Instruction(format!("jupiter swap {} -> {}: {}", mint_in, mint_out, e));
+        let quote = self.quote(mint_in, mint_out, amount).map_err(|e| failed(e.to_string()))?;
+        let response = self
+            .client
+            .post(&self.config.swap_instructions_url)
+            .json(&json!({
+                "quoteResponse": quote,
+                "userPublicKey": owner.to_string(),
+                "wrapAndUnwrapSol": false,
+                "asLegacyTransaction": true,
+            }))
+            .send()
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.json::<Value>())
+            .map_err(|e| failed(e.to_string()))?;
+        let ixs = swap_instructions(&response).ok_or_else(|| {
+            warn!("unexpected jupiter swap-instructions response: {}", response);
+            failed("unexpected response".to_string())
+        })?;
+        swaps.insert(key, (Instant::now(), ixs.clone()));
+        Ok(ixs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_instructions_responses_parse() {
+        let (program, account) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let ix = json!({
+            "programId": program.to_string(),
+            "accounts": [{ "pubkey": account.to_string(), "isSigner": false, "isWritable": true }],
+            "data": base64::encode([1u8, 2, 3]),
+        });
+        let response = json!({
+            "computeBudgetInstructions": [ix],
+            "setupInstructions": [ix],
+            "swapInstruction": ix,
+            "cleanupInstruction": null,
+            "addressLookupTableAddresses": [],
+        });
+        let ixs = swap_instructions(&response).unwrap();
+        assert_eq!(ixs.len(), 2);
+        assert_eq!(ixs[1].program_id, program);
+        assert_eq!(ixs[1].accounts, vec![AccountMeta::new(account, false)]);
+        assert_eq!(ixs[1].data, vec![1, 2, 3]);
+        assert!(swap_instructions(&json!({ "swapInstruction": { "programId": "x" } })).is_none());
+    }
+
+    #[test]
+    fn ladders_quote_between_their_rungs() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let ladders = JupiterLadders::default();
+        assert!(ladders.quote(1, &a, &b).is_err());
+        // the last rung pays less than the one before and is dropped
+        ladders.set(&a, &b, vec![(1_000, 2_000), (100, 210), (2_000, 1_900)], 7);
+        assert_eq!(ladders.slot(), Some(7));
+        assert!(ladders.has_quotes(&a, &b) && !ladders.has_quotes(&b, &a));
+        assert_eq!(ladders.quote(50, &a, &b).unwrap(), 105);
+        assert_eq!(ladders.quote(550, &a, &b).unwrap(), 210 + 1_790 / 2);
+        assert!(ladders.quote(1_001, &a, &b).is_err());
+    }
+}
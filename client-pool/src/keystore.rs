@@ -0,0 +1,170 @@
+//! Encrypted keypair files
+//!
+//! A keypair file can be stored encrypted instead of as a raw JSON byte array.
+//! The encryption key is derived from a passphrase with argon2id and the
+//! keypair bytes are sealed with XChaCha20-Poly1305. The passphrase comes
+//! from an environment variable or is prompted for at startup.
+//!
+//! Encrypted files are JSON too, so `Config::load_keypair` tells them apart
+//! by content and plain keypair files keep working.
+
+use anchor_client::solana_sdk::signature::Keypair;
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Current version of the file format
+const VERSION: u32 = 1;
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+
+#[derive(Debug, Error)]
+pub enum KeystoreError {
+    #[error("key derivation failed: {0}")]
+    Kdf(String),
+    #[error("wrong passphrase or corrupted keypair file")]
+    Decrypt,
+    #[error("unsupported keypair file version {0}")]
+    Version(u32),
+    #[error("invalid keypair file: {0}")]
+    Format(String),
+    #[error("could not read passphrase: {0}")]
+    Passphrase(String),
+}
+
+/// argon2id cost parameters, stored with the file so they can be raised later
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub struct KdfParams {
+    /// Memory in KiB
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            m_cost: 64 * 1024,
+            t_cost: 3,
+            p_cost: 1,
+        }
+    }
+}
+
+/// On-disk format of an encrypted keypair
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EncryptedKeypair {
+    pub version: u32,
+    pub kdf: KdfParams,
+    /// base64
+    pub salt: String,
+    /// base64
+    pub nonce: String,
+    /// base64, the sealed 64 keypair bytes
+    pub ciphertext: String,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], params: &KdfParams) -> Result<[u8; KEY_LEN], KeystoreError> {
+    let params = Params::new(params.m_cost, params.t_cost, params.p_cost, Some(KEY_LEN))
+        .map_err(|e| KeystoreError::Kdf(e.to_string()))?;
+    let mut key = [0u8; KEY_LEN];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| KeystoreError::Kdf(e.to_string()))?;
+    Ok(key)
+}
+
+fn decode(field: &str, value: &str) -> Result<Vec<u8>, KeystoreError> {
+    base64::decode(value).map_err(|e| KeystoreError::Format(format!("{}: {}", field, e)))
+}
+
+impl EncryptedKeypair {
+    /// `true` if `contents` is an encrypted keypair file rather than a plain one
+    pub fn is_encrypted(contents: &str) -> bool {
+        serde_json::from_str::<EncryptedKeypair>(contents).is_ok()
+    }
+
+    pub fn encrypt(keypair: &Keypair, passphrase: &str, params: KdfParams) -> Result<Self, KeystoreError> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let key = derive_key(passphrase, &salt, &params)?;
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&key));
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, keypair.to_bytes().as_ref())
+            .map_err(|_| KeystoreError::Format("encryption failed".to_string()))?;
+        Ok(Self {
+            version: VERSION,
+            kdf: params,
+            salt: base64::encode(salt),
+            nonce: base64::encode(nonce),
+            ciphertext: base64::encode(ciphertext),
+        })
+    }
+
+    pub fn decrypt(&self, passphrase: &str) -> Result<Keypair, KeystoreError> {
+        if self.version != VERSION {
+            return Err(KeystoreError::Version(self.version));
+        }
+        let salt = decode("salt", &self.salt)?;
+        let nonce = decode("nonce", &self.nonce)?;
+        let ciphertext = decode("ciphertext", &self.ciphertext)?;
+        if nonce.len() != 24 {
+            return Err(KeystoreError::Format("nonce must be 24 bytes".to_string()));
+        }
+
+        let key = derive_key(passphrase, &salt, &self.kdf)?;
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&key));
+        let bytes = cipher
+            .decrypt(XNonce::from_slice(&nonce), ciphertext.as_ref())
+            .map_err(|_| KeystoreError::Decrypt)?;
+        Keypair::from_bytes(&bytes).map_err(|e| KeystoreError::Format(e.to_string()))
+    }
+}
+
+/// The passphrase from the environment variable `env` if it's set, otherwise
+/// prompted for on the terminal
+pub fn read_passphrase(env: Option<&str>, prompt: &str) -> Result<String, KeystoreError> {
+    if let Some(passphrase) = env.and_then(|var| std::env::var(var).ok()) {
+        return Ok(passphrase);
+    }
+    rpassword::prompt_password(prompt).map_err(|e| KeystoreError::Passphrase(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // cheap parameters, the defaults take a while
+    const TEST_PARAMS: KdfParams = KdfParams {
+        m_cost: 256,
+        t_cost: 1,
+        p_cost: 1,
+    };
+
+    #[test]
+    fn round_trip() {
+        let keypair = Keypair::new();
+        let encrypted = EncryptedKeypair::encrypt(&keypair, "hunter2", TEST_PARAMS).unwrap();
+        let json = serde_json::to_string(&encrypted).unwrap();
+        assert!(EncryptedKeypair::is_encrypted(&json));
+
+        let decrypted: EncryptedKeypair = serde_json::from_str(&json).unwrap();
+        assert_eq!(decrypted.decrypt("hunter2").unwrap().to_bytes(), keypair.to_bytes());
+    }
+
+    #[test]
+    fn wrong_passphrase_is_rejected() {
+        let encrypted = EncryptedKeypair::encrypt(&Keypair::new(), "hunter2", TEST_PARAMS).unwrap();
+        assert!(matches!(encrypted.decrypt("hunter3"), Err(KeystoreError::Decrypt)));
+    }
+
+    #[test]
+    fn plain_keypair_files_are_not_encrypted() {
+        let plain = serde_json::to_string(&Keypair::new().to_bytes().to_vec()).unwrap();
+        assert!(!EncryptedKeypair::is_encrypted(&plain));
+    }
+}
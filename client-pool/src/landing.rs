@@ -0,0 +1,391 @@
+//! Transaction landing analytics
+//!
+//! Every broadcast signature is handed to a background thread that polls its
+//! status until it lands, fails or expires, and records the landed slot,
+//! confirmation latency and error code. Outcomes are aggregated by the
+//! endpoint that acked first, the priority fee band and the tip band, so
+//! submission parameters can be tuned from data (`GET /landing` on the
+//! control api, and the end of the session summary).
+
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use serde::Serialize;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::TransactionError;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, warn};
+
+/// How often pending signatures are polled
+const POLL_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Most signatures `getSignatureStatuses` takes at once
+const MAX_STATUSES_PER_REQUEST: usize = 256;
+
+/// A broadcast transaction to follow
+#[derive(Debug, Clone)]
+pub struct Submission {
+    pub signature: Signature,
+    /// Endpoint that acked the transaction first
+    pub endpoint: String,
+    pub sent_at: Instant,
+    /// Slot the route was found at
+    pub sent_slot: u64,
+    /// Compute unit price (micro-lamports)
+    pub priority_fee: u64,
+    pub tip_lamports: u64,
+}
+
+/// Final status of a submission
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Landed { slot: u64, latency: Duration },
+    Failed { slot: u64, latency: Duration, error: String },
+    /// Not seen on chain before the timeout
+    Expired,
+}
+
+/// Short error code of a failed transaction, e.g. `ix 2: custom 0x1771`
+pub fn error_code(err: &TransactionError) -> String {
+    match err {
+        TransactionError::InstructionError(idx, InstructionError::Custom(code)) => {
+            format!("ix {}: custom {:#x}", idx, code)
+        }
+        TransactionError::InstructionError(idx, err) => format!("ix {}: {:?}", idx, err),
+        err => format!("{:?}", err),
+    }
+}
+
+/// Decade band of a value: `0`, `1-9`, `10-99`, `100-999`, ...
+pub fn band(value: u64) -> String {
+    if value == 0 {
+        return "0".to_string();
+    }
+    let low = 10u64.pow((value as f64).log10().floor() as u32);
+    format!("{}-{}", low, low.saturating_mul(10) - 1)
+}
+
+/// Counters of one endpoint / fee band / tip band
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LandingBucket {
+    pub submitted: u64,
+    pub landed: u64,
+    pub failed: u64,
+    pub expired: u64,
+    /// Sum over landed and failed submissions
+    pub total_latency_ms: u64,
+    pub total_slot_delay: u64,
+}
+
+impl LandingBucket {
+    fn record(&mut self, submission: &Submission, outcome: &Outcome) {
+        self.submitted += 1;
+        let (slot, latency) = match outcome {
+            Outcome::Landed { slot, latency } => {
+                self.landed += 1;
+                (*slot, *latency)
+            }
+            Outcome::Failed { slot, latency, .. } => {
+                self.failed += 1;
+                (*slot, *latency)
+            }
+            Outcome::Expired => {
+                self.expired += 1;
+                return;
+            }
+        };
+        self.total_latency_ms += latency.as_millis() as u64;
+        self.total_slot_delay += slot.saturating_sub(submission.sent_slot);
+    }
+
+    /// Share of submissions that landed successfully
+    pub fn landing_rate(&self) -> f64 {
+        if self.submitted == 0 {
+            return 0.0;
+        }
+        self.landed as f64 / self.submitted as f64
+    }
+
+    /// Mean confirmation latency of the submissions seen on chain
+    pub fn avg_latency_ms(&self) -> Option<u64> {
+        let seen = self.landed + self.failed;
+        if seen == 0 {
+            return None;
+        }
+        Some(self.total_latency_ms / seen)
+    }
+}
+
+/// Aggregated outcomes, see the module docs
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LandingReport {
+    pub by_endpoint: BTreeMap<String, LandingBucket>,
+    pub by_fee_band: BTreeMap<String, LandingBucket>,
+    pub by_tip_band: BTreeMap<String, LandingBucket>,
+    /// Failed submissions per error code
+    pub errors: BTreeMap<String, u64>,
+}
+
+impl LandingReport {
+    pub fn record(&mut self, submission: &Submission, outcome: &Outcome) {
+        self.by_endpoint
+            .entry(submission.endpoint.clone())
+            .or_default()
+            .record(submission, outcome);
+        self.by_fee_band
+            .entry(band(submission.priority_fee))
+            .or_default()
+            .record(submission, outcome);
+        self.by_tip_band
+            .entry(band(submission.tip_lamports))
+            .or_default()
+            .record(submission, outcome);
+        if let Outcome::Failed { error, .. } = outcome {
+            *self.errors.entry(error.clone()).or_insert(0) += 1;
+        }
+    }
+
+    /// Writes the report as `landing-<session start>.json` into `dir`
+    pub fn persist(&self, dir: &str, started_unix: u64) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let path = Path::new(dir).join(format!("landing-{}.json", started_unix));
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+}
+
+impl fmt::Display for LandingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sections = [
+            ("endpoint", &self.by_endpoint),
+            ("priority fee (micro-lamports/cu)", &self.by_fee_band),
+            ("tip (lamports)", &self.by_tip_band),
+        ];
+        writeln!(f, "===== landing =====")?;
+        for (name, buckets) in sections {
+            writeln!(f, "by {}:", name)?;
+            for (key, bucket) in buckets.iter() {
+                writeln!(
+                    f,
+                    "  {:>5.1}% of {:>4} ({} failed, {} expired, avg {} ms) {}",
+                    bucket.landing_rate() * 100.0,
+                    bucket.submitted,
+                    bucket.failed,
+                    bucket.expired,
+                    bucket.avg_latency_ms().map_or("-".to_string(), |ms| ms.to_string()),
+                    key
+                )?;
+            }
+        }
+        writeln!(f, "errors:")?;
+        for (code, count) in self.errors.iter() {
+            writeln!(f, "  {:>6} {}", count, code)?;
+        }
+        Ok(())
+    }
+}
+
+/// Follows submissions on a background thread, see the module docs
+pub struct LandingTracker {
+    submissions: Mutex<mpsc::Sender<Submission>>,
+    report: Arc<Mutex<LandingReport>>,
+    /// submissions without a final status yet
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl LandingTracker {
+    /// Starts the confirmation thread
+    ///
+    /// # Arguments
+    /// * `rpc_url` - Endpoint signature statuses are polled from
+    /// * `timeout` - Submissions not seen on chain within this are expired
+    /// * `commitment` - Status a submission has to reach to count as landed
+    pub fn spawn(rpc_url: &str, timeout: Duration, commitment: CommitmentConfig) -> Self {
+        let (sender, receiver) = mpsc::channel::<Submission>();
+        let report = Arc::new(Mutex::new(LandingReport::default()));
+        let client = RpcClient::new_with_commitment(rpc_url.to_string(), commitment);
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+
+        let thread_report = report.clone();
+        let thread_in_flight = in_flight.clone();
+        thread::spawn(move || {
+            let mut pending: Vec<Submission> = vec![];
+            loop {
+                match receiver.recv_timeout(POLL_INTERVAL) {
+                    Ok(submission) => pending.push(submission),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) if pending.is_empty() => return,
+                    Err(RecvTimeoutError::Disconnected) => thread::sleep(POLL_INTERVAL),
+                }
+                pending.extend(receiver.try_iter());
+                if pending.is_empty() {
+                    continue;
+                }
+                let finished = poll(&client, &mut pending, timeout);
+                let mut report = thread_report.lock().unwrap();
+                for (submission, outcome) in finished {
+                    debug!("{} -> {:?}", submission.signature, outcome);
+                    report.record(&submission, &outcome);
+                    thread_in_flight.fetch_sub(1, Ordering::Relaxed);
+                }
+            }
+        });
+
+        Self {
+            submissions: Mutex::new(sender),
+            report,
+            in_flight,
+        }
+    }
+
+    /// Follows `submission` until it lands, fails or expires
+    pub fn track(&self, submission: Submission) {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        if self.submissions.lock().unwrap().send(submission).is_err() {
+            self.in_flight.fetch_sub(1, Ordering::Relaxed);
+            warn!("landing tracker stopped, submission dropped");
+        }
+    }
+
+    /// Submissions without a final status yet
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Waits until every submission has a final status, `false` if some
+    /// are still in flight after `timeout`
+    pub fn wait_idle(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while self.in_flight() > 0 {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        true
+    }
+
+    /// Outcomes recorded so far
+    pub fn report(&self) -> LandingReport {
+        self.report.lock().unwrap().clone()
+    }
+
+    /// Shared handle to the report, for the control api
+    pub fn shared_report(&self) -> Arc<Mutex<LandingReport>> {
+        self.report.clone()
+    }
+}
+
+/// Polls the pending submissions once and removes the finished ones
+fn poll(client: &RpcClient, pending: &mut Vec<Submission>, timeout: Duration) -> Vec<(Submission, Outcome)> {
+    let mut statuses = HashMap::new();
+    for chunk in pending.chunks(MAX_STATUSES_PER_REQUEST) {
+        let signatures: Vec<Signature> = chunk.iter().map(|s| s.signature).collect();
+        match client.get_signature_statuses(&signatures) {
+            Ok(res) => {
+                for (signature, status) in signatures.into_iter().zip(res.value) {
+                    let status = status.filter(|s| s.satisfies_commitment(client.commitment()));
+                    if let Some(status) = status {
+                        statuses.insert(signature, status);
+                    }
+                }
+            }
+            Err(e) => warn!("failed to poll signature statuses: {}", e),
+        }
+    }
+
+    let mut finished = vec![];
+    let mut still_pending = vec![];
+    for submission in pending.drain(..) {
+        let latency = submission.sent_at.elapsed();
+        let outcome = match statuses.get(&submission.signature) {
+            Some(status) => match &status.err {
+                None => Outcome::Landed {
+                    slot: status.slot,
+                    latency,
+                },
+                Some(err) => Outcome::Failed {
+                    slot: status.slot,
+                    latency,
+                    error: error_code(err),
+                },
+            },
+            None if latency > timeout => Outcome::Expired,
+            None => {
+                still_pending.push(submission);
+                continue;
+            }
+        };
+        finished.push((submission, outcome));
+    }
+    *pending = still_pending;
+    finished
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn submission(endpoint: &str, tip_lamports: u64) -> Submission {
+        Submission {
+            signature: Signature::default(),
+            endpoint: endpoint.to_string(),
+            sent_at: Instant::now(),
+            sent_slot: 100,
+            priority_fee: 0,
+            tip_lamports,
+        }
+    }
+
+    #[test]
+    fn bands_are_decades() {
+        assert_eq!(band(0), "0");
+        assert_eq!(band(7), "1-9");
+        assert_eq!(band(10), "10-99");
+        assert_eq!(band(25_000), "10000-99999");
+    }
+
+    #[test]
+    fn custom_errors_are_short() {
+        let err = TransactionError::InstructionError(2, InstructionError::Custom(6001));
+        assert_eq!(error_code(&err), "ix 2: custom 0x1771");
+        assert_eq!(error_code(&TransactionError::BlockhashNotFound), "BlockhashNotFound");
+    }
+
+    #[test]
+    fn outcomes_are_aggregated_per_key() {
+        let mut report = LandingReport::default();
+        let landed = Outcome::Landed {
+            slot: 102,
+            latency: Duration::from_millis(800),
+        };
+        let failed = Outcome::Failed {
+            slot: 101,
+            latency: Duration::from_millis(400),
+            error: "ix 2: custom 0x1771".to_string(),
+        };
+        report.record(&submission("jito", 10_000), &landed);
+        report.record(&submission("jito", 50_000), &failed);
+        report.record(&submission("rpc", 0), &Outcome::Expired);
+
+        let jito = &report.by_endpoint["jito"];
+        assert_eq!((jito.submitted, jito.landed, jito.failed), (2, 1, 1));
+        assert_eq!(jito.avg_latency_ms(), Some(600));
+        assert_eq!(jito.total_slot_delay, 3);
+        assert_eq!(report.by_endpoint["rpc"].expired, 1);
+        assert_eq!(report.by_endpoint["rpc"].avg_latency_ms(), None);
+        assert_eq!(report.by_tip_band["10000-99999"].landing_rate(), 0.5);
+        assert_eq!(report.by_fee_band["0"].submitted, 3);
+        assert_eq!(report.errors["ix 2: custom 0x1771"], 1);
+    }
+}
@@ -0,0 +1,151 @@
+//! Per-stage latency
+//!
+//! Times every stage of the pipeline, from reading the pool accounts to
+//! confirming a transaction, so it's visible where the milliseconds of a
+//! tick go. Each measurement is logged at debug level with its stage and
+//! duration; the aggregates are served on `GET /latency` and printed at the
+//! end of the session.
+
+use serde::Serialize;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tracing::debug;
+
+/// A pipeline stage, in pipeline order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Stage {
+    /// `getMultipleAccounts` of the pool accounts
+    AccountFetch,
+    /// Decoding the fetched accounts into the pools
+    Deserialize,
+    /// Walking the graph and quoting, without handling the cycles it found
+    Search,
+    /// Swap, tip, priority fee and compute limit instructions
+    TxBuild,
+    Sign,
+    /// Broadcast (mainnet) or simulation (localnet)
+    Send,
+    /// Waiting for the broadcast transaction to land
+    Confirm,
+}
+
+impl Stage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::AccountFetch => "account_fetch",
+            Stage::Deserialize => "deserialize",
+            Stage::Search => "search",
+            Stage::TxBuild => "tx_build",
+            Stage::Sign => "sign",
+            Stage::Send => "send",
+            Stage::Confirm => "confirm",
+        }
+    }
+}
+
+/// Aggregated timings of one stage (microseconds)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StageStats {
+    pub count: u64,
+    pub total_us: u64,
+    pub max_us: u64,
+    pub last_us: u64,
+}
+
+impl StageStats {
+    pub fn avg_us(&self) -> Option<u64> {
+        (self.count > 0).then(|| self.total_us / self.count)
+    }
+}
+
+/// Timings of every stage, keyed by stage name in pipeline order
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LatencyReport {
+    pub stages: Vec<(&'static str, StageStats)>,
+}
+
+impl fmt::Display for LatencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "===== stage latency (ms) =====")?;
+        writeln!(f, "{:<14} {:>8} {:>10} {:>10}", "stage", "count", "avg", "max")?;
+        for (stage, stats) in self.stages.iter() {
+            writeln!(
+                f,
+                "{:<14} {:>8} {:>10.1} {:>10.1}",
+                stage,
+                stats.count,
+                stats.avg_us().unwrap_or(0) as f64 / 1000.0,
+                stats.max_us as f64 / 1000.0
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Running per-stage timings, shared between the run loop and the control api
+#[derive(Default)]
+pub struct LatencyStats {
+    stages: Mutex<BTreeMap<Stage, StageStats>>,
+}
+
+impl LatencyStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, stage: Stage, elapsed: Duration) {
+        let us = elapsed.as_micros() as u64;
+        debug!(stage = stage.name(), elapsed_us = us, "stage timing");
+        let mut stages = self.stages.lock().unwrap();
+        let stats = stages.entry(stage).or_default();
+        stats.count += 1;
+        stats.total_us += us;
+        stats.max_us = stats.max_us.max(us);
+        stats.last_us = us;
+    }
+
+    /// Runs `f` and records how long it took as `stage`
+    pub fn time<T>(&self, stage: Stage, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.record(stage, start.elapsed());
+        result
+    }
+
+    pub fn report(&self) -> LatencyReport {
+        let stages = self.stages.lock().unwrap();
+        LatencyReport {
+            stages: stages
+                .iter()
+                .map(|(stage, stats)| (stage.name(), stats.clone()))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages_are_aggregated_in_pipeline_order() {
+        let latency = LatencyStats::new();
+        latency.record(Stage::Send, Duration::from_millis(3));
+        latency.record(Stage::AccountFetch, Duration::from_millis(10));
+        latency.record(Stage::Send, Duration::from_millis(5));
+        assert_eq!(latency.time(Stage::Sign, || 7), 7);
+
+        let report = latency.report();
+        let names: Vec<_> = report.stages.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, vec!["account_fetch", "sign", "send"]);
+        let send = &report.stages[2].1;
+        assert_eq!(send.count, 2);
+        assert_eq!(send.max_us, 5_000);
+        assert_eq!(send.last_us, 5_000);
+        assert_eq!(send.avg_us(), Some(4_000));
+    }
+}
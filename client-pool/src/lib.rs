@@ -1,12 +1,70 @@
+pub mod allocator;
+pub mod analytics;
 pub mod arb;
-pub mod serialize;
-pub mod utils;
-pub mod pool_utils; 
-pub mod error; 
-pub mod pool; 
-pub mod constants; 
+pub mod blockhash;
+pub mod bundle;
+pub mod competitors;
+pub mod compute;
+pub mod config;
+pub mod config_watcher;
+pub mod graph_cache;
+pub mod control;
+pub mod cooldown;
+pub mod dedup;
+pub mod doctor;
+pub mod dust;
+pub mod events;
+pub mod executor;
+pub mod export;
+pub mod exposure;
+pub mod failure;
+pub mod fee_balance;
+pub mod fee_tiers;
+pub mod fetch;
+pub mod flash_loan;
+pub mod fleet;
+pub mod incremental;
+pub mod jupiter_check;
+pub mod jupiter_pools;
+pub mod keystore;
+pub mod landing;
+pub mod latency;
+pub mod locks;
+pub mod monitor;
+pub mod oracle;
+pub mod overrides;
+pub mod profit_sweep;
 pub mod tests;
-pub mod pools; 
+pub mod provider;
+pub mod prune;
+pub mod quarantine;
+pub mod replay;
+pub mod queue;
+pub mod rate_limit;
+pub mod requote;
+pub mod route_cache;
+pub mod rpc;
+pub mod rpc_api;
+pub mod sender;
+pub mod session;
+pub mod settle;
+pub mod shard;
+pub mod shutdown;
+pub mod signer;
+pub mod sizing;
+pub mod slippage;
+pub mod symbols;
+pub mod telemetry;
+pub mod templates;
+pub mod tip;
+pub mod token_probe;
+pub mod tokens;
+pub mod tx_size;
+pub mod universe;
+pub mod warmup;
 
-#[macro_use]
-extern crate lazy_static;
+// the quote engine lives in its own crate so other tools can embed it
+pub use solana_arb_core::{
+    amount, constants, error, graph, pool, pool_schema, pool_utils, pools, registry, search, serialize,
+    utils,
+};
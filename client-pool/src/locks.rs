@@ -0,0 +1,80 @@
+//! Write-lock conflicts between routes
+//!
+//! The runtime serializes transactions that write the same account, and the
+//! later one swaps against the state the earlier one left behind: two routes
+//! through the same vault or open orders account sent together means the
+//! second one most likely reverts on its profit check. Routes that run
+//! together (in flight at the same time, or in one bundle) therefore need
+//! disjoint write locks. Conflicting routes are sequenced: the rpc executor
+//! confirms the earlier one first, a bundle leaves the later one out.
+//!
+//! The owner's own accounts (its token accounts, the swap state and the
+//! flash loan reserve) are written by every route; they serialize, but don't
+//! change what a route quotes, so they don't count.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use solana_sdk::instruction::Instruction;
+
+use std::collections::HashSet;
+
+use crate::flash_loan::FlashLoan;
+use crate::utils::derive_token_address;
+
+/// Accounts every route of `owner` writes: the owner, its token accounts of
+/// `mints`, the router's swap state and the flash loan accounts
+pub fn own_accounts(
+    owner: &Pubkey,
+    mints: &[Pubkey],
+    program_id: &Pubkey,
+    flash_loan: Option<&FlashLoan>,
+) -> HashSet<Pubkey> {
+    let (swap_state, _) = Pubkey::find_program_address(&[b"swap_state"], program_id);
+    let mut own: HashSet<Pubkey> = mints.iter().map(|mint| derive_token_address(owner, mint)).collect();
+    own.insert(*owner);
+    own.insert(swap_state);
+    if let Some(flash_loan) = flash_loan {
+        own.extend([
+            flash_loan.lending_market,
+            flash_loan.reserve,
+            flash_loan.reserve_liquidity_supply,
+            flash_loan.reserve_fee_receiver,
+            flash_loan.host_fee_receiver,
+        ]);
+    }
+    own
+}
+
+/// Writable accounts of a route's transactions, other than `own`
+pub fn write_locks(txs: &[Vec<Instruction>], own: &HashSet<Pubkey>) -> HashSet<Pubkey> {
+    txs.iter()
+        .flatten()
+        .flat_map(|ix| ix.accounts.iter())
+        .filter(|meta| meta.is_writable && !own.contains(&meta.pubkey))
+        .map(|meta| meta.pubkey)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::instruction::AccountMeta;
+
+    #[test]
+    fn only_foreign_writable_accounts_lock() {
+        let (owner, program) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mint, vault, market) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let own = own_accounts(&owner, &[mint], &program, None);
+        let ix = Instruction {
+            program_id: program,
+            accounts: vec![
+                AccountMeta::new(derive_token_address(&owner, &mint), false),
+                AccountMeta::new(owner, true),
+                AccountMeta::new(vault, false),
+                AccountMeta::new_readonly(market, false),
+            ],
+            data: vec![],
+        };
+        let locks = write_locks(&[vec![ix]], &own);
+        assert_eq!(locks, [vault].into_iter().collect());
+    }
+}
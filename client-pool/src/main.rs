@@ -1,351 +1,1530 @@
 /**
  * Solana DEX Arbitrage Bot - Main Program
- * 
+ *
  * This module implements the main functionality of a Solana DEX arbitrage bot that:
  * 1. Monitors multiple DEX pools (Orca, Raydium, Jupiter, etc.)
  * 2. Identifies profitable arbitrage opportunities
  * 3. Executes trades across different pools
  * 4. Manages token accounts and transactions
+ *
+ * Besides `run` (the trading loop) the binary has read-only subcommands to
+ * inspect what the bot sees: `quote`, `list-pools`, `balances` and `route`,
+ * and `fetch-pools` to regenerate the pool files from chain.
  */
 
 // External crate imports for Solana client interaction
 use anchor_client::solana_client::rpc_client::RpcClient;
-use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
 use anchor_client::solana_sdk::pubkey::Pubkey;
-use anchor_client::solana_sdk::signature::read_keypair_file;
-use anchor_client::solana_sdk::signature::{Keypair, Signer};
+use anchor_client::solana_sdk::signature::{read_keypair_file, Signer};
 use anchor_client::{Client, Cluster};
 
 // Command line argument parsing and logging
-use clap::Parser;
-use log::{debug, info, warn};
+use clap::{Parser, Subcommand};
+use tracing::{field, info, info_span, warn};
 
-// HTTP client and JSON handling
-use reqwest::blocking::get;
+// JSON handling
 use serde_json::Value;
 
 // Standard library imports
 use std::collections::{HashMap, HashSet};
-use std::fmt::Debug;
 use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::str::FromStr;
-use solana_sdk::account::Account;
-use std::fs::File;
-use std::io::{self, Read};
+
+use client::allocator::{CapitalAllocator, Strategy};
+use client::amount::ScaledAmount;
+use client::analytics::EventExporter;
+use client::blockhash::BlockhashCache;
+use client::arb::Arbitrager;
+use client::competitors::scan;
+use client::compute::ComputeEstimator;
+use client::config::{Config, RuntimeParams};
+use client::config_watcher::{ConfigWatcher, ReloadOutcome};
+use client::constants::*;
+use client::dedup::DedupCache;
+use client::dust::DustSweeper;
+use client::doctor::{run_checks, OPEN_ORDERS_PATH};
+use client::events::{EventStream, OpportunityEvent};
+use client::executor::{Dispatcher, ExecutorKind};
+use client::exposure::ExposureTracker;
+use client::export::{GraphExport, GraphFormat};
+use client::fee_balance::FeeBalanceMonitor;
+use client::fee_tiers::{apply_fee_tiers, srm_holdings};
+use client::control::{spawn_server, ControlState};
+use client::cooldown::RouteCooldowns;
+use client::quarantine::{PoolFailure, PoolQuarantine, QuarantineReport};
+use client::replay::{Entry, Recorded, Recorder, Recording};
+use client::queue::InFlightLimit;
+use client::fetch::write_pools;
+use client::jupiter_check::JupiterCheck;
+use client::jupiter_pools::JupiterApi;
+use client::flash_loan::FlashLoan;
+use client::fleet::{FleetPublisher, FleetRole, FleetSubscriber};
+use client::incremental::{IncrementalConfig, IncrementalSearch};
+use client::graph::{AccountSource, PoolSet, ACCOUNTS_PER_REQUEST};
+use client::graph_cache::load_cached;
+use client::keystore::{read_passphrase, EncryptedKeypair, KdfParams};
+use client::landing::LandingTracker;
+use client::latency::{LatencyStats, Stage};
+use client::monitor::MonitorTick;
+use client::oracle::Oracle;
+use client::overrides::apply_fee_overrides;
+use client::pool::{PoolDir, PoolOperations, PoolType};
+use client::profit_sweep::ProfitSweeper;
+use client::provider::PriorityFeeEstimator;
+use client::prune::{prune, usd_mints};
+use client::route_cache::RouteCache;
+use client::symbols::TokenSymbols;
+use client::templates::SwapTemplates;
+use client::rpc::RpcPool;
+use client::sender::BroadcastSender;
+use client::tokens::load_tokens;
+use client::tx_size::LookupTables;
+use client::serialize::decoder::DECODERS;
+use client::session::SessionStats;
+use client::settle::Settler;
+use client::search::shard_first_hops;
+use client::shard::{ShardCoordinator, ShardRole, ShardWorker};
+use client::shutdown::Shutdown;
+use client::telemetry::{self, LogFormat};
+use client::sizing::PositionSizer;
+use client::slippage::SlippageTracker;
+use client::tip::TipController;
+use client::token_probe::TokenProbe;
+use client::universe::restrict;
+use client::utils::derive_token_address;
+use client::warmup::{record_open_orders, WarmupPlan, OPEN_ORDERS_SPACE, TOKEN_ACCOUNT_SPACE};
+
+/// How long a shutdown waits for sent transactions to land or expire
+const IN_FLIGHT_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// Command line arguments structure
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 pub struct Args {
-    /// Solana cluster to connect to (mainnet/localnet)
+    /// Cluster to run on: `mainnet`, `devnet`, `testnet`, `localnet` (routes
+    /// are only simulated) or any other cluster name under `clusters`
     #[clap(short, long)]
     pub cluster: String,
-    
+
+    /// RPC endpoint for reads and sends instead of the cluster's configured ones
+    #[clap(long)]
+    pub rpc_url: Option<String>,
+
+    /// WebSocket endpoint; derived from the rpc endpoint if unset
+    #[clap(long)]
+    pub ws_url: Option<String>,
+
     /// Configuration file path
     #[clap(short, long, default_value = "config.json")]
     pub config: String,
+
+    /// Log output: `json` (one object per line, with span fields) or `text`
+    #[clap(long, default_value = "json")]
+    pub log_format: LogFormat,
+
+    /// What to do (defaults to `run`)
+    #[clap(subcommand)]
+    pub command: Option<Command>,
 }
 
-/// Configuration structure for the arbitrage bot
-#[derive(Debug, Deserialize)]
-struct Config {
-    /// Transaction fee percentage
-    fee_percentage: f64,
-    /// URLs for different DEX APIs
-    dex_urls: Vec<String>,
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Search for arbitrages and execute them
+    Run,
+    /// Quote a single swap on every pool trading the pair
+    Quote {
+        mint_in: String,
+        mint_out: String,
+        /// Input amount (scaled)
+        amount: u128,
+    },
+    /// List loaded pools per DEX
+    ListPools {
+        /// Also print every pool with its mints
+        #[clap(short, long)]
+        verbose: bool,
+    },
+    /// Show the wallet's SOL balance and token balances of every pool mint
+    Balances,
+    /// Check the signer, balances, open orders and router program before a run
+    Doctor,
+    /// Quote a cycle hop by hop using the best pool per hop
+    Route {
+        /// Comma separated mints, e.g. `USDC,SOL,USDC` as pubkeys
+        cycle: String,
+        /// Input amount (scaled); defaults to the start mint's min swap amount
+        #[clap(short, long)]
+        amount: Option<u128>,
+    },
+    /// Regenerate the pool JSON files from chain
+    FetchPools {
+        /// Comma separated DEXes to fetch
+        #[clap(short, long, default_value = "orca,raydium,serum")]
+        dexes: String,
+        /// Pools are written to `<out>/<dex>/<address>.json`
+        #[clap(short, long, default_value = "../pools")]
+        out: String,
+        /// Only keep pools between these mints (comma separated pubkeys)
+        #[clap(short, long)]
+        mints: Option<String>,
+        /// Remove pool files of a DEX that weren't fetched
+        #[clap(long)]
+        prune: bool,
+    },
+    /// Time quotes on every loaded pool, statically dispatched and through
+    /// trait objects
+    BenchQuotes {
+        /// Quotes per pool and dispatch
+        #[clap(short, long, default_value = "1000")]
+        rounds: u32,
+    },
+    /// Write the pool graph (mints, and pools with their DEX, fee and
+    /// liquidity) for analysis in other tools
+    ExportGraph {
+        /// `json`, `dot` (Graphviz) or `graphml`
+        #[clap(short, long, default_value = "json")]
+        format: GraphFormat,
+        /// File the graph is written to, stdout if unset
+        #[clap(short, long)]
+        out: Option<String>,
+    },
+    /// Find competitors' arbitrages through the loaded pools in recent
+    /// blocks and report their signers, route shapes, tips and timing
+    Competitors {
+        /// Blocks scanned back from the current slot
+        #[clap(short, long, default_value = "50")]
+        slots: u64,
+        /// Print the report, with every arbitrage, as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Search a round of a recorded run (see `record`) again, offline, on
+    /// the accounts it searched on
+    Replay {
+        /// The run's recording
+        #[clap(short, long)]
+        recording: String,
+        /// Search round to replay
+        #[clap(long)]
+        round: usize,
+    },
+    /// Search continuously and print the most profitable cycles of every
+    /// tick, without building or sending a transaction
+    Monitor {
+        /// Cycles printed per tick
+        #[clap(short, long, default_value = "10")]
+        top: usize,
+        /// Milliseconds from the start of a tick to the start of the next
+        #[clap(short, long, default_value = "1000")]
+        interval_ms: u64,
+        /// Input amount (scaled); defaults to the start mint's min swap amount
+        #[clap(short, long)]
+        amount: Option<u128>,
+        /// Print every tick as a line of JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Create the owner's missing token accounts (wrapped SOL's too) and
+    /// Serum open orders accounts of the loaded pools, and report their rent
+    Warmup {
+        /// Only report what is missing and the rent it would take
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Encrypt a plain keypair file with a passphrase (doesn't need a config)
+    EncryptKeypair {
+        /// Plain JSON keypair file
+        input: String,
+        /// Where the encrypted keypair is written
+        output: String,
+    },
 }
 
-/// Fetches trending tokens from Jupiter API
-/// 
-/// Returns a vector of token addresses that are currently trending
-/// on the Birdeye platform through Jupiter's API
-fn fetch_tokens() -> Vec<String> {
-    let response: Value = get("https://tokens.jup.ag/tokens?tags=birdeye-trending")
-        .expect("Failed to fetch tokens")
-        .json()
-        .expect("Failed to parse JSON");
-
-    response.as_array()
-        .expect("Expected an array")
-        .iter()
-        .filter_map(|token| token.get("address").and_then(Value::as_str))
-        .map(String::from)
-        .collect()
+impl Command {
+    /// Whether the command needs the owner's signer; the others run without
+    /// a key (Serum markets are quoted at the base fee tier)
+    fn signs(&self) -> bool {
+        matches!(self, Command::Run | Command::Balances | Command::Doctor | Command::Warmup { .. })
+    }
 }
 
-/// Adds a pool connection to the arbitrage graph
-/// 
-/// # Arguments
-/// * `graph` - Mutable reference to the pool graph
-/// * `idx0` - Index of the first token
-/// * `idx1` - Index of the second token
-/// * `quote` - Pool quote information
-fn add_pool_to_graph<'a>(
-    graph: &mut PoolGraph,
-    idx0: PoolIndex,
-    idx1: PoolIndex,
-    quote: &PoolQuote,
-) {
-    let edges = graph
-        .0
-        .entry(idx0)
-        .or_insert_with(|| PoolEdge(HashMap::new()));
-    let quotes = edges.0.entry(idx1).or_insert_with(|| vec![]);
-    quotes.push(quote.clone());
+/// Everything the subcommands share
+struct Context {
+    args: Args,
+    config: Config,
+    cluster: Cluster,
+    rpc_pool: RpcPool,
+    /// Only loaded for the commands that sign, see `Command::signs`
+    owner: Option<Rc<dyn Signer>>,
 }
 
 /// Calculates transaction fees based on amount and percentage
-/// 
+///
 /// # Arguments
-/// * `amount` - Transaction amount in base units
-/// * `fee_percentage` - Fee percentage to apply
-/// 
+/// * `amount` - Transaction amount in base units of its mint
+/// * `fee_bps` - Fee in basis points
+///
 /// # Returns
-/// * Calculated fee amount in base units
-fn calculate_fees(amount: u128, fee_percentage: f64) -> u128 {
-    let fee = (amount as f64 * fee_percentage).round() as u128;
-    fee
+/// * Calculated fee amount in base units of the same mint, rounded up
+fn calculate_fees(amount: ScaledAmount, fee_bps: u64) -> ScaledAmount {
+    amount.fee(fee_bps)
 }
 
-/// Loads configuration from a JSON file
-/// 
-/// # Arguments
-/// * `file_path` - Path to the configuration file
-/// 
-/// # Returns
-/// * Parsed Config structure
-fn load_config(file_path: &str) -> Config {
-    let mut file = File::open(file_path).expect("Could not open config file");
-    let mut contents = String::new();
-    file.read_to_string(&mut contents).expect("Could not read config file");
-    serde_json::from_str(&contents).expect("Could not parse config JSON")
+fn parse_pubkey(s: &str) -> Pubkey {
+    Pubkey::from_str(s.trim()).unwrap_or_else(|_| {
+        eprintln!("invalid pubkey: {}", s);
+        std::process::exit(1);
+    })
+}
+
+/// Loads + validates the config, with a signer if the command `signs`
+fn load_config(args: &Args, signs: bool) -> Config {
+    let mut config = Config::load(&args.config).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    config.override_endpoints(&args.cluster, args.rpc_url.as_deref(), args.ws_url.as_deref());
+    if let Err(e) = config.validate_for(&args.cluster, signs) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    config
+}
+
+/// Loads + validates the config and connects to the cluster, loading the
+/// owner's signer if the command `signs`
+fn setup(args: Args, signs: bool) -> Context {
+    let config = load_config(&args, signs);
+    let cluster = config.cluster(&args.cluster).kind(&args.cluster);
+
+    // Set up the RPC pool used for reads
+    let rpc_urls = &config.cluster(&args.cluster).rpc_endpoints;
+    info!("Using {} rpc endpoint(s)", rpc_urls.len());
+    let rpc_pool = RpcPool::new(rpc_urls, config.commitment.reads(), &config.rate_limit);
+    rpc_pool.health_check();
+
+    // Initialize the owner's signer (keypair, remote signer or ledger)
+    let owner = signs.then(|| {
+        config.load_signer(&args.cluster).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })
+    });
+
+    Context {
+        args,
+        config,
+        cluster,
+        rpc_pool,
+        owner,
+    }
+}
+
+impl Context {
+    /// The owner's signer, for the commands that sign
+    fn owner(&self) -> &Rc<dyn Signer> {
+        self.owner.as_ref().expect("only commands that sign use the owner")
+    }
+
+    /// Pool directories and the pool type their JSON files deserialize into
+    fn pool_dirs(&self) -> Vec<PoolDir> {
+        self.config
+            .cluster_pool_dirs(&self.args.cluster)
+            .iter()
+            .filter(|dir| self.config.overrides.dex_enabled(dir.pool_type))
+            .map(|dir| PoolDir {
+                tipe: dir.pool_type,
+                dir_path: dir.dir_path.clone(),
+            })
+            .collect()
+    }
+
+    /// Reads the pool files, through the graph cache if one is configured
+    fn read_pools(&self) -> PoolSet {
+        match &self.config.graph_cache {
+            Some(cache_path) => load_cached(&self.pool_dirs(), cache_path),
+            None => PoolSet::load(&self.pool_dirs()),
+        }
+    }
+
+    /// The pools a run searches, before their accounts are read
+    fn start_pools(&self, listed: Vec<Pubkey>) -> PoolSet {
+        // Listed tokens are indexed first so they keep stable indices
+        let mut pool_set = PoolSet::new();
+        for mint in listed {
+            pool_set.add_mint(mint);
+        }
+        info!("Extracting pool + mints...");
+        let loaded = self.read_pools();
+        for (tipe, pool) in loaded.pool_types.into_iter().zip(loaded.pools) {
+            pool_set.add_pool(tipe, pool);
+        }
+        // pools no path connects to the start mint are never on a cycle, skip fetching them
+        let unreachable = pool_set.retain_reachable(&[self.config.start_mint()]);
+        info!("Dropped {} pools unreachable from the start mint", unreachable);
+        pool_set
+    }
+
+    /// Replaces the fees and decimals of the pool files with the on-chain
+    /// ones (with `onchain_params`), then the fees with the configured
+    /// overrides, and sets the owner's fee tier on the Serum markets (the
+    /// base one without an owner); returns the SRM and MSRM the tier was
+    /// taken from
+    fn apply_params<S: AccountSource + ?Sized>(&self, pool_set: &mut PoolSet, reads: &S) -> (u64, u64) {
+        if self.config.onchain_params {
+            info!("Reading pool fees and decimals...");
+            let report = pool_set.refresh_params(reads);
+            info!(
+                "{} pools differ from their files, {} could not be read",
+                report.changed, report.failed
+            );
+        }
+        apply_fee_overrides(pool_set, &self.config.overrides);
+        let (srm_held, msrm_held) = match &self.owner {
+            Some(owner) => srm_holdings(reads, &owner.pubkey()),
+            None => (0, 0),
+        };
+        apply_fee_tiers(pool_set, srm_held, msrm_held);
+        (srm_held, msrm_held)
+    }
+
+    /// Loads every pool and fetches their current on-chain state
+    fn load_pools(&self) -> PoolSet {
+        info!("Extracting pool + mints...");
+        let mut pool_set = self.read_pools();
+        self.apply_params(&mut pool_set, &self.rpc_pool);
+        info!("Getting pool amounts...");
+        if let Err(e) = pool_set.refresh(&self.rpc_pool, &self.cluster) {
+            eprintln!("failed to fetch pool accounts: {}", e);
+            std::process::exit(1);
+        }
+        pool_set
+    }
+
+    /// Balance of the owner's ATA for `mint`, if the account exists
+    fn token_balance(&self, mint: &Pubkey) -> Option<u128> {
+        let addr = derive_token_address(&self.owner().pubkey(), mint);
+        self.rpc_pool
+            .get_multiple_accounts(&[addr])
+            .ok()
+            .and_then(|accs| accs.into_iter().next().flatten())
+            .and_then(|acc| DECODERS.token_account(&acc).ok())
+            .map(|token| token.amount as u128)
+    }
 }
 
 /// Main entry point for the arbitrage bot
 fn main() {
     // Parse command-line arguments
-    let args = Args::parse();
-    let cluster = match args.cluster.as_str() {
-        "localnet" => Cluster::Localnet,
-        "mainnet" => Cluster::Mainnet,
-        _ => panic!("invalid cluster type"),
-    };
+    let mut args = Args::parse();
+    telemetry::init(args.log_format);
+    let command = args.command.take().unwrap_or(Command::Run);
+    if let Command::EncryptKeypair { input, output } = &command {
+        encrypt_keypair(input, output);
+        return;
+    }
+    // offline, everything comes from the recording
+    if let Command::Replay { recording, round } = &command {
+        replay(&args, recording, *round);
+        return;
+    }
+    let ctx = setup(args, command.signs());
 
-    // Initialize logging
-    env_logger::init();
-    
-    // Load configuration
-    let config = load_config(&args.config);
-    
-    // Set up keypair path based on cluster
-    let owner_kp_path = match cluster {
-        Cluster::Localnet => "../../mainnet_fork/localnet_owner.key",
-        Cluster::Mainnet => {
-            "/Users/edgar/.config/solana/uwuU3qc2RwN6CpzfBAhg6wAxiEx138jy5wB3Xvx18Rw.json"
-        }
-        _ => panic!("shouldn't get here"),
-    };
+    match command {
+        Command::Run => run(ctx),
+        Command::Quote {
+            mint_in,
+            mint_out,
+            amount,
+        } => quote(ctx, &parse_pubkey(&mint_in), &parse_pubkey(&mint_out), amount),
+        Command::ListPools { verbose } => list_pools(ctx, verbose),
+        Command::Balances => balances(ctx),
+        Command::Doctor => doctor(ctx),
+        Command::Route { cycle, amount } => {
+            let mints: Vec<Pubkey> = cycle.split(',').map(parse_pubkey).collect();
+            route(ctx, &mints, amount)
+        }
+        Command::FetchPools {
+            dexes,
+            out,
+            mints,
+            prune,
+        } => fetch_pools(ctx, &dexes, &out, mints.as_deref(), prune),
+        Command::BenchQuotes { rounds } => bench_quotes(ctx, rounds),
+        Command::ExportGraph { format, out } => export_graph(ctx, format, out.as_deref()),
+        Command::Competitors { slots, json } => competitors(ctx, slots, json),
+        Command::Monitor {
+            top,
+            interval_ms,
+            amount,
+            json,
+        } => monitor(ctx, top, Duration::from_millis(interval_ms), amount, json),
+        Command::Warmup { dry_run } => warmup(ctx, dry_run),
+        Command::EncryptKeypair { .. } | Command::Replay { .. } => unreachable!("handled before setup"),
+    }
+}
 
-    // Initialize RPC connection
-    let connection_url = match cluster {
-        Cluster::Mainnet => {
-            "https://mainnet.rpc.jito.wtf/?access-token=746bee55-1b6f-4130-8347-5e1ea373333f"
+/// Fetches the pools of every DEX in `dexes` from chain and writes them under `out`
+fn fetch_pools(ctx: Context, dexes: &str, out: &str, mints: Option<&str>, prune: bool) {
+    let mints: Option<HashSet<Pubkey>> = mints.map(|m| m.split(',').map(parse_pubkey).collect());
+    for dex in dexes.split(',') {
+        let tipe: PoolType = serde_json::from_value(Value::String(dex.trim().to_string()))
+            .unwrap_or_else(|_| {
+                eprintln!("unknown dex: {}", dex);
+                std::process::exit(1);
+            });
+        let pools = match client::fetch::fetch_pools(&ctx.rpc_pool, tipe, mints.as_ref()) {
+            Ok(pools) => pools,
+            Err(e) => {
+                eprintln!("{}: {}", dex, e);
+                continue;
+            }
+        };
+        match write_pools(out, tipe, &pools, prune) {
+            Ok(dir) => println!("{:<10} {} pools written to {}", dex, pools.len(), dir),
+            Err(e) => eprintln!("{}: {}", dex, e),
         }
-        _ => cluster.url(),
+    }
+}
+
+/// Writes `input` encrypted with a prompted passphrase to `output`
+fn encrypt_keypair(input: &str, output: &str) {
+    let keypair = read_keypair_file(input).unwrap_or_else(|e| {
+        eprintln!("could not read keypair {}: {}", input, e);
+        std::process::exit(1);
+    });
+    let passphrase = read_passphrase(None, "New passphrase: ").unwrap();
+    if read_passphrase(None, "Repeat passphrase: ").unwrap() != passphrase {
+        eprintln!("passphrases don't match");
+        std::process::exit(1);
+    }
+    let encrypted = EncryptedKeypair::encrypt(&keypair, &passphrase, KdfParams::default())
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+    std::fs::write(output, serde_json::to_string_pretty(&encrypted).unwrap()).unwrap_or_else(|e| {
+        eprintln!("could not write {}: {}", output, e);
+        std::process::exit(1);
+    });
+    println!("encrypted keypair {} written to {}", keypair.pubkey(), output);
+}
+
+/// Searches for arbitrages starting and ending in the configured start mint
+fn run(ctx: Context) {
+    let Context {
+        args,
+        config,
+        cluster,
+        rpc_pool,
+        ..
+    } = &ctx;
+    let rc_owner = ctx.owner();
+    let cluster_config = config.cluster(&args.cluster);
+
+    // Pool reads, simulations and blockhashes go to the recording if one is set
+    let recorder = config.record.as_ref().map(|path| {
+        Arc::new(Recorder::create(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }))
+    });
+    let reads: Box<dyn AccountSource + '_> = match &recorder {
+        Some(recorder) => Box::new(Recorded::new(rpc_pool, recorder.clone())),
+        None => Box::new(rpc_pool),
     };
-    info!("Using connection: {}", connection_url);
 
-    // Set up RPC clients for different purposes
-    let connection = RpcClient::new_with_commitment(connection_url, CommitmentConfig::confirmed());
+    // Set up clients for sending
     let send_tx_connection =
-        RpcClient::new_with_commitment(cluster.url(), CommitmentConfig::confirmed());
-
-    // Initialize owner keypair and Anchor client
-    let owner = read_keypair_file(owner_kp_path.clone()).unwrap();
-    let rc_owner = Rc::new(owner);
+        RpcClient::new_with_commitment(rpc_pool.best().url.clone(), config.commitment.simulations());
+    let sender = BroadcastSender::new(
+        &cluster_config.send_endpoints,
+        cluster_config.jito_url.as_deref(),
+        config.commitment.sends(),
+    );
     let provider = Client::new_with_options(
-        cluster.clone(),
+        cluster_config.endpoints(),
         rc_owner.clone(),
-        CommitmentConfig::confirmed(),
+        config.commitment.sends(),
     );
     let program = provider.program(*ARB_PROGRAM_ID);
 
-    // Initialize pool directories vector
-    let mut pool_dirs = vec![];
+    let mut pool_set = ctx.start_pools(load_tokens(&config.tokens));
+    let holdings = ctx.apply_params(&mut pool_set, &*reads);
+    if let Some(recorder) = &recorder {
+        recorder.mints(&pool_set, holdings);
+    }
+    // jupiter pools only take the refresh once they were quoted
+    let jupiter = config.jupiter_pools.as_ref().map(|cfg| {
+        let jupiter = Arc::new(JupiterApi::new(cfg, &pool_set));
+        jupiter.refresh(rpc_pool.call(|client| client.get_slot()).unwrap_or(0));
+        jupiter
+    });
+    info!("Getting pool amounts...");
+    let latency = Arc::new(LatencyStats::new());
+    let quarantine = Arc::new(PoolQuarantine::new(&config.quarantine));
+    match pool_set.refresh(&*reads, cluster) {
+        Ok(report) => {
+            for (i, error) in report.decode_errors.iter() {
+                quarantine.quarantine(&pool_set.pools[*i].get_id(), PoolFailure::Deserialize, error);
+            }
+            info!(
+                "fetched {} chunks ({} retries, {} failed)",
+                report.chunks,
+                report.chunk_retries,
+                report.failed_chunks.len()
+            );
+            latency.record(Stage::AccountFetch, report.fetch_time);
+            latency.record(Stage::Deserialize, report.decode_time);
+        }
+        Err(e) => {
+            eprintln!("failed to fetch pool accounts: {}", e);
+            std::process::exit(1);
+        }
+    }
+    prune(&mut pool_set, &config.prune);
+    if let Some(universe) = &config.universe {
+        restrict(&mut pool_set, universe, &usd_mints(&config.prune), &config.start_mint());
+    }
 
-    // Add supported DEX pool directories
-    let orca_dir = PoolDir {
-        tipe: PoolType::OrcaPoolType,
-        dir_path: "../pools/orca".to_string(),
+    // Set up the configured start mint (e.g. USDC) as the starting point for arbitrage
+    let start_mint = config.start_mint();
+    let start_mint_config = config.start_mint.as_ref().unwrap();
+    let start_mint_idx = match pool_set.mint2idx.get(&start_mint) {
+        Some(idx) => *idx,
+        None => {
+            eprintln!("no loaded pool trades the start mint {}", start_mint);
+            std::process::exit(1);
+        }
     };
-    pool_dirs.push(orca_dir);
 
-    let raydium_dir = PoolDir {
-        tipe: PoolType::SaberPoolType,
-        dir_path: "../pools/raydium/".to_string(),
-    };
-    pool_dirs.push(saber_dir);
+    // Process initial token balance
+    let owner_start_addr = derive_token_address(&rc_owner.pubkey(), &start_mint);
+    let init_token_acc = rpc_pool
+        .get_multiple_accounts(&[owner_start_addr])
+        .unwrap()
+        .pop()
+        .flatten()
+        .expect("start mint token account does not exist");
+    let init_token_balance = DECODERS.token_account(&init_token_acc).unwrap().amount as u128;
+    info!(
+        "Init token acc: {:?}, balance: {:#}",
+        init_token_acc, init_token_balance
+    );
+    info!("Starting balance = {}", init_token_balance);
 
-    let jupiter_dir = PoolDir {
-        tipe: PoolType::JupiterPoolType,
-        dir_path: "../pools/jupiter/".to_string(),
+    // Initialize exchange graph for arbitrage opportunities
+    info!("Setting up exchange graph...");
+    let (pools_loaded, mints_loaded) = (pool_set.pools.len(), pool_set.token_mints.len());
+    let (token_mints, graph_edges, mut graph) = pool_set.into_graph();
+    // best rates first, `max_pools_per_edge` keeps the head of every edge
+    graph.rank_pools(&token_mints);
+
+    // Optional HTTP control api
+    let stats = Arc::new(SessionStats::new(init_token_balance));
+    // localnet transactions are only simulated, there is nothing to follow
+    let landing = (*cluster != Cluster::Localnet)
+        .then(|| LandingTracker::spawn(
+            &rpc_pool.best().url,
+            Duration::from_secs(60),
+            config.commitment.sends(),
+        ));
+    let cooldowns = Arc::new(RouteCooldowns::new(&config.cooldown));
+    let mut control_state = ControlState::new(pools_loaded, mints_loaded, stats.clone())
+        .with_cooldowns(cooldowns.clone())
+        .with_quarantine(quarantine.clone())
+        .with_latency(latency.clone());
+    if let Some(landing) = &landing {
+        control_state = control_state.with_landing(landing.shared_report());
+    }
+    let control = Arc::new(control_state);
+    if let Some(addr) = &config.control_addr {
+        if let Err(e) = spawn_server(addr, config.control_token.clone(), control.clone()) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+    let events = config.events.as_ref().map(|cfg| {
+        EventStream::spawn(cfg).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })
+    });
+    let fleet = config.fleet.as_ref();
+    let mut fleet_publisher = fleet.filter(|cfg| cfg.role == FleetRole::Discover).map(|cfg| {
+        FleetPublisher::new(cfg).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })
+    });
+    let shard_coordinator = config
+        .shard
+        .as_ref()
+        .filter(|cfg| cfg.role == ShardRole::Coordinator)
+        .map(|cfg| {
+            ShardCoordinator::spawn(cfg).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            })
+        });
+    let shard_worker = config
+        .shard
+        .as_ref()
+        .filter(|cfg| cfg.role == ShardRole::Worker)
+        .map(ShardWorker::new);
+    let mut fleet_subscriber = fleet.filter(|cfg| cfg.role == FleetRole::Execute).map(|cfg| {
+        let instance = cfg.instance.clone().unwrap_or_else(|| rc_owner.pubkey().to_string());
+        FleetSubscriber::spawn(cfg, instance).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })
+    });
+
+    let lookup_tables = LookupTables::fetch(&send_tx_connection, &config.lookup_tables);
+    let flash_loan = config
+        .flash_loan
+        .as_ref()
+        .filter(|fl| fl.enabled)
+        .map(FlashLoan::from_config);
+    // registered once loading is done, a signal before that just exits
+    let shutdown = Shutdown::register();
+    let sizer = config.sizing.as_ref().map(|cfg| Arc::new(PositionSizer::new(cfg)));
+    let slippage = config.slippage.as_ref().map(|cfg| Arc::new(SlippageTracker::new(cfg)));
+    let exposure = config.exposure.as_ref().map(|cfg| Arc::new(ExposureTracker::new(cfg)));
+    let mut arbitrager = Arbitrager {
+        token_mints,
+        graph_edges,
+        graph,
+        params: RuntimeParams::from_config(config),
+        flash_loan: flash_loan.clone(),
+        current_slot: 0,
+        shutdown: shutdown.clone(),
+        sizer: sizer.clone(),
+        slippage: slippage.clone(),
+        exposure: exposure.clone(),
+        quarantine: quarantine.clone(),
+        latency: latency.clone(),
+        first_hops: None,
     };
-    pool_dirs.push(saber_dir);
-
-    // Fetch token mints and initialize data structures
-    let mut token_mints = fetch_tokens();
-    let mut pools = vec![];
-    let mut update_pks = vec![];
-    let mut update_pks_lengths = vec![];
-    let mut all_mint_idxs = vec![];
-    let mut mint2idx = HashMap::new();
-    let mut graph_edges = vec![];
-
-    // Process pool directories and build token graph
-    info!("Extracting pool + mints...");
-    for pool_dir in pool_dirs {
-        debug!("Pool dir: {:#?}", pool_dir);
-        let pool_paths = read_json_dir(&pool_dir.dir_path);
-
-        for pool_path in pool_paths {
-            let json_str = std::fs::read_to_string(&pool_path).unwrap();
-            let pool = pool_factory(&pool_dir.tipe, &json_str);
-
-            // Validate and process pool mints
-            let pool_mints = pool.get_mints();
-            if pool_mints.len() != 2 {
-                warn!("Skipping pool with mints != 2: {:?}", pool_path);
-                continue;
-            }
+    if let Some(shard) = &config.shard {
+        let first_hops =
+            shard_first_hops(&arbitrager.graph_edges, start_mint_idx, shard.index, shard.shards);
+        info!("Searching shard {} of {}: {} first hops", shard.index, shard.shards, first_hops.len());
+        arbitrager.first_hops = Some(first_hops);
+    }
+    let mut incremental = config
+        .incremental
+        .as_ref()
+        .map(|cfg| IncrementalSearch::new(cfg, &arbitrager, cluster.clone()));
+    let mut route_cache = config.route_cache.as_ref().map(RouteCache::new);
+    let analytics = config.analytics.as_ref().map(EventExporter::spawn);
+    let templates = SwapTemplates::build(&program, &rc_owner.pubkey(), &arbitrager);
+    let executor_kind = config
+        .executor
+        .unwrap_or_else(|| ExecutorKind::default_for(cluster, config.bundle.is_some()));
+    info!("Executing routes with the {:?} executor", executor_kind);
+    let route_rpc =
+        RpcClient::new_with_commitment(rpc_pool.best().url.clone(), config.commitment.simulations());
+    let mut dispatcher = Dispatcher {
+        cluster: cluster.clone(),
+        owner: rc_owner.clone(),
+        program,
+        connection: send_tx_connection,
+        rpc: match &recorder {
+            Some(recorder) => Box::new(Recorded::new(route_rpc, recorder.clone())),
+            None => Box::new(route_rpc),
+        },
+        sender,
+        params: RuntimeParams::from_config(config),
+        stats,
+        flash_loan,
+        current_slot: 0,
+        oracle: config.oracle.as_ref().map(Oracle::from_config),
+        jupiter_check: config.jupiter_check.as_ref().map(JupiterCheck::new),
+        jupiter: jupiter.clone(),
+        requote: config.requote.clone(),
+        tips: config.tip.as_ref().map(TipController::new),
+        compute: ComputeEstimator::new(&config.compute),
+        landing,
+        priority_fees: config
+            .priority_fee
+            .as_ref()
+            .map(|cfg| PriorityFeeEstimator::new(&rpc_pool.best().url, cfg)),
+        shutdown,
+        sizer,
+        slippage,
+        exposure,
+        cooldowns,
+        quarantine,
+        latency,
+        backend: executor_kind.backend(config.bundle.as_ref()),
+        lookup_tables,
+        commitment: config.commitment,
+        blockhashes: config
+            .blockhash_cache
+            .as_ref()
+            .map(|cfg| BlockhashCache::spawn(&rpc_pool.best().url, cfg, config.commitment.sends())),
+        templates,
+        rpc_retries: config.rate_limit.max_retries,
+        in_flight: InFlightLimit::new(config.max_in_flight),
+        settler: config.settle.as_ref().map(Settler::new),
+        symbols: TokenSymbols::load(&config.tokens, rpc_pool, &arbitrager.token_mints),
+        analytics: analytics.clone(),
+    };
+    // mints that tax transfers or block sells stay out of every route
+    let token_probe = config.token_probe.as_ref().map(|cfg| {
+        let mut probe = TokenProbe::new(cfg);
+        probe.probe_new(&dispatcher, &arbitrager, &start_mint);
+        probe
+    });
+    let probed_blacklist = token_probe.as_ref().map_or_else(Vec::new, |probe| probe.blacklisted());
+    arbitrager.params.blacklisted_mints.extend(probed_blacklist.iter().copied());
+    dispatcher.params.blacklisted_mints.extend(probed_blacklist.iter().copied());
 
-            // Build token indices and graph edges
-            let mut mint_idxs = vec![];
-            for mint in pool_mints {
-                let idx;
-                if !token_mints.contains(&mint) {
-                    idx = token_mints.len();
-                    mint2idx.insert(mint, idx);
-                    token_mints.push(mint);
-                    graph_edges.push(HashSet::new());
-                } else {
-                    idx = *mint2idx.get(&mint).unwrap();
-                }
-                mint_idxs.push(idx);
+    info!("Searching for arbitrages...");
+    let min_swap_amount = start_mint_config.min_swap_amount; // scaled!
+    let mut swap_start_amount = match &arbitrager.flash_loan {
+        Some(flash_loan) => flash_loan.max_borrow, // not limited by the wallet balance
+        None => init_token_balance,
+    }; // scaled!
+    let mut dedup = DedupCache::new(&config.dedup);
+    let mut dust = config.dust.as_ref().map(DustSweeper::new);
+    let mut fee_balance = config
+        .fee_balance
+        .as_ref()
+        .map(|cfg| FeeBalanceMonitor::new(cfg, config));
+    let mut profit_sweep = config
+        .profit_sweep
+        .as_ref()
+        .map(|cfg| ProfitSweeper::new(cfg, init_token_balance));
+    let allocator = CapitalAllocator::new(init_token_balance, config.strategy_weights.clone());
+    let mut watcher = ConfigWatcher::new(&args.config, &args.cluster, config.clone())
+        .with_endpoint_overrides(args.rpc_url.clone(), args.ws_url.clone());
+
+    for round in 0..start_mint_config.search_rounds {
+        if arbitrager.shutdown.is_requested() {
+            break;
+        }
+        // pick up fee / slippage / blacklist / tip / weight changes (file edit or SIGHUP)
+        if let Some(ReloadOutcome::Applied(mut params)) = watcher.poll() {
+            // the probe's blacklist isn't in the config file
+            params.blacklisted_mints.extend(probed_blacklist.iter().copied());
+            allocator.set_weights(params.strategy_weights.clone());
+            arbitrager.params = params.clone();
+            dispatcher.params = params;
+        }
+        if let Some(update) = control.take_params_update() {
+            update.apply(&mut arbitrager.params);
+            update.apply(&mut dispatcher.params);
+        }
+        if control.is_paused() {
+            info!("Execution paused, waiting for resume...");
+            while control.is_paused() && !arbitrager.shutdown.is_requested() {
+                std::thread::sleep(Duration::from_millis(500));
             }
+        }
+        // latencies move during a run, the reads follow the fastest node
+        rpc_pool.health_check_if_due();
+        // every endpoint is throttled or down: wait it out instead of
+        // searching on reads that fail
+        if let Some(wait) = rpc_pool.throttled_for() {
+            warn!("All rpc endpoints are backing off, pausing for {:?}", wait);
+            std::thread::sleep(wait);
+        }
+        let tick_start = Instant::now();
+        let tick = info_span!(
+            "tick",
+            round,
+            amount = %swap_start_amount,
+            slot = field::Empty,
+            latency_ms = field::Empty,
+        );
+        let _tick = tick.enter();
 
-            // Update account tracking
-            let update_accounts = pool.get_update_accounts();
-            update_pks_lengths.push(update_accounts.len());
-            update_pks.push(update_accounts);
+        // slot expiry lets routes be retried once the chain moved on,
+        // and pools too far behind the chain are skipped
+        match rpc_pool.call(|client| client.get_slot()) {
+            Ok(slot) => {
+                tick.record("slot", &slot);
+                dedup.set_slot(slot);
+                arbitrager.current_slot = slot;
+                dispatcher.current_slot = slot;
+            }
+            Err(e) => warn!("Failed to fetch the current slot: {}", e),
+        }
+        if let Some(jupiter) = &jupiter {
+            jupiter.refresh(arbitrager.current_slot);
+        }
+        dedup.purge();
+        if let Some(tips) = &dispatcher.tips {
+            tips.refresh_floor();
+        }
 
-            // Add edges to the graph
-            let mint0_idx = mint_idxs[0];
-            let mint1_idx = mint_idxs[1];
+        // flash loans don't spend wallet capital, so only wallet trades reserve it
+        let reservation = if arbitrager.flash_loan.is_some() {
+            None
+        } else {
+            match allocator.reserve_up_to(Strategy::DeepSearch, swap_start_amount) {
+                Some(reservation) => Some(reservation),
+                None => {
+                    warn!("No capital available for deep search");
+                    break;
+                }
+            }
+        };
+        let amount = reservation.as_ref().map_or(swap_start_amount, |r| r.amount);
+        let scaled = ScaledAmount::new(start_mint, amount);
+        let fees = calculate_fees(scaled, arbitrager.params.fee_bps);
+        let net_amount = scaled.checked_sub(fees).expect("fees above the swap amount").amount;
 
-            if !graph_edges[mint0_idx].contains(&mint1_idx) {
-                graph_edges[mint0_idx].insert(mint1_idx);
+        if let Some(incremental) = incremental.as_mut() {
+            incremental.refresh(&mut arbitrager, &*reads);
+        }
+        if let Some(recorder) = &recorder {
+            let pads = arbitrager.slippage.as_ref().map(|slippage| slippage.pads());
+            recorder.round(round, arbitrager.current_slot, (net_amount, amount), &arbitrager.params, pads);
+        }
+        let mut opportunities = match fleet_subscriber.as_mut() {
+            // execution instances send what the discovery instances found
+            Some(subscriber) => subscriber.take(&arbitrager, start_mint_idx, net_amount, amount),
+            None => {
+                // known routes first, the search only when none of them pays
+                let cached = route_cache
+                    .as_ref()
+                    .map(|cache| cache.check(&arbitrager, start_mint_idx, net_amount, amount))
+                    .unwrap_or_default();
+                if !cached.is_empty() {
+                    info!("{} cached routes still pay, skipping the search", cached.len());
+                    cached
+                } else {
+                    match incremental.as_mut() {
+                        Some(incremental) => {
+                            incremental.search(&arbitrager, start_mint_idx, net_amount, amount)
+                        }
+                        None => arbitrager.find_opportunities(start_mint_idx, net_amount, amount),
+                    }
+                }
             }
-            if !graph_edges[mint1_idx].contains(&mint0_idx) {
-                graph_edges[mint1_idx].insert(mint0_idx);
+        };
+        // the coordinator sends the other shards' finds along with its own
+        if let Some(coordinator) = &shard_coordinator {
+            opportunities.extend(coordinator.collect(&arbitrager, start_mint_idx, net_amount, amount));
+            opportunities.sort_by(|a, b| b.expected_profit.cmp(&a.expected_profit));
+        }
+        if let Some(cache) = route_cache.as_mut() {
+            cache.record(&opportunities);
+        }
+        info!("{} opportunities found", opportunities.len());
+        if let Some(analytics) = &analytics {
+            analytics.record_opportunities(&opportunities, arbitrager.current_slot);
+        }
+        if events.is_some() || fleet_publisher.is_some() || shard_worker.is_some() {
+            let slot = arbitrager.current_slot;
+            let published: Vec<OpportunityEvent> = opportunities
+                .iter()
+                .map(|opportunity| OpportunityEvent::new(opportunity, &dispatcher.symbols, slot))
+                .collect();
+            if let Some(events) = &events {
+                events.publish(&published);
+            }
+            if let Some(publisher) = fleet_publisher.as_mut() {
+                publisher.publish(&published);
             }
+            if let Some(worker) = &shard_worker {
+                worker.report(slot, published);
+            }
+        }
+        // discovery instances and shard workers leave sending to others
+        if fleet_publisher.is_none() && shard_worker.is_none() {
+            dispatcher.execute_all(opportunities, &mut dedup);
+        }
+        // every route of the round is confirmed, nothing holds the tokens
+        if let Some(dust) = dust.as_mut().filter(|d| d.is_due()) {
+            dust.run(&dispatcher, &arbitrager, rpc_pool, &start_mint);
+        }
+        if let Some(fee_balance) = fee_balance.as_mut().filter(|m| m.is_due()) {
+            fee_balance.check(&dispatcher, &arbitrager);
+        }
+        if let Some(sweeper) = profit_sweep.as_mut().filter(|s| s.is_due()) {
+            sweeper.run(&dispatcher, &start_mint, &allocator);
+        }
+        // sends are confirmed synchronously, so the capital is free again
+        if let Some(reservation) = reservation {
+            allocator.release(reservation.id);
+        }
+        control.record_tick(tick_start.elapsed());
+        tick.record("latency_ms", &(tick_start.elapsed().as_millis() as u64));
+        info!("search round done");
 
-            pools.push(pool);
+        swap_start_amount /= 2; // half input amount and search again
+        if swap_start_amount < min_swap_amount {
+            break;
         }
     }
-    let mut update_pks = update_pks.concat();
 
-    info!("Added {:?} mints", token_mints.len());
-    info!("Added {:?} pools", pools.len());
+    if arbitrager.shutdown.is_requested() {
+        info!("Shutdown requested, no new routes are sent");
+    }
+    // let sent transactions land before the final balance is read
+    if let Some(landing) = &dispatcher.landing {
+        if !landing.wait_idle(IN_FLIGHT_TIMEOUT) {
+            warn!("{} transactions still in flight", landing.in_flight());
+        }
+    }
 
-    // Set up USDC as the starting point for arbitrage
-    let usdc_mint = Pubkey::from_str("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v").unwrap();
-    let start_mint = usdc_mint;
-    let start_mint_idx = *mint2idx.get(&start_mint).unwrap();
+    // Session summary
+    let end_balance = ctx.token_balance(&start_mint);
+    let summary = dispatcher.stats.summary(end_balance);
+    let session_dir = &watcher.current().session_dir;
+    println!("{}", summary);
+    match summary.persist(session_dir) {
+        Ok(path) => info!("Session summary written to {:?}", path),
+        Err(e) => warn!("Failed to write session summary: {}", e),
+    }
+    println!("{}", arbitrager.latency.report());
+    println!("{}", QuarantineReport(arbitrager.quarantine.quarantined()));
+    if let Some(sizer) = &arbitrager.sizer {
+        if let Err(e) = sizer.persist() {
+            warn!("Failed to write the sizing history: {}", e);
+        }
+    }
+    if let Some(slippage) = &arbitrager.slippage {
+        println!("{}", slippage.report());
+        if let Err(e) = slippage.persist() {
+            warn!("Failed to write the slippage history: {}", e);
+        }
+    }
+    if let Some(probe) = &token_probe {
+        if let Err(e) = probe.persist() {
+            warn!("Failed to write the token probe verdicts: {}", e);
+        }
+    }
+    if let Some(cache) = &route_cache {
+        if let Err(e) = cache.persist() {
+            warn!("Failed to write the route cache: {}", e);
+        }
+    }
+    if let Some(analytics) = &analytics {
+        analytics.close();
+    }
+    if let Some(landing) = &dispatcher.landing {
+        let report = landing.report();
+        println!("{}", report);
+        match report.persist(session_dir, summary.started_unix) {
+            Ok(path) => info!("Landing report written to {:?}", path),
+            Err(e) => warn!("Failed to write landing report: {}", e),
+        }
+    }
+}
 
-    // Set up owner token account
-    let owner: &Keypair = rc_owner.borrow();
-    let owner_start_addr = derive_token_address(&owner.pubkey(), &start_mint);
-    update_pks.push(owner_start_addr);
+/// Prints the quote of every pool that trades `mint_in` -> `mint_out`
+fn quote(ctx: Context, mint_in: &Pubkey, mint_out: &Pubkey, amount: u128) {
+    let pool_set = ctx.load_pools();
+    let mut found = 0;
+    for (pool, tradeable) in pool_set.pools.iter().zip(pool_set.tradeable.iter()) {
+        let mints = pool.get_mints();
+        if !mints.contains(mint_in) || !mints.contains(mint_out) {
+            continue;
+        }
+        found += 1;
+        if !tradeable {
+            println!("{:<18} cannot trade (accounts unavailable)", pool.get_id());
+            continue;
+        }
+        if !pool.can_trade(mint_in, mint_out) {
+            println!("{:<18} cannot trade (no liquidity)", pool.get_id());
+            continue;
+        }
+        match pool.get_quote_with_amounts_scaled(amount, mint_in, mint_out) {
+            Ok(out) => println!("{:<18} {} -> {}", pool.get_id(), amount, out),
+            Err(e) => println!("{:<18} cannot quote ({})", pool.get_id(), e),
+        }
+    }
+    if found == 0 {
+        println!("no pool trades {} -> {}", mint_in, mint_out);
+    }
+}
 
-    // Fetch and process account information
-    info!("Getting pool amounts...");
-    let mut update_accounts = vec![];
-    for token_addr_chunk in update_pks.chunks(99) {
-        let accounts = connection.get_multiple_accounts(token_addr_chunk).unwrap_or_else(|e| {
-            warn!("Failed to get accounts: {}", e);
-            vec![None; token_addr_chunk.len()]
-        });
-        update_accounts.push(accounts);
+/// Quotes every tradeable pool `rounds` times through `PoolVariant` and
+/// through `Box<dyn PoolOperations>` and prints the time per quote of each
+fn bench_quotes(ctx: Context, rounds: u32) {
+    let pool_set = ctx.load_pools();
+    let cases: Vec<(usize, Pubkey, Pubkey, u128)> = pool_set
+        .tradeable_pools()
+        .filter_map(|i| {
+            let pool = &pool_set.pools[i];
+            let mints = pool.get_mints();
+            let (mint_in, mint_out) = (*mints.first()?, *mints.get(1)?);
+            // a thousandth of the input reserve, so the quote walks the curve
+            let amount = pool
+                .get_reserves()
+                .and_then(|reserves| reserves.into_iter().find(|(mint, _)| *mint == mint_in))
+                .map_or(1_000_000, |(_, reserve)| (reserve / 1_000).max(1));
+            Some((i, mint_in, mint_out, amount))
+        })
+        .collect();
+    if cases.is_empty() {
+        println!("no tradeable pools loaded");
+        return;
     }
-    let mut update_accounts = update_accounts
-        .concat()
-        .into_iter()
-        .filter(|s| s.is_some())
-        .collect::<Vec<Option<Account>>>();
+    let boxed: Vec<Box<dyn PoolOperations>> = pool_set.pools.iter().map(|p| p.clone_box()).collect();
+    let rounds = rounds.max(1);
+    let quotes = cases.len() as u128 * rounds as u128;
 
-    // Process initial token balance
-    info!("Update accounts count: {:?}", update_accounts.len());
-    let init_token_acc = update_accounts.pop().unwrap().unwrap();
-    let init_token_balance = unpack_token_account(&init_token_acc.data).amount as u128;
-    info!(
-        "Init token acc: {:?}, balance: {:#}",
-        init_token_acc, init_token_balance
-    );
-    info!("Starting balance = {}", init_token_balance);
+    // the sums are printed so the quotes can't be optimized away
+    let start = Instant::now();
+    let mut sum_static = 0u128;
+    for _ in 0..rounds {
+        for (i, mint_in, mint_out, amount) in cases.iter() {
+            let pool = &pool_set.pools[*i];
+            sum_static = sum_static.wrapping_add(
+                pool.get_quote_with_amounts_scaled(*amount, mint_in, mint_out).unwrap_or(0),
+            );
+        }
+    }
+    let static_time = start.elapsed();
 
-    // Initialize exchange graph for arbitrage opportunities
-    info!("Setting up exchange graph...");
-    let mut graph = PoolGraph::new();
-    let mut pool_count = 0;
-    let mut account_ptr = 0;
+    let start = Instant::now();
+    let mut sum_dyn = 0u128;
+    for _ in 0..rounds {
+        for (i, mint_in, mint_out, amount) in cases.iter() {
+            let pool = &boxed[*i];
+            sum_dyn = sum_dyn.wrapping_add(
+                pool.get_quote_with_amounts_scaled(*amount, mint_in, mint_out).unwrap_or(0),
+            );
+        }
+    }
+    let dyn_time = start.elapsed();
 
-    for pool in pools.into_iter() {
-        let length = update_pks_lengths[pool_count];
-        let _account_slice = &update_accounts[account_ptr..account_ptr + length].to_vec();
-        account_ptr += length;
+    println!("{} pools, {} quotes per dispatch", cases.len(), quotes);
+    println!(
+        "{:<16} {:>10} ns/quote (checksum {})",
+        "enum dispatch",
+        static_time.as_nanos() / quotes,
+        sum_static
+    );
+    println!(
+        "{:<16} {:>10} ns/quote (checksum {})",
+        "dyn dispatch",
+        dyn_time.as_nanos() / quotes,
+        sum_dyn
+    );
+}
 
-        let idxs = &all_mint_idxs[pool_count * 2..(pool_count + 1) * 2].to_vec();
-        let idx0 = PoolIndex(idxs[0]);
-        let idx1 = PoolIndex(idxs[1]);
+/// Prints the number of loaded pools per DEX
+fn list_pools(ctx: Context, verbose: bool) {
+    let pool_set = ctx.read_pools();
+    let mut counts: Vec<_> = pool_set.counts_by_type().into_iter().collect();
+    counts.sort_by_key(|(tipe, _)| format!("{:?}", tipe));
+    for (tipe, count) in counts {
+        println!("{:<16} {}", format!("{:?}", tipe), count);
+    }
+    println!("{:<16} {}", "total", pool_set.pools.len());
+    println!("{:<16} {}", "mints", pool_set.token_mints.len());
 
-        let mut pool_ptr = PoolQuote::new(Rc::new(pool));
-        add_pool_to_graph(&mut graph, idx0, idx1, &mut pool_ptr.clone());
-        add_pool_to_graph(&mut graph, idx1, idx0, &mut pool_ptr);
+    if verbose {
+        for (pool, idxs) in pool_set.pools.iter().zip(pool_set.pool_mints.iter()) {
+            let mints: Vec<String> = idxs.iter().map(|idx| pool_set.token_mints[*idx].to_string()).collect();
+            println!("{:<10} {}", pool.get_id(), mints.join(" / "));
+        }
+    }
+}
+
+/// Writes the loaded pools (with their on-chain state) as a graph in `format`
+fn export_graph(ctx: Context, format: GraphFormat, out: Option<&str>) {
+    let pool_set = ctx.load_pools();
+    let symbols = TokenSymbols::load(&ctx.config.tokens, &ctx.rpc_pool, &pool_set.token_mints);
+    let export = GraphExport::from_pool_set(&pool_set, &symbols);
+    let rendered = export.render(format);
+    match out {
+        Some(path) => {
+            if let Err(e) = std::fs::write(path, rendered) {
+                eprintln!("could not write {}: {}", path, e);
+                std::process::exit(1);
+            }
+            println!("{} mints and {} pools written to {}", export.nodes.len(), export.edges.len(), path);
+        }
+        None => print!("{}", rendered),
+    }
+}
 
-        pool_count += 1;
+/// Reports the arbitrages other bots landed through the loaded pools
+fn competitors(ctx: Context, slots: u64, json: bool) {
+    let pool_set = ctx.read_pools();
+    let report = scan(&ctx.rpc_pool, &pool_set, slots).unwrap_or_else(|e| {
+        eprintln!("could not scan blocks: {}", e);
+        std::process::exit(1);
+    });
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        print!("{}", report);
     }
+}
 
+/// Searches `round` of a recording again and prints what it finds next to
+/// what the run simulated and sent in that round; needs no rpc nor key
+fn replay(args: &Args, recording: &str, round: usize) {
+    let config = load_config(args, false);
+    let cluster = config.cluster(&args.cluster).kind(&args.cluster);
+    let recording = Recording::load(recording).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let reads = match recording.replay(round) {
+        Some(reads) => reads,
+        None => {
+            eprintln!("round {} is not in the recording", round);
+            std::process::exit(1);
+        }
+    };
+    let entries = recording.round(round);
+    let (net_amount, amount, params, pads) = match entries.first() {
+        Some(Entry::Round {
+            net_amount,
+            amount,
+            params,
+            pads,
+            ..
+        }) => (*net_amount, *amount, params.clone(), pads.as_ref()),
+        _ => unreachable!("a replayed round starts at its marker"),
+    };
+    let params = params.unwrap_or_else(|| {
+        eprintln!("the parameters of round {} weren't recorded, record it again", round);
+        std::process::exit(1);
+    });
+
+    // the pools of the run, on the accounts of the round
+    let mut pool_set = recording.pool_set(round).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    if let Err(e) = pool_set.refresh(&reads, &cluster) {
+        eprintln!("failed to read the pool accounts from the recording: {}", e);
+        std::process::exit(1);
+    }
+    prune(&mut pool_set, &config.prune);
+    if let Some(universe) = &config.universe {
+        restrict(&mut pool_set, universe, &usd_mints(&config.prune), &config.start_mint());
+    }
+    let start_mint_idx = match pool_set.mint2idx.get(&config.start_mint()) {
+        Some(idx) => *idx,
+        None => {
+            eprintln!("no loaded pool trades the start mint {}", config.start_mint());
+            std::process::exit(1);
+        }
+    };
+    let (token_mints, graph_edges, mut graph) = pool_set.into_graph();
+    graph.rank_pools(&token_mints);
+    let first_hops = config
+        .shard
+        .as_ref()
+        .map(|shard| shard_first_hops(&graph_edges, start_mint_idx, shard.index, shard.shards));
     let arbitrager = Arbitrager {
         token_mints,
         graph_edges,
         graph,
+        params,
+        flash_loan: config
+            .flash_loan
+            .as_ref()
+            .filter(|fl| fl.enabled)
+            .map(FlashLoan::from_config),
+        current_slot: reads.slot,
+        shutdown: Shutdown::default(),
+        sizer: None,
+        slippage: pads.map(|pads| Arc::new(SlippageTracker::with_pads(pads))),
+        exposure: config.exposure.as_ref().map(|cfg| Arc::new(ExposureTracker::new(cfg))),
+        quarantine: Arc::new(PoolQuarantine::new(&config.quarantine)),
+        latency: Arc::new(LatencyStats::new()),
+        first_hops,
+    };
+
+    let opportunities = arbitrager.find_opportunities(start_mint_idx, net_amount, amount);
+    println!(
+        "round {} (slot {}, {} in): {} opportunities",
+        round,
+        reads.slot,
+        net_amount,
+        opportunities.len()
+    );
+    for opportunity in opportunities.iter() {
+        println!(
+            "  {}: {} -> {} (profit {})",
+            opportunity.route, opportunity.amount_in, opportunity.min_amount_out, opportunity.expected_profit
+        );
+    }
+    for entry in entries.iter() {
+        match entry {
+            Entry::Simulate { signature, result } => match result {
+                Ok((slot, res)) => match &res.err {
+                    Some(err) => println!("simulated {} at slot {}: {:?}", signature, slot, err),
+                    None => println!("simulated {} at slot {}: ok", signature, slot),
+                },
+                Err(e) => println!("simulation of {} failed: {}", signature, e),
+            },
+            Entry::Send { signature, result } => match result {
+                Ok(_) => println!("sent {}", signature),
+                Err(e) => println!("send of {} failed: {}", signature, e),
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Searches the live pools every `interval` and prints the `top` cycles of
+/// every tick, until interrupted; nothing is built or sent
+fn monitor(ctx: Context, top: usize, interval: Duration, amount: Option<u128>, json: bool) {
+    let Context {
+        config,
         cluster,
-        owner: rc_owner,
-        program,
-        connection: send_tx_connection,
+        rpc_pool,
+        ..
+    } = &ctx;
+    let mut pool_set = ctx.start_pools(load_tokens(&config.tokens));
+    ctx.apply_params(&mut pool_set, rpc_pool);
+    // jupiter pools only take the refresh once they were quoted
+    let jupiter = config.jupiter_pools.as_ref().map(|cfg| {
+        let jupiter = JupiterApi::new(cfg, &pool_set);
+        jupiter.refresh(rpc_pool.call(|client| client.get_slot()).unwrap_or(0));
+        jupiter
+    });
+    if let Err(e) = pool_set.refresh(rpc_pool, cluster) {
+        eprintln!("failed to fetch pool accounts: {}", e);
+        std::process::exit(1);
+    }
+    prune(&mut pool_set, &config.prune);
+    if let Some(universe) = &config.universe {
+        restrict(&mut pool_set, universe, &usd_mints(&config.prune), &config.start_mint());
+    }
+    let start_mint = config.start_mint();
+    let start_mint_idx = match pool_set.mint2idx.get(&start_mint) {
+        Some(idx) => *idx,
+        None => {
+            eprintln!("no loaded pool trades the start mint {}", start_mint);
+            std::process::exit(1);
+        }
     };
+    let (token_mints, graph_edges, mut graph) = pool_set.into_graph();
+    graph.rank_pools(&token_mints);
+    let first_hops = config
+        .shard
+        .as_ref()
+        .map(|shard| shard_first_hops(&graph_edges, start_mint_idx, shard.index, shard.shards));
+    let mut arbitrager = Arbitrager {
+        token_mints,
+        graph_edges,
+        graph,
+        params: RuntimeParams::from_config(config),
+        flash_loan: config
+            .flash_loan
+            .as_ref()
+            .filter(|fl| fl.enabled)
+            .map(FlashLoan::from_config),
+        current_slot: 0,
+        shutdown: Shutdown::register(),
+        sizer: None,
+        slippage: config.slippage.as_ref().map(|cfg| Arc::new(SlippageTracker::new(cfg))),
+        exposure: config.exposure.as_ref().map(|cfg| Arc::new(ExposureTracker::new(cfg))),
+        quarantine: Arc::new(PoolQuarantine::new(&config.quarantine)),
+        latency: Arc::new(LatencyStats::new()),
+        first_hops,
+    };
+    let symbols = TokenSymbols::load(&config.tokens, rpc_pool, &arbitrager.token_mints);
+    // only the changed pools are read into the graph again, but the top
+    // cycles are only right on a search of the whole graph
+    let full_every_tick = IncrementalConfig { full_search_every: 1 };
+    let mut watch = IncrementalSearch::new(&full_every_tick, &arbitrager, cluster.clone());
 
-    info!("Searching for arbitrages...");
-    let min_swap_amount = 10_u128.pow(6_u32); // scaled! -- 1 USDC
-    let mut swap_start_amount = init_token_balance; // scaled!
-    let mut sent_arbs = HashSet::new();
+    let amount = amount.unwrap_or(config.start_mint.as_ref().unwrap().min_swap_amount);
+    let scaled = ScaledAmount::new(start_mint, amount);
+    let fees = calculate_fees(scaled, arbitrager.params.fee_bps);
+    let net_amount = scaled.checked_sub(fees).expect("fees above the swap amount").amount;
+    let mut tick = 0;
+    while !arbitrager.shutdown.is_requested() {
+        rpc_pool.health_check_if_due();
+        let tick_start = Instant::now();
+        match rpc_pool.call(|client| client.get_slot()) {
+            Ok(slot) => arbitrager.current_slot = slot,
+            Err(e) => warn!("Failed to fetch the current slot: {}", e),
+        }
+        if let Some(jupiter) = &jupiter {
+            jupiter.refresh(arbitrager.current_slot);
+        }
+        let changed = watch.refresh(&mut arbitrager, rpc_pool);
+        let fetched = tick_start.elapsed();
+        let opportunities = watch.search(&arbitrager, start_mint_idx, net_amount, amount);
+        let times = (fetched, tick_start.elapsed() - fetched);
+        let slot = arbitrager.current_slot;
+        let report = MonitorTick::new(tick, slot, changed, times, &opportunities, &symbols, top);
+        if json {
+            println!("{}", serde_json::to_string(&report).unwrap());
+        } else {
+            print!("{}", report);
+        }
+        tick += 1;
+        std::thread::sleep(interval.saturating_sub(tick_start.elapsed()));
+    }
+}
 
-    for _ in 0..4 {
-        let fees = calculate_fees(swap_start_amount, config.fee_percentage);
-        let net_amount = swap_start_amount - fees;
+/// Creates the accounts the loaded pools' routes need that don't exist yet,
+/// after printing them with their rent
+fn warmup(ctx: Context, dry_run: bool) {
+    let Context {
+        args,
+        config,
+        cluster,
+        rpc_pool,
+        ..
+    } = &ctx;
+    let owner = ctx.owner();
+    let pool_set = ctx.read_pools();
+    let mut mints = pool_set.token_mints.clone();
+    if !mints.contains(&WSOL_MINT) {
+        mints.push(*WSOL_MINT);
+    }
+    let markets: Vec<Pubkey> = pool_set
+        .pools
+        .iter()
+        .zip(pool_set.pool_types.iter())
+        .filter(|(_, tipe)| **tipe == PoolType::SerumPoolType)
+        .map(|(pool, _)| pool.get_address())
+        .collect();
+    // serum only trades on localnet, open orders elsewhere would go unused
+    let markets = match *cluster == Cluster::Localnet {
+        true => markets,
+        false => vec![],
+    };
+    let book: HashMap<String, String> = std::fs::read_to_string(OPEN_ORDERS_PATH)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    let rent = |space: usize| {
+        rpc_pool
+            .call(|client| client.get_minimum_balance_for_rent_exemption(space))
+            .unwrap_or_else(|e| {
+                eprintln!("failed to fetch the rent of {} bytes: {}", space, e);
+                std::process::exit(1);
+            })
+    };
+    let rents = (rent(TOKEN_ACCOUNT_SPACE), rent(OPEN_ORDERS_SPACE));
+    let plan = WarmupPlan::new(rpc_pool, &owner.pubkey(), &mints, &markets, &book, rents)
+        .unwrap_or_else(|e| {
+            eprintln!("failed to read the owner's accounts: {}", e);
+            std::process::exit(1);
+        });
+    print!("{}", plan);
+    if dry_run || plan.creations.is_empty() {
+        return;
+    }
 
-        arbitrager.brute_force_search(
-            start_mint_idx,
-            net_amount,
-            swap_start_amount,
-            vec![start_mint_idx],
-            vec![],
-            &mut sent_arbs,
-        );
+    let cluster_config = config.cluster(&args.cluster);
+    let sender = BroadcastSender::new(
+        &cluster_config.send_endpoints,
+        cluster_config.jito_url.as_deref(),
+        config.commitment.sends(),
+    );
+    let rpc = RpcClient::new_with_commitment(rpc_pool.best().url.clone(), config.commitment.sends());
+    let landed = plan.send(&rpc, &sender, owner.as_ref(), config.commitment.sends());
+    if let Err(e) = record_open_orders(OPEN_ORDERS_PATH, &landed) {
+        eprintln!("failed to write {}: {}", OPEN_ORDERS_PATH, e);
+    }
+    println!("created {} of {} accounts", landed.len(), plan.creations.len());
+    if landed.len() < plan.creations.len() {
+        std::process::exit(1);
+    }
+}
 
-        swap_start_amount /= 2; // half input amount and search again
-        if swap_start_amount < min_swap_amount {
-            break;
+/// Runs the prerequisite checks, exits non-zero if one failed
+fn doctor(ctx: Context) {
+    let pool_set = ctx.read_pools();
+    let report = run_checks(&ctx.config, &ctx.cluster, &ctx.rpc_pool, ctx.owner().as_ref(), &pool_set);
+    print!("{}", report);
+    if !report.passed() {
+        std::process::exit(1);
+    }
+}
+
+/// Prints the owner's SOL balance and the balance of every pool mint's ATA
+fn balances(ctx: Context) {
+    let owner = ctx.owner().pubkey();
+    println!("owner: {}", owner);
+    match ctx.rpc_pool.call(|client| client.get_balance(&owner)) {
+        Ok(lamports) => println!("SOL: {} lamports", lamports),
+        Err(e) => println!("SOL: failed to fetch ({})", e),
+    }
+
+    let pool_set = PoolSet::load(&ctx.pool_dirs());
+    let atas: Vec<Pubkey> = pool_set
+        .token_mints
+        .iter()
+        .map(|mint| derive_token_address(&owner, mint))
+        .collect();
+    for (mints, addrs) in pool_set
+        .token_mints
+        .chunks(ACCOUNTS_PER_REQUEST)
+        .zip(atas.chunks(ACCOUNTS_PER_REQUEST))
+    {
+        let accounts = match ctx.rpc_pool.get_multiple_accounts(addrs) {
+            Ok(accounts) => accounts,
+            Err(e) => {
+                warn!("Failed to get accounts: {}", e);
+                continue;
+            }
+        };
+        for (mint, account) in mints.iter().zip(accounts) {
+            let amount = account
+                .and_then(|acc| DECODERS.token_account(&acc).ok())
+                .map(|token| token.amount);
+            match amount {
+                Some(amount) => println!("{} {}", mint, amount),
+                None => println!("{} (no token account)", mint),
+            }
         }
     }
 }
+
+/// Quotes a cycle hop by hop, taking the best pool for each hop
+fn route(ctx: Context, mints: &[Pubkey], amount: Option<u128>) {
+    if mints.len() < 2 {
+        eprintln!("a route needs at least two mints");
+        std::process::exit(1);
+    }
+    let amount = amount.unwrap_or(ctx.config.start_mint.as_ref().unwrap().min_swap_amount);
+    let pool_set = ctx.load_pools();
+
+    let mut balance = amount;
+    for hop in mints.windows(2) {
+        let (mint_in, mint_out) = (&hop[0], &hop[1]);
+        let best = pool_set
+            .tradeable_pools()
+            .map(|i| &pool_set.pools[i])
+            .filter(|pool| {
+                let pool_mints = pool.get_mints();
+                pool_mints.contains(mint_in)
+                    && pool_mints.contains(mint_out)
+                    && pool.can_trade(mint_in, mint_out)
+            })
+            .filter_map(|pool| {
+                let out = pool.get_quote_with_amounts_scaled(balance, mint_in, mint_out).ok()?;
+                Some((pool, out))
+            })
+            .max_by_key(|(_, out)| *out);
+        match best {
+            Some((pool, out)) => {
+                println!("{} -> {} via {}: {} -> {}", mint_in, mint_out, pool.get_name(), balance, out);
+                balance = out;
+            }
+            None => {
+                println!("{} -> {}: no tradable pool", mint_in, mint_out);
+                return;
+            }
+        }
+    }
+
+    if mints.first() == mints.last() {
+        println!("profit: {}", balance as i128 - amount as i128);
+    }
+}
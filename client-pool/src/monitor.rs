@@ -0,0 +1,129 @@
+//! Watch-only monitor
+//!
+//! The `monitor` subcommand searches like a run but builds no transaction:
+//! every tick reads the pools' accounts again, searches the whole graph and
+//! prints the most profitable cycles it found with their quoted profit, the
+//! slot and how long the read and the search took. Nothing is signed or sent,
+//! so an rpc setup can be judged on the spreads it sees before funds are at
+//! stake.
+
+use serde::Serialize;
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::arb::ArbOpportunity;
+use crate::events::OpportunityEvent;
+use crate::symbols::TokenSymbols;
+
+/// What a tick of the monitor saw
+#[derive(Debug, Clone, Serialize)]
+pub struct MonitorTick {
+    pub tick: u64,
+    /// Slot the pools were read at
+    pub slot: u64,
+    /// Pools whose accounts changed since the last tick
+    pub changed_pools: usize,
+    pub fetch_ms: u64,
+    pub search_ms: u64,
+    /// Cycles quoted to return more than they take in
+    pub found: usize,
+    /// The `top` most profitable of them
+    pub top: Vec<OpportunityEvent>,
+}
+
+impl MonitorTick {
+    pub fn new(
+        tick: u64,
+        slot: u64,
+        changed_pools: usize,
+        (fetch, search): (Duration, Duration),
+        opportunities: &[ArbOpportunity],
+        symbols: &TokenSymbols,
+        top: usize,
+    ) -> Self {
+        let mut best: Vec<&ArbOpportunity> = opportunities.iter().collect();
+        best.sort_by(|a, b| b.expected_profit.cmp(&a.expected_profit));
+        best.truncate(top);
+        Self {
+            tick,
+            slot,
+            changed_pools,
+            fetch_ms: fetch.as_millis() as u64,
+            search_ms: search.as_millis() as u64,
+            found: opportunities.len(),
+            top: best
+                .into_iter()
+                .map(|opportunity| OpportunityEvent::new(opportunity, symbols, slot))
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for MonitorTick {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "tick {} at slot {}: {} cycles pay, {} pools changed (read {}ms, search {}ms)",
+            self.tick, self.slot, self.found, self.changed_pools, self.fetch_ms, self.search_ms
+        )?;
+        for (rank, opportunity) in self.top.iter().enumerate() {
+            writeln!(
+                f,
+                "  {:>2}. {} ({} -> {})",
+                rank + 1,
+                opportunity.path,
+                opportunity.amount_in,
+                opportunity.min_amount_out
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pools::PoolVariant;
+    use crate::tests::pools::orca_pool;
+    use crate::utils::PoolQuote;
+    use anchor_client::solana_sdk::pubkey::Pubkey;
+    use std::rc::Rc;
+    use std::time::Instant;
+
+    #[test]
+    fn ticks_keep_the_most_profitable_cycles() {
+        let (usdc, sol) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut symbols = TokenSymbols::default();
+        symbols.insert(usdc, "USDC".to_string());
+        symbols.insert(sol, "SOL".to_string());
+        let opportunity = |expected_profit: i128| ArbOpportunity {
+            route: format!("route {}", expected_profit),
+            mints: vec![usdc, sol, usdc],
+            pools: vec![
+                PoolQuote::new(Rc::new(PoolVariant::from(orca_pool(&usdc, &sol, 1_000, 1_000)))),
+                PoolQuote::new(Rc::new(PoolVariant::from(orca_pool(&sol, &usdc, 1_000, 1_000)))),
+            ],
+            amount_in: 1_000_000,
+            amounts: vec![],
+            min_amount_out: (1_000_000 + expected_profit) as u128,
+            expected_profit,
+            found_slot: 0,
+            found_at: Instant::now(),
+            split: None,
+        };
+        let found = vec![opportunity(500), opportunity(12_300), opportunity(7)];
+        let times = (Duration::from_millis(210), Duration::from_millis(35));
+
+        let tick = MonitorTick::new(3, 1_234, 9, times, &found, &symbols, 2);
+        assert_eq!(tick.found, 3);
+        let routes: Vec<&str> = tick.top.iter().map(|event| event.route.as_str()).collect();
+        assert_eq!(routes, ["route 12300", "route 500"]);
+        assert_eq!(
+            tick.to_string(),
+            "tick 3 at slot 1234: 3 cycles pay, 9 pools changed (read 210ms, search 35ms)\n\
+             \x20  1. USDC → SOL (Orca) → USDC (Orca) +0.0123 USDC (1000000 -> 1012300)\n\
+             \x20  2. USDC → SOL (Orca) → USDC (Orca) +0.0005 USDC (1000000 -> 1000500)\n"
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! Oracle price sanity checks
+//!
+//! Before a route is sent, every hop's implied exchange rate is compared with
+//! the rate implied by the Pyth prices of its two mints. A hop that deviates
+//! by more than `max_deviation_pct` means the pool is manipulated or its
+//! quote is broken, so the route is rejected.
+//!
+//! Hops whose mints don't both have a fresh, trading price are only rejected
+//! with `require_feeds`; otherwise they are left unchecked.
+
+use anchor_client::solana_client::client_error::Result as ClientResult;
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use tracing::debug;
+
+use crate::serialize::decoder::{PythPrice, DECODERS};
+
+/// Pyth feeds and the allowed deviation
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OracleConfig {
+    /// Pyth price account per mint (both as base58)
+    pub feeds: HashMap<String, String>,
+    /// Largest allowed deviation of a hop's rate from the oracle rate (percent)
+    #[serde(default = "default_max_deviation_pct")]
+    pub max_deviation_pct: f64,
+    /// Prices published more than this many slots ago are ignored
+    #[serde(default = "default_max_age_slots")]
+    pub max_age_slots: u64,
+    /// Reject routes with hops that can't be checked
+    #[serde(default)]
+    pub require_feeds: bool,
+}
+
+fn default_max_deviation_pct() -> f64 {
+    2.0
+}
+
+fn default_max_age_slots() -> u64 {
+    25
+}
+
+impl OracleConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        for (mint, feed) in self.feeds.iter() {
+            if Pubkey::from_str(mint).is_err() {
+                problems.push(format!("oracle.feeds key {} is not a valid pubkey", mint));
+            }
+            if Pubkey::from_str(feed).is_err() {
+                problems.push(format!("oracle.feeds.{} {} is not a valid pubkey", mint, feed));
+            }
+        }
+        if self.max_deviation_pct <= 0.0 {
+            problems.push("oracle.max_deviation_pct must be > 0".to_string());
+        }
+        problems
+    }
+}
+
+/// One swap of a route, as quoted
+#[derive(Debug, Clone)]
+pub struct Hop {
+    pub pool: String,
+    pub mint_in: Pubkey,
+    pub mint_out: Pubkey,
+    /// scaled amounts
+    pub amount_in: u128,
+    pub amount_out: u128,
+    pub decimals_in: u64,
+    pub decimals_out: u64,
+}
+
+impl Hop {
+    /// Whole `mint_out` tokens received per whole `mint_in` token
+    pub fn rate(&self) -> f64 {
+        let ui = |amount: u128, decimals: u64| amount as f64 / 10f64.powi(decimals as i32);
+        ui(self.amount_out, self.decimals_out) / ui(self.amount_in, self.decimals_in)
+    }
+}
+
+/// Relative deviation of `rate` from `oracle_rate`
+pub fn deviation(rate: f64, oracle_rate: f64) -> f64 {
+    (rate / oracle_rate - 1.0).abs()
+}
+
+/// Checks routes against Pyth prices (see the module docs)
+pub struct Oracle {
+    feeds: HashMap<Pubkey, Pubkey>,
+    max_deviation: f64,
+    max_age_slots: u64,
+    require_feeds: bool,
+}
+
+impl Oracle {
+    /// Call `OracleConfig::problems` first, invalid keys are skipped
+    pub fn from_config(config: &OracleConfig) -> Self {
+        let feeds = config
+            .feeds
+            .iter()
+            .filter_map(|(mint, feed)| Some((Pubkey::from_str(mint).ok()?, Pubkey::from_str(feed).ok()?)))
+            .collect();
+        Self {
+            feeds,
+            max_deviation: config.max_deviation_pct / 100.0,
+            max_age_slots: config.max_age_slots,
+            require_feeds: config.require_feeds,
+        }
+    }
+
+    /// Fresh, trading prices (USD per whole token) of the given mints;
+    /// mints without a usable price are left out
+    pub fn prices(&self, rpc: &RpcClient, mints: &[Pubkey], current_slot: u64) -> ClientResult<HashMap<Pubkey, f64>> {
+        let wanted: Vec<(Pubkey, Pubkey)> = mints
+            .iter()
+            .filter_map(|mint| self.feeds.get(mint).map(|feed| (*mint, *feed)))
+            .collect();
+        let feeds: Vec<Pubkey> = wanted.iter().map(|(_, feed)| *feed).collect();
+        let accounts = if feeds.is_empty() {
+            vec![]
+        } else {
+            rpc.get_multiple_accounts(&feeds)?
+        };
+
+        let mut prices = HashMap::new();
+        for ((mint, feed), account) in wanted.iter().zip(accounts) {
+            let price = match account.map(|acc| DECODERS.pyth_price(&acc)) {
+                Some(Ok(price)) => price,
+                Some(Err(e)) => {
+                    debug!("pyth feed {} of {}: {}", feed, mint, e);
+                    continue;
+                }
+                None => continue,
+            };
+            if self.usable(&price, current_slot) {
+                prices.insert(*mint, price.value());
+            }
+        }
+        Ok(prices)
+    }
+
+    fn usable(&self, price: &PythPrice, current_slot: u64) -> bool {
+        price.status == PythPrice::STATUS_TRADING
+            && price.price > 0
+            && current_slot.saturating_sub(price.publish_slot) <= self.max_age_slots
+    }
+
+    /// `Err` with the reason if any hop deviates too far from the oracle
+    pub fn check_hops(&self, hops: &[Hop], prices: &HashMap<Pubkey, f64>) -> Result<(), String> {
+        for hop in hops {
+            let (price_in, price_out) = match (prices.get(&hop.mint_in), prices.get(&hop.mint_out)) {
+                (Some(price_in), Some(price_out)) => (*price_in, *price_out),
+                _ if self.require_feeds => {
+                    return Err(format!("no oracle price for {} -> {}", hop.mint_in, hop.mint_out))
+                }
+                _ => continue,
+            };
+            if hop.amount_in == 0 {
+                continue;
+            }
+            let oracle_rate = price_in / price_out;
+            let dev = deviation(hop.rate(), oracle_rate);
+            if dev > self.max_deviation {
+                return Err(format!(
+                    "{} {} -> {} rate {:.6} is {:.2}% off the oracle rate {:.6}",
+                    hop.pool,
+                    hop.mint_in,
+                    hop.mint_out,
+                    hop.rate(),
+                    dev * 100.0,
+                    oracle_rate
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Fetches the prices of the route's mints and checks every hop
+    pub fn check_route(&self, rpc: &RpcClient, hops: &[Hop], current_slot: u64) -> Result<(), String> {
+        let mut mints: Vec<Pubkey> = hops.iter().flat_map(|h| [h.mint_in, h.mint_out]).collect();
+        mints.sort();
+        mints.dedup();
+        let prices = self
+            .prices(rpc, &mints, current_slot)
+            .map_err(|e| format!("could not fetch oracle prices: {}", e))?;
+        self.check_hops(hops, &prices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn oracle(require_feeds: bool) -> Oracle {
+        Oracle {
+            feeds: HashMap::new(),
+            max_deviation: 0.02,
+            max_age_slots: 25,
+            require_feeds,
+        }
+    }
+
+    fn hop(mint_in: Pubkey, mint_out: Pubkey, amount_out: u128) -> Hop {
+        // 1 SOL (9 decimals) -> `amount_out` USDC (6 decimals)
+        Hop {
+            pool: "Orca".to_string(),
+            mint_in,
+            mint_out,
+            amount_in: 1_000_000_000,
+            amount_out,
+            decimals_in: 9,
+            decimals_out: 6,
+        }
+    }
+
+    #[test]
+    fn hops_far_off_the_oracle_are_rejected() {
+        let (sol, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let prices: HashMap<Pubkey, f64> = [(sol, 100.0), (usdc, 1.0)].into_iter().collect();
+
+        assert!(oracle(false).check_hops(&[hop(sol, usdc, 99_500_000)], &prices).is_ok());
+        assert!(oracle(false).check_hops(&[hop(sol, usdc, 120_000_000)], &prices).is_err());
+    }
+
+    #[test]
+    fn unpriced_hops_only_fail_when_feeds_are_required() {
+        let (sol, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let prices: HashMap<Pubkey, f64> = [(usdc, 1.0)].into_iter().collect();
+
+        assert!(oracle(false).check_hops(&[hop(sol, usdc, 1)], &prices).is_ok());
+        assert!(oracle(true).check_hops(&[hop(sol, usdc, 1)], &prices).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! Per-DEX and per-pool overrides
+//!
+//! Pool JSON goes stale (a pool's fee changes, a DEX gets paused) and some
+//! pools need more care than the global settings give them. `dexes` turns
+//! whole pool types off: their pool directories aren't read. `pools` is keyed
+//! by pool address:
+//! - `fee_bps` replaces the fee from the pool's JSON when the pools are loaded
+//!   (pool types whose quotes don't use that fee are left alone with a warning)
+//! - `slippage_bps` is used for a route through the pool when it's over the
+//!   global `slippage_bps`
+//! - `max_trade_size` caps what a route puts into the pool (scaled, in the
+//!   mint the route trades into it)
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use tracing::{info, warn};
+
+use crate::graph::PoolSet;
+use crate::pool::{PoolOperations, PoolType};
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct OverridesConfig {
+    /// Settings of a whole pool type
+    #[serde(default)]
+    pub dexes: HashMap<PoolType, DexOverride>,
+    /// Settings of single pools, by pool address
+    #[serde(default)]
+    pub pools: HashMap<String, PoolOverride>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DexOverride {
+    /// Load the pools of this type
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+pub struct PoolOverride {
+    /// Slippage of routes through the pool, if over the global one
+    #[serde(default)]
+    pub slippage_bps: Option<u64>,
+    /// Most a route puts into the pool (scaled)
+    #[serde(default)]
+    pub max_trade_size: Option<u64>,
+    /// Trading fee replacing the pool's JSON fee
+    #[serde(default)]
+    pub fee_bps: Option<u64>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl OverridesConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        for (address, pool) in &self.pools {
+            if Pubkey::from_str(address).is_err() {
+                problems.push(format!("overrides.pools: {} is not a valid pubkey", address));
+            }
+            if pool.slippage_bps.map_or(false, |bps| bps > 10_000) {
+                problems.push(format!("overrides.pools.{}.slippage_bps must be <= 10000", address));
+            }
+            if pool.max_trade_size == Some(0) {
+                problems.push(format!("overrides.pools.{}.max_trade_size must be > 0", address));
+            }
+            if pool.fee_bps.map_or(false, |bps| bps >= 10_000) {
+                problems.push(format!("overrides.pools.{}.fee_bps must be < 10000", address));
+            }
+        }
+        problems
+    }
+
+    /// Whether pools of type `tipe` are loaded
+    pub fn dex_enabled(&self, tipe: PoolType) -> bool {
+        self.dexes.get(&tipe).map_or(true, |dex| dex.enabled)
+    }
+
+    /// The pool overrides by parsed address, for `RuntimeParams`
+    pub fn pool_overrides(&self) -> HashMap<Pubkey, PoolOverride> {
+        self.pools
+            .iter()
+            .filter_map(|(address, pool)| Some((Pubkey::from_str(address).ok()?, *pool)))
+            .collect()
+    }
+}
+
+/// Replaces the fees of the pools with a `fee_bps` override
+pub fn apply_fee_overrides(pool_set: &mut PoolSet, config: &OverridesConfig) {
+    let fees: HashMap<Pubkey, u64> = config
+        .pool_overrides()
+        .into_iter()
+        .filter_map(|(address, pool)| Some((address, pool.fee_bps?)))
+        .collect();
+    if fees.is_empty() {
+        return;
+    }
+    for pool in pool_set.pools.iter_mut() {
+        if let Some(fee_bps) = fees.get(&pool.get_address()) {
+            match pool.set_fee_bps(*fee_bps) {
+                Ok(()) => info!("{}: fee overridden to {} bps", pool.get_id(), fee_bps),
+                Err(e) => warn!("{}: can't override the fee: {}", pool.get_id(), e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, RuntimeParams};
+    use crate::pools::PoolVariant;
+    use crate::tests::pools::orca_pool;
+    use crate::utils::PoolQuote;
+    use std::rc::Rc;
+
+    #[test]
+    fn fee_overrides_replace_the_json_fee() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut pool_set = PoolSet::new();
+        pool_set.add_pool(PoolType::OrcaPoolType, orca_pool(&a, &b, 1_000_000, 1_000_000).into());
+        let address = pool_set.pools[0].get_address();
+        let before = pool_set.pools[0].get_quote_with_amounts_scaled(10_000, &a, &b).unwrap();
+
+        let config: OverridesConfig = serde_json::from_str(&format!(
+            r#"{{ "dexes": {{ "serum": {{ "enabled": false }} }}, "pools": {{ "{}": {{ "fee_bps": 0 }} }} }}"#,
+            address
+        ))
+        .unwrap();
+        assert!(config.problems().is_empty());
+        assert!(!config.dex_enabled(PoolType::SerumPoolType));
+        assert!(config.dex_enabled(PoolType::OrcaPoolType));
+
+        apply_fee_overrides(&mut pool_set, &config);
+        let after = pool_set.pools[0].get_quote_with_amounts_scaled(10_000, &a, &b).unwrap();
+        assert!(after > before, "{} <= {}", after, before);
+    }
+
+    #[test]
+    fn routes_take_the_highest_slippage_of_their_pools() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let risky = PoolQuote::new(Rc::new(PoolVariant::from(orca_pool(&a, &b, 1_000, 1_000))));
+        let plain = PoolQuote::new(Rc::new(PoolVariant::from(orca_pool(&b, &a, 1_000, 1_000))));
+        let config: Config = serde_json::from_str(&format!(
+            r#"{{ "fee_bps": 10, "slippage_bps": 10,
+                 "overrides": {{ "pools": {{ "{}": {{ "slippage_bps": 50, "max_trade_size": 500 }} }} }} }}"#,
+            risky.0.get_address()
+        ))
+        .unwrap();
+        let params = RuntimeParams::from_config(&config);
+        assert_eq!(params.route_slippage_bps(&[plain.clone()]), 10);
+        assert_eq!(params.route_slippage_bps(&[risky.clone(), plain.clone()]), 50);
+        assert_eq!(params.apply_route_slippage(10_000, &[risky.clone(), plain]), 9_950);
+        assert_eq!(params.max_trade_size(&risky.0.get_address()), Some(500));
+    }
+}
@@ -1,206 +0,0 @@
-/**
- * Jupiter DEX Pool Implementation
- * 
- * This module implements the pool operations interface for Jupiter DEX pools.
- * Jupiter is a Solana DEX aggregator that provides optimal swap routes across
- * multiple DEX protocols.
- */
-
-use core::panic;
-
-use std::collections::HashMap;
-use std::fmt::Debug;
-use serde::{Deserialize, Serialize};
-use crate::pool::PoolOperations;
-use crate::serialize::token::{WrappedPubkey};
-
-use crate::utils::{derive_token_address};
-
-use solana_sdk::pubkey::Pubkey;
-
-use anchor_client::{Program, Cluster};
-use solana_sdk::instruction::Instruction;
-
-use solana_sdk::clock::Epoch;
-use solana_sdk::account::Account;
-use solana_sdk::account_info::AccountInfo;
-use crate::constants::*;
-
-/// Represents a Jupiter DEX pool with its associated accounts and parameters
-#[derive(Deserialize, Serialize, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct JupiterPool {
-    /// The pool's own address
-    pub own_address: WrappedPubkey,
-    /// Base token mint address
-    pub base_mint: WrappedPubkey,
-    /// Quote token mint address
-    pub quote_mint: WrappedPubkey,
-    /// Base token vault address
-    pub base_vault: WrappedPubkey,
-    /// Quote token vault address
-    pub quote_vault: WrappedPubkey,
-    /// Jupiter swap program ID
-    pub swap_program_id: WrappedPubkey,
-    /// Taker fee percentage
-    pub taker_fee_pct: f64,
-    /// Pool accounts (loaded at runtime)
-    #[serde(skip)]
-    pub accounts: Option<Vec<Option<Account>>>,
-    /// Open orders map (loaded at runtime)
-    #[serde(skip)]
-    pub open_orders: Option<HashMap<String, String>>,
-}
-
-/// Creates an AccountInfo structure from a Pubkey and Account
-/// 
-/// # Arguments
-/// * `pk` - The public key of the account
-/// * `account` - The account data
-/// 
-/// # Returns
-/// * AccountInfo structure initialized with the provided data
-fn account_info<'a>(pk: &'a Pubkey, account: &'a mut Account) -> AccountInfo<'a> {
-    AccountInfo::new(
-        pk,
-        false,
-        true,
-        &mut account.lamports,
-        &mut account.data,
-        &account.owner,
-        false,
-        Epoch::default(),
-    )
-}
-
-/// Implementation of pool operations for Jupiter DEX
-impl PoolOperations for JupiterPool {
-    /// Returns the name of the DEX
-    fn get_name(&self) -> String {
-        "Jupiter".to_string()
-    }
-
-    /// Returns a list of account public keys that need to be updated
-    fn get_update_accounts(&self) -> Vec<Pubkey> {
-        vec![
-            self.own_address.0,
-            self.base_vault.0,
-            self.quote_vault.0,
-        ]
-    }
-
-    /// Updates the pool's accounts with new account data
-    /// 
-    /// # Arguments
-    /// * `accounts` - Vector of optional accounts
-    /// * `cluster` - The Solana cluster being used
-    fn set_update_accounts(
-        &mut self,
-        accounts: Vec<Option<Account>>,
-        cluster: Cluster,
-    ) {
-        self.accounts = Some(accounts);
-        // Load open orders from a file or other source as needed
-        self.open_orders = Some(HashMap::new()); // Placeholder
-    }
-
-    /// Returns the token account address for a given mint (Not implemented)
-    fn mint_2_addr(&self, _mint: &Pubkey) -> Pubkey {
-        panic!("Function not implemented");
-    }
-
-    /// Returns a sorted vector of the pool's token mint addresses
-    fn get_mints(&self) -> Vec<Pubkey> {
-        let mut mints = vec![
-            self.base_mint.0,
-            self.quote_mint.0,
-        ];
-        mints.sort();
-        mints
-    }
-
-    /// Returns the decimal scale for a given mint (Not implemented)
-    fn mint_2_scale(&self, mint: &Pubkey) -> u64 {
-        // Implement logic to return the scale based on the mint
-        panic!("Invalid mint provided");
-    }
-
-    /// Calculates the expected output amount for a given input amount
-    /// 
-    /// # Arguments
-    /// * `amount_in` - Input token amount
-    /// * `mint_in` - Input token mint
-    /// * `_mint_out` - Output token mint
-    /// 
-    /// # Returns
-    /// * Expected output amount
-    fn get_quote_with_amounts_scaled(
-        &self,
-        amount_in: u128,
-        mint_in: &Pubkey,
-        _mint_out: &Pubkey,
-    ) -> u128 {
-        // Logic to calculate the quote based on the amount in
-        // Placeholder logic
-        amount_in / 2 // Replace with actual calculation
-    }
-
-    /// Creates swap instructions for executing a trade
-    /// 
-    /// # Arguments
-    /// * `program` - The Anchor program instance
-    /// * `owner` - The owner's public key
-    /// * `mint_in` - Input token mint
-    /// * `_mint_out` - Output token mint
-    /// 
-    /// # Returns
-    /// * Vector of instructions for executing the swap
-    fn swap_ix(
-        &self,
-        program: &Program,
-        owner: &Pubkey,
-        mint_in: &Pubkey,
-        _mint_out: &Pubkey,
-    ) -> Vec<Instruction> {
-        let base_ata = derive_token_address(owner, &self.base_mint);
-        let quote_ata = derive_token_address(owner, &self.quote_mint);
-
-        // Construct the swap instruction
-        let swap_ix = program
-            .request()
-            .accounts(tmp_accounts::RaydiumSwap {
-                token_swap: self.address.0, 
-                authority: authority_pda,
-                user_transfer_authority: *owner,
-                user_src,
-                pool_src,
-                user_dst,
-                pool_dst,
-                lp_mint: self.lp_token_mint.0,
-                fee_account: self.fee_account.0,
-                token_program: *TOKEN_PROGRAM_ID,
-                swap_program: *RAYDIUM_PROGRAM_ID,
-                swap_state,
-            })
-            .args(tmp_ix::RaydiumSwap { })
-            .instructions()
-            .unwrap();
-    }
-
-    /// Checks if trading is possible between two tokens
-    /// 
-    /// # Arguments
-    /// * `mint_in` - Input token mint
-    /// * `_mint_out` - Output token mint
-    /// 
-    /// # Returns
-    /// * Boolean indicating if trading is possible
-    fn can_trade(
-        &self,
-        mint_in: &Pubkey,
-        _mint_out: &Pubkey,
-    ) -> bool {
-        // Check if trading is possible based on the current state
-        true // Placeholder
-    }
-}
@@ -1,44 +0,0 @@
-/**
- * DEX Pools Module
- * 
- * This module contains implementations for different Solana DEX pools that are supported
- * by the arbitrage bot. Each submodule implements the common pool interface and provides
- * specific functionality for interacting with different DEX protocols.
- * 
- * Currently supported DEXes:
- * - Orca: Concentrated liquidity AMM
- * - Raydium: Traditional AMM
- * - Jupiter: Aggregator and AMM
- * 
- * Planned/Disabled DEXes:
- * - Meteora
- * - Phoenix
- * - Lifinity
- */
-
-// Orca DEX pool implementation
-pub mod orca;
-pub use orca::*;
-
-// Raydium DEX pool implementation
-pub mod raydium;
-pub use raydium::*;
-
-// Jupiter DEX pool implementation
-pub mod jupiter;
-pub use jupiter::*;
-
-// Meteora DEX pool implementation (disabled)
-// pub mod meteora;
-// pub use meteora::*;
-
-// Phoenix DEX pool implementation (disabled)
-// pub mod phoenix;
-// pub use phoenix::*;
-
-// Lifinity DEX pool implementation (disabled)
-// pub mod lifinity;
-// pub use lifinity::*;
-
-
-
@@ -0,0 +1,171 @@
+//! Profit sweeping to a cold wallet
+//!
+//! The hot key signs every route, so whatever it holds is at risk. Every
+//! `interval_secs` the start-mint balance above `working_capital` (the
+//! session's starting balance if unset) is transferred to the start-mint
+//! token account of `destination`, once it reaches `min_sweep`. The account
+//! is created, paid by the hot wallet, if it doesn't exist yet. The capital
+//! allocator is told about the smaller balance, and the session summary
+//! counts what was swept as profit.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signature::Signer;
+use serde::{Deserialize, Serialize};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::system_program;
+
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+use crate::allocator::CapitalAllocator;
+use crate::constants::{ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use crate::executor::Dispatcher;
+use crate::utils::derive_token_address;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ProfitSweepConfig {
+    /// Cold wallet the profits go to (its address, not a token account)
+    pub destination: String,
+    /// Start-mint balance kept on the hot wallet (scaled), the session's
+    /// starting balance if unset
+    #[serde(default)]
+    pub working_capital: Option<u64>,
+    /// Smallest transfer worth sending (scaled)
+    #[serde(default)]
+    pub min_sweep: u64,
+    /// Time between two sweeps
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+}
+
+fn default_interval_secs() -> u64 {
+    3_600
+}
+
+impl ProfitSweepConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if Pubkey::from_str(&self.destination).is_err() {
+            problems.push(format!("profit_sweep.destination {} is not a valid pubkey", self.destination));
+        }
+        if self.interval_secs == 0 {
+            problems.push("profit_sweep.interval_secs must be > 0".to_string());
+        }
+        problems
+    }
+}
+
+/// What of `balance` can be swept, `None` below `min_sweep`
+pub fn sweepable(balance: u128, working_capital: u128, min_sweep: u128) -> Option<u128> {
+    let excess = balance.saturating_sub(working_capital);
+    (excess > 0 && excess >= min_sweep).then(|| excess)
+}
+
+/// The associated token account program's create instruction
+pub fn create_ata_ix(payer: &Pubkey, wallet: &Pubkey, mint: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: *ASSOCIATED_TOKEN_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(derive_token_address(wallet, mint), false),
+            AccountMeta::new_readonly(*wallet, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(*TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(solana_sdk::sysvar::rent::id(), false),
+        ],
+        data: vec![],
+    }
+}
+
+/// Sweeps the start mint above the working capital every `interval_secs`
+pub struct ProfitSweeper {
+    config: ProfitSweepConfig,
+    destination: Pubkey,
+    working_capital: u128,
+    last_run: Instant,
+}
+
+impl ProfitSweeper {
+    /// `start_balance` is the session's starting start-mint balance
+    pub fn new(config: &ProfitSweepConfig, start_balance: u128) -> Self {
+        Self {
+            config: config.clone(),
+            destination: Pubkey::from_str(&config.destination).unwrap(),
+            working_capital: config.working_capital.map_or(start_balance, |w| w as u128),
+            // there's nothing to sweep before the first round
+            last_run: Instant::now(),
+        }
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.last_run.elapsed() >= Duration::from_secs(self.config.interval_secs)
+    }
+
+    /// Transfers the excess of `mint` to the destination; failures are
+    /// logged and the excess is tried again at the next run
+    pub fn run(&mut self, ctx: &Dispatcher, mint: &Pubkey, allocator: &CapitalAllocator) {
+        self.last_run = Instant::now();
+        // simulations and paper trades make no profits to sweep
+        if !ctx.sends() {
+            return;
+        }
+        let balance = match ctx.start_balance(mint) {
+            Some(balance) => balance,
+            None => {
+                warn!("profit sweep: could not read the {} balance", mint);
+                return;
+            }
+        };
+        let amount = match sweepable(balance, self.working_capital, self.config.min_sweep as u128) {
+            Some(amount) => amount,
+            None => return,
+        };
+        let owner = ctx.owner.pubkey();
+        let destination_ata = derive_token_address(&self.destination, mint);
+        let mut ixs = vec![];
+        match ctx.connection.get_account_with_commitment(&destination_ata, ctx.connection.commitment()) {
+            Ok(response) if response.value.is_some() => {}
+            Ok(_) => ixs.push(create_ata_ix(&owner, &self.destination, mint)),
+            Err(e) => {
+                warn!("profit sweep: could not read {}: {}", destination_ata, e);
+                return;
+            }
+        }
+        match spl_token::instruction::transfer(
+            &spl_token::id(),
+            &derive_token_address(&owner, mint),
+            &destination_ata,
+            &owner,
+            &[],
+            amount as u64,
+        ) {
+            Ok(ix) => ixs.push(ix),
+            Err(e) => {
+                warn!("profit sweep: {}", e);
+                return;
+            }
+        }
+        info!("sweeping {} of {} to {}", amount, mint, self.destination);
+        if ctx.send_maintenance(&ixs, &[], "profit sweep") {
+            ctx.stats.record_swept(amount);
+            allocator.set_balance(balance - amount);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_excess_over_the_working_capital_is_swept() {
+        assert_eq!(sweepable(1_500, 1_000, 100), Some(500));
+        assert_eq!(sweepable(1_050, 1_000, 100), None);
+        assert_eq!(sweepable(900, 1_000, 0), None);
+        assert_eq!(sweepable(1_001, 1_000, 0), Some(1));
+    }
+}
@@ -0,0 +1,264 @@
+//! Provider-specific RPC extensions
+//!
+//! Helius and Triton endpoints are detected from their urls and get their
+//! own methods where they are better than the vanilla ones:
+//!
+//! * priority fees: Helius `getPriorityFeeEstimate`, Triton's percentile
+//!   parameter of `getRecentPrioritizationFees`; other endpoints get the
+//!   percentile computed locally from plain `getRecentPrioritizationFees`
+//! * account batches: zstd-compressed `getMultipleAccounts` responses, with a
+//!   fallback to plain base64 for endpoints that reject them (see `rpc.rs`)
+//!
+//! A provider method that fails falls back to the vanilla one.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use reqwest::blocking::Client as HttpClient;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use std::time::Duration;
+
+use tracing::{debug, warn};
+
+/// Most accounts the priority fee methods take
+const MAX_FEE_ACCOUNTS: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcProvider {
+    Helius,
+    Triton,
+    Vanilla,
+}
+
+impl RpcProvider {
+    /// Provider of an endpoint, from its host
+    pub fn detect(url: &str) -> Self {
+        let host = url
+            .split("://")
+            .last()
+            .unwrap_or(url)
+            .split(|c| c == '/' || c == ':' || c == '?')
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+        if host.ends_with("helius-rpc.com") || host.ends_with("helius.xyz") {
+            RpcProvider::Helius
+        } else if host.ends_with("rpcpool.com") || host.contains("triton") {
+            RpcProvider::Triton
+        } else {
+            RpcProvider::Vanilla
+        }
+    }
+
+    /// Whether zstd-compressed account data is known to be served
+    pub fn supports_zstd(&self) -> bool {
+        matches!(self, RpcProvider::Helius | RpcProvider::Triton)
+    }
+}
+
+/// Priority fee settings
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PriorityFeeConfig {
+    /// Helius priority level: Min, Low, Medium, High, VeryHigh or UnsafeMax;
+    /// mapped to a percentile for the other providers
+    #[serde(default = "default_level")]
+    pub level: String,
+    /// Upper bound of the compute unit price (micro-lamports)
+    #[serde(default = "default_max_micro_lamports")]
+    pub max_micro_lamports: u64,
+}
+
+fn default_level() -> String {
+    "High".to_string()
+}
+
+fn default_max_micro_lamports() -> u64 {
+    1_000_000
+}
+
+impl PriorityFeeConfig {
+    /// Percentile (bps) of recent fees the level stands for
+    pub fn percentile_bps(&self) -> Option<u64> {
+        match self.level.as_str() {
+            "Min" => Some(0),
+            "Low" => Some(2_500),
+            "Medium" => Some(5_000),
+            "High" => Some(7_500),
+            "VeryHigh" => Some(9_500),
+            "UnsafeMax" => Some(10_000),
+            _ => None,
+        }
+    }
+
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.percentile_bps().is_none() {
+            problems.push(format!(
+                "priority_fee.level must be Min, Low, Medium, High, VeryHigh or UnsafeMax, got {}",
+                self.level
+            ));
+        }
+        problems
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PrioritizationFee {
+    prioritization_fee: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PriorityFeeEstimate {
+    priority_fee_estimate: f64,
+}
+
+/// `bps` percentile of `fees` (nearest rank), `None` if there are none
+pub fn percentile(mut fees: Vec<u64>, bps: u64) -> Option<u64> {
+    if fees.is_empty() {
+        return None;
+    }
+    fees.sort_unstable();
+    let idx = (fees.len() - 1) * bps.min(10_000) as usize / 10_000;
+    Some(fees[idx])
+}
+
+/// Compute unit price for the accounts a transaction writes
+pub struct PriorityFeeEstimator {
+    url: String,
+    provider: RpcProvider,
+    config: PriorityFeeConfig,
+    client: HttpClient,
+}
+
+impl PriorityFeeEstimator {
+    pub fn new(url: &str, config: &PriorityFeeConfig) -> Self {
+        let provider = RpcProvider::detect(url);
+        debug!("priority fees from {} ({:?})", url, provider);
+        Self {
+            url: url.to_string(),
+            provider,
+            config: config.clone(),
+            client: HttpClient::builder()
+                .timeout(Duration::from_secs(2))
+                .build()
+                .expect("http client"),
+        }
+    }
+
+    pub fn provider(&self) -> RpcProvider {
+        self.provider
+    }
+
+    /// Price (micro-lamports per compute unit) for a transaction writing
+    /// `accounts`, capped at `max_micro_lamports`; `None` if no method answered
+    pub fn estimate(&self, accounts: &[Pubkey]) -> Option<u64> {
+        let accounts: Vec<String> = accounts
+            .iter()
+            .take(MAX_FEE_ACCOUNTS)
+            .map(|a| a.to_string())
+            .collect();
+        let provider_estimate = match self.provider {
+            RpcProvider::Helius => self.helius_estimate(&accounts),
+            RpcProvider::Triton => self.triton_estimate(&accounts),
+            RpcProvider::Vanilla => Err("no provider method".to_string()),
+        };
+        let estimate = match provider_estimate {
+            Ok(fee) => Some(fee),
+            Err(e) => {
+                if self.provider != RpcProvider::Vanilla {
+                    warn!("{:?} priority fee estimate failed, using the vanilla method: {}", self.provider, e);
+                }
+                self.vanilla_estimate(&accounts)
+                    .map_err(|e| warn!("priority fee estimate failed: {}", e))
+                    .ok()
+            }
+        };
+        estimate.map(|fee| fee.min(self.config.max_micro_lamports))
+    }
+
+    fn rpc(&self, method: &str, params: Value) -> Result<Value, String> {
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let resp: Value = self
+            .client
+            .post(&self.url)
+            .json(&body)
+            .send()
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.json())
+            .map_err(|e| format!("{} {}: {}", self.url, method, e))?;
+        if let Some(err) = resp.get("error") {
+            return Err(format!("{} {}: {}", self.url, method, err));
+        }
+        resp.get("result")
+            .cloned()
+            .ok_or_else(|| format!("{} {}: no result", self.url, method))
+    }
+
+    fn helius_estimate(&self, accounts: &[String]) -> Result<u64, String> {
+        let params = json!([{
+            "accountKeys": accounts,
+            "options": { "priorityLevel": self.config.level },
+        }]);
+        let result: PriorityFeeEstimate =
+            serde_json::from_value(self.rpc("getPriorityFeeEstimate", params)?).map_err(|e| e.to_string())?;
+        Ok(result.priority_fee_estimate.ceil() as u64)
+    }
+
+    fn triton_estimate(&self, accounts: &[String]) -> Result<u64, String> {
+        let bps = self.config.percentile_bps().unwrap_or(5_000);
+        let params = json!([accounts, { "percentile": bps }]);
+        let fees: Vec<PrioritizationFee> =
+            serde_json::from_value(self.rpc("getRecentPrioritizationFees", params)?).map_err(|e| e.to_string())?;
+        // already the requested percentile per slot, take the median slot
+        percentile(fees.into_iter().map(|f| f.prioritization_fee).collect(), 5_000)
+            .ok_or_else(|| "no recent fees".to_string())
+    }
+
+    fn vanilla_estimate(&self, accounts: &[String]) -> Result<u64, String> {
+        let bps = self.config.percentile_bps().unwrap_or(5_000);
+        let fees: Vec<PrioritizationFee> =
+            serde_json::from_value(self.rpc("getRecentPrioritizationFees", json!([accounts]))?)
+                .map_err(|e| e.to_string())?;
+        percentile(fees.into_iter().map(|f| f.prioritization_fee).collect(), bps)
+            .ok_or_else(|| "no recent fees".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn providers_are_detected_from_the_host() {
+        assert_eq!(
+            RpcProvider::detect("https://mainnet.helius-rpc.com/?api-key=abc"),
+            RpcProvider::Helius
+        );
+        assert_eq!(
+            RpcProvider::detect("https://my-node.mainnet.rpcpool.com/token"),
+            RpcProvider::Triton
+        );
+        assert_eq!(
+            RpcProvider::detect("https://api.mainnet-beta.solana.com"),
+            RpcProvider::Vanilla
+        );
+        // only the host counts
+        assert_eq!(
+            RpcProvider::detect("https://example.com/helius-rpc.com"),
+            RpcProvider::Vanilla
+        );
+    }
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let fees = vec![500, 0, 100, 300, 200];
+        assert_eq!(percentile(fees.clone(), 0), Some(0));
+        assert_eq!(percentile(fees.clone(), 5_000), Some(200));
+        assert_eq!(percentile(fees.clone(), 7_500), Some(300));
+        assert_eq!(percentile(fees, 10_000), Some(500));
+        assert_eq!(percentile(vec![], 5_000), None);
+    }
+}
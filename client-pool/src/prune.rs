@@ -0,0 +1,350 @@
+//! Graph pruning
+//!
+//! Runs once the pools have their on-chain state and drops pools that would
+//! only waste search time or produce false opportunities:
+//!
+//! * pools whose reserves are worth less than `min_liquidity_usd`, valued
+//!   through pools that trade against a USD stable
+//! * pools that can't fill `min_depth_usd` worth of input in either direction
+//!   without losing more than `max_depth_impact` of its value. Unlike the
+//!   reserve check this goes through the quote, so order books are covered
+//! * pools whose quote for a pair deviates from the median of the other pools
+//!   of that pair by more than `max_quote_deviation`, or whose round trip
+//!   returns more than was put in
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use tracing::{debug, info};
+
+use crate::amount::ScaledAmount;
+use crate::config::PruneConfig;
+use crate::constants::{USDC_MINT, USDT_MINT};
+use crate::graph::{mint_pairs, PoolSet};
+use crate::pool::PoolOperations;
+use crate::pools::PoolVariant;
+
+/// Pairs need at least this many pools before outliers can be told apart
+const MIN_POOLS_FOR_DEVIATION: usize = 3;
+/// Probe amounts are this fraction of the smallest known input reserve
+const PROBE_RESERVE_DIVISOR: u128 = 1_000;
+
+#[derive(Debug, Default)]
+pub struct PruneReport {
+    /// (pool index, liquidity in USD)
+    pub low_liquidity: Vec<(usize, f64)>,
+    /// (pool index, share of the `min_depth_usd` probe's value returned)
+    pub shallow: Vec<(usize, f64)>,
+    /// pools whose quotes look broken
+    pub bad_quotes: Vec<usize>,
+    /// pools whose liquidity couldn't be valued (kept)
+    pub unpriced: usize,
+}
+
+impl PruneReport {
+    pub fn dropped(&self) -> HashSet<usize> {
+        self.low_liquidity
+            .iter()
+            .chain(self.shallow.iter())
+            .map(|(idx, _)| *idx)
+            .chain(self.bad_quotes.iter().copied())
+            .collect()
+    }
+}
+
+/// Mints valued at $1, `usd_mints` or USDC and USDT
+pub fn usd_mints(config: &PruneConfig) -> HashSet<Pubkey> {
+    if config.usd_mints.is_empty() {
+        return [*USDC_MINT, *USDT_MINT].into_iter().collect();
+    }
+    config
+        .usd_mints
+        .iter()
+        .filter_map(|m| Pubkey::from_str(m).ok())
+        .collect()
+}
+
+/// USD price of one whole token, taken from the deepest pool of each mint
+/// that trades against a USD stable
+pub fn usd_prices(pools: &[PoolVariant], usd: &HashSet<Pubkey>) -> HashMap<Pubkey, f64> {
+    let mut prices: HashMap<Pubkey, f64> = usd.iter().map(|m| (*m, 1.0)).collect();
+    let mut depth: HashMap<Pubkey, f64> = HashMap::new();
+
+    for pool in pools {
+        let reserves = match pool.get_reserves() {
+            Some(reserves) if reserves.len() == 2 => reserves,
+            _ => continue,
+        };
+        let (stable, other) = match (usd.contains(&reserves[0].0), usd.contains(&reserves[1].0)) {
+            (true, false) => (reserves[0], reserves[1]),
+            (false, true) => (reserves[1], reserves[0]),
+            _ => continue,
+        };
+        let stable_ui = pool.ui_amount(&ScaledAmount::new(stable.0, stable.1));
+        let other_ui = pool.ui_amount(&ScaledAmount::new(other.0, other.1));
+        if other_ui <= 0.0 {
+            continue;
+        }
+        if stable_ui > *depth.get(&other.0).unwrap_or(&0.0) {
+            depth.insert(other.0, stable_ui);
+            prices.insert(other.0, stable_ui / other_ui);
+        }
+    }
+    prices
+}
+
+/// USD value of a pool's reserves; sides without a price are assumed to be
+/// worth as much as the priced ones. `None` if no side has a price.
+pub fn liquidity_usd(pool: &dyn PoolOperations, prices: &HashMap<Pubkey, f64>) -> Option<f64> {
+    let reserves = pool.get_reserves()?;
+    let priced: Vec<f64> = reserves
+        .iter()
+        .filter_map(|(mint, amount)| {
+            prices
+                .get(mint)
+                .map(|price| pool.ui_amount(&ScaledAmount::new(*mint, *amount)) * price)
+        })
+        .collect();
+    if priced.is_empty() {
+        return None;
+    }
+    let sum: f64 = priced.iter().sum();
+    Some(sum * reserves.len() as f64 / priced.len() as f64)
+}
+
+/// Share of the value of `reference_usd` worth of `mint_in` that comes back
+/// as `mint_out` through `pool`, so 1 minus fees and price impact. `None` if
+/// either mint has no price; a failed quote returns nothing.
+pub fn depth_ratio(
+    pool: &dyn PoolOperations,
+    mint_in: &Pubkey,
+    mint_out: &Pubkey,
+    prices: &HashMap<Pubkey, f64>,
+    reference_usd: f64,
+) -> Option<f64> {
+    let price_in = *prices.get(mint_in)?;
+    let price_out = *prices.get(mint_out)?;
+    if price_in <= 0.0 {
+        return None;
+    }
+    let amount_in = pool.scaled_amount(mint_in, reference_usd / price_in);
+    if amount_in.is_zero() {
+        return None;
+    }
+    let out = pool
+        .quote(&amount_in, mint_out)
+        .unwrap_or_else(|_| ScaledAmount::zero(*mint_out));
+    Some(pool.ui_amount(&out) * price_out / reference_usd)
+}
+
+/// Indices of the rates that deviate from the median by more than `max_deviation`
+/// (relative). Needs `MIN_POOLS_FOR_DEVIATION` rates, otherwise nothing is flagged.
+pub fn deviating(rates: &[f64], max_deviation: f64) -> Vec<usize> {
+    if rates.len() < MIN_POOLS_FOR_DEVIATION {
+        return vec![];
+    }
+    let mut sorted: Vec<f64> = rates.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let mid = sorted.len() / 2;
+    let median = if sorted.len() % 2 == 0 {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    };
+    if median <= 0.0 {
+        return vec![];
+    }
+    rates
+        .iter()
+        .enumerate()
+        .filter(|(_, rate)| (*rate / median - 1.0).abs() > max_deviation)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Probe input for quoting `mint_in` on a group of pools
+fn probe_amount(pool_set: &PoolSet, group: &[usize], mint_in: &Pubkey) -> u128 {
+    let min_reserve = group
+        .iter()
+        .filter_map(|i| pool_set.pools[*i].get_reserves())
+        .flat_map(|reserves| reserves.into_iter())
+        .filter(|(mint, _)| mint == mint_in)
+        .map(|(_, amount)| amount)
+        .min();
+    match min_reserve {
+        Some(reserve) if reserve / PROBE_RESERVE_DIVISOR > 0 => reserve / PROBE_RESERVE_DIVISOR,
+        _ => {
+            let decimals = pool_set.pools[group[0]].mint_2_scale(mint_in);
+            10u128.pow(decimals as u32)
+        }
+    }
+}
+
+/// Finds the pools to drop (without dropping them)
+pub fn find_prunable(pool_set: &PoolSet, config: &PruneConfig) -> PruneReport {
+    let mut report = PruneReport::default();
+
+    let prices = if config.min_liquidity_usd > 0.0 || config.min_depth_usd > 0.0 {
+        usd_prices(&pool_set.pools, &usd_mints(config))
+    } else {
+        HashMap::new()
+    };
+
+    // liquidity
+    if config.min_liquidity_usd > 0.0 {
+        for (i, pool) in pool_set.pools.iter().enumerate() {
+            if !pool_set.tradeable[i] {
+                continue;
+            }
+            match liquidity_usd(pool, &prices) {
+                Some(usd) if usd < config.min_liquidity_usd => report.low_liquidity.push((i, usd)),
+                Some(_) => {}
+                None => report.unpriced += 1,
+            }
+        }
+    }
+
+    // depth, through a quote of `min_depth_usd` in both directions
+    let mut low: HashSet<usize> = report.low_liquidity.iter().map(|(i, _)| *i).collect();
+    if config.min_depth_usd > 0.0 {
+        for (i, idxs) in pool_set.pool_mints.iter().enumerate() {
+            if low.contains(&i) || !pool_set.tradeable[i] {
+                continue;
+            }
+            let pool = &pool_set.pools[i];
+            let ratios: Vec<f64> = mint_pairs(idxs)
+                .map(|(idx_in, idx_out)| (pool_set.token_mints[idx_in], pool_set.token_mints[idx_out]))
+                .filter(|(mint_in, mint_out)| pool.can_trade(mint_in, mint_out))
+                .filter_map(|(mint_in, mint_out)| {
+                    depth_ratio(pool, &mint_in, &mint_out, &prices, config.min_depth_usd)
+                })
+                .collect();
+            if ratios.is_empty() {
+                report.unpriced += 1;
+                continue;
+            }
+            let worst = ratios.into_iter().fold(f64::INFINITY, f64::min);
+            if worst < 1.0 - config.max_depth_impact {
+                report.shallow.push((i, worst));
+                low.insert(i);
+            }
+        }
+    }
+
+    // quote sanity, per pair (pools of more mints are in the group of each of their pairs)
+    let mut pairs: HashMap<[usize; 2], Vec<usize>> = HashMap::new();
+    for (i, idxs) in pool_set.pool_mints.iter().enumerate() {
+        if low.contains(&i) || !pool_set.tradeable[i] {
+            continue;
+        }
+        for (idx0, idx1) in mint_pairs(idxs).filter(|(idx0, idx1)| idx0 < idx1) {
+            pairs.entry([idx0, idx1]).or_default().push(i);
+        }
+    }
+
+    let mut bad = HashSet::new();
+    for ([idx0, idx1], group) in pairs.iter() {
+        let mint_in = pool_set.token_mints[*idx0];
+        let mint_out = pool_set.token_mints[*idx1];
+        let tradable: Vec<usize> = group
+            .iter()
+            .copied()
+            .filter(|i| pool_set.pools[*i].can_trade(&mint_in, &mint_out))
+            .collect();
+        if tradable.is_empty() {
+            continue;
+        }
+        let probe = probe_amount(pool_set, &tradable, &mint_in);
+
+        let mut rates = vec![];
+        for i in tradable.iter() {
+            let pool = &pool_set.pools[*i];
+            // a pool that can't quote is as good as an empty one
+            let out = pool
+                .get_quote_with_amounts_scaled(probe, &mint_in, &mint_out)
+                .unwrap_or(0);
+            let back = if out > 0 {
+                pool.get_quote_with_amounts_scaled(out, &mint_out, &mint_in)
+                    .unwrap_or(0)
+            } else {
+                0
+            };
+            if out == 0 || back > probe {
+                debug!(
+                    "{} {} -> {}: round trip {} -> {} -> {}",
+                    pool.get_id(),
+                    mint_in,
+                    mint_out,
+                    probe,
+                    out,
+                    back
+                );
+                bad.insert(*i);
+            }
+            rates.push(out as f64 / probe as f64);
+        }
+        for j in deviating(&rates, config.max_quote_deviation) {
+            bad.insert(tradable[j]);
+        }
+    }
+    report.bad_quotes = bad.into_iter().collect();
+    report.bad_quotes.sort_unstable();
+    report
+}
+
+/// Drops tiny and broken pools from the set
+pub fn prune(pool_set: &mut PoolSet, config: &PruneConfig) -> PruneReport {
+    let report = find_prunable(pool_set, config);
+    let dropped = report.dropped();
+    info!(
+        "Pruned {} pools ({} below ${}, {} shallower than ${}, {} with bad quotes, {} unpriced kept)",
+        dropped.len(),
+        report.low_liquidity.len(),
+        config.min_liquidity_usd,
+        report.shallow.len(),
+        config.min_depth_usd,
+        report.bad_quotes.len(),
+        report.unpriced
+    );
+    pool_set.remove_pools(&dropped);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::pools::orca_pool;
+
+    #[test]
+    fn shallow_pools_lose_the_probe_to_price_impact() {
+        let (usd, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let prices: HashMap<Pubkey, f64> = [(usd, 1.0), (other, 1.0)].into_iter().collect();
+
+        // $1M a side: only the 0.3% fee is lost on $1000
+        let deep = orca_pool(&usd, &other, 1_000_000_000_000, 1_000_000_000_000);
+        let ratio = depth_ratio(&deep, &usd, &other, &prices, 1_000.0).unwrap();
+        assert!(ratio > 0.99 && ratio < 0.998, "{}", ratio);
+
+        // $1000 a side: half the probe goes to price impact
+        let shallow = orca_pool(&usd, &other, 1_000_000_000, 1_000_000_000);
+        let ratio = depth_ratio(&shallow, &usd, &other, &prices, 1_000.0).unwrap();
+        assert!(ratio < 0.55, "{}", ratio);
+
+        let unpriced = Pubkey::new_unique();
+        assert!(depth_ratio(&orca_pool(&usd, &unpriced, 1_000, 1_000), &usd, &unpriced, &prices, 1_000.0).is_none());
+    }
+
+    #[test]
+    fn outliers_are_flagged() {
+        let rates = [1.0, 1.02, 0.98, 5.0];
+        assert_eq!(deviating(&rates, 0.5), vec![3]);
+        assert!(deviating(&rates, 10.0).is_empty());
+    }
+
+    #[test]
+    fn two_pools_are_never_flagged() {
+        assert!(deviating(&[1.0, 100.0], 0.1).is_empty());
+    }
+}
@@ -0,0 +1,290 @@
+//! Pool quarantine
+//!
+//! Some pools are broken rather than unlucky: their accounts don't decode,
+//! their quotes fail or panic, they were drained to zero reserves or an
+//! empty book, or every route through them reverts on chain. Each of these
+//! is a failure of the pool; after `max_failures` of them in a row the pool
+//! is quarantined and the search skips it. Accounts that don't decode won't
+//! decode any better the next time, so those pools are quarantined right
+//! away. Every `recheck_secs` a quarantined pool is let back on probation: a
+//! landed route through it clears it, a single further failure quarantines
+//! it again.
+//!
+//! Quarantined pools are served on `GET /quarantine` and printed at the end
+//! of the session.
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+/// Quarantine settings
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct QuarantineConfig {
+    /// Failures in a row before a pool is quarantined; 0 disables quarantine
+    #[serde(default = "default_max_failures")]
+    pub max_failures: u32,
+    /// Seconds a pool stays quarantined before it's tried again
+    #[serde(default = "default_recheck_secs")]
+    pub recheck_secs: u64,
+}
+
+fn default_max_failures() -> u32 {
+    5
+}
+
+fn default_recheck_secs() -> u64 {
+    300
+}
+
+impl Default for QuarantineConfig {
+    fn default() -> Self {
+        Self {
+            max_failures: default_max_failures(),
+            recheck_secs: default_recheck_secs(),
+        }
+    }
+}
+
+impl QuarantineConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.recheck_secs == 0 {
+            problems.push("quarantine.recheck_secs must be > 0".to_string());
+        }
+        problems
+    }
+}
+
+/// What went wrong with a pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolFailure {
+    /// Its accounts failed to decode
+    Deserialize,
+    /// A quote failed (overflow, unknown mint, bad pool data)
+    QuoteError,
+    /// A quote panicked
+    QuotePanic,
+    /// A quote returned nothing for a non-zero input
+    ZeroReserves,
+    /// A route through it failed on chain (reverted or failed simulation)
+    OnChain,
+}
+
+impl fmt::Display for PoolFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PoolFailure::Deserialize => "deserialize",
+            PoolFailure::QuoteError => "quote error",
+            PoolFailure::QuotePanic => "quote panic",
+            PoolFailure::ZeroReserves => "zero reserves",
+            PoolFailure::OnChain => "on-chain",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PoolState {
+    failures: u32,
+    /// quarantined until then; a past instant means on probation
+    until: Option<Instant>,
+    last_failure: PoolFailure,
+    last_error: String,
+}
+
+/// A quarantined pool, served on `GET /quarantine`
+#[derive(Debug, Clone, Serialize)]
+pub struct QuarantineEntry {
+    pub pool: String,
+    /// Failures in a row
+    pub failures: u32,
+    pub last_failure: PoolFailure,
+    pub last_error: String,
+    /// Seconds until the pool is re-checked
+    pub recheck_in_secs: u64,
+}
+
+/// Failure streaks and quarantines of every pool, see the module docs
+pub struct PoolQuarantine {
+    config: QuarantineConfig,
+    pools: Mutex<HashMap<String, PoolState>>,
+}
+
+impl PoolQuarantine {
+    pub fn new(config: &QuarantineConfig) -> Self {
+        Self {
+            config: config.clone(),
+            pools: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether the search must skip `pool` right now
+    pub fn is_quarantined(&self, pool: &str) -> bool {
+        let pools = self.pools.lock().unwrap();
+        match pools.get(pool).and_then(|state| state.until) {
+            Some(until) => Instant::now() < until,
+            None => false,
+        }
+    }
+
+    /// `pool` failed; returns true if this put it into quarantine
+    pub fn record_failure(&self, pool: &str, failure: PoolFailure, error: &str) -> bool {
+        self.fail(pool, failure, error, false)
+    }
+
+    /// `pool` failed in a way retries won't fix, it's quarantined right away
+    pub fn quarantine(&self, pool: &str, failure: PoolFailure, error: &str) -> bool {
+        self.fail(pool, failure, error, true)
+    }
+
+    fn fail(&self, pool: &str, failure: PoolFailure, error: &str, now: bool) -> bool {
+        if self.config.max_failures == 0 {
+            return false;
+        }
+        let mut pools = self.pools.lock().unwrap();
+        let state = pools.entry(pool.to_string()).or_insert(PoolState {
+            failures: 0,
+            until: None,
+            last_failure: failure,
+            last_error: String::new(),
+        });
+        state.failures += 1;
+        state.last_failure = failure;
+        state.last_error = error.to_string();
+        // on probation one more failure is enough
+        let on_probation = state.until.is_some();
+        if state.failures < self.config.max_failures && !on_probation && !now {
+            return false;
+        }
+        if state.until.map_or(false, |until| Instant::now() < until) {
+            return false;
+        }
+        state.until = Some(Instant::now() + Duration::from_secs(self.config.recheck_secs));
+        warn!(
+            "quarantined pool {} after {} failures in a row ({}: {}), re-checking in {}s",
+            pool, state.failures, failure, error, self.config.recheck_secs
+        );
+        true
+    }
+
+    /// A route through `pool` landed, its failure streak is over
+    pub fn record_success(&self, pool: &str) {
+        let mut pools = self.pools.lock().unwrap();
+        if let Some(state) = pools.remove(pool) {
+            if state.until.is_some() {
+                info!("pool {} passed its re-check, out of quarantine", pool);
+            }
+        }
+    }
+
+    /// Pools quarantined right now, longest failure streak first
+    pub fn quarantined(&self) -> Vec<QuarantineEntry> {
+        let now = Instant::now();
+        let pools = self.pools.lock().unwrap();
+        let mut entries: Vec<QuarantineEntry> = pools
+            .iter()
+            .filter_map(|(pool, state)| {
+                let until = state.until.filter(|until| *until > now)?;
+                Some(QuarantineEntry {
+                    pool: pool.clone(),
+                    failures: state.failures,
+                    last_failure: state.last_failure,
+                    last_error: state.last_error.clone(),
+                    recheck_in_secs: (until - now).as_secs(),
+                })
+            })
+            .collect();
+        entries.sort_by(|a, b| b.failures.cmp(&a.failures).then(a.pool.cmp(&b.pool)));
+        entries
+    }
+}
+
+/// Quarantined pools at the end of a session
+pub struct QuarantineReport(pub Vec<QuarantineEntry>);
+
+impl fmt::Display for QuarantineReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "===== quarantined pools =====")?;
+        if self.0.is_empty() {
+            return writeln!(f, "none");
+        }
+        for entry in self.0.iter() {
+            writeln!(
+                f,
+                "{} - {} failures, last {} ({}), re-check in {}s",
+                entry.pool, entry.failures, entry.last_failure, entry.last_error, entry.recheck_in_secs
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pools_are_quarantined_after_repeated_failures() {
+        let quarantine = PoolQuarantine::new(&QuarantineConfig {
+            max_failures: 2,
+            recheck_secs: 60,
+        });
+        assert!(!quarantine.record_failure("orca:a", PoolFailure::ZeroReserves, "no output"));
+        assert!(!quarantine.is_quarantined("orca:a"));
+        // a success in between starts the streak over
+        quarantine.record_success("orca:a");
+        assert!(!quarantine.record_failure("orca:a", PoolFailure::QuotePanic, "overflow"));
+        assert!(quarantine.record_failure("orca:a", PoolFailure::QuotePanic, "overflow"));
+        assert!(quarantine.is_quarantined("orca:a"));
+        // already quarantined
+        assert!(!quarantine.record_failure("orca:a", PoolFailure::QuotePanic, "overflow"));
+
+        let entries = quarantine.quarantined();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].failures, 3);
+        assert_eq!(entries[0].last_failure, PoolFailure::QuotePanic);
+        assert!(entries[0].recheck_in_secs <= 60);
+
+        quarantine.record_success("orca:a");
+        assert!(!quarantine.is_quarantined("orca:a"));
+
+        assert!(quarantine.quarantine("orca:b", PoolFailure::Deserialize, "bad data"));
+        assert!(quarantine.is_quarantined("orca:b"));
+    }
+
+    #[test]
+    fn one_failure_on_probation_quarantines_again() {
+        let quarantine = PoolQuarantine::new(&QuarantineConfig {
+            max_failures: 3,
+            recheck_secs: 60,
+        });
+        for _ in 0..3 {
+            quarantine.record_failure("orca:a", PoolFailure::OnChain, "reverted");
+        }
+        // the re-check is due
+        quarantine.pools.lock().unwrap().get_mut("orca:a").unwrap().until = Some(Instant::now());
+        assert!(!quarantine.is_quarantined("orca:a"));
+        assert!(quarantine.record_failure("orca:a", PoolFailure::OnChain, "reverted"));
+        assert!(quarantine.is_quarantined("orca:a"));
+    }
+
+    #[test]
+    fn zero_max_failures_disables_quarantine() {
+        let quarantine = PoolQuarantine::new(&QuarantineConfig {
+            max_failures: 0,
+            recheck_secs: 60,
+        });
+        for _ in 0..10 {
+            assert!(!quarantine.record_failure("orca:a", PoolFailure::OnChain, "reverted"));
+        }
+        assert!(!quarantine.quarantine("orca:a", PoolFailure::Deserialize, "bad data"));
+        assert!(!quarantine.is_quarantined("orca:a"));
+    }
+}
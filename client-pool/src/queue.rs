@@ -0,0 +1,155 @@
+//! Opportunity ranking and the in-flight limit
+//!
+//! The opportunities of a round go through an `OpportunityQueue` ordered by
+//! net expected profit (profit after what sending the route costs), so the
+//! best routes are executed first. Broadcast transactions hold a `Permit` of
+//! the `InFlightLimit` until their outcome is known; once `max_in_flight`
+//! are out, the oldest one is confirmed before the next route is sent.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::{Arc, Mutex};
+
+use crate::arb::ArbOpportunity;
+
+struct Ranked {
+    net_profit: i128,
+    opportunity: ArbOpportunity,
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // equal profits pop in route order, so rounds are reproducible
+        self.net_profit
+            .cmp(&other.net_profit)
+            .then_with(|| other.opportunity.route.cmp(&self.opportunity.route))
+    }
+}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+/// Opportunities, highest net profit first
+#[derive(Default)]
+pub struct OpportunityQueue {
+    heap: BinaryHeap<Ranked>,
+}
+
+impl OpportunityQueue {
+    pub fn push(&mut self, net_profit: i128, opportunity: ArbOpportunity) {
+        self.heap.push(Ranked {
+            net_profit,
+            opportunity,
+        });
+    }
+
+    pub fn pop(&mut self) -> Option<ArbOpportunity> {
+        self.heap.pop().map(|ranked| ranked.opportunity)
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
+
+/// Counting semaphore over the transactions in flight
+pub struct InFlightLimit {
+    max: usize,
+    in_flight: Mutex<usize>,
+}
+
+/// One transaction in flight, released on drop
+pub struct Permit {
+    limit: Arc<InFlightLimit>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        *self.limit.in_flight.lock().unwrap() -= 1;
+    }
+}
+
+impl InFlightLimit {
+    pub fn new(max: usize) -> Arc<Self> {
+        Arc::new(Self {
+            max: max.max(1),
+            in_flight: Mutex::new(0),
+        })
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    pub fn in_flight(&self) -> usize {
+        *self.in_flight.lock().unwrap()
+    }
+
+    /// A permit if fewer than `max` are out
+    pub fn try_acquire(self: &Arc<Self>) -> Option<Permit> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if *in_flight >= self.max {
+            return None;
+        }
+        *in_flight += 1;
+        Some(Permit { limit: self.clone() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn opportunity(route: &str, expected_profit: i128) -> ArbOpportunity {
+        ArbOpportunity {
+            route: route.to_string(),
+            mints: vec![],
+            pools: vec![],
+            amount_in: 1_000,
+            amounts: vec![1_000],
+            min_amount_out: 1_000 + expected_profit as u128,
+            expected_profit,
+            found_slot: 0,
+            found_at: Instant::now(),
+            split: None,
+        }
+    }
+
+    #[test]
+    fn highest_net_profit_pops_first() {
+        let mut queue = OpportunityQueue::default();
+        // the larger gross profit costs more to send
+        queue.push(40, opportunity("b", 100));
+        queue.push(60, opportunity("a", 80));
+        queue.push(60, opportunity("c", 70));
+        let order: Vec<String> = std::iter::from_fn(|| queue.pop()).map(|o| o.route).collect();
+        assert_eq!(order, vec!["a", "c", "b"]);
+    }
+
+    #[test]
+    fn permits_are_released_on_drop() {
+        let limit = InFlightLimit::new(2);
+        let first = limit.try_acquire().unwrap();
+        let _second = limit.try_acquire().unwrap();
+        assert!(limit.try_acquire().is_none());
+        drop(first);
+        assert_eq!(limit.in_flight(), 1);
+        assert!(limit.try_acquire().is_some());
+    }
+}
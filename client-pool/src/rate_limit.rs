@@ -0,0 +1,279 @@
+//! RPC rate limiting and retries
+//!
+//! RPC plans cap the requests per second and answer 429 once the cap is
+//! hit; retrying right away only digs deeper. Every endpoint of the
+//! `RpcPool` gets a token bucket refilled at `requests_per_sec` (or its own
+//! entry in `endpoints`) and holding at most `burst` requests. A call waits
+//! for a token of the endpoint it's routed to, or moves on to the next
+//! endpoint if that would take longer than `max_wait_ms`.
+//!
+//! Transient errors (429s, timeouts, dropped connections, 5xx) are retried
+//! `max_retries` times over the whole pool. Every backoff is jittered to
+//! between half and all of its length, so the threads of a parallel fetch
+//! don't all come back at the same moment. While every endpoint is backing
+//! off the main loop pauses instead of burning its ticks on failed reads.
+
+use anchor_client::solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
+use serde::{Deserialize, Serialize};
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// RPC request budgets and retries
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RateLimitConfig {
+    /// Requests per second per endpoint; 0 disables the limit
+    #[serde(default = "default_requests_per_sec")]
+    pub requests_per_sec: f64,
+    /// Requests per second of single endpoints (by url), overriding `requests_per_sec`
+    #[serde(default)]
+    pub endpoints: HashMap<String, f64>,
+    /// Requests an endpoint may make at once after being idle
+    #[serde(default = "default_burst")]
+    pub burst: u32,
+    /// Longest wait (ms) for an endpoint's budget before trying the next one
+    #[serde(default = "default_max_wait_ms")]
+    pub max_wait_ms: u64,
+    /// Rounds over the whole pool a call is retried after transient errors
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+}
+
+fn default_requests_per_sec() -> f64 {
+    25.0
+}
+
+fn default_burst() -> u32 {
+    10
+}
+
+fn default_max_wait_ms() -> u64 {
+    1_000
+}
+
+fn default_max_retries() -> u32 {
+    2
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_sec: default_requests_per_sec(),
+            endpoints: HashMap::new(),
+            burst: default_burst(),
+            max_wait_ms: default_max_wait_ms(),
+            max_retries: default_max_retries(),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.requests_per_sec.is_nan() || self.requests_per_sec < 0.0 {
+            problems.push(format!(
+                "rate_limit.requests_per_sec must be >= 0, got {}",
+                self.requests_per_sec
+            ));
+        }
+        for (url, rate) in self.endpoints.iter() {
+            if rate.is_nan() || *rate <= 0.0 {
+                problems.push(format!("rate_limit.endpoints.{} must be > 0, got {}", url, rate));
+            }
+        }
+        if self.burst == 0 {
+            problems.push("rate_limit.burst must be > 0".to_string());
+        }
+        problems
+    }
+
+    /// Request budget of the endpoint at `url`
+    pub fn limiter(&self, url: &str) -> RateLimiter {
+        let rate = self.endpoints.get(url).copied().unwrap_or(self.requests_per_sec);
+        RateLimiter::new(rate, self.burst)
+    }
+
+    pub fn max_wait(&self) -> Duration {
+        Duration::from_millis(self.max_wait_ms)
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// negative while requests are queued for tokens not refilled yet
+    tokens: f64,
+    refilled: Instant,
+}
+
+/// Token bucket of one endpoint
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// tokens per second, 0 if unlimited
+    rate: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new(rate: f64, burst: u32) -> Self {
+        Self {
+            rate,
+            burst: burst.max(1) as f64,
+            bucket: Mutex::new(Bucket {
+                tokens: burst.max(1) as f64,
+                refilled: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn unlimited() -> Self {
+        Self::new(0.0, 1)
+    }
+
+    /// Takes a token, waiting for it if it's at most `max_wait` away;
+    /// returns false, without taking one, if it's further away
+    pub fn acquire(&self, max_wait: Duration) -> bool {
+        match self.reserve(Instant::now(), max_wait) {
+            Some(wait) => {
+                if !wait.is_zero() {
+                    thread::sleep(wait);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Takes the next token at `now`, returns how long until it's available
+    /// or `None` if that's longer than `max_wait`
+    fn reserve(&self, now: Instant, max_wait: Duration) -> Option<Duration> {
+        if self.rate <= 0.0 {
+            return Some(Duration::ZERO);
+        }
+        let mut bucket = self.bucket.lock().unwrap();
+        let elapsed = now.saturating_duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.refilled = now;
+        let wait = if bucket.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate)
+        };
+        if wait > max_wait {
+            return None;
+        }
+        bucket.tokens -= 1.0;
+        Some(wait)
+    }
+}
+
+/// Whether the endpoint said it's getting too many requests
+pub fn is_throttled(err: &ClientError) -> bool {
+    let err = err.to_string().to_lowercase();
+    err.contains("429") || err.contains("too many requests") || err.contains("rate limit")
+}
+
+/// Whether the same request may well succeed if it's tried again
+pub fn is_transient(err: &ClientError) -> bool {
+    if is_throttled(err) {
+        return true;
+    }
+    if matches!(err.kind(), ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_)) {
+        return true;
+    }
+    let err = err.to_string().to_lowercase();
+    ["timed out", "timeout", "connection", "502", "503", "504", "node is behind"]
+        .iter()
+        .any(|transient| err.contains(transient))
+}
+
+/// `delay` cut to a random share between half and all of it
+pub fn jittered(delay: Duration) -> Duration {
+    // every `RandomState` is keyed differently, which is all the randomness needed
+    let random = RandomState::new().build_hasher().finish();
+    delay.mul_f64(0.5 + (random % 1_000) as f64 / 2_000.0)
+}
+
+/// Runs `f`, retrying transient errors up to `retries` times after a
+/// jittered, doubling pause starting at `base`
+pub fn with_retries<T>(retries: u32, base: Duration, mut f: impl FnMut() -> ClientResult<T>) -> ClientResult<T> {
+    let mut attempt = 0;
+    loop {
+        match f() {
+            Err(e) if attempt < retries && is_transient(&e) => {
+                thread::sleep(jittered(base * (1 << attempt.min(8))));
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budget_refills_over_time() {
+        let limiter = RateLimiter::new(10.0, 2);
+        let start = Instant::now();
+        let max_wait = Duration::from_millis(150);
+        assert_eq!(limiter.reserve(start, max_wait), Some(Duration::ZERO));
+        assert_eq!(limiter.reserve(start, max_wait), Some(Duration::ZERO));
+        // the burst is spent, the next token is 100ms away
+        let wait = limiter.reserve(start, max_wait).unwrap();
+        assert!((wait.as_secs_f64() - 0.1).abs() < 1e-6);
+        // and the one after that is queued behind it
+        assert_eq!(limiter.reserve(start, max_wait), None);
+        let later = start + Duration::from_millis(300);
+        assert_eq!(limiter.reserve(later, max_wait), Some(Duration::ZERO));
+
+        let unlimited = RateLimiter::unlimited();
+        for _ in 0..100 {
+            assert_eq!(unlimited.reserve(start, Duration::ZERO), Some(Duration::ZERO));
+        }
+    }
+
+    #[test]
+    fn throttling_and_transient_errors_are_recognized() {
+        let error = |msg: &str| ClientError::from(ClientErrorKind::Custom(msg.to_string()));
+        assert!(is_throttled(&error("HTTP status client error (429 Too Many Requests)")));
+        assert!(is_transient(&error("429 Too Many Requests")));
+        assert!(is_transient(&error("operation timed out")));
+        assert!(is_transient(&error("503 Service Unavailable")));
+        assert!(!is_transient(&error("Invalid param: WrongSize")));
+    }
+
+    #[test]
+    fn jitter_stays_within_half_and_all_of_the_delay() {
+        let delay = Duration::from_millis(200);
+        for _ in 0..100 {
+            let jittered = jittered(delay);
+            assert!(jittered >= delay / 2 && jittered <= delay);
+        }
+    }
+
+    #[test]
+    fn only_transient_errors_are_retried() {
+        let mut calls = 0;
+        let result: ClientResult<()> = with_retries(2, Duration::from_millis(1), || {
+            calls += 1;
+            Err(ClientError::from(ClientErrorKind::Custom("429 Too Many Requests".to_string())))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 3);
+
+        let mut calls = 0;
+        let result: ClientResult<()> = with_retries(2, Duration::from_millis(1), || {
+            calls += 1;
+            Err(ClientError::from(ClientErrorKind::Custom("Invalid param".to_string())))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,512 @@
+//! Record and replay of RPC responses
+//!
+//! With `record` set, every pool account read of a run and every
+//! simulation and blockhash fetch of its routes (see `RpcApi`) is appended
+//! to the recording, one JSON object per line, along with a marker right
+//! before the search of every round (its slot and input). The accounts a
+//! round searched on are the last ones read of each key before its marker,
+//! so `replay --recording <file> --round <n>` rebuilds the graph from them
+//! and runs the round's search again offline, the same quotes down to the
+//! last unit, next to what the run sent and simulated in that round.
+//!
+//! What the search depended on besides the accounts is recorded too: the
+//! pools as the run loaded them (fees and decimals read on chain and
+//! overridden, the owner's Serum fee tier) with their mints in index order,
+//! and with every marker the round's parameters (hot reloads, control api
+//! updates and the token probe's blacklist included) and realized slippage
+//! pads. The replay takes none of those from the pool files, the config or
+//! the slippage history at hand, reads nothing from the cluster and needs
+//! no key. Pools in quarantine, routes in cooldown and the position sizing
+//! history of the run aren't known to the replay: those pools are searched
+//! again and routes are sized in full.
+
+use anchor_client::solana_client::client_error::{ClientErrorKind, Result as ClientResult};
+use anchor_client::solana_client::rpc_config::RpcSendTransactionConfig;
+use anchor_client::solana_client::rpc_response::{RpcResult, RpcSimulateTransactionResult};
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use serde::{Deserialize, Serialize};
+use solana_sdk::account::Account;
+use solana_sdk::hash::Hash;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, LineWriter, Write};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use tracing::warn;
+
+use crate::config::RuntimeParams;
+use crate::fee_tiers::apply_fee_tiers;
+use crate::graph::{AccountSource, ChunkFetch, PoolSet};
+use crate::pool::{pool_factory, PoolType};
+use crate::pools::PoolVariant;
+use crate::rpc_api::RpcApi;
+
+/// An account as it is recorded, its data base64
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedAccount {
+    pub lamports: u64,
+    pub data: String,
+    pub owner: String,
+    pub executable: bool,
+    pub rent_epoch: u64,
+}
+
+impl From<&Account> for RecordedAccount {
+    fn from(account: &Account) -> Self {
+        Self {
+            lamports: account.lamports,
+            data: base64::encode(&account.data),
+            owner: account.owner.to_string(),
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+        }
+    }
+}
+
+impl RecordedAccount {
+    fn account(&self) -> Result<Account, String> {
+        Ok(Account {
+            lamports: self.lamports,
+            data: base64::decode(&self.data).map_err(|e| format!("account data: {}", e))?,
+            owner: Pubkey::from_str(&self.owner).map_err(|e| format!("account owner: {}", e))?,
+            executable: self.executable,
+            rent_epoch: self.rent_epoch,
+        })
+    }
+}
+
+/// A pool as the run loaded it, before its accounts were read
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedPool {
+    pub pool_type: PoolType,
+    /// The pool serialized like its JSON file
+    pub json: String,
+}
+
+impl RecordedPool {
+    fn new(pool_type: PoolType, pool: &PoolVariant) -> Self {
+        let json = match pool {
+            PoolVariant::Orca(pool) => serde_json::to_string(pool),
+            PoolVariant::Raydium(pool) => serde_json::to_string(pool),
+            PoolVariant::Serum(pool) => serde_json::to_string(pool),
+            PoolVariant::Jupiter(pool) => serde_json::to_string(pool),
+        };
+        Self {
+            pool_type,
+            json: json.expect("pools serialize"),
+        }
+    }
+}
+
+/// One line of a recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "call", rename_all = "snake_case")]
+pub enum Entry {
+    /// The pools of the run and their mints in index order, the token
+    /// lists' first (the pool files and lists may have changed by the time
+    /// of the replay)
+    Mints {
+        mints: Vec<String>,
+        #[serde(default)]
+        pools: Vec<RecordedPool>,
+        /// The owner's SRM and MSRM the Serum fee tiers were taken from
+        #[serde(default)]
+        srm_held: u64,
+        #[serde(default)]
+        msrm_held: u64,
+    },
+    /// A search round is about to search on what was read so far
+    Round {
+        round: usize,
+        slot: u64,
+        /// Input of the search (scaled start mint, fees taken off) and the
+        /// amount it was taken from
+        net_amount: u128,
+        amount: u128,
+        /// Parameters the round searched with, `None` in older recordings
+        #[serde(default)]
+        params: Option<RuntimeParams>,
+        /// Realized slippage pad of every venue (bps), `None` without
+        /// `slippage`
+        #[serde(default)]
+        pads: Option<BTreeMap<String, u64>>,
+    },
+    /// A `getMultipleAccounts` request, the accounts in the order of `keys`
+    Accounts {
+        keys: Vec<String>,
+        result: Result<(Vec<Option<RecordedAccount>>, u64), String>,
+    },
+    Send {
+        signature: String,
+        result: Result<String, String>,
+    },
+    Simulate {
+        signature: String,
+        result: Result<(u64, RpcSimulateTransactionResult), String>,
+    },
+    Blockhash {
+        commitment: String,
+        result: Result<(String, u64), String>,
+    },
+}
+
+/// Appends entries to a recording, shared by the sources it records
+pub struct Recorder {
+    out: Mutex<LineWriter<File>>,
+}
+
+impl Recorder {
+    /// Records to `path`, appending if it exists
+    pub fn create(path: &str) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("could not open the recording {}: {}", path, e))?;
+        Ok(Self {
+            out: Mutex::new(LineWriter::new(file)),
+        })
+    }
+
+    /// Records the pools of the run once their parameters are set, and the
+    /// SRM and MSRM holdings their fee tiers were taken from
+    pub fn mints(&self, pool_set: &PoolSet, (srm_held, msrm_held): (u64, u64)) {
+        self.write(&Entry::Mints {
+            mints: pool_set.token_mints.iter().map(|mint| mint.to_string()).collect(),
+            pools: pool_set
+                .pool_types
+                .iter()
+                .zip(pool_set.pools.iter())
+                .map(|(pool_type, pool)| RecordedPool::new(*pool_type, pool))
+                .collect(),
+            srm_held,
+            msrm_held,
+        });
+    }
+
+    /// Marks the start of the search of `round`, with the parameters and
+    /// slippage pads it searches with
+    pub fn round(
+        &self,
+        round: usize,
+        slot: u64,
+        (net_amount, amount): (u128, u128),
+        params: &RuntimeParams,
+        pads: Option<BTreeMap<String, u64>>,
+    ) {
+        self.write(&Entry::Round {
+            round,
+            slot,
+            net_amount,
+            amount,
+            params: Some(params.clone()),
+            pads,
+        });
+    }
+
+    fn write(&self, entry: &Entry) {
+        let line = serde_json::to_string(entry).expect("entries serialize");
+        if let Err(e) = writeln!(self.out.lock().unwrap(), "{}", line) {
+            warn!("could not write to the recording: {}", e);
+        }
+    }
+
+    fn accounts(&self, keys: &[Pubkey], result: &ClientResult<(Vec<Option<Account>>, u64)>) {
+        self.write(&Entry::Accounts {
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+            result: match result {
+                Ok((accounts, slot)) => Ok((
+                    accounts.iter().map(|a| a.as_ref().map(RecordedAccount::from)).collect(),
+                    *slot,
+                )),
+                Err(e) => Err(e.to_string()),
+            },
+        });
+    }
+}
+
+/// `source` with every response recorded
+pub struct Recorded<S> {
+    source: S,
+    recorder: Arc<Recorder>,
+}
+
+impl<S> Recorded<S> {
+    pub fn new(source: S, recorder: Arc<Recorder>) -> Self {
+        Self { source, recorder }
+    }
+}
+
+impl<S: AccountSource> AccountSource for Recorded<S> {
+    fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> ClientResult<(Vec<Option<Account>>, u64)> {
+        let result = self.source.get_multiple_accounts_with_slot(pubkeys);
+        self.recorder.accounts(pubkeys, &result);
+        result
+    }
+
+    fn get_chunks_with_slot(&self, chunks: &[&[Pubkey]]) -> Vec<ChunkFetch> {
+        // the source fetches them its own way (in parallel, with retries)
+        let fetches = self.source.get_chunks_with_slot(chunks);
+        for (keys, fetch) in chunks.iter().zip(fetches.iter()) {
+            self.recorder.accounts(keys, &fetch.result);
+        }
+        fetches
+    }
+}
+
+impl<S: RpcApi> RpcApi for Recorded<S> {
+    fn send(
+        &self,
+        tx: &Transaction,
+        config: RpcSendTransactionConfig,
+        min_context_slot: Option<u64>,
+    ) -> ClientResult<Signature> {
+        let result = self.source.send(tx, config, min_context_slot);
+        self.recorder.write(&Entry::Send {
+            signature: signature_of(tx),
+            result: result.as_ref().map(|sig| sig.to_string()).map_err(|e| e.to_string()),
+        });
+        result
+    }
+
+    fn simulate(
+        &self,
+        tx: &Transaction,
+        min_context_slot: Option<u64>,
+    ) -> RpcResult<RpcSimulateTransactionResult> {
+        let result = self.source.simulate(tx, min_context_slot);
+        self.recorder.write(&Entry::Simulate {
+            signature: signature_of(tx),
+            result: match &result {
+                Ok(response) => Ok((response.context.slot, response.value.clone())),
+                Err(e) => Err(e.to_string()),
+            },
+        });
+        result
+    }
+
+    fn latest_blockhash(&self, commitment: CommitmentConfig) -> ClientResult<(Hash, u64)> {
+        let result = self.source.latest_blockhash(commitment);
+        self.recorder.write(&Entry::Blockhash {
+            commitment: format!("{:?}", commitment.commitment),
+            result: match &result {
+                Ok((blockhash, height)) => Ok((blockhash.to_string(), *height)),
+                Err(e) => Err(e.to_string()),
+            },
+        });
+        result
+    }
+}
+
+fn signature_of(tx: &Transaction) -> String {
+    tx.signatures.first().map(|sig| sig.to_string()).unwrap_or_default()
+}
+
+/// A recording read back
+pub struct Recording {
+    pub entries: Vec<Entry>,
+}
+
+impl Recording {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("could not open the recording {}: {}", path, e))?;
+        let mut entries = vec![];
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| format!("{}: {}", path, e))?;
+            // a run killed mid-write leaves a partial last line
+            match serde_json::from_str(&line) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => warn!("{}:{} skipped: {}", path, i + 1, e),
+            }
+        }
+        Ok(Self { entries })
+    }
+
+    /// The pools of the run `round` is in, as it loaded them, before their
+    /// accounts are read
+    pub fn pool_set(&self, round: usize) -> Result<PoolSet, String> {
+        let marker = self.marker(round).ok_or_else(|| format!("round {} is not in the recording", round))?;
+        let (mints, pools, srm_held, msrm_held) = self.entries[..marker]
+            .iter()
+            .rev()
+            .find_map(|entry| match entry {
+                Entry::Mints {
+                    mints,
+                    pools,
+                    srm_held,
+                    msrm_held,
+                } if !pools.is_empty() => Some((mints, pools, *srm_held, *msrm_held)),
+                _ => None,
+            })
+            .ok_or_else(|| format!("the pools of round {} weren't recorded, record it again", round))?;
+        let mut pool_set = PoolSet::new();
+        for mint in mints {
+            pool_set.add_mint(Pubkey::from_str(mint).map_err(|e| format!("recorded mint {}: {}", mint, e))?);
+        }
+        for pool in pools {
+            let variant =
+                pool_factory(&pool.pool_type, &pool.json).map_err(|e| format!("recorded pool: {}", e))?;
+            pool_set.add_pool(pool.pool_type, variant);
+        }
+        apply_fee_tiers(&mut pool_set, srm_held, msrm_held);
+        Ok(pool_set)
+    }
+
+    /// Position of the marker of `round`; a run that appended to an older
+    /// recording has the round twice, its last one is taken
+    fn marker(&self, round: usize) -> Option<usize> {
+        self.entries
+            .iter()
+            .rposition(|entry| matches!(entry, Entry::Round { round: r, .. } if *r == round))
+    }
+
+    /// The accounts `round` searched on, `None` if it wasn't recorded
+    pub fn replay(&self, round: usize) -> Option<ReplayRpc> {
+        let marker = self.marker(round)?;
+        let slot = match &self.entries[marker] {
+            Entry::Round { slot, .. } => *slot,
+            _ => unreachable!("marker positions point at rounds"),
+        };
+        let mut accounts = HashMap::new();
+        for entry in self.entries[..marker].iter() {
+            if let Entry::Accounts { keys, result: Ok((read, _)) } = entry {
+                for (key, account) in keys.iter().zip(read.iter()) {
+                    accounts.insert(key.clone(), account.clone());
+                }
+            }
+        }
+        Some(ReplayRpc { accounts, slot })
+    }
+
+    /// The marker of `round` and the entries after it, up to the next round
+    pub fn round(&self, round: usize) -> &[Entry] {
+        let marker = match self.marker(round) {
+            Some(marker) => marker,
+            None => return &[],
+        };
+        let len = self.entries[marker + 1..]
+            .iter()
+            .position(|entry| matches!(entry, Entry::Round { .. }))
+            .map_or(self.entries.len() - marker, |next| next + 1);
+        &self.entries[marker..marker + len]
+    }
+}
+
+/// Serves the accounts of a recording as they were at a round's search
+pub struct ReplayRpc {
+    /// last read of every recorded key, `None` if it didn't exist
+    accounts: HashMap<String, Option<RecordedAccount>>,
+    pub slot: u64,
+}
+
+impl AccountSource for ReplayRpc {
+    fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> ClientResult<(Vec<Option<Account>>, u64)> {
+        let mut accounts = Vec::with_capacity(pubkeys.len());
+        for pubkey in pubkeys {
+            let recorded = self.accounts.get(&pubkey.to_string()).ok_or_else(|| {
+                ClientErrorKind::Custom(format!("{} was never read in the recording", pubkey))
+            })?;
+            let account = match recorded {
+                Some(recorded) => Some(recorded.account().map_err(ClientErrorKind::Custom)?),
+                None => None,
+            };
+            accounts.push(account);
+        }
+        Ok((accounts, self.slot))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::pool::PoolOperations;
+    use crate::rpc_api::MockRpc;
+    use crate::tests::pools::orca_pool;
+
+    fn account(lamports: u64, data: &[u8]) -> Account {
+        Account {
+            lamports,
+            data: data.to_vec(),
+            owner: Pubkey::new_unique(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn rounds_replay_the_accounts_read_before_them() {
+        let path = std::env::temp_dir().join(format!("recording-{}.jsonl", Pubkey::new_unique()));
+        let path = path.to_str().unwrap();
+        let recorder = Arc::new(Recorder::create(path).unwrap());
+        let rpc = MockRpc::new(100);
+        let (pool, vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        rpc.set_account(pool, account(1, &[1, 2, 3]));
+        rpc.set_account(vault, account(2, &[4]));
+        let recorded = Recorded::new(&rpc, recorder.clone());
+        let (usdc, sol) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut pool_set = PoolSet::new();
+        pool_set.add_mint(vault);
+        pool_set.add_pool(PoolType::OrcaPoolType, orca_pool(&usdc, &sol, 1_000, 1_000).into());
+        let config: Config = serde_json::from_str(r#"{ "fee_bps": 10 }"#).unwrap();
+        let mut params = RuntimeParams::from_config(&config);
+        params.blacklisted_mints.insert(sol);
+
+        recorder.mints(&pool_set, (0, 0));
+        recorded.get_chunks_with_slot(&[&[pool], &[vault]]);
+        recorder.round(0, 100, (990, 1_000), &params, None);
+        // read after round 0 searched, only round 1 sees it
+        rpc.set_account(vault, account(3, &[5, 6]));
+        rpc.set_slot(101);
+        recorded.get_multiple_accounts_with_slot(&[vault]).unwrap();
+        // a reload between the rounds
+        params.fee_bps = 25;
+        let pads: BTreeMap<String, u64> = [("orca".to_string(), 12)].into_iter().collect();
+        recorder.round(1, 101, (495, 500), &params, Some(pads.clone()));
+        drop(recorded);
+
+        let recording = Recording::load(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let round0 = recording.replay(0).unwrap();
+        let (accounts, slot) = round0.get_multiple_accounts_with_slot(&[pool, vault]).unwrap();
+        assert_eq!((accounts[1].as_ref().unwrap().data.clone(), slot), (vec![4], 100));
+        assert_eq!(accounts[0].as_ref().unwrap().data, vec![1, 2, 3]);
+        assert!(round0.get_multiple_accounts_with_slot(&[Pubkey::new_unique()]).is_err());
+
+        let round1 = recording.replay(1).unwrap();
+        let (accounts, slot) = round1.get_multiple_accounts_with_slot(&[vault]).unwrap();
+        let vault_account = accounts[0].as_ref().unwrap();
+        assert_eq!((vault_account.lamports, vault_account.data.clone(), slot), (3, vec![5, 6], 101));
+        // the marker and the read that followed it
+        assert_eq!(recording.round(0).len(), 2);
+        assert!(recording.replay(2).is_none());
+
+        // the pools as they were loaded, whatever the pool files are now
+        let replayed = recording.pool_set(1).unwrap();
+        assert_eq!(replayed.token_mints, vec![vault, usdc, sol]);
+        assert_eq!(replayed.pools[0].get_address(), pool_set.pools[0].get_address());
+        match &recording.round(1)[0] {
+            Entry::Round {
+                params: Some(recorded),
+                pads: Some(recorded_pads),
+                ..
+            } => {
+                assert_eq!(*recorded, params);
+                assert_eq!(*recorded_pads, pads);
+            }
+            entry => panic!("not the marker of round 1: {:?}", entry),
+        }
+        assert!(recording.pool_set(2).is_err());
+    }
+}
@@ -0,0 +1,268 @@
+//! Re-quote before send
+//!
+//! Pools are quoted on the accounts fetched before the search, and the chain
+//! keeps moving while the search runs. Right before a route is built, its
+//! pools' accounts are fetched again and the route is quoted on copies of the
+//! pools holding the new data. If the fresh profit dropped by more than
+//! `max_profit_drop_pct` of the profit the search found, the route is
+//! aborted; with `resize` the route is instead quoted at halved sizes and
+//! sent at the most profitable one, as long as that one still pays. Fresh
+//! quotes are padded by the slippage tracker like the search's were, so the
+//! profit guard and the tolerance see the same minimum.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+use serde::{Deserialize, Serialize};
+
+use std::rc::Rc;
+
+use crate::amount::bps_ceil;
+use crate::arb::ArbOpportunity;
+use crate::config::RuntimeParams;
+use crate::flash_loan::FlashLoan;
+use crate::graph::AccountSource;
+use crate::pool::PoolOperations;
+use crate::slippage::SlippageTracker;
+use crate::utils::PoolQuote;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RequoteConfig {
+    /// Largest drop of the fresh profit below the searched one (percent)
+    #[serde(default = "default_max_profit_drop_pct")]
+    pub max_profit_drop_pct: f64,
+    /// Try smaller sizes instead of aborting
+    #[serde(default)]
+    pub resize: bool,
+    /// How many times the size is halved when resizing
+    #[serde(default = "default_resize_steps")]
+    pub resize_steps: u32,
+}
+
+fn default_max_profit_drop_pct() -> f64 {
+    20.0
+}
+
+fn default_resize_steps() -> u32 {
+    3
+}
+
+impl RequoteConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if !(0.0..=100.0).contains(&self.max_profit_drop_pct) {
+            problems.push("requote.max_profit_drop_pct must be in [0, 100]".to_string());
+        }
+        if self.resize && self.resize_steps == 0 {
+            problems.push("requote.resize_steps must be > 0 with resize".to_string());
+        }
+        problems
+    }
+}
+
+/// What the fresh quote says about an opportunity
+#[derive(Debug)]
+pub enum Requote {
+    /// Within the tolerance; re-quoted, and smaller if it was resized
+    Send(ArbOpportunity),
+    /// Dropped beyond the tolerance, with the fresh profit at full size
+    Abort(i128),
+}
+
+/// Copies of `pools` updated with their accounts as of now
+pub fn fresh_pools<S: AccountSource + ?Sized>(
+    rpc: &S,
+    cluster: &Cluster,
+    pools: &[PoolQuote],
+) -> Result<Vec<PoolQuote>, String> {
+    let keys: Vec<Vec<Pubkey>> = pools.iter().map(|p| p.0.get_update_accounts()).collect();
+    let (accounts, _) = rpc
+        .get_multiple_accounts_with_slot(&keys.concat())
+        .map_err(|e| format!("could not fetch pool accounts: {}", e))?;
+    let mut accounts = accounts.into_iter();
+    let mut fresh = Vec::with_capacity(pools.len());
+    for (pool, keys) in pools.iter().zip(keys.iter()) {
+        let mut copy = (*pool.0).clone();
+        copy.set_update_accounts(accounts.by_ref().take(keys.len()).collect(), cluster.clone())
+            .map_err(|e| format!("{}: {}", pool.0.get_id(), e))?;
+        fresh.push(PoolQuote::new(Rc::new(copy)));
+    }
+    Ok(fresh)
+}
+
+/// Input of every hop and the route's output for `amount`, `None` if a hop
+/// can't quote
+fn route_amounts(amount: u128, mints: &[Pubkey], pools: &[PoolQuote]) -> Option<Vec<u128>> {
+    let mut amounts = vec![amount];
+    for (i, pool) in pools.iter().enumerate() {
+        let out = pool
+            .0
+            .get_quote_with_amounts_scaled(amounts[i], &mints[i], &mints[i + 1])
+            .ok()?;
+        amounts.push(out);
+    }
+    Some(amounts)
+}
+
+/// `opportunity` quoted on `pools` at `size` (scaled, what goes into the
+/// first hop), `None` if it doesn't repay its input (and loan fee)
+fn quote_at(
+    opportunity: &ArbOpportunity,
+    pools: &[PoolQuote],
+    size: u128,
+    params: &RuntimeParams,
+    slippage: Option<&SlippageTracker>,
+    flash_loan: Option<&FlashLoan>,
+) -> Option<ArbOpportunity> {
+    let amounts = route_amounts(size, &opportunity.mints, pools)?;
+    let amount_in = opportunity.amount_in * size / opportunity.amounts[0].max(1);
+    let mut min_amount_out = params.apply_route_slippage(*amounts.last()?, pools);
+    if let Some(slippage) = slippage {
+        min_amount_out -= bps_ceil(min_amount_out, slippage.route_pad_bps(pools));
+    }
+    let required = amount_in + flash_loan.map_or(0, |fl| fl.fee(amount_in));
+    if min_amount_out <= required {
+        return None;
+    }
+    Some(ArbOpportunity {
+        amount_in,
+        amounts,
+        min_amount_out,
+        expected_profit: min_amount_out as i128 - amount_in as i128,
+        // sent on its own pools, a divided leg would need both quoted again
+        split: None,
+        ..opportunity.clone()
+    })
+}
+
+/// Re-quotes `opportunity` on `fresh` (see `fresh_pools`), padded by
+/// `slippage` like the search pads its quotes
+pub fn requote(
+    config: &RequoteConfig,
+    opportunity: &ArbOpportunity,
+    fresh: &[PoolQuote],
+    params: &RuntimeParams,
+    slippage: Option<&SlippageTracker>,
+    flash_loan: Option<&FlashLoan>,
+) -> Requote {
+    let size = opportunity.amounts[0];
+    let full = quote_at(opportunity, fresh, size, params, slippage, flash_loan);
+    let floor = opportunity.expected_profit as f64 * (1.0 - config.max_profit_drop_pct / 100.0);
+    let fresh_profit = full.as_ref().map_or(0, |o| o.expected_profit);
+    if let Some(full) = full.filter(|o| o.expected_profit as f64 >= floor) {
+        return Requote::Send(full);
+    }
+    if !config.resize {
+        return Requote::Abort(fresh_profit);
+    }
+    // price impact grows with size, a moved pool may still pay on less
+    (0..=config.resize_steps)
+        .filter_map(|step| quote_at(opportunity, fresh, size >> step, params, slippage, flash_loan))
+        .max_by_key(|o| o.expected_profit)
+        .map_or(Requote::Abort(fresh_profit), Requote::Send)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::pools::PoolVariant;
+    use crate::tests::pools::orca_pool;
+    use std::collections::BTreeMap;
+    use std::time::Instant;
+
+    fn params() -> RuntimeParams {
+        let config: Config = serde_json::from_str(r#"{ "fee_bps": 10 }"#).unwrap();
+        RuntimeParams::from_config(&config)
+    }
+
+    /// a -> b -> a, b is 10% cheaper in the first pool, whose reserves are `reserve` a
+    fn route(reserve: u128, size: u128, expected_profit: i128) -> (ArbOpportunity, Vec<PoolQuote>) {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let pools = vec![
+            PoolQuote::new(Rc::new(PoolVariant::from(orca_pool(&a, &b, reserve, reserve * 11 / 10)))),
+            PoolQuote::new(Rc::new(PoolVariant::from(orca_pool(&b, &a, 1_000_000_000, 1_000_000_000)))),
+        ];
+        let opportunity = ArbOpportunity {
+            route: "a->b->a".to_string(),
+            mints: vec![a, b, a],
+            pools: pools.clone(),
+            amount_in: size,
+            amounts: vec![size, 0, size + expected_profit as u128],
+            min_amount_out: size + expected_profit as u128,
+            expected_profit,
+            found_slot: 0,
+            found_at: Instant::now(),
+            split: None,
+        };
+        (opportunity, pools)
+    }
+
+    #[test]
+    fn profit_within_tolerance_is_sent_with_fresh_amounts() {
+        let config: RequoteConfig = serde_json::from_str("{}").unwrap();
+        let (opportunity, pools) = route(1_000_000_000, 10_000_000, 500_000);
+        match requote(&config, &opportunity, &pools, &params(), None, None) {
+            Requote::Send(fresh) => {
+                assert_eq!(fresh.amount_in, 10_000_000);
+                assert!(fresh.expected_profit > 500_000);
+                assert!(fresh.amounts[1] > 0);
+            }
+            other => panic!("{:?}", other),
+        }
+
+        // the first pool moved: the 10% gap is gone
+        let (a, b) = (opportunity.mints[0], opportunity.mints[1]);
+        let moved = vec![
+            PoolQuote::new(Rc::new(PoolVariant::from(orca_pool(&a, &b, 1_000_000_000, 1_000_000_000)))),
+            pools[1].clone(),
+        ];
+        assert!(matches!(
+            requote(&config, &opportunity, &moved, &params(), None, None),
+            Requote::Abort(0)
+        ));
+    }
+
+    #[test]
+    fn resizing_finds_the_size_that_still_pays() {
+        // 50 a into 100 a of reserves eats the gap, 6.25 a doesn't
+        let (opportunity, pools) = route(100_000_000, 50_000_000, 1_000_000);
+        let config: RequoteConfig = serde_json::from_str(r#"{ "resize": false }"#).unwrap();
+        assert!(matches!(
+            requote(&config, &opportunity, &pools, &params(), None, None),
+            Requote::Abort(_)
+        ));
+
+        let config: RequoteConfig = serde_json::from_str(r#"{ "resize": true, "resize_steps": 3 }"#).unwrap();
+        match requote(&config, &opportunity, &pools, &params(), None, None) {
+            Requote::Send(resized) => {
+                assert_eq!(resized.amounts[0], 6_250_000);
+                assert_eq!(resized.amount_in, 6_250_000);
+                assert!(resized.expected_profit > 0);
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn fresh_quotes_are_padded_for_venues_that_under_deliver() {
+        let config: RequoteConfig = serde_json::from_str("{}").unwrap();
+        let (opportunity, pools) = route(1_000_000_000, 10_000_000, 500_000);
+        let unpadded = match requote(&config, &opportunity, &pools, &params(), None, None) {
+            Requote::Send(fresh) => fresh,
+            other => panic!("{:?}", other),
+        };
+
+        // both legs are orca, 20 bps each
+        let pads = BTreeMap::from([("orca".to_string(), 20)]);
+        let tracker = SlippageTracker::with_pads(&pads);
+        match requote(&config, &opportunity, &pools, &params(), Some(&tracker), None) {
+            Requote::Send(padded) => {
+                let pad = bps_ceil(unpadded.min_amount_out, 40);
+                assert_eq!(padded.min_amount_out, unpadded.min_amount_out - pad);
+                assert_eq!(padded.expected_profit, unpadded.expected_profit - pad as i128);
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+}
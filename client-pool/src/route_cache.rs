@@ -0,0 +1,192 @@
+//! Cache of profitable routes
+//!
+//! Opportunities tend to come back on the same routes. Every route a tick
+//! found profitable is kept by its mints and pool addresses, and the cached
+//! routes are quoted first on every tick; a few known cycles are much cheaper
+//! to quote than the graph is to walk. When one of them still pays, the tick
+//! goes with those and skips the search, otherwise it falls back to the
+//! search. The `max_routes` most recently profitable routes are kept and,
+//! with `path`, read from there at start and written back on exit. Routes
+//! through mints or pools that aren't loaded any more are skipped.
+
+use serde::{Deserialize, Serialize};
+
+use tracing::warn;
+
+use crate::arb::{ArbOpportunity, Arbitrager};
+use crate::pool::PoolOperations;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RouteCacheConfig {
+    /// Routes kept, the least recently profitable go first
+    #[serde(default = "default_max_routes")]
+    pub max_routes: usize,
+    /// JSON file the cache is loaded from at start and written to on exit;
+    /// kept in memory only if unset
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+fn default_max_routes() -> usize {
+    100
+}
+
+impl RouteCacheConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.max_routes == 0 {
+            problems.push("route_cache.max_routes must be > 0".to_string());
+        }
+        problems
+    }
+}
+
+/// A route that was profitable, by addresses so it outlives the run
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct CachedRoute {
+    /// Mints of the route, starting and ending with the start mint
+    pub mints: Vec<String>,
+    /// Pool of every hop
+    pub pools: Vec<String>,
+    /// Expected profit the last time it was found (scaled)
+    pub last_profit: i128,
+    /// Times it was found profitable
+    pub hits: u64,
+}
+
+/// The profitable routes, most recent first
+pub struct RouteCache {
+    max_routes: usize,
+    path: Option<String>,
+    routes: Vec<CachedRoute>,
+}
+
+impl RouteCache {
+    /// Starts from the routes in `config.path` if it exists
+    pub fn new(config: &RouteCacheConfig) -> Self {
+        let routes = match &config.path {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                    warn!("ignoring unreadable route cache {}: {}", path, e);
+                    vec![]
+                }),
+                Err(_) => vec![],
+            },
+            None => vec![],
+        };
+        let mut cache = Self {
+            max_routes: config.max_routes,
+            path: config.path.clone(),
+            routes,
+        };
+        cache.routes.truncate(cache.max_routes);
+        cache
+    }
+
+    pub fn routes(&self) -> &[CachedRoute] {
+        &self.routes
+    }
+
+    /// Moves the routes of `opportunities` to the front, the most profitable
+    /// first, and drops the oldest past `max_routes`
+    pub fn record(&mut self, opportunities: &[ArbOpportunity]) {
+        // opportunities are most profitable first, the first ends up in front
+        for opportunity in opportunities.iter().rev() {
+            let mints: Vec<String> = opportunity.mints.iter().map(|m| m.to_string()).collect();
+            let pools: Vec<String> =
+                opportunity.pools.iter().map(|p| p.0.get_address().to_string()).collect();
+            let hits = match self.routes.iter().position(|r| r.mints == mints && r.pools == pools) {
+                Some(i) => self.routes.remove(i).hits,
+                None => 0,
+            };
+            self.routes.insert(
+                0,
+                CachedRoute {
+                    mints,
+                    pools,
+                    last_profit: opportunity.expected_profit,
+                    hits: hits + 1,
+                },
+            );
+        }
+        self.routes.truncate(self.max_routes);
+    }
+
+    /// The cached routes from `start_mint_idx` that are profitable on the
+    /// graph's pools now, most profitable first
+    pub fn check(
+        &self,
+        arb: &Arbitrager,
+        start_mint_idx: usize,
+        init_balance: u128,
+        amount: u128,
+    ) -> Vec<ArbOpportunity> {
+        arb.requote_routes(
+            self.routes.iter().map(|route| (route.mints.as_slice(), route.pools.as_slice())),
+            start_mint_idx,
+            init_balance,
+            amount,
+        )
+    }
+
+    /// Writes the routes to `path`, if set
+    pub fn persist(&self) -> std::io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let json = serde_json::to_string_pretty(&self.routes)?;
+        std::fs::write(path, json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_client::solana_sdk::pubkey::Pubkey;
+    use crate::pools::PoolVariant;
+    use crate::tests::pools::orca_pool;
+    use crate::utils::PoolQuote;
+    use std::rc::Rc;
+    use std::time::Instant;
+
+    fn opportunity(mints: &[Pubkey], profit: i128) -> ArbOpportunity {
+        let pools: Vec<PoolQuote> = mints
+            .windows(2)
+            .map(|pair| PoolQuote::new(Rc::new(PoolVariant::from(orca_pool(&pair[0], &pair[1], 1_000, 1_000)))))
+            .collect();
+        ArbOpportunity {
+            route: format!("{} hops", pools.len()),
+            mints: mints.to_vec(),
+            pools,
+            amount_in: 1_000,
+            amounts: vec![],
+            min_amount_out: 1_000 + profit as u128,
+            expected_profit: profit,
+            found_slot: 0,
+            found_at: Instant::now(),
+            split: None,
+        }
+    }
+
+    #[test]
+    fn recent_routes_stay_and_repeats_count_hits() {
+        let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut cache = RouteCache::new(&RouteCacheConfig { max_routes: 2, path: None });
+        let ab = opportunity(&[a, b, a], 10);
+        let ac = opportunity(&[a, c, a], 20);
+        cache.record(&[ac.clone(), ab.clone()]);
+        assert_eq!(cache.routes()[0].mints[1], c.to_string());
+
+        // found again, it moves to the front; the other drops out past the limit
+        let abc = opportunity(&[a, b, c, a], 5);
+        cache.record(&[ab]);
+        cache.record(&[abc]);
+        let routes = cache.routes();
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].pools.len(), 3);
+        assert_eq!(routes[1].mints[1], b.to_string());
+        assert_eq!(routes[1].hits, 2);
+    }
+}
@@ -0,0 +1,536 @@
+//! RPC endpoint pool
+//!
+//! Wraps a list of RPC endpoints, health-checks them and routes reads to the
+//! fastest healthy node. The health check runs again every
+//! `HEALTH_CHECK_INTERVAL` (`health_check_if_due`), so a node that turns slow
+//! loses its place to a faster one. When a call fails the endpoint is put into an
+//! exponential backoff and the call is retried against the next one. Every
+//! endpoint has a request budget, and transient errors are retried with
+//! jitter (see `rate_limit.rs`).
+//!
+//! Account batches are requested zstd-compressed; an endpoint that isn't a
+//! known provider (see `provider.rs`) and rejects the encoding is switched to
+//! plain base64.
+//!
+//! A pool refresh takes many `getMultipleAccounts` chunks. They are fetched
+//! by up to `MAX_PARALLEL_CHUNKS` threads, spread round-robin over the
+//! healthy endpoints; a failed chunk is retried on the next endpoint after a
+//! short, growing pause, at most `CHUNK_ATTEMPTS` times.
+
+use anchor_client::solana_client::client_error::{ClientError, Result as ClientResult};
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use anchor_client::solana_client::rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType};
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use solana_account_decoder::UiAccountEncoding;
+use solana_sdk::account::Account;
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, info, warn};
+
+use crate::constants::TOKEN_PROGRAM_ID;
+use crate::graph::{AccountSource, ChunkFetch};
+use crate::provider::RpcProvider;
+use crate::rate_limit::{is_throttled, is_transient, jittered, RateLimitConfig, RateLimiter};
+use crate::serialize::token::TOKEN_ACCOUNT_LEN;
+
+/// Initial backoff applied after an endpoint's first failure
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+/// Upper bound on the backoff of a single endpoint
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Endpoints further behind the best observed slot are treated as unhealthy
+const MAX_SLOT_LAG: u64 = 25;
+/// Most account chunks fetched at the same time
+const MAX_PARALLEL_CHUNKS: usize = 8;
+/// Requests per chunk before it's given up on
+const CHUNK_ATTEMPTS: u32 = 3;
+/// Pause before a chunk's first retry, doubled for every further one
+const CHUNK_RETRY_DELAY: Duration = Duration::from_millis(100);
+/// Time between two health checks of the endpoints
+pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Health bookkeeping for a single endpoint
+#[derive(Debug, Clone)]
+struct EndpointHealth {
+    /// Latency of the last successful health check
+    latency: Option<Duration>,
+    /// Slot reported by the last successful health check
+    slot: u64,
+    /// Consecutive failures since the last success
+    failures: u32,
+    /// Endpoint is skipped until this instant
+    retry_after: Option<Instant>,
+}
+
+impl EndpointHealth {
+    fn new() -> Self {
+        Self {
+            latency: None,
+            slot: 0,
+            failures: 0,
+            retry_after: None,
+        }
+    }
+
+    fn is_backing_off(&self, now: Instant) -> bool {
+        match self.retry_after {
+            Some(t) => now < t,
+            None => false,
+        }
+    }
+}
+
+/// A single RPC endpoint and its current health
+pub struct RpcEndpoint {
+    pub url: String,
+    pub client: RpcClient,
+    pub provider: RpcProvider,
+    health: Mutex<EndpointHealth>,
+    /// cleared once the endpoint rejected zstd-compressed accounts
+    zstd: AtomicBool,
+    /// requests per second the endpoint may be sent
+    budget: RateLimiter,
+}
+
+impl RpcEndpoint {
+    pub fn new(url: &str, commitment: CommitmentConfig, budget: RateLimiter) -> Self {
+        Self {
+            url: url.to_string(),
+            client: RpcClient::new_with_commitment(url.to_string(), commitment),
+            provider: RpcProvider::detect(url),
+            health: Mutex::new(EndpointHealth::new()),
+            zstd: AtomicBool::new(true),
+            budget,
+        }
+    }
+
+    /// `getMultipleAccounts` with the endpoint's account encoding, plus the
+    /// slot the accounts were read at
+    fn get_multiple_accounts_with_slot(&self, pubkeys: &[Pubkey]) -> ClientResult<(Vec<Option<Account>>, u64)> {
+        let zstd = self.zstd.load(Ordering::Relaxed);
+        let config = RpcAccountInfoConfig {
+            encoding: Some(if zstd {
+                UiAccountEncoding::Base64Zstd
+            } else {
+                UiAccountEncoding::Base64
+            }),
+            commitment: Some(self.client.commitment()),
+            ..RpcAccountInfoConfig::default()
+        };
+        match self.client.get_multiple_accounts_with_config(pubkeys, config) {
+            Ok(response) => Ok((response.value, response.context.slot)),
+            Err(e) if zstd && !self.provider.supports_zstd() && rejects_zstd(&e) => {
+                info!("rpc endpoint {} doesn't serve zstd accounts, using base64", self.url);
+                self.zstd.store(false, Ordering::Relaxed);
+                self.get_multiple_accounts_with_slot(pubkeys)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn record_success(&self, latency: Option<Duration>, slot: Option<u64>) {
+        let mut health = self.health.lock().unwrap();
+        health.failures = 0;
+        health.retry_after = None;
+        if let Some(latency) = latency {
+            health.latency = Some(latency);
+        }
+        if let Some(slot) = slot {
+            health.slot = slot;
+        }
+    }
+
+    fn record_failure(&self, err: &ClientError) {
+        let mut health = self.health.lock().unwrap();
+        health.failures = health.failures.saturating_add(1);
+        let backoff = jittered(backoff_for(health.failures));
+        health.retry_after = Some(Instant::now() + backoff);
+        warn!(
+            "rpc endpoint {} {} ({} in a row), backing off {:?}: {}",
+            self.url,
+            if is_throttled(err) { "is throttling" } else { "failed" },
+            health.failures,
+            backoff,
+            err
+        );
+    }
+}
+
+/// Whether an error means the endpoint can't encode accounts with zstd
+fn rejects_zstd(err: &ClientError) -> bool {
+    let err = err.to_string().to_lowercase();
+    err.contains("zstd") || err.contains("encoding")
+}
+
+/// Exponential backoff for the given number of consecutive failures
+fn backoff_for(failures: u32) -> Duration {
+    let exp = failures.saturating_sub(1).min(16);
+    BASE_BACKOFF
+        .checked_mul(1 << exp)
+        .unwrap_or(MAX_BACKOFF)
+        .min(MAX_BACKOFF)
+}
+
+/// Pause before retrying a chunk that failed `attempts` times
+fn chunk_retry_delay(attempts: u32) -> Duration {
+    CHUNK_RETRY_DELAY * (1 << attempts.saturating_sub(1).min(8))
+}
+
+/// Whether a health check last run at `last` is due again at `now`
+fn health_check_due(last: Option<Instant>, now: Instant) -> bool {
+    last.map_or(true, |last| now.saturating_duration_since(last) >= HEALTH_CHECK_INTERVAL)
+}
+
+/// Pool of RPC endpoints with health checking and failover
+pub struct RpcPool {
+    endpoints: Vec<RpcEndpoint>,
+    /// longest wait for an endpoint's budget before trying the next one
+    max_wait: Duration,
+    /// rounds over the pool a call is retried after transient errors
+    max_retries: u32,
+    /// when the endpoints were last health-checked
+    last_health_check: Mutex<Option<Instant>>,
+}
+
+impl RpcPool {
+    /// Creates a pool from a list of endpoint URLs
+    ///
+    /// # Arguments
+    /// * `urls` - RPC endpoint URLs, in order of preference
+    /// * `commitment` - Commitment level used by every client in the pool
+    /// * `limits` - Request budget of every endpoint and retries of a call
+    pub fn new(urls: &[String], commitment: CommitmentConfig, limits: &RateLimitConfig) -> Self {
+        assert!(!urls.is_empty(), "rpc pool needs at least one endpoint");
+        let endpoints = urls
+            .iter()
+            .map(|url| RpcEndpoint::new(url, commitment, limits.limiter(url)))
+            .collect();
+        Self {
+            endpoints,
+            max_wait: limits.max_wait(),
+            max_retries: limits.max_retries,
+            last_health_check: Mutex::new(None),
+        }
+    }
+
+    pub fn endpoints(&self) -> &[RpcEndpoint] {
+        &self.endpoints
+    }
+
+    /// Pings every endpoint with `get_slot` and records latency + slot.
+    /// Endpoints lagging too far behind the best slot are put into backoff.
+    pub fn health_check(&self) {
+        *self.last_health_check.lock().unwrap() = Some(Instant::now());
+        for endpoint in self.endpoints.iter() {
+            let start = Instant::now();
+            match endpoint.client.get_slot() {
+                Ok(slot) => endpoint.record_success(Some(start.elapsed()), Some(slot)),
+                Err(e) => endpoint.record_failure(&e),
+            }
+        }
+
+        let best_slot = self
+            .endpoints
+            .iter()
+            .map(|e| e.health.lock().unwrap().slot)
+            .max()
+            .unwrap_or(0);
+
+        for endpoint in self.endpoints.iter() {
+            let mut health = endpoint.health.lock().unwrap();
+            if health.failures == 0 && best_slot.saturating_sub(health.slot) > MAX_SLOT_LAG {
+                health.failures = 1;
+                health.retry_after = Some(Instant::now() + backoff_for(1));
+                warn!(
+                    "rpc endpoint {} is lagging ({} < {})",
+                    endpoint.url, health.slot, best_slot
+                );
+            }
+            debug!(
+                "rpc endpoint {}: latency {:?}, slot {}, failures {}",
+                endpoint.url, health.latency, health.slot, health.failures
+            );
+        }
+    }
+
+    /// Runs `health_check` if the last one is `HEALTH_CHECK_INTERVAL` old;
+    /// latencies are only measured there, so the ranking follows nodes that
+    /// turn slow without failing
+    pub fn health_check_if_due(&self) {
+        let last = *self.last_health_check.lock().unwrap();
+        if health_check_due(last, Instant::now()) {
+            self.health_check();
+        }
+    }
+
+    /// Endpoint indexes ordered by preference: healthy endpoints first
+    /// (fastest first), then endpoints in backoff (soonest retry first).
+    fn ranked(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let snapshot: Vec<EndpointHealth> = self
+            .endpoints
+            .iter()
+            .map(|e| e.health.lock().unwrap().clone())
+            .collect();
+
+        let mut idxs: Vec<usize> = (0..self.endpoints.len()).collect();
+        idxs.sort_by_key(|&i| {
+            let h = &snapshot[i];
+            let backing_off = h.is_backing_off(now);
+            let retry = h.retry_after.map(|t| t.saturating_duration_since(now));
+            let latency = h.latency.unwrap_or(Duration::MAX);
+            (backing_off, retry, latency, i)
+        });
+        idxs
+    }
+
+    /// Returns the currently preferred endpoint
+    pub fn best(&self) -> &RpcEndpoint {
+        &self.endpoints[self.ranked()[0]]
+    }
+
+    /// How long until the first endpoint is out of backoff, if all of them
+    /// are backing off (e.g. throttled); `None` while any is usable
+    pub fn throttled_for(&self) -> Option<Duration> {
+        let now = Instant::now();
+        self.endpoints
+            .iter()
+            .map(|e| {
+                let health = e.health.lock().unwrap();
+                health
+                    .retry_after
+                    .filter(|_| health.is_backing_off(now))
+                    .map(|t| t - now)
+            })
+            .collect::<Option<Vec<Duration>>>()
+            .and_then(|waits| waits.into_iter().min())
+    }
+
+    /// Runs `f` against the best endpoint, failing over to the next one on error
+    pub fn call<T, F>(&self, f: F) -> ClientResult<T>
+    where
+        F: Fn(&RpcClient) -> ClientResult<T>,
+    {
+        self.call_endpoint(|endpoint| f(&endpoint.client))
+    }
+
+    /// Like `call`, for calls that need more of the endpoint than its client
+    ///
+    /// Endpoints without budget left within `max_wait` are skipped (the last
+    /// one is waited for if none was tried). If every endpoint failed and
+    /// the last error was transient, the pool is tried again after a
+    /// jittered pause, up to `max_retries` times.
+    fn call_endpoint<T, F>(&self, f: F) -> ClientResult<T>
+    where
+        F: Fn(&RpcEndpoint) -> ClientResult<T>,
+    {
+        let mut last_err: Option<ClientError> = None;
+        for round in 0..=self.max_retries {
+            if round > 0 {
+                thread::sleep(jittered(backoff_for(round)));
+            }
+            let ranked = self.ranked();
+            let mut tried = 0;
+            for (i, idx) in ranked.iter().enumerate() {
+                let endpoint = &self.endpoints[*idx];
+                let max_wait = if tried == 0 && i == ranked.len() - 1 {
+                    Duration::MAX
+                } else {
+                    self.max_wait
+                };
+                if !endpoint.budget.acquire(max_wait) {
+                    debug!("rpc endpoint {} is out of budget, skipping", endpoint.url);
+                    continue;
+                }
+                tried += 1;
+                match f(endpoint) {
+                    Ok(v) => {
+                        endpoint.record_success(None, None);
+                        return Ok(v);
+                    }
+                    Err(e) => {
+                        endpoint.record_failure(&e);
+                        last_err = Some(e);
+                    }
+                }
+            }
+            if !last_err.as_ref().map_or(false, is_transient) {
+                break;
+            }
+        }
+        info!("all {} rpc endpoints failed", self.endpoints.len());
+        Err(last_err.unwrap())
+    }
+
+    pub fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> ClientResult<Vec<Option<Account>>> {
+        self.call(|client| client.get_multiple_accounts(pubkeys))
+    }
+
+    /// Like `get_multiple_accounts`, plus the slot the accounts were read at
+    pub fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> ClientResult<(Vec<Option<Account>>, u64)> {
+        self.call_endpoint(|endpoint| endpoint.get_multiple_accounts_with_slot(pubkeys))
+    }
+
+    /// Fetches one chunk, starting at `endpoints[first]` and moving to the next
+    /// endpoint on every retry
+    fn fetch_chunk(&self, chunk: &[Pubkey], endpoints: &[usize], first: usize) -> ChunkFetch {
+        let mut attempts = 0;
+        loop {
+            let endpoint = &self.endpoints[endpoints[(first + attempts as usize) % endpoints.len()]];
+            attempts += 1;
+            endpoint.budget.acquire(Duration::MAX);
+            match endpoint.get_multiple_accounts_with_slot(chunk) {
+                Ok(fetched) => {
+                    endpoint.record_success(None, None);
+                    return ChunkFetch {
+                        result: Ok(fetched),
+                        attempts,
+                    };
+                }
+                Err(e) => {
+                    endpoint.record_failure(&e);
+                    if attempts >= CHUNK_ATTEMPTS {
+                        return ChunkFetch {
+                            result: Err(e),
+                            attempts,
+                        };
+                    }
+                    thread::sleep(jittered(chunk_retry_delay(attempts)));
+                }
+            }
+        }
+    }
+
+    /// Every account of `program_id` whose data is exactly `data_size` bytes
+    pub fn get_program_accounts_with_size(
+        &self,
+        program_id: &Pubkey,
+        data_size: usize,
+    ) -> ClientResult<Vec<(Pubkey, Account)>> {
+        self.call(|client| {
+            let config = RpcProgramAccountsConfig {
+                filters: Some(vec![RpcFilterType::DataSize(data_size as u64)]),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    commitment: Some(client.commitment()),
+                    ..RpcAccountInfoConfig::default()
+                },
+                ..RpcProgramAccountsConfig::default()
+            };
+            client.get_program_accounts_with_config(program_id, config)
+        })
+    }
+
+    /// Every spl-token account owned by `owner`, ATA or not
+    pub fn get_token_accounts_of(&self, owner: &Pubkey) -> ClientResult<Vec<(Pubkey, Account)>> {
+        self.call(|client| {
+            let config = RpcProgramAccountsConfig {
+                filters: Some(vec![
+                    RpcFilterType::DataSize(TOKEN_ACCOUNT_LEN as u64),
+                    // the owner follows the mint
+                    RpcFilterType::Memcmp(Memcmp {
+                        offset: 32,
+                        bytes: MemcmpEncodedBytes::Base58(owner.to_string()),
+                        encoding: None,
+                    }),
+                ]),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    commitment: Some(client.commitment()),
+                    ..RpcAccountInfoConfig::default()
+                },
+                ..RpcProgramAccountsConfig::default()
+            };
+            client.get_program_accounts_with_config(&TOKEN_PROGRAM_ID, config)
+        })
+    }
+}
+
+impl AccountSource for RpcPool {
+    fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> ClientResult<(Vec<Option<Account>>, u64)> {
+        RpcPool::get_multiple_accounts_with_slot(self, pubkeys)
+    }
+
+    fn get_chunks_with_slot(&self, chunks: &[&[Pubkey]]) -> Vec<ChunkFetch> {
+        // healthy endpoints share the chunks, retries fail over to the rest
+        let now = Instant::now();
+        let ranked = self.ranked();
+        let healthy = ranked
+            .iter()
+            .filter(|&&i| !self.endpoints[i].health.lock().unwrap().is_backing_off(now))
+            .count()
+            .max(1);
+
+        let next = AtomicUsize::new(0);
+        let fetched: Mutex<Vec<Option<ChunkFetch>>> = Mutex::new(chunks.iter().map(|_| None).collect());
+        thread::scope(|s| {
+            for _ in 0..chunks.len().min(MAX_PARALLEL_CHUNKS) {
+                s.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= chunks.len() {
+                        break;
+                    }
+                    let fetch = self.fetch_chunk(chunks[i], &ranked, i % healthy);
+                    fetched.lock().unwrap()[i] = Some(fetch);
+                });
+            }
+        });
+        fetched
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|fetch| fetch.expect("every chunk is fetched"))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zstd_rejections_are_recognized() {
+        use anchor_client::solana_client::client_error::ClientErrorKind;
+
+        let rejected = ClientError::from(ClientErrorKind::Custom(
+            "RPC response error -32602: base64+zstd encoding not supported".to_string(),
+        ));
+        let other = ClientError::from(ClientErrorKind::Custom("429 Too Many Requests".to_string()));
+        assert!(rejects_zstd(&rejected));
+        assert!(!rejects_zstd(&other));
+    }
+
+    #[test]
+    fn backoff_grows_and_caps() {
+        assert_eq!(backoff_for(1), BASE_BACKOFF);
+        assert_eq!(backoff_for(2), BASE_BACKOFF * 2);
+        assert_eq!(backoff_for(3), BASE_BACKOFF * 4);
+        assert_eq!(backoff_for(100), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn health_checks_come_back_every_interval() {
+        let now = Instant::now();
+        assert!(health_check_due(None, now));
+        assert!(!health_check_due(Some(now), now + HEALTH_CHECK_INTERVAL / 2));
+        assert!(health_check_due(Some(now), now + HEALTH_CHECK_INTERVAL));
+    }
+
+    #[test]
+    fn chunk_retries_wait_longer_each_time() {
+        assert_eq!(chunk_retry_delay(1), CHUNK_RETRY_DELAY);
+        assert_eq!(chunk_retry_delay(2), CHUNK_RETRY_DELAY * 2);
+        assert!(chunk_retry_delay(CHUNK_ATTEMPTS) < BASE_BACKOFF * 2);
+    }
+}
@@ -0,0 +1,260 @@
+//! RPC operations of the trading loop
+//!
+//! The pool refreshes, simulations and blockhash fetches of the bot go
+//! through `RpcApi` (its account reads are the `AccountSource` the graph
+//! builder and the updater read from), and sends take the same shape.
+//! `RpcClient` implements it against a node; `MockRpc` answers from memory,
+//! so the graph builder, the updater and the executor's simulations and
+//! sends can be tested deterministically without a cluster.
+
+use anchor_client::solana_client::client_error::{ClientErrorKind, Result as ClientResult};
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_client::rpc_config::RpcSendTransactionConfig;
+use anchor_client::solana_client::rpc_response::{
+    Response, RpcResponseContext, RpcResult, RpcSimulateTransactionResult,
+};
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use serde_json::json;
+use solana_sdk::account::Account;
+use solana_sdk::hash::Hash;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::{Transaction, TransactionError};
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::graph::AccountSource;
+use crate::sender::{self, MIN_CONTEXT_SLOT_NOT_REACHED};
+
+/// The RPC calls the bot makes, besides its account reads
+pub trait RpcApi: AccountSource {
+    /// Sends `tx`, refused by a node behind `min_context_slot` if set
+    fn send(
+        &self,
+        tx: &Transaction,
+        config: RpcSendTransactionConfig,
+        min_context_slot: Option<u64>,
+    ) -> ClientResult<Signature>;
+
+    /// Simulates `tx`, refused by a node behind `min_context_slot` if set
+    fn simulate(
+        &self,
+        tx: &Transaction,
+        min_context_slot: Option<u64>,
+    ) -> RpcResult<RpcSimulateTransactionResult>;
+
+    /// Latest blockhash at `commitment` and the last block height it is valid at
+    fn latest_blockhash(&self, commitment: CommitmentConfig) -> ClientResult<(Hash, u64)>;
+}
+
+impl RpcApi for RpcClient {
+    fn send(
+        &self,
+        tx: &Transaction,
+        config: RpcSendTransactionConfig,
+        min_context_slot: Option<u64>,
+    ) -> ClientResult<Signature> {
+        sender::send_transaction(self, tx, config, min_context_slot)
+    }
+
+    fn simulate(
+        &self,
+        tx: &Transaction,
+        min_context_slot: Option<u64>,
+    ) -> RpcResult<RpcSimulateTransactionResult> {
+        sender::simulate_transaction(self, tx, min_context_slot)
+    }
+
+    fn latest_blockhash(&self, commitment: CommitmentConfig) -> ClientResult<(Hash, u64)> {
+        self.get_latest_blockhash_with_commitment(commitment)
+    }
+}
+
+/// What `MockRpc` serves and what it was sent
+#[derive(Default)]
+struct MockState {
+    accounts: HashMap<Pubkey, Account>,
+    slot: u64,
+    blockhash: (Hash, u64),
+    /// answers of the next simulations, a success without logs once empty
+    simulations: VecDeque<RpcSimulateTransactionResult>,
+    /// every call fails with this while set
+    failure: Option<String>,
+    sent: Vec<Transaction>,
+    simulated: Vec<Transaction>,
+    /// `getMultipleAccounts` requests served
+    reads: usize,
+}
+
+/// A node in memory: serves the accounts, slot and blockhash it is given,
+/// answers simulations from a queue and records every transaction it gets
+#[derive(Default)]
+pub struct MockRpc {
+    state: Mutex<MockState>,
+}
+
+impl MockRpc {
+    /// A node at `slot` without any account
+    pub fn new(slot: u64) -> Self {
+        let mock = Self::default();
+        mock.set_slot(slot);
+        mock.set_blockhash(Hash::new_unique(), slot + 150);
+        mock
+    }
+
+    pub fn set_account(&self, pubkey: Pubkey, account: Account) {
+        self.state.lock().unwrap().accounts.insert(pubkey, account);
+    }
+
+    pub fn remove_account(&self, pubkey: &Pubkey) {
+        self.state.lock().unwrap().accounts.remove(pubkey);
+    }
+
+    /// Slot reads are served at, sends and simulations behind it are refused
+    pub fn set_slot(&self, slot: u64) {
+        self.state.lock().unwrap().slot = slot;
+    }
+
+    pub fn set_blockhash(&self, blockhash: Hash, last_valid_block_height: u64) {
+        self.state.lock().unwrap().blockhash = (blockhash, last_valid_block_height);
+    }
+
+    /// Queues the answer of the next simulation
+    pub fn push_simulation(&self, result: RpcSimulateTransactionResult) {
+        self.state.lock().unwrap().simulations.push_back(result);
+    }
+
+    /// Fails every call with `error` until cleared with `None`
+    pub fn fail_with(&self, error: Option<&str>) {
+        self.state.lock().unwrap().failure = error.map(str::to_string);
+    }
+
+    /// Transactions sent so far
+    pub fn sent(&self) -> Vec<Transaction> {
+        self.state.lock().unwrap().sent.clone()
+    }
+
+    /// Transactions simulated so far
+    pub fn simulated(&self) -> Vec<Transaction> {
+        self.state.lock().unwrap().simulated.clone()
+    }
+
+    /// `getMultipleAccounts` requests served so far
+    pub fn reads(&self) -> usize {
+        self.state.lock().unwrap().reads
+    }
+
+    /// A simulation result with `err` and `logs`
+    pub fn simulation(err: Option<TransactionError>, logs: &[&str]) -> RpcSimulateTransactionResult {
+        // from json, newer sdks add fields the struct literal would miss
+        serde_json::from_value(json!({ "err": err, "logs": logs, "accounts": null }))
+            .expect("simulation result")
+    }
+
+    /// The state, or the error of a call that fails (set by `fail_with`, or
+    /// a `min_context_slot` the node hasn't reached)
+    fn serve(&self, min_context_slot: Option<u64>) -> ClientResult<MutexGuard<MockState>> {
+        let state = self.state.lock().unwrap();
+        if let Some(failure) = &state.failure {
+            return Err(ClientErrorKind::Custom(failure.clone()).into());
+        }
+        match min_context_slot {
+            Some(min) if min > state.slot => Err(ClientErrorKind::Custom(format!(
+                "{}: context slot {}",
+                MIN_CONTEXT_SLOT_NOT_REACHED, state.slot
+            ))
+            .into()),
+            _ => Ok(state),
+        }
+    }
+}
+
+impl AccountSource for MockRpc {
+    fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> ClientResult<(Vec<Option<Account>>, u64)> {
+        let mut state = self.serve(None)?;
+        state.reads += 1;
+        let accounts = pubkeys.iter().map(|pk| state.accounts.get(pk).cloned()).collect();
+        Ok((accounts, state.slot))
+    }
+}
+
+impl RpcApi for MockRpc {
+    fn send(
+        &self,
+        tx: &Transaction,
+        _config: RpcSendTransactionConfig,
+        min_context_slot: Option<u64>,
+    ) -> ClientResult<Signature> {
+        let mut state = self.serve(min_context_slot)?;
+        state.sent.push(tx.clone());
+        Ok(tx.signatures.first().copied().unwrap_or_default())
+    }
+
+    fn simulate(
+        &self,
+        tx: &Transaction,
+        min_context_slot: Option<u64>,
+    ) -> RpcResult<RpcSimulateTransactionResult> {
+        let mut state = self.serve(min_context_slot)?;
+        state.simulated.push(tx.clone());
+        let value = match state.simulations.pop_front() {
+            Some(result) => result,
+            None => Self::simulation(None, &[]),
+        };
+        Ok(Response {
+            context: RpcResponseContext { slot: state.slot },
+            value,
+        })
+    }
+
+    fn latest_blockhash(&self, _commitment: CommitmentConfig) -> ClientResult<(Hash, u64)> {
+        Ok(self.serve(None)?.blockhash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sender::behind_context_slot;
+    use solana_sdk::instruction::InstructionError;
+    use solana_sdk::signature::{Keypair, Signer};
+
+    fn transaction(payer: &Keypair, blockhash: Hash) -> Transaction {
+        Transaction::new_signed_with_payer(&[], Some(&payer.pubkey()), &[payer], blockhash)
+    }
+
+    #[test]
+    fn mock_serves_what_it_is_given() {
+        let rpc = MockRpc::new(100);
+        let (present, missing) = (Pubkey::new_unique(), Pubkey::new_unique());
+        rpc.set_account(present, Account::new(1, 0, &Pubkey::default()));
+        let (accounts, slot) = rpc.get_multiple_accounts_with_slot(&[present, missing]).unwrap();
+        assert_eq!(accounts[0].as_ref().map(|a| a.lamports), Some(1));
+        assert_eq!((accounts[1].is_none(), slot), (true, 100));
+
+        let payer = Keypair::new();
+        let (blockhash, _) = rpc.latest_blockhash(CommitmentConfig::confirmed()).unwrap();
+        let tx = transaction(&payer, blockhash);
+        let config = RpcSendTransactionConfig::default();
+        assert_eq!(rpc.send(&tx, config, Some(100)).unwrap(), tx.signatures[0]);
+        let behind = rpc.send(&tx, config, Some(101)).unwrap_err();
+        assert!(behind_context_slot(&behind.to_string()));
+        assert_eq!(rpc.sent(), vec![tx.clone()]);
+
+        let failed = TransactionError::InstructionError(0, InstructionError::Custom(6000));
+        rpc.push_simulation(MockRpc::simulation(Some(failed.clone()), &["Program log: no profit"]));
+        assert_eq!(rpc.simulate(&tx, None).unwrap().value.err, Some(failed));
+        assert_eq!(rpc.simulate(&tx, None).unwrap().value.err, None);
+        assert_eq!(rpc.simulated().len(), 2);
+
+        rpc.fail_with(Some("connection refused"));
+        assert!(rpc.get_multiple_accounts_with_slot(&[present]).is_err());
+        assert!(rpc.latest_blockhash(CommitmentConfig::confirmed()).is_err());
+        rpc.fail_with(None);
+        assert_eq!(rpc.reads(), 1);
+    }
+}
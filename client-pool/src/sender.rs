@@ -0,0 +1,297 @@
+//! Broadcast transaction sender
+//!
+//! Fires a signed transaction at every configured send endpoint (and
+//! optionally a Jito block engine) at once, dedups the returned signatures
+//! and keeps track of which endpoint landed transactions first.
+//!
+//! Route transactions are built from pool accounts read at some slot; with
+//! `commitment.min_context_slot` they are sent and simulated with
+//! `minContextSlot` set to it, so a node that lags behind that slot refuses
+//! them instead of running them against older pool state.
+
+use anchor_client::solana_client::client_error::{ClientErrorKind, Result as ClientResult};
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_client::rpc_config::RpcSendTransactionConfig;
+use anchor_client::solana_client::rpc_request::RpcRequest;
+use anchor_client::solana_client::rpc_response::{RpcResult, RpcSimulateTransactionResult};
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use serde_json::{json, Value};
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::{Transaction, TransactionError};
+
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+/// How often signature statuses are polled while waiting for a landing
+const CONFIRM_POLL_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Error message of a node that hasn't reached the requested `minContextSlot`
+pub(crate) const MIN_CONTEXT_SLOT_NOT_REACHED: &str = "Minimum context slot has not been reached";
+
+/// Params of a `sendTransaction` or `simulateTransaction` request of `tx`:
+/// the base64 transaction and `config` with `minContextSlot` set
+pub fn context_slot_params(tx: &Transaction, mut config: Value, min_context_slot: u64) -> ClientResult<Value> {
+    let wire =
+        bincode::serialize(tx).map_err(|e| ClientErrorKind::Custom(format!("serialize: {}", e)))?;
+    config["encoding"] = json!("base64");
+    config["minContextSlot"] = json!(min_context_slot);
+    Ok(json!([base64::encode(wire), config]))
+}
+
+/// Sends `tx` like `send_transaction_with_config`, refused by a node behind
+/// `min_context_slot` if set
+pub fn send_transaction(
+    client: &RpcClient,
+    tx: &Transaction,
+    config: RpcSendTransactionConfig,
+    min_context_slot: Option<u64>,
+) -> ClientResult<Signature> {
+    let slot = match min_context_slot {
+        Some(slot) => slot,
+        None => return client.send_transaction_with_config(tx, config),
+    };
+    let config = json!({
+        "skipPreflight": config.skip_preflight,
+        "preflightCommitment": config.preflight_commitment,
+        "maxRetries": config.max_retries,
+    });
+    let signature: String = client.send(RpcRequest::SendTransaction, context_slot_params(tx, config, slot)?)?;
+    signature
+        .parse()
+        .map_err(|_| ClientErrorKind::Custom(format!("invalid signature {}", signature)).into())
+}
+
+/// Simulates `tx` at the client's commitment like `simulate_transaction`,
+/// refused by a node behind `min_context_slot` if set
+pub fn simulate_transaction(
+    client: &RpcClient,
+    tx: &Transaction,
+    min_context_slot: Option<u64>,
+) -> RpcResult<RpcSimulateTransactionResult> {
+    let slot = match min_context_slot {
+        Some(slot) => slot,
+        None => return client.simulate_transaction(tx),
+    };
+    let config = json!({ "commitment": client.commitment().commitment });
+    client.send(RpcRequest::SimulateTransaction, context_slot_params(tx, config, slot)?)
+}
+
+/// Whether a send or simulation failed because the node lags behind its
+/// `minContextSlot`, which says nothing about the route
+pub fn behind_context_slot(error: &str) -> bool {
+    error.contains(MIN_CONTEXT_SLOT_NOT_REACHED)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndpointKind {
+    Rpc,
+    Jito,
+}
+
+pub struct SendEndpoint {
+    pub url: String,
+    pub kind: EndpointKind,
+    pub client: RpcClient,
+}
+
+impl SendEndpoint {
+    pub fn new(url: &str, kind: EndpointKind, commitment: CommitmentConfig) -> Self {
+        Self {
+            url: url.to_string(),
+            kind,
+            client: RpcClient::new_with_commitment(url.to_string(), commitment),
+        }
+    }
+}
+
+/// Outcome of a single broadcast
+#[derive(Debug, Clone)]
+pub struct BroadcastResult {
+    pub signature: Option<Signature>,
+    /// Endpoints that accepted the transaction, in the order they answered
+    pub acks: Vec<(String, Duration)>,
+    pub errors: Vec<(String, String)>,
+    /// Endpoint that first reported the transaction as landed
+    pub landed_by: Option<String>,
+    /// Why the transaction failed, if it landed and failed
+    pub error: Option<TransactionError>,
+}
+
+impl BroadcastResult {
+    pub fn first_ack(&self) -> Option<&str> {
+        self.acks.first().map(|(url, _)| url.as_str())
+    }
+}
+
+pub struct BroadcastSender {
+    endpoints: Vec<SendEndpoint>,
+    /// status a transaction has to reach to count as landed
+    commitment: CommitmentConfig,
+    /// number of transactions each endpoint landed first
+    landed_counts: Mutex<HashMap<String, u64>>,
+}
+
+impl BroadcastSender {
+    /// Creates a sender from the configured send endpoints
+    ///
+    /// # Arguments
+    /// * `send_urls` - Plain RPC endpoints transactions are sent to
+    /// * `jito_url` - Optional Jito block engine `sendTransaction` endpoint
+    /// * `commitment` - Status a transaction has to reach to count as landed
+    pub fn new(send_urls: &[String], jito_url: Option<&str>, commitment: CommitmentConfig) -> Self {
+        let mut endpoints: Vec<SendEndpoint> = send_urls
+            .iter()
+            .map(|url| SendEndpoint::new(url, EndpointKind::Rpc, commitment))
+            .collect();
+        if let Some(url) = jito_url {
+            endpoints.push(SendEndpoint::new(url, EndpointKind::Jito, commitment));
+        }
+        assert!(!endpoints.is_empty(), "broadcast sender needs at least one endpoint");
+        Self {
+            endpoints,
+            commitment,
+            landed_counts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn endpoints(&self) -> &[SendEndpoint] {
+        &self.endpoints
+    }
+
+    /// Sends `tx` to every endpoint concurrently, with `minContextSlot` set
+    /// to `min_context_slot` if given
+    pub fn broadcast(&self, tx: &Transaction, min_context_slot: Option<u64>) -> BroadcastResult {
+        let config = RpcSendTransactionConfig {
+            skip_preflight: true,
+            preflight_commitment: Some(self.commitment.commitment),
+            ..RpcSendTransactionConfig::default()
+        };
+        let start = Instant::now();
+        let (sender, receiver) = mpsc::channel();
+
+        thread::scope(|s| {
+            for endpoint in self.endpoints.iter() {
+                let sender = sender.clone();
+                s.spawn(move || {
+                    let res = send_transaction(&endpoint.client, tx, config, min_context_slot);
+                    // receiver outlives the scope, send can't fail
+                    sender.send((endpoint.url.clone(), res, start.elapsed())).unwrap();
+                });
+            }
+        });
+        drop(sender);
+
+        let mut signatures = HashSet::new();
+        let mut acks = vec![];
+        let mut errors = vec![];
+        for (url, res, elapsed) in receiver.iter() {
+            match res {
+                Ok(sig) => {
+                    signatures.insert(sig);
+                    acks.push((url, elapsed));
+                }
+                Err(e) => {
+                    let e = e.to_string();
+                    if behind_context_slot(&e) {
+                        warn!("{} is behind slot {:?}, not sent there", url, min_context_slot);
+                    } else {
+                        warn!("send to {} failed: {}", url, e);
+                    }
+                    errors.push((url, e));
+                }
+            }
+        }
+        if signatures.len() > 1 {
+            warn!("endpoints returned {} different signatures", signatures.len());
+        }
+
+        BroadcastResult {
+            signature: tx.signatures.first().copied().filter(|s| signatures.contains(s)),
+            acks,
+            errors,
+            landed_by: None,
+            error: None,
+        }
+    }
+
+    /// Polls every endpoint until one reports the signature as landed (at
+    /// the sender's commitment) or `timeout` elapses, and records the first
+    /// endpoint to see it.
+    pub fn confirm(&self, result: &mut BroadcastResult, timeout: Duration) -> bool {
+        let signature = match result.signature {
+            Some(sig) => sig,
+            None => return false,
+        };
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            for endpoint in self.endpoints.iter() {
+                if endpoint.kind == EndpointKind::Jito {
+                    continue; // block engines don't serve signature statuses
+                }
+                let statuses = match endpoint.client.get_signature_statuses(&[signature]) {
+                    Ok(res) => res.value,
+                    Err(_) => continue,
+                };
+                let status = statuses
+                    .first()
+                    .and_then(|status| status.as_ref())
+                    .filter(|status| status.satisfies_commitment(self.commitment));
+                if let Some(status) = status {
+                    let landed_by = result.first_ack().unwrap_or(&endpoint.url).to_string();
+                    info!(
+                        "{} landed in slot {} (first ack: {})",
+                        signature, status.slot, landed_by
+                    );
+                    *self
+                        .landed_counts
+                        .lock()
+                        .unwrap()
+                        .entry(landed_by.clone())
+                        .or_insert(0) += 1;
+                    result.landed_by = Some(landed_by);
+                    result.error = status.err.clone();
+                    return status.err.is_none();
+                }
+            }
+            thread::sleep(CONFIRM_POLL_INTERVAL);
+        }
+        false
+    }
+
+    /// Number of landed transactions per endpoint that acked them first
+    pub fn landed_counts(&self) -> HashMap<String, u64> {
+        self.landed_counts.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::{Keypair, Signer};
+    use solana_sdk::system_instruction;
+
+    #[test]
+    fn context_slot_params_carry_the_transaction() {
+        let payer = Keypair::new();
+        let ix = system_instruction::transfer(&payer.pubkey(), &payer.pubkey(), 1);
+        let tx =
+            Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], Default::default());
+        let params = context_slot_params(&tx, json!({ "skipPreflight": true }), 1234).unwrap();
+
+        assert_eq!(params[1]["minContextSlot"], json!(1234));
+        assert_eq!(params[1]["encoding"], json!("base64"));
+        assert_eq!(params[1]["skipPreflight"], json!(true));
+        let wire = base64::decode(params[0].as_str().unwrap()).unwrap();
+        assert_eq!(bincode::deserialize::<Transaction>(&wire).unwrap(), tx);
+
+        assert!(behind_context_slot(
+            "RPC response error -32016: Minimum context slot has not been reached"
+        ));
+        assert!(!behind_context_slot("Blockhash not found"));
+    }
+}
@@ -1,2 +0,0 @@
-pub mod pool; 
-pub mod token;
\ No newline at end of file
@@ -0,0 +1,334 @@
+//! Session statistics
+//!
+//! Counts what the bot did during one run (opportunities found, sent, landed,
+//! PnL, fees, errors) and produces the summary printed and persisted on exit.
+
+use serde::Serialize;
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Number of routes listed in the best / worst sections of the summary
+const ROUTES_IN_SUMMARY: usize = 3;
+
+/// Number of recently found opportunities kept for the control api
+const RECENT_OPPORTUNITIES: usize = 50;
+
+/// Base fee of a transaction signature (lamports)
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// Buckets free-form send / confirm errors into a small set of categories
+pub fn categorize_error(err: &str) -> &'static str {
+    let err = err.to_lowercase();
+    if err.contains("blockhash") {
+        "blockhash"
+    } else if err.contains("429") || err.contains("rate limit") || err.contains("too many") {
+        "rate_limited"
+    } else if err.contains("timed out") || err.contains("timeout") {
+        "timeout"
+    } else if err.contains("insufficient") {
+        "insufficient_funds"
+    } else if err.contains("custom program error") || err.contains("instructionerror") {
+        "program_error"
+    } else if err.contains("connection") || err.contains("dns") {
+        "network"
+    } else {
+        "other"
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RouteStats {
+    pub found: u64,
+    pub landed: u64,
+    /// Sum of the quoted profits of every time this route was found (scaled start mint)
+    pub quoted_profit: i128,
+}
+
+/// A profitable cycle found by the search
+#[derive(Debug, Clone, Serialize)]
+pub struct Opportunity {
+    pub route: String,
+    pub input: u128,
+    pub output: u128,
+    pub found_unix: u64,
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[derive(Debug, Default)]
+struct SessionState {
+    found: u64,
+    executed: u64,
+    landed: u64,
+    gross_pnl: i128,
+    fees_lamports: u64,
+    tips_lamports: u64,
+    swept: u128,
+    routes: HashMap<String, RouteStats>,
+    errors: HashMap<&'static str, u64>,
+    failure_reasons: HashMap<String, u64>,
+    recent: VecDeque<Opportunity>,
+}
+
+/// Running counters of a single bot session
+pub struct SessionStats {
+    started: Instant,
+    started_unix: u64,
+    start_balance: u128,
+    state: Mutex<SessionState>,
+}
+
+impl SessionStats {
+    /// # Arguments
+    /// * `start_balance` - Start-mint balance of the wallet when the session begins
+    pub fn new(start_balance: u128) -> Self {
+        Self {
+            started: Instant::now(),
+            started_unix: unix_now(),
+            start_balance,
+            state: Mutex::new(SessionState::default()),
+        }
+    }
+
+    /// A profitable cycle was found (`input` / `output` in scaled start-mint units)
+    pub fn record_found(&self, route: &str, input: u128, output: u128) {
+        let mut state = self.state.lock().unwrap();
+        state.found += 1;
+        let entry = state.routes.entry(route.to_string()).or_default();
+        entry.found += 1;
+        entry.quoted_profit += output as i128 - input as i128;
+
+        if state.recent.len() == RECENT_OPPORTUNITIES {
+            state.recent.pop_front();
+        }
+        state.recent.push_back(Opportunity {
+            route: route.to_string(),
+            input,
+            output,
+            found_unix: unix_now(),
+        });
+    }
+
+    /// Most recently found opportunities, newest last
+    pub fn recent(&self) -> Vec<Opportunity> {
+        self.state.lock().unwrap().recent.iter().cloned().collect()
+    }
+
+    /// A transaction for the route was sent
+    pub fn record_executed(&self) {
+        self.state.lock().unwrap().executed += 1;
+    }
+
+    /// A transaction landed successfully
+    ///
+    /// # Arguments
+    /// * `route` - Route label the transaction executed
+    /// * `profit` - Quoted profit of the route (scaled start mint)
+    /// * `fee_lamports` - Signature fees paid
+    /// * `tip_lamports` - Tip paid to the block engine
+    pub fn record_landed(&self, route: &str, profit: i128, fee_lamports: u64, tip_lamports: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.landed += 1;
+        state.gross_pnl += profit;
+        state.fees_lamports += fee_lamports;
+        state.tips_lamports += tip_lamports;
+        state.routes.entry(route.to_string()).or_default().landed += 1;
+    }
+
+    /// `amount` of the start mint (scaled) was swept out of the wallet
+    pub fn record_swept(&self, amount: u128) {
+        self.state.lock().unwrap().swept += amount;
+    }
+
+    pub fn record_error(&self, err: &str) {
+        *self
+            .state
+            .lock()
+            .unwrap()
+            .errors
+            .entry(categorize_error(err))
+            .or_insert(0) += 1;
+    }
+
+    /// A route failed on chain for `reason` (see `failure.rs`)
+    pub fn record_failure_reason(&self, reason: &str) {
+        *self
+            .state
+            .lock()
+            .unwrap()
+            .failure_reasons
+            .entry(reason.to_string())
+            .or_insert(0) += 1;
+    }
+
+    /// Builds the summary of the session so far
+    ///
+    /// # Arguments
+    /// * `end_balance` - Start-mint balance now, if it could be read; the
+    ///   difference to the start balance (plus what was swept out) is
+    ///   reported as the net PnL
+    pub fn summary(&self, end_balance: Option<u128>) -> SessionSummary {
+        let state = self.state.lock().unwrap();
+
+        let mut routes: Vec<(String, RouteStats)> = state
+            .routes
+            .iter()
+            .map(|(route, stats)| (route.clone(), stats.clone()))
+            .collect();
+        routes.sort_by(|a, b| b.1.quoted_profit.cmp(&a.1.quoted_profit).then(a.0.cmp(&b.0)));
+        let best_routes: Vec<_> = routes.iter().take(ROUTES_IN_SUMMARY).cloned().collect();
+        let worst_routes: Vec<_> = routes.iter().rev().take(ROUTES_IN_SUMMARY).cloned().collect();
+
+        let mut top_errors: Vec<(String, u64)> = state
+            .errors
+            .iter()
+            .map(|(category, count)| (category.to_string(), *count))
+            .collect();
+        top_errors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let mut failure_reasons: Vec<(String, u64)> =
+            state.failure_reasons.iter().map(|(reason, count)| (reason.clone(), *count)).collect();
+        failure_reasons.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        SessionSummary {
+            started_unix: self.started_unix,
+            runtime_secs: self.started.elapsed().as_secs(),
+            opportunities_found: state.found,
+            executed: state.executed,
+            landed: state.landed,
+            gross_pnl: state.gross_pnl,
+            net_pnl: end_balance.map(|b| b as i128 + state.swept as i128 - self.start_balance as i128),
+            swept: state.swept,
+            fees_lamports: state.fees_lamports,
+            tips_lamports: state.tips_lamports,
+            best_routes,
+            worst_routes,
+            top_errors,
+            failure_reasons,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    pub started_unix: u64,
+    pub runtime_secs: u64,
+    pub opportunities_found: u64,
+    pub executed: u64,
+    pub landed: u64,
+    /// Sum of the quoted profits of landed transactions (scaled start mint)
+    pub gross_pnl: i128,
+    /// Change of the wallet's start-mint balance over the session, sweeps
+    /// added back (scaled)
+    pub net_pnl: Option<i128>,
+    /// Start mint swept to the cold wallet (scaled)
+    pub swept: u128,
+    pub fees_lamports: u64,
+    pub tips_lamports: u64,
+    pub best_routes: Vec<(String, RouteStats)>,
+    pub worst_routes: Vec<(String, RouteStats)>,
+    pub top_errors: Vec<(String, u64)>,
+    /// Why routes failed on chain, most frequent first
+    pub failure_reasons: Vec<(String, u64)>,
+}
+
+impl SessionSummary {
+    /// Writes the summary as `session-<start time>.json` into `dir`
+    pub fn persist(&self, dir: &str) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let path = Path::new(dir).join(format!("session-{}.json", self.started_unix));
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, json)?;
+        Ok(path)
+    }
+}
+
+impl fmt::Display for SessionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "===== session summary =====")?;
+        writeln!(f, "runtime:       {}s", self.runtime_secs)?;
+        writeln!(
+            f,
+            "opportunities: {} found, {} executed, {} landed",
+            self.opportunities_found, self.executed, self.landed
+        )?;
+        writeln!(f, "gross pnl:     {}", self.gross_pnl)?;
+        match self.net_pnl {
+            Some(net) => writeln!(f, "net pnl:       {}", net)?,
+            None => writeln!(f, "net pnl:       unknown (end balance unavailable)")?,
+        }
+        if self.swept > 0 {
+            writeln!(f, "swept:         {}", self.swept)?;
+        }
+        writeln!(
+            f,
+            "fees / tips:   {} / {} lamports",
+            self.fees_lamports, self.tips_lamports
+        )?;
+        writeln!(f, "best routes:")?;
+        for (route, stats) in self.best_routes.iter() {
+            writeln!(f, "  {:>12} x{} {}", stats.quoted_profit, stats.found, route)?;
+        }
+        writeln!(f, "worst routes:")?;
+        for (route, stats) in self.worst_routes.iter() {
+            writeln!(f, "  {:>12} x{} {}", stats.quoted_profit, stats.found, route)?;
+        }
+        writeln!(f, "top errors:")?;
+        for (category, count) in self.top_errors.iter() {
+            writeln!(f, "  {:>6} {}", count, category)?;
+        }
+        if !self.failure_reasons.is_empty() {
+            writeln!(f, "failure reasons:")?;
+            for (reason, count) in self.failure_reasons.iter() {
+                writeln!(f, "  {:>6} {}", count, reason)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_ranks_routes_and_errors() {
+        let stats = SessionStats::new(1_000);
+        stats.record_found("a", 100, 150);
+        stats.record_found("b", 100, 110);
+        stats.record_found("b", 100, 105);
+        stats.record_found("c", 100, 101);
+        stats.record_executed();
+        stats.record_landed("a", 50, LAMPORTS_PER_SIGNATURE, 0);
+        stats.record_error("Transaction simulation failed: Blockhash not found");
+        stats.record_error("blockhash expired");
+        stats.record_error("HTTP status client error (429 Too Many Requests)");
+
+        let summary = stats.summary(Some(1_040));
+        assert_eq!(summary.opportunities_found, 4);
+        assert_eq!(summary.landed, 1);
+        assert_eq!(summary.gross_pnl, 50);
+        assert_eq!(summary.net_pnl, Some(40));
+        assert_eq!(summary.best_routes[0].0, "a");
+        assert_eq!(summary.best_routes[1].1.quoted_profit, 15);
+        assert_eq!(summary.worst_routes[0].0, "c");
+        assert_eq!(summary.top_errors[0], ("blockhash".to_string(), 2));
+        assert!(summary.failure_reasons.is_empty());
+        stats.record_failure_reason("slippage");
+        stats.record_failure_reason("no_profit");
+        stats.record_failure_reason("slippage");
+        assert_eq!(stats.summary(None).failure_reasons[0], ("slippage".to_string(), 2));
+
+        // swept profits left the wallet but were still made
+        stats.record_swept(30);
+        assert_eq!(stats.summary(Some(1_010)).net_pnl, Some(40));
+    }
+}
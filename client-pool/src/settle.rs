@@ -0,0 +1,150 @@
+//! Settlement after trades
+//!
+//! A Serum fill credits the owner's open orders account of the market, not
+//! its token accounts; the funds stay there until they are settled. Every
+//! pool of a landed route is remembered, and at the end of the round the
+//! pools that need it (see `PoolOperations::settle_ix`) get their settle
+//! instructions sent, `markets_per_tx` to a transaction. With
+//! `consume_events` the market's event queue is cranked first, so fills
+//! still waiting in it are credited before the settle.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use serde::{Deserialize, Serialize};
+use solana_sdk::instruction::Instruction;
+
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+
+use tracing::warn;
+
+use crate::pool::PoolOperations;
+use crate::utils::PoolQuote;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SettleConfig {
+    /// Consume the market's fill events before settling
+    #[serde(default)]
+    pub consume_events: bool,
+    /// Most events consumed per market
+    #[serde(default = "default_events_limit")]
+    pub events_limit: u16,
+    /// Markets settled in one transaction
+    #[serde(default = "default_markets_per_tx")]
+    pub markets_per_tx: usize,
+}
+
+fn default_events_limit() -> u16 {
+    16
+}
+
+fn default_markets_per_tx() -> usize {
+    4
+}
+
+impl SettleConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.consume_events && self.events_limit == 0 {
+            problems.push("settle.events_limit must be > 0 with consume_events".to_string());
+        }
+        if self.markets_per_tx == 0 {
+            problems.push("settle.markets_per_tx must be > 0".to_string());
+        }
+        problems
+    }
+}
+
+/// Pools traded since the last settlement
+pub struct Settler {
+    config: SettleConfig,
+    /// pools of the routes sent, by id
+    pools: RefCell<HashMap<String, PoolQuote>>,
+    /// ids of the pools of landed routes, sorted so batches are reproducible
+    traded: RefCell<BTreeSet<String>>,
+}
+
+impl Settler {
+    pub fn new(config: &SettleConfig) -> Self {
+        Self {
+            config: config.clone(),
+            pools: RefCell::new(HashMap::new()),
+            traded: RefCell::new(BTreeSet::new()),
+        }
+    }
+
+    /// Remembers the pools of a route about to be sent, bundled routes only
+    /// report their pool ids once they land
+    pub fn register(&self, pools: &[PoolQuote]) {
+        let mut known = self.pools.borrow_mut();
+        for pool in pools {
+            known.entry(pool.0.get_id()).or_insert_with(|| pool.clone());
+        }
+    }
+
+    /// Marks the pools of a landed route as traded
+    pub fn record_landed(&self, pool_ids: &[String]) {
+        let mut traded = self.traded.borrow_mut();
+        for pool_id in pool_ids {
+            traded.insert(pool_id.clone());
+        }
+    }
+
+    /// Settle instructions of every pool traded since the last call, one
+    /// entry per transaction; pools that pay out within the swap are left out
+    pub fn take(&self, owner: &Pubkey) -> Vec<Vec<Instruction>> {
+        let traded = std::mem::take(&mut *self.traded.borrow_mut());
+        let pools = self.pools.borrow();
+        let consume_events = Some(self.config.events_limit).filter(|_| self.config.consume_events);
+        let markets: Vec<Vec<Instruction>> = traded
+            .iter()
+            .filter_map(|pool_id| pools.get(pool_id))
+            .filter_map(|pool| match pool.0.settle_ix(owner, consume_events) {
+                Ok(ixs) if !ixs.is_empty() => Some(ixs),
+                Ok(_) => None,
+                Err(e) => {
+                    warn!("can't settle {}: {}", pool.0.get_id(), e);
+                    None
+                }
+            })
+            .collect();
+        markets
+            .chunks(self.config.markets_per_tx)
+            .map(|chunk| chunk.concat())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pools::PoolVariant;
+    use crate::tests::pools::{orca_pool, serum_market};
+    use std::rc::Rc;
+
+    #[test]
+    fn only_traded_markets_are_settled_in_batches() {
+        let (a, b, owner) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let config: SettleConfig =
+            serde_json::from_str(r#"{ "consume_events": true, "markets_per_tx": 2 }"#).unwrap();
+        let settler = Settler::new(&config);
+        let markets: Vec<PoolQuote> = (0..3)
+            .map(|_| PoolQuote::new(Rc::new(PoolVariant::from(serum_market(&a, &b, Some(Pubkey::new_unique()))))))
+            .collect();
+        let untraded = PoolQuote::new(Rc::new(PoolVariant::from(serum_market(&a, &b, Some(Pubkey::new_unique())))));
+        let orca = PoolQuote::new(Rc::new(PoolVariant::from(orca_pool(&a, &b, 1_000, 1_000))));
+        let no_open_orders = PoolQuote::new(Rc::new(PoolVariant::from(serum_market(&a, &b, None))));
+        let mut all = markets.clone();
+        all.extend([untraded, orca.clone(), no_open_orders.clone()]);
+        settler.register(&all);
+
+        let mut landed: Vec<String> = markets.iter().map(|p| p.0.get_id()).collect();
+        landed.extend([orca.0.get_id(), no_open_orders.0.get_id()]);
+        settler.record_landed(&landed);
+
+        // consume events + settle per market, two markets to a transaction
+        let batches = settler.take(&owner);
+        assert_eq!(batches.iter().map(Vec::len).collect::<Vec<_>>(), vec![4, 2]);
+        assert!(settler.take(&owner).is_empty());
+    }
+}
@@ -21,7 +21,7 @@ use solana_sdk::system_program;
 
 
 
-use log::{warn};
+use tracing::warn;
 
 
 
@@ -32,6 +32,7 @@ use client::serialize::{
 use client::utils::{derive_token_address, read_json_dir};
 use client::pool::{PoolType, PoolOperations, pool_factory};
 use client::constants::*;
+use client::telemetry::{self, LogFormat};
 
 #[derive(Debug)]
 pub struct PoolDir {
@@ -42,7 +43,7 @@ pub struct PoolDir {
 fn main() {
     let cluster = Cluster::Mainnet;
 
-    env_logger::init();
+    telemetry::init(LogFormat::Text);
     let owner_kp_path = "../../../mainnet.key";     
     let owner = read_keypair_file(owner_kp_path.clone()).unwrap();
 
@@ -81,10 +82,16 @@ fn main() {
         
         for pool_path in pool_paths {
             let json_str = std::fs::read_to_string(&pool_path).unwrap();
-            let pool = pool_factory(&pool_dir.tipe, &json_str);
+            let pool = match pool_factory(&pool_dir.tipe, &json_str) {
+                Ok(pool) => pool,
+                Err(e) => {
+                    warn!("skipping pool {:?}: {}", pool_path, e);
+                    continue
+                }
+            };
             let pool_mints = pool.get_mints();
-            if pool_mints.len() != 2 { // only support 2 mint pools
-                warn!("skipping pool with mints != 2: {:?}", pool_path);
+            if pool_mints.len() < 2 {
+                warn!("skipping pool with fewer than 2 mints: {:?}", pool_path);
                 continue
             }
             for mint in pool_mints {
@@ -31,13 +31,14 @@ use tmp::instruction as tmp_instructions;
 use client::utils::{read_json_dir};
 use client::pool::{PoolType, PoolDir};
 use client::constants::*;
+use client::telemetry::{self, LogFormat};
 
 use indicatif::ProgressBar;
 
 fn main() {
     let cluster = Cluster::Localnet;
 
-    env_logger::init();
+    telemetry::init(LogFormat::Text);
     // let owner_kp_path = "../../../mainnet.key";     
     let owner_kp_path = "../mainnet-fork/localnet_owner.key";   
     let owner = read_keypair_file(owner_kp_path.clone()).unwrap();   
@@ -0,0 +1,279 @@
+//! Sharded search across processes
+//!
+//! On pool sets too large for one process to search every tick, `shard`
+//! splits the search between a coordinator and worker processes (on the
+//! same machine or others). The start mint's neighbours are cut into
+//! `shard.shards` ranges (see `search::shard_first_hops`) and every process
+//! only walks the paths whose first hop is in its range `shard.index`; the
+//! coordinator takes range 0.
+//!
+//! Workers send nothing: they post the opportunities of every tick as a
+//! `ShardReport` to the coordinator's `POST /report` on `shard.addr`. The
+//! coordinator quotes the reported routes again on its own pools, adds the
+//! ones that still pay to what its own range found and sends them. If
+//! `token` is set, reports need `Authorization: Bearer <token>`.
+
+use reqwest::blocking::Client as HttpClient;
+use serde::{Deserialize, Serialize};
+use tiny_http::{Method, Request, Response, Server};
+
+use std::io::Read;
+use std::net::SocketAddr;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use tracing::{debug, info, warn};
+
+use crate::arb::{ArbOpportunity, Arbitrager};
+use crate::events::OpportunityEvent;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShardRole {
+    /// Search range 0, collect the workers' reports and send
+    Coordinator,
+    /// Search range `index`, report to the coordinator, send nothing
+    Worker,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ShardConfig {
+    pub role: ShardRole,
+    /// Processes the search is split between, the coordinator included
+    pub shards: usize,
+    /// Range of first hops this process searches; 0 for the coordinator
+    #[serde(default)]
+    pub index: usize,
+    /// Address the coordinator listens on and the workers report to
+    pub addr: String,
+    /// Bearer token reports carry; any report is taken if unset
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+impl ShardConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.shards == 0 {
+            problems.push("shard.shards must be > 0".to_string());
+        }
+        if self.index >= self.shards {
+            problems.push(format!("shard.index {} must be < shard.shards {}", self.index, self.shards));
+        }
+        if self.role == ShardRole::Coordinator && self.index != 0 {
+            problems.push("shard.index of the coordinator must be 0".to_string());
+        }
+        if self.role == ShardRole::Worker && self.index == 0 {
+            problems.push("shard.index 0 is the coordinator's".to_string());
+        }
+        if self.addr.parse::<SocketAddr>().is_err() {
+            problems.push(format!("shard.addr {} is not a socket address", self.addr));
+        }
+        problems
+    }
+}
+
+/// What a worker found in one tick
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ShardReport {
+    pub shard: usize,
+    pub slot: u64,
+    pub opportunities: Vec<OpportunityEvent>,
+}
+
+/// Posts a worker's reports to the coordinator
+pub struct ShardWorker {
+    index: usize,
+    url: String,
+    token: Option<String>,
+    client: HttpClient,
+}
+
+impl ShardWorker {
+    pub fn new(config: &ShardConfig) -> Self {
+        Self {
+            index: config.index,
+            url: format!("http://{}/report", config.addr),
+            token: config.token.clone(),
+            client: HttpClient::builder()
+                .timeout(Duration::from_secs(2))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Reports the opportunities of the tick at `slot`; a report that can't
+    /// be delivered is dropped, the next tick has fresher ones
+    pub fn report(&self, slot: u64, opportunities: Vec<OpportunityEvent>) {
+        if opportunities.is_empty() {
+            return;
+        }
+        let report = ShardReport {
+            shard: self.index,
+            slot,
+            opportunities,
+        };
+        let mut request = self.client.post(&self.url).json(&report);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        match request.send().and_then(|response| response.error_for_status()) {
+            Ok(_) => debug!("shard {}: reported {} opportunities", self.index, report.opportunities.len()),
+            Err(e) => warn!("shard {}: report to {} failed: {}", self.index, self.url, e),
+        }
+    }
+}
+
+/// Receives the workers' reports in the background
+pub struct ShardCoordinator {
+    addr: SocketAddr,
+    reports: Receiver<ShardReport>,
+}
+
+impl ShardCoordinator {
+    /// Binds `config.addr` and takes reports in the background
+    pub fn spawn(config: &ShardConfig) -> Result<Self, String> {
+        let server = Server::http(&config.addr).map_err(|e| format!("could not bind {}: {}", config.addr, e))?;
+        let addr = server
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| format!("{} is not an ip address", config.addr))?;
+        let (sender, reports) = mpsc::channel();
+        let token = config.token.clone();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let (status, report) = read_report(request, token.as_deref());
+                if let Some(report) = report {
+                    if sender.send(report).is_err() {
+                        return;
+                    }
+                }
+                debug!("shard coordinator: report answered {}", status);
+            }
+        });
+        info!("shard coordinator listening on {}", addr);
+        Ok(Self { addr, reports })
+    }
+
+    /// The address the coordinator listens on (with the actual port for port 0)
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The reports received since the last call
+    pub fn reports(&self) -> Vec<ShardReport> {
+        self.reports.try_iter().collect()
+    }
+
+    /// The reported routes that still pay on the coordinator's pools
+    pub fn collect(
+        &self,
+        arb: &Arbitrager,
+        start_mint_idx: usize,
+        init_balance: u128,
+        amount: u128,
+    ) -> Vec<ArbOpportunity> {
+        let reports = self.reports();
+        let events: Vec<&OpportunityEvent> =
+            reports.iter().flat_map(|report| report.opportunities.iter()).collect();
+        let opportunities = arb.requote_routes(
+            events.iter().map(|event| (event.mints.as_slice(), event.pools.as_slice())),
+            start_mint_idx,
+            init_balance,
+            amount,
+        );
+        if !events.is_empty() {
+            info!(
+                "{} of {} opportunities from {} worker reports still pay",
+                opportunities.len(),
+                events.len(),
+                reports.len()
+            );
+        }
+        opportunities
+    }
+}
+
+/// Answers a report request, returning the status and the report if it was taken
+fn read_report(mut request: Request, token: Option<&str>) -> (u16, Option<ShardReport>) {
+    let authorized = token.map_or(true, |token| {
+        let expected = format!("Bearer {}", token);
+        request
+            .headers()
+            .iter()
+            .any(|h| h.field.equiv("Authorization") && h.value.as_str() == expected)
+    });
+    let (status, report) = if !authorized {
+        (401, None)
+    } else if request.method() != &Method::Post || request.url() != "/report" {
+        (404, None)
+    } else {
+        let mut body = String::new();
+        match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => match serde_json::from_str::<ShardReport>(&body) {
+                Ok(report) => (202, Some(report)),
+                Err(_) => (400, None),
+            },
+            Err(_) => (400, None),
+        }
+    };
+    if let Err(e) = request.respond(Response::empty(status)) {
+        warn!("shard coordinator: failed to respond: {}", e);
+    }
+    (status, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn config(role: ShardRole, index: usize, addr: &str) -> ShardConfig {
+        ShardConfig {
+            role,
+            shards: 3,
+            index,
+            addr: addr.to_string(),
+            token: Some("secret".to_string()),
+        }
+    }
+
+    #[test]
+    fn workers_report_to_the_coordinator() {
+        assert_eq!(config(ShardRole::Worker, 0, "127.0.0.1:0").problems().len(), 1);
+        assert_eq!(config(ShardRole::Worker, 3, "nowhere").problems().len(), 2);
+        let coordinator = ShardCoordinator::spawn(&config(ShardRole::Coordinator, 0, "127.0.0.1:0")).unwrap();
+        let addr = coordinator.local_addr().to_string();
+
+        let event = OpportunityEvent {
+            kind: "opportunity".to_string(),
+            route: "0->1->0 via orca:a,orca:b".to_string(),
+            path: String::new(),
+            mints: vec![],
+            pools: vec![],
+            amount_in: 1,
+            min_amount_out: 2,
+            expected_profit: 1,
+            slot: 7,
+            timestamp_ms: 0,
+        };
+        let mut intruder = config(ShardRole::Worker, 1, &addr);
+        intruder.token = None;
+        ShardWorker::new(&intruder).report(7, vec![event.clone()]);
+        ShardWorker::new(&config(ShardRole::Worker, 2, &addr)).report(7, vec![event.clone()]);
+
+        let start = Instant::now();
+        let mut reports = vec![];
+        while reports.is_empty() {
+            assert!(start.elapsed() < Duration::from_secs(5), "report never arrived");
+            thread::sleep(Duration::from_millis(10));
+            reports = coordinator.reports();
+        }
+        // the report without the token was turned away
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].shard, 2);
+        assert_eq!(reports[0].opportunities, vec![event]);
+    }
+}
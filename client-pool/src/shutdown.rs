@@ -0,0 +1,62 @@
+//! Graceful shutdown
+//!
+//! SIGINT / SIGTERM only set a flag: the search stops sending new routes,
+//! the main loop exits after the current round, waits for in-flight
+//! transactions and writes the session summary. A second SIGINT / SIGTERM
+//! exits immediately.
+
+use signal_hook::consts::{SIGINT, SIGTERM};
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tracing::warn;
+
+/// Shared shutdown flag, cheap to clone
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown {
+    requested: Arc<AtomicBool>,
+}
+
+impl Shutdown {
+    /// Registers the SIGINT / SIGTERM handlers
+    pub fn register() -> Self {
+        let shutdown = Self::default();
+        for signal in [SIGINT, SIGTERM] {
+            // the second signal finds the flag set and terminates the process
+            let registered = signal_hook::flag::register_conditional_shutdown(
+                signal,
+                1,
+                shutdown.requested.clone(),
+            )
+            .and_then(|_| signal_hook::flag::register(signal, shutdown.requested.clone()));
+            if let Err(e) = registered {
+                warn!("could not register handler for signal {}: {}", signal, e);
+            }
+        }
+        shutdown
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+
+    /// Requests a shutdown as if a signal was received
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_the_flag() {
+        let shutdown = Shutdown::default();
+        let clone = shutdown.clone();
+        assert!(!clone.is_requested());
+        shutdown.request();
+        assert!(clone.is_requested());
+    }
+}
@@ -0,0 +1,252 @@
+//! Transaction signers
+//!
+//! The owner doesn't have to be a keypair file on the trading box: the arb
+//! transactions can also be signed by an external signing service over HTTP
+//! or by a Ledger. Every variant implements solana's `Signer`, so the rest of
+//! the bot only ever sees an `Rc<dyn Signer>`.
+//!
+//! The remote signing service is expected to expose
+//! * `GET  {url}/pubkey` -> `{ "pubkey": "<base58>" }`
+//! * `POST {url}/sign` with `{ "pubkey": "<base58>", "message": "<base64>" }`
+//!   -> `{ "signature": "<base58>" }`
+//!
+//! (a gRPC signer can be put behind a small HTTP gateway speaking this api).
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signature::{Signature, Signer, SignerError};
+use reqwest::blocking::Client as HttpClient;
+use serde::{Deserialize, Serialize};
+use solana_remote_wallet::locator::Locator;
+use solana_remote_wallet::remote_keypair::generate_remote_keypair;
+use solana_remote_wallet::remote_wallet::maybe_wallet_manager;
+use solana_sdk::derivation_path::DerivationPath;
+
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::Duration;
+
+use tracing::info;
+
+/// Where the owner's signatures come from, instead of a keypair file
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SignerConfig {
+    /// External signing service (see the module docs for the api)
+    Remote {
+        url: String,
+        /// Owner pubkey the service signs for
+        pubkey: String,
+        /// Bearer token sent with every request
+        #[serde(default)]
+        token: Option<String>,
+        #[serde(default = "default_timeout_ms")]
+        timeout_ms: u64,
+    },
+    /// Ledger hardware wallet
+    Ledger {
+        /// Wallet locator, e.g. `usb://ledger` or `usb://ledger/<wallet pubkey>`
+        #[serde(default = "default_ledger_locator")]
+        locator: String,
+        /// BIP44 account / change of the key (`44'/501'/account'/change'`)
+        #[serde(default)]
+        account: Option<u32>,
+        #[serde(default)]
+        change: Option<u32>,
+    },
+}
+
+fn default_timeout_ms() -> u64 {
+    2_000
+}
+
+fn default_ledger_locator() -> String {
+    "usb://ledger".to_string()
+}
+
+impl SignerConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        match self {
+            SignerConfig::Remote { url, pubkey, timeout_ms, .. } => {
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    problems.push(format!("signer.url {} is not an http(s) url", url));
+                }
+                if Pubkey::from_str(pubkey).is_err() {
+                    problems.push(format!("signer.pubkey {} is not a valid pubkey", pubkey));
+                }
+                if *timeout_ms == 0 {
+                    problems.push("signer.timeout_ms must be > 0".to_string());
+                }
+            }
+            SignerConfig::Ledger { locator, .. } => {
+                if Locator::new_from_path(locator).is_err() {
+                    problems.push(format!("signer.locator {} is not a valid wallet locator", locator));
+                }
+            }
+        }
+        problems
+    }
+
+    /// Connects to the signer
+    pub fn connect(&self) -> Result<Rc<dyn Signer>, String> {
+        match self {
+            SignerConfig::Remote { url, pubkey, token, timeout_ms } => {
+                let pubkey = Pubkey::from_str(pubkey).map_err(|e| e.to_string())?;
+                let signer = RemoteSigner::new(url, pubkey, token.clone(), Duration::from_millis(*timeout_ms))?;
+                signer.check_pubkey()?;
+                Ok(Rc::new(signer))
+            }
+            SignerConfig::Ledger { locator, account, change } => {
+                let locator = Locator::new_from_path(locator).map_err(|e| e.to_string())?;
+                let wallet_manager = maybe_wallet_manager()
+                    .map_err(|e| e.to_string())?
+                    .ok_or_else(|| "no hardware wallet found".to_string())?;
+                let keypair = generate_remote_keypair(
+                    locator,
+                    DerivationPath::new_bip44(*account, *change),
+                    &wallet_manager,
+                    false,
+                    "owner",
+                )
+                .map_err(|e| e.to_string())?;
+                info!("Using ledger key {}", keypair.pubkey());
+                Ok(Rc::new(keypair))
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SignRequest {
+    pubkey: String,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct SignResponse {
+    signature: String,
+}
+
+#[derive(Deserialize)]
+struct PubkeyResponse {
+    pubkey: String,
+}
+
+/// Signs through an external signing service
+pub struct RemoteSigner {
+    url: String,
+    pubkey: Pubkey,
+    token: Option<String>,
+    client: HttpClient,
+}
+
+impl RemoteSigner {
+    /// # Arguments
+    /// * `url` - Base url of the signing service
+    /// * `pubkey` - Pubkey the service signs for
+    /// * `token` - Optional bearer token
+    /// * `timeout` - Timeout of a single request
+    pub fn new(url: &str, pubkey: Pubkey, token: Option<String>, timeout: Duration) -> Result<Self, String> {
+        let client = HttpClient::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            url: url.trim_end_matches('/').to_string(),
+            pubkey,
+            token,
+            client,
+        })
+    }
+
+    fn authorized(&self, req: reqwest::blocking::RequestBuilder) -> reqwest::blocking::RequestBuilder {
+        match &self.token {
+            Some(token) => req.bearer_auth(token),
+            None => req,
+        }
+    }
+
+    /// Makes sure the service signs for the configured pubkey
+    pub fn check_pubkey(&self) -> Result<(), String> {
+        let resp: PubkeyResponse = self
+            .authorized(self.client.get(format!("{}/pubkey", self.url)))
+            .send()
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.json())
+            .map_err(|e| format!("signer {}: {}", self.url, e))?;
+        if resp.pubkey != self.pubkey.to_string() {
+            return Err(format!(
+                "signer {} signs for {}, expected {}",
+                self.url, resp.pubkey, self.pubkey
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let body = SignRequest {
+            pubkey: self.pubkey.to_string(),
+            message: base64::encode(message),
+        };
+        let resp: SignResponse = self
+            .authorized(self.client.post(format!("{}/sign", self.url)))
+            .json(&body)
+            .send()
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.json())
+            .map_err(|e| SignerError::Connection(e.to_string()))?;
+        let signature = Signature::from_str(&resp.signature)
+            .map_err(|e| SignerError::InvalidInput(e.to_string()))?;
+        // don't trust the service blindly: a bad signature would only fail on chain
+        if !signature.verify(self.pubkey.as_ref(), message) {
+            return Err(SignerError::InvalidInput("signature doesn't verify".to_string()));
+        }
+        Ok(signature)
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remote_config_is_checked() {
+        let config: SignerConfig = serde_json::from_str(
+            r#"{ "kind": "remote", "url": "localhost:9000", "pubkey": "nope", "timeout_ms": 0 }"#,
+        )
+        .unwrap();
+        assert_eq!(config.problems().len(), 3);
+
+        let config: SignerConfig = serde_json::from_str(&format!(
+            r#"{{ "kind": "remote", "url": "http://localhost:9000", "pubkey": "{}" }}"#,
+            Pubkey::new_unique()
+        ))
+        .unwrap();
+        assert!(config.problems().is_empty());
+    }
+
+    #[test]
+    fn ledger_defaults_to_the_first_usb_wallet() {
+        let config: SignerConfig = serde_json::from_str(r#"{ "kind": "ledger" }"#).unwrap();
+        assert_eq!(
+            config,
+            SignerConfig::Ledger {
+                locator: "usb://ledger".to_string(),
+                account: None,
+                change: None,
+            }
+        );
+        assert!(config.problems().is_empty());
+    }
+}
@@ -0,0 +1,192 @@
+//! Position sizing from route history
+//!
+//! Routes that often fail to land, or land for less than they were quoted
+//! at, shouldn't be traded at the full profitable size. Every send of a route
+//! is recorded with whether it landed and, when it did, the profit the wallet
+//! actually made. The size of the next send is scaled by a Kelly-style
+//! fraction: the route's landing rate times the share of the quoted profit
+//! that was realized, floored at `min_fraction`.
+//!
+//! Both rates start from `prior_sends` virtual sends that landed at their
+//! quoted profit, so a new route trades at full size and a single failure
+//! only shrinks it a little. The history can be kept across runs in
+//! `history_path`.
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use tracing::warn;
+
+/// Position sizing settings
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SizingConfig {
+    /// Virtual sends every route starts with, all landed at their quoted profit
+    #[serde(default = "default_prior_sends")]
+    pub prior_sends: f64,
+    /// Smallest share of the profitable size a route is traded at
+    #[serde(default = "default_min_fraction")]
+    pub min_fraction: f64,
+    /// JSON file the route history is loaded from at start and written to
+    /// on exit; kept in memory only if unset
+    #[serde(default)]
+    pub history_path: Option<String>,
+}
+
+fn default_prior_sends() -> f64 {
+    4.0
+}
+
+fn default_min_fraction() -> f64 {
+    0.1
+}
+
+impl SizingConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.prior_sends < 0.0 {
+            problems.push(format!("sizing.prior_sends must be >= 0, got {}", self.prior_sends));
+        }
+        if !(self.min_fraction > 0.0 && self.min_fraction <= 1.0) {
+            problems.push(format!(
+                "sizing.min_fraction must be in (0, 1], got {}",
+                self.min_fraction
+            ));
+        }
+        problems
+    }
+}
+
+/// Send history of one route
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct RouteHistory {
+    pub sent: u64,
+    pub landed: u64,
+    /// Quoted profit of the landed sends whose realized profit is known (scaled)
+    pub quoted_profit: i128,
+    /// Wallet balance change of those sends (scaled)
+    pub realized_profit: i128,
+}
+
+/// Scales trade sizes by route history, see the module docs
+pub struct PositionSizer {
+    prior_sends: f64,
+    min_fraction: f64,
+    history_path: Option<String>,
+    routes: Mutex<HashMap<String, RouteHistory>>,
+}
+
+impl PositionSizer {
+    /// Starts from the history in `config.history_path` if it exists
+    pub fn new(config: &SizingConfig) -> Self {
+        let routes = match &config.history_path {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                    warn!("ignoring unreadable sizing history {}: {}", path, e);
+                    HashMap::new()
+                }),
+                Err(_) => HashMap::new(),
+            },
+            None => HashMap::new(),
+        };
+        Self {
+            prior_sends: config.prior_sends,
+            min_fraction: config.min_fraction,
+            history_path: config.history_path.clone(),
+            routes: Mutex::new(routes),
+        }
+    }
+
+    /// Share of the profitable size `route` should be traded at, in `[min_fraction, 1]`
+    pub fn fraction(&self, route: &str) -> f64 {
+        let routes = self.routes.lock().unwrap();
+        let history = match routes.get(route) {
+            Some(history) => history,
+            None => return 1.0,
+        };
+        let landing_rate =
+            (history.landed as f64 + self.prior_sends) / (history.sent as f64 + self.prior_sends);
+        // the prior realized all of its (unknown) profit, weigh it by the
+        // average quoted profit so it counts like `prior_sends` sends
+        let realized_share = if history.quoted_profit > 0 {
+            let avg_quoted = history.quoted_profit as f64 / history.landed.max(1) as f64;
+            let prior = self.prior_sends * avg_quoted;
+            ((history.realized_profit as f64 + prior) / (history.quoted_profit as f64 + prior))
+                .max(0.0)
+        } else {
+            1.0
+        };
+        (landing_rate * realized_share).clamp(self.min_fraction, 1.0)
+    }
+
+    /// Records a send of `route`
+    ///
+    /// # Arguments
+    /// * `landed` - Whether the transaction landed successfully
+    /// * `quoted_profit` - Profit the route was sent for (scaled start mint)
+    /// * `realized_profit` - Balance change of the wallet, if it was measured
+    pub fn record(&self, route: &str, landed: bool, quoted_profit: i128, realized_profit: Option<i128>) {
+        let mut routes = self.routes.lock().unwrap();
+        let history = routes.entry(route.to_string()).or_default();
+        history.sent += 1;
+        if landed {
+            history.landed += 1;
+            if let Some(realized) = realized_profit {
+                history.quoted_profit += quoted_profit;
+                history.realized_profit += realized;
+            }
+        }
+    }
+
+    /// Writes the history to `history_path`, if set
+    pub fn persist(&self) -> std::io::Result<()> {
+        let path = match &self.history_path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let json = serde_json::to_string_pretty(&*self.routes.lock().unwrap())?;
+        std::fs::write(path, json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sizer() -> PositionSizer {
+        PositionSizer::new(&SizingConfig {
+            prior_sends: 4.0,
+            min_fraction: 0.1,
+            history_path: None,
+        })
+    }
+
+    #[test]
+    fn new_routes_trade_full_size() {
+        let sizer = sizer();
+        assert_eq!(sizer.fraction("a"), 1.0);
+        sizer.record("a", true, 100, Some(100));
+        assert_eq!(sizer.fraction("a"), 1.0);
+    }
+
+    #[test]
+    fn failures_and_slippage_shrink_the_size() {
+        let sizer = sizer();
+        for _ in 0..4 {
+            sizer.record("a", false, 100, None);
+        }
+        // 4 of 8 landed, counting the prior
+        assert!((sizer.fraction("a") - 0.5).abs() < 1e-9);
+
+        sizer.record("b", true, 100, Some(0));
+        // landed, but only the prior's profit was realized: 400 / 500
+        assert!((sizer.fraction("b") - 0.8).abs() < 1e-9);
+
+        for _ in 0..100 {
+            sizer.record("c", false, 100, None);
+        }
+        assert_eq!(sizer.fraction("c"), 0.1);
+    }
+}
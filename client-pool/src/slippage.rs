@@ -0,0 +1,391 @@
+//! Realized slippage per venue
+//!
+//! A quote is only as good as the pool state it was made on: a venue whose
+//! state lags, or whose curve the quote only approximates, delivers less
+//! than quoted. After a route sent by the `rpc` executor lands, its
+//! transaction is fetched and the token transfers into the owner's accounts
+//! are read from its inner instructions. The first credit of a leg's output
+//! mint after the previous leg's is that leg's actual output. It is compared
+//! with the leg's quote scaled to the input the leg actually got (the
+//! previous leg's actual output), so one leg's shortfall isn't charged to
+//! the next.
+//!
+//! Shortfalls are kept per DEX (`PoolOperations::get_name`) in bps. Once a
+//! venue has `min_legs` measured legs and under-delivers on average, the
+//! search takes that average off the output of every route through it (once
+//! per leg), at most `max_pad_bps` per leg. Legs whose output isn't
+//! transferred in the transaction (Serum fills wait in the open orders
+//! account until they are settled) aren't measured, and neither are
+//! bundles, simulations and paper trades. The statistics can be kept across
+//! runs in `history_path`.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::sync::Mutex;
+
+use tracing::warn;
+
+use crate::amount::BPS;
+use crate::arb::ArbOpportunity;
+use crate::pool::PoolOperations;
+use crate::utils::PoolQuote;
+
+/// Realized slippage settings
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SlippageConfig {
+    /// Measured legs a venue needs before its quotes are padded
+    #[serde(default = "default_min_legs")]
+    pub min_legs: u64,
+    /// Most a leg's quote is padded by (bps)
+    #[serde(default = "default_max_pad_bps")]
+    pub max_pad_bps: u64,
+    /// JSON file the statistics are loaded from at start and written to on
+    /// exit; kept in memory only if unset
+    #[serde(default)]
+    pub history_path: Option<String>,
+}
+
+fn default_min_legs() -> u64 {
+    5
+}
+
+fn default_max_pad_bps() -> u64 {
+    100
+}
+
+impl SlippageConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.max_pad_bps > BPS {
+            problems.push(format!(
+                "slippage.max_pad_bps must be <= 10000, got {}",
+                self.max_pad_bps
+            ));
+        }
+        problems
+    }
+}
+
+/// Measured legs of one venue
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub struct VenueSlippage {
+    pub legs: u64,
+    /// Sum of the legs' shortfalls (bps, negative when a leg delivered more)
+    pub shortfall_bps: i128,
+}
+
+impl VenueSlippage {
+    /// Average shortfall of a leg (bps, rounded towards zero)
+    pub fn avg_bps(&self) -> i128 {
+        self.shortfall_bps / self.legs.max(1) as i128
+    }
+}
+
+/// A leg of a landed route, what it was expected to deliver and what it did
+#[derive(Debug, Clone, PartialEq)]
+pub struct LegSlippage {
+    /// `PoolOperations::get_name` of the leg's pool
+    pub venue: String,
+    /// Quoted output scaled to the leg's actual input
+    pub expected: u128,
+    pub actual: u128,
+}
+
+impl LegSlippage {
+    /// Share of the expected output that didn't come (bps)
+    pub fn shortfall_bps(&self) -> i128 {
+        if self.expected == 0 {
+            return 0;
+        }
+        (self.expected as i128 - self.actual as i128) * BPS as i128 / self.expected as i128
+    }
+}
+
+/// Token transfers into `accounts`, as `(destination, amount)` in the order
+/// they happened, from the `innerInstructions` of a JSON-parsed transaction
+/// meta
+pub fn credits(meta: &Value, accounts: &HashSet<String>) -> Vec<(String, u128)> {
+    let mut groups: Vec<&Value> = meta["innerInstructions"].as_array().into_iter().flatten().collect();
+    groups.sort_by_key(|group| group["index"].as_u64());
+    groups
+        .iter()
+        .flat_map(|group| group["instructions"].as_array().into_iter().flatten())
+        .filter_map(|ix| {
+            let parsed = &ix["parsed"];
+            let info = &parsed["info"];
+            let amount = match parsed["type"].as_str()? {
+                "transfer" => info["amount"].as_str()?,
+                "transferChecked" => info["tokenAmount"]["amount"].as_str()?,
+                _ => return None,
+            };
+            let destination = info["destination"].as_str()?;
+            // moves between the owner's own accounts aren't a leg's output
+            let source = info["source"].as_str().unwrap_or_default();
+            if !accounts.contains(destination) || accounts.contains(source) {
+                return None;
+            }
+            Some((destination.to_string(), amount.parse().ok()?))
+        })
+        .collect()
+}
+
+/// The legs of `opportunity` measured from the `credits` of its transaction;
+/// `token_account` is the owner's account of a mint. Measuring stops at the
+/// first leg whose output wasn't credited, the input of the next is unknown.
+pub fn measure(
+    opportunity: &ArbOpportunity,
+    token_account: impl Fn(&Pubkey) -> Pubkey,
+    credits: &[(String, u128)],
+) -> Vec<LegSlippage> {
+    let mut legs = vec![];
+    // the credits of two chains through the same accounts can't be told apart
+    if opportunity.split.is_some() {
+        return legs;
+    }
+    let mut next_credit = 0;
+    let mut actual_in = opportunity.amounts[0];
+    for (hop, pool) in opportunity.hops().iter().zip(opportunity.pools.iter()) {
+        let account = token_account(&hop.mint_out).to_string();
+        let found = credits[next_credit..].iter().position(|(destination, _)| *destination == account);
+        let (offset, actual) = match found {
+            Some(offset) => (offset, credits[next_credit + offset].1),
+            None => break,
+        };
+        next_credit += offset + 1;
+        let expected = match hop.amount_in {
+            0 => 0,
+            quoted_in => hop.amount_out * actual_in / quoted_in,
+        };
+        legs.push(LegSlippage {
+            venue: pool.0.get_name(),
+            expected,
+            actual,
+        });
+        actual_in = actual;
+    }
+    legs
+}
+
+/// Realized slippage of every venue, see the module docs
+pub struct SlippageTracker {
+    min_legs: u64,
+    max_pad_bps: u64,
+    history_path: Option<String>,
+    venues: Mutex<BTreeMap<String, VenueSlippage>>,
+}
+
+impl SlippageTracker {
+    /// Starts from the statistics in `config.history_path` if it exists
+    pub fn new(config: &SlippageConfig) -> Self {
+        let venues = match &config.history_path {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                    warn!("ignoring unreadable slippage history {}: {}", path, e);
+                    BTreeMap::new()
+                }),
+                Err(_) => BTreeMap::new(),
+            },
+            None => BTreeMap::new(),
+        };
+        Self {
+            min_legs: config.min_legs,
+            max_pad_bps: config.max_pad_bps,
+            history_path: config.history_path.clone(),
+            venues: Mutex::new(venues),
+        }
+    }
+
+    /// A tracker padding every venue of `pads` by its pad (bps), what a
+    /// recorded round searched with (see `replay`)
+    pub fn with_pads(pads: &BTreeMap<String, u64>) -> Self {
+        let venues = pads
+            .iter()
+            .map(|(venue, pad)| {
+                let stats = VenueSlippage {
+                    legs: 1,
+                    shortfall_bps: *pad as i128,
+                };
+                (venue.to_lowercase(), stats)
+            })
+            .collect();
+        Self {
+            min_legs: 1,
+            max_pad_bps: BPS,
+            history_path: None,
+            venues: Mutex::new(venues),
+        }
+    }
+
+    pub fn record(&self, legs: &[LegSlippage]) {
+        let mut venues = self.venues.lock().unwrap();
+        for leg in legs {
+            let venue = venues.entry(leg.venue.to_lowercase()).or_default();
+            venue.legs += 1;
+            venue.shortfall_bps += leg.shortfall_bps();
+        }
+    }
+
+    /// What the quotes of `venue` are padded by (bps)
+    pub fn pad_bps(&self, venue: &str) -> u64 {
+        let venues = self.venues.lock().unwrap();
+        match venues.get(&venue.to_lowercase()) {
+            Some(stats) if stats.legs >= self.min_legs && stats.avg_bps() > 0 => {
+                (stats.avg_bps() as u64).min(self.max_pad_bps)
+            }
+            _ => 0,
+        }
+    }
+
+    /// The pad of every measured venue (bps)
+    pub fn pads(&self) -> BTreeMap<String, u64> {
+        let venues: Vec<String> = self.venues.lock().unwrap().keys().cloned().collect();
+        venues
+            .into_iter()
+            .map(|venue| {
+                let pad = self.pad_bps(&venue);
+                (venue, pad)
+            })
+            .collect()
+    }
+
+    /// What the output of a route through `pools` is padded by (bps), the
+    /// pads of its legs added up
+    pub fn route_pad_bps(&self, pools: &[PoolQuote]) -> u64 {
+        let pad: u64 = pools.iter().map(|pool| self.pad_bps(&pool.0.get_name())).sum();
+        pad.min(BPS)
+    }
+
+    pub fn report(&self) -> SlippageReport {
+        SlippageReport(self.venues.lock().unwrap().clone())
+    }
+
+    /// Writes the statistics to `history_path`, if set
+    pub fn persist(&self) -> std::io::Result<()> {
+        let path = match &self.history_path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let json = serde_json::to_string_pretty(&*self.venues.lock().unwrap())?;
+        std::fs::write(path, json)
+    }
+}
+
+/// Realized slippage by venue, for the end of the session
+pub struct SlippageReport(pub BTreeMap<String, VenueSlippage>);
+
+impl fmt::Display for SlippageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return writeln!(f, "realized slippage: no leg measured");
+        }
+        writeln!(f, "realized slippage (avg shortfall per leg):")?;
+        for (venue, stats) in self.0.iter() {
+            writeln!(f, "  {:<10} {:>6} bps over {} legs", venue, stats.avg_bps(), stats.legs)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::rc::Rc;
+    use std::time::Instant;
+
+    use crate::pools::PoolVariant;
+    use crate::tests::pools::orca_pool;
+
+    fn tracker() -> SlippageTracker {
+        SlippageTracker::new(&SlippageConfig {
+            min_legs: 2,
+            max_pad_bps: 50,
+            history_path: None,
+        })
+    }
+
+    fn transfer(source: &str, destination: &str, amount: u128) -> Value {
+        json!({
+            "program": "spl-token",
+            "parsed": { "type": "transfer", "info": {
+                "source": source, "destination": destination, "amount": amount.to_string()
+            } }
+        })
+    }
+
+    #[test]
+    fn legs_are_measured_from_the_credits_of_their_output() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let pool = PoolQuote::new(Rc::new(PoolVariant::from(orca_pool(&a, &b, 1_000, 1_000))));
+        let opportunity = ArbOpportunity {
+            route: "0->1->0".to_string(),
+            mints: vec![a, b, a],
+            pools: vec![pool.clone(), pool],
+            amount_in: 1_000,
+            amounts: vec![1_000, 2_000, 1_100],
+            min_amount_out: 1_050,
+            expected_profit: 50,
+            found_slot: 0,
+            found_at: Instant::now(),
+            split: None,
+        };
+        let (ata_a, ata_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let token_account = |mint: &Pubkey| if *mint == a { ata_a } else { ata_b };
+        let (ata_a, ata_b) = (ata_a.to_string(), ata_b.to_string());
+        let owned: HashSet<String> = [ata_a.clone(), ata_b.clone()].into_iter().collect();
+        // a flash loan credit before the legs, a move between own accounts,
+        // then the two legs' outputs
+        let meta = json!({ "innerInstructions": [
+            { "index": 3, "instructions": [transfer("vault-a", &ata_a, 1_089)] },
+            { "index": 0, "instructions": [transfer("reserve", &ata_a, 1_000)] },
+            { "index": 2, "instructions": [
+                transfer(&ata_a, "vault-a", 1_000),
+                transfer(&ata_a, &ata_b, 1),
+                transfer("vault-b", &ata_b, 1_980),
+            ] },
+        ] });
+        let credits = credits(&meta, &owned);
+        assert_eq!(credits, vec![(ata_a.clone(), 1_000), (ata_b, 1_980), (ata_a, 1_089)]);
+
+        let legs = measure(&opportunity, token_account, &credits);
+        assert_eq!(legs.len(), 2);
+        assert_eq!(legs[0].venue, "Orca");
+        // 1% short on the first leg
+        assert_eq!((legs[0].expected, legs[0].actual, legs[0].shortfall_bps()), (2_000, 1_980, 100));
+        // the second is expected to deliver on the 1_980 it got: 1_089
+        assert_eq!((legs[1].expected, legs[1].actual, legs[1].shortfall_bps()), (1_089, 1_089, 0));
+        // nothing credited, nothing measured
+        assert!(measure(&opportunity, token_account, &[]).is_empty());
+    }
+
+    #[test]
+    fn venues_are_padded_once_they_under_deliver() {
+        let tracker = tracker();
+        let leg = |venue: &str, actual| LegSlippage {
+            venue: venue.to_string(),
+            expected: 10_000,
+            actual,
+        };
+        tracker.record(&[leg("Orca", 9_960)]);
+        // one leg isn't enough
+        assert_eq!(tracker.pad_bps("Orca"), 0);
+        tracker.record(&[leg("Orca", 9_980), leg("Raydium", 10_010), leg("Raydium", 10_000)]);
+        assert_eq!(tracker.pad_bps("orca"), 30);
+        // delivering more isn't a reason to quote more
+        assert_eq!(tracker.pad_bps("Raydium"), 0);
+        tracker.record(&[leg("Orca", 9_000), leg("Orca", 9_000)]);
+        assert_eq!(tracker.pad_bps("Orca"), 50);
+        assert_eq!(tracker.report().0["orca"].legs, 4);
+
+        // a replay pads like the tracker did
+        let pads = tracker.pads();
+        assert_eq!(pads, [("orca".to_string(), 50), ("raydium".to_string(), 0)].into_iter().collect());
+        let replayed = SlippageTracker::with_pads(&pads);
+        assert_eq!((replayed.pad_bps("Orca"), replayed.pad_bps("Raydium")), (50, 0));
+    }
+}
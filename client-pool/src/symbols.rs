@@ -0,0 +1,276 @@
+//! Token symbols for logs
+//!
+//! Routes are keyed by mint indices and pool ids, which say little when
+//! reading a log. With `tokens.symbols` (the default) the symbols of the
+//! graph's mints are resolved once at start: from Jupiter's strict token
+//! list (cached next to the token sources for `tokens.ttl_secs`), then from
+//! the Metaplex metadata account of every mint the list doesn't know. Mints
+//! with neither show the start of their address.
+//!
+//! `format_route` gives `USDC → SOL (Orca) → BONK (Raydium) → USDC (Serum) +12.3 USDC`.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use reqwest::blocking::Client as HttpClient;
+use serde_json::Value;
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use tracing::{info, warn};
+
+use crate::arb::ArbOpportunity;
+use crate::graph::{AccountSource, ACCOUNTS_PER_REQUEST};
+use crate::pool::PoolOperations;
+use crate::tokens::{read_cache, write_cache, TokensConfig, JUPITER_TOKENS_URL};
+
+/// Metaplex token metadata program
+pub const METADATA_PROGRAM_ID: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s";
+/// Name of the cache file of the Jupiter symbols
+const JUPITER_SYMBOLS_CACHE: &str = "symbols_jupiter_strict";
+/// key, update authority and mint come before the name of a metadata account
+const METADATA_NAME_OFFSET: usize = 1 + 32 + 32;
+
+/// Symbol of every known mint
+#[derive(Debug, Clone, Default)]
+pub struct TokenSymbols {
+    symbols: HashMap<Pubkey, String>,
+}
+
+impl TokenSymbols {
+    /// The symbols of `mints`; never fails, mints that can't be resolved
+    /// show their address
+    pub fn load<S: AccountSource + ?Sized>(config: &TokensConfig, rpc: &S, mints: &[Pubkey]) -> Self {
+        let mut symbols = Self::default();
+        if !config.symbols {
+            return symbols;
+        }
+        for (mint, symbol) in jupiter_symbols(config) {
+            symbols.insert(mint, symbol);
+        }
+        let missing: Vec<Pubkey> = mints.iter().filter(|mint| !symbols.knows(mint)).copied().collect();
+        for (mint, symbol) in metaplex_symbols(rpc, &missing) {
+            symbols.insert(mint, symbol);
+        }
+        info!(
+            "resolved {} of {} token symbols",
+            mints.iter().filter(|mint| symbols.knows(mint)).count(),
+            mints.len()
+        );
+        symbols
+    }
+
+    pub fn insert(&mut self, mint: Pubkey, symbol: String) {
+        self.symbols.insert(mint, symbol);
+    }
+
+    pub fn knows(&self, mint: &Pubkey) -> bool {
+        self.symbols.contains_key(mint)
+    }
+
+    /// The mint's symbol, or the first characters of its address
+    pub fn symbol(&self, mint: &Pubkey) -> String {
+        match self.symbols.get(mint) {
+            Some(symbol) => symbol.clone(),
+            None => {
+                let address = mint.to_string();
+                format!("{}…", &address[..4])
+            }
+        }
+    }
+
+    /// `USDC → SOL (Orca) → USDC (Raydium) +1.5 USDC`: every mint of the
+    /// route with the DEX of the hop into it, then the expected profit
+    pub fn format_route(&self, opportunity: &ArbOpportunity) -> String {
+        let mut route = self.symbol(&opportunity.mints[0]);
+        for (mint, pool) in opportunity.mints[1..].iter().zip(opportunity.pools.iter()) {
+            route.push_str(&format!(" → {} ({})", self.symbol(mint), pool.0.get_name()));
+        }
+        let start = &opportunity.mints[0];
+        let decimals = opportunity.pools.first().map_or(0, |pool| pool.0.mint_2_scale(start));
+        format!(
+            "{} {} {}",
+            route,
+            ui_amount(opportunity.expected_profit, decimals),
+            self.symbol(start)
+        )
+    }
+}
+
+/// `amount` of a mint with `decimals` as a signed decimal, trailing zeros cut
+pub fn ui_amount(amount: i128, decimals: u64) -> String {
+    let sign = if amount < 0 { "-" } else { "+" };
+    let digits = format!("{:0>width$}", amount.unsigned_abs(), width = decimals as usize + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals as usize);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        format!("{}{}", sign, whole)
+    } else {
+        format!("{}{}.{}", sign, whole, fraction)
+    }
+}
+
+/// Symbols of Jupiter's strict list: a fresh cache, else a fetch (cached on
+/// success), else the stale cache
+fn jupiter_symbols(config: &TokensConfig) -> HashMap<Pubkey, String> {
+    let path = config.cache_file(JUPITER_SYMBOLS_CACHE);
+    let cached: Option<(HashMap<String, String>, bool)> =
+        read_cache(&path, Duration::from_secs(config.ttl_secs));
+    let symbols = match cached {
+        Some((symbols, true)) => symbols,
+        stale => match fetch_jupiter_symbols() {
+            Ok(symbols) => {
+                if let Err(e) = write_cache(&path, &symbols) {
+                    warn!("failed to cache token symbols at {}: {}", path.display(), e);
+                }
+                symbols
+            }
+            Err(e) => {
+                warn!("could not fetch token symbols: {}", e);
+                stale.map(|(symbols, _)| symbols).unwrap_or_default()
+            }
+        },
+    };
+    symbols
+        .into_iter()
+        .filter_map(|(mint, symbol)| Some((Pubkey::from_str(&mint).ok()?, symbol)))
+        .collect()
+}
+
+fn fetch_jupiter_symbols() -> Result<HashMap<String, String>, reqwest::Error> {
+    let client = HttpClient::builder().timeout(Duration::from_secs(10)).build()?;
+    let response: Value = client
+        .get(JUPITER_TOKENS_URL)
+        .query(&[("tags", "strict")])
+        .send()?
+        .error_for_status()?
+        .json()?;
+    Ok(list_symbols(&response))
+}
+
+/// `address -> symbol` of a JSON array of token objects
+fn list_symbols(list: &Value) -> HashMap<String, String> {
+    list.as_array()
+        .map(|tokens| {
+            tokens
+                .iter()
+                .filter_map(|token| {
+                    let address = token.get("address")?.as_str()?;
+                    let symbol = token.get("symbol")?.as_str()?.trim();
+                    (!symbol.is_empty()).then(|| (address.to_string(), symbol.to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Address of the Metaplex metadata account of `mint`
+pub fn metadata_address(mint: &Pubkey) -> Pubkey {
+    let program = Pubkey::from_str(METADATA_PROGRAM_ID).unwrap();
+    Pubkey::find_program_address(&[b"metadata", program.as_ref(), mint.as_ref()], &program).0
+}
+
+/// Symbols read from the metadata accounts of `mints`; mints without one
+/// (or in a chunk that can't be fetched) are left out
+fn metaplex_symbols<S: AccountSource + ?Sized>(rpc: &S, mints: &[Pubkey]) -> HashMap<Pubkey, String> {
+    let mut symbols = HashMap::new();
+    for chunk in mints.chunks(ACCOUNTS_PER_REQUEST) {
+        let addresses: Vec<Pubkey> = chunk.iter().map(metadata_address).collect();
+        let accounts = match rpc.get_multiple_accounts_with_slot(&addresses) {
+            Ok((accounts, _)) => accounts,
+            Err(e) => {
+                warn!("could not fetch {} token metadata accounts: {}", chunk.len(), e);
+                continue;
+            }
+        };
+        for (mint, account) in chunk.iter().zip(accounts) {
+            if let Some(symbol) = account.and_then(|account| metadata_symbol(&account.data)) {
+                symbols.insert(*mint, symbol);
+            }
+        }
+    }
+    symbols
+}
+
+/// The symbol of a metadata account: after the name, a borsh string padded
+/// with zeros
+fn metadata_symbol(data: &[u8]) -> Option<String> {
+    let (_, rest) = borsh_string(data.get(METADATA_NAME_OFFSET..)?)?;
+    let (symbol, _) = borsh_string(rest)?;
+    let symbol = symbol.trim_end_matches('\0').trim();
+    (!symbol.is_empty()).then(|| symbol.to_string())
+}
+
+/// A u32 length-prefixed string and what follows it
+fn borsh_string(data: &[u8]) -> Option<(String, &[u8])> {
+    let len = u32::from_le_bytes(data.get(..4)?.try_into().ok()?) as usize;
+    let bytes = data.get(4..4 + len)?;
+    Some((String::from_utf8_lossy(bytes).to_string(), &data[4 + len..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pools::PoolVariant;
+    use crate::tests::pools::orca_pool;
+    use crate::utils::PoolQuote;
+    use std::rc::Rc;
+    use std::time::Instant;
+
+    fn borsh(s: &str, padded: usize) -> Vec<u8> {
+        let mut bytes = (padded as u32).to_le_bytes().to_vec();
+        bytes.extend(s.as_bytes());
+        bytes.extend(std::iter::repeat(0).take(padded - s.len()));
+        bytes
+    }
+
+    #[test]
+    fn routes_show_symbols_dexes_and_profit() {
+        let (usdc, sol, odd) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut symbols = TokenSymbols::default();
+        symbols.insert(usdc, "USDC".to_string());
+        symbols.insert(sol, "SOL".to_string());
+        let mints = vec![usdc, sol, odd, usdc];
+        let pools: Vec<PoolQuote> = mints
+            .windows(2)
+            .map(|pair| PoolQuote::new(Rc::new(PoolVariant::from(orca_pool(&pair[0], &pair[1], 1_000, 1_000)))))
+            .collect();
+        let opportunity = ArbOpportunity {
+            route: "0->1->2->0".to_string(),
+            mints,
+            pools,
+            amount_in: 1_000_000,
+            amounts: vec![],
+            min_amount_out: 1_012_300,
+            expected_profit: 12_300,
+            found_slot: 0,
+            found_at: Instant::now(),
+            split: None,
+        };
+        // the test pools have 6 decimals
+        let short = &odd.to_string()[..4];
+        assert_eq!(
+            symbols.format_route(&opportunity),
+            format!("USDC → SOL (Orca) → {}… (Orca) → USDC (Orca) +0.0123 USDC", short)
+        );
+        assert_eq!(ui_amount(-12_300_000, 6), "-12.3");
+        assert_eq!(ui_amount(5, 0), "+5");
+    }
+
+    #[test]
+    fn symbols_come_from_lists_and_metadata_accounts() {
+        let list = serde_json::json!([
+            { "address": "a", "symbol": "A" },
+            { "address": "b", "symbol": " " },
+            { "symbol": "C" },
+        ]);
+        assert_eq!(list_symbols(&list), [("a".to_string(), "A".to_string())].into_iter().collect());
+
+        let mut data = vec![4; METADATA_NAME_OFFSET];
+        data.extend(borsh("Bonk", 32));
+        data.extend(borsh("BONK", 10));
+        data.extend(borsh("https://", 200));
+        assert_eq!(metadata_symbol(&data), Some("BONK".to_string()));
+        assert_eq!(metadata_symbol(&data[..METADATA_NAME_OFFSET + 10]), None);
+    }
+}
@@ -0,0 +1,63 @@
+//! Log output
+//!
+//! Logs go through `tracing`: the run loop opens a span per search tick, per
+//! executed route and (at trace level) per pool quote, and records the
+//! route, amounts, signature and latency on them. In the JSON format every
+//! line is one object carrying the fields of its spans, ready for a log
+//! pipeline; the text format is for reading in a terminal. Records the
+//! dependencies write through the `log` crate end up in the same output.
+//!
+//! The level is set with `RUST_LOG` as before, e.g.
+//! `RUST_LOG=info,solana_arb_core::search=trace` to also get every quote.
+
+use std::str::FromStr;
+
+use tracing_subscriber::EnvFilter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// One JSON object per line
+    Json,
+    /// Human readable lines
+    Text,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(LogFormat::Json),
+            "text" => Ok(LogFormat::Text),
+            other => Err(format!("unknown log format {} (json or text)", other)),
+        }
+    }
+}
+
+/// Installs the global subscriber, filtered by `RUST_LOG`
+pub fn init(format: LogFormat) {
+    let builder = tracing_subscriber::fmt().with_env_filter(EnvFilter::from_default_env());
+    let result = match format {
+        LogFormat::Json => builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .try_init(),
+        LogFormat::Text => builder.try_init(),
+    };
+    if let Err(e) = result {
+        eprintln!("could not set up logging: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_formats_parse() {
+        assert_eq!("json".parse::<LogFormat>(), Ok(LogFormat::Json));
+        assert_eq!("text".parse::<LogFormat>(), Ok(LogFormat::Text));
+        assert!("yaml".parse::<LogFormat>().is_err());
+    }
+}
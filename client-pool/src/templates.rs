@@ -0,0 +1,183 @@
+//! Pre-built swap instruction templates
+//!
+//! `PoolOperations::swap_ix` builds the router's Anchor request from scratch
+//! and derives the swap state, the pool authority and the owner's token
+//! accounts on every call. The routes of a run are all signed by the same
+//! owner, so `SwapTemplates` builds every pool's swap in both directions
+//! once the pools are loaded, along with the owner's token account per
+//! mint. Building a route then copies the swaps and only encodes the amounts
+//! of its start and profit instructions.
+//!
+//! Swaps the template build failed on (unsupported pool types, markets
+//! without open orders) are built on the spot as before, and fail the same.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use solana_sdk::instruction::Instruction;
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use tracing::info;
+
+use tmp::accounts as tmp_accounts;
+use tmp::instruction as tmp_ix;
+
+use crate::arb::Arbitrager;
+use crate::error::ArbResult;
+use crate::pool::PoolOperations;
+use crate::utils::{derive_token_address, PoolQuote};
+
+/// The routes' instructions of one owner, built ahead
+pub struct SwapTemplates {
+    program_id: Pubkey,
+    owner: Pubkey,
+    swap_state: Pubkey,
+    /// The owner's token account of every mint of the graph
+    token_accounts: HashMap<Pubkey, Pubkey>,
+    /// Swap instructions by pool address, mint in and mint out
+    swaps: HashMap<(Pubkey, Pubkey, Pubkey), Vec<Instruction>>,
+}
+
+impl SwapTemplates {
+    /// Templates of `owner` without any swap, every swap is built on the spot
+    pub fn empty(program: &Program, owner: &Pubkey) -> Self {
+        let program_id = program.id();
+        let (swap_state, _) = Pubkey::find_program_address(&[b"swap_state"], &program_id);
+        Self {
+            program_id,
+            owner: *owner,
+            swap_state,
+            token_accounts: HashMap::new(),
+            swaps: HashMap::new(),
+        }
+    }
+
+    /// Builds the swaps of every pool of `arb`'s graph, both directions
+    pub fn build(program: &Program, owner: &Pubkey, arb: &Arbitrager) -> Self {
+        let start = Instant::now();
+        let mut templates = Self::empty(program, owner);
+        for mint in arb.token_mints.iter() {
+            templates.token_accounts.insert(*mint, derive_token_address(owner, mint));
+        }
+        let mut failed = 0;
+        for (idx_in, edge) in arb.graph.0.iter() {
+            for (idx_out, pools) in edge.0.iter() {
+                let (mint_in, mint_out) = (arb.token_mints[idx_in.0], arb.token_mints[idx_out.0]);
+                for pool in pools {
+                    match pool.0.swap_ix(program, owner, &mint_in, &mint_out) {
+                        Ok(ixs) => {
+                            templates.swaps.insert((pool.0.get_address(), mint_in, mint_out), ixs);
+                        }
+                        Err(_) => failed += 1,
+                    }
+                }
+            }
+        }
+        info!(
+            "Built {} swap templates in {:?} ({} swaps left to build on the spot)",
+            templates.swaps.len(),
+            start.elapsed(),
+            failed
+        );
+        templates
+    }
+
+    pub fn len(&self) -> usize {
+        self.swaps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.swaps.is_empty()
+    }
+
+    /// The owner's token account of `mint`
+    pub fn token_account(&self, mint: &Pubkey) -> Pubkey {
+        match self.token_accounts.get(mint) {
+            Some(account) => *account,
+            None => derive_token_address(&self.owner, mint),
+        }
+    }
+
+    /// The swap of `mint_in` into `mint_out` through `pool`, from its
+    /// template if there is one
+    pub fn swap_ix(
+        &self,
+        program: &Program,
+        pool: &PoolQuote,
+        mint_in: &Pubkey,
+        mint_out: &Pubkey,
+    ) -> ArbResult<Vec<Instruction>> {
+        match self.swaps.get(&(pool.0.get_address(), *mint_in, *mint_out)) {
+            Some(ixs) => Ok(ixs.clone()),
+            None => pool.0.swap_ix(program, &self.owner, mint_in, mint_out),
+        }
+    }
+
+    /// The router's start-balance snapshot of `swap_input` of the owner's `mint`
+    pub fn start_swap_ix(&self, mint: &Pubkey, swap_input: u64) -> Instruction {
+        self.token_and_swap_state_ix(mint, tmp_ix::StartSwap { swap_input }.data())
+    }
+
+    /// The router's profit guard, reverting unless the owner's `mint`
+    /// gained `min_profit` since the start instruction
+    pub fn profit_or_revert_ix(&self, mint: &Pubkey, min_profit: u64) -> Instruction {
+        self.token_and_swap_state_ix(mint, tmp_ix::ProfitOrRevert { min_profit }.data())
+    }
+
+    fn token_and_swap_state_ix(&self, mint: &Pubkey, data: Vec<u8>) -> Instruction {
+        let accounts = tmp_accounts::TokenAndSwapState {
+            src: self.token_account(mint),
+            swap_state: self.swap_state,
+        };
+        Instruction {
+            program_id: self.program_id,
+            accounts: accounts.to_account_metas(None),
+            data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+    use anchor_client::solana_sdk::signature::Keypair;
+    use anchor_client::{Client, Cluster};
+    use std::rc::Rc;
+
+    use crate::constants::ARB_PROGRAM_ID;
+    use crate::pools::PoolVariant;
+    use crate::tests::pools::orca_pool;
+
+    #[test]
+    fn templates_build_what_the_pools_build() {
+        let owner_kp = Rc::new(Keypair::new());
+        let client = Client::new_with_options(Cluster::Localnet, owner_kp, CommitmentConfig::confirmed());
+        let program = client.program(*ARB_PROGRAM_ID);
+        let (owner, a, b) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let pool = PoolQuote::new(Rc::new(PoolVariant::from(orca_pool(&a, &b, 1_000, 1_000))));
+
+        let mut templates = SwapTemplates::empty(&program, &owner);
+        let expected = pool.0.swap_ix(&program, &owner, &a, &b).unwrap();
+        templates.swaps.insert((pool.0.get_address(), a, b), expected.clone());
+        assert_eq!(templates.swap_ix(&program, &pool, &a, &b).unwrap(), expected);
+        // no template the other way, built on the spot
+        assert_eq!(
+            templates.swap_ix(&program, &pool, &b, &a).unwrap(),
+            pool.0.swap_ix(&program, &owner, &b, &a).unwrap()
+        );
+
+        let start = program
+            .request()
+            .accounts(tmp_accounts::TokenAndSwapState {
+                src: derive_token_address(&owner, &a),
+                swap_state: templates.swap_state,
+            })
+            .args(tmp_ix::StartSwap { swap_input: 42 })
+            .instructions()
+            .unwrap();
+        assert_eq!(vec![templates.start_swap_ix(&a, 42)], start);
+    }
+}
@@ -1 +1,44 @@
-pub mod quotes;
\ No newline at end of file
+pub mod quotes;
+
+/// Pools for unit tests, built in memory
+#[cfg(test)]
+pub mod pools {
+    use anchor_client::solana_sdk::pubkey::Pubkey;
+
+    use crate::pool_utils::serum::FeeTier;
+    use crate::pools::orca::OrcaPool;
+    use crate::pools::serum::SerumPool;
+    use crate::serialize::token::WrappedPubkey;
+
+    /// Constant product Orca pool (6 decimals, 0.3% fee) between `a` and `b`
+    pub fn orca_pool(a: &Pubkey, b: &Pubkey, reserve_a: u128, reserve_b: u128) -> OrcaPool {
+        solana_arb_core::test_pools::orca_pool(&[*a, *b], &[reserve_a, reserve_b])
+    }
+
+    /// Serum market between `base` and `quote` with no book loaded, and
+    /// `open_orders` as the owner's open orders account if set
+    pub fn serum_market(base: &Pubkey, quote: &Pubkey, open_orders: Option<Pubkey>) -> SerumPool {
+        let key = || WrappedPubkey(Pubkey::new_unique());
+        let market = Pubkey::new_unique();
+        SerumPool {
+            own_address: WrappedPubkey(market),
+            base_mint: WrappedPubkey(*base),
+            quote_mint: WrappedPubkey(*quote),
+            base_scale: 6,
+            quote_scale: 6,
+            base_vault: key(),
+            quote_vault: key(),
+            request_queue: key(),
+            event_queue: key(),
+            bids: key(),
+            asks: key(),
+            vault_signer: key(),
+            taker_fee_pct: 0.0004,
+            accounts: None,
+            open_orders: open_orders.map(|oo| [(market.to_string(), oo.to_string())].into_iter().collect()),
+            update_slot: None,
+            book: None,
+            fee_tier: FeeTier::Base,
+        }
+    }
+}
@@ -16,6 +16,7 @@ use tmp::instruction as tmp_ix;
 
 use crate::utils::{derive_token_address, read_json_dir};
 use crate::pool::{PoolType, PoolOperations, pool_factory};
+use crate::pools::PoolVariant;
 use crate::constants::*;
 
 
@@ -47,6 +48,13 @@ fn mercurial() {
     test_all_pool_quotes(pool_dir, pool_tipe);
 }
 
+#[test]
+fn raydium() {
+    let pool_dir = "../pools/raydium/".to_string();
+    let pool_tipe = PoolType::RaydiumType; 
+    test_all_pool_quotes(pool_dir, pool_tipe);
+}
+
 #[test]
 fn orca() {
     let pool_dir = "../pools/orca/".to_string();
@@ -86,7 +94,7 @@ fn test_all_pool_quotes(
     for pool_path in pool_paths {        
 
         let contents = std::fs::read_to_string(&pool_path).unwrap();
-        let mut pool = pool_factory(&pool_tipe, &contents);
+        let mut pool = pool_factory(&pool_tipe, &contents).unwrap();
 
         // println!("{}", pool_path);
         let err_flag = test_pool_quote(
@@ -102,7 +110,7 @@ fn test_all_pool_quotes(
 }
 
 fn test_pool_quote(
-    pool: &mut Box<dyn PoolOperations>, 
+    pool: &mut PoolVariant, 
     pool_path: &str,
     connection: &RpcClient,
     program: &Program, 
@@ -113,7 +121,7 @@ fn test_pool_quote(
     let accounts = connection
             .get_multiple_accounts(&update_accounts)
             .unwrap();
-    pool.set_update_accounts(accounts, Cluster::Localnet);
+    pool.set_update_accounts(accounts, Cluster::Localnet).unwrap();
 
     // get a quote 
     let pool_mints = pool.get_mints(); 
@@ -151,7 +159,7 @@ fn test_pool_quote(
             amount_in, 
             mint_in, 
             mint_out
-        );
+        ).unwrap_or(0);
         // println!("quote: {}", quote_out_amount);
 
         if quote_out_amount == 0 {
@@ -234,7 +242,7 @@ fn test_pool_quote(
         &owner.pubkey(), 
         mint_in, 
         mint_out
-    );
+    ).unwrap();
     ixs.push(swap_ix);
     
     let ixs = ixs.concat();
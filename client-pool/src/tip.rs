@@ -0,0 +1,334 @@
+//! Adaptive Jito tips
+//!
+//! A static tip either overpays or doesn't land. The controller tips a share
+//! of the route's expected profit instead: the share goes up while recent
+//! sends land less often than `target_landing_rate` and down while they land
+//! more often. The tip never goes below the observed landed-tip percentile
+//! (polled from the Jito tip floor API) and is bounded by `min_lamports`,
+//! `max_lamports` and `max_profit_share_bps`; a route that can't afford its
+//! tip isn't sent.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use reqwest::blocking::Client as HttpClient;
+use serde::{Deserialize, Serialize};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::sol_to_lamports;
+use solana_sdk::system_instruction;
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, warn};
+
+use crate::constants::JITO_TIP_ACCOUNTS;
+
+/// Percentiles published by the tip floor API
+const PERCENTILES: [u8; 5] = [25, 50, 75, 95, 99];
+
+/// Adaptive tip settings
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TipConfig {
+    /// Jito REST endpoint with the recently landed tip percentiles; the
+    /// floor is left out if unset
+    #[serde(default = "default_tip_floor_url")]
+    pub tip_floor_url: Option<String>,
+    /// Landed-tip percentile used as the floor (25, 50, 75, 95 or 99)
+    #[serde(default = "default_floor_percentile")]
+    pub floor_percentile: u8,
+    /// Seconds between tip floor polls
+    #[serde(default = "default_refresh_secs")]
+    pub refresh_secs: u64,
+    /// Share of the expected profit tipped while sends land well
+    #[serde(default = "default_min_profit_share_bps")]
+    pub min_profit_share_bps: u64,
+    /// Largest share of the expected profit ever tipped
+    #[serde(default = "default_max_profit_share_bps")]
+    pub max_profit_share_bps: u64,
+    /// How far the share moves after every send
+    #[serde(default = "default_step_bps")]
+    pub step_bps: u64,
+    /// Landing rate over the last `window` sends the controller aims for
+    #[serde(default = "default_target_landing_rate")]
+    pub target_landing_rate: f64,
+    #[serde(default = "default_window")]
+    pub window: usize,
+    #[serde(default = "default_min_lamports")]
+    pub min_lamports: u64,
+    #[serde(default = "default_max_lamports")]
+    pub max_lamports: u64,
+}
+
+fn default_tip_floor_url() -> Option<String> {
+    Some("https://bundles.jito.wtf/api/v1/bundles/tip_floor".to_string())
+}
+
+fn default_floor_percentile() -> u8 {
+    50
+}
+
+fn default_refresh_secs() -> u64 {
+    10
+}
+
+fn default_min_profit_share_bps() -> u64 {
+    1_000
+}
+
+fn default_max_profit_share_bps() -> u64 {
+    5_000
+}
+
+fn default_step_bps() -> u64 {
+    250
+}
+
+fn default_target_landing_rate() -> f64 {
+    0.5
+}
+
+fn default_window() -> usize {
+    20
+}
+
+fn default_min_lamports() -> u64 {
+    1_000
+}
+
+fn default_max_lamports() -> u64 {
+    1_000_000
+}
+
+impl TipConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if !PERCENTILES.contains(&self.floor_percentile) {
+            problems.push(format!(
+                "tip.floor_percentile must be one of {:?}, got {}",
+                PERCENTILES, self.floor_percentile
+            ));
+        }
+        if self.min_profit_share_bps > self.max_profit_share_bps {
+            problems.push("tip.min_profit_share_bps must be <= tip.max_profit_share_bps".to_string());
+        }
+        if self.max_profit_share_bps > 10_000 {
+            problems.push("tip.max_profit_share_bps must be <= 10000".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.target_landing_rate) {
+            problems.push("tip.target_landing_rate must be between 0 and 1".to_string());
+        }
+        if self.window == 0 {
+            problems.push("tip.window must be > 0".to_string());
+        }
+        if self.min_lamports > self.max_lamports {
+            problems.push("tip.min_lamports must be <= tip.max_lamports".to_string());
+        }
+        problems
+    }
+}
+
+/// One entry of the tip floor API (tips in SOL)
+#[derive(Debug, Deserialize)]
+struct TipFloor {
+    landed_tips_25th_percentile: f64,
+    landed_tips_50th_percentile: f64,
+    landed_tips_75th_percentile: f64,
+    landed_tips_95th_percentile: f64,
+    landed_tips_99th_percentile: f64,
+}
+
+impl TipFloor {
+    fn percentile(&self, p: u8) -> f64 {
+        match p {
+            25 => self.landed_tips_25th_percentile,
+            50 => self.landed_tips_50th_percentile,
+            75 => self.landed_tips_75th_percentile,
+            95 => self.landed_tips_95th_percentile,
+            _ => self.landed_tips_99th_percentile,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TipState {
+    outcomes: VecDeque<bool>,
+    share_bps: u64,
+    floor_lamports: Option<u64>,
+    last_refresh: Option<Instant>,
+    next_account: usize,
+}
+
+/// Picks the tip of every send, see the module docs
+pub struct TipController {
+    config: TipConfig,
+    client: HttpClient,
+    state: Mutex<TipState>,
+}
+
+impl TipController {
+    pub fn new(config: &TipConfig) -> Self {
+        Self {
+            config: config.clone(),
+            client: HttpClient::builder()
+                .timeout(Duration::from_secs(2))
+                .build()
+                .expect("http client"),
+            state: Mutex::new(TipState {
+                outcomes: VecDeque::with_capacity(config.window),
+                share_bps: (config.min_profit_share_bps + config.max_profit_share_bps) / 2,
+                floor_lamports: None,
+                last_refresh: None,
+                next_account: 0,
+            }),
+        }
+    }
+
+    /// Polls the tip floor API if the last poll is older than `refresh_secs`;
+    /// a failed poll keeps the previous floor
+    pub fn refresh_floor(&self) {
+        let url = match &self.config.tip_floor_url {
+            Some(url) => url,
+            None => return,
+        };
+        {
+            let state = self.state.lock().unwrap();
+            let refresh = Duration::from_secs(self.config.refresh_secs);
+            if state.last_refresh.map_or(false, |at| at.elapsed() < refresh) {
+                return;
+            }
+        }
+        let floors: Result<Vec<TipFloor>, _> = self
+            .client
+            .get(url)
+            .send()
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.json());
+
+        let mut state = self.state.lock().unwrap();
+        state.last_refresh = Some(Instant::now());
+        match floors {
+            Ok(floors) => match floors.first() {
+                Some(floor) => {
+                    let lamports = sol_to_lamports(floor.percentile(self.config.floor_percentile));
+                    debug!("tip floor p{}: {} lamports", self.config.floor_percentile, lamports);
+                    state.floor_lamports = Some(lamports);
+                }
+                None => warn!("tip floor {} returned no data", url),
+            },
+            Err(e) => warn!("failed to fetch the tip floor from {}: {}", url, e),
+        }
+    }
+
+    /// Sets the floor directly instead of polling it
+    pub fn set_floor(&self, lamports: u64) {
+        self.state.lock().unwrap().floor_lamports = Some(lamports);
+    }
+
+    /// Whether a tipped send landed; moves the profit share toward the
+    /// target landing rate
+    pub fn record_outcome(&self, landed: bool) {
+        let mut state = self.state.lock().unwrap();
+        if state.outcomes.len() == self.config.window {
+            state.outcomes.pop_front();
+        }
+        state.outcomes.push_back(landed);
+
+        let rate = landing_rate(&state.outcomes);
+        state.share_bps = if rate < self.config.target_landing_rate {
+            (state.share_bps + self.config.step_bps).min(self.config.max_profit_share_bps)
+        } else {
+            state
+                .share_bps
+                .saturating_sub(self.config.step_bps)
+                .max(self.config.min_profit_share_bps)
+        };
+    }
+
+    /// Share of the profit currently tipped (bps)
+    pub fn share_bps(&self) -> u64 {
+        self.state.lock().unwrap().share_bps
+    }
+
+    /// Landing rate of the last `window` sends, `None` before the first send
+    pub fn landing_rate(&self) -> Option<f64> {
+        let state = self.state.lock().unwrap();
+        if state.outcomes.is_empty() {
+            None
+        } else {
+            Some(landing_rate(&state.outcomes))
+        }
+    }
+
+    /// Tip for a route expected to make `profit_lamports`, `None` if the
+    /// bounded tip is more than `max_profit_share_bps` of the profit
+    pub fn tip_for(&self, profit_lamports: u64) -> Option<u64> {
+        let state = self.state.lock().unwrap();
+        let share = share_of(profit_lamports, state.share_bps);
+        let tip = share
+            .max(state.floor_lamports.unwrap_or(0))
+            .max(self.config.min_lamports)
+            .min(self.config.max_lamports);
+        if tip > share_of(profit_lamports, self.config.max_profit_share_bps) {
+            return None;
+        }
+        Some(tip)
+    }
+
+    /// Transfer of `lamports` to one of the Jito tip accounts (rotated
+    /// between sends to spread write locks)
+    pub fn tip_ix(&self, payer: &Pubkey, lamports: u64) -> Instruction {
+        let mut state = self.state.lock().unwrap();
+        let account = JITO_TIP_ACCOUNTS[state.next_account % JITO_TIP_ACCOUNTS.len()];
+        state.next_account += 1;
+        system_instruction::transfer(payer, &account, lamports)
+    }
+}
+
+fn landing_rate(outcomes: &VecDeque<bool>) -> f64 {
+    outcomes.iter().filter(|landed| **landed).count() as f64 / outcomes.len() as f64
+}
+
+fn share_of(amount: u64, bps: u64) -> u64 {
+    (amount as u128 * bps as u128 / 10_000) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> TipConfig {
+        serde_json::from_str("{ \"tip_floor_url\": null }").unwrap()
+    }
+
+    #[test]
+    fn share_follows_the_landing_rate() {
+        let tips = TipController::new(&config());
+        let start = tips.share_bps();
+
+        tips.record_outcome(false);
+        tips.record_outcome(false);
+        assert_eq!(tips.share_bps(), start + 2 * 250);
+
+        for _ in 0..20 {
+            tips.record_outcome(true);
+        }
+        assert_eq!(tips.share_bps(), 1_000);
+        assert_eq!(tips.landing_rate(), Some(1.0));
+    }
+
+    #[test]
+    fn tips_are_bounded() {
+        let tips = TipController::new(&config()); // 30% share to start with
+
+        assert_eq!(tips.tip_for(100_000), Some(30_000));
+        // never above max_lamports
+        assert_eq!(tips.tip_for(100_000_000), Some(1_000_000));
+        // the floor is paid if the profit can afford it ...
+        tips.set_floor(45_000);
+        assert_eq!(tips.tip_for(100_000), Some(45_000));
+        // ... and the route is skipped if it can't
+        tips.set_floor(60_000);
+        assert_eq!(tips.tip_for(100_000), None);
+    }
+}
@@ -0,0 +1,313 @@
+//! Fee-on-transfer and honeypot token detection
+//!
+//! A token that taxes its transfers returns less than any pool quotes for
+//! it, and one that blocks sells can be bought but never swapped back; a
+//! route through either loses. Before the search trades a mint, the start
+//! mint is swapped into it and back through their most liquid direct pool
+//! in a simulation, ending with a profit guard that requires nothing: it
+//! logs the balances and reverts on the fees the round trip lost, which is
+//! expected. A sell that fails blacklists the mint, and so does a round
+//! trip returning more than `max_loss_bps` less than its quote.
+//!
+//! Verdicts are kept in `path` across runs, so only new mints are probed.
+//! Mints without a direct pool to the start mint can't be probed, and those
+//! whose buy failed have no verdict and are probed again at the next start.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signature::Signer;
+use serde::{Deserialize, Serialize};
+use solana_sdk::transaction::TransactionError;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use tracing::{info, warn};
+
+use crate::amount::BPS;
+use crate::arb::Arbitrager;
+use crate::executor::Dispatcher;
+use crate::graph::AccountSource;
+use crate::pool::PoolOperations;
+use crate::profit_sweep::create_ata_ix;
+use crate::utils::derive_token_address;
+
+/// Token probe settings
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TokenProbeConfig {
+    /// Start mint (scaled) swapped into every new mint and back
+    pub amount: u64,
+    /// Most the round trip may return below its quote before the mint is
+    /// taken for taxing its transfers
+    #[serde(default = "default_max_loss_bps")]
+    pub max_loss_bps: u64,
+    /// Most mints probed at a start, the others wait for the next one
+    #[serde(default = "default_max_probes")]
+    pub max_probes: usize,
+    /// JSON file the verdicts are kept in across runs; in memory only if unset
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+fn default_max_loss_bps() -> u64 {
+    100
+}
+
+fn default_max_probes() -> usize {
+    100
+}
+
+impl TokenProbeConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.amount == 0 {
+            problems.push("token_probe.amount must be > 0".to_string());
+        }
+        if self.max_loss_bps > BPS {
+            problems.push(format!(
+                "token_probe.max_loss_bps must be <= {}, got {}",
+                BPS, self.max_loss_bps
+            ));
+        }
+        problems
+    }
+}
+
+/// What the round trip of a mint showed
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "verdict", rename_all = "snake_case")]
+pub enum ProbeVerdict {
+    Clean,
+    /// The round trip returned `loss_bps` less than quoted
+    Taxed { loss_bps: u64 },
+    /// Swapping the mint back failed with `error`
+    SellBlocked { error: String },
+}
+
+impl ProbeVerdict {
+    pub fn blacklists(&self) -> bool {
+        *self != ProbeVerdict::Clean
+    }
+}
+
+impl fmt::Display for ProbeVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProbeVerdict::Clean => write!(f, "clean"),
+            ProbeVerdict::Taxed { loss_bps } => {
+                write!(f, "transfers taxed, {} bps below the quote", loss_bps)
+            }
+            ProbeVerdict::SellBlocked { error } => write!(f, "sells fail: {}", error),
+        }
+    }
+}
+
+/// The balances the profit guard logs, `start balance <start> end balance <end> ...`
+fn logged_balances(log: &str) -> Option<(u128, u128)> {
+    let mut words = log.split("start balance ").nth(1)?.split_whitespace();
+    let start = words.next()?.parse().ok()?;
+    match (words.next(), words.next()) {
+        (Some("end"), Some("balance")) => Some((start, words.next()?.parse().ok()?)),
+        _ => None,
+    }
+}
+
+/// The verdict on a simulated round trip of `amount` quoted to return
+/// `quoted`, whose sell is instructions `sell`; `None` if the buy failed
+/// and nothing can be told
+pub fn verdict(
+    amount: u128,
+    quoted: u128,
+    err: Option<&TransactionError>,
+    logs: &[String],
+    sell: Range<usize>,
+    max_loss_bps: u64,
+) -> Option<ProbeVerdict> {
+    if let Some(TransactionError::InstructionError(index, error)) = err {
+        if sell.contains(&(*index as usize)) {
+            return Some(ProbeVerdict::SellBlocked {
+                error: format!("{:?}", error),
+            });
+        }
+    }
+    let (start, end) = logs.iter().find_map(|log| logged_balances(log))?;
+    let returned = (amount + end).saturating_sub(start);
+    let loss_bps = match quoted.checked_sub(returned) {
+        Some(shortfall) if quoted > 0 => (shortfall * BPS as u128 / quoted) as u64,
+        _ => 0,
+    };
+    Some(match loss_bps > max_loss_bps {
+        true => ProbeVerdict::Taxed { loss_bps },
+        false => ProbeVerdict::Clean,
+    })
+}
+
+/// Verdicts by mint, see the module docs
+pub struct TokenProbe {
+    config: TokenProbeConfig,
+    verdicts: BTreeMap<String, ProbeVerdict>,
+}
+
+impl TokenProbe {
+    /// Starts from the verdicts in `config.path` if it exists
+    pub fn new(config: &TokenProbeConfig) -> Self {
+        let verdicts = match &config.path {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                    warn!("ignoring unreadable token probe verdicts {}: {}", path, e);
+                    BTreeMap::new()
+                }),
+                Err(_) => BTreeMap::new(),
+            },
+            None => BTreeMap::new(),
+        };
+        Self {
+            config: config.clone(),
+            verdicts,
+        }
+    }
+
+    /// Mints a probe found taxing or blocking sells
+    pub fn blacklisted(&self) -> Vec<Pubkey> {
+        self.verdicts
+            .iter()
+            .filter(|(_, verdict)| verdict.blacklists())
+            .filter_map(|(mint, _)| Pubkey::from_str(mint).ok())
+            .collect()
+    }
+
+    /// Probes the mints of the graph without a verdict that trade directly
+    /// against the start mint (at most `max_probes`), returns how many got one
+    pub fn probe_new(&mut self, ctx: &Dispatcher, arb: &Arbitrager, start_mint: &Pubkey) -> usize {
+        let mut new: Vec<Pubkey> = arb
+            .token_mints
+            .iter()
+            .filter(|mint| *mint != start_mint && !self.verdicts.contains_key(&mint.to_string()))
+            .filter(|mint| !arb.pools_between(start_mint, mint).is_empty())
+            .copied()
+            .collect();
+        new.sort();
+        new.truncate(self.config.max_probes);
+        let mut probed = 0;
+        for mint in new {
+            let verdict = match self.probe(ctx, arb, start_mint, &mint) {
+                Some(verdict) => verdict,
+                None => continue,
+            };
+            if verdict.blacklists() {
+                warn!("blacklisting {}: {}", mint, verdict);
+            }
+            self.verdicts.insert(mint.to_string(), verdict);
+            probed += 1;
+        }
+        info!("probed {} new tokens, {} blacklisted in all", probed, self.blacklisted().len());
+        probed
+    }
+
+    fn probe(
+        &self,
+        ctx: &Dispatcher,
+        arb: &Arbitrager,
+        start_mint: &Pubkey,
+        mint: &Pubkey,
+    ) -> Option<ProbeVerdict> {
+        let amount = self.config.amount as u128;
+        let (pool, quoted) = arb
+            .pools_between(start_mint, mint)
+            .into_iter()
+            .filter_map(|pool| {
+                let out = pool.0.get_quote_with_amounts_scaled(amount, start_mint, mint).ok()?;
+                let back = pool.0.get_quote_with_amounts_scaled(out, mint, start_mint).ok()?;
+                Some((pool, back))
+            })
+            .max_by_key(|(_, back)| *back)?;
+
+        let owner = ctx.owner.pubkey();
+        let mut ixs = vec![];
+        // the owner may hold no account of the mint yet
+        match ctx.rpc.get_multiple_accounts_with_slot(&[derive_token_address(&owner, mint)]) {
+            Ok((accounts, _)) if accounts.first().map_or(true, |a| a.is_none()) => {
+                ixs.push(create_ata_ix(&owner, &owner, mint))
+            }
+            Ok(_) => {}
+            Err(e) => {
+                warn!("can't probe {}: {}", mint, e);
+                return None;
+            }
+        }
+        let swap = |mint_in: &Pubkey, mint_out: &Pubkey| {
+            ctx.templates
+                .swap_ix(&ctx.program, &pool, mint_in, mint_out)
+                .map_err(|e| warn!("can't probe {} through {}: {}", mint, pool.0.get_id(), e))
+                .ok()
+        };
+        let (buy, sell) = (swap(start_mint, mint)?, swap(mint, start_mint)?);
+        ixs.push(ctx.templates.start_swap_ix(start_mint, self.config.amount));
+        ixs.extend(buy);
+        let sell_ixs = ixs.len()..ixs.len() + sell.len();
+        ixs.extend(sell);
+        // logs both balances, then reverts on the fees the round trip lost
+        ixs.push(ctx.templates.profit_or_revert_ix(start_mint, 0));
+
+        let result = ctx.simulate_maintenance(&ixs, "token probe")?;
+        verdict(
+            amount,
+            quoted,
+            result.err.as_ref(),
+            result.logs.as_deref().unwrap_or_default(),
+            sell_ixs,
+            self.config.max_loss_bps,
+        )
+    }
+
+    /// Writes the verdicts to `path`, if set
+    pub fn persist(&self) -> std::io::Result<()> {
+        let path = match &self.config.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let json = serde_json::to_string_pretty(&self.verdicts)?;
+        std::fs::write(path, json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::instruction::InstructionError;
+
+    fn guard_logs(start: u128, end: u128) -> Vec<String> {
+        vec![
+            "Program log: Instruction: ProfitOrRevert".to_string(),
+            format!("Program log: start balance {} end balance {} min profit 0", start, end),
+        ]
+    }
+
+    #[test]
+    fn round_trips_are_judged_against_their_quote() {
+        let no_profit = TransactionError::InstructionError(4, InstructionError::Custom(6001));
+        // lost the fees the quote expected
+        let clean = verdict(1_000, 994, Some(&no_profit), &guard_logs(10_000, 9_994), 2..3, 100);
+        assert_eq!(clean, Some(ProbeVerdict::Clean));
+        // 10% of every transfer is taken
+        let taxed = verdict(1_000, 994, Some(&no_profit), &guard_logs(10_000, 9_805), 2..3, 100);
+        assert_eq!(taxed, Some(ProbeVerdict::Taxed { loss_bps: 1_901 }));
+        assert!(taxed.unwrap().blacklists());
+    }
+
+    #[test]
+    fn failed_sells_blacklist_and_failed_buys_tell_nothing() {
+        let sell_failed = TransactionError::InstructionError(2, InstructionError::Custom(1));
+        let verdict_of = |err| verdict(1_000, 994, Some(&err), &[], 2..3, 100);
+        assert_eq!(
+            verdict_of(sell_failed),
+            Some(ProbeVerdict::SellBlocked {
+                error: "Custom(1)".to_string()
+            })
+        );
+        let buy_failed = TransactionError::InstructionError(1, InstructionError::Custom(1));
+        assert_eq!(verdict_of(buy_failed), None);
+    }
+}
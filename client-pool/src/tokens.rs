@@ -0,0 +1,328 @@
+//! Token universe sources
+//!
+//! The mints indexed before the pools are loaded (so they keep stable
+//! indices) come from a list of sources: Jupiter's trending or strict token
+//! lists, Birdeye's top tokens by 24h volume or a local JSON file of mint
+//! addresses. Every network source is cached on disk for `ttl_secs`; when a
+//! fetch fails the stale cache is used instead, and a source with neither is
+//! skipped with a warning instead of stopping the run.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use reqwest::blocking::Client as HttpClient;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use tracing::{info, warn};
+
+pub(crate) const JUPITER_TOKENS_URL: &str = "https://tokens.jup.ag/tokens";
+const BIRDEYE_TOKENLIST_URL: &str = "https://public-api.birdeye.so/defi/tokenlist";
+
+#[derive(Debug, Error)]
+pub enum TokenSourceError {
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("unexpected response: {0}")]
+    Format(String),
+}
+
+/// Where a list of mints comes from
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TokenSource {
+    /// Jupiter's `birdeye-trending` tag
+    JupiterTrending,
+    /// Jupiter's strict (verified) list
+    JupiterStrict,
+    /// Birdeye's tokens with the most 24h volume
+    Birdeye {
+        #[serde(default = "default_birdeye_limit")]
+        limit: usize,
+        /// Name of the environment variable holding the api key
+        #[serde(default = "default_birdeye_api_key_env")]
+        api_key_env: String,
+    },
+    /// JSON array of mint addresses, never cached
+    File { path: String },
+}
+
+fn default_birdeye_limit() -> usize {
+    50
+}
+
+fn default_birdeye_api_key_env() -> String {
+    "BIRDEYE_API_KEY".to_string()
+}
+
+impl TokenSource {
+    /// Name of the cache file (without extension)
+    fn name(&self) -> String {
+        match self {
+            TokenSource::JupiterTrending => "jupiter_trending".to_string(),
+            TokenSource::JupiterStrict => "jupiter_strict".to_string(),
+            TokenSource::Birdeye { limit, .. } => format!("birdeye_top_{}", limit),
+            TokenSource::File { path } => path.clone(),
+        }
+    }
+
+    pub fn problems(&self) -> Vec<String> {
+        match self {
+            TokenSource::Birdeye { limit, .. } if *limit == 0 => {
+                vec!["tokens.sources: birdeye limit must be > 0".to_string()]
+            }
+            TokenSource::File { path } if path.is_empty() => {
+                vec!["tokens.sources: file path must not be empty".to_string()]
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Fetches the source's mint addresses, unparsed
+    fn fetch(&self, client: &HttpClient) -> Result<Vec<String>, TokenSourceError> {
+        match self {
+            TokenSource::JupiterTrending => jupiter_list(client, "birdeye-trending"),
+            TokenSource::JupiterStrict => jupiter_list(client, "strict"),
+            TokenSource::Birdeye { limit, api_key_env } => {
+                let api_key = std::env::var(api_key_env)
+                    .map_err(|_| TokenSourceError::Format(format!("{} is not set", api_key_env)))?;
+                let response: Value = client
+                    .get(BIRDEYE_TOKENLIST_URL)
+                    .query(&[
+                        ("sort_by", "v24hUSD".to_string()),
+                        ("sort_type", "desc".to_string()),
+                        ("offset", "0".to_string()),
+                        ("limit", limit.to_string()),
+                    ])
+                    .header("X-API-KEY", api_key)
+                    .header("x-chain", "solana")
+                    .send()?
+                    .error_for_status()?
+                    .json()?;
+                addresses(&response["data"]["tokens"])
+            }
+            TokenSource::File { path } => {
+                let response: Value = serde_json::from_str(&fs::read_to_string(path)?)
+                    .map_err(|e| TokenSourceError::Format(format!("{}: {}", path, e)))?;
+                addresses(&response)
+            }
+        }
+    }
+}
+
+fn jupiter_list(client: &HttpClient, tag: &str) -> Result<Vec<String>, TokenSourceError> {
+    let response: Value = client
+        .get(JUPITER_TOKENS_URL)
+        .query(&[("tags", tag)])
+        .send()?
+        .error_for_status()?
+        .json()?;
+    addresses(&response)
+}
+
+/// Addresses of a JSON array of either address strings or objects with an
+/// `address` field
+fn addresses(list: &Value) -> Result<Vec<String>, TokenSourceError> {
+    let list = list
+        .as_array()
+        .ok_or_else(|| TokenSourceError::Format("expected an array of tokens".to_string()))?;
+    Ok(list
+        .iter()
+        .filter_map(|token| token.as_str().or_else(|| token.get("address").and_then(Value::as_str)))
+        .map(str::to_string)
+        .collect())
+}
+
+/// The sources and their cache
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct TokensConfig {
+    /// Merged in order, duplicates keep their first position
+    #[serde(default = "default_sources")]
+    pub sources: Vec<TokenSource>,
+    /// Directory the fetched lists are cached in
+    #[serde(default = "default_cache_dir")]
+    pub cache_dir: String,
+    /// How long a cached list is used before it's fetched again
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+    /// Resolve the symbols logs show for the mints (see `symbols.rs`)
+    #[serde(default = "default_symbols")]
+    pub symbols: bool,
+}
+
+fn default_sources() -> Vec<TokenSource> {
+    vec![TokenSource::JupiterTrending]
+}
+
+fn default_cache_dir() -> String {
+    "cache/tokens".to_string()
+}
+
+fn default_ttl_secs() -> u64 {
+    3_600
+}
+
+fn default_symbols() -> bool {
+    true
+}
+
+impl Default for TokensConfig {
+    fn default() -> Self {
+        Self {
+            sources: default_sources(),
+            cache_dir: default_cache_dir(),
+            ttl_secs: default_ttl_secs(),
+            symbols: default_symbols(),
+        }
+    }
+}
+
+impl TokensConfig {
+    pub fn problems(&self) -> Vec<String> {
+        self.sources.iter().flat_map(TokenSource::problems).collect()
+    }
+
+    fn cache_path(&self, source: &TokenSource) -> PathBuf {
+        self.cache_file(&source.name())
+    }
+
+    /// Path of the cache file `name` (without extension)
+    pub(crate) fn cache_file(&self, name: &str) -> PathBuf {
+        Path::new(&self.cache_dir).join(format!("{}.json", name))
+    }
+}
+
+/// A cached list and whether it's younger than `ttl`, `None` if missing or unreadable
+pub(crate) fn read_cache<T: DeserializeOwned>(path: &Path, ttl: Duration) -> Option<(T, bool)> {
+    let modified = fs::metadata(path).and_then(|meta| meta.modified()).ok()?;
+    let fresh = SystemTime::now()
+        .duration_since(modified)
+        .map_or(true, |age| age < ttl);
+    let list = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    Some((list, fresh))
+}
+
+pub(crate) fn write_cache<T: Serialize + ?Sized>(path: &Path, list: &T) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let json = serde_json::to_string(list).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    // write then rename, a crash mid-write must not leave a truncated cache
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, json)?;
+    fs::rename(&tmp, path)
+}
+
+/// One source's list: a fresh cache, else a fetch (cached on success), else
+/// the stale cache
+fn load_source(config: &TokensConfig, source: &TokenSource, client: &HttpClient) -> Vec<String> {
+    if let TokenSource::File { .. } = source {
+        return source.fetch(client).unwrap_or_else(|e| {
+            warn!("token source {}: {}", source.name(), e);
+            vec![]
+        });
+    }
+    let path = config.cache_path(source);
+    let cached = read_cache(&path, Duration::from_secs(config.ttl_secs));
+    if let Some((list, true)) = cached {
+        return list;
+    }
+    match source.fetch(client) {
+        Ok(list) => {
+            if let Err(e) = write_cache(&path, &list) {
+                warn!("failed to cache token source {} at {}: {}", source.name(), path.display(), e);
+            }
+            list
+        }
+        Err(e) => match cached {
+            Some((list, _)) => {
+                warn!("token source {} failed, using its stale cache: {}", source.name(), e);
+                list
+            }
+            None => {
+                warn!("token source {} failed and has no cache, skipping it: {}", source.name(), e);
+                vec![]
+            }
+        },
+    }
+}
+
+/// Mints of every source, deduplicated in source order. Never fails, a
+/// source that can't be loaded just contributes nothing.
+pub fn load_tokens(config: &TokensConfig) -> Vec<Pubkey> {
+    let client = HttpClient::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .expect("http client");
+    merge(config.sources.iter().map(|source| {
+        let list = load_source(config, source, &client);
+        info!("token source {}: {} tokens", source.name(), list.len());
+        list
+    }))
+}
+
+fn merge(lists: impl Iterator<Item = Vec<String>>) -> Vec<Pubkey> {
+    let mut seen = HashSet::new();
+    lists
+        .flatten()
+        .filter_map(|addr| Pubkey::from_str(&addr).ok())
+        .filter(|mint| seen.insert(*mint))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addresses_take_strings_or_objects() {
+        let list = serde_json::json!(["a", { "address": "b", "symbol": "B" }, { "symbol": "C" }]);
+        assert_eq!(addresses(&list).unwrap(), vec!["a".to_string(), "b".to_string()]);
+        assert!(addresses(&serde_json::json!({ "tokens": [] })).is_err());
+    }
+
+    #[test]
+    fn merge_keeps_first_position_and_drops_bad_keys() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let merged = merge(
+            vec![
+                vec![a.to_string(), "not a key".to_string()],
+                vec![b.to_string(), a.to_string()],
+            ]
+            .into_iter(),
+        );
+        assert_eq!(merged, vec![a, b]);
+    }
+
+    #[test]
+    fn stale_cache_is_used_when_the_fetch_fails() {
+        let dir = std::env::temp_dir().join(format!("tokens-{}", Pubkey::new_unique()));
+        let config = TokensConfig {
+            sources: vec![],
+            cache_dir: dir.to_string_lossy().to_string(),
+            ttl_secs: 0,
+            symbols: false,
+        };
+        // no api key, so the fetch fails without touching the network
+        let source = TokenSource::Birdeye {
+            limit: 10,
+            api_key_env: format!("MISSING_{}", Pubkey::new_unique()),
+        };
+        let client = HttpClient::new();
+        assert!(load_source(&config, &source, &client).is_empty());
+
+        let mint = Pubkey::new_unique().to_string();
+        write_cache(&config.cache_path(&source), &[mint.clone()]).unwrap();
+        assert_eq!(load_source(&config, &source, &client), vec![mint]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,254 @@
+//! Route transaction sizing
+//!
+//! A transaction can't be more than `PACKET_DATA_SIZE` bytes on the wire.
+//! Long routes through pools with many accounts (Serum markets take a dozen)
+//! get past that, and were only found out when the send failed. Routes are
+//! now measured before they are signed: the message lists every account
+//! once, however many legs use it, so the size is that of the compiled
+//! message plus the signatures.
+//!
+//! A route that doesn't fit is split at instruction boundaries into as few
+//! transactions as fit, which only stays atomic inside a Jito bundle, and
+//! can't be done with a flash loan (the loan has to be repaid in the
+//! transaction that took it). Routes that can't be split are rejected.
+//!
+//! The size with the configured address lookup tables is reported along:
+//! every account found in a table costs a 1-byte index instead of its 32
+//! byte key. Sending v0 transactions needs a newer solana-sdk than the one
+//! this crate builds with, so this only tells which routes tables would save.
+
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::Message;
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use tracing::{debug, warn};
+
+use crate::bundle::MAX_BUNDLE_TXS;
+
+/// Largest serialized transaction a validator accepts
+pub const PACKET_DATA_SIZE: usize = 1232;
+
+/// Size of the header of an address lookup table account, the addresses follow
+const LOOKUP_TABLE_META_SIZE: usize = 56;
+
+/// Addresses of the configured lookup tables
+#[derive(Debug, Clone, Default)]
+pub struct LookupTables {
+    pub tables: Vec<(Pubkey, HashSet<Pubkey>)>,
+}
+
+impl LookupTables {
+    /// Reads the tables' addresses from chain, skipping tables that can't be read
+    pub fn fetch(connection: &RpcClient, addresses: &[String]) -> Self {
+        let mut tables = vec![];
+        for address in addresses {
+            let key = match Pubkey::from_str(address) {
+                Ok(key) => key,
+                Err(_) => continue,
+            };
+            match connection.get_account_data(&key) {
+                Ok(data) if data.len() >= LOOKUP_TABLE_META_SIZE => {
+                    let keys = data[LOOKUP_TABLE_META_SIZE..]
+                        .chunks_exact(32)
+                        .map(Pubkey::new)
+                        .collect();
+                    tables.push((key, keys));
+                }
+                Ok(_) => warn!("{} is not an address lookup table", key),
+                Err(e) => warn!("failed to read lookup table {}: {}", key, e),
+            }
+        }
+        Self { tables }
+    }
+}
+
+/// Wire size of a set of instructions as one transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxSize {
+    /// Distinct accounts over all instructions, payer and programs included
+    pub accounts: usize,
+    /// Legacy transaction
+    pub legacy: usize,
+    /// v0 transaction with the accounts found in the lookup tables
+    pub with_lookup_tables: usize,
+}
+
+impl TxSize {
+    pub fn fits(&self) -> bool {
+        self.legacy <= PACKET_DATA_SIZE
+    }
+}
+
+/// Bytes a compact-u16 length prefix takes
+fn compact_len(len: usize) -> usize {
+    match len {
+        0..=0x7f => 1,
+        0x80..=0x3fff => 2,
+        _ => 3,
+    }
+}
+
+/// Measures `ixs` paid by `payer`
+pub fn measure(ixs: &[Instruction], payer: &Pubkey, tables: &LookupTables) -> TxSize {
+    let message = Message::new(ixs, Some(payer));
+    let signatures = message.header.num_required_signatures as usize;
+    let message_size = bincode::serialized_size(&message).unwrap_or(u64::MAX) as usize;
+    let legacy = compact_len(signatures) + 64 * signatures + message_size;
+
+    // signers and invoked programs have to stay in the message
+    let programs: HashSet<Pubkey> = ixs.iter().map(|ix| ix.program_id).collect();
+    let mut movable: HashSet<Pubkey> = message
+        .account_keys
+        .iter()
+        .enumerate()
+        .filter(|(i, key)| *i >= signatures && !programs.contains(key))
+        .map(|(_, key)| *key)
+        .collect();
+    // version prefix and the empty table list
+    let mut with_lookup_tables = legacy + 2;
+    for (_, keys) in tables.tables.iter() {
+        let moved = movable.iter().filter(|key| keys.contains(key)).count();
+        if moved == 0 {
+            continue;
+        }
+        movable.retain(|key| !keys.contains(key));
+        // table key and two index lists, a 1-byte index per moved key
+        with_lookup_tables = with_lookup_tables + 32 + 2 + moved - 32 * moved;
+    }
+
+    TxSize {
+        accounts: message.account_keys.len(),
+        legacy,
+        with_lookup_tables,
+    }
+}
+
+/// Fits a route's instructions into transactions
+///
+/// # Arguments
+/// * `ixs` - The route's instructions, in order
+/// * `overhead` - Instructions added to every transaction before it's sent
+///   (compute budget, tip), with placeholder values of the same size
+/// * `can_split` - Whether the route may span several transactions
+///
+/// Returns the instructions of each transaction, or why the route can't be sent
+pub fn fit(
+    ixs: Vec<Instruction>,
+    overhead: &[Instruction],
+    payer: &Pubkey,
+    tables: &LookupTables,
+    can_split: bool,
+) -> Result<Vec<Vec<Instruction>>, String> {
+    let with_overhead = |ixs: &[Instruction]| -> Vec<Instruction> {
+        overhead.iter().chain(ixs.iter()).cloned().collect()
+    };
+    let size = measure(&with_overhead(&ixs), payer, tables);
+    debug!(
+        accounts = size.accounts,
+        legacy = size.legacy,
+        with_lookup_tables = size.with_lookup_tables,
+        "route transaction size"
+    );
+    if size.fits() {
+        return Ok(vec![ixs]);
+    }
+    let too_large = format!(
+        "transaction is {} bytes ({} with lookup tables), the limit is {}",
+        size.legacy, size.with_lookup_tables, PACKET_DATA_SIZE
+    );
+    if !can_split {
+        return Err(too_large);
+    }
+
+    let mut parts: Vec<Vec<Instruction>> = vec![];
+    let mut part: Vec<Instruction> = vec![];
+    for ix in ixs {
+        part.push(ix);
+        if measure(&with_overhead(&part), payer, tables).fits() {
+            continue;
+        }
+        let ix = part.pop().unwrap();
+        if part.is_empty() {
+            return Err(format!("{}, and a single instruction doesn't fit", too_large));
+        }
+        parts.push(std::mem::replace(&mut part, vec![ix]));
+        if !measure(&with_overhead(&part), payer, tables).fits() {
+            return Err(format!("{}, and a single instruction doesn't fit", too_large));
+        }
+    }
+    parts.push(part);
+    if parts.len() > MAX_BUNDLE_TXS {
+        return Err(format!("{}, and it takes more than {} transactions", too_large, MAX_BUNDLE_TXS));
+    }
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::instruction::AccountMeta;
+
+    fn ix(program: Pubkey, accounts: &[Pubkey]) -> Instruction {
+        Instruction {
+            program_id: program,
+            accounts: accounts.iter().map(|k| AccountMeta::new(*k, false)).collect(),
+            data: vec![0; 17],
+        }
+    }
+
+    #[test]
+    fn repeated_accounts_are_counted_once() {
+        let payer = Pubkey::new_unique();
+        let program = Pubkey::new_unique();
+        let shared: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+        let one = measure(&[ix(program, &shared)], &payer, &LookupTables::default());
+        let two = measure(
+            &[ix(program, &shared), ix(program, &shared)],
+            &payer,
+            &LookupTables::default(),
+        );
+        assert_eq!(one.accounts, 7);
+        assert_eq!(two.accounts, 7);
+        // only the second instruction's indexes and data, no keys
+        assert_eq!(two.legacy - one.legacy, 1 + 1 + 5 + 1 + 17);
+    }
+
+    #[test]
+    fn lookup_tables_shrink_the_estimate() {
+        let payer = Pubkey::new_unique();
+        let program = Pubkey::new_unique();
+        let keys: Vec<Pubkey> = (0..10).map(|_| Pubkey::new_unique()).collect();
+        let tables = LookupTables {
+            tables: vec![(Pubkey::new_unique(), keys.iter().copied().collect())],
+        };
+        let size = measure(&[ix(program, &keys)], &payer, &tables);
+        assert_eq!(size.with_lookup_tables, size.legacy + 2 + 34 - 31 * 10);
+    }
+
+    #[test]
+    fn oversized_routes_are_split_or_rejected() {
+        let payer = Pubkey::new_unique();
+        let program = Pubkey::new_unique();
+        // 3 legs of 15 accounts each, about 500 bytes of keys per leg
+        let legs: Vec<Instruction> = (0..3)
+            .map(|_| {
+                let keys: Vec<Pubkey> = (0..15).map(|_| Pubkey::new_unique()).collect();
+                ix(program, &keys)
+            })
+            .collect();
+        let tables = LookupTables::default();
+        assert!(fit(legs.clone(), &[], &payer, &tables, false).is_err());
+
+        let parts = fit(legs, &[], &payer, &tables, true).unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].len(), 2);
+        assert!(parts.iter().all(|part| measure(part, &payer, &tables).fits()));
+
+        let huge: Vec<Pubkey> = (0..40).map(|_| Pubkey::new_unique()).collect();
+        assert!(fit(vec![ix(program, &huge)], &[], &payer, &tables, true).is_err());
+    }
+}
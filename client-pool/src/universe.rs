@@ -0,0 +1,166 @@
+//! Trading universes
+//!
+//! Presets restricting the tokens routes may go through, and with them the
+//! pools: a pool stays in the graph only if both of its mints are in the
+//! universe. The start mint always is.
+//!
+//! * `stables` keeps the USD stables (`prune.usd_mints`, USDC and USDT by
+//!   default)
+//! * `top_n` keeps the `top_n` tokens with the most pool liquidity, valued
+//!   in USD as in pruning (pools without a price count for nothing)
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use serde::{Deserialize, Serialize};
+
+use std::collections::{HashMap, HashSet};
+
+use tracing::info;
+
+use crate::graph::PoolSet;
+use crate::prune::{liquidity_usd, usd_prices};
+
+/// Which tokens a universe keeps, see the module docs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UniversePreset {
+    Stables,
+    TopN,
+}
+
+/// Trading universe settings
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UniverseConfig {
+    pub preset: UniversePreset,
+    /// Tokens `top_n` keeps besides the start mint
+    #[serde(default = "default_top_n")]
+    pub top_n: usize,
+}
+
+fn default_top_n() -> usize {
+    20
+}
+
+impl UniverseConfig {
+    /// Every problem with the config, for `Config::validate`
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.preset == UniversePreset::TopN && self.top_n == 0 {
+            problems.push("universe.top_n must be > 0".to_string());
+        }
+        problems
+    }
+}
+
+/// The mints of the pool set in the universe, `usd` being the stables
+pub fn universe_mints(
+    pool_set: &PoolSet,
+    config: &UniverseConfig,
+    usd: &HashSet<Pubkey>,
+    start_mint: &Pubkey,
+) -> HashSet<Pubkey> {
+    let mut mints: HashSet<Pubkey> = match config.preset {
+        UniversePreset::Stables => usd.clone(),
+        UniversePreset::TopN => {
+            let prices = usd_prices(&pool_set.pools, usd);
+            let mut liquidity: HashMap<Pubkey, f64> = HashMap::new();
+            for (i, idxs) in pool_set.pool_mints.iter().enumerate() {
+                if !pool_set.tradeable[i] {
+                    continue;
+                }
+                let usd = liquidity_usd(&pool_set.pools[i], &prices).unwrap_or(0.0);
+                for idx in idxs {
+                    *liquidity.entry(pool_set.token_mints[*idx]).or_default() += usd;
+                }
+            }
+            let mut ranked: Vec<(Pubkey, f64)> =
+                liquidity.into_iter().filter(|(mint, _)| mint != start_mint).collect();
+            // ties by mint so the universe is the same every run
+            ranked.sort_by(|a, b| {
+                b.1.partial_cmp(&a.1)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                    .then_with(|| a.0.cmp(&b.0))
+            });
+            ranked.into_iter().take(config.top_n).map(|(mint, _)| mint).collect()
+        }
+    };
+    mints.insert(*start_mint);
+    mints
+}
+
+/// Drops the pools trading a mint outside the universe, returns how many
+pub fn restrict(
+    pool_set: &mut PoolSet,
+    config: &UniverseConfig,
+    usd: &HashSet<Pubkey>,
+    start_mint: &Pubkey,
+) -> usize {
+    let mints = universe_mints(pool_set, config, usd, start_mint);
+    let outside: HashSet<usize> = pool_set
+        .pool_mints
+        .iter()
+        .enumerate()
+        .filter(|(_, pool_mints)| {
+            pool_mints
+                .iter()
+                .any(|idx| !mints.contains(&pool_set.token_mints[*idx]))
+        })
+        .map(|(i, _)| i)
+        .collect();
+    info!(
+        "{:?} universe: {} tokens, dropped {} pools trading others",
+        config.preset,
+        mints.len(),
+        outside.len()
+    );
+    pool_set.remove_pools(&outside);
+    outside.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pool::PoolType;
+    use crate::tests::pools::orca_pool;
+
+    #[test]
+    fn presets_keep_the_pools_of_their_tokens() {
+        let (usdc, usdt, sol, meme) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let usd: HashSet<Pubkey> = [usdc, usdt].into_iter().collect();
+        let pool_set = || {
+            let mut set = PoolSet::new();
+            for (a, b, reserve) in [
+                (usdc, usdt, 1_000_000_000_000),
+                (usdc, sol, 500_000_000_000),
+                (usdc, meme, 1_000_000_000),
+                (sol, meme, 1_000_000_000),
+            ] {
+                assert!(set.add_pool(PoolType::OrcaPoolType, orca_pool(&a, &b, reserve, reserve).into()));
+            }
+            set
+        };
+
+        let stables = UniverseConfig {
+            preset: UniversePreset::Stables,
+            top_n: 0,
+        };
+        let mut set = pool_set();
+        assert_eq!(restrict(&mut set, &stables, &usd, &usdc), 3);
+        assert_eq!(set.pools.len(), 1);
+
+        // usdc is the start mint, usdt and sol have the most liquidity
+        let top_2 = UniverseConfig {
+            preset: UniversePreset::TopN,
+            top_n: 2,
+        };
+        let set = pool_set();
+        let mints = universe_mints(&set, &top_2, &usd, &usdc);
+        assert_eq!(mints, [usdc, usdt, sol].into_iter().collect());
+        let mut set = set;
+        assert_eq!(restrict(&mut set, &top_2, &usd, &usdc), 2);
+    }
+}
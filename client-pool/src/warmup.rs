@@ -0,0 +1,340 @@
+//! Account warm-up
+//!
+//! A route fails when an account it swaps through doesn't exist yet: the
+//! owner's associated token account of a mint it trades, or the owner's
+//! open orders account on a Serum market it crosses. `warmup` reads these
+//! accounts for every loaded pool before a run and creates the missing
+//! ones, packed into as few transactions as fit, after reporting the rent
+//! they take. The wrapped SOL account is always among them, fee top-ups
+//! (`fee_balance`) swap into it whether or not a loaded pool trades SOL.
+//!
+//! Open orders accounts are only made on localnet, the one cluster Serum
+//! markets are traded on, and are added to `OPEN_ORDERS_PATH` as they land.
+
+use anchor_client::solana_client::client_error::Result as ClientResult;
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signature::{Keypair, Signer};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use solana_sdk::account::Account;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::Transaction;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::time::Duration;
+
+use tracing::{info, warn};
+
+use tmp::accounts as tmp_accounts;
+use tmp::instruction as tmp_ix;
+
+use crate::constants::{ARB_PROGRAM_ID, SERUM_PROGRAM_ID};
+use crate::graph::{AccountSource, ACCOUNTS_PER_REQUEST};
+use crate::profit_sweep::create_ata_ix;
+use crate::rpc_api::RpcApi;
+use crate::sender::BroadcastSender;
+use crate::tx_size::{measure, LookupTables};
+use crate::utils::derive_token_address;
+
+/// Size of an SPL token account
+pub const TOKEN_ACCOUNT_SPACE: usize = 165;
+/// Size of a Serum open orders account (market layout v2 and later)
+pub const OPEN_ORDERS_SPACE: usize = 3228;
+
+/// An account `warmup` creates
+pub enum Creation {
+    /// The owner's associated token account of `mint`
+    TokenAccount { mint: Pubkey },
+    /// An open orders account of the owner on `market`, at the key of `account`
+    OpenOrders { market: Pubkey, account: Keypair },
+}
+
+impl Creation {
+    /// The instructions creating the account, paid by `owner` with `rent`
+    pub fn ixs(&self, owner: &Pubkey, rent: u64) -> Vec<Instruction> {
+        match self {
+            Creation::TokenAccount { mint } => vec![create_ata_ix(owner, owner, mint)],
+            Creation::OpenOrders { market, account } => {
+                let create = system_instruction::create_account(
+                    owner,
+                    &account.pubkey(),
+                    rent,
+                    OPEN_ORDERS_SPACE as u64,
+                    &SERUM_PROGRAM_ID,
+                );
+                let accounts = tmp_accounts::InitOpenOrder {
+                    open_orders: account.pubkey(),
+                    authority: *owner,
+                    market: *market,
+                    dex_program: *SERUM_PROGRAM_ID,
+                    rent: solana_sdk::sysvar::rent::id(),
+                };
+                let init = Instruction {
+                    program_id: *ARB_PROGRAM_ID,
+                    accounts: accounts.to_account_metas(None),
+                    data: tmp_ix::InitOpenOrder {}.data(),
+                };
+                vec![create, init]
+            }
+        }
+    }
+
+    /// The new account's keypair, if it has to sign its creation
+    pub fn signer(&self) -> Option<&Keypair> {
+        match self {
+            Creation::TokenAccount { .. } => None,
+            Creation::OpenOrders { account, .. } => Some(account),
+        }
+    }
+}
+
+impl fmt::Display for Creation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Creation::TokenAccount { mint } => write!(f, "token account of {}", mint),
+            Creation::OpenOrders { market, account } => {
+                write!(f, "open orders {} on {}", account.pubkey(), market)
+            }
+        }
+    }
+}
+
+/// The account of each of `keys`, `None` if it doesn't exist
+fn fetch<S: AccountSource + ?Sized>(reads: &S, keys: &[Pubkey]) -> ClientResult<Vec<Option<Account>>> {
+    let chunks: Vec<&[Pubkey]> = keys.chunks(ACCOUNTS_PER_REQUEST).collect();
+    let mut fetched = Vec::with_capacity(keys.len());
+    for (chunk, fetch) in chunks.iter().zip(reads.get_chunks_with_slot(&chunks)) {
+        let (mut accounts, _) = fetch.result?;
+        // a short answer leaves the rest of the chunk missing
+        accounts.resize(chunk.len(), None);
+        fetched.extend(accounts);
+    }
+    Ok(fetched)
+}
+
+/// The accounts the loaded pools need that don't exist, and their rent
+pub struct WarmupPlan {
+    pub owner: Pubkey,
+    pub creations: Vec<Creation>,
+    /// Token accounts and open orders accounts checked
+    pub checked: (usize, usize),
+    /// Rent of a token account and of an open orders account
+    pub rent: (u64, u64),
+}
+
+impl WarmupPlan {
+    /// Reads the owner's token accounts of `mints` and the open orders
+    /// accounts `book` (market -> account) holds for `markets`; markets
+    /// without one in `book`, or whose account isn't Serum's, get a new one
+    pub fn new<S: AccountSource + ?Sized>(
+        reads: &S,
+        owner: &Pubkey,
+        mints: &[Pubkey],
+        markets: &[Pubkey],
+        book: &HashMap<String, String>,
+        rent: (u64, u64),
+    ) -> ClientResult<Self> {
+        let atas: Vec<Pubkey> = mints.iter().map(|mint| derive_token_address(owner, mint)).collect();
+        let mut creations: Vec<Creation> = mints
+            .iter()
+            .zip(fetch(reads, &atas)?)
+            .filter(|(_, account)| account.is_none())
+            .map(|(mint, _)| Creation::TokenAccount { mint: *mint })
+            .collect();
+
+        let listed: Vec<Option<Pubkey>> = markets
+            .iter()
+            .map(|market| book.get(&market.to_string()).and_then(|oo| oo.parse().ok()))
+            .collect();
+        let keys: Vec<Pubkey> = listed.iter().flatten().copied().collect();
+        let mut accounts = fetch(reads, &keys)?.into_iter();
+        for (market, oo) in markets.iter().zip(listed) {
+            let live = oo.is_some()
+                && accounts
+                    .next()
+                    .flatten()
+                    .map_or(false, |account| account.owner == *SERUM_PROGRAM_ID);
+            if !live {
+                creations.push(Creation::OpenOrders {
+                    market: *market,
+                    account: Keypair::new(),
+                });
+            }
+        }
+
+        Ok(Self {
+            owner: *owner,
+            creations,
+            checked: (mints.len(), markets.len()),
+            rent,
+        })
+    }
+
+    fn rent_of(&self, creation: &Creation) -> u64 {
+        match creation {
+            Creation::TokenAccount { .. } => self.rent.0,
+            Creation::OpenOrders { .. } => self.rent.1,
+        }
+    }
+
+    /// Lamports the new accounts lock up
+    pub fn total_rent(&self) -> u64 {
+        self.creations.iter().map(|creation| self.rent_of(creation)).sum()
+    }
+
+    fn batch_ixs(&self, batch: Range<usize>) -> Vec<Instruction> {
+        self.creations[batch]
+            .iter()
+            .flat_map(|creation| creation.ixs(&self.owner, self.rent_of(creation)))
+            .collect()
+    }
+
+    /// The creations in order, split into transactions each as full as fits
+    pub fn batches(&self) -> Vec<Range<usize>> {
+        let tables = LookupTables::default();
+        let fits = |batch: Range<usize>| measure(&self.batch_ixs(batch), &self.owner, &tables).fits();
+        let mut batches = vec![];
+        let mut start = 0;
+        for end in 1..=self.creations.len() {
+            if end - start > 1 && !fits(start..end) {
+                batches.push(start..end - 1);
+                start = end - 1;
+            }
+        }
+        if start < self.creations.len() {
+            batches.push(start..self.creations.len());
+        }
+        batches
+    }
+
+    /// Sends every batch signed by `owner` and the batch's new accounts,
+    /// waiting for each to land; returns the creations that did
+    pub fn send(
+        &self,
+        rpc: &dyn RpcApi,
+        sender: &BroadcastSender,
+        owner: &dyn Signer,
+        commitment: CommitmentConfig,
+    ) -> Vec<&Creation> {
+        let mut landed = vec![];
+        let batches = self.batches();
+        for (i, batch) in batches.iter().enumerate() {
+            let blockhash = match rpc.latest_blockhash(commitment) {
+                Ok((blockhash, _)) => blockhash,
+                Err(e) => {
+                    warn!("warmup batch {} of {}: blockhash: {}", i + 1, batches.len(), e);
+                    continue;
+                }
+            };
+            let creations = &self.creations[batch.clone()];
+            let mut signers: Vec<&dyn Signer> = vec![owner];
+            signers.extend(creations.iter().filter_map(|c| c.signer().map(|kp| kp as &dyn Signer)));
+            let mut tx = Transaction::new_with_payer(&self.batch_ixs(batch.clone()), Some(&self.owner));
+            if let Err(e) = tx.try_sign(&signers, blockhash) {
+                warn!("warmup batch {} of {}: failed to sign: {}", i + 1, batches.len(), e);
+                continue;
+            }
+            let mut result = sender.broadcast(&tx, None);
+            if sender.confirm(&mut result, Duration::from_secs(30)) {
+                info!("warmup batch {} of {} landed: {:?}", i + 1, batches.len(), result.signature);
+                landed.extend(creations.iter());
+            } else {
+                warn!("warmup batch {} of {} did not land: {:?}", i + 1, batches.len(), result.error);
+            }
+        }
+        landed
+    }
+}
+
+impl fmt::Display for WarmupPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let token_accounts = self
+            .creations
+            .iter()
+            .filter(|creation| matches!(creation, Creation::TokenAccount { .. }))
+            .count();
+        writeln!(
+            f,
+            "missing: {} of {} token accounts, {} of {} open orders accounts",
+            token_accounts,
+            self.checked.0,
+            self.creations.len() - token_accounts,
+            self.checked.1
+        )?;
+        for creation in self.creations.iter() {
+            writeln!(f, "  {}", creation)?;
+        }
+        let rent = self.total_rent();
+        writeln!(
+            f,
+            "rent: {} lamports ({:.6} SOL) in {} transactions",
+            rent,
+            rent as f64 / 1e9,
+            self.batches().len()
+        )
+    }
+}
+
+/// Adds the open orders accounts of `landed` to the market -> account map
+/// in `path`, keeping the ones already there
+pub fn record_open_orders(path: &str, landed: &[&Creation]) -> std::io::Result<()> {
+    let mut book: HashMap<String, String> = match std::fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json)?,
+        Err(_) => HashMap::new(),
+    };
+    for creation in landed {
+        if let Creation::OpenOrders { market, account } = creation {
+            book.insert(market.to_string(), account.pubkey().to_string());
+        }
+    }
+    std::fs::write(path, serde_json::to_string(&book)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TOKEN_PROGRAM_ID;
+    use crate::rpc_api::MockRpc;
+
+    #[test]
+    fn missing_accounts_are_planned_in_transactions_that_fit() {
+        let rpc = MockRpc::new(100);
+        let owner = Pubkey::new_unique();
+        let mints: Vec<Pubkey> = (0..30).map(|_| Pubkey::new_unique()).collect();
+        let held = derive_token_address(&owner, &mints[0]);
+        rpc.set_account(held, Account::new(2_039_280, TOKEN_ACCOUNT_SPACE, &TOKEN_PROGRAM_ID));
+
+        // listed and live, listed but closed, not listed
+        let markets = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let (live, closed) = (Pubkey::new_unique(), Pubkey::new_unique());
+        rpc.set_account(live, Account::new(23_357_760, OPEN_ORDERS_SPACE, &SERUM_PROGRAM_ID));
+        let book: HashMap<String, String> = [(markets[0], live), (markets[1], closed)]
+            .iter()
+            .map(|(market, oo)| (market.to_string(), oo.to_string()))
+            .collect();
+
+        let rent = (2_039_280, 23_357_760);
+        let plan = WarmupPlan::new(&rpc, &owner, &mints, &markets, &book, rent).unwrap();
+        let planned: Vec<String> = plan.creations.iter().map(|creation| creation.to_string()).collect();
+        assert_eq!(planned.len(), 29 + 2);
+        assert!(!planned.contains(&format!("token account of {}", mints[0])));
+        assert!(planned.contains(&format!("token account of {}", mints[1])));
+        assert!(planned[29].ends_with(&format!("on {}", markets[1])));
+        assert!(planned[30].ends_with(&format!("on {}", markets[2])));
+        assert_eq!(plan.total_rent(), 29 * rent.0 + 2 * rent.1);
+
+        let batches = plan.batches();
+        assert!(batches.len() > 1);
+        assert_eq!(batches.first().unwrap().start, 0);
+        assert_eq!(batches.last().unwrap().end, plan.creations.len());
+        for pair in batches.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+        for batch in batches {
+            assert!(measure(&plan.batch_ixs(batch), &owner, &LookupTables::default()).fits());
+        }
+    }
+}
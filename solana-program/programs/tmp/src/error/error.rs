@@ -12,4 +12,12 @@ pub enum ErrorCode {
     RaydiumSwapFailed,
     #[msg("Invalid Raydium pool state")]
     InvalidRaydiumPool,
+    #[msg("Profit is below the minimum. Reverting...")]
+    ProfitBelowMinimum,
+    #[msg("Arithmetic overflow")]
+    ArithmeticOverflow,
+    #[msg("Account is not a Phoenix market")]
+    InvalidPhoenixMarket,
+    #[msg("Swap amount is below the market's lot size")]
+    AmountBelowLotSize,
 }
\ No newline at end of file
@@ -0,0 +1,68 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+
+use crate::error::ErrorCode;
+use crate::state::SwapState;
+
+#[derive(Accounts)]
+pub struct InitializeSwapState<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + 8 + 8 + 1 + 32 + 32,
+        seeds = [b"swap_state"],
+        bump
+    )]
+    pub swap_state: Account<'info, SwapState>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// First and last instruction of an arb: `start_swap` snapshots the balance
+/// of the start-token account, `profit_or_revert` fails the transaction
+/// unless that balance grew by the minimum profit since.
+#[derive(Accounts)]
+pub struct TokenAndSwapState<'info> {
+    pub src: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"swap_state"], bump)]
+    pub swap_state: Account<'info, SwapState>,
+}
+
+impl<'info> TokenAndSwapState<'info> {
+    pub fn start_swap(&mut self, swap_input: u64) -> Result<()> {
+        require!(self.src.amount >= swap_input, ErrorCode::NotEnoughFunds);
+
+        let swap_state = &mut self.swap_state;
+        swap_state.start_balance = self.src.amount;
+        swap_state.swap_input = swap_input;
+        swap_state.is_valid = true;
+        swap_state.input_token = self.src.mint;
+        swap_state.current_token = self.src.mint;
+        Ok(())
+    }
+
+    pub fn profit_or_revert(&mut self, min_profit: u64) -> Result<()> {
+        let swap_state = &mut self.swap_state;
+        require!(swap_state.is_valid, ErrorCode::InvalidState);
+        require_keys_eq!(self.src.mint, swap_state.input_token, ErrorCode::InvalidState);
+
+        let min_balance = swap_state
+            .start_balance
+            .checked_add(min_profit)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        let end_balance = self.src.amount;
+        msg!(
+            "start balance {} end balance {} min profit {}",
+            swap_state.start_balance,
+            end_balance,
+            min_profit
+        );
+        require!(end_balance > swap_state.start_balance, ErrorCode::NoProfit);
+        require!(end_balance >= min_balance, ErrorCode::ProfitBelowMinimum);
+
+        // a later arb has to snapshot its own start balance
+        swap_state.is_valid = false;
+        Ok(())
+    }
+}
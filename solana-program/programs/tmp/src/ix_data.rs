@@ -4,4 +4,60 @@ use anchor_lang::prelude::*;
 pub struct SwapData {
     pub amount_in: u64,
     pub minimum_amount_out: u64,
-} 
\ No newline at end of file
+} 
+
+/// Anchor discriminator of `swap`, shared by Whirlpool, Raydium CLMM and Meteora DLMM
+pub const ANCHOR_SWAP_DISCRIMINATOR: [u8; 8] = [248, 198, 158, 145, 225, 117, 135, 200];
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct WhirlpoolSwapData {
+    pub amount: u64,
+    pub other_amount_threshold: u64,
+    pub sqrt_price_limit: u128,
+    pub amount_specified_is_input: bool,
+    pub a_to_b: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct RaydiumClmmSwapData {
+    pub amount: u64,
+    pub other_amount_threshold: u64,
+    pub sqrt_price_limit_x64: u128,
+    pub is_base_input: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct MeteoraDlmmSwapData {
+    pub amount_in: u64,
+    pub min_amount_out: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PhoenixSide {
+    Bid,
+    Ask,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub enum PhoenixSelfTradeBehavior {
+    Abort,
+    CancelProvide,
+    DecrementTake,
+}
+
+/// `OrderPacket::ImmediateOrCancel` of Phoenix v1 (variant 2 of the packet)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PhoenixImmediateOrCancel {
+    pub side: PhoenixSide,
+    pub price_in_ticks: Option<u64>,
+    pub num_base_lots: u64,
+    pub num_quote_lots: u64,
+    pub min_base_lots_to_fill: u64,
+    pub min_quote_lots_to_fill: u64,
+    pub self_trade_behavior: PhoenixSelfTradeBehavior,
+    pub match_limit: Option<u64>,
+    pub client_order_id: u128,
+    pub use_only_deposited_funds: bool,
+    pub last_valid_slot: Option<u64>,
+    pub last_valid_unix_timestamp_in_seconds: Option<u64>,
+}
@@ -7,11 +7,18 @@ pub mod ix_data;
 pub mod state;
 pub mod swaps;
 pub mod error;
+pub mod guard;
 
 use swaps::orca::*;
 use swaps::raydium::*;
 use swaps::meteora::*;
 use swaps::jupiter::*;
+use swaps::whirlpool::*;
+use swaps::raydium_clmm::*;
+use swaps::meteora_dlmm::*;
+use swaps::phoenix::*;
+use ix_data::PhoenixSide;
+use guard::*;
 
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 
@@ -28,6 +35,21 @@ pub mod tmp {
         Ok(())
     }
 
+    pub fn initialize_swap_state(_ctx: Context<InitializeSwapState>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Records the start-token balance before the swaps of an arb
+    pub fn start_swap(ctx: Context<TokenAndSwapState>, swap_input: u64) -> Result<()> {
+        ctx.accounts.start_swap(swap_input)
+    }
+
+    /// Reverts the whole transaction unless the start-token balance grew by
+    /// at least `min_profit` since `start_swap`
+    pub fn profit_or_revert(ctx: Context<TokenAndSwapState>, min_profit: u64) -> Result<()> {
+        ctx.accounts.profit_or_revert(min_profit)
+    }
+
     pub fn orca_swap(
         ctx: Context<OrcaSwap>,
         amount_in: u64,
@@ -60,6 +82,44 @@ pub mod tmp {
     ) -> Result<()> {
         ctx.accounts.process_swap(amount_in, minimum_amount_out, route_data)
     }
+
+    pub fn whirlpool_swap(
+        ctx: Context<WhirlpoolSwap>,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        a_to_b: bool,
+    ) -> Result<()> {
+        ctx.accounts.process_swap(amount_in, minimum_amount_out, a_to_b)
+    }
+
+    /// Tick arrays after the first go in the remaining accounts
+    pub fn raydium_clmm_swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, RaydiumClmmSwap<'info>>,
+        amount_in: u64,
+        minimum_amount_out: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .process_swap(amount_in, minimum_amount_out, ctx.remaining_accounts)
+    }
+
+    /// Bin arrays go in the remaining accounts
+    pub fn meteora_dlmm_swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, MeteoraDlmmSwap<'info>>,
+        amount_in: u64,
+        minimum_amount_out: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .process_swap(amount_in, minimum_amount_out, ctx.remaining_accounts)
+    }
+
+    pub fn phoenix_swap(
+        ctx: Context<PhoenixSwap>,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        side: PhoenixSide,
+    ) -> Result<()> {
+        ctx.accounts.process_swap(amount_in, minimum_amount_out, side)
+    }
 }
 
 #[derive(Accounts)]
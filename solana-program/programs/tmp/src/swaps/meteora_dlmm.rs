@@ -0,0 +1,112 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{instruction::Instruction, program::invoke};
+use anchor_spl::token::TokenAccount;
+
+use crate::ix_data::{MeteoraDlmmSwapData, ANCHOR_SWAP_DISCRIMINATOR};
+
+// Meteora DLMM program ID
+pub const METEORA_DLMM_PROGRAM_ID: &str = "LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo";
+
+/// The bin arrays the swap crosses are passed as remaining accounts, in
+/// swap direction. Pairs without a bitmap extension take the DLMM program
+/// id as `bin_array_bitmap_extension`, which the program reads as none.
+#[derive(Accounts)]
+pub struct MeteoraDlmmSwap<'info> {
+    /// CHECK: Validated by Meteora DLMM program
+    #[account(mut)]
+    pub lb_pair: AccountInfo<'info>,
+    /// CHECK: Validated by Meteora DLMM program
+    pub bin_array_bitmap_extension: AccountInfo<'info>,
+    /// CHECK: Validated by Meteora DLMM program
+    #[account(mut)]
+    pub reserve_x: AccountInfo<'info>,
+    /// CHECK: Validated by Meteora DLMM program
+    #[account(mut)]
+    pub reserve_y: AccountInfo<'info>,
+    #[account(mut)]
+    pub user_token_in: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub user_token_out: Account<'info, TokenAccount>,
+    /// CHECK: Validated by Meteora DLMM program
+    pub token_x_mint: AccountInfo<'info>,
+    /// CHECK: Validated by Meteora DLMM program
+    pub token_y_mint: AccountInfo<'info>,
+    /// CHECK: Validated by Meteora DLMM program
+    #[account(mut)]
+    pub oracle: AccountInfo<'info>,
+    pub user: Signer<'info>,
+    /// CHECK: Token program of mint x
+    pub token_x_program: AccountInfo<'info>,
+    /// CHECK: Token program of mint y
+    pub token_y_program: AccountInfo<'info>,
+    /// CHECK: Validated by Meteora DLMM program
+    pub event_authority: AccountInfo<'info>,
+    /// CHECK: Meteora DLMM program
+    #[account(address = METEORA_DLMM_PROGRAM_ID.parse::<Pubkey>().unwrap())]
+    pub dlmm_program: AccountInfo<'info>,
+}
+
+impl<'info> MeteoraDlmmSwap<'info> {
+    /// Exact-input swap of `amount_in` of `user_token_in`'s mint
+    pub fn process_swap(
+        &self,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        bin_arrays: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        let args = MeteoraDlmmSwapData {
+            amount_in,
+            min_amount_out: minimum_amount_out,
+        };
+        let mut data = ANCHOR_SWAP_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&args.try_to_vec()?);
+
+        let program_id = self.dlmm_program.key();
+        let mut accounts = vec![
+            AccountMeta::new(self.lb_pair.key(), false),
+            AccountMeta::new_readonly(self.bin_array_bitmap_extension.key(), false),
+            AccountMeta::new(self.reserve_x.key(), false),
+            AccountMeta::new(self.reserve_y.key(), false),
+            AccountMeta::new(self.user_token_in.key(), false),
+            AccountMeta::new(self.user_token_out.key(), false),
+            AccountMeta::new_readonly(self.token_x_mint.key(), false),
+            AccountMeta::new_readonly(self.token_y_mint.key(), false),
+            AccountMeta::new(self.oracle.key(), false),
+            // no host fee account
+            AccountMeta::new_readonly(program_id, false),
+            AccountMeta::new_readonly(self.user.key(), true),
+            AccountMeta::new_readonly(self.token_x_program.key(), false),
+            AccountMeta::new_readonly(self.token_y_program.key(), false),
+            AccountMeta::new_readonly(self.event_authority.key(), false),
+            AccountMeta::new_readonly(program_id, false),
+        ];
+        accounts.extend(bin_arrays.iter().map(|a| AccountMeta::new(a.key(), false)));
+
+        let mut infos = vec![
+            self.lb_pair.to_account_info(),
+            self.bin_array_bitmap_extension.to_account_info(),
+            self.reserve_x.to_account_info(),
+            self.reserve_y.to_account_info(),
+            self.user_token_in.to_account_info(),
+            self.user_token_out.to_account_info(),
+            self.token_x_mint.to_account_info(),
+            self.token_y_mint.to_account_info(),
+            self.oracle.to_account_info(),
+            self.user.to_account_info(),
+            self.token_x_program.to_account_info(),
+            self.token_y_program.to_account_info(),
+            self.event_authority.to_account_info(),
+            self.dlmm_program.to_account_info(),
+        ];
+        infos.extend(bin_arrays.iter().cloned());
+
+        let ix = Instruction {
+            program_id,
+            accounts,
+            data,
+        };
+        invoke(&ix, &infos)?;
+
+        Ok(())
+    }
+}
@@ -2,10 +2,18 @@
 
 pub mod jupiter;
 pub mod meteora;
+pub mod meteora_dlmm;
 pub mod orca;
+pub mod phoenix;
 pub mod raydium;
+pub mod raydium_clmm;
+pub mod whirlpool;
 
 pub use jupiter::*;
 pub use meteora::*;
+pub use meteora_dlmm::*;
 pub use orca::*;
-pub use raydium::*;
\ No newline at end of file
+pub use phoenix::*;
+pub use raydium::*;
+pub use raydium_clmm::*;
+pub use whirlpool::*;
\ No newline at end of file
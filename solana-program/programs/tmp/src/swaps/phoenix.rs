@@ -0,0 +1,141 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{instruction::Instruction, program::invoke};
+use anchor_spl::token::{Token, TokenAccount};
+
+use crate::error::ErrorCode;
+use crate::ix_data::{PhoenixImmediateOrCancel, PhoenixSelfTradeBehavior, PhoenixSide};
+
+// Phoenix v1 program ID
+pub const PHOENIX_PROGRAM_ID: &str = "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY";
+
+// Tag of the `Swap` instruction and of the `ImmediateOrCancel` order packet
+const PHOENIX_SWAP_TAG: u8 = 0;
+const PHOENIX_IOC_PACKET_TAG: u8 = 2;
+
+// Offsets of the lot sizes in the market header: discriminant, status and
+// size params, then base params (decimals, vault bump, mint, vault) followed
+// by the base lot size, same for quote
+const BASE_LOT_SIZE_OFFSET: usize = 112;
+const QUOTE_LOT_SIZE_OFFSET: usize = 192;
+
+#[derive(Accounts)]
+pub struct PhoenixSwap<'info> {
+    /// CHECK: Phoenix program
+    #[account(address = PHOENIX_PROGRAM_ID.parse::<Pubkey>().unwrap())]
+    pub phoenix_program: AccountInfo<'info>,
+    /// CHECK: Validated by Phoenix program
+    pub log_authority: AccountInfo<'info>,
+    /// CHECK: Validated by Phoenix program, lot sizes are read from its header
+    #[account(mut, owner = PHOENIX_PROGRAM_ID.parse::<Pubkey>().unwrap())]
+    pub market: AccountInfo<'info>,
+    pub trader: Signer<'info>,
+    #[account(mut)]
+    pub base_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub quote_account: Account<'info, TokenAccount>,
+    /// CHECK: Validated by Phoenix program
+    #[account(mut)]
+    pub base_vault: AccountInfo<'info>,
+    /// CHECK: Validated by Phoenix program
+    #[account(mut)]
+    pub quote_vault: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> PhoenixSwap<'info> {
+    /// Immediate-or-cancel order spending `amount_in` of quote (`Bid`) or
+    /// base (`Ask`), failing unless it fills at least `minimum_amount_out`
+    pub fn process_swap(
+        &self,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        side: PhoenixSide,
+    ) -> Result<()> {
+        let (base_lot_size, quote_lot_size) = self.lot_sizes()?;
+        let (num_base_lots, num_quote_lots, min_base_lots_to_fill, min_quote_lots_to_fill) =
+            match side {
+                PhoenixSide::Bid => (
+                    0,
+                    amount_in / quote_lot_size,
+                    ceil_div(minimum_amount_out, base_lot_size),
+                    0,
+                ),
+                PhoenixSide::Ask => (
+                    amount_in / base_lot_size,
+                    0,
+                    0,
+                    ceil_div(minimum_amount_out, quote_lot_size),
+                ),
+            };
+        require!(
+            num_base_lots > 0 || num_quote_lots > 0,
+            ErrorCode::AmountBelowLotSize
+        );
+
+        let order = PhoenixImmediateOrCancel {
+            side,
+            price_in_ticks: None,
+            num_base_lots,
+            num_quote_lots,
+            min_base_lots_to_fill,
+            min_quote_lots_to_fill,
+            self_trade_behavior: PhoenixSelfTradeBehavior::Abort,
+            match_limit: None,
+            client_order_id: 0,
+            use_only_deposited_funds: false,
+            last_valid_slot: None,
+            last_valid_unix_timestamp_in_seconds: None,
+        };
+        let mut data = vec![PHOENIX_SWAP_TAG, PHOENIX_IOC_PACKET_TAG];
+        data.extend_from_slice(&order.try_to_vec()?);
+
+        let ix = Instruction {
+            program_id: self.phoenix_program.key(),
+            accounts: vec![
+                AccountMeta::new_readonly(self.phoenix_program.key(), false),
+                AccountMeta::new_readonly(self.log_authority.key(), false),
+                AccountMeta::new(self.market.key(), false),
+                AccountMeta::new_readonly(self.trader.key(), true),
+                AccountMeta::new(self.base_account.key(), false),
+                AccountMeta::new(self.quote_account.key(), false),
+                AccountMeta::new(self.base_vault.key(), false),
+                AccountMeta::new(self.quote_vault.key(), false),
+                AccountMeta::new_readonly(self.token_program.key(), false),
+            ],
+            data,
+        };
+
+        invoke(
+            &ix,
+            &[
+                self.phoenix_program.to_account_info(),
+                self.log_authority.to_account_info(),
+                self.market.to_account_info(),
+                self.trader.to_account_info(),
+                self.base_account.to_account_info(),
+                self.quote_account.to_account_info(),
+                self.base_vault.to_account_info(),
+                self.quote_vault.to_account_info(),
+                self.token_program.to_account_info(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    fn lot_sizes(&self) -> Result<(u64, u64)> {
+        let data = self.market.try_borrow_data()?;
+        let read = |offset: usize| -> Option<u64> {
+            let bytes = data.get(offset..offset + 8)?;
+            Some(u64::from_le_bytes(bytes.try_into().ok()?))
+        };
+        match (read(BASE_LOT_SIZE_OFFSET), read(QUOTE_LOT_SIZE_OFFSET)) {
+            (Some(base), Some(quote)) if base > 0 && quote > 0 => Ok((base, quote)),
+            _ => err!(ErrorCode::InvalidPhoenixMarket),
+        }
+    }
+}
+
+fn ceil_div(amount: u64, lot_size: u64) -> u64 {
+    amount / lot_size + (amount % lot_size != 0) as u64
+}
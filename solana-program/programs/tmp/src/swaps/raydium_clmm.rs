@@ -0,0 +1,97 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{instruction::Instruction, program::invoke};
+use anchor_spl::token::{Token, TokenAccount};
+
+use crate::ix_data::{RaydiumClmmSwapData, ANCHOR_SWAP_DISCRIMINATOR};
+
+// Raydium concentrated liquidity program ID
+pub const RAYDIUM_CLMM_PROGRAM_ID: &str = "CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK";
+
+/// The tick arrays the swap crosses after `tick_array` are passed as
+/// remaining accounts, in swap direction.
+#[derive(Accounts)]
+pub struct RaydiumClmmSwap<'info> {
+    pub payer: Signer<'info>,
+    /// CHECK: Validated by Raydium CLMM program
+    pub amm_config: AccountInfo<'info>,
+    /// CHECK: Validated by Raydium CLMM program
+    #[account(mut)]
+    pub pool_state: AccountInfo<'info>,
+    #[account(mut)]
+    pub input_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub output_token_account: Account<'info, TokenAccount>,
+    /// CHECK: Validated by Raydium CLMM program
+    #[account(mut)]
+    pub input_vault: AccountInfo<'info>,
+    /// CHECK: Validated by Raydium CLMM program
+    #[account(mut)]
+    pub output_vault: AccountInfo<'info>,
+    /// CHECK: Validated by Raydium CLMM program
+    #[account(mut)]
+    pub observation_state: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
+    /// CHECK: Validated by Raydium CLMM program
+    #[account(mut)]
+    pub tick_array: AccountInfo<'info>,
+    /// CHECK: Raydium CLMM program
+    #[account(address = RAYDIUM_CLMM_PROGRAM_ID.parse::<Pubkey>().unwrap())]
+    pub clmm_program: AccountInfo<'info>,
+}
+
+impl<'info> RaydiumClmmSwap<'info> {
+    /// Exact-input swap of `amount_in` from the input to the output vault
+    pub fn process_swap(
+        &self,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        tick_arrays: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        let args = RaydiumClmmSwapData {
+            amount: amount_in,
+            other_amount_threshold: minimum_amount_out,
+            sqrt_price_limit_x64: 0, // no limit
+            is_base_input: true,
+        };
+        let mut data = ANCHOR_SWAP_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&args.try_to_vec()?);
+
+        let mut accounts = vec![
+            AccountMeta::new_readonly(self.payer.key(), true),
+            AccountMeta::new_readonly(self.amm_config.key(), false),
+            AccountMeta::new(self.pool_state.key(), false),
+            AccountMeta::new(self.input_token_account.key(), false),
+            AccountMeta::new(self.output_token_account.key(), false),
+            AccountMeta::new(self.input_vault.key(), false),
+            AccountMeta::new(self.output_vault.key(), false),
+            AccountMeta::new(self.observation_state.key(), false),
+            AccountMeta::new_readonly(self.token_program.key(), false),
+            AccountMeta::new(self.tick_array.key(), false),
+        ];
+        accounts.extend(tick_arrays.iter().map(|a| AccountMeta::new(a.key(), false)));
+
+        let mut infos = vec![
+            self.payer.to_account_info(),
+            self.amm_config.to_account_info(),
+            self.pool_state.to_account_info(),
+            self.input_token_account.to_account_info(),
+            self.output_token_account.to_account_info(),
+            self.input_vault.to_account_info(),
+            self.output_vault.to_account_info(),
+            self.observation_state.to_account_info(),
+            self.token_program.to_account_info(),
+            self.tick_array.to_account_info(),
+        ];
+        infos.extend(tick_arrays.iter().cloned());
+        infos.push(self.clmm_program.to_account_info());
+
+        let ix = Instruction {
+            program_id: self.clmm_program.key(),
+            accounts,
+            data,
+        };
+        invoke(&ix, &infos)?;
+
+        Ok(())
+    }
+}
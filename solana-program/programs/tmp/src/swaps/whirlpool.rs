@@ -0,0 +1,107 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{instruction::Instruction, program::invoke};
+use anchor_spl::token::{Token, TokenAccount};
+
+use crate::ix_data::{WhirlpoolSwapData, ANCHOR_SWAP_DISCRIMINATOR};
+
+// Whirlpool program ID
+pub const WHIRLPOOL_V2_PROGRAM_ID: &str = "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc";
+
+// Price bounds of a whirlpool, used as "no limit" for either direction
+pub const WHIRLPOOL_MIN_SQRT_PRICE: u128 = 4295048016;
+pub const WHIRLPOOL_MAX_SQRT_PRICE: u128 = 79226673515401279992447579055;
+
+#[derive(Accounts)]
+pub struct WhirlpoolSwap<'info> {
+    pub token_program: Program<'info, Token>,
+    pub token_authority: Signer<'info>,
+    /// CHECK: Validated by Whirlpool program
+    #[account(mut)]
+    pub whirlpool: AccountInfo<'info>,
+    #[account(mut)]
+    pub token_owner_account_a: Account<'info, TokenAccount>,
+    /// CHECK: Validated by Whirlpool program
+    #[account(mut)]
+    pub token_vault_a: AccountInfo<'info>,
+    #[account(mut)]
+    pub token_owner_account_b: Account<'info, TokenAccount>,
+    /// CHECK: Validated by Whirlpool program
+    #[account(mut)]
+    pub token_vault_b: AccountInfo<'info>,
+    /// CHECK: Validated by Whirlpool program
+    #[account(mut)]
+    pub tick_array_0: AccountInfo<'info>,
+    /// CHECK: Validated by Whirlpool program
+    #[account(mut)]
+    pub tick_array_1: AccountInfo<'info>,
+    /// CHECK: Validated by Whirlpool program
+    #[account(mut)]
+    pub tick_array_2: AccountInfo<'info>,
+    /// CHECK: Validated by Whirlpool program
+    pub oracle: AccountInfo<'info>,
+    /// CHECK: Whirlpool program
+    #[account(address = WHIRLPOOL_V2_PROGRAM_ID.parse::<Pubkey>().unwrap())]
+    pub whirlpool_program: AccountInfo<'info>,
+}
+
+impl<'info> WhirlpoolSwap<'info> {
+    /// Exact-input swap of `amount_in`, token A for B when `a_to_b`
+    pub fn process_swap(
+        &self,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        a_to_b: bool,
+    ) -> Result<()> {
+        let args = WhirlpoolSwapData {
+            amount: amount_in,
+            other_amount_threshold: minimum_amount_out,
+            sqrt_price_limit: if a_to_b {
+                WHIRLPOOL_MIN_SQRT_PRICE
+            } else {
+                WHIRLPOOL_MAX_SQRT_PRICE
+            },
+            amount_specified_is_input: true,
+            a_to_b,
+        };
+        let mut data = ANCHOR_SWAP_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&args.try_to_vec()?);
+
+        let ix = Instruction {
+            program_id: self.whirlpool_program.key(),
+            accounts: vec![
+                AccountMeta::new_readonly(self.token_program.key(), false),
+                AccountMeta::new_readonly(self.token_authority.key(), true),
+                AccountMeta::new(self.whirlpool.key(), false),
+                AccountMeta::new(self.token_owner_account_a.key(), false),
+                AccountMeta::new(self.token_vault_a.key(), false),
+                AccountMeta::new(self.token_owner_account_b.key(), false),
+                AccountMeta::new(self.token_vault_b.key(), false),
+                AccountMeta::new(self.tick_array_0.key(), false),
+                AccountMeta::new(self.tick_array_1.key(), false),
+                AccountMeta::new(self.tick_array_2.key(), false),
+                AccountMeta::new_readonly(self.oracle.key(), false),
+            ],
+            data,
+        };
+
+        invoke(
+            &ix,
+            &[
+                self.token_program.to_account_info(),
+                self.token_authority.to_account_info(),
+                self.whirlpool.to_account_info(),
+                self.token_owner_account_a.to_account_info(),
+                self.token_vault_a.to_account_info(),
+                self.token_owner_account_b.to_account_info(),
+                self.token_vault_b.to_account_info(),
+                self.tick_array_0.to_account_info(),
+                self.tick_array_1.to_account_info(),
+                self.tick_array_2.to_account_info(),
+                self.oracle.to_account_info(),
+                self.whirlpool_program.to_account_info(),
+            ],
+        )?;
+
+        Ok(())
+    }
+}